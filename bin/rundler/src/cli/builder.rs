@@ -11,17 +11,16 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
 
 use anyhow::Context;
 use clap::Args;
-use ethers::types::H256;
 use rundler_builder::{
-    self, BuilderEvent, BuilderEventKind, BuilderTask, BuilderTaskArgs, LocalBuilderBuilder,
-    TransactionSenderType,
+    self, BuilderEvent, BuilderEventKind, BuilderTask, BuilderTaskArgs, BundleLedger,
+    LocalBuilderBuilder, TransactionSenderType,
 };
 use rundler_pool::RemotePoolClient;
-use rundler_sim::{MempoolConfig, PriorityFeeMode};
+use rundler_sim::PriorityFeeMode;
 use rundler_task::{
     server::{connect_with_retries_shutdown, format_socket_addr},
     spawn_tasks_with_shutdown,
@@ -29,7 +28,7 @@ use rundler_task::{
 use rundler_utils::emit::{self, WithEntryPoint, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::broadcast;
 
-use super::{json::get_json_config, CommonArgs};
+use super::CommonArgs;
 
 const REQUEST_CHANNEL_CAPACITY: usize = 1024;
 
@@ -168,6 +167,15 @@ pub struct BuilderArgs {
         default_value = "0"
     )]
     pub builder_index_offset: u64,
+
+    /// Path of a database to record the costs and revenue of mined bundles in.
+    /// If not provided, no ledger is kept.
+    #[arg(
+        long = "builder.ledger_path",
+        name = "builder.ledger_path",
+        env = "BUILDER_LEDGER_PATH"
+    )]
+    ledger_path: Option<PathBuf>,
 }
 
 impl BuilderArgs {
@@ -189,12 +197,7 @@ impl BuilderArgs {
             .context("should have a node HTTP URL")?;
         let submit_url = self.submit_url.clone().unwrap_or_else(|| rpc_url.clone());
 
-        let mempool_configs = match &common.mempool_config_path {
-            Some(path) => {
-                get_json_config::<HashMap<H256, MempoolConfig>>(path, &common.aws_region).await?
-            }
-            None => HashMap::from([(H256::zero(), MempoolConfig::default())]),
-        };
+        let mempool_configs = common.mempool_configs().await?;
 
         Ok(BuilderTaskArgs {
             rpc_url,
@@ -212,16 +215,15 @@ impl BuilderArgs {
                 .context("should be a valid aws region")?,
             redis_uri: self.redis_uri.clone(),
             redis_lock_ttl_millis: self.redis_lock_ttl_millis,
-            chain_id: common.chain_id,
+            chain_spec: common.chain_spec()?,
             max_bundle_size: self.max_bundle_size,
-            max_bundle_gas: common.max_bundle_gas,
             submit_url,
             bundle_priority_fee_overhead_percent: common.bundle_priority_fee_overhead_percent,
             priority_fee_mode,
             sender_type: self.sender_type,
-            eth_poll_interval: Duration::from_millis(common.eth_poll_interval_millis),
+            eth_poll_interval: common.eth_poll_interval()?,
             sim_settings: common.into(),
-            mempool_configs,
+            mempool_configs: mempool_configs.into(),
             max_blocks_to_wait_for_mine: self.max_blocks_to_wait_for_mine,
             replacement_fee_percent_increase: self.replacement_fee_percent_increase,
             max_fee_increases: self.max_fee_increases,
@@ -229,6 +231,14 @@ impl BuilderArgs {
             bloxroute_auth_header: self.bloxroute_auth_header.clone(),
             num_bundle_builders: common.num_builders,
             bundle_builder_index_offset: self.builder_index_offset,
+            simulation_cache: None,
+            ledger: self
+                .ledger_path
+                .as_deref()
+                .map(BundleLedger::open)
+                .transpose()?
+                .map(Arc::new),
+            abi_registry: common.abi_registry()?,
         })
     }
 }
@@ -247,12 +257,21 @@ pub struct BuilderCliArgs {
         global = true
     )]
     pool_url: String,
+
+    #[arg(
+        long = "builder.mempool_config_reload_interval_secs",
+        name = "builder.mempool_config_reload_interval_secs",
+        env = "BUILDER_MEMPOOL_CONFIG_RELOAD_INTERVAL_SECS",
+        global = true
+    )]
+    mempool_config_reload_interval_secs: Option<u64>,
 }
 
 pub async fn run(builder_args: BuilderCliArgs, common_args: CommonArgs) -> anyhow::Result<()> {
     let BuilderCliArgs {
         builder: builder_args,
         pool_url,
+        mempool_config_reload_interval_secs,
     } = builder_args;
 
     let (event_sender, event_rx) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
@@ -273,6 +292,10 @@ pub async fn run(builder_args: BuilderCliArgs, common_args: CommonArgs) -> anyho
     )
     .await?;
 
+    let reloader_task = common_args.mempool_config_reloader(
+        mempool_config_reload_interval_secs,
+        task_args.mempool_configs.clone(),
+    )?;
     spawn_tasks_with_shutdown(
         [BuilderTask::new(
             task_args,
@@ -280,7 +303,9 @@ pub async fn run(builder_args: BuilderCliArgs, common_args: CommonArgs) -> anyho
             LocalBuilderBuilder::new(REQUEST_CHANNEL_CAPACITY),
             pool,
         )
-        .boxed()],
+        .boxed()]
+        .into_iter()
+        .chain(reloader_task),
         tokio::signal::ctrl_c(),
     )
     .await;
@@ -288,15 +313,14 @@ pub async fn run(builder_args: BuilderCliArgs, common_args: CommonArgs) -> anyho
 }
 
 pub fn is_nonspammy_event(event: &WithEntryPoint<BuilderEvent>) -> bool {
-    if let BuilderEventKind::FormedBundle {
-        tx_details,
-        fee_increase_count,
-        ..
-    } = &event.event.kind
-    {
-        if tx_details.is_none() && *fee_increase_count == 0 {
-            return false;
-        }
+    match &event.event.kind {
+        BuilderEventKind::FormedBundle {
+            tx_details,
+            fee_increase_count,
+            ..
+        } => tx_details.is_some() || *fee_increase_count > 0,
+        // the bundle is logged once it is sent
+        BuilderEventKind::ProposedBundle { .. } => false,
+        _ => true,
     }
-    true
 }
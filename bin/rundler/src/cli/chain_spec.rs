@@ -0,0 +1,227 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use anyhow::{bail, Context};
+use rundler_types::chain::ChainSpec;
+use serde_json::{Map, Value};
+
+/// Key of a chain spec that names the preset or file it extends
+const BASE_KEY: &str = "base";
+
+/// Maximum depth of `base` references, guarding against cycles
+const MAX_BASE_DEPTH: usize = 8;
+
+macro_rules! preset {
+    ($name:literal) => {
+        (
+            $name,
+            include_str!(concat!("../../chain_specs/", $name, ".toml")),
+        )
+    };
+}
+
+/// Built-in chain spec presets, by name
+const PRESETS: &[(&str, &str)] = &[
+    preset!("dev"),
+    preset!("ethereum"),
+    preset!("ethereum_goerli"),
+    preset!("ethereum_sepolia"),
+    preset!("optimism"),
+    preset!("optimism_goerli"),
+    preset!("optimism_sepolia"),
+    preset!("base"),
+    preset!("base_goerli"),
+    preset!("base_sepolia"),
+    preset!("arbitrum"),
+    preset!("arbitrum_goerli"),
+    preset!("arbitrum_sepolia"),
+    preset!("arbitrum_nova"),
+    preset!("polygon"),
+    preset!("polygon_mumbai"),
+    preset!("scroll"),
+    preset!("linea"),
+    preset!("mantle"),
+];
+
+/// Resolves the chain spec to run with.
+///
+/// `spec` is either the name of a built-in preset or the path of a TOML or JSON
+/// file. When it is not set, the preset with the given chain ID is used, falling
+/// back to the default spec for unknown chains. A spec may extend a preset or
+/// another file by naming it in its `base` key. `chain_id`, if set, overrides
+/// the ID of the spec.
+pub fn resolve_chain_spec(spec: Option<&str>, chain_id: Option<u64>) -> anyhow::Result<ChainSpec> {
+    let value = match (spec, chain_id) {
+        (Some(spec), _) => load(spec, 0)?,
+        (None, Some(id)) => match preset_for_chain_id(id)? {
+            Some(value) => value,
+            None => Value::Object(Map::new()),
+        },
+        (None, None) => load("dev", 0)?,
+    };
+
+    let mut chain_spec: ChainSpec =
+        serde_json::from_value(value).context("should parse chain spec")?;
+    if let Some(id) = chain_id {
+        chain_spec.id = id;
+    }
+    Ok(chain_spec)
+}
+
+fn preset_for_chain_id(chain_id: u64) -> anyhow::Result<Option<Value>> {
+    for (name, _) in PRESETS {
+        let value = load(name, 0)?;
+        if value.get("id").and_then(Value::as_u64) == Some(chain_id) {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+// Loads a preset or file, merged over the spec named by its `base` key
+fn load(name_or_path: &str, depth: usize) -> anyhow::Result<Value> {
+    if depth > MAX_BASE_DEPTH {
+        bail!("chain spec {name_or_path} exceeds the maximum base depth of {MAX_BASE_DEPTH}");
+    }
+
+    let mut value = match PRESETS.iter().find(|(name, _)| *name == name_or_path) {
+        Some((_, contents)) => toml::from_str(contents)
+            .with_context(|| format!("should parse chain spec preset {name_or_path}"))?,
+        None => {
+            let contents = std::fs::read_to_string(name_or_path).with_context(|| {
+                format!("{name_or_path} is neither a chain spec preset nor a readable file")
+            })?;
+            if name_or_path.ends_with(".json") {
+                serde_json::from_str(&contents)?
+            } else {
+                toml::from_str(&contents)?
+            }
+        }
+    };
+
+    let Value::Object(map) = &mut value else {
+        bail!("chain spec {name_or_path} should be a table");
+    };
+    match map.remove(BASE_KEY) {
+        Some(Value::String(base)) => {
+            let mut base = load(&base, depth + 1)?;
+            merge(&mut base, value);
+            Ok(base)
+        }
+        Some(_) => bail!("chain spec {name_or_path} base should be a string"),
+        None => Ok(value),
+    }
+}
+
+// Recursively merges `value` over `base`
+fn merge(base: &mut Value, value: Value) {
+    match (base, value) {
+        (Value::Object(base), Value::Object(value)) => {
+            for (key, value) in value {
+                merge(base.entry(key).or_insert(Value::Null), value);
+            }
+        }
+        (base, value) => *base = value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use ethers::types::H256;
+    use rundler_types::chain::{
+        L1GasOracleContractType, PriorityFeeOracleType, TransactionSenderType,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_presets_parse_with_unique_ids() {
+        let mut ids = HashSet::new();
+        for (name, _) in PRESETS {
+            let spec = resolve_chain_spec(Some(name), None).unwrap();
+            assert!(ids.insert(spec.id), "duplicate chain id in preset {name}");
+        }
+    }
+
+    #[test]
+    fn test_preset_by_chain_id() {
+        let spec = resolve_chain_spec(None, Some(84532)).unwrap();
+        assert_eq!(spec.id, 84532);
+        assert_eq!(
+            spec.l1_gas.oracle_type,
+            L1GasOracleContractType::OptimismBedrock
+        );
+        assert_eq!(
+            spec.priority_fee_oracle_type,
+            PriorityFeeOracleType::UsageBased
+        );
+        assert_eq!(spec.chain_history_size, 128);
+
+        let spec = resolve_chain_spec(None, Some(999_999)).unwrap();
+        assert_eq!(
+            spec,
+            ChainSpec {
+                id: 999_999,
+                ..Default::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_file_extends_preset() {
+        let path =
+            std::env::temp_dir().join(format!("rundler-chain-spec-{:?}.json", H256::random()));
+        std::fs::write(
+            &path,
+            r#"{"base": "polygon", "id": 12345, "max_bundle_gas": 1000}"#,
+        )
+        .unwrap();
+
+        let spec = resolve_chain_spec(path.to_str(), None).unwrap();
+        assert_eq!(spec.id, 12345);
+        assert_eq!(spec.max_bundle_gas, 1000);
+        assert_eq!(spec.min_max_priority_fee_per_gas, 30_000_000_000);
+        assert_eq!(
+            spec.supported_senders,
+            vec![
+                TransactionSenderType::Raw,
+                TransactionSenderType::Conditional,
+                TransactionSenderType::PolygonBloxroute
+            ]
+        );
+        assert_eq!(
+            spec.precompile_addresses,
+            vec!["0x0000000000000000000000000000000000000100"
+                .parse()
+                .unwrap()]
+        );
+
+        assert_eq!(spec.block_time_millis, 2_000);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_unknown_field_rejected() {
+        let path =
+            std::env::temp_dir().join(format!("rundler-chain-spec-{:?}.toml", H256::random()));
+        std::fs::write(&path, "base = \"polygon\"\nblock_time = 1_000\n").unwrap();
+
+        let error = resolve_chain_spec(path.to_str(), None).unwrap_err();
+        assert!(format!("{error:#}").contains("unknown field `block_time`"));
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,520 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use clap::Args;
+use ethers::{
+    abi::{AbiDecode, RawLog},
+    prelude::EthEvent,
+    types::{
+        spoof, transaction::eip2718::TypedTransaction, Address, Bytes, CallConfig,
+        GethDebugBuiltInTracerConfig, GethDebugBuiltInTracerType, GethDebugTracerConfig,
+        GethDebugTracerType, GethDebugTracingCallOptions, GethDebugTracingOptions, GethTrace,
+        GethTraceFrame, H256, U256,
+    },
+};
+use rundler_provider::{EntryPoint, Provider};
+use rundler_rpc::RpcUserOperation;
+use rundler_sim::{
+    EstimationSettings, FeeEstimator, GasEstimator, GasEstimatorImpl, PrecheckSettings, Prechecker,
+    PrecheckerImpl, SimulateValidationTracerImpl, SimulationSettings, Simulator, SimulatorImpl,
+    UserOperationOptionalGas, ValidationReport, ViolationError,
+};
+use rundler_types::{
+    contracts::i_entry_point::{
+        IEntryPoint, IEntryPointCalls, UserOperationEventFilter, UserOperationRevertReasonFilter,
+    },
+    UserOperation,
+};
+use rundler_utils::eth;
+
+use super::CommonArgs;
+
+/// CLI options for the debug command
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Debug")]
+pub struct DebugCliArgs {
+    /// User operation to debug, as JSON or the path of a JSON file
+    #[arg(
+        long = "debug.user_op",
+        name = "debug.user_op",
+        env = "DEBUG_USER_OP",
+        required_unless_present = "debug.tx_hash",
+        conflicts_with = "debug.tx_hash"
+    )]
+    user_op: Option<String>,
+
+    /// Hash of a mined bundle transaction to take the user operation from
+    #[arg(long = "debug.tx_hash", name = "debug.tx_hash", env = "DEBUG_TX_HASH")]
+    tx_hash: Option<H256>,
+
+    /// Index of the user operation in the bundle transaction
+    #[arg(
+        long = "debug.op_index",
+        name = "debug.op_index",
+        env = "DEBUG_OP_INDEX",
+        default_value = "0"
+    )]
+    op_index: usize,
+
+    /// Block number to debug the user operation at
+    ///
+    /// Defaults to the block before the bundle transaction if one is given,
+    /// and otherwise to the latest block.
+    #[arg(long = "debug.block", name = "debug.block", env = "DEBUG_BLOCK")]
+    block: Option<u64>,
+
+    /// Entry point of the user operation
+    ///
+    /// Defaults to the recipient of the bundle transaction if one is given, and
+    /// otherwise to the first of the configured entry points.
+    #[arg(
+        long = "debug.entry_point",
+        name = "debug.entry_point",
+        env = "DEBUG_ENTRY_POINT"
+    )]
+    entry_point: Option<Address>,
+}
+
+/// Runs a user operation through precheck, validation, gas estimation,
+/// `simulateHandleOp`, and a traced `handleOps` at a block, and prints a report
+/// of the results.
+pub async fn run(args: DebugCliArgs, common_args: CommonArgs) -> anyhow::Result<()> {
+    let node_http = common_args
+        .node_http
+        .clone()
+        .context("debug requires node_http arg")?;
+    let provider = eth::new_provider(&node_http, None)?;
+
+    let (op, tx_entry_point, tx_block) = match (&args.user_op, args.tx_hash) {
+        (Some(user_op), _) => (parse_user_op(user_op)?, None, None),
+        (None, Some(tx_hash)) => {
+            let tx = provider
+                .get_transaction(tx_hash)
+                .await?
+                .with_context(|| format!("transaction {tx_hash:?} should exist"))?;
+            let ops = user_ops_from_bundle(tx.input)?;
+            let op = ops.get(args.op_index).cloned().with_context(|| {
+                format!(
+                    "bundle has {} user operations, no index {}",
+                    ops.len(),
+                    args.op_index
+                )
+            })?;
+            let block = tx
+                .block_number
+                .map(|number| number.as_u64().saturating_sub(1));
+            (op, tx.to, block)
+        }
+        (None, None) => bail!("debug requires a user operation or a bundle transaction hash"),
+    };
+
+    let entry_point_address = match args.entry_point.or(tx_entry_point) {
+        Some(address) => address,
+        None => common_args
+            .entry_points
+            .first()
+            .context("debug requires an entry point")?
+            .parse()
+            .context("Invalid entry_points argument")?,
+    };
+    let block_hash = match args.block.or(tx_block) {
+        Some(number) => provider
+            .get_block(number)
+            .await?
+            .and_then(|block| block.hash)
+            .with_context(|| format!("block {number} should exist"))?,
+        None => provider.get_latest_block_hash_and_number().await?.0,
+    };
+
+    let precheck_settings: PrecheckSettings = (&common_args).try_into()?;
+    let estimation_settings: EstimationSettings = (&common_args).try_into()?;
+    let sim_settings: SimulationSettings = (&common_args).into();
+    let mempool_configs = common_args.mempool_configs().await?;
+    let abi_registry = common_args.abi_registry()?;
+    let entry_point = IEntryPoint::new(entry_point_address, Arc::clone(&provider));
+
+    println!("User operation {:#?}", op);
+    println!("Entry point {entry_point_address:?}, block {block_hash:?}");
+
+    println!("\n== Precheck ==");
+    let prechecker = PrecheckerImpl::new(
+        Arc::clone(&provider),
+        entry_point.clone(),
+        precheck_settings.clone(),
+    );
+    match prechecker.check_at_block(&op, Some(block_hash)).await {
+        Ok(_) => println!("passed"),
+        Err(ViolationError::Violations(violations)) => {
+            for violation in violations {
+                println!("violation: {violation}");
+            }
+        }
+        Err(ViolationError::Other(error)) => println!("error: {error:#}"),
+    }
+
+    println!("\n== Validation ==");
+    let simulator = SimulatorImpl::new(
+        Arc::clone(&provider),
+        entry_point_address,
+        SimulateValidationTracerImpl::new(Arc::clone(&provider), entry_point.clone()),
+        sim_settings,
+        mempool_configs,
+    )
+    .with_allowed_precompiles(&precheck_settings.chain_spec.precompile_addresses);
+    match simulator
+        .trace_validation(op.clone(), Some(block_hash))
+        .await
+    {
+        Ok(report) => print_validation_report(&report),
+        Err(error) => println!("error: {}", error.violation_error),
+    }
+
+    // Estimation simulates at the block, but prices preVerificationGas at the
+    // current fees
+    println!("\n== Gas estimation ==");
+    let estimator = GasEstimatorImpl::new(
+        precheck_settings.chain_spec.l1_gas,
+        Arc::clone(&provider),
+        entry_point.clone(),
+        estimation_settings,
+        FeeEstimator::new(
+            Arc::clone(&provider),
+            &precheck_settings.chain_spec,
+            precheck_settings.priority_fee_mode,
+            precheck_settings.bundle_priority_fee_overhead_percent,
+        ),
+        SimulateValidationTracerImpl::new(Arc::clone(&provider), entry_point.clone()),
+    );
+    match estimator
+        .estimate_op_gas_at_block(optional_gas(&op), spoof::State::default(), block_hash)
+        .await
+    {
+        Ok(estimate) => {
+            print_gas(
+                "preVerificationGas",
+                op.pre_verification_gas,
+                estimate.pre_verification_gas,
+            );
+            print_gas(
+                "verificationGasLimit",
+                op.verification_gas_limit,
+                estimate.verification_gas_limit,
+            );
+            print_gas("callGasLimit", op.call_gas_limit, estimate.call_gas_limit);
+        }
+        Err(error) => println!("error: {error}"),
+    }
+
+    println!("\n== simulateHandleOp ==");
+    let gas = U256::from(sim_settings.max_simulate_handle_ops_gas);
+    match entry_point
+        .call_spoofed_simulate_op(
+            op.clone(),
+            Address::zero(),
+            Bytes::new(),
+            block_hash,
+            gas,
+            &spoof::State::default(),
+        )
+        .await
+    {
+        Ok(Ok(result)) => {
+            println!("preOpGas: {}", result.pre_op_gas);
+            println!("paid: {}", result.paid);
+            println!("validAfter: {}", result.valid_after);
+            println!("validUntil: {}", result.valid_until);
+        }
+        Ok(Err(reason)) => println!("reverted: {reason}"),
+        Err(error) => println!("error: {error:#}"),
+    }
+
+    // simulateHandleOp always reverts, which discards the events reporting the
+    // result of the operation's call, so trace the call in handleOps instead
+    println!("\n== Call ==");
+    let op_hash = op.op_hash(entry_point_address, precheck_settings.chain_spec.id);
+    // the sender is the beneficiary, so that no funds leave the operation's accounts
+    let tx = entry_point
+        .handle_ops(vec![op.clone()], op.sender)
+        .gas(gas)
+        .tx;
+    match trace_call_result(&*provider, tx, block_hash, entry_point_address, op_hash).await {
+        Ok(CallResult::Succeeded) => println!("succeeded"),
+        Ok(CallResult::Reverted(Some(reason))) => {
+            println!("reverted: {}", abi_registry.format(&reason))
+        }
+        Ok(CallResult::Reverted(None)) => println!("reverted"),
+        Ok(CallResult::NotExecuted(Some(revert_data))) => println!(
+            "handleOps reverted: {}",
+            entry_point
+                .decode_simulate_handle_ops_revert(revert_data)
+                .err()
+                .unwrap_or_else(|| "unknown revert".to_string())
+        ),
+        Ok(CallResult::NotExecuted(None)) => println!("handleOps did not execute the operation"),
+        Err(error) => println!("error: {error:#}"),
+    }
+
+    Ok(())
+}
+
+fn print_validation_report(report: &ValidationReport) {
+    for phase in &report.phases {
+        let address = phase
+            .address
+            .map(|address| format!("{address:?}"))
+            .unwrap_or_else(|| "none".to_string());
+        println!(
+            "{} {address}: gas used {}{}",
+            phase.entity,
+            phase.gas_used,
+            if phase.ran_out_of_gas {
+                ", ran out of gas"
+            } else {
+                ""
+            }
+        );
+        for (contract, opcode) in &phase.forbidden_opcodes_used {
+            println!("  forbidden opcode {opcode:?} used by {contract:?}");
+        }
+        for (contract, precompile) in &phase.forbidden_precompiles_used {
+            println!("  forbidden precompile {precompile:?} called by {contract:?}");
+        }
+        for address in &phase.undeployed_contract_accesses {
+            println!("  accessed undeployed contract {address:?}");
+        }
+        for (contract, access) in &phase.storage_accesses {
+            println!(
+                "  storage of {contract:?}: {} slots read, {} written",
+                access.reads.len(),
+                access.writes.len()
+            );
+        }
+    }
+
+    if report.violations.is_empty() {
+        println!("no violations");
+    }
+    for violation in &report.violations {
+        println!("violation: {}", violation.violation);
+        if violation.allow_rules.is_empty() {
+            println!("  cannot be allowlisted");
+        } else {
+            println!("  allowlist rules: {}", violation.allow_rules.join(", "));
+        }
+        for allowed in &violation.allowed_by {
+            println!(
+                "  allowed by mempool {:?} entry {}{}",
+                allowed.mempool,
+                allowed.index,
+                allowed
+                    .description
+                    .as_ref()
+                    .map(|description| format!(" ({description})"))
+                    .unwrap_or_default()
+            );
+        }
+    }
+    if !report.mempools.is_empty() {
+        println!("valid in alternative mempools: {:?}", report.mempools);
+    }
+}
+
+// The result of an operation's call in `handleOps`
+enum CallResult {
+    Succeeded,
+    // With the revert data of the call, if the entry point reported it
+    Reverted(Option<Bytes>),
+    // `handleOps` did not run the call, such as when validation failed, with
+    // the revert data of `handleOps`, if any
+    NotExecuted(Option<Bytes>),
+}
+
+// Traces a `handleOps` transaction and reads the result of an operation's call
+// from the `UserOperationEvent` and `UserOperationRevertReason` events
+async fn trace_call_result<P: Provider>(
+    provider: &P,
+    tx: TypedTransaction,
+    block_hash: H256,
+    entry_point: Address,
+    op_hash: H256,
+) -> anyhow::Result<CallResult> {
+    let trace = provider
+        .debug_trace_call(
+            tx,
+            Some(block_hash.into()),
+            GethDebugTracingCallOptions {
+                tracing_options: GethDebugTracingOptions {
+                    tracer: Some(GethDebugTracerType::BuiltInTracer(
+                        GethDebugBuiltInTracerType::CallTracer,
+                    )),
+                    tracer_config: Some(GethDebugTracerConfig::BuiltInTracer(
+                        GethDebugBuiltInTracerConfig::CallTracer(CallConfig {
+                            only_top_call: Some(false),
+                            with_log: Some(true),
+                        }),
+                    )),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .await
+        .context("should trace handleOps")?;
+    let GethTrace::Known(GethTraceFrame::CallTracer(root)) = trace else {
+        bail!("trace should be a call trace");
+    };
+    if root.error.is_some() {
+        return Ok(CallResult::NotExecuted(root.output));
+    }
+
+    let mut revert_reason = None;
+    let mut frames = vec![root];
+    while let Some(frame) = frames.pop() {
+        for log in frame.logs.into_iter().flatten() {
+            let (Some(address), Some(topics)) = (log.address, log.topics) else {
+                continue;
+            };
+            if address != entry_point || topics.get(1) != Some(&op_hash) {
+                continue;
+            }
+            let raw_log = RawLog {
+                topics,
+                data: log.data.unwrap_or_default().to_vec(),
+            };
+            if let Ok(event) = UserOperationRevertReasonFilter::decode_log(&raw_log) {
+                revert_reason = Some(event.revert_reason);
+            } else if let Ok(event) = UserOperationEventFilter::decode_log(&raw_log) {
+                return Ok(if event.success {
+                    CallResult::Succeeded
+                } else {
+                    CallResult::Reverted(revert_reason)
+                });
+            }
+        }
+        frames.extend(frame.calls.into_iter().flatten().rev());
+    }
+    Ok(CallResult::NotExecuted(None))
+}
+
+fn print_gas(name: &str, value: U256, estimate: U256) {
+    println!(
+        "{name}: {value} (estimate {estimate}){}",
+        if value < estimate { " TOO LOW" } else { "" }
+    );
+}
+
+// Parses a user operation from JSON, or from the JSON file at the given path
+fn parse_user_op(user_op: &str) -> anyhow::Result<UserOperation> {
+    let json = if user_op.trim_start().starts_with('{') {
+        user_op.to_string()
+    } else {
+        std::fs::read_to_string(user_op)
+            .with_context(|| format!("should read user operation file {user_op}"))?
+    };
+    let op: RpcUserOperation =
+        serde_json::from_str(&json).context("should parse user operation")?;
+    Ok(op.into())
+}
+
+// Returns the user operations of a bundle transaction, in order
+fn user_ops_from_bundle(tx_data: Bytes) -> anyhow::Result<Vec<UserOperation>> {
+    match IEntryPointCalls::decode(tx_data).context("transaction should call the entry point")? {
+        IEntryPointCalls::HandleOps(call) => Ok(call.ops),
+        IEntryPointCalls::HandleAggregatedOps(call) => Ok(call
+            .ops_per_aggregator
+            .into_iter()
+            .flat_map(|ops| ops.user_ops)
+            .collect()),
+        _ => bail!("transaction should be a handleOps or handleAggregatedOps call"),
+    }
+}
+
+fn optional_gas(op: &UserOperation) -> UserOperationOptionalGas {
+    UserOperationOptionalGas {
+        sender: op.sender,
+        nonce: op.nonce,
+        init_code: op.init_code.clone(),
+        call_data: op.call_data.clone(),
+        call_gas_limit: None,
+        verification_gas_limit: None,
+        pre_verification_gas: None,
+        max_fee_per_gas: Some(op.max_fee_per_gas),
+        max_priority_fee_per_gas: Some(op.max_priority_fee_per_gas),
+        paymaster_and_data: op.paymaster_and_data.clone(),
+        signature: op.signature.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::abi::AbiEncode;
+    use rundler_types::contracts::i_entry_point::{
+        HandleAggregatedOpsCall, HandleOpsCall, UserOpsPerAggregator,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_user_op() {
+        let op = UserOperation {
+            sender: Address::random(),
+            nonce: 3.into(),
+            call_data: Bytes::from(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        let json = serde_json::to_string(&RpcUserOperation::from(op.clone())).unwrap();
+        assert_eq!(parse_user_op(&json).unwrap(), op);
+        assert!(parse_user_op("/nonexistent/op.json").is_err());
+    }
+
+    #[test]
+    fn test_user_ops_from_bundle() {
+        let ops = vec![
+            UserOperation {
+                nonce: 1.into(),
+                ..Default::default()
+            },
+            UserOperation {
+                nonce: 2.into(),
+                ..Default::default()
+            },
+        ];
+        let handle_ops = IEntryPointCalls::HandleOps(HandleOpsCall {
+            ops: ops.clone(),
+            beneficiary: Address::zero(),
+        });
+        assert_eq!(
+            user_ops_from_bundle(handle_ops.encode().into()).unwrap(),
+            ops
+        );
+
+        let handle_aggregated_ops =
+            IEntryPointCalls::HandleAggregatedOps(HandleAggregatedOpsCall {
+                ops_per_aggregator: vec![UserOpsPerAggregator {
+                    user_ops: ops.clone(),
+                    aggregator: Address::random(),
+                    signature: Bytes::default(),
+                }],
+                beneficiary: Address::zero(),
+            });
+        assert_eq!(
+            user_ops_from_bundle(handle_aggregated_ops.encode().into()).unwrap(),
+            ops
+        );
+
+        assert!(user_ops_from_bundle(Bytes::from(vec![1, 2, 3, 4])).is_err());
+    }
+}
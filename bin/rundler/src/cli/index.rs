@@ -0,0 +1,94 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::path::PathBuf;
+
+use anyhow::Context;
+use clap::Args;
+use ethers::types::Address;
+use rundler_pool::UserOperationIndex;
+use rundler_provider::Provider;
+use rundler_utils::eth;
+
+use super::CommonArgs;
+
+/// CLI options for the index command
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Index")]
+pub struct IndexCliArgs {
+    /// Path of the user operation index database
+    #[arg(long = "index.path", name = "index.path", env = "INDEX_PATH")]
+    path: PathBuf,
+
+    /// First block to backfill
+    #[arg(
+        long = "index.from_block",
+        name = "index.from_block",
+        env = "INDEX_FROM_BLOCK",
+        default_value = "0"
+    )]
+    from_block: u64,
+
+    /// Last block to backfill. Defaults to the latest block.
+    #[arg(
+        long = "index.to_block",
+        name = "index.to_block",
+        env = "INDEX_TO_BLOCK"
+    )]
+    to_block: Option<u64>,
+
+    /// Maximum number of blocks to load user operation events for in one request
+    #[arg(
+        long = "index.blocks_per_request",
+        name = "index.blocks_per_request",
+        env = "INDEX_BLOCKS_PER_REQUEST",
+        default_value = "1000"
+    )]
+    blocks_per_request: u64,
+}
+
+/// Backfills the user operation index with the operations mined in a range of
+/// blocks. The index must not be in use by a running node.
+pub async fn run(args: IndexCliArgs, common_args: CommonArgs) -> anyhow::Result<()> {
+    let node_http = common_args
+        .node_http
+        .clone()
+        .context("index requires node_http arg")?;
+    let provider = eth::new_provider(&node_http, None)?;
+    let entry_points = common_args
+        .entry_points
+        .iter()
+        .map(|ep| ep.parse())
+        .collect::<Result<Vec<Address>, _>>()
+        .context("Invalid entry_points argument")?;
+
+    let index = UserOperationIndex::open(&args.path)?;
+    let to_block = match args.to_block {
+        Some(block) => block,
+        None => provider.get_block_number().await?,
+    };
+    let num_ops = index
+        .backfill(
+            &*provider,
+            &entry_points,
+            args.from_block,
+            to_block,
+            args.blocks_per_request,
+        )
+        .await?;
+    tracing::info!(
+        "Indexed {num_ops} user operations in blocks {}-{to_block}",
+        args.from_block
+    );
+    Ok(())
+}
@@ -19,7 +19,7 @@ use rusoto_s3::{GetObjectRequest, S3Client, S3};
 use serde::de::DeserializeOwned;
 use tokio::io::AsyncReadExt;
 
-/// Reads and deserializes a JSON config file from a local path or an S3 bucket.
+/// Reads and deserializes a JSON config file from a local path, an HTTP(S) URL, or an S3 bucket.
 ///
 /// If the path starts with `s3://`, the file is read from S3 using the given region.
 /// T must implement `serde::Deserialize`.
@@ -29,6 +29,8 @@ where
 {
     if path.starts_with("s3://") {
         get_s3_json_config(path, aws_s3_region).await
+    } else if path.starts_with("http://") || path.starts_with("https://") {
+        get_http_json_config(path).await
     } else {
         get_local_json_config(path)
     }
@@ -43,6 +45,14 @@ where
     Ok(serde_json::from_reader(reader)?)
 }
 
+async fn get_http_json_config<T>(url: &str) -> anyhow::Result<T>
+where
+    T: DeserializeOwned,
+{
+    let resp = reqwest::get(url).await?.error_for_status()?;
+    Ok(resp.json().await?)
+}
+
 async fn get_s3_json_config<T>(path: &str, aws_s3_region: &str) -> anyhow::Result<T>
 where
     T: DeserializeOwned,
@@ -0,0 +1,234 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use clap::{builder::PossibleValuesParser, Args};
+use ethers::types::Address;
+use rundler_builder::{
+    BuilderServer, BundleLedger, LedgerEntry, LedgerQuery, LedgerReport, LedgerSummary,
+    RemoteBuilderClient,
+};
+
+/// Number of bundles to request from a builder at a time
+const BUILDER_PAGE_SIZE: u64 = 1000;
+
+/// CLI options for the ledger command
+#[derive(Args, Debug)]
+#[command(next_help_heading = "Ledger")]
+pub struct LedgerCliArgs {
+    /// Path of the bundle ledger database. The database can't be opened while
+    /// a builder is recording to it, use `ledger.builder_url` instead.
+    #[arg(
+        long = "ledger.path",
+        name = "ledger.path",
+        env = "LEDGER_PATH",
+        required_unless_present = "ledger.builder_url",
+        conflicts_with = "ledger.builder_url"
+    )]
+    path: Option<PathBuf>,
+
+    /// URL of a running builder to export its ledger from
+    #[arg(
+        long = "ledger.builder_url",
+        name = "ledger.builder_url",
+        env = "LEDGER_BUILDER_URL"
+    )]
+    builder_url: Option<String>,
+
+    /// Format to export in
+    #[arg(
+        long = "ledger.format",
+        name = "ledger.format",
+        env = "LEDGER_FORMAT",
+        default_value = "json",
+        value_parser = PossibleValuesParser::new(["json", "csv"])
+    )]
+    format: String,
+
+    /// Whether CSV rows are bundles or the user operations in them
+    #[arg(
+        long = "ledger.csv_rows",
+        name = "ledger.csv_rows",
+        env = "LEDGER_CSV_ROWS",
+        default_value = "bundles",
+        value_parser = PossibleValuesParser::new(["bundles", "ops"])
+    )]
+    csv_rows: String,
+
+    /// Earliest block timestamp to export, in seconds since the epoch
+    #[arg(
+        long = "ledger.from_timestamp",
+        name = "ledger.from_timestamp",
+        env = "LEDGER_FROM_TIMESTAMP"
+    )]
+    from_timestamp: Option<u64>,
+
+    /// Latest block timestamp to export, in seconds since the epoch
+    #[arg(
+        long = "ledger.to_timestamp",
+        name = "ledger.to_timestamp",
+        env = "LEDGER_TO_TIMESTAMP"
+    )]
+    to_timestamp: Option<u64>,
+
+    /// Only export bundles sent by this builder
+    #[arg(
+        long = "ledger.builder_index",
+        name = "ledger.builder_index",
+        env = "LEDGER_BUILDER_INDEX"
+    )]
+    builder_index: Option<u64>,
+
+    /// Only export the user operations of this paymaster, and the bundles containing them
+    #[arg(
+        long = "ledger.paymaster",
+        name = "ledger.paymaster",
+        env = "LEDGER_PAYMASTER"
+    )]
+    paymaster: Option<Address>,
+
+    /// File to write the export to. Defaults to stdout.
+    #[arg(long = "ledger.output", name = "ledger.output", env = "LEDGER_OUTPUT")]
+    output: Option<PathBuf>,
+}
+
+/// Exports the bundles recorded in the ledger, either from its database or,
+/// page by page, from the running builder recording to it.
+pub async fn run(args: LedgerCliArgs) -> anyhow::Result<()> {
+    let query = LedgerQuery {
+        from_timestamp: args.from_timestamp,
+        to_timestamp: args.to_timestamp,
+        builder_index: args.builder_index,
+        paymaster: args.paymaster,
+        ..Default::default()
+    };
+    let report = match (&args.path, &args.builder_url) {
+        (Some(path), _) => BundleLedger::open(path)?.report(&query)?,
+        (None, Some(url)) => {
+            let builder = RemoteBuilderClient::connect(url.clone())
+                .await
+                .with_context(|| format!("should connect to builder at {url}"))?;
+            let mut query = LedgerQuery {
+                limit: Some(BUILDER_PAGE_SIZE),
+                ..query
+            };
+            let mut bundles = vec![];
+            loop {
+                let page = builder.get_ledger(query.clone()).await?;
+                bundles.extend(page.bundles);
+                match page.next_cursor {
+                    Some(cursor) => query.cursor = Some(cursor),
+                    None => break,
+                }
+            }
+            LedgerReport {
+                summary: LedgerSummary::new(&bundles),
+                bundles,
+                next_cursor: None,
+            }
+        }
+        (None, None) => unreachable!("clap should require a ledger path or builder url"),
+    };
+
+    let mut out: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).with_context(|| format!("should create {path:?}"))?,
+        )),
+        None => Box::new(io::stdout().lock()),
+    };
+    match (args.format.as_str(), args.csv_rows.as_str()) {
+        ("csv", "ops") => write_op_csv(&mut out, &report.bundles)?,
+        ("csv", _) => write_bundle_csv(&mut out, &report.bundles)?,
+        _ => write_json(&mut out, &report)?,
+    }
+    out.flush()?;
+
+    tracing::info!(
+        "Exported {} bundles with {} user operations, profit {} wei",
+        report.summary.bundles,
+        report.summary.ops,
+        report.summary.profit
+    );
+    Ok(())
+}
+
+fn write_json(out: &mut impl Write, report: &LedgerReport) -> anyhow::Result<()> {
+    serde_json::to_writer_pretty(&mut *out, report)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+// Amounts are written in wei as decimals, and contain no characters that need quoting
+fn write_bundle_csv(out: &mut impl Write, bundles: &[LedgerEntry]) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        "tx_hash,entry_point,builder_index,beneficiary,block_number,block_timestamp,gas_used,\
+         effective_gas_price,l1_fee,gas_cost,ops,op_revenue,profit,beneficiary_balance_delta"
+    )?;
+    for b in bundles {
+        writeln!(
+            out,
+            "{:?},{:?},{},{:?},{},{},{},{},{},{},{},{},{},{}",
+            b.tx_hash,
+            b.entry_point,
+            b.builder_index,
+            b.beneficiary,
+            b.block_number,
+            b.block_timestamp,
+            b.gas_used,
+            b.effective_gas_price,
+            b.l1_fee(),
+            b.gas_cost(),
+            b.ops.len(),
+            b.op_revenue(),
+            b.profit(),
+            b.beneficiary_balance_delta(),
+        )?;
+    }
+    Ok(())
+}
+
+fn write_op_csv(out: &mut impl Write, bundles: &[LedgerEntry]) -> anyhow::Result<()> {
+    writeln!(
+        out,
+        "op_hash,sender,paymaster,success,actual_gas_cost,actual_gas_used,tx_hash,builder_index,\
+         block_number,block_timestamp"
+    )?;
+    for b in bundles {
+        for op in &b.ops {
+            writeln!(
+                out,
+                "{:?},{:?},{},{},{},{},{:?},{},{},{}",
+                op.hash,
+                op.sender,
+                op.paymaster
+                    .map(|paymaster| format!("{paymaster:?}"))
+                    .unwrap_or_default(),
+                op.success,
+                op.actual_gas_cost,
+                op.actual_gas_used,
+                b.tx_hash,
+                b.builder_index,
+                b.block_number,
+                b.block_timestamp,
+            )?;
+        }
+    }
+    Ok(())
+}
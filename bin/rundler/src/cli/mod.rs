@@ -11,11 +11,23 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
 use anyhow::Context;
 use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
+use ethers::types::{Address, H256};
 
 mod builder;
+mod chain_spec;
+mod debug;
+mod index;
 mod json;
+mod ledger;
 mod metrics;
 mod node;
 mod pool;
@@ -23,13 +35,30 @@ mod rpc;
 mod tracing;
 
 use builder::BuilderCliArgs;
+use debug::DebugCliArgs;
+use index::IndexCliArgs;
+use ledger::LedgerCliArgs;
 use node::NodeCliArgs;
 use pool::PoolCliArgs;
 use rpc::RpcCliArgs;
+use rundler_pool::{MempoolConfigReloader, ReloadSettings};
 use rundler_rpc::EthApiSettings;
 use rundler_sim::{
-    EstimationSettings, PrecheckSettings, PriorityFeeMode, SimulationSettings, MIN_CALL_GAS_LIMIT,
+    EstimationSettings, MempoolConfig, MempoolRegistry, PrecheckSettings, PriorityFeeMode,
+    SharedMempoolConfigs, SimulationSettings, MIN_CALL_GAS_LIMIT,
 };
+use rundler_task::Task;
+use rundler_types::chain::{ChainSpec, L1GasConfig, L1GasOracleContractType};
+use rundler_utils::revert::AbiRegistry;
+
+use self::{chain_spec::resolve_chain_spec, json::get_json_config};
+
+/// How often a builder or RPC server in its own process retries resolving
+/// mempool IDs, if it doesn't reload mempool configs
+const DEFAULT_MEMPOOL_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Lower bound of the Eth node poll interval derived from the chain's block time
+const MIN_DEFAULT_ETH_POLL_INTERVAL_MILLIS: u64 = 100;
 
 /// Main entry point for the CLI
 ///
@@ -37,22 +66,32 @@ use rundler_sim::{
 /// Listens for a ctrl-c signal and shuts down all components when received.
 pub async fn run() -> anyhow::Result<()> {
     let opt = Cli::parse();
-    let _guard = tracing::configure_logging(&opt.logs)?;
+    let _guard = tracing::configure_logging(&opt.logs, &opt.tracing)?;
     tracing::info!("Parsed CLI options: {:#?}", opt);
 
-    let metrics_addr = format!("{}:{}", opt.metrics.host, opt.metrics.port).parse()?;
-    metrics::initialize(
-        opt.metrics.sample_interval_millis,
-        metrics_addr,
-        &opt.metrics.tags,
-    )
-    .context("metrics server should start")?;
+    // Debugging, indexing, and ledger exports run once and exit, and would conflict with a
+    // running node's metrics server
+    if !matches!(
+        opt.command,
+        Command::Debug(_) | Command::Index(_) | Command::Ledger(_)
+    ) {
+        let metrics_addr = format!("{}:{}", opt.metrics.host, opt.metrics.port).parse()?;
+        metrics::initialize(
+            opt.metrics.sample_interval_millis,
+            metrics_addr,
+            &opt.metrics.tags,
+        )
+        .context("metrics server should start")?;
+    }
 
     match opt.command {
         Command::Node(args) => node::run(*args, opt.common).await?,
         Command::Pool(args) => pool::run(args, opt.common).await?,
         Command::Rpc(args) => rpc::run(args, opt.common).await?,
         Command::Builder(args) => builder::run(args, opt.common).await?,
+        Command::Debug(args) => debug::run(args, opt.common).await?,
+        Command::Index(args) => index::run(args, opt.common).await?,
+        Command::Ledger(args) => ledger::run(args).await?,
     }
 
     tracing::info!("Shutdown, goodbye");
@@ -85,6 +124,26 @@ enum Command {
     /// Runs the Builder server
     #[command(name = "builder")]
     Builder(BuilderCliArgs),
+
+    /// Debug command
+    ///
+    /// Runs a user operation through precheck, validation, gas estimation, and
+    /// simulateHandleOp at a block, and prints a report
+    #[command(name = "debug")]
+    Debug(DebugCliArgs),
+
+    /// Index command
+    ///
+    /// Backfills the user operation index with the operations mined in a range
+    /// of blocks
+    #[command(name = "index")]
+    Index(IndexCliArgs),
+
+    /// Ledger command
+    ///
+    /// Exports the bundles recorded in the builder's ledger as JSON or CSV
+    #[command(name = "ledger")]
+    Ledger(LedgerCliArgs),
 }
 
 /// CLI common options
@@ -103,14 +162,41 @@ pub struct CommonArgs {
     entry_points: Vec<String>,
 
     /// Chain ID to target
+    ///
+    /// Overrides the ID of the chain spec. Defaults to the ID of the chain spec.
+    #[arg(long = "chain_id", name = "chain_id", env = "CHAIN_ID", global = true)]
+    chain_id: Option<u64>,
+
+    /// Chain spec preset name, or path to a TOML or JSON chain spec file
+    ///
+    /// Defaults to the preset for `chain_id`, or the `dev` preset.
+    #[arg(
+        long = "chain_spec",
+        name = "chain_spec",
+        env = "CHAIN_SPEC",
+        global = true
+    )]
+    chain_spec: Option<String>,
+
+    /// Type of contract used to price L1 data on the chain
+    ///
+    /// Overrides the L1 gas oracle type of the chain spec.
+    #[arg(
+        long = "l1_gas_oracle_type",
+        name = "l1_gas_oracle_type",
+        env = "L1_GAS_ORACLE_TYPE",
+        global = true
+    )]
+    l1_gas_oracle_type: Option<L1GasOracleContractType>,
+
+    /// Address of the L1 gas oracle contract, overriding the chain spec
     #[arg(
-        long = "chain_id",
-        name = "chain_id",
-        env = "CHAIN_ID",
-        default_value = "1337",
+        long = "l1_gas_oracle_address",
+        name = "l1_gas_oracle_address",
+        env = "L1_GAS_ORACLE_ADDRESS",
         global = true
     )]
-    chain_id: u64,
+    l1_gas_oracle_address: Option<Address>,
 
     /// ETH Node HTTP URL to connect to
     #[arg(
@@ -133,11 +219,10 @@ pub struct CommonArgs {
     #[arg(
         long = "max_bundle_gas",
         name = "max_bundle_gas",
-        default_value = "25000000",
         env = "MAX_BUNDLE_GAS",
         global = true
     )]
-    max_bundle_gas: u64,
+    max_bundle_gas: Option<u64>,
 
     #[arg(
         long = "min_stake_value",
@@ -227,14 +312,14 @@ pub struct CommonArgs {
     pre_verification_gas_accept_percent: u64,
 
     /// Interval at which the builder polls an Eth node for new blocks and
-    /// mined transactions.
+    /// mined transactions. Defaults to a tenth of the chain's block time, and
+    /// at least 100ms.
     #[arg(
         long = "eth_poll_interval_millis",
         name = "eth_poll_interval_millis",
-        env = "ETH_POLL_INTERVAL_MILLIS",
-        default_value = "100"
+        env = "ETH_POLL_INTERVAL_MILLIS"
     )]
-    pub eth_poll_interval_millis: u64,
+    pub eth_poll_interval_millis: Option<u64>,
 
     #[arg(
         long = "aws_region",
@@ -251,6 +336,28 @@ pub struct CommonArgs {
     )]
     pub mempool_config_path: Option<String>,
 
+    #[arg(
+        long = "mempool_ids",
+        name = "mempool_ids",
+        env = "MEMPOOL_IDS",
+        value_delimiter = ','
+    )]
+    pub mempool_ids: Vec<H256>,
+
+    #[arg(
+        long = "mempool_registry_url",
+        name = "mempool_registry_url",
+        env = "MEMPOOL_REGISTRY_URL"
+    )]
+    pub mempool_registry_url: Option<String>,
+
+    #[arg(
+        long = "mempool_cache_dir",
+        name = "mempool_cache_dir",
+        env = "MEMPOOL_CACHE_DIR"
+    )]
+    pub mempool_cache_dir: Option<PathBuf>,
+
     #[arg(
         long = "num_builders",
         name = "num_builders",
@@ -258,10 +365,147 @@ pub struct CommonArgs {
         default_value = "1"
     )]
     pub num_builders: u64,
+
+    /// Directory of JSON ABIs and error signature files used to decode the
+    /// custom errors of accounts and paymasters
+    #[arg(
+        long = "abi_registry_path",
+        name = "abi_registry_path",
+        env = "ABI_REGISTRY_PATH",
+        global = true
+    )]
+    pub abi_registry_path: Option<PathBuf>,
+
+    // The chain spec, resolved on first use
+    #[arg(skip)]
+    resolved_chain_spec: OnceLock<ChainSpec>,
 }
 
 const SIMULATION_GAS_OVERHEAD: u64 = 100_000;
 
+impl CommonArgs {
+    /// Returns the registry used to resolve `mempool_ids`, if any are set.
+    pub fn mempool_registry(&self) -> anyhow::Result<Option<MempoolRegistry>> {
+        if self.mempool_ids.is_empty() {
+            return Ok(None);
+        }
+        MempoolRegistry::new(
+            self.mempool_registry_url.clone(),
+            self.mempool_cache_dir.clone(),
+        )
+        .map(Some)
+    }
+
+    /// Loads the mempool configs from `mempool_config_path` and resolves the
+    /// configs of `mempool_ids`.
+    ///
+    /// IDs that fail to resolve are logged and omitted.
+    pub async fn mempool_configs(&self) -> anyhow::Result<HashMap<H256, MempoolConfig>> {
+        let mut configs = match &self.mempool_config_path {
+            Some(path) => {
+                get_json_config::<HashMap<H256, MempoolConfig>>(path, &self.aws_region).await?
+            }
+            None => HashMap::from([(H256::zero(), MempoolConfig::default())]),
+        };
+        MempoolRegistry::check_conflicts(&configs, &self.mempool_ids)?;
+        if let Some(registry) = self.mempool_registry()? {
+            configs.extend(registry.resolve_all(&self.mempool_ids).await);
+        }
+        Ok(configs)
+    }
+
+    /// Settings to reload the mempool configs from `mempool_config_path` at
+    /// `reload_interval`, if set, and to retry resolving `mempool_ids` at
+    /// `refresh_interval` otherwise. `None` if there is nothing to reload.
+    ///
+    /// The blocklist and allowlist are not set.
+    pub fn mempool_reload_settings(
+        &self,
+        reload_interval: Option<Duration>,
+        refresh_interval: Duration,
+    ) -> anyhow::Result<Option<ReloadSettings>> {
+        // Mempool IDs are refreshed even if file reloading is disabled
+        if reload_interval.is_none() && self.mempool_ids.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ReloadSettings {
+            blocklist_path: None,
+            allowlist_path: None,
+            mempool_config_path: reload_interval
+                .and(pool::reloadable_path(&self.mempool_config_path)),
+            mempool_ids: self.mempool_ids.clone(),
+            mempool_registry: self.mempool_registry()?,
+            poll_interval: reload_interval.unwrap_or(refresh_interval),
+        }))
+    }
+
+    /// The task reloading the mempool configs of a builder or RPC server
+    /// running in its own process, if there is anything to reload. In a node,
+    /// the pool's reloader updates the configs shared with the builder and
+    /// RPC server instead.
+    pub fn mempool_config_reloader(
+        &self,
+        reload_interval_secs: Option<u64>,
+        mempool_configs: SharedMempoolConfigs,
+    ) -> anyhow::Result<Option<Box<dyn Task>>> {
+        let settings = self.mempool_reload_settings(
+            reload_interval_secs.map(Duration::from_secs),
+            DEFAULT_MEMPOOL_REFRESH_INTERVAL,
+        )?;
+        Ok(settings
+            .map(|settings| MempoolConfigReloader::new(settings, mempool_configs, None).boxed()))
+    }
+
+    /// Returns the registry used to decode revert data, loaded with the ABIs in
+    /// `abi_registry_path`.
+    pub fn abi_registry(&self) -> anyhow::Result<Arc<AbiRegistry>> {
+        let mut registry = AbiRegistry::default();
+        if let Some(path) = &self.abi_registry_path {
+            let num_errors = registry.load_dir(path)?;
+            tracing::info!("Loaded {num_errors} errors into the ABI registry from {path:?}");
+        }
+        Ok(Arc::new(registry))
+    }
+
+    /// Returns the chain spec with the chain overrides set on the command line
+    /// applied. The spec is resolved once, and reused by later calls.
+    pub fn chain_spec(&self) -> anyhow::Result<ChainSpec> {
+        if let Some(chain_spec) = self.resolved_chain_spec.get() {
+            return Ok(chain_spec.clone());
+        }
+        let chain_spec = self.load_chain_spec()?;
+        Ok(self.resolved_chain_spec.get_or_init(|| chain_spec).clone())
+    }
+
+    /// Returns the interval at which Eth nodes are polled for new blocks and
+    /// mined transactions
+    pub fn eth_poll_interval(&self) -> anyhow::Result<Duration> {
+        let millis = match self.eth_poll_interval_millis {
+            Some(millis) => millis,
+            None => (self.chain_spec()?.block_time_millis / 10)
+                .max(MIN_DEFAULT_ETH_POLL_INTERVAL_MILLIS),
+        };
+        Ok(Duration::from_millis(millis))
+    }
+
+    fn load_chain_spec(&self) -> anyhow::Result<ChainSpec> {
+        let mut chain_spec = resolve_chain_spec(self.chain_spec.as_deref(), self.chain_id)?;
+        if let Some(oracle_type) = self.l1_gas_oracle_type {
+            chain_spec.l1_gas = L1GasConfig {
+                oracle_type,
+                oracle_address: None,
+            };
+        }
+        if self.l1_gas_oracle_address.is_some() {
+            chain_spec.l1_gas.oracle_address = self.l1_gas_oracle_address;
+        }
+        if let Some(max_bundle_gas) = self.max_bundle_gas {
+            chain_spec.max_bundle_gas = max_bundle_gas;
+        }
+        Ok(chain_spec)
+    }
+}
+
 impl TryFrom<&CommonArgs> for EstimationSettings {
     type Error = anyhow::Error;
 
@@ -297,10 +541,11 @@ impl TryFrom<&CommonArgs> for PrecheckSettings {
     type Error = anyhow::Error;
 
     fn try_from(value: &CommonArgs) -> anyhow::Result<Self> {
+        let chain_spec = value.chain_spec()?;
         Ok(Self {
-            chain_id: value.chain_id,
             max_verification_gas: value.max_verification_gas.into(),
-            max_total_execution_gas: value.max_bundle_gas.into(),
+            max_total_execution_gas: chain_spec.max_bundle_gas.into(),
+            chain_spec,
             bundle_priority_fee_overhead_percent: value.bundle_priority_fee_overhead_percent,
             priority_fee_mode: PriorityFeeMode::try_from(
                 value.priority_fee_mode_kind.as_str(),
@@ -407,6 +652,54 @@ pub struct LogsArgs {
     json: bool,
 }
 
+/// CLI options for exporting traces
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Tracing")]
+pub struct TracingArgs {
+    /// OTLP collector endpoint to export traces to
+    ///
+    /// If not provided, traces are not exported
+    #[arg(
+        long = "tracing.otlp_endpoint",
+        name = "tracing.otlp_endpoint",
+        env = "TRACING_OTLP_ENDPOINT",
+        global = true
+    )]
+    otlp_endpoint: Option<String>,
+
+    /// Protocol used to export traces to the OTLP collector
+    #[arg(
+        long = "tracing.otlp_protocol",
+        name = "tracing.otlp_protocol",
+        env = "TRACING_OTLP_PROTOCOL",
+        default_value = "grpc",
+        value_parser = ["grpc", "http"],
+        global = true
+    )]
+    otlp_protocol: String,
+
+    /// Service name reported with exported traces
+    #[arg(
+        long = "tracing.service_name",
+        name = "tracing.service_name",
+        env = "TRACING_SERVICE_NAME",
+        default_value = "rundler",
+        global = true
+    )]
+    service_name: String,
+
+    /// Fraction of traces to sample, between 0 and 1. Traces continued from a
+    /// caller follow the caller's sampling decision.
+    #[arg(
+        long = "tracing.sample_ratio",
+        name = "tracing.sample_ratio",
+        env = "TRACING_SAMPLE_RATIO",
+        default_value = "1.0",
+        global = true
+    )]
+    sample_ratio: f64,
+}
+
 /// CLI options
 #[derive(Debug, Parser)]
 pub struct Cli {
@@ -421,4 +714,7 @@ pub struct Cli {
 
     #[clap(flatten)]
     logs: LogsArgs,
+
+    #[clap(flatten)]
+    tracing: TracingArgs,
 }
@@ -51,13 +51,25 @@ pub async fn run(bundler_args: NodeCliArgs, common_args: CommonArgs) -> anyhow::
     } = bundler_args;
 
     let pool_task_args = pool_args.to_args(&common_args, None).await?;
-    let builder_task_args = builder_args.to_args(&common_args, None).await?;
-    let rpc_task_args = rpc_args.to_args(
-        &common_args,
-        (&common_args).try_into()?,
-        (&common_args).into(),
-        (&common_args).try_into()?,
-    )?;
+    let mut builder_task_args = builder_args.to_args(&common_args, None).await?;
+    // the builder reuses the simulations of the pool, which keeps the cache up to date
+    builder_task_args.simulation_cache = pool_task_args.simulation_cache.clone();
+    let mut rpc_task_args = rpc_args
+        .to_args(
+            &common_args,
+            (&common_args).try_into()?,
+            (&common_args).into(),
+            (&common_args).try_into()?,
+        )
+        .await?;
+    // the rpc server tracks op statuses from the events of the pool and builder
+    let op_status_tracker = rpc_args.op_status_tracker();
+    rpc_task_args.op_status_tracker = op_status_tracker.clone();
+    // the builder and rpc server follow the mempool configs reloaded by the pool
+    if let Some(pool_config) = pool_task_args.pool_configs.first() {
+        builder_task_args.mempool_configs = pool_config.mempool_channel_configs.clone();
+        rpc_task_args.mempool_configs = pool_config.mempool_channel_configs.clone();
+    }
 
     let (event_sender, event_rx) =
         broadcast::channel::<WithEntryPoint<Event>>(EVENT_CHANNEL_CAPACITY);
@@ -69,13 +81,20 @@ pub async fn run(bundler_args: NodeCliArgs, common_args: CommonArgs) -> anyhow::
     emit::receive_and_log_events_with_filter(event_rx, |_| true);
     emit::receive_events("op pool", op_pool_event_rx, {
         let event_sender = event_sender.clone();
+        let op_status_tracker = op_status_tracker.clone();
         move |event| {
+            if let Some(tracker) = &op_status_tracker {
+                tracker.on_pool_event(&event);
+            }
             let _ = event_sender.send(WithEntryPoint::of(event));
         }
     });
     emit::receive_events("builder", builder_event_rx, {
         let event_sender = event_sender.clone();
         move |event| {
+            if let Some(tracker) = &op_status_tracker {
+                tracker.on_builder_event(&event);
+            }
             if builder::is_nonspammy_event(&event) {
                 let _ = event_sender.send(WithEntryPoint::of(event));
             }
@@ -84,13 +103,20 @@ pub async fn run(bundler_args: NodeCliArgs, common_args: CommonArgs) -> anyhow::
 
     let pool_builder = LocalPoolBuilder::new(REQUEST_CHANNEL_CAPACITY, BLOCK_CHANNEL_CAPACITY);
     let pool_handle = pool_builder.get_handle();
+    let pool_task = PoolTask::new(pool_task_args, op_pool_event_sender, pool_builder);
+    // the index follows the chain updates of the pool
+    let indexer_task = rpc_args.indexer_task(
+        &common_args,
+        &rpc_task_args,
+        Some(pool_task.subscribe_chain()),
+    )?;
 
     let builder_builder = LocalBuilderBuilder::new(REQUEST_CHANNEL_CAPACITY);
     let builder_handle = builder_builder.get_handle();
 
     spawn_tasks_with_shutdown(
         [
-            PoolTask::new(pool_task_args, op_pool_event_sender, pool_builder).boxed(),
+            pool_task.boxed(),
             BuilderTask::new(
                 builder_task_args,
                 builder_event_sender,
@@ -99,7 +125,9 @@ pub async fn run(bundler_args: NodeCliArgs, common_args: CommonArgs) -> anyhow::
             )
             .boxed(),
             RpcTask::new(rpc_task_args, pool_handle, builder_handle).boxed(),
-        ],
+        ]
+        .into_iter()
+        .chain(indexer_task),
         tokio::signal::ctrl_c(),
     )
     .await;
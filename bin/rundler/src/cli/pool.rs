@@ -11,13 +11,16 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
 
 use anyhow::Context;
-use clap::Args;
-use ethers::types::{Chain, H256};
-use rundler_pool::{LocalPoolBuilder, PoolConfig, PoolTask, PoolTaskArgs};
-use rundler_sim::MempoolConfig;
+use clap::{builder::PossibleValuesParser, Args};
+use ethers::types::Address;
+use rundler_pool::{
+    LocalPoolBuilder, OrderingKind, PaymasterAlertSettings, PoolConfig, PoolTask, PoolTaskArgs,
+    PriorityBoosts, ReloadSettings, ReputationParams,
+};
+use rundler_sim::{SharedMempoolConfigs, SimulationCache, TokenPaymasterProfile};
 use rundler_task::spawn_tasks_with_shutdown;
 use rundler_utils::emit::{self, EVENT_CHANNEL_CAPACITY};
 use tokio::sync::broadcast;
@@ -126,6 +129,37 @@ pub struct PoolArgs {
     )]
     pub paymaster_tracking_enabled: bool,
 
+    #[arg(
+        long = "pool.paymaster_spend_window_seconds",
+        name = "pool.paymaster_spend_window_seconds",
+        env = "POOL_PAYMASTER_SPEND_WINDOW_SECONDS",
+        default_value = "3600"
+    )]
+    pub paymaster_spend_window_seconds: u64,
+
+    #[arg(
+        long = "pool.paymaster_alert_webhook_url",
+        name = "pool.paymaster_alert_webhook_url",
+        env = "POOL_PAYMASTER_ALERT_WEBHOOK_URL"
+    )]
+    pub paymaster_alert_webhook_url: Option<String>,
+
+    #[arg(
+        long = "pool.paymaster_alert_min_runway_seconds",
+        name = "pool.paymaster_alert_min_runway_seconds",
+        env = "POOL_PAYMASTER_ALERT_MIN_RUNWAY_SECONDS",
+        default_value = "86400"
+    )]
+    pub paymaster_alert_min_runway_seconds: u64,
+
+    #[arg(
+        long = "pool.paymaster_alert_cooldown_seconds",
+        name = "pool.paymaster_alert_cooldown_seconds",
+        env = "POOL_PAYMASTER_ALERT_COOLDOWN_SECONDS",
+        default_value = "3600"
+    )]
+    pub paymaster_alert_cooldown_seconds: u64,
+
     #[arg(
         long = "pool.reputation_tracking_enabled",
         name = "pool.reputation_tracking_enabled",
@@ -133,6 +167,66 @@ pub struct PoolArgs {
         default_value = "true"
     )]
     pub reputation_tracking_enabled: bool,
+
+    #[arg(
+        long = "pool.ordering_policy",
+        name = "pool.ordering_policy",
+        env = "POOL_ORDERING_POLICY",
+        value_parser = PossibleValuesParser::new(["max_fee", "effective_tip", "fifo"]),
+        default_value = "max_fee"
+    )]
+    pub ordering_policy: String,
+
+    #[arg(
+        long = "pool.priority_boosts_path",
+        name = "pool.priority_boosts_path",
+        env = "POOL_PRIORITY_BOOSTS_PATH"
+    )]
+    pub priority_boosts_path: Option<String>,
+
+    #[arg(
+        long = "pool.token_paymasters_path",
+        name = "pool.token_paymasters_path",
+        env = "POOL_TOKEN_PAYMASTERS_PATH"
+    )]
+    pub token_paymasters_path: Option<String>,
+
+    #[arg(
+        long = "pool.reputation_config_path",
+        name = "pool.reputation_config_path",
+        env = "POOL_REPUTATION_CONFIG_PATH"
+    )]
+    pub reputation_config_path: Option<String>,
+
+    #[arg(
+        long = "pool.reputation_snapshot_dir",
+        name = "pool.reputation_snapshot_dir",
+        env = "POOL_REPUTATION_SNAPSHOT_DIR"
+    )]
+    pub reputation_snapshot_dir: Option<PathBuf>,
+
+    #[arg(
+        long = "pool.config_reload_interval_secs",
+        name = "pool.config_reload_interval_secs",
+        env = "POOL_CONFIG_RELOAD_INTERVAL_SECS"
+    )]
+    pub config_reload_interval_secs: Option<u64>,
+
+    #[arg(
+        long = "pool.mempool_refresh_interval_secs",
+        name = "pool.mempool_refresh_interval_secs",
+        env = "POOL_MEMPOOL_REFRESH_INTERVAL_SECS",
+        default_value = "60"
+    )]
+    pub mempool_refresh_interval_secs: u64,
+
+    #[arg(
+        long = "pool.simulation_cache_size",
+        name = "pool.simulation_cache_size",
+        env = "POOL_SIMULATION_CACHE_SIZE",
+        default_value = "0"
+    )]
+    pub simulation_cache_size: usize,
 }
 
 impl PoolArgs {
@@ -154,13 +248,53 @@ impl PoolArgs {
         tracing::info!("blocklist: {:?}", blocklist);
         tracing::info!("allowlist: {:?}", allowlist);
 
-        let mempool_channel_configs = match &common.mempool_config_path {
+        let priority_boosts = match &self.priority_boosts_path {
+            Some(path) => Some(get_json_config::<PriorityBoosts>(path, &common.aws_region).await?),
+            None => None,
+        };
+        tracing::info!("priority boosts: {:?}", priority_boosts);
+        let ordering_policy =
+            OrderingKind::try_from(&self.ordering_policy)?.into_policy(priority_boosts);
+
+        let reputation_params = match &self.reputation_config_path {
+            Some(path) => {
+                let params = get_json_config::<ReputationParams>(path, &common.aws_region).await?;
+                params.validate().context("invalid reputation config")?;
+                params
+            }
+            None => ReputationParams::default(),
+        };
+        tracing::info!("reputation params: {:?}", reputation_params);
+
+        let mempool_channel_configs: SharedMempoolConfigs = common.mempool_configs().await?.into();
+        tracing::info!(
+            "Mempool channel configs: {:?}",
+            mempool_channel_configs.configs()
+        );
+
+        let chain_spec = common.chain_spec()?;
+        tracing::info!("Chain spec: {:?}", chain_spec);
+
+        let token_paymasters = match &self.token_paymasters_path {
             Some(path) => {
-                get_json_config::<HashMap<H256, MempoolConfig>>(path, &common.aws_region).await?
+                get_json_config::<HashMap<Address, TokenPaymasterProfile>>(path, &common.aws_region)
+                    .await?
             }
-            None => HashMap::from([(H256::zero(), MempoolConfig::default())]),
+            None => HashMap::new(),
         };
-        tracing::info!("Mempool channel configs: {:?}", mempool_channel_configs);
+        tracing::info!("token paymasters: {:?}", token_paymasters);
+        if !token_paymasters.is_empty() && !self.paymaster_tracking_enabled {
+            tracing::warn!("Paymaster tracking is disabled, so the token funds of senders are only checked per operation, not against their pending operations");
+        }
+
+        let paymaster_alerts =
+            self.paymaster_alert_webhook_url
+                .clone()
+                .map(|webhook_url| PaymasterAlertSettings {
+                    webhook_url,
+                    min_runway: Duration::from_secs(self.paymaster_alert_min_runway_seconds),
+                    cooldown: Duration::from_secs(self.paymaster_alert_cooldown_seconds),
+                });
 
         let pool_configs = common
             .entry_points
@@ -169,7 +303,7 @@ impl PoolArgs {
                 let entry_point = ep.parse().context("Invalid entry_points argument")?;
                 Ok(PoolConfig {
                     entry_point,
-                    chain_id: common.chain_id,
+                    chain_id: chain_spec.id,
                     // Currently use the same shard count as the number of builders
                     num_shards: common.num_builders,
                     same_sender_mempool_count: self.same_sender_mempool_count,
@@ -184,44 +318,62 @@ impl PoolArgs {
                     throttled_entity_mempool_count: self.throttled_entity_mempool_count,
                     throttled_entity_live_blocks: self.throttled_entity_live_blocks,
                     paymaster_tracking_enabled: self.paymaster_tracking_enabled,
+                    paymaster_spend_window: Duration::from_secs(
+                        self.paymaster_spend_window_seconds,
+                    ),
+                    paymaster_alerts: paymaster_alerts.clone(),
+                    token_paymasters: token_paymasters.clone(),
                     reputation_tracking_enabled: self.reputation_tracking_enabled,
+                    ordering_policy: ordering_policy.clone(),
+                    reputation_params: reputation_params.clone(),
+                    reputation_snapshot_dir: self.reputation_snapshot_dir.clone(),
                 })
             })
             .collect::<anyhow::Result<Vec<PoolConfig>>>()?;
 
+        let reload_interval = self.config_reload_interval_secs.map(Duration::from_secs);
+        let reload_settings = common
+            .mempool_reload_settings(
+                reload_interval,
+                Duration::from_secs(self.mempool_refresh_interval_secs),
+            )?
+            .map(|settings| ReloadSettings {
+                blocklist_path: reload_interval.and(reloadable_path(&self.blocklist_path)),
+                allowlist_path: reload_interval.and(reloadable_path(&self.allowlist_path)),
+                ..settings
+            });
+
         Ok(PoolTaskArgs {
-            chain_id: common.chain_id,
+            chain_id: chain_spec.id,
             chain_history_size: self
                 .chain_history_size
-                .unwrap_or_else(|| default_chain_history_size(common.chain_id)),
+                .unwrap_or(chain_spec.chain_history_size),
             http_url: common
                 .node_http
                 .clone()
                 .context("pool requires node_http arg")?,
-            http_poll_interval: Duration::from_millis(common.eth_poll_interval_millis),
+            http_poll_interval: common.eth_poll_interval()?,
             pool_configs,
             remote_address,
             chain_update_channel_capacity: self.chain_update_channel_capacity.unwrap_or(1024),
+            reload_settings,
+            simulation_cache: (self.simulation_cache_size > 0)
+                .then(|| Arc::new(SimulationCache::new(self.simulation_cache_size))),
+            abi_registry: common.abi_registry()?,
         })
     }
 }
 
-const SMALL_HISTORY_SIZE: u64 = 16;
-const LARGE_HISTORY_SIZE: u64 = 128;
-
-// Mainnets that are known to not have large reorgs can use the small history
-// size. Use the large history size for all testnets because I don't trust them.
-const SMALL_HISTORY_CHAIN_IDS: &[u64] = &[
-    Chain::Mainnet as u64,
-    Chain::Arbitrum as u64,
-    Chain::Optimism as u64,
-];
-
-fn default_chain_history_size(chain_id: u64) -> u64 {
-    if SMALL_HISTORY_CHAIN_IDS.contains(&chain_id) {
-        SMALL_HISTORY_SIZE
-    } else {
-        LARGE_HISTORY_SIZE
+// Config files can only be reloaded from local paths or HTTP URLs
+pub(crate) fn reloadable_path(path: &Option<String>) -> Option<String> {
+    match path {
+        Some(path) if path.starts_with("s3://") => {
+            tracing::warn!(
+                "Config reloading is not supported for S3 paths, {path} will not be reloaded"
+            );
+            None
+        }
+        path => path.clone(),
     }
 }
 
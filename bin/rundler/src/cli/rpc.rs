@@ -11,17 +11,24 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::time::Duration;
+use std::{collections::HashMap, path::PathBuf, str::FromStr, sync::Arc, time::Duration};
 
 use anyhow::Context;
 use clap::Args;
+use ethers::types::U256;
 use rundler_builder::RemoteBuilderClient;
-use rundler_pool::RemotePoolClient;
-use rundler_rpc::{EthApiSettings, RpcTask, RpcTaskArgs};
+use rundler_pool::{
+    ChainSubscription, IndexerTask, IndexerTaskArgs, RemotePoolClient, UserOperationIndex,
+};
+use rundler_rpc::{
+    EthApiSettings, HealthSettings, OpStatusSettings, OpStatusTracker, RpcLimitSettings, RpcTask,
+    RpcTaskArgs,
+};
 use rundler_sim::{EstimationSettings, PrecheckSettings};
-use rundler_task::{server::connect_with_retries_shutdown, spawn_tasks_with_shutdown};
+use rundler_task::{server::connect_with_retries_shutdown, spawn_tasks_with_shutdown, Task};
 
 use super::CommonArgs;
+use crate::cli::json::get_json_config;
 
 /// CLI options for the RPC server
 #[derive(Args, Debug)]
@@ -56,7 +63,7 @@ pub struct RpcArgs {
     )]
     api: Vec<String>,
 
-    /// Timeout for RPC requests
+    /// Default timeout for RPC requests
     #[arg(
         long = "rpc.timeout_seconds",
         name = "rpc.timeout_seconds",
@@ -73,13 +80,167 @@ pub struct RpcArgs {
         default_value = "100"
     )]
     max_connections: u32,
+
+    /// Maximum size of a request body, in bytes
+    #[arg(
+        long = "rpc.max_request_body_size",
+        name = "rpc.max_request_body_size",
+        env = "RPC_MAX_REQUEST_BODY_SIZE",
+        default_value = "10485760"
+    )]
+    max_request_body_size: u32,
+
+    /// Maximum number of calls in a batch request. Set to 0 to disable batch requests.
+    #[arg(
+        long = "rpc.max_batch_len",
+        name = "rpc.max_batch_len",
+        env = "RPC_MAX_BATCH_LEN",
+        default_value = "100"
+    )]
+    max_batch_len: usize,
+
+    /// Per-method timeouts, as `method=seconds` pairs, overriding `rpc.timeout_seconds`
+    #[arg(
+        long = "rpc.method_timeouts",
+        name = "rpc.method_timeouts",
+        env = "RPC_METHOD_TIMEOUTS",
+        value_delimiter = ','
+    )]
+    method_timeouts: Vec<String>,
+
+    /// Per-method caps on concurrent calls, as `method=count` pairs
+    #[arg(
+        long = "rpc.method_max_concurrent",
+        name = "rpc.method_max_concurrent",
+        env = "RPC_METHOD_MAX_CONCURRENT",
+        value_delimiter = ','
+    )]
+    method_max_concurrent: Vec<String>,
+
+    /// Maximum number of concurrent calls outside the priority lane, counting
+    /// each call of a batch. Set to 0 for no limit. Keep this below `rpc.max_connections` to leave room for
+    /// priority requests.
+    #[arg(
+        long = "rpc.max_concurrent_requests",
+        name = "rpc.max_concurrent_requests",
+        env = "RPC_MAX_CONCURRENT_REQUESTS",
+        default_value = "80"
+    )]
+    max_concurrent_requests: usize,
+
+    /// Methods served in the priority lane, which is not subject to
+    /// `rpc.max_concurrent_requests`
+    #[arg(
+        long = "rpc.priority_methods",
+        name = "rpc.priority_methods",
+        env = "RPC_PRIORITY_METHODS",
+        default_value = "eth_sendUserOperation",
+        value_delimiter = ','
+    )]
+    priority_methods: Vec<String>,
+
+    /// Path of the user operation index database. If set, mined user operations
+    /// are indexed and looked up by hash from the index.
+    #[arg(
+        long = "rpc.user_operation_index_path",
+        name = "rpc.user_operation_index_path",
+        env = "RPC_USER_OPERATION_INDEX_PATH"
+    )]
+    user_operation_index_path: Option<PathBuf>,
+
+    /// Maximum number of blocks to load user operation events for in one
+    /// request when the index catches up
+    #[arg(
+        long = "rpc.user_operation_index_blocks_per_request",
+        name = "rpc.user_operation_index_blocks_per_request",
+        env = "RPC_USER_OPERATION_INDEX_BLOCKS_PER_REQUEST",
+        default_value = "1000"
+    )]
+    user_operation_index_blocks_per_request: u64,
+
+    /// Maximum age of the node's latest block for the RPC server to be ready
+    #[arg(
+        long = "rpc.health_max_head_age_seconds",
+        name = "rpc.health_max_head_age_seconds",
+        env = "RPC_HEALTH_MAX_HEAD_AGE_SECONDS",
+        default_value = "60"
+    )]
+    health_max_head_age_seconds: u64,
+
+    /// Maximum time since the pool last reported a new block for the RPC
+    /// server to be ready
+    #[arg(
+        long = "rpc.health_max_pool_head_staleness_seconds",
+        name = "rpc.health_max_pool_head_staleness_seconds",
+        env = "RPC_HEALTH_MAX_POOL_HEAD_STALENESS_SECONDS",
+        default_value = "60"
+    )]
+    health_max_pool_head_staleness_seconds: u64,
+
+    /// Minimum balance, in wei, of each builder signer for the RPC server to be ready
+    #[arg(
+        long = "rpc.health_min_signer_balance",
+        name = "rpc.health_min_signer_balance",
+        env = "RPC_HEALTH_MIN_SIGNER_BALANCE",
+        default_value = "0"
+    )]
+    health_min_signer_balance: String,
+
+    /// Window in which a builder sending or mining a bundle is reported as recent
+    #[arg(
+        long = "rpc.health_recent_bundle_window_seconds",
+        name = "rpc.health_recent_bundle_window_seconds",
+        env = "RPC_HEALTH_RECENT_BUNDLE_WINDOW_SECONDS",
+        default_value = "600"
+    )]
+    health_recent_bundle_window_seconds: u64,
+
+    /// Number of builder signers that may be below the minimum balance for the
+    /// RPC server to still be ready. By default, one underfunded signer makes
+    /// the RPC server unready.
+    #[arg(
+        long = "rpc.health_max_underfunded_signers",
+        name = "rpc.health_max_underfunded_signers",
+        env = "RPC_HEALTH_MAX_UNDERFUNDED_SIGNERS",
+        default_value = "0"
+    )]
+    health_max_underfunded_signers: usize,
+
+    /// Maximum number of user operations to track the lifecycle of for
+    /// rundler_getUserOperationStatus. Set to 0 to disable tracking. Only
+    /// used when running as a node, where pool and builder events are available.
+    #[arg(
+        long = "rpc.op_status_max_ops",
+        name = "rpc.op_status_max_ops",
+        env = "RPC_OP_STATUS_MAX_OPS",
+        default_value = "100000"
+    )]
+    op_status_max_ops: usize,
+
+    /// How long to keep the status of a user operation after its last update
+    #[arg(
+        long = "rpc.op_status_retention_seconds",
+        name = "rpc.op_status_retention_seconds",
+        env = "RPC_OP_STATUS_RETENTION_SECONDS",
+        default_value = "3600"
+    )]
+    op_status_retention_seconds: u64,
+
+    /// Path to a JSON file mapping partner API keys, sent in the `x-api-key`
+    /// header, to partner names used for priority boosts in the pool
+    #[arg(
+        long = "rpc.partner_api_keys_path",
+        name = "rpc.partner_api_keys_path",
+        env = "RPC_PARTNER_API_KEYS_PATH"
+    )]
+    partner_api_keys_path: Option<String>,
 }
 
 impl RpcArgs {
     /// Convert the CLI arguments into the arguments for the RPC server combining
     /// common and rpc specific arguments.
     #[allow(clippy::too_many_arguments)]
-    pub fn to_args(
+    pub async fn to_args(
         &self,
         common: &CommonArgs,
         precheck_settings: PrecheckSettings,
@@ -91,6 +252,11 @@ impl RpcArgs {
             .iter()
             .map(|api| api.parse())
             .collect::<Result<Vec<_>, _>>()?;
+        let mempool_configs = common.mempool_configs().await?;
+        let partner_api_keys = match &self.partner_api_keys_path {
+            Some(path) => get_json_config(path, &common.aws_region).await?,
+            None => HashMap::new(),
+        };
 
         Ok(RpcTaskArgs {
             port: self.port,
@@ -105,15 +271,105 @@ impl RpcArgs {
                 .node_http
                 .clone()
                 .context("rpc requires node_http arg")?,
-            chain_id: common.chain_id,
+            chain_id: precheck_settings.chain_spec.id,
             api_namespaces: apis,
             precheck_settings,
             eth_api_settings,
             estimation_settings,
-            rpc_timeout: Duration::from_secs(self.timeout_seconds.parse()?),
+            sim_settings: common.into(),
+            mempool_configs: mempool_configs.into(),
+            abi_registry: common.abi_registry()?,
+            user_operation_index: self
+                .user_operation_index_path
+                .as_deref()
+                .map(UserOperationIndex::open)
+                .transpose()?
+                .map(Arc::new),
+            op_status_tracker: None,
+            health_settings: HealthSettings {
+                max_head_age: Duration::from_secs(self.health_max_head_age_seconds),
+                max_pool_head_staleness: Duration::from_secs(
+                    self.health_max_pool_head_staleness_seconds,
+                ),
+                min_signer_balance: U256::from_dec_str(&self.health_min_signer_balance)
+                    .context("Invalid rpc.health_min_signer_balance argument")?,
+                recent_bundle_window: Duration::from_secs(self.health_recent_bundle_window_seconds),
+                max_underfunded_signers: self.health_max_underfunded_signers,
+            },
+            limit_settings: RpcLimitSettings {
+                max_request_body_size: self.max_request_body_size,
+                max_batch_len: self.max_batch_len,
+                default_timeout: Duration::from_secs(self.timeout_seconds.parse()?),
+                method_timeouts: parse_method_values(&self.method_timeouts)?
+                    .into_iter()
+                    .map(|(method, secs)| (method, Duration::from_secs(secs)))
+                    .collect(),
+                method_max_concurrent: parse_method_values(&self.method_max_concurrent)?,
+                max_concurrent_requests: self.max_concurrent_requests,
+                priority_methods: self.priority_methods.iter().cloned().collect(),
+            },
+            partner_api_keys,
             max_connections: self.max_connections,
         })
     }
+
+    /// The task keeping the user operation index up to date, if the index is
+    /// enabled. It follows the chain updates of the pool if the pool runs in the
+    /// same process, and otherwise watches the chain itself.
+    pub fn indexer_task(
+        &self,
+        common: &CommonArgs,
+        rpc_task_args: &RpcTaskArgs,
+        chain_subscription: Option<ChainSubscription>,
+    ) -> anyhow::Result<Option<Box<dyn Task>>> {
+        let Some(index) = &rpc_task_args.user_operation_index else {
+            return Ok(None);
+        };
+        let args = IndexerTaskArgs {
+            http_url: rpc_task_args.rpc_url.clone(),
+            http_poll_interval: common.eth_poll_interval()?,
+            chain_history_size: common.chain_spec()?.chain_history_size,
+            entry_points: rpc_task_args.entry_points.clone(),
+            blocks_per_request: self.user_operation_index_blocks_per_request,
+        };
+        let mut task = IndexerTask::new(args, Arc::clone(index));
+        if let Some(chain_subscription) = chain_subscription {
+            task = task.with_chain_subscription(chain_subscription);
+        }
+        Ok(Some(task.boxed()))
+    }
+
+    /// The tracker of user operation lifecycles, if tracking is enabled. It must
+    /// be fed pool and builder events.
+    pub fn op_status_tracker(&self) -> Option<Arc<OpStatusTracker>> {
+        (self.op_status_max_ops > 0).then(|| {
+            Arc::new(OpStatusTracker::new(OpStatusSettings {
+                max_ops: self.op_status_max_ops,
+                retention: Duration::from_secs(self.op_status_retention_seconds),
+            }))
+        })
+    }
+}
+
+/// Parses `method=value` pairs into a map from method name to value
+fn parse_method_values<T>(entries: &[String]) -> anyhow::Result<HashMap<String, T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    entries
+        .iter()
+        .map(|entry| {
+            let (method, value) = entry
+                .split_once('=')
+                .with_context(|| format!("expected method=value, got {entry}"))?;
+            let value = value
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid value for method {method}"))?;
+            Ok((method.trim().to_string(), value))
+        })
+        .collect()
 }
 
 /// CLI options for the RPC server standalone
@@ -139,6 +395,14 @@ pub struct RpcCliArgs {
         global = true
     )]
     builder_url: String,
+
+    #[arg(
+        long = "rpc.mempool_config_reload_interval_secs",
+        name = "rpc.mempool_config_reload_interval_secs",
+        env = "RPC_MEMPOOL_CONFIG_RELOAD_INTERVAL_SECS",
+        global = true
+    )]
+    mempool_config_reload_interval_secs: Option<u64>,
 }
 
 pub async fn run(rpc_args: RpcCliArgs, common_args: CommonArgs) -> anyhow::Result<()> {
@@ -146,14 +410,17 @@ pub async fn run(rpc_args: RpcCliArgs, common_args: CommonArgs) -> anyhow::Resul
         rpc: rpc_args,
         pool_url,
         builder_url,
+        mempool_config_reload_interval_secs,
     } = rpc_args;
 
-    let task_args = rpc_args.to_args(
-        &common_args,
-        (&common_args).try_into()?,
-        (&common_args).into(),
-        (&common_args).try_into()?,
-    )?;
+    let task_args = rpc_args
+        .to_args(
+            &common_args,
+            (&common_args).try_into()?,
+            (&common_args).into(),
+            (&common_args).try_into()?,
+        )
+        .await?;
 
     let pool = connect_with_retries_shutdown(
         "op pool from rpc",
@@ -171,8 +438,16 @@ pub async fn run(rpc_args: RpcCliArgs, common_args: CommonArgs) -> anyhow::Resul
     )
     .await?;
 
+    let indexer_task = rpc_args.indexer_task(&common_args, &task_args, None)?;
+    let reloader_task = common_args.mempool_config_reloader(
+        mempool_config_reload_interval_secs,
+        task_args.mempool_configs.clone(),
+    )?;
     spawn_tasks_with_shutdown(
-        [RpcTask::new(task_args, pool, builder).boxed()],
+        [RpcTask::new(task_args, pool, builder).boxed()]
+            .into_iter()
+            .chain(indexer_task)
+            .chain(reloader_task),
         tokio::signal::ctrl_c(),
     )
     .await;
@@ -13,15 +13,48 @@
 
 use std::io;
 
+use anyhow::Context;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+use opentelemetry_sdk::{
+    propagation::TraceContextPropagator,
+    runtime,
+    trace::{self as sdktrace, Sampler},
+    Resource,
+};
 pub use tracing::*;
 use tracing::{subscriber, subscriber::Interest, Metadata, Subscriber};
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_log::LogTracer;
 use tracing_subscriber::{layer::SubscriberExt, EnvFilter, FmtSubscriber, Layer};
 
-use super::LogsArgs;
+use super::{LogsArgs, TracingArgs};
+
+/// Flushes buffered logs and exported traces when dropped.
+pub struct LoggingGuard {
+    _worker_guard: WorkerGuard,
+    exporting_traces: bool,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        if self.exporting_traces {
+            global::shutdown_tracer_provider();
+        }
+    }
+}
+
+pub fn configure_logging(
+    config: &LogsArgs,
+    tracing_config: &TracingArgs,
+) -> anyhow::Result<LoggingGuard> {
+    let tracer = tracing_config
+        .otlp_endpoint
+        .as_ref()
+        .map(|endpoint| otlp_tracer(endpoint, tracing_config))
+        .transpose()?;
+    let exporting_traces = tracer.is_some();
 
-pub fn configure_logging(config: &LogsArgs) -> anyhow::Result<WorkerGuard> {
     let (appender, guard) = if let Some(log_file) = &config.file {
         tracing_appender::non_blocking(tracing_appender::rolling::never(".", log_file))
     } else {
@@ -36,21 +69,63 @@ pub fn configure_logging(config: &LogsArgs) -> anyhow::Result<WorkerGuard> {
             subscriber_builder
                 .json()
                 .finish()
-                .with(TargetBlacklistLayer),
+                .with(TargetBlacklistLayer)
+                .with(
+                    tracer
+                        .clone()
+                        .map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)),
+                ),
         )?;
     } else {
         subscriber::set_global_default(
             subscriber_builder
                 .pretty()
                 .finish()
-                .with(TargetBlacklistLayer),
+                .with(TargetBlacklistLayer)
+                .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer))),
         )?;
     }
 
     // Redirect logs from external crates using `log` to the tracing subscriber
     LogTracer::init()?;
 
-    Ok(guard)
+    Ok(LoggingGuard {
+        _worker_guard: guard,
+        exporting_traces,
+    })
+}
+
+/// Builds a tracer that batches spans and exports them to an OTLP collector,
+/// and sets up W3C trace context propagation for outgoing and incoming requests.
+fn otlp_tracer(endpoint: &str, config: &TracingArgs) -> anyhow::Result<sdktrace::Tracer> {
+    let exporter: SpanExporterBuilder = match config.otlp_protocol.as_str() {
+        "http" => opentelemetry_otlp::new_exporter()
+            .http()
+            .with_endpoint(endpoint)
+            .into(),
+        _ => opentelemetry_otlp::new_exporter()
+            .tonic()
+            .with_endpoint(endpoint)
+            .into(),
+    };
+    let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(config.sample_ratio)));
+
+    let tracer =
+        opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(exporter)
+            .with_trace_config(sdktrace::config().with_sampler(sampler).with_resource(
+                Resource::new(vec![KeyValue::new(
+                    "service.name",
+                    config.service_name.clone(),
+                )]),
+            ))
+            .install_batch(runtime::Tokio)
+            .context("should build OTLP trace exporter")?;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(tracer)
 }
 
 const BLACKLISTED_TARGETS: &[&str] = &["h2", "hyper", "tower::buffer"];
@@ -32,17 +32,18 @@ use mockall::automock;
 use rundler_pool::{PoolOperation, PoolServer};
 use rundler_provider::{EntryPoint, HandleOpsOut, Provider};
 use rundler_sim::{
-    gas::{self, GasOverheads},
+    gas::{self, GasOverheads, L1GasCalculator},
     EntityInfo, EntityInfos, ExpectedStorage, FeeEstimator, PriorityFeeMode, SimulationError,
     SimulationResult, SimulationViolation, Simulator, ViolationError,
 };
 use rundler_types::{
+    chain::{ChainSpec, L1GasConfig},
     Entity, EntityType, EntityUpdate, EntityUpdateType, GasFees, Timestamp, UserOperation,
     UserOpsPerAggregator,
 };
-use rundler_utils::{emit::WithEntryPoint, math};
+use rundler_utils::{emit::WithEntryPoint, math, revert::AbiRegistry};
 use tokio::{sync::broadcast, try_join};
-use tracing::{error, info, warn};
+use tracing::{error, info, instrument, warn};
 
 use crate::emit::{BuilderEvent, OpRejectionReason, SkipReason};
 
@@ -99,14 +100,15 @@ where
     provider: Arc<P>,
     settings: Settings,
     fee_estimator: FeeEstimator<P>,
+    l1_gas_calculator: Arc<dyn L1GasCalculator>,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+    abi_registry: Arc<AbiRegistry>,
 }
 
 #[derive(Debug)]
 pub(crate) struct Settings {
-    pub(crate) chain_id: u64,
+    pub(crate) chain_spec: ChainSpec,
     pub(crate) max_bundle_size: u64,
-    pub(crate) max_bundle_gas: u64,
     pub(crate) beneficiary: Address,
     pub(crate) bundle_priority_fee_overhead_percent: u64,
     pub(crate) priority_fee_mode: PriorityFeeMode,
@@ -120,6 +122,7 @@ where
     P: Provider,
     C: PoolServer,
 {
+    #[instrument(skip_all, fields(builder_index = self.builder_index))]
     async fn make_bundle(&self, required_fees: Option<GasFees>) -> anyhow::Result<Bundle> {
         let (ops, (block_hash, _), (bundle_fees, base_fee)) = try_join!(
             self.get_ops_from_pool(),
@@ -229,22 +232,32 @@ where
             entry_point,
             provider: provider.clone(),
             fee_estimator: FeeEstimator::new(
-                provider,
-                settings.chain_id,
+                Arc::clone(&provider),
+                &settings.chain_spec,
                 settings.priority_fee_mode,
                 settings.bundle_priority_fee_overhead_percent,
             ),
+            l1_gas_calculator: gas::new_l1_gas_calculator(settings.chain_spec.l1_gas, provider),
             settings,
             event_sender,
+            abi_registry: Arc::new(AbiRegistry::default()),
         }
     }
 
+    /// Decode revert data in the reasons of rejected ops with the errors of
+    /// the registry
+    pub(crate) fn with_abi_registry(mut self, abi_registry: Arc<AbiRegistry>) -> Self {
+        self.abi_registry = abi_registry;
+        self
+    }
+
     // Filter and simulate a single op. Returns None if the op should be skipped.
     //
     // Filters on:
     // - gas fees
     // - pre-verification gas
     // - any errors
+    #[instrument(skip_all, fields(op_hash = ?self.op_hash(&op.uo)))]
     async fn filter_and_simulate(
         &self,
         op: PoolOperation,
@@ -274,8 +287,7 @@ where
         let required_pvg = gas::calc_required_pre_verification_gas(
             &op.uo,
             self.entry_point.address(),
-            self.provider.clone(),
-            self.settings.chain_id,
+            self.l1_gas_calculator.as_ref(),
             base_fee,
         )
         .await
@@ -364,7 +376,7 @@ where
                         self.builder_index,
                         self.op_hash(&op),
                         OpRejectionReason::FailedRevalidation {
-                            error: error.clone(),
+                            error: self.decode_simulation_error(error.clone()),
                         },
                     ));
                     if let SimulationError {
@@ -400,12 +412,12 @@ where
             // Skip this op if the bundle does not have enough remaining gas to execute it.
             let required_gas = get_gas_required_for_op(
                 gas_spent,
-                self.settings.chain_id,
+                &self.settings.chain_spec.l1_gas,
                 ov,
                 &op,
                 simulation.requires_post_op,
             );
-            if required_gas > self.settings.max_bundle_gas.into() {
+            if required_gas > self.settings.chain_spec.max_bundle_gas.into() {
                 continue;
             }
 
@@ -442,7 +454,7 @@ where
             // Update the running gas that would need to be be spent to execute the bundle so far.
             gas_spent += gas::user_operation_execution_gas_limit(
                 &op,
-                self.settings.chain_id,
+                &self.settings.chain_spec.l1_gas,
                 false,
                 simulation.requires_post_op,
             );
@@ -513,7 +525,7 @@ where
         // sum up the gas needed for all the ops in the bundle
         // and apply an overhead multiplier
         let gas = math::increase_by_percent(
-            context.get_bundle_gas_limit(self.settings.chain_id),
+            context.get_bundle_gas_limit(&self.settings.chain_spec.l1_gas),
             BUNDLE_TRANSACTION_GAS_OVERHEAD_PERCENT,
         );
 
@@ -534,7 +546,7 @@ where
                     self.builder_index,
                     self.op_hash(&context.get_op_at(index)?.op),
                     OpRejectionReason::FailedInBundle {
-                        message: Arc::new(message.clone()),
+                        message: Arc::new(self.abi_registry.decode_message(&message)),
                     },
                 ));
                 self.process_failed_op(context, index, message).await?;
@@ -792,14 +804,15 @@ where
         ops: Vec<PoolOperation>,
     ) -> (Vec<PoolOperation>, u64) {
         // Make the bundle gas limit 10% higher here so that we simulate more UOs than we need in case that we end up dropping some UOs later so we can still pack a full bundle
-        let mut gas_left = math::increase_by_percent(U256::from(self.settings.max_bundle_gas), 10);
+        let mut gas_left =
+            math::increase_by_percent(U256::from(self.settings.chain_spec.max_bundle_gas), 10);
         let mut ops_in_bundle = Vec::new();
         for op in ops {
             // Here we use optimistic gas limits for the UOs by assuming none of the paymaster UOs use postOp calls.
             // This way after simulation once we have determined if each UO actually uses a postOp call or not we can still pack a full bundle
             let gas = gas::user_operation_execution_gas_limit(
                 &op.uo,
-                self.settings.chain_id,
+                &self.settings.chain_spec.l1_gas,
                 false,
                 false,
             );
@@ -817,6 +830,7 @@ where
         (
             ops_in_bundle,
             self.settings
+                .chain_spec
                 .max_bundle_gas
                 .saturating_sub(gas_left.as_u64()),
         )
@@ -829,8 +843,38 @@ where
         });
     }
 
+    // Decodes the revert data left in the messages of a simulation error
+    fn decode_simulation_error(&self, error: SimulationError) -> SimulationError {
+        let violation_error = match error.violation_error {
+            ViolationError::Violations(violations) => ViolationError::Violations(
+                violations
+                    .into_iter()
+                    .map(|violation| match violation {
+                        SimulationViolation::UnintendedRevertWithMessage(
+                            entity_type,
+                            message,
+                            address,
+                        ) => SimulationViolation::UnintendedRevertWithMessage(
+                            entity_type,
+                            self.abi_registry.decode_message(&message),
+                            address,
+                        ),
+                        violation => violation,
+                    })
+                    .collect(),
+            ),
+            ViolationError::Other(error) => ViolationError::Other(anyhow::anyhow!(self
+                .abi_registry
+                .decode_message(&format!("{error:#}")))),
+        };
+        SimulationError {
+            violation_error,
+            ..error
+        }
+    }
+
     fn op_hash(&self, op: &UserOperation) -> H256 {
-        op.op_hash(self.entry_point.address(), self.settings.chain_id)
+        op.op_hash(self.entry_point.address(), self.settings.chain_spec.id)
     }
 }
 
@@ -1021,7 +1065,7 @@ impl ProposalContext {
             .collect()
     }
 
-    fn get_bundle_gas_limit(&self, chain_id: u64) -> U256 {
+    fn get_bundle_gas_limit(&self, l1_gas: &L1GasConfig) -> U256 {
         let ov = GasOverheads::default();
         let mut gas_spent = ov.transaction_gas_overhead;
         let mut max_gas = U256::zero();
@@ -1029,7 +1073,7 @@ impl ProposalContext {
             let op = &op_with_sim.op;
             let required_gas = get_gas_required_for_op(
                 gas_spent,
-                chain_id,
+                l1_gas,
                 ov,
                 op,
                 op_with_sim.simulation.requires_post_op,
@@ -1037,7 +1081,7 @@ impl ProposalContext {
             max_gas = cmp::max(max_gas, required_gas);
             gas_spent += gas::user_operation_gas_limit(
                 op,
-                chain_id,
+                l1_gas,
                 false,
                 op_with_sim.simulation.requires_post_op,
             );
@@ -1216,7 +1260,7 @@ impl ProposalContext {
 
 fn get_gas_required_for_op(
     gas_spent: U256,
-    chain_id: u64,
+    l1_gas: &L1GasConfig,
     ov: GasOverheads,
     op: &UserOperation,
     requires_post_op: bool,
@@ -1228,7 +1272,7 @@ fn get_gas_required_for_op(
     };
 
     gas_spent
-        + gas::user_operation_pre_verification_gas_limit(op, chain_id, false)
+        + gas::user_operation_pre_verification_gas_limit(op, l1_gas, false)
         + op.verification_gas_limit * 2
         + op.call_gas_limit
         + post_exec_req_gas
@@ -1756,7 +1800,7 @@ mod tests {
     async fn test_bundle_gas_limit() {
         let op1 = op_with_gas(100_000.into(), 100_000.into(), 1_000_000.into(), false);
         let op2 = op_with_gas(100_000.into(), 100_000.into(), 200_000.into(), false);
-        let chain_id = 1;
+        let l1_gas = L1GasConfig::default();
         let mut groups_by_aggregator = LinkedHashMap::new();
         groups_by_aggregator.insert(
             None,
@@ -1795,14 +1839,14 @@ mod tests {
             + 5_000
             + 21_000;
 
-        assert_eq!(context.get_bundle_gas_limit(chain_id), expected_gas_limit);
+        assert_eq!(context.get_bundle_gas_limit(&l1_gas), expected_gas_limit);
     }
 
     #[tokio::test]
     async fn test_bundle_gas_limit_with_paymaster_op() {
         let op1 = op_with_gas(100_000.into(), 100_000.into(), 1_000_000.into(), true); // has paymaster
         let op2 = op_with_gas(100_000.into(), 100_000.into(), 200_000.into(), false);
-        let chain_id = 1;
+        let l1_gas = L1GasConfig::default();
         let mut groups_by_aggregator = LinkedHashMap::new();
         groups_by_aggregator.insert(
             None,
@@ -1831,7 +1875,7 @@ mod tests {
             rejected_ops: vec![],
             entity_updates: BTreeMap::new(),
         };
-        let gas_limit = context.get_bundle_gas_limit(chain_id);
+        let gas_limit = context.get_bundle_gas_limit(&l1_gas);
 
         // The gas requirement from the execution of the first UO is: g >= p_1 + 3v_1 + c_1
         // The gas requirement from the execution of the second UO is: g >= p_1 + 3v_1 + c_1 + p_2 + 2v_2 + c_2 + 5000
@@ -2078,9 +2122,12 @@ mod tests {
             entry_point,
             Arc::new(provider),
             Settings {
-                chain_id: 0,
+                chain_spec: ChainSpec {
+                    id: 0,
+                    max_bundle_gas: 10_000_000,
+                    ..Default::default()
+                },
                 max_bundle_size,
-                max_bundle_gas: 10_000_000,
                 beneficiary,
                 priority_fee_mode: PriorityFeeMode::PriorityFeeIncreasePercent(10),
                 bundle_priority_fee_overhead_percent: 0,
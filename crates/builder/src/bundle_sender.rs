@@ -11,29 +11,36 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    collections::{BTreeMap, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use anyhow::{bail, Context};
 use async_trait::async_trait;
-use ethers::types::{transaction::eip2718::TypedTransaction, Address, H256, U256};
+use ethers::types::{
+    transaction::eip2718::TypedTransaction, Address, TransactionRequest, H256, U256,
+};
 use futures_util::StreamExt;
+use parking_lot::Mutex;
 use rundler_pool::PoolServer;
 use rundler_provider::EntryPoint;
 use rundler_sim::ExpectedStorage;
-use rundler_types::{EntityUpdate, GasFees, UserOperation};
+use rundler_types::{EntityUpdate, GasFees, Timestamp, UserOperation};
 use rundler_utils::emit::WithEntryPoint;
 use tokio::{
     join,
     sync::{broadcast, mpsc, oneshot},
 };
-use tracing::{error, info, trace, warn};
+use tracing::{error, info, trace, warn, Instrument};
 
 use crate::{
     bundle_proposer::BundleProposer,
     emit::{BuilderEvent, BundleTxDetails},
+    server::BundleSenderStatus,
     transaction_tracker::{SendResult, TrackerUpdate, TransactionTracker},
 };
 
@@ -46,6 +53,8 @@ pub(crate) trait BundleSender: Send + Sync + 'static {
 pub(crate) struct Settings {
     pub(crate) replacement_fee_percent_increase: u64,
     pub(crate) max_fee_increases: u64,
+    /// If false, bundles are sent as legacy transactions
+    pub(crate) eip1559_enabled: bool,
 }
 
 #[derive(Debug)]
@@ -67,6 +76,93 @@ where
     pool: C,
     settings: Settings,
     event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+    inclusion_latencies: Arc<InclusionLatencies>,
+    sender_activity: Arc<SenderActivity>,
+}
+
+/// Maximum number of recent bundle inclusion latencies to keep
+const MAX_INCLUSION_LATENCIES: usize = 32;
+
+/// Recent bundle inclusion latencies, shared between the bundle senders and the builder server.
+///
+/// The latency of a bundle is the number of blocks between the block that triggered
+/// its formation and the block it was mined in, including any fee increases.
+#[derive(Debug, Default)]
+pub(crate) struct InclusionLatencies {
+    latencies: Mutex<VecDeque<u64>>,
+}
+
+impl InclusionLatencies {
+    pub(crate) fn record(&self, blocks: u64) {
+        let mut latencies = self.latencies.lock();
+        if latencies.len() == MAX_INCLUSION_LATENCIES {
+            latencies.pop_front();
+        }
+        latencies.push_back(blocks);
+    }
+
+    /// Returns the recent latencies, oldest first
+    pub(crate) fn get(&self) -> Vec<u64> {
+        self.latencies.lock().iter().copied().collect()
+    }
+}
+
+/// Recent activity of each bundle sender, shared between the bundle senders and the builder server.
+#[derive(Debug, Default)]
+pub(crate) struct SenderActivity {
+    senders: Mutex<BTreeMap<u64, BundleSenderStatus>>,
+}
+
+impl SenderActivity {
+    pub(crate) fn register(&self, builder_index: u64, signer: Address) {
+        self.senders.lock().insert(
+            builder_index,
+            BundleSenderStatus {
+                builder_index,
+                signer,
+                last_sent_at: None,
+                last_mined_at: None,
+                last_mined_block: None,
+            },
+        );
+    }
+
+    pub(crate) fn record_sent(&self, builder_index: u64) {
+        if let Some(status) = self.senders.lock().get_mut(&builder_index) {
+            status.last_sent_at = Some(Timestamp::now().seconds_since_epoch());
+        }
+    }
+
+    pub(crate) fn record_mined(&self, builder_index: u64, block_number: u64) {
+        if let Some(status) = self.senders.lock().get_mut(&builder_index) {
+            status.last_mined_at = Some(Timestamp::now().seconds_since_epoch());
+            status.last_mined_block = Some(block_number);
+        }
+    }
+
+    /// Returns the status of each sender, ordered by builder index
+    pub(crate) fn get(&self) -> Vec<BundleSenderStatus> {
+        self.senders.lock().values().cloned().collect()
+    }
+}
+
+// Converts an EIP-1559 transaction to a legacy transaction that pays its max fee as the gas price
+fn into_legacy_transaction(tx: TypedTransaction) -> TypedTransaction {
+    let TypedTransaction::Eip1559(tx) = tx else {
+        return tx;
+    };
+    TransactionRequest {
+        from: tx.from,
+        to: tx.to,
+        gas: tx.gas,
+        gas_price: tx.max_fee_per_gas,
+        value: tx.value,
+        data: tx.data,
+        nonce: tx.nonce,
+        chain_id: tx.chain_id,
+        ..Default::default()
+    }
+    .into()
 }
 
 #[derive(Debug)]
@@ -182,12 +278,17 @@ where
                     block_number,
                     attempt_number,
                     tx_hash,
-                } =>
+                } => {
+                    // Manually triggered bundles have no formation block
+                    if let Some(formed_at) = &last_block {
+                        self.inclusion_latencies.record(block_number.saturating_sub(formed_at.block_number));
+                    }
                     if *attempt_number == 0 {
                         info!("Bundle with hash {tx_hash:?} landed in block {block_number}");
                     } else {
                         info!("Bundle with hash {tx_hash:?} landed in block {block_number} after increasing gas fees {attempt_number} time(s)");
                     }
+                }
                 SendBundleResult::NoOperationsInitially => trace!("No ops to send at block {}", last_block.unwrap_or_default().block_number),
                 SendBundleResult::NoOperationsAfterFeeIncreases {
                     initial_op_count,
@@ -229,7 +330,10 @@ where
         pool: C,
         settings: Settings,
         event_sender: broadcast::Sender<WithEntryPoint<BuilderEvent>>,
+        inclusion_latencies: Arc<InclusionLatencies>,
+        sender_activity: Arc<SenderActivity>,
     ) -> Self {
+        sender_activity.register(builder_index, beneficiary);
         Self {
             builder_index,
             manual_bundling_mode,
@@ -242,6 +346,8 @@ where
             pool,
             settings,
             event_sender,
+            inclusion_latencies,
+            sender_activity,
         }
     }
 
@@ -260,12 +366,20 @@ where
         match update {
             TrackerUpdate::Mined {
                 tx_hash,
+                nonce,
                 block_number,
                 attempt_number,
                 gas_limit,
                 gas_used,
-                ..
             } => {
+                self.emit(BuilderEvent::transaction_mined(
+                    self.builder_index,
+                    tx_hash,
+                    nonce.low_u64(),
+                    block_number,
+                ));
+                self.sender_activity
+                    .record_mined(self.builder_index, block_number);
                 BuilderMetrics::increment_bundle_txns_success(self.builder_index);
                 BuilderMetrics::set_bundle_gas_stats(gas_limit, gas_used);
                 if attempt_number == 0 {
@@ -351,24 +465,40 @@ where
             if initial_op_count.is_none() {
                 initial_op_count = Some(op_hashes.len());
             }
+            let op_hashes = Arc::new(op_hashes);
+            self.emit(BuilderEvent::proposed_bundle(
+                self.builder_index,
+                Arc::clone(&op_hashes),
+                nonce.low_u64(),
+                fee_increase_count,
+            ));
             let current_fees = GasFees::from(&tx);
 
             BuilderMetrics::increment_bundle_txns_sent(self.builder_index);
             BuilderMetrics::set_current_fees(&current_fees);
 
+            let span = tracing::info_span!(
+                "send_bundle_transaction",
+                builder_index = self.builder_index,
+                nonce = %nonce,
+                attempt = fee_increase_count,
+                op_hashes = ?op_hashes,
+            );
             let send_result = self
                 .transaction_tracker
                 .send_transaction(tx.clone(), &expected_storage)
+                .instrument(span)
                 .await?;
             let update = match send_result {
                 SendResult::TrackerUpdate(update) => update,
                 SendResult::TxHash(tx_hash) => {
+                    self.sender_activity.record_sent(self.builder_index);
                     self.emit(BuilderEvent::formed_bundle(
                         self.builder_index,
                         Some(BundleTxDetails {
                             tx_hash,
                             tx,
-                            op_hashes: Arc::new(op_hashes),
+                            op_hashes,
                         }),
                         nonce.low_u64(),
                         fee_increase_count,
@@ -392,6 +522,8 @@ where
                         nonce.low_u64(),
                         block_number,
                     ));
+                    self.sender_activity
+                        .record_mined(self.builder_index, block_number);
                     BuilderMetrics::increment_bundle_txns_success(self.builder_index);
                     BuilderMetrics::set_bundle_gas_stats(gas_limit, gas_used);
                     return Ok(SendBundleResult::Success {
@@ -488,6 +620,9 @@ where
             bundle.gas_estimate,
             bundle.gas_fees,
         );
+        if !self.settings.eip1559_enabled {
+            tx = into_legacy_transaction(tx);
+        }
         tx.set_nonce(nonce);
         Ok(Some(BundleTx {
             tx,
@@ -53,6 +53,22 @@ impl BuilderEvent {
         )
     }
 
+    pub(crate) fn proposed_bundle(
+        builder_index: u64,
+        op_hashes: Arc<Vec<H256>>,
+        nonce: u64,
+        fee_increase_count: u64,
+    ) -> Self {
+        Self::new(
+            builder_index,
+            BuilderEventKind::ProposedBundle {
+                op_hashes,
+                nonce,
+                fee_increase_count,
+            },
+        )
+    }
+
     pub(crate) fn transaction_mined(
         builder_index: u64,
         tx_hash: H256,
@@ -105,6 +121,15 @@ impl BuilderEvent {
 /// BuilderEventKind
 #[derive(Clone, Debug)]
 pub enum BuilderEventKind {
+    /// A bundle was proposed and is about to be sent
+    ProposedBundle {
+        /// Operation hashes included in the bundle
+        op_hashes: Arc<Vec<H256>>,
+        /// Nonce of the transaction to be sent
+        nonce: u64,
+        /// Number of times fees were increased
+        fee_increase_count: u64,
+    },
     /// A bundle was formed
     FormedBundle {
         /// Details of the transaction that was sent
@@ -260,6 +285,24 @@ impl Display for BuilderEvent {
                     ),
                 }
             }
+            BuilderEventKind::ProposedBundle {
+                op_hashes,
+                nonce,
+                fee_increase_count,
+            } => write!(
+                f,
+                concat!(
+                    "Bundle proposed.",
+                    "    Builder index: {:?}",
+                    "    Nonce: {}",
+                    "    Fee increases: {}",
+                    "    Op count: {}",
+                ),
+                self.builder_index,
+                nonce,
+                fee_increase_count,
+                op_hashes.len(),
+            ),
             BuilderEventKind::TransactionMined {
                 tx_hash,
                 nonce,
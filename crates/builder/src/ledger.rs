@@ -0,0 +1,790 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{collections::HashMap, ops::Bound, path::Path, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use ethers::{
+    contract,
+    types::{Address, BlockId, Bytes, TransactionReceipt, H256, I256, U256},
+};
+use redb::{Database, ReadableTable, TableDefinition};
+use rundler_provider::Provider;
+use rundler_types::contracts::i_entry_point::UserOperationEventFilter;
+use rundler_utils::emit::WithEntryPoint;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    try_join,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::emit::{BuilderEvent, BuilderEventKind};
+
+/// Mined bundles by block timestamp followed by transaction hash, serialized as JSON
+const BUNDLES: TableDefinition<&[u8], &[u8]> = TableDefinition::new("bundles");
+/// Key in `BUNDLES` of each recorded bundle, by transaction hash
+const BUNDLE_KEYS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("bundle_keys");
+
+/// A user operation in a mined bundle, as recorded by its `UserOperationEvent`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerOp {
+    /// Hash of the user operation
+    pub hash: H256,
+    /// Sender of the user operation
+    pub sender: Address,
+    /// Paymaster of the user operation, if any
+    pub paymaster: Option<Address>,
+    /// Whether the user operation's call succeeded
+    pub success: bool,
+    /// Gas cost charged to the user operation, paid to the beneficiary
+    pub actual_gas_cost: U256,
+    /// Gas used by the user operation
+    pub actual_gas_used: U256,
+}
+
+/// A bundle transaction mined for one of the builder's senders
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerEntry {
+    /// Hash of the bundle transaction
+    pub tx_hash: H256,
+    /// Entry point the bundle was sent to
+    pub entry_point: Address,
+    /// Index of the builder that sent the bundle
+    pub builder_index: u64,
+    /// Beneficiary of the bundle, which is the sender of the transaction
+    pub beneficiary: Address,
+    /// Number of the block the bundle was mined in
+    pub block_number: u64,
+    /// Hash of the block the bundle was mined in
+    #[serde(default)]
+    pub block_hash: H256,
+    /// Timestamp of the block the bundle was mined in
+    pub block_timestamp: u64,
+    /// Gas used by the bundle transaction
+    pub gas_used: U256,
+    /// Gas price paid by the bundle transaction
+    pub effective_gas_price: U256,
+    /// L1 data fee paid by the bundle transaction, zero on chains without one
+    pub l1_fee: U256,
+    /// Balance of the beneficiary at the end of the block before the bundle
+    pub beneficiary_balance_before: U256,
+    /// Balance of the beneficiary at the end of the bundle's block
+    pub beneficiary_balance_after: U256,
+    /// User operations in the bundle
+    pub ops: Vec<LedgerOp>,
+    /// Share of the transaction cost, including the L1 data fee, allocated to
+    /// the operations of the queried paymaster in proportion to their gas used.
+    /// Only set on the results of a query by paymaster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocated_gas_cost: Option<U256>,
+    /// Share of the L1 data fee allocated to the operations of the queried
+    /// paymaster. Only set on the results of a query by paymaster.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allocated_l1_fee: Option<U256>,
+}
+
+impl LedgerEntry {
+    /// Cost of the bundle transaction, including the L1 data fee, or the
+    /// share of it allocated to the operations of a queried paymaster
+    pub fn gas_cost(&self) -> U256 {
+        self.allocated_gas_cost
+            .unwrap_or_else(|| self.gas_used * self.effective_gas_price + self.l1_fee)
+    }
+
+    /// L1 data fee of the bundle transaction, or the share of it allocated to
+    /// the operations of a queried paymaster
+    pub fn l1_fee(&self) -> U256 {
+        self.allocated_l1_fee.unwrap_or(self.l1_fee)
+    }
+
+    /// Sum of the gas costs charged to the bundle's user operations
+    pub fn op_revenue(&self) -> U256 {
+        self.ops
+            .iter()
+            .map(|op| op.actual_gas_cost)
+            .fold(U256::zero(), |a, b| a + b)
+    }
+
+    /// Revenue from the bundle's user operations less the cost of the transaction
+    pub fn profit(&self) -> I256 {
+        I256::from_raw(self.op_revenue()) - I256::from_raw(self.gas_cost())
+    }
+
+    /// Change in the beneficiary's balance over the bundle's block. Differs
+    /// from the profit if the beneficiary had other transfers in the block,
+    /// and is never allocated to a paymaster's operations.
+    pub fn beneficiary_balance_delta(&self) -> I256 {
+        I256::from_raw(self.beneficiary_balance_after)
+            - I256::from_raw(self.beneficiary_balance_before)
+    }
+
+    // Sorts bundles by time, then by transaction hash
+    fn key(&self) -> Vec<u8> {
+        let mut key = self.block_timestamp.to_be_bytes().to_vec();
+        key.extend_from_slice(self.tx_hash.as_bytes());
+        key
+    }
+
+    // Keeps only the operations of `paymaster`, allocating them a share of
+    // the transaction cost in proportion to their gas used, or to their
+    // number if no gas use was recorded
+    fn retain_paymaster(&mut self, paymaster: Address) {
+        let is_paymasters = |op: &&LedgerOp| op.paymaster == Some(paymaster);
+        let gas_used = |op: &LedgerOp| op.actual_gas_used;
+        let total_gas = self
+            .ops
+            .iter()
+            .map(gas_used)
+            .fold(U256::zero(), |a, b| a + b);
+        let gas = self
+            .ops
+            .iter()
+            .filter(is_paymasters)
+            .map(gas_used)
+            .fold(U256::zero(), |a, b| a + b);
+        let (share, total) = if total_gas.is_zero() {
+            (
+                self.ops.iter().filter(is_paymasters).count().into(),
+                self.ops.len().into(),
+            )
+        } else {
+            (gas, total_gas)
+        };
+        if !total.is_zero() {
+            self.allocated_gas_cost = Some(self.gas_cost() * share / total);
+            self.allocated_l1_fee = Some(self.l1_fee() * share / total);
+        }
+        self.ops.retain(|op| is_paymasters(&op));
+    }
+}
+
+/// Filters for querying the ledger. Unset fields match every bundle.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerQuery {
+    /// Earliest block timestamp to include, in seconds since the epoch
+    pub from_timestamp: Option<u64>,
+    /// Latest block timestamp to include, in seconds since the epoch
+    pub to_timestamp: Option<u64>,
+    /// Only include bundles sent by this builder
+    pub builder_index: Option<u64>,
+    /// Only include bundles with user operations of this paymaster, and only
+    /// the paymaster's operations within them
+    pub paymaster: Option<Address>,
+    /// Maximum number of bundles to return
+    pub limit: Option<u64>,
+    /// Only include bundles after this cursor, taken from the `nextCursor` of
+    /// the previous page
+    pub cursor: Option<Bytes>,
+}
+
+/// Totals over a set of ledger entries
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerSummary {
+    /// Number of bundles
+    pub bundles: u64,
+    /// Number of user operations
+    pub ops: u64,
+    /// Total cost of the bundle transactions, including L1 data fees. Under a
+    /// paymaster filter, only the share allocated to the paymaster's operations.
+    pub gas_cost: U256,
+    /// Total L1 data fees of the bundle transactions. Under a paymaster filter,
+    /// only the share allocated to the paymaster's operations.
+    pub l1_fee: U256,
+    /// Total gas costs charged to the user operations
+    pub op_revenue: U256,
+    /// Revenue less costs in wei, as a signed decimal
+    pub profit: String,
+    /// Total change in beneficiary balances in wei, as a signed decimal
+    pub beneficiary_balance_delta: String,
+}
+
+impl LedgerSummary {
+    /// Sums up the given entries
+    pub fn new(entries: &[LedgerEntry]) -> Self {
+        let mut profit = I256::zero();
+        let mut beneficiary_balance_delta = I256::zero();
+        let mut summary = Self::default();
+        for entry in entries {
+            summary.bundles += 1;
+            summary.ops += entry.ops.len() as u64;
+            summary.gas_cost += entry.gas_cost();
+            summary.l1_fee += entry.l1_fee();
+            summary.op_revenue += entry.op_revenue();
+            profit += entry.profit();
+            beneficiary_balance_delta += entry.beneficiary_balance_delta();
+        }
+        summary.profit = profit.to_string();
+        summary.beneficiary_balance_delta = beneficiary_balance_delta.to_string();
+        summary
+    }
+}
+
+/// Ledger entries matching a query along with their totals
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LedgerReport {
+    /// Totals over the bundles
+    pub summary: LedgerSummary,
+    /// Bundles ordered by block timestamp
+    pub bundles: Vec<LedgerEntry>,
+    /// Cursor to query the next page with, set if the page is full
+    pub next_cursor: Option<Bytes>,
+}
+
+/// An embedded database recording the costs and revenue of each bundle mined
+/// by the builder.
+///
+/// The ledger is filled by the builder task as its bundles are mined. The
+/// database can only be opened by one process at a time, so other processes
+/// query it through the builder server.
+#[derive(Debug)]
+pub struct BundleLedger {
+    db: Database,
+}
+
+impl BundleLedger {
+    /// Open the ledger at `path`, creating it if it does not exist
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = Database::create(path)
+            .with_context(|| format!("should open bundle ledger at {path:?}"))?;
+        // create the tables so that read transactions can always open them
+        let txn = db.begin_write()?;
+        txn.open_table(BUNDLES)?;
+        txn.open_table(BUNDLE_KEYS)?;
+        txn.commit()?;
+        Ok(Self { db })
+    }
+
+    /// Runs `f` against the ledger on the blocking thread pool, as redb reads
+    /// and writes block the calling thread
+    pub(crate) async fn run_blocking<T, F>(self: &Arc<Self>, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&BundleLedger) -> anyhow::Result<T> + Send + 'static,
+    {
+        let ledger = Arc::clone(self);
+        tokio::task::spawn_blocking(move || f(&ledger))
+            .await
+            .context("bundle ledger access should not panic")?
+    }
+
+    /// Records a mined bundle, replacing any earlier record of the same
+    /// transaction, e.g. from before a reorg
+    pub fn insert(&self, entry: &LedgerEntry) -> anyhow::Result<()> {
+        let key = entry.key();
+        let value = serde_json::to_vec(entry)?;
+        let txn = self.db.begin_write()?;
+        {
+            let mut bundles = txn.open_table(BUNDLES)?;
+            let mut bundle_keys = txn.open_table(BUNDLE_KEYS)?;
+            let old_key = bundle_keys
+                .insert(entry.tx_hash.as_bytes(), key.as_slice())?
+                .map(|value| value.value().to_vec());
+            if let Some(old_key) = old_key {
+                bundles.remove(old_key.as_slice())?;
+            }
+            bundles.insert(key.as_slice(), value.as_slice())?;
+        }
+        txn.commit()?;
+        Ok(())
+    }
+
+    /// Removes the record of a bundle, e.g. one reorged out of the chain.
+    /// Returns whether the bundle was recorded.
+    pub fn remove(&self, tx_hash: H256) -> anyhow::Result<bool> {
+        let txn = self.db.begin_write()?;
+        let removed = {
+            let mut bundles = txn.open_table(BUNDLES)?;
+            let mut bundle_keys = txn.open_table(BUNDLE_KEYS)?;
+            let key = bundle_keys
+                .remove(tx_hash.as_bytes())?
+                .map(|value| value.value().to_vec());
+            match key {
+                Some(key) => {
+                    bundles.remove(key.as_slice())?;
+                    true
+                }
+                None => false,
+            }
+        };
+        txn.commit()?;
+        Ok(removed)
+    }
+
+    /// Get the bundles matching the query, ordered by block timestamp
+    pub fn query(&self, query: &LedgerQuery) -> anyhow::Result<Vec<LedgerEntry>> {
+        let mut start = query.from_timestamp.unwrap_or(0).to_be_bytes().to_vec();
+        start.extend_from_slice(&[0; 32]);
+        let start = match &query.cursor {
+            Some(cursor) if cursor.as_ref() >= start.as_slice() => Bound::Excluded(cursor.as_ref()),
+            _ => Bound::Included(start.as_slice()),
+        };
+        let mut end = query
+            .to_timestamp
+            .unwrap_or(u64::MAX)
+            .to_be_bytes()
+            .to_vec();
+        end.extend_from_slice(&[0xff; 32]);
+
+        let txn = self.db.begin_read()?;
+        let bundles = txn.open_table(BUNDLES)?;
+        let mut entries = vec![];
+        for item in bundles.range::<&[u8]>((start, Bound::Included(end.as_slice())))? {
+            if query
+                .limit
+                .is_some_and(|limit| entries.len() as u64 >= limit)
+            {
+                break;
+            }
+            let (_, value) = item?;
+            let mut entry: LedgerEntry =
+                serde_json::from_slice(value.value()).context("should deserialize ledger entry")?;
+            if query
+                .builder_index
+                .is_some_and(|index| index != entry.builder_index)
+            {
+                continue;
+            }
+            if let Some(paymaster) = query.paymaster {
+                entry.retain_paymaster(paymaster);
+                if entry.ops.is_empty() {
+                    continue;
+                }
+            }
+            entries.push(entry);
+        }
+        Ok(entries)
+    }
+
+    /// Get the bundles matching the query along with their totals
+    pub fn report(&self, query: &LedgerQuery) -> anyhow::Result<LedgerReport> {
+        let bundles = self.query(query)?;
+        let next_cursor = query
+            .limit
+            .filter(|&limit| bundles.len() as u64 >= limit)
+            .and(bundles.last())
+            .map(|entry| entry.key().into());
+        Ok(LedgerReport {
+            summary: LedgerSummary::new(&bundles),
+            bundles,
+            next_cursor,
+        })
+    }
+}
+
+/// Records the bundles mined by the builder's senders, as reported by their
+/// `TransactionMined` events, until shutdown.
+///
+/// Recorded bundles are checked whenever the head of the chain changes, as
+/// polled every `poll_interval`, until they are `chain_history_size` blocks
+/// deep. Bundles that were reorged into another block are recorded again, and
+/// those no longer mined are removed until they are mined again.
+pub(crate) async fn record_mined_bundles<P: Provider>(
+    ledger: Arc<BundleLedger>,
+    provider: Arc<P>,
+    mut events: broadcast::Receiver<WithEntryPoint<BuilderEvent>>,
+    chain_history_size: u64,
+    poll_interval: Duration,
+    shutdown_token: CancellationToken,
+) -> anyhow::Result<()> {
+    info!("Started bundle ledger");
+    let mut unconfirmed = HashMap::<H256, UnconfirmedBundle>::new();
+    let mut checked_head = 0;
+    let mut poll = tokio::time::interval(poll_interval);
+    loop {
+        let event = tokio::select! {
+            _ = shutdown_token.cancelled() => break,
+            _ = poll.tick() => {
+                check_unconfirmed_bundles(
+                    &ledger,
+                    &*provider,
+                    &mut unconfirmed,
+                    &mut checked_head,
+                    chain_history_size,
+                )
+                .await;
+                continue;
+            }
+            event = events.recv() => event,
+        };
+        let event = match event {
+            Ok(event) => event,
+            Err(RecvError::Lagged(skipped)) => {
+                warn!(
+                    "Bundle ledger skipped {skipped} builder events, mined bundles may be missing"
+                );
+                continue;
+            }
+            Err(RecvError::Closed) => break,
+        };
+        let BuilderEventKind::TransactionMined {
+            tx_hash,
+            block_number,
+            ..
+        } = event.event.kind
+        else {
+            continue;
+        };
+
+        let mut bundle = UnconfirmedBundle {
+            entry_point: event.entry_point,
+            builder_index: event.event.builder_index,
+            block_number,
+            block_hash: None,
+        };
+        match provider.get_transaction_receipt(tx_hash).await {
+            Ok(Some(receipt)) => {
+                bundle.record(&ledger, &*provider, tx_hash, receipt).await;
+            }
+            Ok(None) => {
+                warn!("Receipt of bundle {tx_hash:?} mined in block {block_number} not found, recording it once it is");
+            }
+            Err(error) => {
+                error!("Failed to load bundle {tx_hash:?} for the ledger: {error:?}");
+            }
+        }
+        unconfirmed.insert(tx_hash, bundle);
+    }
+    info!("Shutting down bundle ledger");
+    Ok(())
+}
+
+/// A recently mined bundle that may still be reorged out of the chain
+#[derive(Debug)]
+struct UnconfirmedBundle {
+    entry_point: Address,
+    builder_index: u64,
+    block_number: u64,
+    /// Hash of the block the bundle is recorded in, if it is recorded
+    block_hash: Option<H256>,
+}
+
+impl UnconfirmedBundle {
+    async fn record<P: Provider>(
+        &mut self,
+        ledger: &Arc<BundleLedger>,
+        provider: &P,
+        tx_hash: H256,
+        receipt: TransactionReceipt,
+    ) {
+        let entry = load_entry(provider, self.entry_point, self.builder_index, receipt).await;
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(error) => {
+                error!("Failed to load bundle {tx_hash:?} for the ledger: {error:?}");
+                return;
+            }
+        };
+        let (block_number, block_hash) = (entry.block_number, entry.block_hash);
+        let inserted = ledger
+            .run_blocking(move |ledger| ledger.insert(&entry))
+            .await;
+        if let Err(error) = inserted {
+            error!("Failed to record bundle {tx_hash:?} in the ledger: {error:?}");
+            return;
+        }
+        if self.block_hash.is_none() {
+            LedgerMetrics::increment_bundles_recorded(self.builder_index);
+        }
+        self.block_number = block_number;
+        self.block_hash = Some(block_hash);
+    }
+
+    async fn remove(&mut self, ledger: &Arc<BundleLedger>, tx_hash: H256) {
+        match ledger
+            .run_blocking(move |ledger| ledger.remove(tx_hash))
+            .await
+        {
+            Ok(_) => {
+                info!("Bundle {tx_hash:?} is no longer mined, removed it from the ledger");
+                LedgerMetrics::increment_bundles_removed(self.builder_index);
+                self.block_hash = None;
+            }
+            Err(error) => {
+                error!("Failed to remove bundle {tx_hash:?} from the ledger: {error:?}");
+            }
+        }
+    }
+}
+
+async fn check_unconfirmed_bundles<P: Provider>(
+    ledger: &Arc<BundleLedger>,
+    provider: &P,
+    unconfirmed: &mut HashMap<H256, UnconfirmedBundle>,
+    checked_head: &mut u64,
+    chain_history_size: u64,
+) {
+    if unconfirmed.is_empty() {
+        return;
+    }
+    let head = match provider.get_block_number().await {
+        Ok(head) => head,
+        Err(error) => {
+            error!("Failed to get the block number for the bundle ledger: {error:?}");
+            return;
+        }
+    };
+    // receipts only change with the head of the chain
+    if head == *checked_head {
+        return;
+    }
+    *checked_head = head;
+    unconfirmed.retain(|_, bundle| head <= bundle.block_number + chain_history_size);
+
+    for (&tx_hash, bundle) in unconfirmed.iter_mut() {
+        match provider.get_transaction_receipt(tx_hash).await {
+            Ok(Some(receipt)) if receipt.block_hash.is_some() => {
+                if receipt.block_hash != bundle.block_hash {
+                    bundle.record(ledger, provider, tx_hash, receipt).await;
+                }
+            }
+            Ok(_) => {
+                if bundle.block_hash.is_some() {
+                    bundle.remove(ledger, tx_hash).await;
+                }
+            }
+            Err(error) => {
+                error!("Failed to check bundle {tx_hash:?} for the ledger: {error:?}");
+            }
+        }
+    }
+}
+
+async fn load_entry<P: Provider>(
+    provider: &P,
+    entry_point: Address,
+    builder_index: u64,
+    receipt: TransactionReceipt,
+) -> anyhow::Result<LedgerEntry> {
+    let block_number = receipt
+        .block_number
+        .context("mined bundle receipt should have a block number")?
+        .as_u64();
+    let block_hash = receipt
+        .block_hash
+        .context("mined bundle receipt should have a block hash")?;
+    // The builder uses each sender's address as the bundle beneficiary
+    let beneficiary = receipt.from;
+    let (block, beneficiary_balance_before, beneficiary_balance_after) = try_join!(
+        provider.get_block(block_hash),
+        provider.get_balance(
+            beneficiary,
+            Some(BlockId::from(block_number.saturating_sub(1)))
+        ),
+        provider.get_balance(beneficiary, Some(BlockId::from(block_hash))),
+    )?;
+    let block = block.context("block of mined bundle should exist")?;
+
+    let ops = receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == entry_point)
+        .filter_map(|log| contract::parse_log::<UserOperationEventFilter>(log.clone()).ok())
+        .map(|event| LedgerOp {
+            hash: event.user_op_hash.into(),
+            sender: event.sender,
+            paymaster: (!event.paymaster.is_zero()).then_some(event.paymaster),
+            success: event.success,
+            actual_gas_cost: event.actual_gas_cost,
+            actual_gas_used: event.actual_gas_used,
+        })
+        .collect();
+
+    Ok(LedgerEntry {
+        tx_hash: receipt.transaction_hash,
+        entry_point,
+        builder_index,
+        beneficiary,
+        block_number,
+        block_hash,
+        block_timestamp: block.timestamp.as_u64(),
+        gas_used: receipt.gas_used.unwrap_or_default(),
+        effective_gas_price: receipt.effective_gas_price.unwrap_or_default(),
+        // Reported in the receipts of OP stack chains
+        l1_fee: receipt
+            .other
+            .get_deserialized::<U256>("l1Fee")
+            .and_then(Result::ok)
+            .unwrap_or_default(),
+        beneficiary_balance_before,
+        beneficiary_balance_after,
+        ops,
+        ..Default::default()
+    })
+}
+
+struct LedgerMetrics {}
+
+impl LedgerMetrics {
+    fn increment_bundles_recorded(builder_index: u64) {
+        metrics::increment_counter!("builder_ledger_bundles_recorded", "builder_index" => builder_index.to_string());
+    }
+
+    fn increment_bundles_removed(builder_index: u64) {
+        metrics::increment_counter!("builder_ledger_bundles_removed", "builder_index" => builder_index.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        block_timestamp: u64,
+        builder_index: u64,
+        paymasters: &[Option<Address>],
+    ) -> LedgerEntry {
+        LedgerEntry {
+            tx_hash: H256::random(),
+            builder_index,
+            block_timestamp,
+            gas_used: 100.into(),
+            effective_gas_price: 10.into(),
+            l1_fee: 500.into(),
+            beneficiary_balance_before: 10_000.into(),
+            beneficiary_balance_after: 9_900.into(),
+            ops: paymasters
+                .iter()
+                .map(|&paymaster| LedgerOp {
+                    hash: H256::random(),
+                    paymaster,
+                    actual_gas_cost: 700.into(),
+                    actual_gas_used: 100.into(),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_filters_and_summarizes() {
+        let path = std::env::temp_dir().join(format!("rundler-ledger-{:?}.redb", H256::random()));
+        let ledger = BundleLedger::open(&path).unwrap();
+        let paymaster = Address::random();
+        let first = entry(100, 0, &[None, Some(paymaster)]);
+        let second = entry(200, 1, &[None]);
+        let third = entry(300, 0, &[Some(paymaster)]);
+        for e in [&third, &first, &second] {
+            ledger.insert(e).unwrap();
+        }
+
+        let all = ledger.query(&LedgerQuery::default()).unwrap();
+        assert_eq!(all, vec![first.clone(), second.clone(), third.clone()]);
+
+        let by_time = ledger
+            .query(&LedgerQuery {
+                from_timestamp: Some(150),
+                to_timestamp: Some(300),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_time, vec![second.clone(), third.clone()]);
+
+        let by_builder = ledger
+            .query(&LedgerQuery {
+                builder_index: Some(1),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_builder, vec![second.clone()]);
+
+        let by_paymaster = ledger
+            .report(&LedgerQuery {
+                paymaster: Some(paymaster),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(by_paymaster.bundles.len(), 2);
+        assert_eq!(by_paymaster.bundles[0].ops, vec![first.ops[1].clone()]);
+        assert_eq!(by_paymaster.summary.bundles, 2);
+        assert_eq!(by_paymaster.summary.ops, 2);
+        // half of the first bundle's cost and all of the third's
+        assert_eq!(by_paymaster.bundles[0].gas_cost(), 750.into());
+        assert_eq!(by_paymaster.bundles[1].gas_cost(), 1500.into());
+        assert_eq!(by_paymaster.summary.gas_cost, 2250.into());
+        assert_eq!(by_paymaster.summary.l1_fee, 750.into());
+        assert_eq!(by_paymaster.summary.op_revenue, 1400.into());
+        assert_eq!(by_paymaster.summary.profit, "-850");
+        assert_eq!(by_paymaster.summary.beneficiary_balance_delta, "-200");
+        assert_eq!(by_paymaster.next_cursor, None);
+
+        // recording a bundle again, e.g. after a reorg, replaces it
+        let mut reorged = first.clone();
+        reorged.block_timestamp = 400;
+        ledger.insert(&reorged).unwrap();
+        let all = ledger.query(&LedgerQuery::default()).unwrap();
+        assert_eq!(all, vec![second.clone(), third.clone(), reorged.clone()]);
+
+        // removing a bundle, e.g. after it was reorged out
+        assert!(ledger.remove(second.tx_hash).unwrap());
+        assert!(!ledger.remove(second.tx_hash).unwrap());
+        let all = ledger.query(&LedgerQuery::default()).unwrap();
+        assert_eq!(all, vec![third, reorged]);
+
+        drop(ledger);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_allocates_cost_by_op_count_without_gas_used() {
+        let paymaster = Address::random();
+        let mut entry = entry(100, 0, &[None, None, Some(paymaster), Some(paymaster)]);
+        for op in &mut entry.ops {
+            op.actual_gas_used = U256::zero();
+        }
+        entry.retain_paymaster(paymaster);
+        assert_eq!(entry.ops.len(), 2);
+        assert_eq!(entry.gas_cost(), 750.into());
+        assert_eq!(entry.l1_fee(), 250.into());
+    }
+
+    #[test]
+    fn test_query_pages() {
+        let path = std::env::temp_dir().join(format!("rundler-ledger-{:?}.redb", H256::random()));
+        let ledger = BundleLedger::open(&path).unwrap();
+        let entries = (1..=5)
+            .map(|i| entry(i * 100, 0, &[None]))
+            .collect::<Vec<_>>();
+        for e in &entries {
+            ledger.insert(e).unwrap();
+        }
+
+        let mut query = LedgerQuery {
+            from_timestamp: Some(200),
+            limit: Some(2),
+            ..Default::default()
+        };
+        let first = ledger.report(&query).unwrap();
+        assert_eq!(first.bundles, entries[1..3]);
+        assert_eq!(first.summary.bundles, 2);
+
+        query.cursor = first.next_cursor;
+        let second = ledger.report(&query).unwrap();
+        assert_eq!(second.bundles, entries[3..5]);
+
+        query.cursor = second.next_cursor;
+        let third = ledger.report(&query).unwrap();
+        assert!(third.bundles.is_empty());
+        assert_eq!(third.next_cursor, None);
+
+        drop(ledger);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -23,15 +23,18 @@ mod bundle_proposer;
 mod bundle_sender;
 
 mod emit;
-pub use emit::{BuilderEvent, BuilderEventKind};
+pub use emit::{BuilderEvent, BuilderEventKind, OpRejectionReason, SkipReason};
+
+mod ledger;
+pub use ledger::{BundleLedger, LedgerEntry, LedgerOp, LedgerQuery, LedgerReport, LedgerSummary};
 
 mod sender;
 pub use sender::TransactionSenderType;
 
 mod server;
 pub use server::{
-    BuilderResult, BuilderServer, BuilderServerError, BundlingMode, LocalBuilderBuilder,
-    LocalBuilderHandle, RemoteBuilderClient,
+    BuilderResult, BuilderServer, BuilderServerError, BuilderStatus, BundleSenderStatus,
+    BundlingMode, LocalBuilderBuilder, LocalBuilderHandle, RemoteBuilderClient,
 };
 
 mod signer;
@@ -15,9 +15,9 @@ mod bloxroute;
 mod conditional;
 mod flashbots;
 mod raw;
-use std::{str::FromStr, sync::Arc, time::Duration};
+use std::{sync::Arc, time::Duration};
 
-use anyhow::{bail, Context, Error};
+use anyhow::Context;
 use async_trait::async_trait;
 pub(crate) use bloxroute::PolygonBloxrouteTransactionSender;
 pub(crate) use conditional::ConditionalTransactionSender;
@@ -26,8 +26,7 @@ use ethers::{
     prelude::SignerMiddleware,
     providers::{JsonRpcClient, Middleware, Provider, ProviderError},
     types::{
-        transaction::eip2718::TypedTransaction, Address, Bytes, Chain, TransactionReceipt, H256,
-        U256,
+        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionReceipt, H256, U256,
     },
 };
 use ethers_signers::Signer;
@@ -36,7 +35,8 @@ pub(crate) use flashbots::FlashbotsTransactionSender;
 use mockall::automock;
 pub(crate) use raw::RawTransactionSender;
 use rundler_sim::ExpectedStorage;
-use serde::Serialize;
+use rundler_types::chain::ChainSpec;
+pub use rundler_types::chain::TransactionSenderType;
 
 #[derive(Debug)]
 pub(crate) struct SentTxInfo {
@@ -93,93 +93,45 @@ where
     PolygonBloxroute(PolygonBloxrouteTransactionSender<C, S>),
 }
 
-/// Transaction sender types
-#[derive(Debug, Clone, Copy, Serialize)]
-#[serde(rename_all = "snake_case")]
-pub enum TransactionSenderType {
-    /// Raw transaction sender
-    Raw,
-    /// Conditional transaction sender
-    Conditional,
-    /// Flashbots transaction sender
-    ///
-    /// Currently only supported on Eth mainnet
-    Flashbots,
-    /// Bloxroute transaction sender
-    ///
-    /// Currently only supported on Polygon mainnet
-    PolygonBloxroute,
-}
-
-impl FromStr for TransactionSenderType {
-    type Err = Error;
-
-    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-        match s.to_ascii_lowercase().as_str() {
-            "raw" => Ok(TransactionSenderType::Raw),
-            "conditional" => Ok(TransactionSenderType::Conditional),
-            "flashbots" => Ok(TransactionSenderType::Flashbots),
-            "polygon_bloxroute" => Ok(TransactionSenderType::PolygonBloxroute),
-            _ => bail!("Invalid sender input. Must be one of either 'raw', 'conditional', 'flashbots' or 'polygon_bloxroute'"),
-        }
+/// Creates the transaction sender of the given type, if the chain supports it
+pub(crate) fn new_sender<C: JsonRpcClient + 'static, S: Signer + 'static>(
+    sender_type: TransactionSenderType,
+    client: Arc<Provider<C>>,
+    signer: S,
+    chain_spec: &ChainSpec,
+    eth_poll_interval: Duration,
+    bloxroute_header: &Option<String>,
+) -> std::result::Result<TransactionSenderEnum<C, S>, SenderConstructorErrors> {
+    if !chain_spec.supported_senders.contains(&sender_type) {
+        return Err(SenderConstructorErrors::InvalidChainForSender(
+            chain_spec.id,
+            sender_type.to_string(),
+        ));
     }
-}
-
-impl TransactionSenderType {
-    fn into_snake_case(self) -> String {
-        match self {
-            TransactionSenderType::Raw => "raw",
-            TransactionSenderType::Conditional => "conditional",
-            TransactionSenderType::Flashbots => "flashbots",
-            TransactionSenderType::PolygonBloxroute => "polygon_bloxroute",
+    let sender = match sender_type {
+        TransactionSenderType::Raw => {
+            TransactionSenderEnum::Raw(RawTransactionSender::new(client, signer))
         }
-        .to_string()
-    }
-
-    pub(crate) fn into_sender<C: JsonRpcClient + 'static, S: Signer + 'static>(
-        self,
-        client: Arc<Provider<C>>,
-        signer: S,
-        chain_id: u64,
-        eth_poll_interval: Duration,
-        bloxroute_header: &Option<String>,
-    ) -> std::result::Result<TransactionSenderEnum<C, S>, SenderConstructorErrors> {
-        let sender = match self {
-            Self::Raw => TransactionSenderEnum::Raw(RawTransactionSender::new(client, signer)),
-            Self::Conditional => TransactionSenderEnum::Conditional(
-                ConditionalTransactionSender::new(client, signer),
-            ),
-            Self::Flashbots => {
-                if chain_id != Chain::Mainnet as u64 {
-                    return Err(SenderConstructorErrors::InvalidChainForSender(
-                        chain_id,
-                        self.into_snake_case(),
-                    ));
-                }
-                TransactionSenderEnum::Flashbots(FlashbotsTransactionSender::new(client, signer)?)
-            }
-            Self::PolygonBloxroute => {
-                if let Some(header) = bloxroute_header {
-                    if chain_id == Chain::Polygon as u64 {
-                        return Err(SenderConstructorErrors::InvalidChainForSender(
-                            chain_id,
-                            self.into_snake_case(),
-                        ));
-                    }
-
-                    TransactionSenderEnum::PolygonBloxroute(PolygonBloxrouteTransactionSender::new(
-                        client,
-                        signer,
-                        eth_poll_interval,
-                        header,
-                    )?)
-                } else {
-                    return Err(SenderConstructorErrors::BloxRouteMissingToken);
-                }
+        TransactionSenderType::Conditional => {
+            TransactionSenderEnum::Conditional(ConditionalTransactionSender::new(client, signer))
+        }
+        TransactionSenderType::Flashbots => {
+            TransactionSenderEnum::Flashbots(FlashbotsTransactionSender::new(client, signer)?)
+        }
+        TransactionSenderType::PolygonBloxroute => {
+            if let Some(header) = bloxroute_header {
+                TransactionSenderEnum::PolygonBloxroute(PolygonBloxrouteTransactionSender::new(
+                    client,
+                    signer,
+                    eth_poll_interval,
+                    header,
+                )?)
+            } else {
+                return Err(SenderConstructorErrors::BloxRouteMissingToken);
             }
-        };
-        Ok(sender)
-    }
+        }
+    };
+    Ok(sender)
 }
 
 /// Custom errors for the sender constructor
@@ -26,8 +26,9 @@ use tokio::{
 use tokio_util::sync::CancellationToken;
 
 use crate::{
-    bundle_sender::{SendBundleRequest, SendBundleResult},
-    server::{BuilderResult, BuilderServer, BuilderServerError, BundlingMode},
+    bundle_sender::{InclusionLatencies, SendBundleRequest, SendBundleResult, SenderActivity},
+    ledger::{BundleLedger, LedgerQuery, LedgerReport},
+    server::{BuilderResult, BuilderServer, BuilderServerError, BuilderStatus, BundlingMode},
 };
 
 /// Local builder server builder
@@ -55,18 +56,24 @@ impl LocalBuilderBuilder {
     }
 
     /// Run the local builder server, consuming the builder
-    pub fn run(
+    pub(crate) fn run(
         self,
         manual_bundling_mode: Arc<AtomicBool>,
+        inclusion_latencies: Arc<InclusionLatencies>,
+        sender_activity: Arc<SenderActivity>,
         send_bundle_requesters: Vec<mpsc::Sender<SendBundleRequest>>,
         entry_points: Vec<Address>,
+        ledger: Option<Arc<BundleLedger>>,
         shutdown_token: CancellationToken,
     ) -> JoinHandle<anyhow::Result<()>> {
         let mut runner = LocalBuilderServerRunner::new(
             self.req_receiver,
             manual_bundling_mode,
+            inclusion_latencies,
+            sender_activity,
             send_bundle_requesters,
             entry_points,
+            ledger,
         );
         tokio::spawn(async move { runner.run(shutdown_token).await })
     }
@@ -82,7 +89,10 @@ struct LocalBuilderServerRunner {
     req_receiver: mpsc::Receiver<ServerRequest>,
     send_bundle_requesters: Vec<mpsc::Sender<SendBundleRequest>>,
     manual_bundling_mode: Arc<AtomicBool>,
+    inclusion_latencies: Arc<InclusionLatencies>,
+    sender_activity: Arc<SenderActivity>,
     entry_points: Vec<Address>,
+    ledger: Option<Arc<BundleLedger>>,
 }
 
 impl LocalBuilderHandle {
@@ -113,6 +123,15 @@ impl BuilderServer for LocalBuilderHandle {
         }
     }
 
+    async fn get_inclusion_latencies(&self) -> BuilderResult<Vec<u64>> {
+        let req = ServerRequestKind::GetInclusionLatencies;
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetInclusionLatencies { latencies } => Ok(latencies),
+            _ => Err(BuilderServerError::UnexpectedResponse),
+        }
+    }
+
     async fn debug_send_bundle_now(&self) -> BuilderResult<(H256, u64)> {
         let req = ServerRequestKind::DebugSendBundleNow;
         let resp = self.send(req).await?;
@@ -130,6 +149,24 @@ impl BuilderServer for LocalBuilderHandle {
             _ => Err(BuilderServerError::UnexpectedResponse),
         }
     }
+
+    async fn get_status(&self) -> BuilderResult<BuilderStatus> {
+        let req = ServerRequestKind::GetStatus;
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetStatus { status } => Ok(status),
+            _ => Err(BuilderServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn get_ledger(&self, query: LedgerQuery) -> BuilderResult<LedgerReport> {
+        let req = ServerRequestKind::GetLedger { query };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetLedger { report } => Ok(report),
+            _ => Err(BuilderServerError::UnexpectedResponse),
+        }
+    }
 }
 
 #[async_trait]
@@ -151,14 +188,20 @@ impl LocalBuilderServerRunner {
     fn new(
         req_receiver: mpsc::Receiver<ServerRequest>,
         manual_bundling_mode: Arc<AtomicBool>,
+        inclusion_latencies: Arc<InclusionLatencies>,
+        sender_activity: Arc<SenderActivity>,
         send_bundle_requesters: Vec<mpsc::Sender<SendBundleRequest>>,
         entry_points: Vec<Address>,
+        ledger: Option<Arc<BundleLedger>>,
     ) -> Self {
         Self {
             req_receiver,
             manual_bundling_mode,
+            inclusion_latencies,
+            sender_activity,
             send_bundle_requesters,
             entry_points,
+            ledger,
         }
     }
 
@@ -176,6 +219,11 @@ impl LocalBuilderServerRunner {
                                     entry_points: self.entry_points.clone()
                                 })
                             },
+                            ServerRequestKind::GetInclusionLatencies => {
+                                Ok(ServerResponse::GetInclusionLatencies {
+                                    latencies: self.inclusion_latencies.get()
+                                })
+                            },
                             ServerRequestKind::DebugSendBundleNow => {
                                 if !self.manual_bundling_mode.load(Ordering::Relaxed) {
                                     break 'a Err(anyhow::anyhow!("bundling mode is not manual").into())
@@ -214,6 +262,28 @@ impl LocalBuilderServerRunner {
                                 self.manual_bundling_mode.store(mode == BundlingMode::Manual, Ordering::Relaxed);
                                 Ok(ServerResponse::DebugSetBundlingMode)
                             },
+                            ServerRequestKind::GetStatus => {
+                                let bundling_mode = if self.manual_bundling_mode.load(Ordering::Relaxed) {
+                                    BundlingMode::Manual
+                                } else {
+                                    BundlingMode::Auto
+                                };
+                                Ok(ServerResponse::GetStatus {
+                                    status: BuilderStatus {
+                                        bundling_mode,
+                                        senders: self.sender_activity.get(),
+                                    }
+                                })
+                            },
+                            ServerRequestKind::GetLedger { query } => {
+                                let Some(ledger) = &self.ledger else {
+                                    break 'a Err(anyhow::anyhow!("bundle ledger is not enabled").into())
+                                };
+                                match ledger.run_blocking(move |ledger| ledger.report(&query)).await {
+                                    Ok(report) => Ok(ServerResponse::GetLedger { report }),
+                                    Err(e) => Err(e.into()),
+                                }
+                            },
                         }
                     };
 
@@ -229,8 +299,11 @@ impl LocalBuilderServerRunner {
 #[derive(Clone, Debug)]
 enum ServerRequestKind {
     GetSupportedEntryPoints,
+    GetInclusionLatencies,
     DebugSendBundleNow,
     DebugSetBundlingMode { mode: BundlingMode },
+    GetStatus,
+    GetLedger { query: LedgerQuery },
 }
 
 #[derive(Debug)]
@@ -242,6 +315,9 @@ struct ServerRequest {
 #[derive(Clone, Debug)]
 enum ServerResponse {
     GetSupportedEntryPoints { entry_points: Vec<Address> },
+    GetInclusionLatencies { latencies: Vec<u64> },
     DebugSendBundleNow { hash: H256, block_number: u64 },
     DebugSetBundlingMode,
+    GetStatus { status: BuilderStatus },
+    GetLedger { report: LedgerReport },
 }
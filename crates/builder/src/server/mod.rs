@@ -24,6 +24,8 @@ pub(crate) use remote::spawn_remote_builder_server;
 pub use remote::RemoteBuilderClient;
 use serde::{Deserialize, Serialize};
 
+use crate::ledger::{LedgerQuery, LedgerReport};
+
 /// Builder server errors
 #[derive(Debug, thiserror::Error)]
 pub enum BuilderServerError {
@@ -45,6 +47,9 @@ pub trait BuilderServer: Send + Sync + 'static {
     /// Get the supported entry points of this builder
     async fn get_supported_entry_points(&self) -> BuilderResult<Vec<Address>>;
 
+    /// Get the inclusion latencies of recently mined bundles, in blocks, oldest first
+    async fn get_inclusion_latencies(&self) -> BuilderResult<Vec<u64>>;
+
     /// Trigger the builder to send a bundle now, used for debugging.
     ///
     /// Bundling mode must be set to `Manual`, or this will error
@@ -52,6 +57,39 @@ pub trait BuilderServer: Send + Sync + 'static {
 
     /// Set the bundling mode
     async fn debug_set_bundling_mode(&self, mode: BundlingMode) -> BuilderResult<()>;
+
+    /// Get the bundling mode and the recent activity of each bundle sender
+    async fn get_status(&self) -> BuilderResult<BuilderStatus>;
+
+    /// Get the mined bundles recorded in the ledger that match the query,
+    /// along with their totals.
+    ///
+    /// Errors if the builder does not keep a ledger
+    async fn get_ledger(&self, query: LedgerQuery) -> BuilderResult<LedgerReport>;
+}
+
+/// Status of the builder, used for health checks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BuilderStatus {
+    /// Current bundling mode
+    pub bundling_mode: BundlingMode,
+    /// Status of each bundle sender, by builder index
+    pub senders: Vec<BundleSenderStatus>,
+}
+
+/// Recent activity of a bundle sender
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BundleSenderStatus {
+    /// Index of the bundle sender
+    pub builder_index: u64,
+    /// Address of the sender's signer
+    pub signer: Address,
+    /// Unix time, in seconds, the sender last sent a bundle transaction
+    pub last_sent_at: Option<u64>,
+    /// Unix time, in seconds, a bundle from the sender was last mined
+    pub last_mined_at: Option<u64>,
+    /// Block number a bundle from the sender was last mined in
+    pub last_mined_block: Option<u64>,
 }
 
 /// Builder bundling mode
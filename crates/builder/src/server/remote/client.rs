@@ -17,6 +17,7 @@ use ethers::types::{Address, H256};
 use rundler_task::{
     grpc::protos::{from_bytes, ConversionError},
     server::{HealthCheck, ServerStatus},
+    trace::TracePropagation,
 };
 use tonic::{
     async_trait,
@@ -29,22 +30,27 @@ use tonic_health::{
 
 use super::protos::{
     builder_client::BuilderClient, debug_send_bundle_now_response,
-    debug_set_bundling_mode_response, BundlingMode as ProtoBundlingMode, DebugSendBundleNowRequest,
-    DebugSetBundlingModeRequest, GetSupportedEntryPointsRequest,
+    debug_set_bundling_mode_response, get_ledger_response, BundlingMode as ProtoBundlingMode,
+    DebugSendBundleNowRequest, DebugSetBundlingModeRequest, GetInclusionLatenciesRequest,
+    GetLedgerRequest, GetStatusRequest, GetSupportedEntryPointsRequest,
+};
+use crate::{
+    ledger::{LedgerQuery, LedgerReport},
+    server::{BuilderResult, BuilderServer, BuilderServerError, BuilderStatus, BundlingMode},
 };
-use crate::server::{BuilderResult, BuilderServer, BuilderServerError, BundlingMode};
 
 /// Remote builder client, used for communicating with a remote builder server
 #[derive(Debug, Clone)]
 pub struct RemoteBuilderClient {
-    grpc_client: BuilderClient<Channel>,
+    grpc_client: BuilderClient<TracePropagation<Channel>>,
     health_client: HealthClient<Channel>,
 }
 
 impl RemoteBuilderClient {
     /// Connect to a remote builder server
     pub async fn connect(url: String) -> anyhow::Result<Self> {
-        let grpc_client = BuilderClient::connect(url.clone()).await?;
+        let channel = Channel::builder(Uri::from_str(&url)?).connect().await?;
+        let grpc_client = BuilderClient::new(TracePropagation::new(channel));
         let health_client =
             HealthClient::new(Channel::builder(Uri::from_str(&url)?).connect().await?);
         Ok(Self {
@@ -69,6 +75,16 @@ impl BuilderServer for RemoteBuilderClient {
             .collect::<Result<_, ConversionError>>()?)
     }
 
+    async fn get_inclusion_latencies(&self) -> BuilderResult<Vec<u64>> {
+        Ok(self
+            .grpc_client
+            .clone()
+            .get_inclusion_latencies(GetInclusionLatenciesRequest {})
+            .await?
+            .into_inner()
+            .latencies)
+    }
+
     async fn debug_send_bundle_now(&self) -> BuilderResult<(H256, u64)> {
         let res = self
             .grpc_client
@@ -108,6 +124,34 @@ impl BuilderServer for RemoteBuilderClient {
             )))?,
         }
     }
+
+    async fn get_status(&self) -> BuilderResult<BuilderStatus> {
+        Ok(self
+            .grpc_client
+            .clone()
+            .get_status(GetStatusRequest {})
+            .await?
+            .into_inner()
+            .try_into()?)
+    }
+
+    async fn get_ledger(&self, query: LedgerQuery) -> BuilderResult<LedgerReport> {
+        let res = self
+            .grpc_client
+            .clone()
+            .get_ledger(GetLedgerRequest::from(query))
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_ledger_response::Result::Success(report)) => Ok(report.try_into()?),
+            Some(get_ledger_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(BuilderServerError::Other(anyhow::anyhow!(
+                "should have received result from builder"
+            )))?,
+        }
+    }
 }
 
 #[async_trait]
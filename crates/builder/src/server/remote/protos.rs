@@ -11,9 +11,18 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use rundler_task::grpc::protos::ConversionError;
+use rundler_task::grpc::protos::{from_bytes, to_le_bytes, ConversionError, FromProtoBytes};
 
-use crate::server::BundlingMode as RpcBundlingMode;
+use crate::{
+    ledger::{
+        LedgerEntry as RpcLedgerEntry, LedgerOp as RpcLedgerOp, LedgerQuery as RpcLedgerQuery,
+        LedgerReport as RpcLedgerReport, LedgerSummary as RpcLedgerSummary,
+    },
+    server::{
+        BuilderStatus as RpcBuilderStatus, BundleSenderStatus as RpcBundleSenderStatus,
+        BundlingMode as RpcBundlingMode,
+    },
+};
 
 tonic::include_proto!("builder");
 
@@ -52,3 +61,246 @@ impl TryFrom<i32> for RpcBundlingMode {
         }
     }
 }
+
+impl From<RpcBuilderStatus> for GetStatusResponse {
+    fn from(status: RpcBuilderStatus) -> Self {
+        Self {
+            bundling_mode: BundlingMode::from(status.bundling_mode) as i32,
+            senders: status.senders.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl TryFrom<GetStatusResponse> for RpcBuilderStatus {
+    type Error = ConversionError;
+
+    fn try_from(response: GetStatusResponse) -> Result<Self, Self::Error> {
+        Ok(Self {
+            bundling_mode: response.bundling_mode.try_into()?,
+            senders: response
+                .senders
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl From<RpcBundleSenderStatus> for BundleSenderStatus {
+    fn from(status: RpcBundleSenderStatus) -> Self {
+        Self {
+            builder_index: status.builder_index,
+            signer: status.signer.as_bytes().to_vec(),
+            last_sent_at: status.last_sent_at.unwrap_or_default(),
+            last_mined_at: status.last_mined_at.unwrap_or_default(),
+            last_mined_block: status.last_mined_block.unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<BundleSenderStatus> for RpcBundleSenderStatus {
+    type Error = ConversionError;
+
+    fn try_from(status: BundleSenderStatus) -> Result<Self, Self::Error> {
+        let non_zero = |v: u64| (v != 0).then_some(v);
+        Ok(Self {
+            builder_index: status.builder_index,
+            signer: from_bytes(&status.signer)?,
+            last_sent_at: non_zero(status.last_sent_at),
+            last_mined_at: non_zero(status.last_mined_at),
+            last_mined_block: non_zero(status.last_mined_block),
+        })
+    }
+}
+
+impl From<RpcLedgerQuery> for GetLedgerRequest {
+    fn from(query: RpcLedgerQuery) -> Self {
+        Self {
+            from_timestamp: query.from_timestamp.unwrap_or_default(),
+            to_timestamp: query.to_timestamp.unwrap_or_default(),
+            builder_index: query
+                .builder_index
+                .map(|builder_index| BuilderIndexFilter { builder_index }),
+            paymaster: query
+                .paymaster
+                .map(|paymaster| paymaster.as_bytes().to_vec())
+                .unwrap_or_default(),
+            limit: query.limit.unwrap_or_default(),
+            cursor: query
+                .cursor
+                .map(|cursor| cursor.to_vec())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<GetLedgerRequest> for RpcLedgerQuery {
+    type Error = ConversionError;
+
+    fn try_from(request: GetLedgerRequest) -> Result<Self, Self::Error> {
+        let non_zero = |v: u64| (v != 0).then_some(v);
+        Ok(Self {
+            from_timestamp: non_zero(request.from_timestamp),
+            to_timestamp: non_zero(request.to_timestamp),
+            builder_index: request.builder_index.map(|filter| filter.builder_index),
+            paymaster: non_empty_bytes(&request.paymaster)?,
+            limit: non_zero(request.limit),
+            cursor: (!request.cursor.is_empty()).then(|| request.cursor.into()),
+        })
+    }
+}
+
+impl From<RpcLedgerReport> for LedgerReport {
+    fn from(report: RpcLedgerReport) -> Self {
+        Self {
+            summary: Some(report.summary.into()),
+            bundles: report.bundles.into_iter().map(Into::into).collect(),
+            next_cursor: report
+                .next_cursor
+                .map(|cursor| cursor.to_vec())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<LedgerReport> for RpcLedgerReport {
+    type Error = ConversionError;
+
+    fn try_from(report: LedgerReport) -> Result<Self, Self::Error> {
+        Ok(Self {
+            summary: report
+                .summary
+                .map(TryInto::try_into)
+                .transpose()?
+                .unwrap_or_default(),
+            bundles: report
+                .bundles
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            next_cursor: (!report.next_cursor.is_empty()).then(|| report.next_cursor.into()),
+        })
+    }
+}
+
+impl From<RpcLedgerSummary> for LedgerSummary {
+    fn from(summary: RpcLedgerSummary) -> Self {
+        Self {
+            bundles: summary.bundles,
+            ops: summary.ops,
+            gas_cost: to_le_bytes(summary.gas_cost),
+            l1_fee: to_le_bytes(summary.l1_fee),
+            op_revenue: to_le_bytes(summary.op_revenue),
+            profit: summary.profit,
+            beneficiary_balance_delta: summary.beneficiary_balance_delta,
+        }
+    }
+}
+
+impl TryFrom<LedgerSummary> for RpcLedgerSummary {
+    type Error = ConversionError;
+
+    fn try_from(summary: LedgerSummary) -> Result<Self, Self::Error> {
+        Ok(Self {
+            bundles: summary.bundles,
+            ops: summary.ops,
+            gas_cost: from_bytes(&summary.gas_cost)?,
+            l1_fee: from_bytes(&summary.l1_fee)?,
+            op_revenue: from_bytes(&summary.op_revenue)?,
+            profit: summary.profit,
+            beneficiary_balance_delta: summary.beneficiary_balance_delta,
+        })
+    }
+}
+
+impl From<RpcLedgerEntry> for LedgerEntry {
+    fn from(entry: RpcLedgerEntry) -> Self {
+        Self {
+            tx_hash: entry.tx_hash.as_bytes().to_vec(),
+            entry_point: entry.entry_point.as_bytes().to_vec(),
+            builder_index: entry.builder_index,
+            beneficiary: entry.beneficiary.as_bytes().to_vec(),
+            block_number: entry.block_number,
+            block_hash: entry.block_hash.as_bytes().to_vec(),
+            block_timestamp: entry.block_timestamp,
+            gas_used: to_le_bytes(entry.gas_used),
+            effective_gas_price: to_le_bytes(entry.effective_gas_price),
+            l1_fee: to_le_bytes(entry.l1_fee),
+            beneficiary_balance_before: to_le_bytes(entry.beneficiary_balance_before),
+            beneficiary_balance_after: to_le_bytes(entry.beneficiary_balance_after),
+            ops: entry.ops.into_iter().map(Into::into).collect(),
+            allocated_gas_cost: entry
+                .allocated_gas_cost
+                .map(to_le_bytes)
+                .unwrap_or_default(),
+            allocated_l1_fee: entry.allocated_l1_fee.map(to_le_bytes).unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<LedgerEntry> for RpcLedgerEntry {
+    type Error = ConversionError;
+
+    fn try_from(entry: LedgerEntry) -> Result<Self, Self::Error> {
+        Ok(Self {
+            tx_hash: from_bytes(&entry.tx_hash)?,
+            entry_point: from_bytes(&entry.entry_point)?,
+            builder_index: entry.builder_index,
+            beneficiary: from_bytes(&entry.beneficiary)?,
+            block_number: entry.block_number,
+            block_hash: from_bytes(&entry.block_hash)?,
+            block_timestamp: entry.block_timestamp,
+            gas_used: from_bytes(&entry.gas_used)?,
+            effective_gas_price: from_bytes(&entry.effective_gas_price)?,
+            l1_fee: from_bytes(&entry.l1_fee)?,
+            beneficiary_balance_before: from_bytes(&entry.beneficiary_balance_before)?,
+            beneficiary_balance_after: from_bytes(&entry.beneficiary_balance_after)?,
+            ops: entry
+                .ops
+                .into_iter()
+                .map(TryInto::try_into)
+                .collect::<Result<_, _>>()?,
+            allocated_gas_cost: non_empty_bytes(&entry.allocated_gas_cost)?,
+            allocated_l1_fee: non_empty_bytes(&entry.allocated_l1_fee)?,
+        })
+    }
+}
+
+impl From<RpcLedgerOp> for LedgerOp {
+    fn from(op: RpcLedgerOp) -> Self {
+        Self {
+            hash: op.hash.as_bytes().to_vec(),
+            sender: op.sender.as_bytes().to_vec(),
+            paymaster: op
+                .paymaster
+                .map(|paymaster| paymaster.as_bytes().to_vec())
+                .unwrap_or_default(),
+            success: op.success,
+            actual_gas_cost: to_le_bytes(op.actual_gas_cost),
+            actual_gas_used: to_le_bytes(op.actual_gas_used),
+        }
+    }
+}
+
+impl TryFrom<LedgerOp> for RpcLedgerOp {
+    type Error = ConversionError;
+
+    fn try_from(op: LedgerOp) -> Result<Self, Self::Error> {
+        Ok(Self {
+            hash: from_bytes(&op.hash)?,
+            sender: from_bytes(&op.sender)?,
+            paymaster: non_empty_bytes(&op.paymaster)?,
+            success: op.success,
+            actual_gas_cost: from_bytes(&op.actual_gas_cost)?,
+            actual_gas_used: from_bytes(&op.actual_gas_used)?,
+        })
+    }
+}
+
+fn non_empty_bytes<T: FromProtoBytes>(bytes: &[u8]) -> Result<Option<T>, ConversionError> {
+    if bytes.is_empty() {
+        Ok(None)
+    } else {
+        from_bytes(bytes).map(Some)
+    }
+}
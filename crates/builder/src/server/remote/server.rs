@@ -13,19 +13,23 @@
 
 use std::net::SocketAddr;
 
+use rundler_task::trace::TraceContextLayer;
 use tokio::task::JoinHandle;
 use tokio_util::sync::CancellationToken;
 use tonic::{async_trait, transport::Server, Request, Response, Status};
 
 use super::protos::{
     builder_server::{Builder as GrpcBuilder, BuilderServer as GrpcBuilderServer},
-    debug_send_bundle_now_response, debug_set_bundling_mode_response, DebugSendBundleNowRequest,
-    DebugSendBundleNowResponse, DebugSetBundlingModeRequest, DebugSetBundlingModeResponse,
-    DebugSetBundlingModeSuccess, GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse,
+    debug_send_bundle_now_response, debug_set_bundling_mode_response, get_ledger_response,
+    DebugSendBundleNowRequest, DebugSendBundleNowResponse, DebugSetBundlingModeRequest,
+    DebugSetBundlingModeResponse, DebugSetBundlingModeSuccess, GetInclusionLatenciesRequest,
+    GetInclusionLatenciesResponse, GetLedgerRequest, GetLedgerResponse, GetStatusRequest,
+    GetStatusResponse, GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse,
     BUILDER_FILE_DESCRIPTOR_SET,
 };
-use crate::server::{
-    local::LocalBuilderHandle, remote::protos::DebugSendBundleNowSuccess, BuilderServer,
+use crate::{
+    ledger::LedgerQuery,
+    server::{local::LocalBuilderHandle, remote::protos::DebugSendBundleNowSuccess, BuilderServer},
 };
 
 /// Spawn a remote builder server
@@ -51,6 +55,7 @@ pub(crate) async fn spawn_remote_builder_server(
 
     Ok(tokio::spawn(async move {
         Server::builder()
+            .layer(TraceContextLayer::new("builder"))
             .add_service(builder_server)
             .add_service(reflection_service)
             .add_service(health_service)
@@ -97,6 +102,22 @@ impl GrpcBuilder for GrpcBuilderServerImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_inclusion_latencies(
+        &self,
+        _request: Request<GetInclusionLatenciesRequest>,
+    ) -> tonic::Result<Response<GetInclusionLatenciesResponse>> {
+        let resp = match self.local_builder.get_inclusion_latencies().await {
+            Ok(latencies) => GetInclusionLatenciesResponse { latencies },
+            Err(e) => {
+                return Err(Status::internal(format!(
+                    "Failed to get inclusion latencies: {e}"
+                )));
+            }
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn debug_send_bundle_now(
         &self,
         _request: Request<DebugSendBundleNowRequest>,
@@ -143,4 +164,38 @@ impl GrpcBuilder for GrpcBuilderServerImpl {
 
         Ok(Response::new(resp))
     }
+
+    async fn get_status(
+        &self,
+        _request: Request<GetStatusRequest>,
+    ) -> tonic::Result<Response<GetStatusResponse>> {
+        let resp = match self.local_builder.get_status().await {
+            Ok(status) => status.into(),
+            Err(e) => {
+                return Err(Status::internal(format!("Failed to get status: {e}")));
+            }
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn get_ledger(
+        &self,
+        request: Request<GetLedgerRequest>,
+    ) -> tonic::Result<Response<GetLedgerResponse>> {
+        let query: LedgerQuery = request.into_inner().try_into().map_err(|e| {
+            Status::internal(format!("Failed to convert from proto ledger query {e}"))
+        })?;
+
+        let resp = match self.local_builder.get_ledger(query).await {
+            Ok(report) => GetLedgerResponse {
+                result: Some(get_ledger_response::Result::Success(report.into())),
+            },
+            Err(e) => GetLedgerResponse {
+                result: Some(get_ledger_response::Result::Failure(e.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
 }
@@ -12,7 +12,6 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use std::{
-    collections::HashMap,
     net::SocketAddr,
     sync::{atomic::AtomicBool, Arc},
     time::Duration,
@@ -22,18 +21,19 @@ use anyhow::{bail, Context};
 use async_trait::async_trait;
 use ethers::{
     providers::{JsonRpcClient, Provider},
-    types::{Address, H256},
+    types::Address,
 };
 use ethers_signers::Signer;
 use futures::future;
 use futures_util::TryFutureExt;
 use rundler_pool::PoolServer;
 use rundler_sim::{
-    MempoolConfig, PriorityFeeMode, SimulateValidationTracerImpl, SimulationSettings, SimulatorImpl,
+    gas, PriorityFeeMode, SharedMempoolConfigs, SimulateValidationTracerImpl, SimulationCache,
+    SimulationSettings, SimulatorImpl,
 };
 use rundler_task::Task;
-use rundler_types::contracts::i_entry_point::IEntryPoint;
-use rundler_utils::{emit::WithEntryPoint, eth, handle};
+use rundler_types::{chain::ChainSpec, contracts::i_entry_point::IEntryPoint};
+use rundler_utils::{emit::WithEntryPoint, eth, handle, revert::AbiRegistry};
 use rusoto_core::Region;
 use tokio::{
     sync::{broadcast, mpsc},
@@ -45,9 +45,12 @@ use tracing::info;
 
 use crate::{
     bundle_proposer::{self, BundleProposerImpl},
-    bundle_sender::{self, BundleSender, BundleSenderImpl, SendBundleRequest},
+    bundle_sender::{
+        self, BundleSender, BundleSenderImpl, InclusionLatencies, SendBundleRequest, SenderActivity,
+    },
     emit::BuilderEvent,
-    sender::TransactionSenderType,
+    ledger::{self, BundleLedger},
+    sender::{self, TransactionSenderType},
     server::{spawn_remote_builder_server, LocalBuilderBuilder},
     signer::{BundlerSigner, KmsSigner, LocalSigner},
     transaction_tracker::{self, TransactionTrackerImpl},
@@ -72,12 +75,10 @@ pub struct Args {
     pub redis_uri: String,
     /// Redis lease TTL in milliseconds
     pub redis_lock_ttl_millis: u64,
-    /// Chain ID
-    pub chain_id: u64,
+    /// Chain specification
+    pub chain_spec: ChainSpec,
     /// Maximum bundle size in number of operations
     pub max_bundle_size: u64,
-    /// Maximum bundle size in gas limit
-    pub max_bundle_gas: u64,
     /// URL to submit bundles too
     pub submit_url: String,
     /// Percentage to add to the the network priority fee for the bundle priority fee
@@ -91,7 +92,7 @@ pub struct Args {
     /// Operation simulation settings
     pub sim_settings: SimulationSettings,
     /// Alt-mempool configs
-    pub mempool_configs: HashMap<H256, MempoolConfig>,
+    pub mempool_configs: SharedMempoolConfigs,
     /// Maximum number of blocks to wait for a transaction to be mined
     pub max_blocks_to_wait_for_mine: u64,
     /// Percentage to increase the fees by when replacing a bundle transaction
@@ -110,6 +111,15 @@ pub struct Args {
     pub num_bundle_builders: u64,
     /// Index offset for bundle builders
     pub bundle_builder_index_offset: u64,
+    /// Cache of simulation results shared with the pool, if enabled.
+    ///
+    /// Only set when running in the same process as the pool, which keeps the
+    /// cache up to date with the chain.
+    pub simulation_cache: Option<Arc<SimulationCache>>,
+    /// Ledger to record the costs and revenue of mined bundles in, if enabled.
+    pub ledger: Option<Arc<BundleLedger>>,
+    /// Registry of errors to decode unexpected reverts of rejected operations with
+    pub abi_registry: Arc<AbiRegistry>,
 }
 
 /// Builder task
@@ -127,10 +137,13 @@ where
     P: PoolServer + Clone,
 {
     async fn run(mut self: Box<Self>, shutdown_token: CancellationToken) -> anyhow::Result<()> {
-        info!("Mempool config: {:?}", self.args.mempool_configs);
+        info!("Mempool config: {:?}", self.args.mempool_configs.configs());
 
         let provider = eth::new_provider(&self.args.rpc_url, Some(self.args.eth_poll_interval))?;
+        gas::check_l1_gas_oracle(self.args.chain_spec.l1_gas, &*provider).await?;
         let manual_bundling_mode = Arc::new(AtomicBool::new(false));
+        let inclusion_latencies = Arc::new(InclusionLatencies::default());
+        let sender_activity = Arc::new(SenderActivity::default());
 
         let mut sender_handles = vec![];
         let mut send_bundle_txs = vec![];
@@ -139,6 +152,8 @@ where
                 .create_bundle_builder(
                     i + self.args.bundle_builder_index_offset,
                     Arc::clone(&manual_bundling_mode),
+                    Arc::clone(&inclusion_latencies),
+                    Arc::clone(&sender_activity),
                     Arc::clone(&provider),
                 )
                 .await?;
@@ -155,16 +170,31 @@ where
         let builder_handle = self.builder_builder.get_handle();
         let builder_runnder_handle = self.builder_builder.run(
             manual_bundling_mode,
+            inclusion_latencies,
+            sender_activity,
             send_bundle_txs,
             vec![self.args.entry_point_address],
+            self.args.ledger.clone(),
             shutdown_token.clone(),
         );
 
+        let ledger_handle = match &self.args.ledger {
+            Some(ledger) => tokio::spawn(ledger::record_mined_bundles(
+                Arc::clone(ledger),
+                Arc::clone(&provider),
+                self.event_sender.subscribe(),
+                self.args.chain_spec.chain_history_size,
+                self.args.eth_poll_interval,
+                shutdown_token.clone(),
+            )),
+            None => tokio::spawn(async { Ok(()) }),
+        };
+
         let remote_handle = match self.args.remote_address {
             Some(addr) => {
                 spawn_remote_builder_server(
                     addr,
-                    self.args.chain_id,
+                    self.args.chain_spec.id,
                     builder_handle,
                     shutdown_token,
                 )
@@ -179,6 +209,7 @@ where
             handle::flatten_handle(sender_handle),
             handle::flatten_handle(builder_runnder_handle),
             handle::flatten_handle(remote_handle),
+            handle::flatten_handle(ledger_handle),
         ) {
             Ok(_) => {
                 info!("Builder server shutdown");
@@ -220,6 +251,8 @@ where
         &self,
         index: u64,
         manual_bundling_mode: Arc<AtomicBool>,
+        inclusion_latencies: Arc<InclusionLatencies>,
+        sender_activity: Arc<SenderActivity>,
         provider: Arc<Provider<C>>,
     ) -> anyhow::Result<(
         JoinHandle<anyhow::Result<()>>,
@@ -230,8 +263,12 @@ where
         let signer = if let Some(pk) = &self.args.private_key {
             info!("Using local signer");
             BundlerSigner::Local(
-                LocalSigner::connect(Arc::clone(&provider), self.args.chain_id, pk.to_owned())
-                    .await?,
+                LocalSigner::connect(
+                    Arc::clone(&provider),
+                    self.args.chain_spec.id,
+                    pk.to_owned(),
+                )
+                .await?,
             )
         } else {
             info!("Using AWS KMS signer");
@@ -243,7 +280,7 @@ where
                 Duration::from_millis(self.args.redis_lock_ttl_millis / 10),
                 KmsSigner::connect(
                     Arc::clone(&provider),
-                    self.args.chain_id,
+                    self.args.chain_spec.id,
                     self.args.aws_kms_region.clone(),
                     self.args.aws_kms_key_ids.clone(),
                     self.args.redis_uri.clone(),
@@ -259,9 +296,8 @@ where
         };
         let beneficiary = signer.address();
         let proposer_settings = bundle_proposer::Settings {
-            chain_id: self.args.chain_id,
+            chain_spec: self.args.chain_spec.clone(),
             max_bundle_size: self.args.max_bundle_size,
-            max_bundle_gas: self.args.max_bundle_gas,
             beneficiary,
             priority_fee_mode: self.args.priority_fee_mode,
             bundle_priority_fee_overhead_percent: self.args.bundle_priority_fee_overhead_percent,
@@ -270,21 +306,27 @@ where
         let entry_point = IEntryPoint::new(self.args.entry_point_address, Arc::clone(&provider));
         let simulate_validation_tracer =
             SimulateValidationTracerImpl::new(Arc::clone(&provider), entry_point.clone());
-        let simulator = SimulatorImpl::new(
+        let mut simulator = SimulatorImpl::new(
             Arc::clone(&provider),
             entry_point.address(),
             simulate_validation_tracer,
             self.args.sim_settings,
             self.args.mempool_configs.clone(),
-        );
+        )
+        .with_allowed_precompiles(&self.args.chain_spec.precompile_addresses)
+        .with_abi_registry(Arc::clone(&self.args.abi_registry));
+        if let Some(cache) = &self.args.simulation_cache {
+            simulator = simulator.with_cache(Arc::clone(cache));
+        }
 
         let submit_provider =
             eth::new_provider(&self.args.submit_url, Some(self.args.eth_poll_interval))?;
 
-        let transaction_sender = self.args.sender_type.into_sender(
+        let transaction_sender = sender::new_sender(
+            self.args.sender_type,
             submit_provider,
             signer,
-            self.args.chain_id,
+            &self.args.chain_spec,
             self.args.eth_poll_interval,
             &self.args.bloxroute_auth_header,
         )?;
@@ -305,6 +347,7 @@ where
         let builder_settings = bundle_sender::Settings {
             replacement_fee_percent_increase: self.args.replacement_fee_percent_increase,
             max_fee_increases: self.args.max_fee_increases,
+            eip1559_enabled: self.args.chain_spec.eip1559_enabled,
         };
 
         let proposer = BundleProposerImpl::new(
@@ -315,12 +358,13 @@ where
             Arc::clone(&provider),
             proposer_settings,
             self.event_sender.clone(),
-        );
+        )
+        .with_abi_registry(Arc::clone(&self.args.abi_registry));
         let builder = BundleSenderImpl::new(
             index,
             manual_bundling_mode.clone(),
             send_bundle_rx,
-            self.args.chain_id,
+            self.args.chain_spec.id,
             beneficiary,
             proposer,
             entry_point,
@@ -328,6 +372,8 @@ where
             self.pool.clone(),
             builder_settings,
             self.event_sender.clone(),
+            inclusion_latencies,
+            sender_activity,
         );
 
         // Spawn each sender as its own independent task
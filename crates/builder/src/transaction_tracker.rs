@@ -456,7 +456,9 @@ impl TransactionTrackerMetrics {
 mod tests {
     use std::sync::Arc;
 
-    use ethers::types::{Address, Eip1559TransactionRequest, Transaction, TransactionReceipt};
+    use ethers::types::{
+        Address, Eip1559TransactionRequest, Transaction, TransactionReceipt, TransactionRequest,
+    };
     use mockall::Sequence;
     use rundler_provider::MockProvider;
 
@@ -529,6 +531,53 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_legacy_replacement_fees() {
+        let (mut sender, mut provider) = create_base_config();
+        sender.expect_address().return_const(Address::zero());
+        sender.expect_send_transaction().returning(move |_a, _b| {
+            Box::pin(async {
+                Ok(SentTxInfo {
+                    nonce: U256::from(0),
+                    tx_hash: H256::zero(),
+                })
+            })
+        });
+
+        provider
+            .expect_get_transaction_count()
+            .returning(move |_a| Ok(U256::from(0)));
+
+        let tracker = create_tracker(sender, provider).await;
+
+        let tx = TransactionRequest::new()
+            .nonce(0)
+            .gas(10000)
+            .gas_price(10000);
+        let exp = ExpectedStorage::default();
+        tracker.send_transaction(tx.into(), &exp).await.unwrap();
+
+        // a replacement must increase the gas price
+        assert_eq!(
+            tracker.get_nonce_and_required_fees().unwrap(),
+            (
+                U256::from(0),
+                Some(GasFees {
+                    max_fee_per_gas: U256::from(10500),
+                    max_priority_fee_per_gas: U256::zero(),
+                })
+            )
+        );
+        let underpriced = TransactionRequest::new()
+            .nonce(0)
+            .gas(10000)
+            .gas_price(10400);
+        assert!(tracker
+            .send_transaction(underpriced.into(), &exp)
+            .await
+            .is_err());
+    }
+
     // TODO(#295): fix dropped status
     // #[tokio::test]
     // async fn test_nonce_and_fees_dropped() {
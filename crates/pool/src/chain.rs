@@ -24,7 +24,7 @@ use ethers::{
     types::{Address, Block, Filter, Log, H256, U256},
 };
 use futures::future;
-use rundler_provider::Provider;
+use rundler_provider::{Provider, StateDiff};
 use rundler_task::block_watcher;
 use rundler_types::{
     contracts::{
@@ -77,9 +77,18 @@ pub struct ChainUpdate {
     /// Boolean to state if the most recent chain update had a reorg
     /// that was larger than the existing history that has been tracked
     pub reorg_larger_than_history: bool,
+    /// Accounts and storage slots changed by the blocks added in this update.
+    /// Only set if state diff tracking is enabled, and not set when the chain
+    /// history is reset.
+    pub state_diff: Option<StateDiff>,
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// A subscription to the chain updates of a pool task, for following the
+/// chain without watching it separately.
+#[derive(Debug)]
+pub struct ChainSubscription(pub(crate) broadcast::Receiver<Arc<ChainUpdate>>);
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct MinedOp {
     pub hash: H256,
     pub entry_point: Address,
@@ -87,6 +96,13 @@ pub struct MinedOp {
     pub nonce: U256,
     pub actual_gas_cost: U256,
     pub paymaster: Option<Address>,
+    pub actual_gas_used: U256,
+    pub success: bool,
+    /// Where the op's `UserOperationEvent` was emitted
+    pub block_number: u64,
+    pub block_hash: H256,
+    pub transaction_hash: H256,
+    pub log_index: u64,
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -104,6 +120,31 @@ impl MinedOp {
             nonce: self.nonce,
         }
     }
+
+    /// Parses a mined op from a `UserOperationEvent` log, returning `None` for
+    /// other logs.
+    pub(crate) fn from_log(log: &Log) -> Option<Self> {
+        let event = contract::parse_log::<UserOperationEventFilter>(log.clone()).ok()?;
+        let paymaster = if event.paymaster.is_zero() {
+            None
+        } else {
+            Some(event.paymaster)
+        };
+        Some(Self {
+            hash: event.user_op_hash.into(),
+            entry_point: log.address,
+            sender: event.sender,
+            nonce: event.nonce,
+            actual_gas_cost: event.actual_gas_cost,
+            paymaster,
+            actual_gas_used: event.actual_gas_used,
+            success: event.success,
+            block_number: log.block_number.unwrap_or_default().as_u64(),
+            block_hash: log.block_hash.unwrap_or_default(),
+            transaction_hash: log.transaction_hash.unwrap_or_default(),
+            log_index: log.log_index.unwrap_or_default().as_u64(),
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -111,6 +152,8 @@ pub(crate) struct Settings {
     pub(crate) history_size: u64,
     pub(crate) poll_interval: Duration,
     pub(crate) entry_point_addresses: Vec<Address>,
+    /// Whether to load the state diff of each new block
+    pub(crate) track_state_diffs: bool,
 }
 
 #[derive(Debug)]
@@ -121,6 +164,7 @@ struct BlockSummary {
     parent_hash: H256,
     ops: Vec<MinedOp>,
     entity_balance_updates: Vec<BalanceUpdate>,
+    state_diff: StateDiff,
 }
 
 impl<P: Provider> Chain<P> {
@@ -230,7 +274,15 @@ impl<P: Provider> Chain<P> {
             .copied()
             .collect();
 
-        Ok(self.new_update(0, mined_ops, vec![], entity_balance_updates, vec![], false))
+        Ok(self.new_update(
+            0,
+            mined_ops,
+            vec![],
+            entity_balance_updates,
+            vec![],
+            false,
+            None,
+        ))
     }
 
     /// Given a collection of blocks to add to the chain, whose numbers may
@@ -253,6 +305,14 @@ impl<P: Provider> Chain<P> {
             .copied()
             .collect();
 
+        let state_diff = self.settings.track_state_diffs.then(|| {
+            let mut state_diff = StateDiff::default();
+            for block in &added_blocks {
+                state_diff.extend(block.state_diff.clone());
+            }
+            state_diff
+        });
+
         let reorg_depth = current_block_number + 1 - added_blocks[0].number;
         let unmined_ops: Vec<_> = self
             .blocks
@@ -293,6 +353,7 @@ impl<P: Provider> Chain<P> {
             entity_balance_updates,
             unmined_entity_balance_updates,
             is_reorg_larger_than_history,
+            state_diff,
         )
     }
 
@@ -344,6 +405,10 @@ impl<P: Provider> Chain<P> {
         }
         self.load_ops_into_block_summaries(&mut added_blocks)
             .await?;
+        if self.settings.track_state_diffs {
+            self.load_state_diffs_into_block_summaries(&mut added_blocks)
+                .await?;
+        }
         Ok(added_blocks)
     }
 
@@ -392,6 +457,27 @@ impl<P: Provider> Chain<P> {
         Ok(())
     }
 
+    async fn load_state_diffs_into_block_summaries(
+        &self,
+        blocks: &mut VecDeque<BlockSummary>,
+    ) -> anyhow::Result<()> {
+        let future_diffs = blocks.iter().map(|block| async move {
+            let _permit = self
+                .load_ops_semaphore
+                .acquire()
+                .await
+                .expect("semaphore should not be closed");
+            self.provider.get_block_state_diff(block.hash).await
+        });
+        let diffs = future::try_join_all(future_diffs)
+            .await
+            .context("should load state diffs for new blocks")?;
+        for (block, diff) in blocks.iter_mut().zip(diffs) {
+            block.state_diff = diff;
+        }
+        Ok(())
+    }
+
     async fn load_ops_in_block_with_hash(
         &self,
         block_hash: H256,
@@ -422,31 +508,8 @@ impl<P: Provider> Chain<P> {
         Ok((mined_ops, entity_balance_updates))
     }
 
-    fn load_mined_ops(&self, logs: &Vec<Log>) -> Vec<MinedOp> {
-        let mut mined_ops = vec![];
-        for log in logs {
-            let entry_point = log.address;
-            if let Ok(event) = contract::parse_log::<UserOperationEventFilter>(log.clone()) {
-                let paymaster = if event.paymaster.is_zero() {
-                    None
-                } else {
-                    Some(event.paymaster)
-                };
-
-                let mined = MinedOp {
-                    hash: event.user_op_hash.into(),
-                    entry_point,
-                    sender: event.sender,
-                    nonce: event.nonce,
-                    actual_gas_cost: event.actual_gas_cost,
-                    paymaster,
-                };
-
-                mined_ops.push(mined);
-            }
-        }
-
-        mined_ops
+    fn load_mined_ops(&self, logs: &[Log]) -> Vec<MinedOp> {
+        logs.iter().filter_map(MinedOp::from_log).collect()
     }
 
     fn load_entity_balance_updates(&self, logs: &Vec<Log>) -> Vec<BalanceUpdate> {
@@ -497,6 +560,7 @@ impl<P: Provider> Chain<P> {
         entity_balance_updates: Vec<BalanceUpdate>,
         unmined_entity_balance_updates: Vec<BalanceUpdate>,
         reorg_larger_than_history: bool,
+        state_diff: Option<StateDiff>,
     ) -> ChainUpdate {
         let latest_block = self
             .blocks
@@ -513,6 +577,7 @@ impl<P: Provider> Chain<P> {
             entity_balance_updates,
             unmined_entity_balance_updates,
             reorg_larger_than_history,
+            state_diff,
         }
     }
 }
@@ -548,6 +613,7 @@ impl BlockSummary {
             parent_hash: block.parent_hash,
             ops: Vec::new(),
             entity_balance_updates: Vec::new(),
+            state_diff: StateDiff::default(),
         })
     }
 }
@@ -580,6 +646,21 @@ impl ChainUpdate {
             unmined_ops,
         }
     }
+
+    /// Addresses whose entry point state changed in the added blocks: the
+    /// senders and paymasters of mined ops, and the accounts whose deposits changed.
+    pub fn touched_entities(&self) -> HashSet<Address> {
+        self.mined_ops
+            .iter()
+            .flat_map(|op| [Some(op.sender), op.paymaster])
+            .flatten()
+            .chain(
+                self.entity_balance_updates
+                    .iter()
+                    .map(|update| update.address),
+            )
+            .collect()
+    }
 }
 
 struct ChainMetrics {}
@@ -727,6 +808,7 @@ mod tests {
                 entity_balance_updates: vec![],
                 unmined_entity_balance_updates: vec![],
                 reorg_larger_than_history: false,
+                state_diff: None,
             }
         );
     }
@@ -758,6 +840,7 @@ mod tests {
                 entity_balance_updates: vec![],
                 unmined_entity_balance_updates: vec![],
                 reorg_larger_than_history: false,
+                state_diff: None,
             }
         );
     }
@@ -803,6 +886,7 @@ mod tests {
                     fake_mined_balance_update(addr(1), 0.into(), false),
                 ],
                 reorg_larger_than_history: false,
+                state_diff: None,
             }
         );
     }
@@ -843,6 +927,7 @@ mod tests {
                     fake_mined_balance_update(addr(9), 0.into(), false),
                 ],
                 reorg_larger_than_history: false,
+                state_diff: None,
             }
         );
     }
@@ -882,6 +967,7 @@ mod tests {
                 unmined_ops: vec![fake_mined_op(101), fake_mined_op(102)],
                 unmined_entity_balance_updates: vec![],
                 reorg_larger_than_history: false,
+                state_diff: None,
             }
         );
     }
@@ -917,6 +1003,7 @@ mod tests {
                 entity_balance_updates: vec![],
                 unmined_entity_balance_updates: vec![],
                 reorg_larger_than_history: true,
+                state_diff: None,
             }
         );
     }
@@ -955,6 +1042,7 @@ mod tests {
                 mined_ops: vec![fake_mined_op(104), fake_mined_op(105), fake_mined_op(106)],
                 unmined_ops: vec![],
                 reorg_larger_than_history: false,
+                state_diff: None,
             }
         );
     }
@@ -982,10 +1070,43 @@ mod tests {
                 entity_balance_updates: vec![],
                 unmined_entity_balance_updates: vec![],
                 reorg_larger_than_history: false,
+                state_diff: None,
             }
         );
     }
 
+    #[tokio::test]
+    async fn test_state_diff_tracking() {
+        let (provider, controller) = new_mock_provider();
+        let mut chain = Chain::new(
+            Arc::new(provider),
+            Settings {
+                history_size: HISTORY_SIZE,
+                poll_interval: Duration::from_secs(250), // Not used in tests.
+                entry_point_addresses: vec![ENTRY_POINT_ADDRESS],
+                track_state_diffs: true,
+            },
+        );
+        controller.set_blocks(vec![
+            MockBlock::new(hash(0), vec![], vec![], vec![]),
+            MockBlock::new(hash(1), vec![], vec![], vec![]),
+        ]);
+        // the history is unknown when initializing
+        let update = chain.sync_to_block(controller.get_head()).await.unwrap();
+        assert_eq!(update.state_diff, None);
+
+        {
+            let mut blocks = controller.get_blocks_mut();
+            blocks.push(MockBlock::new(hash(2), vec![], vec![], vec![]));
+            blocks.push(MockBlock::new(hash(3), vec![], vec![], vec![]));
+        }
+        let update = chain.sync_to_block(controller.get_head()).await.unwrap();
+        assert_eq!(
+            update.state_diff.unwrap().accounts,
+            HashSet::from([touched_address(hash(2)), touched_address(hash(3))])
+        );
+    }
+
     fn new_chain() -> (Chain<impl Provider>, ProviderController) {
         let (provider, controller) = new_mock_provider();
         let chain = Chain::new(
@@ -994,6 +1115,7 @@ mod tests {
                 history_size: HISTORY_SIZE,
                 poll_interval: Duration::from_secs(250), // Not used in tests.
                 entry_point_addresses: vec![ENTRY_POINT_ADDRESS],
+                track_state_diffs: false,
             },
         );
         (chain, controller)
@@ -1010,6 +1132,15 @@ mod tests {
             move |hash| Ok(controller.get_block_by_hash(hash))
         });
 
+        provider
+            .expect_get_block_state_diff()
+            .returning(|block_hash| {
+                Ok(StateDiff {
+                    accounts: HashSet::from([touched_address(block_hash)]),
+                    ..Default::default()
+                })
+            });
+
         provider.expect_get_logs().returning({
             let controller = controller.clone();
             move |filter| {
@@ -1023,6 +1154,10 @@ mod tests {
         (provider, controller)
     }
 
+    fn touched_address(block_hash: H256) -> Address {
+        Address::from_slice(&block_hash[..20])
+    }
+
     fn fake_log(op_hash: H256) -> Log {
         Log {
             address: ENTRY_POINT_ADDRESS,
@@ -1088,6 +1223,8 @@ mod tests {
             nonce: U256::zero(),
             actual_gas_cost: U256::zero(),
             paymaster: None,
+            success: true,
+            ..Default::default()
         }
     }
 
@@ -89,7 +89,10 @@ pub enum EntityReputation {
 #[derive(Clone, Debug)]
 pub enum OpRemovalReason {
     /// Removal was requested
-    Requested,
+    Requested {
+        /// Who requested the removal
+        requester: OpRemovalRequester,
+    },
     /// Op was mined
     Mined {
         /// Mined at block number
@@ -120,6 +123,17 @@ pub enum OpRemovalReason {
     },
 }
 
+/// Who requested an operation be removed from the pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpRemovalRequester {
+    /// The builder, after the operation failed in a bundle
+    Builder,
+    /// The sender, with a signed cancellation
+    Sender,
+    /// An operator, through the admin API
+    Admin,
+}
+
 impl EntitySummary {
     pub fn set_status(&mut self, kind: EntityType, status: EntityStatus) {
         match kind {
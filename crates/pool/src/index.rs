@@ -0,0 +1,584 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{path::Path, sync::Arc, time::Duration};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use ethers::{
+    abi::AbiEncode,
+    prelude::EthEvent,
+    types::{Address, Filter, Log, H256, U256},
+};
+use redb::{Database, ReadableTable, TableDefinition, WriteTransaction};
+use rundler_provider::Provider;
+use rundler_task::Task;
+use rundler_types::contracts::i_entry_point::UserOperationEventFilter;
+use rundler_utils::{eth, handle};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
+
+use crate::chain::{self, Chain, ChainSubscription, ChainUpdate, MinedOp};
+
+/// User operations by hash, serialized as JSON
+const OPS: TableDefinition<&[u8], &[u8]> = TableDefinition::new("user_operations");
+/// Block hash followed by the hashes of the ops indexed in the block, by block number
+const BLOCKS: TableDefinition<u64, &[u8]> = TableDefinition::new("blocks");
+const META: TableDefinition<&str, u64> = TableDefinition::new("meta");
+/// Key of the latest block indexed by following the chain
+const LATEST_BLOCK_KEY: &str = "latest_block";
+
+/// A mined user operation, as recorded by its `UserOperationEvent`
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedUserOperation {
+    /// Hash of the user operation
+    pub hash: H256,
+    /// Entry point that emitted the event
+    pub entry_point: Address,
+    /// Sender of the user operation
+    pub sender: Address,
+    /// Nonce of the user operation
+    pub nonce: U256,
+    /// Paymaster of the user operation, if any
+    pub paymaster: Option<Address>,
+    /// Whether the user operation's call succeeded
+    pub success: bool,
+    /// Gas cost charged to the user operation
+    pub actual_gas_cost: U256,
+    /// Gas used by the user operation
+    pub actual_gas_used: U256,
+    /// Number of the block the user operation was mined in
+    pub block_number: u64,
+    /// Hash of the block the user operation was mined in
+    pub block_hash: H256,
+    /// Hash of the transaction the user operation was mined in
+    pub transaction_hash: H256,
+    /// Index of the event log in the block
+    pub log_index: u64,
+}
+
+impl IndexedUserOperation {
+    /// Rebuilds the `UserOperationEvent` log of the user operation
+    pub fn log(&self) -> Log {
+        Log {
+            address: self.entry_point,
+            topics: vec![
+                UserOperationEventFilter::signature(),
+                self.hash,
+                self.sender.into(),
+                self.paymaster.unwrap_or_default().into(),
+            ],
+            data: (
+                self.nonce,
+                self.success,
+                self.actual_gas_cost,
+                self.actual_gas_used,
+            )
+                .encode()
+                .into(),
+            block_hash: Some(self.block_hash),
+            block_number: Some(self.block_number.into()),
+            transaction_hash: Some(self.transaction_hash),
+            log_index: Some(self.log_index.into()),
+            ..Default::default()
+        }
+    }
+}
+
+impl From<MinedOp> for IndexedUserOperation {
+    fn from(op: MinedOp) -> Self {
+        Self {
+            hash: op.hash,
+            entry_point: op.entry_point,
+            sender: op.sender,
+            nonce: op.nonce,
+            paymaster: op.paymaster,
+            success: op.success,
+            actual_gas_cost: op.actual_gas_cost,
+            actual_gas_used: op.actual_gas_used,
+            block_number: op.block_number,
+            block_hash: op.block_hash,
+            transaction_hash: op.transaction_hash,
+            log_index: op.log_index,
+        }
+    }
+}
+
+/// An embedded database of mined user operations by hash.
+///
+/// The index is kept up to date by an [`IndexerTask`], which follows the chain
+/// and drops the operations of reorged blocks. Historical ranges can be added
+/// with [`UserOperationIndex::backfill`]. The database can only be opened by
+/// one process at a time.
+///
+/// Reads and writes block on disk IO, so they run on the blocking thread pool.
+#[derive(Debug)]
+pub struct UserOperationIndex {
+    db: Arc<Database>,
+}
+
+impl UserOperationIndex {
+    /// Open the index at `path`, creating it if it does not exist
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let db = Database::create(path)
+            .with_context(|| format!("should open user operation index at {path:?}"))?;
+        // create the tables so that read transactions can always open them
+        let txn = db.begin_write()?;
+        txn.open_table(OPS)?;
+        txn.open_table(BLOCKS)?;
+        txn.open_table(META)?;
+        txn.commit()?;
+        Ok(Self { db: Arc::new(db) })
+    }
+
+    /// Get a mined user operation by hash
+    pub async fn get(&self, hash: H256) -> anyhow::Result<Option<IndexedUserOperation>> {
+        self.run_blocking(move |db| {
+            let txn = db.begin_read()?;
+            let ops = txn.open_table(OPS)?;
+            let Some(value) = ops.get(hash.as_bytes())? else {
+                return Ok(None);
+            };
+            Ok(Some(
+                serde_json::from_slice(value.value())
+                    .context("should deserialize indexed user operation")?,
+            ))
+        })
+        .await
+    }
+
+    /// The latest block indexed by following the chain, if any
+    pub async fn latest_block(&self) -> anyhow::Result<Option<u64>> {
+        self.run_blocking(|db| {
+            let txn = db.begin_read()?;
+            let meta = txn.open_table(META)?;
+            Ok(meta.get(LATEST_BLOCK_KEY)?.map(|value| value.value()))
+        })
+        .await
+    }
+
+    /// Index the user operations mined between `from_block` and `to_block`
+    /// inclusive, loading events for at most `blocks_per_request` blocks at a
+    /// time. Returns the number of operations indexed.
+    pub async fn backfill<P: Provider>(
+        &self,
+        provider: &P,
+        entry_points: &[Address],
+        from_block: u64,
+        to_block: u64,
+        blocks_per_request: u64,
+    ) -> anyhow::Result<usize> {
+        let mut num_ops = 0;
+        let mut start = from_block;
+        while start <= to_block {
+            let end = to_block.min(start.saturating_add(blocks_per_request.max(1) - 1));
+            let filter = Filter::new()
+                .address(entry_points.to_vec())
+                .event(&UserOperationEventFilter::abi_signature())
+                .from_block(start)
+                .to_block(end);
+            let logs = provider.get_logs(&filter).await.with_context(|| {
+                format!("should load user operation events in blocks {start}-{end}")
+            })?;
+            let ops: Vec<_> = logs.iter().filter_map(MinedOp::from_log).collect();
+            let num_block_ops = ops.len();
+
+            self.run_blocking(move |db| {
+                let txn = db.begin_write()?;
+                for op in &ops {
+                    insert_op(&txn, op)?;
+                }
+                txn.commit()?;
+                Ok(())
+            })
+            .await?;
+
+            num_ops += num_block_ops;
+            info!("Indexed {num_block_ops} user operations in blocks {start}-{end}");
+            UserOperationIndexMetrics::increment_ops_indexed(num_block_ops);
+            start = end + 1;
+        }
+        Ok(num_ops)
+    }
+
+    // Removes the ops of reorged blocks, adds newly mined ops, and advances the
+    // latest indexed block
+    async fn apply_update(&self, update: Arc<ChainUpdate>) -> anyhow::Result<()> {
+        let (num_ops, block_number) = (update.mined_ops.len(), update.latest_block_number);
+        self.run_blocking(move |db| {
+            let txn = db.begin_write()?;
+            for op in &update.unmined_ops {
+                remove_op(&txn, op)?;
+            }
+            for op in &update.mined_ops {
+                insert_op(&txn, op)?;
+            }
+            txn.open_table(META)?
+                .insert(LATEST_BLOCK_KEY, update.latest_block_number)?;
+            txn.commit()?;
+            Ok(())
+        })
+        .await?;
+
+        UserOperationIndexMetrics::increment_ops_indexed(num_ops);
+        UserOperationIndexMetrics::set_block_height(block_number);
+        Ok(())
+    }
+
+    async fn run_blocking<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Database) -> anyhow::Result<T> + Send + 'static,
+    {
+        let db = Arc::clone(&self.db);
+        tokio::task::spawn_blocking(move || f(&db))
+            .await
+            .context("user operation index access should not panic")?
+    }
+}
+
+// Indexes a mined op. If a different block was indexed at the op's block number,
+// that block was reorged out, so its ops are removed first.
+fn insert_op(txn: &WriteTransaction<'_>, op: &MinedOp) -> anyhow::Result<()> {
+    let mut ops = txn.open_table(OPS)?;
+    let mut blocks = txn.open_table(BLOCKS)?;
+
+    let mut block = blocks
+        .get(op.block_number)?
+        .map(|value| value.value().to_vec())
+        .unwrap_or_default();
+    if block.len() < 32 || block[..32] != *op.block_hash.as_bytes() {
+        for hash in block.get(32..).unwrap_or_default().chunks_exact(32) {
+            ops.remove(hash)?;
+        }
+        block = op.block_hash.as_bytes().to_vec();
+    }
+    if !block[32..]
+        .chunks_exact(32)
+        .any(|hash| hash == op.hash.as_bytes())
+    {
+        block.extend_from_slice(op.hash.as_bytes());
+    }
+    blocks.insert(op.block_number, block.as_slice())?;
+
+    let value = serde_json::to_vec(&IndexedUserOperation::from(*op))?;
+    ops.insert(op.hash.as_bytes(), value.as_slice())?;
+    Ok(())
+}
+
+// Removes an unmined op, along with its hash in the block it was mined in so
+// that a later reorg of that block can't remove the op if it is mined again.
+fn remove_op(txn: &WriteTransaction<'_>, op: &MinedOp) -> anyhow::Result<()> {
+    let mut ops = txn.open_table(OPS)?;
+    let mut blocks = txn.open_table(BLOCKS)?;
+
+    ops.remove(op.hash.as_bytes())?;
+    let Some(block) = blocks
+        .get(op.block_number)?
+        .map(|value| value.value().to_vec())
+    else {
+        return Ok(());
+    };
+    if block.len() < 32 || block[..32] != *op.block_hash.as_bytes() {
+        return Ok(());
+    }
+    let mut remaining = block[..32].to_vec();
+    for hash in block[32..].chunks_exact(32) {
+        if hash != op.hash.as_bytes() {
+            remaining.extend_from_slice(hash);
+        }
+    }
+    blocks.insert(op.block_number, remaining.as_slice())?;
+    Ok(())
+}
+
+/// Arguments for the user operation indexer task
+#[derive(Debug)]
+pub struct Args {
+    /// HTTP URL for the full node
+    pub http_url: String,
+    /// Poll interval for new blocks
+    pub http_poll_interval: Duration,
+    /// Number of blocks to keep in the chain history, bounding the reorgs that
+    /// can be handled
+    pub chain_history_size: u64,
+    /// Entry points whose user operations to index
+    pub entry_points: Vec<Address>,
+    /// Maximum number of blocks to load events for in one request when catching up
+    pub blocks_per_request: u64,
+}
+
+/// Task that follows the chain and records mined user operations in a
+/// [`UserOperationIndex`].
+///
+/// When it starts, or falls behind the chain, the task catches up from the
+/// latest block it indexed. An empty index is only filled from the current
+/// chain history onwards, older blocks need to be backfilled.
+///
+/// The task follows the chain updates of a pool in the same process if given a
+/// [`ChainSubscription`], and otherwise watches the chain itself.
+#[derive(Debug)]
+pub struct IndexerTask {
+    args: Args,
+    index: Arc<UserOperationIndex>,
+    chain_subscription: Option<ChainSubscription>,
+}
+
+#[async_trait]
+impl Task for IndexerTask {
+    async fn run(mut self: Box<Self>, shutdown_token: CancellationToken) -> anyhow::Result<()> {
+        let provider = eth::new_provider(&self.args.http_url, Some(self.args.http_poll_interval))?;
+        let (mut updates, chain_handle) = match self.chain_subscription.take() {
+            Some(ChainSubscription(updates)) => (updates, None),
+            None => {
+                let chain = Chain::new(
+                    Arc::clone(&provider),
+                    chain::Settings {
+                        history_size: self.args.chain_history_size,
+                        poll_interval: self.args.http_poll_interval,
+                        entry_point_addresses: self.args.entry_points.clone(),
+                        track_state_diffs: false,
+                    },
+                );
+                let (update_sender, updates) =
+                    broadcast::channel(self.args.chain_history_size as usize);
+                let chain_handle = chain.spawn_watcher(update_sender, shutdown_token.clone());
+                (updates, Some(chain_handle))
+            }
+        };
+        info!("Started user operation indexer");
+
+        let mut catch_up = true;
+        loop {
+            let update = tokio::select! {
+                _ = shutdown_token.cancelled() => break,
+                update = updates.recv() => update,
+            };
+            let update = match update {
+                Ok(update) => update,
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("User operation indexer skipped {skipped} chain updates, catching up");
+                    catch_up = true;
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            if catch_up {
+                // Blocks since the last indexed block may not be covered by the
+                // update. Reindexing the update's blocks is harmless.
+                if let Err(error) = self.catch_up(&*provider, update.latest_block_number).await {
+                    // Skip the update, as applying it would advance the latest
+                    // indexed block past the blocks that failed to load
+                    error!("User operation indexer failed to catch up, will retry at next block: {error:?}");
+                    continue;
+                }
+                catch_up = false;
+            }
+            let block_number = update.latest_block_number;
+            if let Err(error) = self.index.apply_update(update).await {
+                error!("User operation indexer failed to index block {block_number}, will catch up at next block: {error:?}");
+                catch_up = true;
+            }
+        }
+
+        if let Some(chain_handle) = chain_handle {
+            handle::as_anyhow_handle(chain_handle).await?;
+        }
+        info!("Shutting down user operation indexer");
+        Ok(())
+    }
+}
+
+impl IndexerTask {
+    /// Create a new indexer task
+    pub fn new(args: Args, index: Arc<UserOperationIndex>) -> Self {
+        Self {
+            args,
+            index,
+            chain_subscription: None,
+        }
+    }
+
+    /// Follow the chain updates of a pool instead of watching the chain
+    pub fn with_chain_subscription(mut self, chain_subscription: ChainSubscription) -> Self {
+        self.chain_subscription = Some(chain_subscription);
+        self
+    }
+
+    /// Convert this task into a boxed task
+    pub fn boxed(self) -> Box<dyn Task> {
+        Box::new(self)
+    }
+
+    async fn catch_up<P: Provider>(&self, provider: &P, to_block: u64) -> anyhow::Result<()> {
+        let Some(latest) = self.index.latest_block().await? else {
+            return Ok(());
+        };
+        if latest < to_block {
+            self.index
+                .backfill(
+                    provider,
+                    &self.args.entry_points,
+                    latest + 1,
+                    to_block,
+                    self.args.blocks_per_request,
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+struct UserOperationIndexMetrics {}
+
+impl UserOperationIndexMetrics {
+    fn increment_ops_indexed(num_ops: usize) {
+        metrics::counter!("user_operation_index_ops_indexed", num_ops as u64);
+    }
+
+    fn set_block_height(block_height: u64) {
+        metrics::gauge!("user_operation_index_block_height", block_height as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_index() -> (UserOperationIndex, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("rundler-uo-index-{:?}.redb", H256::random()));
+        (UserOperationIndex::open(&path).unwrap(), path)
+    }
+
+    fn mined_op(block_number: u64, block_hash: H256) -> MinedOp {
+        MinedOp {
+            hash: H256::random(),
+            entry_point: Address::random(),
+            sender: Address::random(),
+            nonce: 3.into(),
+            actual_gas_cost: 100.into(),
+            paymaster: Some(Address::random()),
+            actual_gas_used: 10.into(),
+            success: true,
+            block_number,
+            block_hash,
+            transaction_hash: H256::random(),
+            log_index: 2,
+        }
+    }
+
+    #[test]
+    fn test_indexed_op_log_round_trips() {
+        let op = mined_op(5, H256::random());
+        let log = IndexedUserOperation::from(op).log();
+        assert_eq!(MinedOp::from_log(&log), Some(op));
+    }
+
+    #[tokio::test]
+    async fn test_apply_update_handles_reorgs() {
+        let (index, path) = open_index();
+        let (block, reorged_block) = (H256::random(), H256::random());
+        let (op, reorged_op) = (mined_op(1, block), mined_op(2, H256::random()));
+
+        index
+            .apply_update(Arc::new(ChainUpdate {
+                latest_block_number: 2,
+                mined_ops: vec![op, reorged_op],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        assert_eq!(index.latest_block().await.unwrap(), Some(2));
+        assert_eq!(index.get(op.hash).await.unwrap(), Some(op.into()));
+
+        // a different block at the same number replaces the ops of the old one
+        let new_op = mined_op(2, reorged_block);
+        index
+            .apply_update(Arc::new(ChainUpdate {
+                latest_block_number: 3,
+                mined_ops: vec![new_op],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        assert!(index.get(reorged_op.hash).await.unwrap().is_none());
+        assert_eq!(index.get(new_op.hash).await.unwrap(), Some(new_op.into()));
+        assert_eq!(index.get(op.hash).await.unwrap(), Some(op.into()));
+
+        // unmined ops are removed
+        index
+            .apply_update(Arc::new(ChainUpdate {
+                latest_block_number: 3,
+                unmined_ops: vec![new_op],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        assert!(index.get(new_op.hash).await.unwrap().is_none());
+
+        drop(index);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unmined_op_removed_from_block() {
+        let (index, path) = open_index();
+        let block = H256::random();
+        let (op, other_op) = (mined_op(2, block), mined_op(2, block));
+        index
+            .apply_update(Arc::new(ChainUpdate {
+                latest_block_number: 2,
+                mined_ops: vec![op, other_op],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        // the op is reorged out and mined again in a later block
+        let remined_op = MinedOp {
+            block_number: 3,
+            block_hash: H256::random(),
+            ..op
+        };
+        index
+            .apply_update(Arc::new(ChainUpdate {
+                latest_block_number: 3,
+                unmined_ops: vec![op],
+                mined_ops: vec![remined_op],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+
+        // reorging out its old block again only removes the ops still in it
+        let new_op = mined_op(2, H256::random());
+        index
+            .apply_update(Arc::new(ChainUpdate {
+                latest_block_number: 3,
+                mined_ops: vec![new_op],
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        assert_eq!(index.get(op.hash).await.unwrap(), Some(remined_op.into()));
+        assert!(index.get(other_op.hash).await.unwrap().is_none());
+        assert_eq!(index.get(new_op.hash).await.unwrap(), Some(new_op.into()));
+
+        drop(index);
+        std::fs::remove_file(path).unwrap();
+    }
+}
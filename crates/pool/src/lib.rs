@@ -20,15 +20,26 @@
 //! Mempool implementation for the Rundler.
 
 mod chain;
+pub use chain::ChainSubscription;
 
 mod emit;
-pub use emit::OpPoolEvent as PoolEvent;
+pub use emit::{EntitySummary, OpPoolEvent as PoolEvent, OpRemovalReason, OpRemovalRequester};
+
+mod index;
+pub use index::{Args as IndexerTaskArgs, IndexedUserOperation, IndexerTask, UserOperationIndex};
 
 mod mempool;
 pub use mempool::{
-    MempoolError, PoolConfig, PoolOperation, Reputation, ReputationStatus, StakeStatus,
+    AddressList, AddressListUpdate, BoostedOrdering, EffectiveTipOrdering, FifoOrdering,
+    MaxFeeOrdering, MempoolError, MempoolFilter, MempoolPage, OpPosition, OpPriority,
+    OperationOrigin, OrderingKind, OrderingPolicy, PaymasterAlertSettings, PaymasterStatus,
+    PoolConfig, PoolOperation, PriorityBoosts, Reputation, ReputationParams,
+    ReputationParamsOverride, ReputationStatus, StakeStatus,
 };
 
+mod reload;
+pub use reload::{MempoolConfigReloader, ReloadSettings};
+
 mod server;
 #[cfg(feature = "test-utils")]
 pub use server::MockPoolServer;
@@ -31,6 +31,9 @@ pub enum MempoolError {
     /// Operation with the same hash already in pool
     #[error("Operation already known")]
     OperationAlreadyKnown,
+    /// Operation was recently cancelled by its sender
+    #[error("Operation was cancelled by its sender")]
+    OperationCancelled,
     /// Operation with same sender/nonce already in pool
     /// and the replacement operation has lower gas price.
     #[error("Replacement operation underpriced. Existing priority fee: {0}. Existing fee: {1}")]
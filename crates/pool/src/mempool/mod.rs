@@ -15,33 +15,49 @@ mod error;
 pub use error::MempoolError;
 
 mod entity_tracker;
+mod ordering;
+pub use ordering::{
+    BoostedOrdering, EffectiveTipOrdering, FifoOrdering, MaxFeeOrdering, OpPriority, OrderingKind,
+    OrderingPolicy, PriorityBoosts,
+};
 mod pool;
 
 mod reputation;
-pub(crate) use reputation::{HourlyMovingAverageReputation, ReputationParams};
-pub use reputation::{Reputation, ReputationStatus};
+pub(crate) use reputation::HourlyMovingAverageReputation;
+pub use reputation::{
+    AddressList, AddressListUpdate, Reputation, ReputationParams, ReputationParamsOverride,
+    ReputationStatus,
+};
 use rundler_provider::ProviderResult;
 
 mod size;
 
 mod paymaster;
+mod paymaster_alerts;
+pub use paymaster_alerts::PaymasterAlertSettings;
 
 mod uo_pool;
 use std::{
     collections::{HashMap, HashSet},
+    path::PathBuf,
     sync::Arc,
+    time::Duration,
 };
 
 use ethers::types::{Address, H256, U256};
 #[cfg(test)]
 use mockall::automock;
-use rundler_sim::{EntityInfos, MempoolConfig, PrecheckSettings, SimulationSettings};
+use rundler_sim::{
+    EntityInfos, PrecheckSettings, SharedMempoolConfigs, SimulationSettings, TokenPaymasterProfile,
+};
 use rundler_types::{Entity, EntityType, EntityUpdate, UserOperation, ValidTimeRange};
+use serde::Serialize;
 use tonic::async_trait;
 pub(crate) use uo_pool::UoPool;
 
 use self::error::MempoolResult;
 use super::chain::ChainUpdate;
+use crate::emit::OpRemovalRequester;
 
 #[cfg_attr(test, automock)]
 #[async_trait]
@@ -53,15 +69,16 @@ pub trait Mempool: Send + Sync + 'static {
     /// Returns the entry point address this pool targets.
     fn entry_point(&self) -> Address;
 
-    /// Adds a user operation to the pool
+    /// Adds a user operation to the pool, submitted by `partner` if known
     async fn add_operation(
         &self,
         origin: OperationOrigin,
         op: UserOperation,
+        partner: Option<String>,
     ) -> MempoolResult<H256>;
 
-    /// Removes a set of operations from the pool.
-    fn remove_operations(&self, hashes: &[H256]);
+    /// Removes a set of operations from the pool at the request of `requester`.
+    fn remove_operations(&self, hashes: &[H256], requester: OpRemovalRequester);
 
     /// Updates the reputation of an entity.
     fn update_entity(&self, entity_update: EntityUpdate);
@@ -69,6 +86,10 @@ pub trait Mempool: Send + Sync + 'static {
     /// Returns current paymaster balance
     async fn paymaster_balance(&self, paymaster: Address) -> ProviderResult<PaymasterMetadata>;
 
+    /// Returns the balances and spending of a paymaster. Paymasters the pool
+    /// has not seen are reported from their on-chain deposit.
+    async fn paymaster_status(&self, paymaster: Address) -> ProviderResult<PaymasterStatus>;
+
     /// Returns the best operations from the pool.
     ///
     /// Returns the best operations from the pool based on their gas bids up to
@@ -89,6 +110,25 @@ pub trait Mempool: Send + Sync + 'static {
     /// Looks up a user operation by hash, returns None if not found
     fn get_user_operation_by_hash(&self, hash: H256) -> Option<Arc<PoolOperation>>;
 
+    /// Returns the position of an operation in pool order, or None if the
+    /// operation is not in the pool
+    fn operation_position(&self, hash: H256) -> Option<OpPosition>;
+
+    /// Returns the operations in the pool from a sender, ordered by nonce
+    fn operations_by_sender(&self, sender: Address) -> Vec<Arc<PoolOperation>>;
+
+    /// Returns the operations in the pool using a paymaster, in pool order
+    fn operations_by_paymaster(&self, paymaster: Address) -> Vec<Arc<PoolOperation>>;
+
+    /// Returns a page of the operations matching a filter, in pool order, along
+    /// with the total number of matching operations
+    fn filtered_operations(
+        &self,
+        filter: &MempoolFilter,
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<Arc<PoolOperation>>, usize);
+
     /// Debug methods
 
     /// Clears the mempool of UOs or reputation of all addresses
@@ -103,6 +143,9 @@ pub trait Mempool: Send + Sync + 'static {
     /// Overwrites the mempool's reputation for an address
     fn set_reputation(&self, address: Address, ops_seen: u64, ops_included: u64);
 
+    /// Imports a reputation snapshot, replacing all existing reputation state if `overwrite` is set
+    fn import_reputation(&self, reputations: Vec<Reputation>, overwrite: bool);
+
     /// Get stake status for address
     async fn get_stake_status(&self, address: Address) -> MempoolResult<StakeStatus>;
 
@@ -111,6 +154,9 @@ pub trait Mempool: Send + Sync + 'static {
 
     /// Turns on and off tracking errors
     fn set_tracking(&self, paymaster: bool, reputation: bool);
+
+    /// Updates the blocklist or allowlist, removing operations that use newly blocked entities
+    fn update_address_list(&self, list: AddressList, update: AddressListUpdate);
 }
 
 /// Config for the mempool
@@ -135,8 +181,9 @@ pub struct PoolConfig {
     pub precheck_settings: PrecheckSettings,
     /// Settings for simulation validation
     pub sim_settings: SimulationSettings,
-    /// Configuration for the mempool channels, by channel ID
-    pub mempool_channel_configs: HashMap<H256, MempoolConfig>,
+    /// Configuration for the mempool channels, by channel ID. Shared with the
+    /// builder and RPC server of a node so they follow reloaded configs.
+    pub mempool_channel_configs: SharedMempoolConfigs,
     /// Number of mempool shards to use. A mempool shard is a disjoint subset of the mempool
     /// that is used to ensure that two bundle builders don't attempt to but bundle the same
     /// operations. The mempool is divided into shards by taking the hash of the operation
@@ -148,8 +195,23 @@ pub struct PoolConfig {
     pub throttled_entity_live_blocks: u64,
     /// Boolean field used to toggle the operation of the paymaster tracker
     pub paymaster_tracking_enabled: bool,
+    /// Window over which the spend rates of paymasters are measured
+    pub paymaster_spend_window: Duration,
+    /// Settings for webhook alerts on paymasters running low on deposit, if enabled
+    pub paymaster_alerts: Option<PaymasterAlertSettings>,
+    /// ERC-20 token paymasters, by paymaster address. The token funds of
+    /// their senders are checked in precheck and tracked across the senders'
+    /// pending operations.
+    pub token_paymasters: HashMap<Address, TokenPaymasterProfile>,
     /// Boolean field used to toggle the operation of the reputation tracker
     pub reputation_tracking_enabled: bool,
+    /// Parameters of the reputation algorithm
+    pub reputation_params: ReputationParams,
+    /// Directory to persist reputation snapshots to, if any. Snapshots found in this
+    /// directory are restored on startup.
+    pub reputation_snapshot_dir: Option<PathBuf>,
+    /// Policy used to order operations in the mempool
+    pub ordering_policy: Arc<dyn OrderingPolicy>,
 }
 
 /// Stake status structure
@@ -205,6 +267,89 @@ pub struct PoolOperation {
     pub account_is_staked: bool,
     /// Staking information about all the entities.
     pub entity_infos: EntityInfos,
+    /// Partner that submitted the operation, used for priority boosts.
+    pub partner: Option<String>,
+    /// Max cost of the operation in its paymaster's token, if it uses a token
+    /// paymaster, used to track the sender's pending token spend.
+    pub token_cost: Option<U256>,
+}
+
+/// Filter on the operations in the mempool. Unset fields match every operation.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct MempoolFilter {
+    /// Sender of the operation
+    pub sender: Option<Address>,
+    /// Paymaster of the operation
+    pub paymaster: Option<Address>,
+    /// Factory of the operation
+    pub factory: Option<Address>,
+    /// Aggregator of the operation
+    pub aggregator: Option<Address>,
+    /// Minimum max fee per gas, inclusive
+    pub min_max_fee_per_gas: Option<U256>,
+    /// Maximum max fee per gas, inclusive
+    pub max_max_fee_per_gas: Option<U256>,
+    /// Minimum max priority fee per gas, inclusive
+    pub min_max_priority_fee_per_gas: Option<U256>,
+    /// Maximum max priority fee per gas, inclusive
+    pub max_max_priority_fee_per_gas: Option<U256>,
+    /// Earliest block the operation was simulated at, inclusive
+    pub min_sim_block_number: Option<u64>,
+    /// Latest block the operation was simulated at, inclusive
+    pub max_sim_block_number: Option<u64>,
+}
+
+impl MempoolFilter {
+    /// Returns true if the operation matches the filter
+    pub fn matches(&self, op: &PoolOperation) -> bool {
+        fn in_range<T: PartialOrd>(value: T, min: Option<T>, max: Option<T>) -> bool {
+            min.map_or(true, |min| value >= min) && max.map_or(true, |max| value <= max)
+        }
+
+        self.sender.map_or(true, |sender| op.uo.sender == sender)
+            && self
+                .paymaster
+                .map_or(true, |paymaster| op.uo.paymaster() == Some(paymaster))
+            && self
+                .factory
+                .map_or(true, |factory| op.uo.factory() == Some(factory))
+            && self
+                .aggregator
+                .map_or(true, |aggregator| op.aggregator == Some(aggregator))
+            && in_range(
+                op.uo.max_fee_per_gas,
+                self.min_max_fee_per_gas,
+                self.max_max_fee_per_gas,
+            )
+            && in_range(
+                op.uo.max_priority_fee_per_gas,
+                self.min_max_priority_fee_per_gas,
+                self.max_max_priority_fee_per_gas,
+            )
+            && in_range(
+                op.sim_block_number,
+                self.min_sim_block_number,
+                self.max_sim_block_number,
+            )
+    }
+}
+
+/// The position of an operation in pool order
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct OpPosition {
+    /// Number of operations ahead of the operation
+    pub position: u64,
+    /// Number of operations in the pool
+    pub pool_size: u64,
+}
+
+/// A page of the operations in the mempool
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct MempoolPage {
+    /// Operations in the page
+    pub ops: Vec<PoolOperation>,
+    /// Total number of operations matching the filter
+    pub total: u64,
 }
 
 #[derive(Debug, Default, Clone, Eq, PartialEq, Copy)]
@@ -218,6 +363,26 @@ pub struct PaymasterMetadata {
     pub pending_balance: U256,
 }
 
+/// Balances and spending of a paymaster, as tracked by the pool
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymasterStatus {
+    /// Paymaster address
+    pub address: Address,
+    /// The on-chain deposit of the paymaster
+    pub confirmed_balance: U256,
+    /// The confirmed balance less the max cost of the paymaster's pending operations
+    pub pending_balance: U256,
+    /// Number of operations in the pool using the paymaster
+    pub pending_ops: u64,
+    /// Gas costs charged to the paymaster by mined operations per hour,
+    /// averaged over the spend window
+    pub spend_per_hour: U256,
+    /// Seconds until the pending balance runs out at the current spend rate,
+    /// if the paymaster has spent anything in the spend window
+    pub runway_seconds: Option<u64>,
+}
+
 impl PoolOperation {
     /// Returns true if the operation contains the given entity.
     pub fn contains_entity(&self, entity: &Entity) -> bool {
@@ -328,6 +493,7 @@ mod tests {
                     is_staked: false,
                 }),
             },
+            partner: None,
         };
 
         assert!(po.requires_stake(EntityType::Account));
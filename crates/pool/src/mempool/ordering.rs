@@ -0,0 +1,266 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+use ethers::types::{Address, U256};
+use serde::{Deserialize, Serialize};
+
+use super::PoolOperation;
+
+/// Priority of an operation in the mempool, higher is better.
+///
+/// Operations are first ordered by `tier`, then by `score`. Ties are broken by
+/// submission order, oldest first.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+pub struct OpPriority {
+    /// Priority tier, used to boost operations above all operations in lower tiers
+    pub tier: u64,
+    /// Score of the operation within its tier
+    pub score: U256,
+}
+
+/// Policy used to order operations in the mempool.
+///
+/// The policy is used to determine which operations are returned first by
+/// `best_operations` and which operations are evicted first when the pool is full.
+pub trait OrderingPolicy: Debug + Send + Sync + 'static {
+    /// Returns the priority of the operation given the current base fee.
+    fn priority(&self, op: &PoolOperation, base_fee: U256) -> OpPriority;
+
+    /// Returns true if the priority of an operation can change with the base fee.
+    ///
+    /// If true, the pool is re-sorted whenever a new base fee is observed.
+    fn depends_on_base_fee(&self) -> bool {
+        false
+    }
+}
+
+/// Orders operations by `max_fee_per_gas`, descending.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MaxFeeOrdering;
+
+impl OrderingPolicy for MaxFeeOrdering {
+    fn priority(&self, op: &PoolOperation, _base_fee: U256) -> OpPriority {
+        OpPriority {
+            tier: 0,
+            score: op.uo.max_fee_per_gas,
+        }
+    }
+}
+
+/// Orders operations by the tip they pay at the current base fee, descending.
+///
+/// The effective tip is `min(max_priority_fee_per_gas, max_fee_per_gas - base_fee)`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EffectiveTipOrdering;
+
+impl OrderingPolicy for EffectiveTipOrdering {
+    fn priority(&self, op: &PoolOperation, base_fee: U256) -> OpPriority {
+        let score = op
+            .uo
+            .max_priority_fee_per_gas
+            .min(op.uo.max_fee_per_gas.saturating_sub(base_fee));
+        OpPriority { tier: 0, score }
+    }
+
+    fn depends_on_base_fee(&self) -> bool {
+        true
+    }
+}
+
+/// Orders operations by submission order, oldest first.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FifoOrdering;
+
+impl OrderingPolicy for FifoOrdering {
+    fn priority(&self, _op: &PoolOperation, _base_fee: U256) -> OpPriority {
+        OpPriority::default()
+    }
+}
+
+/// Priority tiers granted to operations using specific entities.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityBoosts {
+    /// Priority tier by paymaster address
+    #[serde(default)]
+    pub paymasters: HashMap<Address, u64>,
+    /// Priority tier by sender address
+    #[serde(default)]
+    pub senders: HashMap<Address, u64>,
+    /// Priority tier by the partner that submitted the operation
+    #[serde(default)]
+    pub partners: HashMap<String, u64>,
+}
+
+impl PriorityBoosts {
+    /// Returns the highest tier granted to the operation, or zero if none.
+    pub fn tier(&self, op: &PoolOperation) -> u64 {
+        let paymaster_tier = op
+            .uo
+            .paymaster()
+            .and_then(|p| self.paymasters.get(&p))
+            .copied()
+            .unwrap_or_default();
+        let sender_tier = self.senders.get(&op.uo.sender).copied().unwrap_or_default();
+        let partner_tier = op
+            .partner
+            .as_ref()
+            .and_then(|p| self.partners.get(p))
+            .copied()
+            .unwrap_or_default();
+        paymaster_tier.max(sender_tier).max(partner_tier)
+    }
+}
+
+/// Wraps another ordering policy, placing operations from boosted entities
+/// into higher priority tiers.
+///
+/// Within a tier, operations are ordered by the wrapped policy.
+#[derive(Debug)]
+pub struct BoostedOrdering {
+    inner: Arc<dyn OrderingPolicy>,
+    boosts: PriorityBoosts,
+}
+
+impl BoostedOrdering {
+    /// Create a new boosted ordering policy
+    pub fn new(inner: Arc<dyn OrderingPolicy>, boosts: PriorityBoosts) -> Self {
+        Self { inner, boosts }
+    }
+}
+
+impl OrderingPolicy for BoostedOrdering {
+    fn priority(&self, op: &PoolOperation, base_fee: U256) -> OpPriority {
+        let inner = self.inner.priority(op, base_fee);
+        OpPriority {
+            tier: inner.tier.max(self.boosts.tier(op)),
+            score: inner.score,
+        }
+    }
+
+    fn depends_on_base_fee(&self) -> bool {
+        self.inner.depends_on_base_fee()
+    }
+}
+
+/// Built in ordering policies
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OrderingKind {
+    /// Order by max fee per gas
+    #[default]
+    MaxFee,
+    /// Order by effective tip at the current base fee
+    EffectiveTip,
+    /// Order by submission order
+    Fifo,
+}
+
+impl OrderingKind {
+    /// Try to create an ordering kind from a string.
+    pub fn try_from(kind: &str) -> anyhow::Result<Self> {
+        match kind {
+            "max_fee" => Ok(Self::MaxFee),
+            "effective_tip" => Ok(Self::EffectiveTip),
+            "fifo" => Ok(Self::Fifo),
+            _ => anyhow::bail!("Invalid ordering policy: {}", kind),
+        }
+    }
+
+    /// Builds the ordering policy for this kind, applying the given boosts, if any.
+    pub fn into_policy(self, boosts: Option<PriorityBoosts>) -> Arc<dyn OrderingPolicy> {
+        let policy: Arc<dyn OrderingPolicy> = match self {
+            Self::MaxFee => Arc::new(MaxFeeOrdering),
+            Self::EffectiveTip => Arc::new(EffectiveTipOrdering),
+            Self::Fifo => Arc::new(FifoOrdering),
+        };
+
+        match boosts {
+            Some(boosts) => Arc::new(BoostedOrdering::new(policy, boosts)),
+            None => policy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::UserOperation;
+
+    use super::*;
+
+    #[test]
+    fn test_effective_tip() {
+        let op = create_op(Address::random(), None, 20, 5);
+
+        let policy = EffectiveTipOrdering;
+        assert_eq!(policy.priority(&op, 10.into()).score, 5.into());
+        assert_eq!(policy.priority(&op, 17.into()).score, 3.into());
+        assert_eq!(policy.priority(&op, 25.into()).score, 0.into());
+    }
+
+    #[test]
+    fn test_boosted_tiers() {
+        let sender = Address::random();
+        let paymaster = Address::random();
+        let boosts = PriorityBoosts {
+            paymasters: HashMap::from([(paymaster, 2)]),
+            senders: HashMap::from([(sender, 1)]),
+            partners: HashMap::from([("acme".to_string(), 3)]),
+        };
+        let policy = OrderingKind::MaxFee.into_policy(Some(boosts));
+
+        let unboosted = create_op(Address::random(), None, 20, 5);
+        let sender_boosted = create_op(sender, None, 10, 5);
+        let both_boosted = create_op(sender, Some(paymaster), 10, 5);
+
+        assert_eq!(
+            policy.priority(&unboosted, U256::zero()),
+            OpPriority {
+                tier: 0,
+                score: 20.into()
+            }
+        );
+        assert_eq!(policy.priority(&sender_boosted, U256::zero()).tier, 1);
+        assert_eq!(policy.priority(&both_boosted, U256::zero()).tier, 2);
+        let partner_boosted = PoolOperation {
+            partner: Some("acme".to_string()),
+            ..both_boosted
+        };
+        assert_eq!(policy.priority(&partner_boosted, U256::zero()).tier, 3);
+        assert!(
+            policy.priority(&sender_boosted, U256::zero())
+                > policy.priority(&unboosted, U256::zero())
+        );
+    }
+
+    fn create_op(
+        sender: Address,
+        paymaster: Option<Address>,
+        max_fee_per_gas: u64,
+        max_priority_fee_per_gas: u64,
+    ) -> PoolOperation {
+        PoolOperation {
+            uo: UserOperation {
+                sender,
+                paymaster_and_data: paymaster
+                    .map(|p| p.as_bytes().to_vec().into())
+                    .unwrap_or_default(),
+                max_fee_per_gas: max_fee_per_gas.into(),
+                max_priority_fee_per_gas: max_priority_fee_per_gas.into(),
+                ..UserOperation::default()
+            },
+            ..PoolOperation::default()
+        }
+    }
+}
@@ -13,13 +13,20 @@
 
 // from reth github: https://github.com/paradigmxyz/reth/blob/main/crates/transaction-pool/src/pool/size.rs
 //! Tracks a size value.
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
 
 use anyhow::Context;
-use ethers::{abi::Address, types::U256};
+use ethers::{
+    abi::Address,
+    types::{H256, U256},
+};
+use rundler_sim::{PrecheckViolation, TokenFunds};
 use rundler_types::UserOperationId;
 
-use super::{error::MempoolResult, PaymasterMetadata};
+use super::{error::MempoolResult, PaymasterMetadata, PaymasterStatus};
 use crate::{chain::MinedOp, MempoolError, PoolOperation};
 
 /// Keeps track of current and pending paymaster balances
@@ -29,14 +36,21 @@ pub(crate) struct PaymasterTracker {
     user_op_fees: HashMap<UserOperationId, UserOpFees>,
     /// map for paymaster balance status
     paymaster_balances: HashMap<Address, PaymasterBalance>,
+    /// max token cost of pending operations using token paymasters, by paymaster and sender
+    pending_token_spends: HashMap<(Address, Address), U256>,
     /// boolean for operation of tracker
     tracker_enabled: bool,
+    /// window over which paymaster spend rates are measured
+    spend_window: Duration,
+    /// timestamp of the latest block whose mined operations were recorded
+    block_timestamp: u64,
 }
 
 impl PaymasterTracker {
-    pub(crate) fn new(tracker_enabled: bool) -> Self {
+    pub(crate) fn new(tracker_enabled: bool, spend_window: Duration) -> Self {
         Self {
             tracker_enabled,
+            spend_window,
             ..Default::default()
         }
     }
@@ -52,6 +66,7 @@ impl PaymasterTracker {
     pub(crate) fn clear(&mut self) {
         self.user_op_fees.clear();
         self.paymaster_balances.clear();
+        self.pending_token_spends.clear();
     }
 
     pub(crate) fn set_confimed_balances(&mut self, addresses: &[Address], balances: &[U256]) {
@@ -74,8 +89,10 @@ impl PaymasterTracker {
 
                 paymaster_balance.pending =
                     paymaster_balance.pending.saturating_sub(op_fee.max_op_cost);
+                paymaster_balance.pending_ops = paymaster_balance.pending_ops.saturating_sub(1);
             }
 
+            self.remove_token_spend(&id);
             self.user_op_fees.remove(&id);
         }
     }
@@ -85,8 +102,10 @@ impl PaymasterTracker {
             if let Some(paymaster_balance) = self.paymaster_balances.get_mut(&op_fee.paymaster) {
                 paymaster_balance.pending =
                     paymaster_balance.pending.saturating_sub(op_fee.max_op_cost);
+                paymaster_balance.pending_ops = paymaster_balance.pending_ops.saturating_sub(1);
             }
 
+            self.remove_token_spend(id);
             self.user_op_fees.remove(id);
         }
     }
@@ -124,6 +143,93 @@ impl PaymasterTracker {
         None
     }
 
+    /// Records the gas costs charged to tracked paymasters by the operations
+    /// mined in a block, including operations that were not in the pool, and
+    /// forgets the costs of operations unmined by a reorg and costs that are
+    /// older than the spend window.
+    pub(crate) fn record_spends<'a>(
+        &mut self,
+        mined_ops: impl IntoIterator<Item = &'a MinedOp>,
+        unmined_ops: impl IntoIterator<Item = &'a MinedOp>,
+        block_timestamp: u64,
+    ) {
+        self.block_timestamp = block_timestamp;
+        for op in unmined_ops {
+            let Some(paymaster) = op.paymaster else {
+                continue;
+            };
+            if let Some(paymaster_balance) = self.paymaster_balances.get_mut(&paymaster) {
+                paymaster_balance
+                    .spends
+                    .retain(|(_, hash, _)| *hash != op.hash);
+            }
+        }
+        for op in mined_ops {
+            let Some(paymaster) = op.paymaster else {
+                continue;
+            };
+            if let Some(paymaster_balance) = self.paymaster_balances.get_mut(&paymaster) {
+                paymaster_balance
+                    .spends
+                    .push_back((block_timestamp, op.hash, op.actual_gas_cost));
+            }
+        }
+
+        let cutoff = block_timestamp.saturating_sub(self.spend_window.as_secs());
+        for paymaster_balance in self.paymaster_balances.values_mut() {
+            while paymaster_balance
+                .spends
+                .front()
+                .is_some_and(|(timestamp, _, _)| *timestamp < cutoff)
+            {
+                paymaster_balance.spends.pop_front();
+            }
+        }
+    }
+
+    /// Balances, pending operations, and spend rate of a tracked paymaster.
+    ///
+    /// The spend rate is averaged over the whole spend window, and the runway
+    /// is the time until the pending balance runs out at that rate.
+    pub(crate) fn paymaster_status(&self, paymaster: Address) -> Option<PaymasterStatus> {
+        let paymaster_balance = self.paymaster_balances.get(&paymaster)?;
+        let window = self.spend_window.as_secs().max(1);
+        let spent = paymaster_balance
+            .spends
+            .iter()
+            .fold(U256::zero(), |total, (_, _, cost)| {
+                total.saturating_add(*cost)
+            });
+        let pending_balance = paymaster_balance.pending_balance();
+        let runway_seconds = (!spent.is_zero()).then(|| {
+            let runway = pending_balance.saturating_mul(window.into()) / spent;
+            if runway > u64::MAX.into() {
+                u64::MAX
+            } else {
+                runway.as_u64()
+            }
+        });
+
+        Some(PaymasterStatus {
+            address: paymaster,
+            confirmed_balance: paymaster_balance.confirmed,
+            pending_balance,
+            pending_ops: paymaster_balance.pending_ops,
+            spend_per_hour: spent.saturating_mul(3600.into()) / window,
+            runway_seconds,
+        })
+    }
+
+    /// Statuses of the tracked paymasters with pending operations or spends
+    /// within the window
+    pub(crate) fn paymaster_statuses(&self) -> Vec<PaymasterStatus> {
+        self.paymaster_balances
+            .iter()
+            .filter(|(_, paymaster_balance)| !paymaster_balance.is_idle())
+            .filter_map(|(paymaster, _)| self.paymaster_status(*paymaster))
+            .collect()
+    }
+
     pub(crate) fn unmine_actual_cost(&mut self, paymaster: &Address, actual_cost: U256) {
         if let Some(paymaster_balance) = self.paymaster_balances.get_mut(paymaster) {
             paymaster_balance.confirmed = paymaster_balance.confirmed.saturating_add(actual_cost);
@@ -134,6 +240,7 @@ impl PaymasterTracker {
         &mut self,
         po: &PoolOperation,
         paymaster_metadata: &PaymasterMetadata,
+        token_funds: Option<&TokenFunds>,
     ) -> MempoolResult<()> {
         let id = po.uo.id();
         let max_op_cost = po.uo.max_gas_cost();
@@ -145,16 +252,109 @@ impl PaymasterTracker {
                 paymaster_metadata.pending_balance,
             ));
         }
+        if let Some(token_funds) = token_funds {
+            if self.tracker_enabled {
+                self.check_token_funds(&id, paymaster_metadata.address, token_funds)?;
+            }
+        }
 
         if self.is_user_op_replacement(&id) {
-            self.replace_existing_user_op(&id, paymaster_metadata, max_op_cost)?;
+            let replaced = self.replace_existing_user_op(&id, paymaster_metadata, max_op_cost)?;
+            // a replaced operation's token spend is only removed once its replacement is tracked
+            self.subtract_token_spend(replaced.paymaster, id.sender, replaced.token_cost);
         } else {
             self.add_new_user_op(&id, paymaster_metadata, max_op_cost);
         }
+        // the token cost is kept on the operation, so that it is counted again
+        // when the operation is put back after a reorg
+        if let Some(token_cost) = po.token_cost {
+            self.add_token_spend(&id, paymaster_metadata.address, token_cost);
+        }
+
+        Ok(())
+    }
+
+    /// Max token cost of a sender's pending operations using a token paymaster
+    pub(crate) fn pending_token_spend(&self, paymaster: Address, sender: Address) -> U256 {
+        self.pending_token_spends
+            .get(&(paymaster, sender))
+            .copied()
+            .unwrap_or_default()
+    }
 
+    /// The sender's token balance and allowance must cover the max token cost
+    /// of the operation along with those of its other pending operations
+    /// using the paymaster.
+    fn check_token_funds(
+        &self,
+        id: &UserOperationId,
+        paymaster: Address,
+        token_funds: &TokenFunds,
+    ) -> MempoolResult<()> {
+        let replaced_token_cost = self
+            .user_op_fees
+            .get(id)
+            .filter(|op_fees| op_fees.paymaster == paymaster)
+            .map_or(U256::zero(), |op_fees| op_fees.token_cost);
+        let required = self
+            .pending_token_spend(paymaster, id.sender)
+            .saturating_sub(replaced_token_cost)
+            .saturating_add(token_funds.max_token_cost);
+
+        if token_funds.balance < required {
+            return Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::SenderTokenBalanceTooLow(
+                    token_funds.token,
+                    token_funds.balance,
+                    required,
+                ),
+            ));
+        }
+        if token_funds.allowance < required {
+            return Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::SenderTokenAllowanceTooLow(
+                    token_funds.token,
+                    token_funds.allowance,
+                    required,
+                ),
+            ));
+        }
         Ok(())
     }
 
+    fn add_token_spend(&mut self, id: &UserOperationId, paymaster: Address, token_cost: U256) {
+        if let Some(op_fees) = self.user_op_fees.get_mut(id) {
+            op_fees.token_cost = token_cost;
+        }
+        let pending = self
+            .pending_token_spends
+            .entry((paymaster, id.sender))
+            .or_default();
+        *pending = pending.saturating_add(token_cost);
+    }
+
+    fn remove_token_spend(&mut self, id: &UserOperationId) {
+        let Some(op_fees) = self.user_op_fees.get_mut(id) else {
+            return;
+        };
+        let token_cost = std::mem::take(&mut op_fees.token_cost);
+        let paymaster = op_fees.paymaster;
+        self.subtract_token_spend(paymaster, id.sender, token_cost);
+    }
+
+    fn subtract_token_spend(&mut self, paymaster: Address, sender: Address, token_cost: U256) {
+        if token_cost.is_zero() {
+            return;
+        }
+        let key = (paymaster, sender);
+        if let Some(pending) = self.pending_token_spends.get_mut(&key) {
+            *pending = pending.saturating_sub(token_cost);
+            if pending.is_zero() {
+                self.pending_token_spends.remove(&key);
+            }
+        }
+    }
+
     fn is_user_op_replacement(&self, id: &UserOperationId) -> bool {
         self.user_op_fees.contains_key(id)
     }
@@ -172,25 +372,29 @@ impl PaymasterTracker {
         prev_paymaster_balance.pending = prev_paymaster_balance
             .pending
             .saturating_sub(previous_max_op_cost);
+        prev_paymaster_balance.pending_ops = prev_paymaster_balance.pending_ops.saturating_sub(1);
 
         Ok(())
     }
 
+    /// Returns the fees of the replaced operation
     fn replace_existing_user_op(
         &mut self,
         id: &UserOperationId,
         paymaster_metadata: &PaymasterMetadata,
         max_op_cost: U256,
-    ) -> MempoolResult<()> {
+    ) -> MempoolResult<UserOpFees> {
         let existing_user_op = self
             .user_op_fees
             .get_mut(id)
             .context("User op must exist to replace values ")?;
 
-        let prev_max_op_cost = existing_user_op.max_op_cost;
-        let prev_paymaster = existing_user_op.paymaster;
-
-        *existing_user_op = UserOpFees::new(paymaster_metadata.address, max_op_cost);
+        let replaced = std::mem::replace(
+            existing_user_op,
+            UserOpFees::new(paymaster_metadata.address, max_op_cost),
+        );
+        let prev_max_op_cost = replaced.max_op_cost;
+        let prev_paymaster = replaced.paymaster;
 
         if let Some(paymaster_balance) =
             self.paymaster_balances.get_mut(&paymaster_metadata.address)
@@ -198,6 +402,7 @@ impl PaymasterTracker {
             // check to see if paymaster has changed
             if prev_paymaster.ne(&paymaster_metadata.address) {
                 paymaster_balance.pending = paymaster_balance.pending.saturating_add(max_op_cost);
+                paymaster_balance.pending_ops += 1;
 
                 //remove previous limit from data
                 self.decrement_previous_paymaster_balance(&prev_paymaster, prev_max_op_cost)?;
@@ -220,7 +425,7 @@ impl PaymasterTracker {
             );
         }
 
-        Ok(())
+        Ok(replaced)
     }
 
     fn add_new_user_op(
@@ -238,6 +443,7 @@ impl PaymasterTracker {
             self.paymaster_balances.get_mut(&paymaster_metadata.address)
         {
             paymaster_balance.pending = paymaster_balance.pending.saturating_add(max_op_cost);
+            paymaster_balance.pending_ops += 1;
         } else {
             self.paymaster_balances.insert(
                 paymaster_metadata.address,
@@ -251,6 +457,8 @@ impl PaymasterTracker {
 pub(crate) struct UserOpFees {
     paymaster: Address,
     max_op_cost: U256,
+    /// max cost of the operation in the paymaster's token, if it is a token paymaster
+    token_cost: U256,
 }
 
 impl UserOpFees {
@@ -258,6 +466,7 @@ impl UserOpFees {
         Self {
             paymaster,
             max_op_cost,
+            token_cost: U256::zero(),
         }
     }
 }
@@ -266,32 +475,54 @@ impl UserOpFees {
 pub(crate) struct PaymasterBalance {
     pending: U256,
     confirmed: U256,
+    /// number of operations in the pool using the paymaster
+    pending_ops: u64,
+    /// gas costs charged to the paymaster by mined operations, with the
+    /// timestamps of their blocks and the operation hashes, oldest first
+    spends: VecDeque<(u64, H256, U256)>,
 }
 
 impl PaymasterBalance {
+    /// Balance of a paymaster with a single pending operation
     fn new(confirmed: U256, pending: U256) -> Self {
-        Self { confirmed, pending }
+        Self {
+            confirmed,
+            pending,
+            pending_ops: 1,
+            spends: VecDeque::new(),
+        }
     }
 
     pub(crate) fn pending_balance(&self) -> U256 {
         self.confirmed.saturating_sub(self.pending)
     }
+
+    /// Whether the paymaster has no pending operations and no spends within
+    /// the window
+    fn is_idle(&self) -> bool {
+        self.pending_ops == 0 && self.spends.is_empty()
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use ethers::types::{Address, H256, U256};
-    use rundler_sim::EntityInfos;
+    use rundler_sim::{EntityInfos, PrecheckViolation, TokenFunds};
     use rundler_types::{UserOperation, UserOperationId, ValidTimeRange};
 
     use crate::{
+        chain::MinedOp,
         mempool::{
             paymaster::{PaymasterBalance, PaymasterTracker, UserOpFees},
             PaymasterMetadata,
         },
-        PoolOperation,
+        MempoolError, PoolOperation,
     };
 
+    const SPEND_WINDOW: Duration = Duration::from_secs(3600);
+
     fn demo_pool_op(uo: UserOperation) -> PoolOperation {
         PoolOperation {
             uo,
@@ -304,12 +535,14 @@ mod tests {
             account_is_staked: true,
             entity_infos: EntityInfos::default(),
             sim_block_number: 0,
+            partner: None,
+            token_cost: None,
         }
     }
 
     #[test]
     fn new_uo_unused_paymaster() {
-        let mut paymaster_tracker = PaymasterTracker::new(true);
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
 
         let paymaster = Address::random();
         let sender = Address::random();
@@ -333,7 +566,7 @@ mod tests {
 
         let po = demo_pool_op(uo);
 
-        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta);
+        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta, None);
         assert!(res.is_ok());
         assert_eq!(
             paymaster_tracker
@@ -355,7 +588,7 @@ mod tests {
 
     #[test]
     fn new_uo_not_enough_balance() {
-        let mut paymaster_tracker = PaymasterTracker::new(true);
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
 
         let paymaster = Address::random();
         let sender = Address::random();
@@ -378,13 +611,13 @@ mod tests {
 
         let po = demo_pool_op(uo);
 
-        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta);
+        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta, None);
         assert!(res.is_err());
     }
 
     #[test]
     fn test_update_balance() {
-        let mut paymaster_tracker = PaymasterTracker::new(true);
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
 
         let paymaster = Address::random();
         let pending_op_cost = U256::from(100);
@@ -395,6 +628,7 @@ mod tests {
             PaymasterBalance {
                 pending: pending_op_cost,
                 confirmed: confirmed_balance,
+                ..Default::default()
             },
         );
 
@@ -421,7 +655,7 @@ mod tests {
 
     #[test]
     fn new_uo_not_enough_balance_tracking_disabled() {
-        let mut paymaster_tracker = PaymasterTracker::new(false);
+        let mut paymaster_tracker = PaymasterTracker::new(false, SPEND_WINDOW);
 
         let paymaster = Address::random();
         let sender = Address::random();
@@ -444,7 +678,7 @@ mod tests {
 
         let po = demo_pool_op(uo);
 
-        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta);
+        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta, None);
         assert!(res.is_ok());
         assert_eq!(
             paymaster_tracker
@@ -466,7 +700,7 @@ mod tests {
 
     #[test]
     fn new_uo_not_enough_balance_existing_paymaster() {
-        let mut paymaster_tracker = PaymasterTracker::new(true);
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
 
         let paymaster = Address::random();
         let sender = Address::random();
@@ -478,6 +712,7 @@ mod tests {
             PaymasterBalance {
                 pending: pending_paymaster_balance,
                 confirmed: paymaster_balance,
+                ..Default::default()
             },
         );
 
@@ -498,13 +733,13 @@ mod tests {
 
         let po = demo_pool_op(uo);
 
-        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta);
+        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta, None);
         assert!(res.is_err());
     }
 
     #[test]
     fn new_uo_existing_paymaster_valid_balance() {
-        let mut paymaster_tracker = PaymasterTracker::new(true);
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
         let paymaster = Address::random();
         let paymaster_balance = U256::from(100000000);
         let pending_paymaster_balance = U256::from(10);
@@ -514,6 +749,7 @@ mod tests {
             PaymasterBalance {
                 pending: pending_paymaster_balance,
                 confirmed: paymaster_balance,
+                ..Default::default()
             },
         );
 
@@ -536,7 +772,7 @@ mod tests {
         };
 
         let po = demo_pool_op(uo);
-        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta);
+        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta, None);
 
         assert!(res.is_ok());
         assert_eq!(
@@ -567,7 +803,7 @@ mod tests {
 
     #[test]
     fn replacement_uo_new_paymaster() {
-        let mut paymaster_tracker = PaymasterTracker::new(true);
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
         let paymaster_0 = Address::random();
         let paymaster_1 = Address::random();
 
@@ -605,7 +841,7 @@ mod tests {
         let po_0 = demo_pool_op(uo);
         // Update first paymaster balance with first uo
         paymaster_tracker
-            .add_or_update_balance(&po_0, &paymaster_meta_0)
+            .add_or_update_balance(&po_0, &paymaster_meta_0, None)
             .unwrap();
 
         assert_eq!(
@@ -620,7 +856,7 @@ mod tests {
         let po_1 = demo_pool_op(uo_1);
         // send same uo with updated fees and new paymaster
         paymaster_tracker
-            .add_or_update_balance(&po_1, &paymaster_meta_1)
+            .add_or_update_balance(&po_1, &paymaster_meta_1, None)
             .unwrap();
 
         // check previous paymaster goes back to normal balance
@@ -646,7 +882,7 @@ mod tests {
 
     #[test]
     fn replacement_uo_same_paymaster() {
-        let mut paymaster_tracker = PaymasterTracker::new(true);
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
         let sender = Address::random();
         let paymaster = Address::random();
         let paymaster_balance = U256::from(100000000);
@@ -660,6 +896,7 @@ mod tests {
             PaymasterBalance {
                 pending: pending_paymaster_balance,
                 confirmed: paymaster_balance,
+                ..Default::default()
             },
         );
 
@@ -669,6 +906,7 @@ mod tests {
             UserOpFees {
                 paymaster,
                 max_op_cost: 30.into(),
+                ..Default::default()
             },
         );
 
@@ -693,7 +931,7 @@ mod tests {
 
         let po = demo_pool_op(uo);
 
-        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta);
+        let res = paymaster_tracker.add_or_update_balance(&po, &paymaster_meta, None);
         assert!(res.is_ok());
         assert_eq!(
             paymaster_tracker
@@ -720,4 +958,153 @@ mod tests {
             paymaster_balance.saturating_sub(max_op_cost),
         );
     }
+
+    #[test]
+    fn test_paymaster_status_spend_rate() {
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
+        let paymaster = Address::random();
+        paymaster_tracker.paymaster_balances.insert(
+            paymaster,
+            PaymasterBalance {
+                pending: 100.into(),
+                confirmed: 10_000.into(),
+                pending_ops: 2,
+                ..Default::default()
+            },
+        );
+        let mined_op = |paymaster, actual_gas_cost: u64| MinedOp {
+            hash: H256::random(),
+            paymaster,
+            actual_gas_cost: actual_gas_cost.into(),
+            ..Default::default()
+        };
+
+        let status = paymaster_tracker.paymaster_status(paymaster).unwrap();
+        assert_eq!(status.pending_balance, 9_900.into());
+        assert_eq!(status.pending_ops, 2);
+        assert_eq!(status.spend_per_hour, 0.into());
+        assert_eq!(status.runway_seconds, None);
+
+        // costs of other paymasters and operations without one are not recorded
+        paymaster_tracker.record_spends(
+            &[
+                mined_op(Some(paymaster), 300),
+                mined_op(Some(Address::random()), 1_000),
+                mined_op(None, 1_000),
+            ],
+            &[],
+            1_000,
+        );
+        let reorged = mined_op(Some(paymaster), 600);
+        paymaster_tracker.record_spends(&[reorged], &[], 2_000);
+        let status = paymaster_tracker.paymaster_status(paymaster).unwrap();
+        assert_eq!(status.spend_per_hour, 900.into());
+        assert_eq!(status.runway_seconds, Some(39_600));
+
+        // costs of operations unmined by a reorg are forgotten
+        paymaster_tracker.record_spends(&[], &[reorged], 2_000);
+        let status = paymaster_tracker.paymaster_status(paymaster).unwrap();
+        assert_eq!(status.spend_per_hour, 300.into());
+        paymaster_tracker.record_spends(&[reorged], &[], 2_000);
+
+        // costs older than the spend window are forgotten
+        paymaster_tracker.record_spends(&[], &[], 4_700);
+        let status = paymaster_tracker.paymaster_status(paymaster).unwrap();
+        assert_eq!(status.spend_per_hour, 600.into());
+        assert_eq!(status.runway_seconds, Some(59_400));
+        assert_eq!(paymaster_tracker.paymaster_statuses().len(), 1);
+
+        // paymasters without pending operations or recent spends are idle
+        paymaster_tracker
+            .paymaster_balances
+            .get_mut(&paymaster)
+            .unwrap()
+            .pending_ops = 0;
+        assert_eq!(paymaster_tracker.paymaster_statuses().len(), 1);
+        paymaster_tracker.record_spends(&[], &[], 6_000);
+        assert!(paymaster_tracker.paymaster_statuses().is_empty());
+        assert!(paymaster_tracker.paymaster_status(paymaster).is_some());
+    }
+
+    #[test]
+    fn test_pending_token_spend() {
+        let mut paymaster_tracker = PaymasterTracker::new(true, SPEND_WINDOW);
+        let paymaster = Address::random();
+        let sender = Address::random();
+        let token = Address::random();
+        let paymaster_meta = PaymasterMetadata {
+            address: paymaster,
+            pending_balance: 100_000.into(),
+            confirmed_balance: 100_000.into(),
+        };
+        let token_op = |nonce: u64, token_cost: u64| PoolOperation {
+            token_cost: Some(token_cost.into()),
+            ..demo_pool_op(UserOperation {
+                sender,
+                nonce: nonce.into(),
+                call_gas_limit: 10.into(),
+                max_fee_per_gas: 1.into(),
+                ..Default::default()
+            })
+        };
+        let token_funds = |max_token_cost: u64| TokenFunds {
+            token,
+            max_token_cost: max_token_cost.into(),
+            balance: 1_000.into(),
+            allowance: 800.into(),
+        };
+
+        paymaster_tracker
+            .add_or_update_balance(&token_op(0, 400), &paymaster_meta, Some(&token_funds(400)))
+            .unwrap();
+        paymaster_tracker
+            .add_or_update_balance(&token_op(1, 300), &paymaster_meta, Some(&token_funds(300)))
+            .unwrap();
+        assert_eq!(
+            paymaster_tracker.pending_token_spend(paymaster, sender),
+            700.into()
+        );
+
+        // the sender's queued operations together exceed its allowance
+        let res = paymaster_tracker.add_or_update_balance(
+            &token_op(2, 200),
+            &paymaster_meta,
+            Some(&token_funds(200)),
+        );
+        assert!(matches!(
+            res,
+            Err(MempoolError::PrecheckViolation(
+                PrecheckViolation::SenderTokenAllowanceTooLow(_, allowance, required)
+            )) if allowance == 800.into() && required == 900.into()
+        ));
+        assert_eq!(
+            paymaster_tracker.pending_token_spend(paymaster, sender),
+            700.into()
+        );
+
+        // a replacement only counts its own token cost
+        paymaster_tracker
+            .add_or_update_balance(&token_op(1, 400), &paymaster_meta, Some(&token_funds(400)))
+            .unwrap();
+        assert_eq!(
+            paymaster_tracker.pending_token_spend(paymaster, sender),
+            800.into()
+        );
+
+        paymaster_tracker.remove_operation(&token_op(0, 400).uo.id());
+        assert_eq!(
+            paymaster_tracker.pending_token_spend(paymaster, sender),
+            400.into()
+        );
+
+        // an operation put back after a reorg counts its recorded token cost
+        // without its token funds being checked again
+        paymaster_tracker
+            .add_or_update_balance(&token_op(0, 400), &paymaster_meta, None)
+            .unwrap();
+        assert_eq!(
+            paymaster_tracker.pending_token_spend(paymaster, sender),
+            800.into()
+        );
+    }
 }
@@ -0,0 +1,203 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use ethers::types::Address;
+use parking_lot::Mutex;
+use reqwest::header::CONTENT_TYPE;
+use serde::Serialize;
+use tracing::warn;
+
+use super::PaymasterStatus;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings for webhook alerts on paymasters whose deposits are running out
+#[derive(Debug, Clone)]
+pub struct PaymasterAlertSettings {
+    /// URL to POST alerts to
+    pub webhook_url: String,
+    /// Alert when a paymaster's projected runway drops below this
+    pub min_runway: Duration,
+    /// Minimum time between alerts for the same paymaster
+    pub cooldown: Duration,
+}
+
+/// Body of an alert
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymasterAlert<'a> {
+    entry_point: Address,
+    min_runway_seconds: u64,
+    #[serde(flatten)]
+    status: &'a PaymasterStatus,
+}
+
+/// Sends webhook alerts when the runway of a paymaster drops below the
+/// configured threshold.
+///
+/// A paymaster is alerted on at most once per cooldown while its runway stays
+/// low. Once its runway recovers, the next drop is alerted on immediately.
+#[derive(Debug)]
+pub(crate) struct PaymasterAlerter {
+    settings: PaymasterAlertSettings,
+    client: reqwest::Client,
+    last_alerted: Mutex<HashMap<Address, Instant>>,
+}
+
+impl PaymasterAlerter {
+    pub(crate) fn new(settings: PaymasterAlertSettings) -> Self {
+        Self {
+            settings,
+            client: reqwest::Client::builder()
+                .timeout(WEBHOOK_TIMEOUT)
+                .build()
+                .expect("webhook client should build"),
+            last_alerted: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Alerts on the paymasters with a low runway. Alerts are sent in the
+    /// background, failures are logged.
+    pub(crate) fn check(&self, entry_point: Address, statuses: &[PaymasterStatus]) {
+        for status in self.to_alert(statuses, Instant::now()) {
+            self.send(entry_point, status);
+        }
+    }
+
+    fn to_alert<'a>(
+        &self,
+        statuses: &'a [PaymasterStatus],
+        now: Instant,
+    ) -> Vec<&'a PaymasterStatus> {
+        let min_runway = self.settings.min_runway.as_secs();
+        let mut last_alerted = self.last_alerted.lock();
+        let mut to_alert = vec![];
+        for status in statuses {
+            if !status.runway_seconds.is_some_and(|r| r < min_runway) {
+                last_alerted.remove(&status.address);
+                continue;
+            }
+            let in_cooldown = last_alerted
+                .get(&status.address)
+                .is_some_and(|at| now.duration_since(*at) < self.settings.cooldown);
+            if !in_cooldown {
+                last_alerted.insert(status.address, now);
+                to_alert.push(status);
+            }
+        }
+        to_alert
+    }
+
+    fn send(&self, entry_point: Address, status: &PaymasterStatus) {
+        warn!(
+            "Paymaster {:?} on entry point {entry_point:?} has {}s of runway left with a pending balance of {}",
+            status.address,
+            status.runway_seconds.unwrap_or_default(),
+            status.pending_balance,
+        );
+        PaymasterAlertMetrics::increment_alerts(entry_point);
+
+        let body = match serde_json::to_vec(&PaymasterAlert {
+            entry_point,
+            min_runway_seconds: self.settings.min_runway.as_secs(),
+            status,
+        }) {
+            Ok(body) => body,
+            Err(error) => {
+                warn!("Failed to serialize paymaster alert: {error:?}");
+                return;
+            }
+        };
+        let request = self
+            .client
+            .post(&self.settings.webhook_url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(body);
+        let paymaster = status.address;
+        tokio::spawn(async move {
+            let result = request
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(error) = result {
+                warn!("Failed to send alert for paymaster {paymaster:?}: {error:?}");
+                PaymasterAlertMetrics::increment_alert_failures(entry_point);
+            }
+        });
+    }
+}
+
+struct PaymasterAlertMetrics {}
+
+impl PaymasterAlertMetrics {
+    fn increment_alerts(entry_point: Address) {
+        metrics::increment_counter!("op_pool_paymaster_alerts", "entrypoint" => entry_point.to_string());
+    }
+
+    fn increment_alert_failures(entry_point: Address) {
+        metrics::increment_counter!("op_pool_paymaster_alert_failures", "entrypoint" => entry_point.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alerts_respect_cooldown_and_recovery() {
+        let alerter = PaymasterAlerter::new(PaymasterAlertSettings {
+            webhook_url: "http://localhost".to_string(),
+            min_runway: Duration::from_secs(3600),
+            cooldown: Duration::from_secs(600),
+        });
+        let status = |address, runway_seconds| PaymasterStatus {
+            address,
+            runway_seconds,
+            ..Default::default()
+        };
+        let (low, healthy, idle) = (Address::random(), Address::random(), Address::random());
+        let statuses = [
+            status(low, Some(100)),
+            status(healthy, Some(7200)),
+            status(idle, None),
+        ];
+        let start = Instant::now();
+
+        let alerted = alerter.to_alert(&statuses, start);
+        assert_eq!(alerted, vec![&statuses[0]]);
+        // still in cooldown
+        assert!(alerter
+            .to_alert(&statuses, start + Duration::from_secs(300))
+            .is_empty());
+        // cooldown over
+        assert_eq!(
+            alerter.to_alert(&statuses, start + Duration::from_secs(601)),
+            vec![&statuses[0]]
+        );
+
+        // a recovered paymaster is alerted on as soon as it drops again
+        let recovered = [status(low, Some(7200))];
+        assert!(alerter
+            .to_alert(&recovered, start + Duration::from_secs(602))
+            .is_empty());
+        assert_eq!(
+            alerter.to_alert(&statuses, start + Duration::from_secs(603)),
+            vec![&statuses[0]]
+        );
+    }
+}
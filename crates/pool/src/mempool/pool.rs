@@ -13,8 +13,9 @@
 
 use std::{
     cmp::Ordering,
-    collections::{hash_map::Entry, BTreeSet, HashMap, HashSet},
+    collections::{hash_map::Entry, BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use anyhow::Context;
@@ -22,6 +23,7 @@ use ethers::{
     abi::Address,
     types::{H256, U256},
 };
+use rundler_sim::TokenFunds;
 use rundler_types::{Entity, EntityType, Timestamp, UserOperation, UserOperationId};
 use rundler_utils::math;
 use tracing::info;
@@ -29,12 +31,19 @@ use tracing::info;
 use super::{
     entity_tracker::EntityCounter,
     error::{MempoolError, MempoolResult},
+    ordering::{OpPriority, OrderingPolicy},
     paymaster::PaymasterTracker,
     size::SizeTracker,
-    PaymasterMetadata, PoolConfig, PoolOperation,
+    MempoolFilter, OpPosition, PaymasterMetadata, PaymasterStatus, PoolConfig, PoolOperation,
 };
 use crate::chain::{BalanceUpdate, MinedOp};
 
+/// How long an operation cancelled by its sender is kept out of the pool
+const CANCELLED_OP_TTL: Duration = Duration::from_secs(60 * 60);
+/// Maximum number of cancelled operation hashes remembered, after which the
+/// oldest cancellations are forgotten early
+const MAX_CANCELLED_OPS: usize = 10_000;
+
 #[derive(Debug, Clone)]
 pub(crate) struct PoolInnerConfig {
     entry_point: Address,
@@ -44,6 +53,8 @@ pub(crate) struct PoolInnerConfig {
     throttled_entity_mempool_count: u64,
     throttled_entity_live_blocks: u64,
     paymaster_tracking_enabled: bool,
+    paymaster_spend_window: Duration,
+    ordering_policy: Arc<dyn OrderingPolicy>,
 }
 
 impl From<PoolConfig> for PoolInnerConfig {
@@ -56,6 +67,8 @@ impl From<PoolConfig> for PoolInnerConfig {
             throttled_entity_mempool_count: config.throttled_entity_mempool_count,
             throttled_entity_live_blocks: config.throttled_entity_live_blocks,
             paymaster_tracking_enabled: config.paymaster_tracking_enabled,
+            paymaster_spend_window: config.paymaster_spend_window,
+            ordering_policy: config.ordering_policy,
         }
     }
 }
@@ -69,8 +82,12 @@ pub(crate) struct PoolInner {
     by_hash: HashMap<H256, OrderedPoolOperation>,
     /// Operations by operation ID
     by_id: HashMap<UserOperationId, OrderedPoolOperation>,
-    /// Best operations, sorted by gas price
+    /// Best operations, sorted by the ordering policy
     best: BTreeSet<OrderedPoolOperation>,
+    /// Operation hashes by sender, sorted by nonce
+    by_sender: HashMap<Address, BTreeMap<U256, H256>>,
+    /// Operation hashes by paymaster
+    by_paymaster: HashMap<Address, HashSet<H256>>,
     /// Removed operations, temporarily kept around in case their blocks are
     /// reorged away. Stored along with the block number at which it was
     /// removed.
@@ -78,6 +95,12 @@ pub(crate) struct PoolInner {
     /// Removed operation hashes sorted by block number, so we can forget them
     /// when enough new blocks have passed.
     mined_hashes_with_block_numbers: BTreeSet<(u64, H256)>,
+    /// Operations cancelled by their senders, which can't be added again until
+    /// `CANCELLED_OP_TTL` after the time they were cancelled
+    cancelled_at_by_hash: HashMap<H256, Instant>,
+    /// Cancelled operation hashes in the order they were cancelled, so we can
+    /// forget them when they expire or there are too many.
+    cancelled_hashes: VecDeque<(Instant, H256)>,
     /// Count of operations by entity address
     count_by_address: HashMap<Address, EntityCounter>,
     /// Submission ID counter
@@ -88,34 +111,45 @@ pub(crate) struct PoolInner {
     pool_size: SizeTracker,
     /// keeps track of the size of the removed cache in bytes
     cache_size: SizeTracker,
+    /// The latest base fee, used by the ordering policy
+    base_fee: U256,
 }
 
 impl PoolInner {
     pub(crate) fn new(config: PoolInnerConfig) -> Self {
         Self {
-            paymaster_balances: PaymasterTracker::new(config.paymaster_tracking_enabled),
+            paymaster_balances: PaymasterTracker::new(
+                config.paymaster_tracking_enabled,
+                config.paymaster_spend_window,
+            ),
             config,
             by_hash: HashMap::new(),
             by_id: HashMap::new(),
             best: BTreeSet::new(),
+            by_sender: HashMap::new(),
+            by_paymaster: HashMap::new(),
             mined_at_block_number_by_hash: HashMap::new(),
             mined_hashes_with_block_numbers: BTreeSet::new(),
+            cancelled_at_by_hash: HashMap::new(),
+            cancelled_hashes: VecDeque::new(),
             count_by_address: HashMap::new(),
             submission_id: 0,
             pool_size: SizeTracker::default(),
             cache_size: SizeTracker::default(),
+            base_fee: U256::zero(),
         }
     }
 
     /// Returns hash of operation to replace if operation is a replacement
     pub(crate) fn check_replacement(&self, op: &UserOperation) -> MempoolResult<Option<H256>> {
         // Check if operation already known
-        if self
-            .by_hash
-            .contains_key(&op.op_hash(self.config.entry_point, self.config.chain_id))
-        {
+        let hash = op.op_hash(self.config.entry_point, self.config.chain_id);
+        if self.by_hash.contains_key(&hash) {
             return Err(MempoolError::OperationAlreadyKnown);
         }
+        if self.is_cancelled(hash, Instant::now()) {
+            return Err(MempoolError::OperationCancelled);
+        }
 
         if let Some(pool_op) = self.by_id.get(&op.id()) {
             let (replacement_priority_fee, replacement_fee) =
@@ -144,8 +178,9 @@ impl PoolInner {
         &mut self,
         op: PoolOperation,
         paymaster_meta: Option<PaymasterMetadata>,
+        token_funds: Option<TokenFunds>,
     ) -> MempoolResult<H256> {
-        let ret = self.add_operation_internal(Arc::new(op), None, paymaster_meta);
+        let ret = self.add_operation_internal(Arc::new(op), None, paymaster_meta, token_funds);
         self.update_metrics();
         ret
     }
@@ -163,6 +198,29 @@ impl PoolInner {
             .set_confimed_balances(addresses, balances);
     }
 
+    /// Updates the base fee used by the ordering policy, re-sorting the pool
+    /// if the policy depends on it.
+    ///
+    /// NOTE: This method is O(n log n) where n is the number of operations in the pool
+    /// when re-sorting is required. It should be called sparingly (e.g. when a block is mined).
+    pub(crate) fn set_base_fee(&mut self, base_fee: U256) {
+        if self.base_fee == base_fee {
+            return;
+        }
+        self.base_fee = base_fee;
+
+        if !self.config.ordering_policy.depends_on_base_fee() {
+            return;
+        }
+
+        self.best.clear();
+        for op in self.by_hash.values_mut() {
+            op.priority = self.config.ordering_policy.priority(&op.po, base_fee);
+            self.by_id.insert(op.uo().id(), op.clone());
+            self.best.insert(op.clone());
+        }
+    }
+
     pub(crate) fn best_operations(&self) -> impl Iterator<Item = Arc<PoolOperation>> {
         self.best.clone().into_iter().map(|v| v.po)
     }
@@ -198,12 +256,93 @@ impl PoolInner {
         self.by_hash.get(&hash).map(|o| o.po.clone())
     }
 
+    /// Returns the position of an operation in pool order, along with the
+    /// number of operations in the pool, or `None` if the operation is not in
+    /// the pool.
+    pub(crate) fn operation_position(&self, hash: H256) -> Option<OpPosition> {
+        let op = self.by_hash.get(&hash)?;
+        Some(OpPosition {
+            position: self.best.range(..op).count() as u64,
+            pool_size: self.by_hash.len() as u64,
+        })
+    }
+
+    pub(crate) fn operations_by_sender(&self, sender: Address) -> Vec<Arc<PoolOperation>> {
+        self.by_sender
+            .get(&sender)
+            .into_iter()
+            .flat_map(|hashes| hashes.values())
+            .filter_map(|hash| self.get_operation_by_hash(*hash))
+            .collect()
+    }
+
+    pub(crate) fn operations_by_paymaster(&self, paymaster: Address) -> Vec<Arc<PoolOperation>> {
+        self.sorted_operations(self.by_paymaster.get(&paymaster).into_iter().flatten())
+            .into_iter()
+            .map(|op| op.po.clone())
+            .collect()
+    }
+
+    /// Returns a page of the operations matching the filter, in pool order,
+    /// along with the total number of matching operations.
+    ///
+    /// NOTE: Unless the filter has a sender or paymaster, this method is O(n)
+    /// where n is the number of operations in the pool.
+    pub(crate) fn filtered_operations(
+        &self,
+        filter: &MempoolFilter,
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<Arc<PoolOperation>>, usize) {
+        let candidates = if let Some(sender) = filter.sender {
+            self.sorted_operations(
+                self.by_sender
+                    .get(&sender)
+                    .into_iter()
+                    .flat_map(|hashes| hashes.values()),
+            )
+        } else if let Some(paymaster) = filter.paymaster {
+            self.sorted_operations(self.by_paymaster.get(&paymaster).into_iter().flatten())
+        } else {
+            self.best.iter().collect()
+        };
+
+        let matching = candidates
+            .into_iter()
+            .filter(|op| filter.matches(&op.po))
+            .collect::<Vec<_>>();
+        let total = matching.len();
+        let page = matching
+            .into_iter()
+            .skip(offset)
+            .take(limit)
+            .map(|op| op.po.clone())
+            .collect();
+        (page, total)
+    }
+
     pub(crate) fn remove_operation_by_hash(&mut self, hash: H256) -> Option<Arc<PoolOperation>> {
         let ret = self.remove_operation_internal(hash, None);
         self.update_metrics();
         ret
     }
 
+    /// Removes an operation cancelled by its sender, and keeps it from being
+    /// added again until the cancellation expires.
+    pub(crate) fn cancel_operation(&mut self, hash: H256) -> Option<Arc<PoolOperation>> {
+        let ret = self.remove_operation_by_hash(hash);
+        if ret.is_some() {
+            let now = Instant::now();
+            self.forget_cancelled_operations(now);
+            if self.cancelled_hashes.len() >= MAX_CANCELLED_OPS {
+                self.forget_oldest_cancelled_operation();
+            }
+            self.cancelled_at_by_hash.insert(hash, now);
+            self.cancelled_hashes.push_back((now, hash));
+        }
+        ret
+    }
+
     // STO-040
     pub(crate) fn check_multiple_roles_violation(&self, uo: &UserOperation) -> MempoolResult<()> {
         if let Some(ec) = self.count_by_address.get(&uo.sender) {
@@ -349,6 +488,34 @@ impl PoolInner {
         self.update_metrics();
     }
 
+    fn is_cancelled(&self, hash: H256, now: Instant) -> bool {
+        self.cancelled_at_by_hash
+            .get(&hash)
+            .is_some_and(|cancelled_at| now.duration_since(*cancelled_at) < CANCELLED_OP_TTL)
+    }
+
+    fn forget_cancelled_operations(&mut self, now: Instant) {
+        while self
+            .cancelled_hashes
+            .front()
+            .is_some_and(|(cancelled_at, _)| now.duration_since(*cancelled_at) >= CANCELLED_OP_TTL)
+        {
+            self.forget_oldest_cancelled_operation();
+        }
+    }
+
+    fn forget_oldest_cancelled_operation(&mut self) {
+        if let Some((cancelled_at, hash)) = self.cancelled_hashes.pop_front() {
+            // the op may have been cancelled again since, in which case the
+            // later cancellation is kept
+            if let Entry::Occupied(entry) = self.cancelled_at_by_hash.entry(hash) {
+                if *entry.get() == cancelled_at {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
     pub(crate) fn paymaster_metadata(&self, paymaster: Address) -> Option<PaymasterMetadata> {
         self.paymaster_balances.paymaster_metadata(paymaster)
     }
@@ -357,6 +524,24 @@ impl PoolInner {
         self.paymaster_balances.paymaster_exists(paymaster)
     }
 
+    pub(crate) fn paymaster_status(&self, paymaster: Address) -> Option<PaymasterStatus> {
+        self.paymaster_balances.paymaster_status(paymaster)
+    }
+
+    pub(crate) fn paymaster_statuses(&self) -> Vec<PaymasterStatus> {
+        self.paymaster_balances.paymaster_statuses()
+    }
+
+    pub(crate) fn record_paymaster_spends<'a>(
+        &mut self,
+        mined_ops: impl IntoIterator<Item = &'a MinedOp>,
+        unmined_ops: impl IntoIterator<Item = &'a MinedOp>,
+        block_timestamp: u64,
+    ) {
+        self.paymaster_balances
+            .record_spends(mined_ops, unmined_ops, block_timestamp);
+    }
+
     pub(crate) fn update_paymaster_balances_after_update<'a>(
         &mut self,
         entity_balance_updates: impl Iterator<Item = &'a BalanceUpdate>,
@@ -384,8 +569,12 @@ impl PoolInner {
             self.by_hash.clear();
             self.by_id.clear();
             self.best.clear();
+            self.by_sender.clear();
+            self.by_paymaster.clear();
             self.mined_at_block_number_by_hash.clear();
             self.mined_hashes_with_block_numbers.clear();
+            self.cancelled_at_by_hash.clear();
+            self.cancelled_hashes.clear();
             self.count_by_address.clear();
             self.pool_size = SizeTracker::default();
             self.cache_size = SizeTracker::default();
@@ -434,7 +623,9 @@ impl PoolInner {
             paymaster_meta = self.paymaster_metadata(paymaster);
         }
 
-        self.add_operation_internal(op.po, Some(op.submission_id), paymaster_meta)
+        // The sender's token funds are not reloaded, so a token paymaster
+        // operation is put back with its recorded token cost, unchecked
+        self.add_operation_internal(op.po, Some(op.submission_id), paymaster_meta, None)
     }
 
     fn add_operation_internal(
@@ -442,6 +633,7 @@ impl PoolInner {
         op: Arc<PoolOperation>,
         submission_id: Option<u64>,
         paymaster_meta: Option<PaymasterMetadata>,
+        token_funds: Option<TokenFunds>,
     ) -> MempoolResult<H256> {
         // Check if operation already known or replacing an existing operation
         // if replacing, remove the existing operation
@@ -451,11 +643,15 @@ impl PoolInner {
 
         // check or update paymaster balance
         if let Some(paymaster_meta) = paymaster_meta {
-            self.paymaster_balances
-                .add_or_update_balance(&op, &paymaster_meta)?;
+            self.paymaster_balances.add_or_update_balance(
+                &op,
+                &paymaster_meta,
+                token_funds.as_ref(),
+            )?;
         }
 
         let pool_op = OrderedPoolOperation {
+            priority: self.config.ordering_policy.priority(&op, self.base_fee),
             po: op,
             submission_id: submission_id.unwrap_or_else(|| self.next_submission_id()),
         };
@@ -475,6 +671,13 @@ impl PoolInner {
         self.pool_size += pool_op.mem_size();
         self.by_hash.insert(hash, pool_op.clone());
         self.by_id.insert(pool_op.uo().id(), pool_op.clone());
+        self.by_sender
+            .entry(pool_op.uo().sender)
+            .or_default()
+            .insert(pool_op.uo().nonce, hash);
+        if let Some(paymaster) = pool_op.uo().paymaster() {
+            self.by_paymaster.entry(paymaster).or_default().insert(hash);
+        }
         self.best.insert(pool_op);
 
         // TODO(danc): This silently drops UOs from the pool without reporting
@@ -498,6 +701,20 @@ impl PoolInner {
         let id = &op.po.uo.id();
         self.by_id.remove(id);
         self.best.remove(&op);
+        if let Entry::Occupied(mut hashes) = self.by_sender.entry(id.sender) {
+            hashes.get_mut().remove(&id.nonce);
+            if hashes.get().is_empty() {
+                hashes.remove_entry();
+            }
+        }
+        if let Some(paymaster) = op.uo().paymaster() {
+            if let Entry::Occupied(mut hashes) = self.by_paymaster.entry(paymaster) {
+                hashes.get_mut().remove(&hash);
+                if hashes.get().is_empty() {
+                    hashes.remove_entry();
+                }
+            }
+        }
         self.paymaster_balances.remove_operation(id);
 
         if let Some(block_number) = block_number {
@@ -525,6 +742,18 @@ impl PoolInner {
         }
     }
 
+    // Looks up operations by hash, sorted in pool order
+    fn sorted_operations<'a>(
+        &'a self,
+        hashes: impl Iterator<Item = &'a H256>,
+    ) -> Vec<&'a OrderedPoolOperation> {
+        let mut ops = hashes
+            .filter_map(|hash| self.by_hash.get(hash))
+            .collect::<Vec<_>>();
+        ops.sort();
+        ops
+    }
+
     fn next_submission_id(&mut self) -> u64 {
         let id = self.submission_id;
         self.submission_id += 1;
@@ -557,12 +786,13 @@ impl PoolInner {
     }
 }
 
-/// Wrapper around PoolOperation that adds a submission ID to implement
-/// a custom ordering for the best operations
+/// Wrapper around PoolOperation that adds a submission ID and a priority
+/// assigned by the ordering policy to implement a custom ordering for the best operations
 #[derive(Debug, Clone)]
 struct OrderedPoolOperation {
     po: Arc<PoolOperation>,
     submission_id: u64,
+    priority: OpPriority,
 }
 
 impl OrderedPoolOperation {
@@ -579,11 +809,10 @@ impl Eq for OrderedPoolOperation {}
 
 impl Ord for OrderedPoolOperation {
     fn cmp(&self, other: &Self) -> Ordering {
-        // Sort by gas price descending then by id ascending
+        // Sort by priority descending then by id ascending
         other
-            .uo()
-            .max_fee_per_gas
-            .cmp(&self.uo().max_fee_per_gas)
+            .priority
+            .cmp(&self.priority)
             .then_with(|| self.submission_id.cmp(&other.submission_id))
     }
 }
@@ -618,12 +847,15 @@ mod tests {
     use rundler_sim::{EntityInfo, EntityInfos};
 
     use super::*;
+    use crate::mempool::{
+        EffectiveTipOrdering, FifoOrdering, MaxFeeOrdering, OrderingKind, PriorityBoosts,
+    };
 
     #[test]
     fn add_single_op() {
         let mut pool = PoolInner::new(conf());
         let op = create_op(Address::random(), 0, 1);
-        let hash = pool.add_operation(op.clone(), None).unwrap();
+        let hash = pool.add_operation(op.clone(), None, None).unwrap();
 
         check_map_entry(pool.by_hash.get(&hash), Some(&op));
         check_map_entry(pool.by_id.get(&op.uo.id()), Some(&op));
@@ -641,7 +873,7 @@ mod tests {
 
         let mut hashes = vec![];
         for op in ops.iter() {
-            hashes.push(pool.add_operation(op.clone(), None).unwrap());
+            hashes.push(pool.add_operation(op.clone(), None, None).unwrap());
         }
 
         for (hash, op) in hashes.iter().zip(&ops) {
@@ -656,6 +888,32 @@ mod tests {
         check_map_entry(pool.best.iter().nth(2), Some(&ops[0]));
     }
 
+    #[test]
+    fn operation_position() {
+        let mut pool = PoolInner::new(conf());
+        let ops = vec![
+            create_op(Address::random(), 0, 1),
+            create_op(Address::random(), 0, 3),
+            create_op(Address::random(), 0, 2),
+        ];
+        let hashes = ops
+            .iter()
+            .map(|op| pool.add_operation(op.clone(), None, None).unwrap())
+            .collect::<Vec<_>>();
+
+        // ordered by max fee
+        for (hash, position) in hashes.iter().zip([2, 0, 1]) {
+            assert_eq!(
+                pool.operation_position(*hash),
+                Some(OpPosition {
+                    position,
+                    pool_size: 3
+                })
+            );
+        }
+        assert_eq!(pool.operation_position(H256::random()), None);
+    }
+
     #[test]
     fn best_ties() {
         let mut pool = PoolInner::new(conf());
@@ -667,7 +925,7 @@ mod tests {
 
         let mut hashes = vec![];
         for op in ops.iter() {
-            hashes.push(pool.add_operation(op.clone(), None).unwrap());
+            hashes.push(pool.add_operation(op.clone(), None, None).unwrap());
         }
 
         // best should be sorted by gas, then by submission id
@@ -677,6 +935,75 @@ mod tests {
         check_map_entry(pool.best.iter().nth(2), Some(&ops[2]));
     }
 
+    #[test]
+    fn best_fifo() {
+        let mut pool = PoolInner::new(PoolInnerConfig {
+            ordering_policy: Arc::new(FifoOrdering),
+            ..conf()
+        });
+        let ops = vec![
+            create_op(Address::random(), 0, 1),
+            create_op(Address::random(), 0, 3),
+            create_op(Address::random(), 0, 2),
+        ];
+        for op in ops.iter() {
+            pool.add_operation(op.clone(), None, None).unwrap();
+        }
+
+        // best should be sorted by submission id only
+        check_map_entry(pool.best.iter().next(), Some(&ops[0]));
+        check_map_entry(pool.best.iter().nth(1), Some(&ops[1]));
+        check_map_entry(pool.best.iter().nth(2), Some(&ops[2]));
+    }
+
+    #[test]
+    fn best_effective_tip_resorts_on_base_fee() {
+        let mut pool = PoolInner::new(PoolInnerConfig {
+            ordering_policy: Arc::new(EffectiveTipOrdering),
+            ..conf()
+        });
+        let mut po1 = create_op(Address::random(), 0, 20);
+        po1.uo.max_priority_fee_per_gas = 3.into();
+        let mut po2 = create_op(Address::random(), 0, 12);
+        po2.uo.max_priority_fee_per_gas = 5.into();
+        pool.add_operation(po1.clone(), None, None).unwrap();
+        let hash2 = pool.add_operation(po2.clone(), None, None).unwrap();
+
+        // base fee of 0, po1 pays 3 and po2 pays 5
+        check_map_entry(pool.best.iter().next(), Some(&po2));
+        check_map_entry(pool.best.iter().nth(1), Some(&po1));
+
+        // base fee of 10, po1 pays 3 and po2 pays 2
+        pool.set_base_fee(10.into());
+        check_map_entry(pool.best.iter().next(), Some(&po1));
+        check_map_entry(pool.best.iter().nth(1), Some(&po2));
+
+        // removal must still find the re-sorted operation
+        assert!(pool.remove_operation_by_hash(hash2).is_some());
+        assert_eq!(pool.best.len(), 1);
+        check_map_entry(pool.best.iter().next(), Some(&po1));
+    }
+
+    #[test]
+    fn best_boosted_paymaster() {
+        let paymaster = Address::random();
+        let mut pool = PoolInner::new(PoolInnerConfig {
+            ordering_policy: OrderingKind::MaxFee.into_policy(Some(PriorityBoosts {
+                paymasters: HashMap::from([(paymaster, 1)]),
+                ..Default::default()
+            })),
+            ..conf()
+        });
+        let po1 = create_op(Address::random(), 0, 10);
+        let mut po2 = create_op(Address::random(), 0, 1);
+        po2.uo.paymaster_and_data = paymaster.as_bytes().to_vec().into();
+        pool.add_operation(po1.clone(), None, None).unwrap();
+        pool.add_operation(po2.clone(), None, None).unwrap();
+
+        check_map_entry(pool.best.iter().next(), Some(&po2));
+        check_map_entry(pool.best.iter().nth(1), Some(&po1));
+    }
+
     #[test]
     fn remove_op() {
         let mut pool = PoolInner::new(conf());
@@ -688,7 +1015,7 @@ mod tests {
 
         let mut hashes = vec![];
         for op in ops.iter() {
-            hashes.push(pool.add_operation(op.clone(), None).unwrap());
+            hashes.push(pool.add_operation(op.clone(), None, None).unwrap());
         }
 
         assert!(pool.remove_operation_by_hash(hashes[0]).is_some());
@@ -719,7 +1046,7 @@ mod tests {
         ];
         for mut op in ops.into_iter() {
             op.aggregator = Some(account);
-            pool.add_operation(op.clone(), None).unwrap();
+            pool.add_operation(op.clone(), None, None).unwrap();
         }
         assert_eq!(pool.by_hash.len(), 3);
 
@@ -739,7 +1066,7 @@ mod tests {
 
         let hash = op.uo.op_hash(pool.config.entry_point, pool.config.chain_id);
 
-        pool.add_operation(op, None).unwrap();
+        pool.add_operation(op, None, None).unwrap();
 
         let mined_op = MinedOp {
             paymaster: None,
@@ -748,6 +1075,7 @@ mod tests {
             entry_point: pool.config.entry_point,
             sender,
             nonce: U256::from(nonce),
+            ..Default::default()
         };
 
         pool.mine_operation(&mined_op, 1);
@@ -770,8 +1098,8 @@ mod tests {
             .uo
             .op_hash(pool.config.entry_point, pool.config.chain_id);
 
-        pool.add_operation(op, None).unwrap();
-        pool.add_operation(op_2, None).unwrap();
+        pool.add_operation(op, None, None).unwrap();
+        pool.add_operation(op_2, None, None).unwrap();
 
         let mined_op = MinedOp {
             paymaster: None,
@@ -780,6 +1108,7 @@ mod tests {
             entry_point: pool.config.entry_point,
             sender,
             nonce: U256::from(nonce),
+            ..Default::default()
         };
 
         pool.mine_operation(&mined_op, 1);
@@ -804,7 +1133,7 @@ mod tests {
                 address: agg,
                 is_staked: false,
             });
-            pool.add_operation(op.clone(), None).unwrap();
+            pool.add_operation(op.clone(), None, None).unwrap();
         }
         assert_eq!(pool.by_hash.len(), 3);
 
@@ -829,7 +1158,7 @@ mod tests {
                 address: op.uo.paymaster().unwrap(),
                 is_staked: false,
             });
-            pool.add_operation(op.clone(), None).unwrap();
+            pool.add_operation(op.clone(), None, None).unwrap();
         }
         assert_eq!(pool.by_hash.len(), 3);
 
@@ -869,7 +1198,7 @@ mod tests {
         for i in 0..count {
             let mut op = op.clone();
             op.uo.nonce = i.into();
-            hashes.push(pool.add_operation(op, None).unwrap());
+            hashes.push(pool.add_operation(op, None, None).unwrap());
         }
 
         assert_eq!(pool.address_count(&sender), 5);
@@ -893,12 +1222,12 @@ mod tests {
         let mut pool = PoolInner::new(args.clone());
         for i in 0..20 {
             let op = create_op(Address::random(), i, i + 1);
-            pool.add_operation(op, None).unwrap();
+            pool.add_operation(op, None, None).unwrap();
         }
 
         // on greater gas, new op should win
         let op = create_op(Address::random(), args.max_size_of_pool_bytes, 2);
-        let result = pool.add_operation(op, None);
+        let result = pool.add_operation(op, None, None);
         assert!(result.is_ok(), "{:?}", result.err());
     }
 
@@ -908,15 +1237,15 @@ mod tests {
         let mut pool = PoolInner::new(args.clone());
         for i in 0..20 {
             let op = create_op(Address::random(), i, i + 1);
-            pool.add_operation(op, None).unwrap();
+            pool.add_operation(op, None, None).unwrap();
         }
 
         let op = create_op(Address::random(), 4, 1);
-        assert!(pool.add_operation(op, None).is_err());
+        assert!(pool.add_operation(op, None, None).is_err());
 
         // on equal gas, worst should remain because it came first
         let op = create_op(Address::random(), 4, 2);
-        let result = pool.add_operation(op, None);
+        let result = pool.add_operation(op, None, None);
         assert!(result.is_ok(), "{:?}", result.err());
     }
 
@@ -926,11 +1255,11 @@ mod tests {
         let sender = Address::random();
         let mut po1 = create_op(sender, 0, 100);
         po1.uo.max_priority_fee_per_gas = 100.into();
-        let _ = pool.add_operation(po1.clone(), None).unwrap();
+        let _ = pool.add_operation(po1.clone(), None, None).unwrap();
 
         let mut po2 = create_op(sender, 0, 101);
         po2.uo.max_priority_fee_per_gas = 101.into();
-        let res = pool.add_operation(po2, None);
+        let res = pool.add_operation(po2, None, None);
         assert!(res.is_err());
         match res.err().unwrap() {
             MempoolError::ReplacementUnderpriced(a, b) => {
@@ -945,7 +1274,8 @@ mod tests {
             pool.pool_size,
             OrderedPoolOperation {
                 po: Arc::new(po1),
-                submission_id: 0
+                submission_id: 0,
+                priority: OpPriority::default(),
             }
             .mem_size()
         );
@@ -963,7 +1293,7 @@ mod tests {
             address: po1.uo.paymaster().unwrap(),
             is_staked: false,
         });
-        let _ = pool.add_operation(po1, None).unwrap();
+        let _ = pool.add_operation(po1, None, None).unwrap();
         assert_eq!(pool.address_count(&paymaster1), 1);
 
         let paymaster2 = Address::random();
@@ -974,7 +1304,7 @@ mod tests {
             address: po2.uo.paymaster().unwrap(),
             is_staked: false,
         });
-        let _ = pool.add_operation(po2.clone(), None).unwrap();
+        let _ = pool.add_operation(po2.clone(), None, None).unwrap();
 
         assert_eq!(pool.address_count(&sender), 1);
         assert_eq!(pool.address_count(&paymaster1), 0);
@@ -983,7 +1313,8 @@ mod tests {
             pool.pool_size,
             OrderedPoolOperation {
                 po: Arc::new(po2),
-                submission_id: 0
+                submission_id: 0,
+                priority: OpPriority::default(),
             }
             .mem_size()
         );
@@ -995,9 +1326,9 @@ mod tests {
         let sender = Address::random();
         let mut po1 = create_op(sender, 0, 10);
         po1.uo.max_priority_fee_per_gas = 10.into();
-        let _ = pool.add_operation(po1.clone(), None).unwrap();
+        let _ = pool.add_operation(po1.clone(), None, None).unwrap();
 
-        let res = pool.add_operation(po1, None);
+        let res = pool.add_operation(po1, None, None);
         assert!(res.is_err());
         match res.err().unwrap() {
             MempoolError::OperationAlreadyKnown => (),
@@ -1005,6 +1336,46 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cancelled() {
+        let conf = conf();
+        let mut pool = PoolInner::new(conf.clone());
+        let po1 = create_op(Address::random(), 0, 10);
+        let hash = po1.uo.op_hash(conf.entry_point, conf.chain_id);
+        let _ = pool.add_operation(po1.clone(), None, None).unwrap();
+        assert!(pool.cancel_operation(hash).is_some());
+
+        let res = pool.add_operation(po1, None, None);
+        assert!(matches!(res, Err(MempoolError::OperationCancelled)));
+
+        // the cancellation expires
+        let later = Instant::now() + CANCELLED_OP_TTL;
+        assert!(!pool.is_cancelled(hash, later));
+        pool.forget_cancelled_operations(later);
+        assert!(pool.cancelled_at_by_hash.is_empty());
+        assert!(pool.cancelled_hashes.is_empty());
+    }
+
+    #[test]
+    fn test_cancelled_bounded() {
+        let conf = conf();
+        let mut pool = PoolInner::new(conf.clone());
+        let po1 = create_op(Address::random(), 0, 10);
+        let hash = po1.uo.op_hash(conf.entry_point, conf.chain_id);
+        for _ in 0..MAX_CANCELLED_OPS {
+            let other = H256::random();
+            pool.cancelled_at_by_hash.insert(other, Instant::now());
+            pool.cancelled_hashes.push_back((Instant::now(), other));
+        }
+        let oldest = pool.cancelled_hashes[0].1;
+
+        let _ = pool.add_operation(po1, None, None).unwrap();
+        pool.cancel_operation(hash);
+        assert_eq!(pool.cancelled_hashes.len(), MAX_CANCELLED_OPS);
+        assert!(!pool.is_cancelled(oldest, Instant::now()));
+        assert!(pool.is_cancelled(hash, Instant::now()));
+    }
+
     #[test]
     fn test_expired() {
         let conf = conf();
@@ -1012,7 +1383,7 @@ mod tests {
         let sender = Address::random();
         let mut po1 = create_op(sender, 0, 10);
         po1.valid_time_range.valid_until = Timestamp::from(1);
-        let _ = pool.add_operation(po1.clone(), None).unwrap();
+        let _ = pool.add_operation(po1.clone(), None, None).unwrap();
 
         let res = pool.remove_expired(Timestamp::from(2));
         assert_eq!(res.len(), 1);
@@ -1027,15 +1398,15 @@ mod tests {
 
         let mut po1 = create_op(Address::random(), 0, 10);
         po1.valid_time_range.valid_until = 5.into();
-        let _ = pool.add_operation(po1.clone(), None).unwrap();
+        let _ = pool.add_operation(po1.clone(), None, None).unwrap();
 
         let mut po2 = create_op(Address::random(), 0, 10);
         po2.valid_time_range.valid_until = 10.into();
-        let _ = pool.add_operation(po2.clone(), None).unwrap();
+        let _ = pool.add_operation(po2.clone(), None, None).unwrap();
 
         let mut po3 = create_op(Address::random(), 0, 10);
         po3.valid_time_range.valid_until = 9.into();
-        let _ = pool.add_operation(po3.clone(), None).unwrap();
+        let _ = pool.add_operation(po3.clone(), None, None).unwrap();
 
         let res = pool.remove_expired(10.into());
         assert_eq!(res.len(), 2);
@@ -1043,6 +1414,86 @@ mod tests {
         assert!(res.contains(&(po3.uo.op_hash(conf.entry_point, conf.chain_id), 9.into())));
     }
 
+    #[test]
+    fn ops_by_sender_and_paymaster() {
+        let mut pool = PoolInner::new(conf());
+        let sender = Address::random();
+        let paymaster = Address::random();
+        let mut ops = vec![
+            create_op(sender, 1, 3),
+            create_op(sender, 0, 1),
+            create_op(Address::random(), 0, 2),
+        ];
+        for op in &mut ops[1..] {
+            op.uo.paymaster_and_data = paymaster.as_bytes().to_vec().into();
+        }
+        for op in &ops {
+            pool.add_operation(op.clone(), None, None).unwrap();
+        }
+
+        // sender ops are ordered by nonce, paymaster ops by priority
+        let by_sender = pool.operations_by_sender(sender);
+        assert_eq!(by_sender.len(), 2);
+        assert_eq!(*by_sender[0], ops[1]);
+        assert_eq!(*by_sender[1], ops[0]);
+        let by_paymaster = pool.operations_by_paymaster(paymaster);
+        assert_eq!(by_paymaster.len(), 2);
+        assert_eq!(*by_paymaster[0], ops[2]);
+        assert_eq!(*by_paymaster[1], ops[1]);
+
+        pool.remove_operation_by_hash(
+            ops[1]
+                .uo
+                .op_hash(pool.config.entry_point, pool.config.chain_id),
+        );
+        assert_eq!(pool.operations_by_sender(sender).len(), 1);
+        assert_eq!(pool.operations_by_paymaster(paymaster).len(), 1);
+        assert!(pool.by_sender.contains_key(&sender));
+
+        pool.clear(true, false);
+        assert!(pool.operations_by_sender(sender).is_empty());
+        assert!(pool.by_paymaster.is_empty());
+    }
+
+    #[test]
+    fn filtered_ops_paginated() {
+        let mut pool = PoolInner::new(conf());
+        let paymaster = Address::random();
+        let mut ops = vec![];
+        for fee in 1..=5 {
+            let mut op = create_op(Address::random(), 0, fee);
+            if fee % 2 == 1 {
+                op.uo.paymaster_and_data = paymaster.as_bytes().to_vec().into();
+            }
+            op.sim_block_number = fee as u64;
+            pool.add_operation(op.clone(), None, None).unwrap();
+            ops.push(op);
+        }
+
+        let filter = MempoolFilter {
+            min_max_fee_per_gas: Some(2.into()),
+            ..Default::default()
+        };
+        let (page, total) = pool.filtered_operations(&filter, 1, 2);
+        assert_eq!(total, 4);
+        assert_eq!(*page[0], ops[3]);
+        assert_eq!(*page[1], ops[2]);
+
+        let filter = MempoolFilter {
+            paymaster: Some(paymaster),
+            max_sim_block_number: Some(3),
+            ..Default::default()
+        };
+        let (page, total) = pool.filtered_operations(&filter, 0, 10);
+        assert_eq!(total, 2);
+        assert_eq!(*page[0], ops[2]);
+        assert_eq!(*page[1], ops[0]);
+
+        let (page, total) = pool.filtered_operations(&MempoolFilter::default(), 10, 10);
+        assert_eq!(total, 5);
+        assert!(page.is_empty());
+    }
+
     fn conf() -> PoolInnerConfig {
         PoolInnerConfig {
             entry_point: Address::random(),
@@ -1052,6 +1503,8 @@ mod tests {
             throttled_entity_mempool_count: 4,
             throttled_entity_live_blocks: 10,
             paymaster_tracking_enabled: true,
+            paymaster_spend_window: Duration::from_secs(3600),
+            ordering_policy: Arc::new(MaxFeeOrdering),
         }
     }
 
@@ -1059,6 +1512,7 @@ mod tests {
         OrderedPoolOperation {
             po: Arc::new(create_op(Address::random(), 1, 1)),
             submission_id: 1,
+            priority: OpPriority::default(),
         }
         .mem_size()
     }
@@ -12,19 +12,30 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
     time::Duration,
 };
 
+use anyhow::Context;
 use ethers::types::Address;
 #[cfg(test)]
 use mockall::automock;
 use parking_lot::RwLock;
+use rundler_types::{Entity, EntityType};
 use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
-use tokio::time::interval;
+use tokio::time::{interval, interval_at, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// How often the reputation snapshot is persisted, if a snapshot path is configured
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(60);
 
 /// Reputation status for an entity
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+///
+/// Ordered by severity, `Ok` < `Throttled` < `Banned`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ReputationStatus {
     /// Entity is not throttled or banned
     Ok,
@@ -63,7 +74,11 @@ impl<'de> Deserialize<'de> for ReputationStatus {
 }
 
 /// The reputation of an entity
-#[derive(Debug, Clone)]
+///
+/// A list of reputations is used as the snapshot format when exporting and importing
+/// reputation state.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Reputation {
     /// The entity's address
     pub address: Address,
@@ -71,6 +86,30 @@ pub struct Reputation {
     pub ops_seen: u64,
     /// Number of ops included in the current interval
     pub ops_included: u64,
+    /// Entity types the address has been seen as, used to apply per entity
+    /// type overrides. Empty if unknown.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<EntityType>,
+}
+
+/// An address list that overrides the reputation of its entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressList {
+    /// Addresses that are always banned
+    Blocklist,
+    /// Addresses that are always exempt from throttling and banning
+    Allowlist,
+}
+
+/// An update to an address list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressListUpdate {
+    /// Replace the entire list
+    Replace(HashSet<Address>),
+    /// Add addresses to the list
+    Add(Vec<Address>),
+    /// Remove addresses from the list
+    Remove(Vec<Address>),
 }
 
 /// Reputation manager trait
@@ -84,13 +123,13 @@ pub(crate) trait ReputationManager: Send + Sync + 'static {
 
     /// Called by mempool when an operation that requires stake is added to the
     /// pool
-    fn add_seen(&self, address: Address);
+    fn add_seen(&self, entity: Entity);
 
     /// Called by mempool when an unstaked entity causes the invalidation of a bundle
-    fn handle_urep_030_penalty(&self, address: Address);
+    fn handle_urep_030_penalty(&self, entity: Entity);
 
     /// Called by mempool when a staked entity causes the invalidation of a bundle
-    fn handle_srep_050_penalty(&self, address: Address);
+    fn handle_srep_050_penalty(&self, entity: Entity);
 
     /// Called by the mempool when an operation that requires stake is removed
     /// from the pool
@@ -106,6 +145,12 @@ pub(crate) trait ReputationManager: Send + Sync + 'static {
     /// Called by debug API
     fn set_reputation(&self, address: Address, ops_seen: u64, ops_included: u64);
 
+    /// Imports a reputation snapshot, typically exported from another bundler.
+    ///
+    /// If `overwrite` is true, all existing reputation state is replaced by the snapshot.
+    /// Otherwise, only addresses without existing reputation state are imported.
+    fn import_reputation(&self, reputations: Vec<Reputation>, overwrite: bool);
+
     /// Get the ops allowed for an unstaked entity
     fn get_ops_allowed(&self, address: Address) -> u64;
 
@@ -114,11 +159,16 @@ pub(crate) trait ReputationManager: Send + Sync + 'static {
 
     /// Sets whether reputation tracking can block user operations
     fn set_tracking(&self, tracking_enabled: bool);
+
+    /// Updates an address list, returning the addresses that were newly added
+    /// to the blocklist
+    fn update_address_list(&self, list: AddressList, update: AddressListUpdate) -> Vec<Address>;
 }
 
 #[derive(Debug)]
 pub(crate) struct HourlyMovingAverageReputation {
     reputation: RwLock<AddressReputation>,
+    snapshot_path: Option<PathBuf>,
 }
 
 impl HourlyMovingAverageReputation {
@@ -133,34 +183,98 @@ impl HourlyMovingAverageReputation {
 
         Self {
             reputation: RwLock::new(rep),
+            snapshot_path: None,
         }
     }
 
-    // run the reputation hourly update job
-    pub(crate) async fn run(&self) {
-        let mut tick = interval(Duration::from_secs(60 * 60));
+    /// Persist reputation state to the given path, restoring any snapshot already there.
+    ///
+    /// A snapshot that can't be read is logged and ignored, starting with empty
+    /// reputation state. It is replaced by the next snapshot written.
+    pub(crate) fn with_snapshot_path(self, path: PathBuf) -> Self {
+        if path.exists() {
+            match read_snapshot(&path) {
+                Ok(reputations) => {
+                    tracing::info!(
+                        "Restored {} reputation entries from {path:?}",
+                        reputations.len()
+                    );
+                    self.import_reputation(reputations, true);
+                }
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to restore reputation snapshot from {path:?}, starting empty: {e:?}"
+                    );
+                }
+            }
+        }
+
+        Self {
+            snapshot_path: Some(path),
+            ..self
+        }
+    }
+
+    // run the reputation decay job, persisting snapshots if configured until shutdown
+    pub(crate) async fn run(&self, shutdown_token: CancellationToken) {
+        let decay_interval = self.reputation.read().params.decay_interval();
+        let mut decay_tick = interval_at(Instant::now() + decay_interval, decay_interval);
+        let mut snapshot_tick = interval(SNAPSHOT_INTERVAL);
         loop {
-            tick.tick().await;
-            self.reputation.write().hourly_update();
+            tokio::select! {
+                _ = decay_tick.tick() => {
+                    self.reputation.write().decay_update();
+                }
+                _ = snapshot_tick.tick() => {
+                    self.persist();
+                }
+                _ = shutdown_token.cancelled() => {
+                    self.persist();
+                    return;
+                }
+            }
+        }
+    }
+
+    fn persist(&self) {
+        if let Some(path) = &self.snapshot_path {
+            if let Err(e) = write_snapshot(path, &self.dump_reputation()) {
+                tracing::error!("Failed to write reputation snapshot: {e:?}");
+            }
         }
     }
 }
 
+fn read_snapshot(path: &Path) -> anyhow::Result<Vec<Reputation>> {
+    let file = File::open(path).context("should open reputation snapshot")?;
+    serde_json::from_reader(BufReader::new(file)).context("should parse reputation snapshot")
+}
+
+fn write_snapshot(path: &Path, reputations: &[Reputation]) -> anyhow::Result<()> {
+    // write to a temporary file and rename so that a crash never leaves a partial snapshot
+    let tmp_path = path.with_extension("tmp");
+    let file = File::create(&tmp_path).context("should create reputation snapshot")?;
+    serde_json::to_writer(BufWriter::new(file), reputations)
+        .context("should write reputation snapshot")?;
+    std::fs::rename(&tmp_path, path).context("should move reputation snapshot")?;
+    Ok(())
+}
+
 impl ReputationManager for HourlyMovingAverageReputation {
     fn status(&self, address: Address) -> ReputationStatus {
         self.reputation.read().status(address)
     }
 
-    fn add_seen(&self, address: Address) {
-        self.reputation.write().add_seen(address);
+    fn add_seen(&self, entity: Entity) {
+        self.reputation.write().add_seen(entity);
     }
 
-    fn handle_urep_030_penalty(&self, address: Address) {
-        self.reputation.write().handle_urep_030_penalty(address);
+    fn handle_urep_030_penalty(&self, entity: Entity) {
+        self.reputation.write().handle_urep_030_penalty(entity);
     }
 
-    fn handle_srep_050_penalty(&self, address: Address) {
-        self.reputation.write().handle_srep_050_penalty(address);
+    fn handle_srep_050_penalty(&self, entity: Entity) {
+        self.reputation.write().handle_srep_050_penalty(entity);
     }
 
     fn add_included(&self, address: Address) {
@@ -180,6 +294,7 @@ impl ReputationManager for HourlyMovingAverageReputation {
                 address: *address,
                 ops_seen: count.ops_seen,
                 ops_included: count.ops_included,
+                kinds: count.kinds.iter().copied().collect(),
             })
             .collect()
     }
@@ -190,6 +305,12 @@ impl ReputationManager for HourlyMovingAverageReputation {
             .set_reputation(address, ops_seen, ops_included)
     }
 
+    fn import_reputation(&self, reputations: Vec<Reputation>, overwrite: bool) {
+        self.reputation
+            .write()
+            .import_reputation(reputations, overwrite)
+    }
+
     fn get_ops_allowed(&self, address: Address) -> u64 {
         self.reputation.read().get_ops_allowed(address)
     }
@@ -201,18 +322,59 @@ impl ReputationManager for HourlyMovingAverageReputation {
     fn set_tracking(&self, tracking_enabled: bool) {
         self.reputation.write().set_tracking(tracking_enabled)
     }
+
+    fn update_address_list(&self, list: AddressList, update: AddressListUpdate) -> Vec<Address> {
+        self.reputation.write().update_address_list(list, update)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
-pub(crate) struct ReputationParams {
-    bundle_invalidation_ops_seen_staked_penalty: u64,
-    bundle_invalidation_ops_seen_unstaked_penalty: u64,
-    same_unstaked_entity_mempool_count: u64,
-    min_inclusion_rate_denominator: u64,
-    inclusion_rate_factor: u64,
-    throttling_slack: u64,
-    ban_slack: u64,
-    tracking_enabled: bool,
+/// Parameters of the reputation algorithm
+///
+/// See the [ERC-4337 spec](https://github.com/eth-infinitism/account-abstraction/blob/develop/erc/ERCS/erc-4337.md#reputation-scoring-and-throttlingbanning-for-global-entities)
+/// for details on each parameter.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ReputationParams {
+    /// Amount `ops_seen` is set to when a staked entity invalidates a bundle
+    pub bundle_invalidation_ops_seen_staked_penalty: u64,
+    /// Amount `ops_seen` is increased by when an unstaked entity invalidates a bundle
+    pub bundle_invalidation_ops_seen_unstaked_penalty: u64,
+    /// Base number of operations an unstaked entity can have in the mempool
+    pub same_unstaked_entity_mempool_count: u64,
+    /// Expected ratio of `ops_seen` to `ops_included`
+    pub min_inclusion_rate_denominator: u64,
+    /// Factor applied to the inclusion rate when calculating allowed operations
+    pub inclusion_rate_factor: u64,
+    /// Number of missed inclusions allowed before an entity is throttled
+    pub throttling_slack: u64,
+    /// Number of missed inclusions allowed before an entity is banned
+    pub ban_slack: u64,
+    /// Interval, in seconds, between reputation decay updates
+    pub decay_interval_secs: u64,
+    /// Each decay update reduces counts by `1 / decay_denominator` of their value
+    pub decay_denominator: u64,
+    /// Overrides of the above parameters by entity type
+    pub entity_overrides: HashMap<EntityType, ReputationParamsOverride>,
+    /// Whether reputation tracking can block user operations, set via
+    /// `reputation_tracking_enabled` rather than the config file
+    #[serde(skip)]
+    pub tracking_enabled: bool,
+}
+
+/// Overrides of reputation parameters for a single entity type
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReputationParamsOverride {
+    /// Override of `bundle_invalidation_ops_seen_staked_penalty`
+    pub bundle_invalidation_ops_seen_staked_penalty: Option<u64>,
+    /// Override of `bundle_invalidation_ops_seen_unstaked_penalty`
+    pub bundle_invalidation_ops_seen_unstaked_penalty: Option<u64>,
+    /// Override of `min_inclusion_rate_denominator`
+    pub min_inclusion_rate_denominator: Option<u64>,
+    /// Override of `throttling_slack`
+    pub throttling_slack: Option<u64>,
+    /// Override of `ban_slack`
+    pub ban_slack: Option<u64>,
 }
 
 impl Default for ReputationParams {
@@ -225,12 +387,16 @@ impl Default for ReputationParams {
             inclusion_rate_factor: 10,
             throttling_slack: 10,
             ban_slack: 50,
+            decay_interval_secs: 60 * 60,
+            decay_denominator: 24,
+            entity_overrides: HashMap::new(),
             tracking_enabled: true,
         }
     }
 }
 
 impl ReputationParams {
+    #[allow(dead_code)]
     pub(crate) fn new(tracking_enabled: bool) -> Self {
         Self {
             tracking_enabled,
@@ -238,6 +404,67 @@ impl ReputationParams {
         }
     }
 
+    /// Validates the parameters, returning an error describing the first invalid parameter
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.decay_interval_secs > 0,
+            "reputation decayIntervalSecs must be greater than 0"
+        );
+        anyhow::ensure!(
+            self.decay_denominator > 0,
+            "reputation decayDenominator must be greater than 0"
+        );
+        anyhow::ensure!(
+            self.min_inclusion_rate_denominator > 0,
+            "reputation minInclusionRateDenominator must be greater than 0"
+        );
+        for (kind, o) in &self.entity_overrides {
+            anyhow::ensure!(
+                o.min_inclusion_rate_denominator != Some(0),
+                "reputation minInclusionRateDenominator override for {kind} must be greater than 0"
+            );
+        }
+        Ok(())
+    }
+
+    fn decay_interval(&self) -> Duration {
+        Duration::from_secs(self.decay_interval_secs)
+    }
+
+    /// Returns the parameters to use for the given entity type, applying overrides
+    fn for_kind(&self, kind: EntityType) -> ResolvedParams {
+        let o = self
+            .entity_overrides
+            .get(&kind)
+            .copied()
+            .unwrap_or_default();
+        ResolvedParams {
+            bundle_invalidation_ops_seen_staked_penalty: o
+                .bundle_invalidation_ops_seen_staked_penalty
+                .unwrap_or(self.bundle_invalidation_ops_seen_staked_penalty),
+            bundle_invalidation_ops_seen_unstaked_penalty: o
+                .bundle_invalidation_ops_seen_unstaked_penalty
+                .unwrap_or(self.bundle_invalidation_ops_seen_unstaked_penalty),
+            min_inclusion_rate_denominator: o
+                .min_inclusion_rate_denominator
+                .unwrap_or(self.min_inclusion_rate_denominator),
+            throttling_slack: o.throttling_slack.unwrap_or(self.throttling_slack),
+            ban_slack: o.ban_slack.unwrap_or(self.ban_slack),
+        }
+    }
+
+    fn default_resolved(&self) -> ResolvedParams {
+        ResolvedParams {
+            bundle_invalidation_ops_seen_staked_penalty: self
+                .bundle_invalidation_ops_seen_staked_penalty,
+            bundle_invalidation_ops_seen_unstaked_penalty: self
+                .bundle_invalidation_ops_seen_unstaked_penalty,
+            min_inclusion_rate_denominator: self.min_inclusion_rate_denominator,
+            throttling_slack: self.throttling_slack,
+            ban_slack: self.ban_slack,
+        }
+    }
+
     #[allow(dead_code)]
     pub(crate) fn bundler_default() -> Self {
         Self::default()
@@ -252,6 +479,29 @@ impl ReputationParams {
     }
 }
 
+/// Reputation parameters after applying entity type overrides
+#[derive(Debug, Clone, Copy)]
+struct ResolvedParams {
+    bundle_invalidation_ops_seen_staked_penalty: u64,
+    bundle_invalidation_ops_seen_unstaked_penalty: u64,
+    min_inclusion_rate_denominator: u64,
+    throttling_slack: u64,
+    ban_slack: u64,
+}
+
+impl ResolvedParams {
+    fn status(&self, count: &AddressCount) -> ReputationStatus {
+        let min_expected_included = count.ops_seen / self.min_inclusion_rate_denominator;
+        if min_expected_included <= (count.ops_included + self.throttling_slack) {
+            ReputationStatus::Ok
+        } else if min_expected_included <= (count.ops_included + self.ban_slack) {
+            ReputationStatus::Throttled
+        } else {
+            ReputationStatus::Banned
+        }
+    }
+}
+
 #[derive(Debug)]
 struct AddressReputation {
     // Addresses that are always banned
@@ -280,6 +530,40 @@ impl AddressReputation {
         Self { allowlist, ..self }
     }
 
+    fn update_address_list(
+        &mut self,
+        list: AddressList,
+        update: AddressListUpdate,
+    ) -> Vec<Address> {
+        let addresses = match list {
+            AddressList::Blocklist => &mut self.blocklist,
+            AddressList::Allowlist => &mut self.allowlist,
+        };
+
+        let added = match update {
+            AddressListUpdate::Replace(new) => {
+                let added = new.difference(addresses).copied().collect();
+                *addresses = new;
+                added
+            }
+            AddressListUpdate::Add(new) => new
+                .into_iter()
+                .filter(|address| addresses.insert(*address))
+                .collect(),
+            AddressListUpdate::Remove(removed) => {
+                for address in removed {
+                    addresses.remove(&address);
+                }
+                vec![]
+            }
+        };
+
+        match list {
+            AddressList::Blocklist => added,
+            AddressList::Allowlist => vec![],
+        }
+    }
+
     fn status(&self, address: Address) -> ReputationStatus {
         if self.blocklist.contains(&address) {
             return ReputationStatus::Banned;
@@ -296,30 +580,44 @@ impl AddressReputation {
             None => return ReputationStatus::Ok,
         };
 
-        let min_expected_included = count.ops_seen / self.params.min_inclusion_rate_denominator;
-        if min_expected_included <= (count.ops_included + self.params.throttling_slack) {
-            ReputationStatus::Ok
-        } else if min_expected_included <= (count.ops_included + self.params.ban_slack) {
-            ReputationStatus::Throttled
+        // An address used as multiple entity types is held to the strictest status
+        if count.kinds.is_empty() {
+            self.params.default_resolved().status(count)
         } else {
-            ReputationStatus::Banned
+            count
+                .kinds
+                .iter()
+                .map(|kind| self.params.for_kind(*kind).status(count))
+                .max()
+                .unwrap_or(ReputationStatus::Ok)
         }
     }
 
-    fn add_seen(&mut self, address: Address) {
-        let count = self.counts.entry(address).or_default();
+    fn add_seen(&mut self, entity: Entity) {
+        let count = self.counts.entry(entity.address).or_default();
+        count.kinds.insert(entity.kind);
         count.ops_seen += 1;
     }
 
-    fn handle_urep_030_penalty(&mut self, address: Address) {
-        let count = self.counts.entry(address).or_default();
-        count.ops_seen += self.params.bundle_invalidation_ops_seen_unstaked_penalty;
+    fn handle_urep_030_penalty(&mut self, entity: Entity) {
+        let penalty = self
+            .params
+            .for_kind(entity.kind)
+            .bundle_invalidation_ops_seen_unstaked_penalty;
+        let count = self.counts.entry(entity.address).or_default();
+        count.kinds.insert(entity.kind);
+        count.ops_seen += penalty;
     }
 
-    fn handle_srep_050_penalty(&mut self, address: Address) {
-        let count = self.counts.entry(address).or_default();
+    fn handle_srep_050_penalty(&mut self, entity: Entity) {
+        let penalty = self
+            .params
+            .for_kind(entity.kind)
+            .bundle_invalidation_ops_seen_staked_penalty;
+        let count = self.counts.entry(entity.address).or_default();
+        count.kinds.insert(entity.kind);
         // According to the spec we set ops_seen here instead of incrementing it
-        count.ops_seen = self.params.bundle_invalidation_ops_seen_staked_penalty;
+        count.ops_seen = penalty;
     }
 
     fn add_included(&mut self, address: Address) {
@@ -338,6 +636,26 @@ impl AddressReputation {
         count.ops_included = ops_included;
     }
 
+    fn import_reputation(&mut self, reputations: Vec<Reputation>, overwrite: bool) {
+        if overwrite {
+            self.counts.clear();
+        }
+
+        for rep in reputations {
+            if self.counts.contains_key(&rep.address) {
+                continue;
+            }
+            self.counts.insert(
+                rep.address,
+                AddressCount {
+                    ops_seen: rep.ops_seen,
+                    ops_included: rep.ops_included,
+                    kinds: rep.kinds.into_iter().collect(),
+                },
+            );
+        }
+    }
+
     fn get_ops_allowed(&self, address: Address) -> u64 {
         let (seen, included) = self
             .counts
@@ -355,10 +673,11 @@ impl AddressReputation {
         self.params.same_unstaked_entity_mempool_count + inclusion_based_count
     }
 
-    fn hourly_update(&mut self) {
+    fn decay_update(&mut self) {
+        let denominator = self.params.decay_denominator;
         for count in self.counts.values_mut() {
-            count.ops_seen -= count.ops_seen / 24;
-            count.ops_included -= count.ops_included / 24;
+            count.ops_seen -= count.ops_seen / denominator;
+            count.ops_included -= count.ops_included / denominator;
         }
         self.counts
             .retain(|_, count| count.ops_seen > 0 || count.ops_included > 0);
@@ -377,6 +696,8 @@ impl AddressReputation {
 struct AddressCount {
     ops_seen: u64,
     ops_included: u64,
+    // Entity types this address has been seen as
+    kinds: BTreeSet<EntityType>,
 }
 
 #[cfg(test)]
@@ -391,7 +712,7 @@ mod tests {
         let mut reputation = AddressReputation::new(ReputationParams::bundler_default());
 
         for _ in 0..1000 {
-            reputation.add_seen(addr);
+            reputation.add_seen(Entity::paymaster(addr));
             reputation.add_included(addr);
         }
         let counts = reputation.counts.get(&addr).unwrap();
@@ -414,7 +735,7 @@ mod tests {
     fn reputation_ok() {
         let addr = Address::random();
         let mut reputation = AddressReputation::new(ReputationParams::bundler_default());
-        reputation.add_seen(addr);
+        reputation.add_seen(Entity::paymaster(addr));
         assert_eq!(reputation.status(addr), ReputationStatus::Ok);
     }
 
@@ -422,7 +743,7 @@ mod tests {
     fn reputation_throttled() {
         let addr = Address::random();
         let params = ReputationParams::bundler_default();
-        let mut reputation = AddressReputation::new(params);
+        let mut reputation = AddressReputation::new(params.clone());
 
         let ops_seen = 1000;
         let ops_included =
@@ -435,7 +756,7 @@ mod tests {
     fn reputation_throttled_edge() {
         let addr = Address::random();
         let params = ReputationParams::bundler_default();
-        let mut reputation = AddressReputation::new(params);
+        let mut reputation = AddressReputation::new(params.clone());
 
         let ops_seen = 1000;
         let ops_included =
@@ -448,7 +769,7 @@ mod tests {
     fn reputation_banned() {
         let addr = Address::random();
         let params = ReputationParams::bundler_default();
-        let mut reputation = AddressReputation::new(params);
+        let mut reputation = AddressReputation::new(params.clone());
 
         let ops_seen = 1000;
         let ops_included = ops_seen / params.min_inclusion_rate_denominator - params.ban_slack - 1;
@@ -460,7 +781,7 @@ mod tests {
     fn reputation_banned_tracking_disabled() {
         let addr = Address::random();
         let params = ReputationParams::new(false);
-        let mut reputation = AddressReputation::new(params);
+        let mut reputation = AddressReputation::new(params.clone());
 
         let ops_seen = 1000;
         let ops_included = ops_seen / params.min_inclusion_rate_denominator - params.ban_slack - 1;
@@ -469,16 +790,16 @@ mod tests {
     }
 
     #[test]
-    fn hourly_update() {
+    fn decay_update() {
         let addr = Address::random();
         let mut reputation = AddressReputation::new(ReputationParams::bundler_default());
 
         for _ in 0..1000 {
-            reputation.add_seen(addr);
+            reputation.add_seen(Entity::paymaster(addr));
             reputation.add_included(addr);
         }
 
-        reputation.hourly_update();
+        reputation.decay_update();
         let counts = reputation.counts.get(&addr).unwrap();
         assert_eq!(counts.ops_seen, 1000 - 1000 / 24);
         assert_eq!(counts.ops_included, 1000 - 1000 / 24);
@@ -504,6 +825,193 @@ mod tests {
         assert_eq!(reputation.status(addr), ReputationStatus::Ok);
     }
 
+    #[test]
+    fn test_update_blocklist() {
+        let addr1 = Address::random();
+        let addr2 = Address::random();
+        let addr3 = Address::random();
+        let mut reputation = AddressReputation::new(ReputationParams::bundler_default())
+            .with_blocklist(HashSet::from([addr1]));
+
+        let added = reputation.update_address_list(
+            AddressList::Blocklist,
+            AddressListUpdate::Add(vec![addr1, addr2]),
+        );
+        assert_eq!(added, vec![addr2]);
+        assert_eq!(reputation.status(addr2), ReputationStatus::Banned);
+
+        let added = reputation.update_address_list(
+            AddressList::Blocklist,
+            AddressListUpdate::Replace(HashSet::from([addr2, addr3])),
+        );
+        assert_eq!(added, vec![addr3]);
+        assert_eq!(reputation.status(addr1), ReputationStatus::Ok);
+        assert_eq!(reputation.status(addr3), ReputationStatus::Banned);
+
+        let added = reputation.update_address_list(
+            AddressList::Blocklist,
+            AddressListUpdate::Remove(vec![addr2]),
+        );
+        assert!(added.is_empty());
+        assert_eq!(reputation.status(addr2), ReputationStatus::Ok);
+    }
+
+    #[test]
+    fn test_update_allowlist() {
+        let addr = Address::random();
+        let mut reputation = AddressReputation::new(ReputationParams::bundler_default());
+        reputation.set_reputation(addr, 1000000, 0);
+        assert_eq!(reputation.status(addr), ReputationStatus::Banned);
+
+        let added = reputation
+            .update_address_list(AddressList::Allowlist, AddressListUpdate::Add(vec![addr]));
+        assert!(added.is_empty());
+        assert_eq!(reputation.status(addr), ReputationStatus::Ok);
+    }
+
+    #[test]
+    fn entity_type_override() {
+        let addr = Address::random();
+        let params = ReputationParams {
+            entity_overrides: HashMap::from([(
+                EntityType::Paymaster,
+                ReputationParamsOverride {
+                    ban_slack: Some(0),
+                    throttling_slack: Some(0),
+                    ..Default::default()
+                },
+            )]),
+            ..ReputationParams::bundler_default()
+        };
+        let mut reputation = AddressReputation::new(params);
+
+        for _ in 0..20 {
+            reputation.add_seen(Entity::factory(addr));
+        }
+        // with default slacks, the factory is ok
+        assert_eq!(reputation.status(addr), ReputationStatus::Ok);
+
+        // once seen as a paymaster, the stricter paymaster slacks apply
+        reputation.add_seen(Entity::paymaster(addr));
+        assert_eq!(reputation.status(addr), ReputationStatus::Banned);
+    }
+
+    #[test]
+    fn configurable_decay() {
+        let addr = Address::random();
+        let params = ReputationParams {
+            decay_denominator: 2,
+            ..ReputationParams::bundler_default()
+        };
+        let mut reputation = AddressReputation::new(params);
+        reputation.set_reputation(addr, 1000, 100);
+
+        reputation.decay_update();
+        let counts = reputation.counts.get(&addr).unwrap();
+        assert_eq!(counts.ops_seen, 500);
+        assert_eq!(counts.ops_included, 50);
+    }
+
+    #[test]
+    fn import_reputation() {
+        let existing = Address::random();
+        let imported = Address::random();
+        let mut reputation = AddressReputation::new(ReputationParams::bundler_default());
+        reputation.set_reputation(existing, 10, 10);
+
+        let snapshot = vec![
+            Reputation {
+                address: existing,
+                ops_seen: 1000,
+                ops_included: 0,
+                kinds: vec![],
+            },
+            Reputation {
+                address: imported,
+                ops_seen: 1000,
+                ops_included: 0,
+                kinds: vec![],
+            },
+        ];
+
+        // merging keeps existing local state
+        reputation.import_reputation(snapshot.clone(), false);
+        assert_eq!(reputation.status(existing), ReputationStatus::Ok);
+        assert_eq!(reputation.status(imported), ReputationStatus::Banned);
+
+        // overwriting replaces local state
+        reputation.import_reputation(snapshot, true);
+        assert_eq!(reputation.status(existing), ReputationStatus::Banned);
+    }
+
+    #[test]
+    fn snapshot_keeps_entity_kinds() {
+        let addr = Address::random();
+        let params = ReputationParams {
+            entity_overrides: HashMap::from([(
+                EntityType::Paymaster,
+                ReputationParamsOverride {
+                    ban_slack: Some(0),
+                    throttling_slack: Some(0),
+                    ..Default::default()
+                },
+            )]),
+            ..ReputationParams::bundler_default()
+        };
+        let path = std::env::temp_dir().join(format!("rundler-reputation-{addr:?}.json"));
+
+        let manager = HourlyMovingAverageReputation::new(params.clone(), None, None)
+            .with_snapshot_path(path.clone());
+        for _ in 0..20 {
+            manager.add_seen(Entity::paymaster(addr));
+        }
+        assert_eq!(manager.status(addr), ReputationStatus::Banned);
+        manager.persist();
+
+        // the paymaster override still applies after restoring
+        let restored =
+            HourlyMovingAverageReputation::new(params, None, None).with_snapshot_path(path.clone());
+        assert_eq!(restored.status(addr), ReputationStatus::Banned);
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn corrupt_snapshot_starts_empty() {
+        let path =
+            std::env::temp_dir().join(format!("rundler-reputation-{:?}.json", Address::random()));
+        std::fs::write(&path, "not json").unwrap();
+
+        let manager =
+            HourlyMovingAverageReputation::new(ReputationParams::bundler_default(), None, None)
+                .with_snapshot_path(path.clone());
+        assert!(manager.dump_reputation().is_empty());
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn params_from_json() {
+        let params: ReputationParams = serde_json::from_str(
+            r#"{
+                "banSlack": 100,
+                "decayIntervalSecs": 600,
+                "entityOverrides": {
+                    "paymaster": { "throttlingSlack": 5 }
+                }
+            }"#,
+        )
+        .unwrap();
+        params.validate().unwrap();
+
+        assert_eq!(params.ban_slack, 100);
+        assert_eq!(params.throttling_slack, 10);
+        assert_eq!(params.decay_interval_secs, 600);
+        assert!(params.tracking_enabled);
+        assert_eq!(params.for_kind(EntityType::Paymaster).throttling_slack, 5);
+        assert_eq!(params.for_kind(EntityType::Factory).throttling_slack, 10);
+    }
+
     // Test HourlyMovingAverageReputation
 
     #[test]
@@ -514,7 +1022,7 @@ mod tests {
 
         for _ in 0..10 {
             for addr in addrs {
-                manager.add_seen(addr);
+                manager.add_seen(Entity::paymaster(addr));
                 manager.add_included(addr);
             }
         }
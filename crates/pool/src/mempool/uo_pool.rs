@@ -25,17 +25,22 @@ use rundler_types::{Entity, EntityUpdate, EntityUpdateType, UserOperation};
 use rundler_utils::emit::WithEntryPoint;
 use tokio::sync::broadcast;
 use tonic::async_trait;
-use tracing::info;
+use tracing::{info, instrument};
 
 use super::{
     error::{MempoolError, MempoolResult},
+    paymaster_alerts::PaymasterAlerter,
     pool::PoolInner,
-    reputation::{Reputation, ReputationManager, ReputationStatus},
-    Mempool, OperationOrigin, PaymasterMetadata, PoolConfig, PoolOperation, StakeInfo, StakeStatus,
+    reputation::{AddressList, AddressListUpdate, Reputation, ReputationManager, ReputationStatus},
+    Mempool, MempoolFilter, OpPosition, OperationOrigin, PaymasterMetadata, PaymasterStatus,
+    PoolConfig, PoolOperation, StakeInfo, StakeStatus,
 };
 use crate::{
     chain::ChainUpdate,
-    emit::{EntityReputation, EntityStatus, EntitySummary, OpPoolEvent, OpRemovalReason},
+    emit::{
+        EntityReputation, EntityStatus, EntitySummary, OpPoolEvent, OpRemovalReason,
+        OpRemovalRequester,
+    },
 };
 
 /// User Operation Mempool
@@ -58,12 +63,15 @@ pub(crate) struct UoPool<
     simulator: S,
     entry_point: E,
     paymaster_helper: PH,
+    paymaster_alerter: Option<PaymasterAlerter>,
 }
 
 struct UoPoolState {
     pool: PoolInner,
     throttled_ops: HashSet<H256>,
     block_number: u64,
+    /// paymasters whose status is reported in metrics
+    metered_paymasters: HashSet<Address>,
 }
 
 impl<R, P, S, E, PH> UoPool<R, P, S, E, PH>
@@ -83,6 +91,7 @@ where
         entry_point: E,
         paymaster_helper: PH,
     ) -> Self {
+        let paymaster_alerter = config.paymaster_alerts.clone().map(PaymasterAlerter::new);
         Self {
             config: config.clone(),
             reputation,
@@ -90,12 +99,14 @@ where
                 pool: PoolInner::new(config.into()),
                 throttled_ops: HashSet::new(),
                 block_number: 0,
+                metered_paymasters: HashSet::new(),
             }),
             event_sender,
             prechecker,
             simulator,
             entry_point,
             paymaster_helper,
+            paymaster_alerter,
         }
     }
 
@@ -149,7 +160,7 @@ where
     PH: PaymasterHelper,
 {
     async fn on_chain_update(&self, update: &ChainUpdate) {
-        {
+        let (paymaster_statuses, idle_paymasters) = {
             let deduped_ops = update.deduped_ops();
             let mined_ops = deduped_ops
                 .mined_ops
@@ -182,6 +193,11 @@ where
                 entity_balance_updates,
                 unmined_entity_balance_updates,
             );
+            state.pool.record_paymaster_spends(
+                mined_ops.clone(),
+                unmined_ops.clone(),
+                update.latest_block_timestamp.seconds_since_epoch(),
+            );
 
             for op in mined_ops {
                 if op.entry_point != self.config.entry_point {
@@ -273,10 +289,34 @@ where
             }
 
             state.block_number = update.latest_block_number;
+            let paymaster_statuses = state.pool.paymaster_statuses();
+            let metered_paymasters = paymaster_statuses
+                .iter()
+                .map(|status| status.address)
+                .collect::<HashSet<_>>();
+            let idle_paymasters = state
+                .metered_paymasters
+                .difference(&metered_paymasters)
+                .copied()
+                .collect::<Vec<_>>();
+            state.metered_paymasters = metered_paymasters;
+            (paymaster_statuses, idle_paymasters)
+        };
+
+        for status in &paymaster_statuses {
+            UoPoolMetrics::set_paymaster_status(status, self.config.entry_point);
+        }
+        for paymaster in idle_paymasters {
+            UoPoolMetrics::clear_paymaster_status(paymaster, self.config.entry_point);
+        }
+        if let Some(alerter) = &self.paymaster_alerter {
+            alerter.check(self.config.entry_point, &paymaster_statuses);
         }
 
         // update required bundle fees and update metrics
         if let Ok((bundle_fees, base_fee)) = self.prechecker.update_fees().await {
+            self.state.write().pool.set_base_fee(base_fee);
+
             let max_fee = match format_units(bundle_fees.max_fee_per_gas, "gwei") {
                 Ok(s) => s.parse::<f64>().unwrap_or_default(),
                 Err(_) => 0.0,
@@ -323,10 +363,15 @@ where
         Ok(())
     }
 
+    #[instrument(
+        skip_all,
+        fields(op_hash = ?op.op_hash(self.config.entry_point, self.config.chain_id))
+    )]
     async fn add_operation(
         &self,
         origin: OperationOrigin,
         op: UserOperation,
+        partner: Option<String>,
     ) -> MempoolResult<H256> {
         // TODO(danc) aggregator reputation is not implemented
         // TODO(danc) catch ops with aggregators prior to simulation and reject
@@ -385,7 +430,7 @@ where
         }
 
         // Prechecks
-        self.prechecker.check(&op).await?;
+        let precheck_return = self.prechecker.check(&op).await?;
 
         // Only let ops with successful simulations through
         let sim_result = self
@@ -416,6 +461,11 @@ where
             entities_needing_stake: sim_result.entities_needing_stake,
             account_is_staked: sim_result.account_is_staked,
             entity_infos: sim_result.entity_infos,
+            partner,
+            token_cost: precheck_return
+                .token_funds
+                .as_ref()
+                .map(|token_funds| token_funds.max_token_cost),
         };
 
         // Check sender count in mempool. If sender has too many operations, must be staked
@@ -449,9 +499,11 @@ where
         // Add op to pool
         let hash = {
             let mut state = self.state.write();
-            let hash = state
-                .pool
-                .add_operation(pool_op.clone(), paymaster_metadata)?;
+            let hash = state.pool.add_operation(
+                pool_op.clone(),
+                paymaster_metadata,
+                precheck_return.token_funds,
+            )?;
             if throttled {
                 state.throttled_ops.insert(hash);
             }
@@ -461,7 +513,7 @@ where
         // Update reputation
         if replacement.is_none() {
             pool_op.entities().unique().for_each(|e| {
-                self.reputation.add_seen(e.address);
+                self.reputation.add_seen(e);
                 if self.reputation.status(e.address) == ReputationStatus::Throttled {
                     self.throttle_entity(e);
                 } else if self.reputation.status(e.address) == ReputationStatus::Banned {
@@ -487,13 +539,19 @@ where
         Ok(hash)
     }
 
-    fn remove_operations(&self, hashes: &[H256]) {
+    fn remove_operations(&self, hashes: &[H256], requester: OpRemovalRequester) {
         let mut count = 0;
         let mut removed_hashes = vec![];
         {
             let mut state = self.state.write();
             for hash in hashes {
-                if state.pool.remove_operation_by_hash(*hash).is_some() {
+                // ops cancelled by their senders can't be resubmitted for a while
+                let removed = if requester == OpRemovalRequester::Sender {
+                    state.pool.cancel_operation(*hash)
+                } else {
+                    state.pool.remove_operation_by_hash(*hash)
+                };
+                if removed.is_some() {
                     count += 1;
                     removed_hashes.push(*hash);
                 }
@@ -503,7 +561,7 @@ where
         for hash in removed_hashes {
             self.emit(OpPoolEvent::RemovedOp {
                 op_hash: hash,
-                reason: OpRemovalReason::Requested,
+                reason: OpRemovalReason::Requested { requester },
             })
         }
         UoPoolMetrics::increment_removed_operations(count, self.config.entry_point);
@@ -513,10 +571,10 @@ where
         let entity = update.entity;
         match update.update_type {
             EntityUpdateType::UnstakedInvalidation => {
-                self.reputation.handle_urep_030_penalty(entity.address);
+                self.reputation.handle_urep_030_penalty(entity);
             }
             EntityUpdateType::StakedInvalidation => {
-                self.reputation.handle_srep_050_penalty(entity.address);
+                self.reputation.handle_srep_050_penalty(entity);
             }
         }
 
@@ -547,6 +605,21 @@ where
         Ok(paymaster_meta)
     }
 
+    async fn paymaster_status(&self, paymaster: Address) -> ProviderResult<PaymasterStatus> {
+        if let Some(status) = self.state.read().pool.paymaster_status(paymaster) {
+            return Ok(status);
+        }
+
+        let balance = self.entry_point.balance_of(paymaster, None).await?;
+
+        Ok(PaymasterStatus {
+            address: paymaster,
+            confirmed_balance: balance,
+            pending_balance: balance,
+            ..Default::default()
+        })
+    }
+
     fn best_operations(
         &self,
         max: usize,
@@ -585,6 +658,30 @@ where
         self.state.read().pool.get_operation_by_hash(hash)
     }
 
+    fn operation_position(&self, hash: H256) -> Option<OpPosition> {
+        self.state.read().pool.operation_position(hash)
+    }
+
+    fn operations_by_sender(&self, sender: Address) -> Vec<Arc<PoolOperation>> {
+        self.state.read().pool.operations_by_sender(sender)
+    }
+
+    fn operations_by_paymaster(&self, paymaster: Address) -> Vec<Arc<PoolOperation>> {
+        self.state.read().pool.operations_by_paymaster(paymaster)
+    }
+
+    fn filtered_operations(
+        &self,
+        filter: &MempoolFilter,
+        offset: usize,
+        limit: usize,
+    ) -> (Vec<Arc<PoolOperation>>, usize) {
+        self.state
+            .read()
+            .pool
+            .filtered_operations(filter, offset, limit)
+    }
+
     fn clear_state(&self, clear_mempool: bool, clear_paymaster: bool, clear_reputation: bool) {
         self.state
             .write()
@@ -629,6 +726,34 @@ where
         self.reputation
             .set_reputation(address, ops_seen, ops_included)
     }
+
+    fn import_reputation(&self, reputations: Vec<Reputation>, overwrite: bool) {
+        self.reputation.import_reputation(reputations, overwrite)
+    }
+
+    fn update_address_list(&self, list: AddressList, update: AddressListUpdate) {
+        let blocked: HashSet<Address> = self
+            .reputation
+            .update_address_list(list, update)
+            .into_iter()
+            .collect();
+        if blocked.is_empty() {
+            return;
+        }
+
+        let entities = self
+            .state
+            .read()
+            .pool
+            .best_operations()
+            .flat_map(|op| op.entities().collect::<Vec<_>>())
+            .filter(|entity| blocked.contains(&entity.address))
+            .collect::<HashSet<_>>();
+        for entity in entities {
+            info!("Removing ops for blocked entity {entity:?}");
+            self.remove_entity(entity);
+        }
+    }
 }
 
 struct UoPoolMetrics {}
@@ -661,23 +786,59 @@ impl UoPoolMetrics {
     fn current_base_fee(fee: f64) {
         metrics::gauge!("op_pool_current_base_fee", fee);
     }
+
+    fn set_paymaster_status(status: &PaymasterStatus, entry_point: Address) {
+        let entry_point = entry_point.to_string();
+        let paymaster = status.address.to_string();
+        let eth = |wei| match format_units(wei, "ether") {
+            Ok(s) => s.parse::<f64>().unwrap_or_default(),
+            Err(_) => 0.0,
+        };
+        metrics::gauge!("op_pool_paymaster_confirmed_balance_eth", eth(status.confirmed_balance), "entrypoint" => entry_point.clone(), "paymaster" => paymaster.clone());
+        metrics::gauge!("op_pool_paymaster_pending_balance_eth", eth(status.pending_balance), "entrypoint" => entry_point.clone(), "paymaster" => paymaster.clone());
+        metrics::gauge!("op_pool_paymaster_pending_ops", status.pending_ops as f64, "entrypoint" => entry_point.clone(), "paymaster" => paymaster.clone());
+        metrics::gauge!("op_pool_paymaster_spend_per_hour_eth", eth(status.spend_per_hour), "entrypoint" => entry_point.clone(), "paymaster" => paymaster.clone());
+        // Paymasters with no recent spending have an unbounded runway
+        let runway = status
+            .runway_seconds
+            .map_or(f64::INFINITY, |seconds| seconds as f64);
+        metrics::gauge!("op_pool_paymaster_runway_seconds", runway, "entrypoint" => entry_point, "paymaster" => paymaster);
+    }
+
+    // Zeroes the status of a paymaster without pending operations or recent spends
+    fn clear_paymaster_status(paymaster: Address, entry_point: Address) {
+        let entry_point = entry_point.to_string();
+        let paymaster = paymaster.to_string();
+        for name in [
+            "op_pool_paymaster_confirmed_balance_eth",
+            "op_pool_paymaster_pending_balance_eth",
+            "op_pool_paymaster_pending_ops",
+            "op_pool_paymaster_spend_per_hour_eth",
+            "op_pool_paymaster_runway_seconds",
+        ] {
+            metrics::gauge!(name, 0.0, "entrypoint" => entry_point.clone(), "paymaster" => paymaster.clone());
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{collections::HashMap, time::Duration};
 
     use ethers::types::{Bytes, H160};
     use rundler_provider::{MockEntryPoint, MockPaymasterHelper};
     use rundler_sim::{
-        EntityInfo, EntityInfos, MockPrechecker, MockSimulator, PrecheckError, PrecheckSettings,
-        PrecheckViolation, SimulationError, SimulationResult, SimulationSettings,
+        EntityInfo, EntityInfos, MockPrechecker, MockSimulator, PrecheckError, PrecheckReturn,
+        PrecheckSettings, PrecheckViolation, SimulationError, SimulationResult, SimulationSettings,
         SimulationViolation, ViolationError,
     };
     use rundler_types::{DepositInfo, EntityType, GasFees, ValidTimeRange};
 
     use super::*;
-    use crate::chain::{BalanceUpdate, MinedOp};
+    use crate::{
+        chain::{BalanceUpdate, MinedOp},
+        mempool::{MaxFeeOrdering, ReputationParams},
+    };
 
     const THROTTLE_SLACK: u64 = 5;
     const BAN_SLACK: u64 = 10;
@@ -690,14 +851,52 @@ mod tests {
         let pool = create_pool(ops);
 
         let hash = pool
-            .add_operation(OperationOrigin::Local, op.op)
+            .add_operation(OperationOrigin::Local, op.op, None)
             .await
             .unwrap();
         check_ops(pool.best_operations(1, 0).unwrap(), uos);
-        pool.remove_operations(&[hash]);
+        pool.remove_operations(&[hash], OpRemovalRequester::Builder);
         assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn remove_requested_ops() {
+        let ops = vec![
+            create_op(Address::random(), 0, 2, None),
+            create_op(Address::random(), 0, 1, None),
+        ];
+        let (pool, uos) = create_pool_insert_ops(ops).await;
+        let hashes = uos
+            .iter()
+            .map(|uo| uo.op_hash(pool.config.entry_point, pool.config.chain_id))
+            .collect::<Vec<_>>();
+        let mut events = pool.event_sender.subscribe();
+
+        pool.remove_operations(&[hashes[0]], OpRemovalRequester::Sender);
+        check_ops(pool.best_operations(2, 0).unwrap(), vec![uos[1].clone()]);
+        pool.remove_operations(&[hashes[1]], OpRemovalRequester::Admin);
+        check_ops(pool.best_operations(2, 0).unwrap(), vec![]);
+        // removing an op that is no longer in the pool emits nothing
+        pool.remove_operations(&[hashes[1]], OpRemovalRequester::Admin);
+
+        for (hash, expected) in hashes
+            .iter()
+            .zip([OpRemovalRequester::Sender, OpRemovalRequester::Admin])
+        {
+            match events.try_recv().unwrap().event {
+                OpPoolEvent::RemovedOp {
+                    op_hash,
+                    reason: OpRemovalReason::Requested { requester },
+                } => {
+                    assert_eq!(op_hash, *hash);
+                    assert_eq!(requester, expected);
+                }
+                event => panic!("unexpected event {event:?}"),
+            }
+        }
+        assert!(events.try_recv().is_err());
+    }
+
     #[tokio::test]
     async fn add_multiple_ops() {
         let ops = vec![
@@ -711,13 +910,13 @@ mod tests {
         let mut hashes = vec![];
         for op in &uos {
             let hash = pool
-                .add_operation(OperationOrigin::Local, op.clone())
+                .add_operation(OperationOrigin::Local, op.clone(), None)
                 .await
                 .unwrap();
             hashes.push(hash);
         }
         check_ops(pool.best_operations(3, 0).unwrap(), uos);
-        pool.remove_operations(&hashes);
+        pool.remove_operations(&hashes, OpRemovalRequester::Builder);
         assert_eq!(pool.best_operations(3, 0).unwrap(), vec![]);
     }
 
@@ -733,7 +932,7 @@ mod tests {
 
         for op in &uos {
             let _ = pool
-                .add_operation(OperationOrigin::Local, op.clone())
+                .add_operation(OperationOrigin::Local, op.clone(), None)
                 .await
                 .unwrap();
         }
@@ -766,6 +965,7 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![BalanceUpdate {
@@ -781,6 +981,7 @@ mod tests {
                 is_addition: false,
             }],
             reorg_larger_than_history: false,
+            state_diff: None,
         })
         .await;
 
@@ -834,6 +1035,7 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: 10.into(),
                 paymaster: Some(paymaster),
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![BalanceUpdate {
@@ -849,6 +1051,7 @@ mod tests {
                 is_addition: false,
             }],
             reorg_larger_than_history: false,
+            state_diff: None,
         })
         .await;
 
@@ -880,6 +1083,7 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: 10.into(),
                 paymaster: None,
+                ..Default::default()
             }],
             entity_balance_updates: vec![],
             unmined_entity_balance_updates: vec![BalanceUpdate {
@@ -889,6 +1093,7 @@ mod tests {
                 is_addition: true,
             }],
             reorg_larger_than_history: false,
+            state_diff: None,
         })
         .await;
 
@@ -926,11 +1131,13 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![],
             unmined_entity_balance_updates: vec![],
             reorg_larger_than_history: false,
+            state_diff: None,
         })
         .await;
 
@@ -968,11 +1175,13 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                ..Default::default()
             }],
             unmined_ops: vec![],
             entity_balance_updates: vec![],
             unmined_entity_balance_updates: vec![],
             reorg_larger_than_history: false,
+            state_diff: None,
         })
         .await;
 
@@ -997,7 +1206,7 @@ mod tests {
 
         // Ops 0 through 3 should be included
         for uo in uos.iter().take(4) {
-            pool.add_operation(OperationOrigin::Local, uo.clone())
+            pool.add_operation(OperationOrigin::Local, uo.clone(), None)
                 .await
                 .unwrap();
         }
@@ -1014,7 +1223,7 @@ mod tests {
 
         // Second op should be throttled
         let ret = pool
-            .add_operation(OperationOrigin::Local, uos[4].clone())
+            .add_operation(OperationOrigin::Local, uos[4].clone(), None)
             .await;
 
         assert!(ret.is_err());
@@ -1040,16 +1249,18 @@ mod tests {
                 nonce: uos[0].nonce,
                 actual_gas_cost: U256::zero(),
                 paymaster: None,
+                ..Default::default()
             }],
             entity_balance_updates: vec![],
             unmined_entity_balance_updates: vec![],
             unmined_ops: vec![],
             reorg_larger_than_history: false,
+            state_diff: None,
         })
         .await;
 
         // Second op should be included
-        pool.add_operation(OperationOrigin::Local, uos[4].clone())
+        pool.add_operation(OperationOrigin::Local, uos[4].clone(), None)
             .await
             .unwrap();
         check_ops(
@@ -1074,7 +1285,9 @@ mod tests {
         pool.set_reputation(address, 1 + BAN_SLACK, 0);
 
         // First op should be banned
-        let ret = pool.add_operation(OperationOrigin::Local, uo.clone()).await;
+        let ret = pool
+            .add_operation(OperationOrigin::Local, uo.clone(), None)
+            .await;
         assert!(ret.is_err());
         match ret.unwrap_err() {
             MempoolError::EntityThrottled(entity) => {
@@ -1098,7 +1311,7 @@ mod tests {
         let pool = create_pool(vec![op]);
 
         let ret = pool
-            .add_operation(OperationOrigin::Local, uo.clone())
+            .add_operation(OperationOrigin::Local, uo.clone(), None)
             .await
             .unwrap_err();
 
@@ -1118,7 +1331,10 @@ mod tests {
         let ops = vec![op.clone()];
         let pool = create_pool(ops);
 
-        match pool.add_operation(OperationOrigin::Local, op.op).await {
+        match pool
+            .add_operation(OperationOrigin::Local, op.op, None)
+            .await
+        {
             Err(MempoolError::PrecheckViolation(PrecheckViolation::InitCodeTooShort(_))) => {}
             _ => panic!("Expected InitCodeTooShort error"),
         }
@@ -1138,7 +1354,10 @@ mod tests {
         let ops = vec![op.clone()];
         let pool = create_pool(ops);
 
-        match pool.add_operation(OperationOrigin::Local, op.op).await {
+        match pool
+            .add_operation(OperationOrigin::Local, op.op, None)
+            .await
+        {
             Err(MempoolError::SimulationViolation(SimulationViolation::DidNotRevert)) => {}
             _ => panic!("Expected DidNotRevert error"),
         }
@@ -1151,12 +1370,12 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
             .await
             .unwrap();
 
         let err = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
             .await
             .unwrap_err();
         assert!(matches!(err, MempoolError::OperationAlreadyKnown));
@@ -1164,13 +1383,32 @@ mod tests {
         check_ops(pool.best_operations(1, 0).unwrap(), vec![op.op]);
     }
 
+    #[tokio::test]
+    async fn test_cancelled_op_not_readded() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let pool = create_pool(vec![op.clone()]);
+
+        let hash = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
+            .await
+            .unwrap();
+        pool.remove_operations(&[hash], OpRemovalRequester::Sender);
+
+        let err = pool
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, MempoolError::OperationCancelled));
+        assert_eq!(pool.best_operations(1, 0).unwrap(), vec![]);
+    }
+
     #[tokio::test]
     async fn test_replacement_underpriced() {
         let op = create_op(Address::random(), 0, 100, None);
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
             .await
             .unwrap();
 
@@ -1178,7 +1416,7 @@ mod tests {
         replacement.max_fee_per_gas = replacement.max_fee_per_gas + 1;
 
         let err = pool
-            .add_operation(OperationOrigin::Local, replacement)
+            .add_operation(OperationOrigin::Local, replacement, None)
             .await
             .unwrap_err();
 
@@ -1224,7 +1462,7 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
             .await
             .unwrap();
 
@@ -1232,7 +1470,7 @@ mod tests {
         replacement.max_fee_per_gas = replacement.max_fee_per_gas + 1;
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, replacement.clone())
+            .add_operation(OperationOrigin::Local, replacement.clone(), None)
             .await
             .unwrap();
 
@@ -1262,7 +1500,7 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
             .await
             .unwrap();
 
@@ -1277,13 +1515,31 @@ mod tests {
         check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
     }
 
+    #[tokio::test]
+    async fn test_blocklist_removes_ops() {
+        let op = create_op(Address::random(), 0, 0, None);
+        let (pool, uos) = create_pool_insert_ops(vec![op]).await;
+
+        pool.update_address_list(
+            AddressList::Blocklist,
+            AddressListUpdate::Add(vec![Address::random()]),
+        );
+        check_ops(pool.best_operations(1, 0).unwrap(), uos.clone());
+
+        pool.update_address_list(
+            AddressList::Blocklist,
+            AddressListUpdate::Add(vec![uos[0].sender]),
+        );
+        check_ops(pool.best_operations(1, 0).unwrap(), vec![]);
+    }
+
     #[tokio::test]
     async fn test_get_user_op_by_hash() {
         let op = create_op(Address::random(), 0, 0, None);
         let pool = create_pool(vec![op.clone()]);
 
         let hash = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
             .await
             .unwrap();
 
@@ -1297,7 +1553,7 @@ mod tests {
         let pool = create_pool(vec![op.clone()]);
 
         let _ = pool
-            .add_operation(OperationOrigin::Local, op.op.clone())
+            .add_operation(OperationOrigin::Local, op.op.clone(), None)
             .await
             .unwrap();
 
@@ -1315,12 +1571,12 @@ mod tests {
         let pool = create_pool(ops.clone());
 
         for op in ops.iter().take(4) {
-            pool.add_operation(OperationOrigin::Local, op.op.clone())
+            pool.add_operation(OperationOrigin::Local, op.op.clone(), None)
                 .await
                 .unwrap();
         }
         assert!(pool
-            .add_operation(OperationOrigin::Local, ops[4].op.clone())
+            .add_operation(OperationOrigin::Local, ops[4].op.clone(), None)
             .await
             .is_err());
     }
@@ -1377,7 +1633,7 @@ mod tests {
                 if let Some(error) = &op.precheck_error {
                     Err(PrecheckError::Violations(vec![error.clone()]))
                 } else {
-                    Ok(())
+                    Ok(PrecheckReturn::default())
                 }
             });
             simulator
@@ -1415,13 +1671,19 @@ mod tests {
             allowlist: None,
             precheck_settings: PrecheckSettings::default(),
             sim_settings: SimulationSettings::default(),
-            mempool_channel_configs: HashMap::new(),
+            mempool_channel_configs: HashMap::new().into(),
             num_shards: 1,
             same_sender_mempool_count: 4,
             throttled_entity_mempool_count: 4,
             throttled_entity_live_blocks: 10,
             paymaster_tracking_enabled: true,
+            paymaster_spend_window: Duration::from_secs(3600),
+            paymaster_alerts: None,
+            token_paymasters: HashMap::new(),
             reputation_tracking_enabled: true,
+            reputation_params: ReputationParams::default(),
+            reputation_snapshot_dir: None,
+            ordering_policy: Arc::new(MaxFeeOrdering),
         };
         let (event_sender, _) = broadcast::channel(4);
 
@@ -1451,7 +1713,9 @@ mod tests {
         let uos = ops.iter().map(|op| op.op.clone()).collect::<Vec<_>>();
         let pool = create_pool(ops);
         for op in &uos {
-            let _ = pool.add_operation(OperationOrigin::Local, op.clone()).await;
+            let _ = pool
+                .add_operation(OperationOrigin::Local, op.clone(), None)
+                .await;
         }
         (pool, uos)
     }
@@ -1556,17 +1820,17 @@ mod tests {
             }
         }
 
-        fn add_seen(&self, address: Address) {
-            *self.counts.write().seen.entry(address).or_default() += 1;
+        fn add_seen(&self, entity: Entity) {
+            *self.counts.write().seen.entry(entity.address).or_default() += 1;
         }
 
-        fn handle_srep_050_penalty(&self, address: Address) {
-            *self.counts.write().seen.entry(address).or_default() =
+        fn handle_srep_050_penalty(&self, entity: Entity) {
+            *self.counts.write().seen.entry(entity.address).or_default() =
                 self.bundle_invalidation_ops_seen_staked_penalty;
         }
 
-        fn handle_urep_030_penalty(&self, address: Address) {
-            *self.counts.write().seen.entry(address).or_default() +=
+        fn handle_urep_030_penalty(&self, entity: Entity) {
+            *self.counts.write().seen.entry(entity.address).or_default() +=
                 self.bundle_invalidation_ops_seen_unstaked_penalty;
         }
 
@@ -1589,6 +1853,7 @@ mod tests {
                     address: *address,
                     ops_seen: *ops_seen,
                     ops_included: *self.counts.read().included.get(address).unwrap_or(&0),
+                    kinds: vec![],
                 })
                 .collect()
         }
@@ -1599,6 +1864,17 @@ mod tests {
             counts.included.insert(address, ops_included);
         }
 
+        fn import_reputation(&self, reputations: Vec<Reputation>, overwrite: bool) {
+            if overwrite {
+                self.clear();
+            }
+            for rep in reputations {
+                if !self.counts.read().seen.contains_key(&rep.address) {
+                    self.set_reputation(rep.address, rep.ops_seen, rep.ops_included);
+                }
+            }
+        }
+
         fn get_ops_allowed(&self, address: Address) -> u64 {
             let counts = self.counts.read();
             let seen = *counts.seen.get(&address).unwrap_or(&0);
@@ -1622,5 +1898,19 @@ mod tests {
         fn set_tracking(&self, tracking_enabled: bool) {
             self.counts.write().tracking_enabled = tracking_enabled;
         }
+
+        fn update_address_list(
+            &self,
+            list: AddressList,
+            update: AddressListUpdate,
+        ) -> Vec<Address> {
+            match (list, update) {
+                (AddressList::Blocklist, AddressListUpdate::Add(addresses)) => addresses,
+                (AddressList::Blocklist, AddressListUpdate::Replace(addresses)) => {
+                    addresses.into_iter().collect()
+                }
+                _ => vec![],
+            }
+        }
     }
 }
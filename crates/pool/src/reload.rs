@@ -0,0 +1,461 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use ethers::types::{Address, H256};
+use rundler_sim::{MempoolConfig, MempoolRegistry, SharedMempoolConfigs, SimulationCache};
+use rundler_task::Task;
+use serde::de::DeserializeOwned;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+
+use crate::mempool::{AddressList, AddressListUpdate, Mempool};
+
+/// Settings for reloading configuration files while the pool is running.
+///
+/// Each path can either be a local file path or an HTTP(S) URL. A file is only
+/// applied when its contents change. Address list changes are applied as the
+/// addresses added to and removed from the file, keeping entries updated
+/// through the admin API. Mempool IDs that have not been resolved by the
+/// registry are retried on every poll.
+#[derive(Debug, Clone)]
+pub struct ReloadSettings {
+    /// Path to the blocklist file
+    pub blocklist_path: Option<String>,
+    /// Path to the allowlist file
+    pub allowlist_path: Option<String>,
+    /// Path to the alternative mempool configuration file
+    pub mempool_config_path: Option<String>,
+    /// Alternative mempool IDs to resolve with the registry
+    pub mempool_ids: Vec<H256>,
+    /// Registry used to resolve mempool IDs
+    pub mempool_registry: Option<MempoolRegistry>,
+    /// How often to poll the files for changes
+    pub poll_interval: Duration,
+}
+
+/// Polls configuration files and applies changes to the mempools
+pub(crate) struct ConfigReloader<M> {
+    settings: ReloadSettings,
+    mempools: Vec<Arc<M>>,
+    fetcher: Fetcher,
+    mempool_configs: MempoolConfigReloader,
+    // Last loaded addresses of each address list file, by path
+    file_addresses: HashMap<String, HashSet<Address>>,
+}
+
+impl<M: Mempool> ConfigReloader<M> {
+    pub(crate) fn new(
+        settings: ReloadSettings,
+        mempools: Vec<Arc<M>>,
+        mempool_configs: SharedMempoolConfigs,
+        simulation_cache: Option<Arc<SimulationCache>>,
+    ) -> Self {
+        Self {
+            mempool_configs: MempoolConfigReloader::new(
+                settings.clone(),
+                mempool_configs,
+                simulation_cache,
+            ),
+            settings,
+            mempools,
+            fetcher: Fetcher::default(),
+            file_addresses: HashMap::new(),
+        }
+    }
+
+    pub(crate) async fn run(mut self, shutdown_token: CancellationToken) -> anyhow::Result<()> {
+        let mut interval = time::interval(self.settings.poll_interval);
+        loop {
+            tokio::select! {
+                _ = shutdown_token.cancelled() => {
+                    tracing::info!("Shutting down config reloader");
+                    return Ok(());
+                }
+                _ = interval.tick() => {
+                    self.reload().await;
+                }
+            }
+        }
+    }
+
+    async fn reload(&mut self) {
+        if let Some(path) = self.settings.blocklist_path.clone() {
+            match self
+                .fetcher
+                .fetch_if_changed::<HashSet<Address>>(&path)
+                .await
+            {
+                Ok(Some(blocklist)) => {
+                    tracing::info!("Reloaded blocklist from {path}: {blocklist:?}");
+                    self.update_address_list(AddressList::Blocklist, &path, blocklist);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to reload blocklist from {path}: {e:?}"),
+            }
+        }
+
+        if let Some(path) = self.settings.allowlist_path.clone() {
+            match self
+                .fetcher
+                .fetch_if_changed::<HashSet<Address>>(&path)
+                .await
+            {
+                Ok(Some(allowlist)) => {
+                    tracing::info!("Reloaded allowlist from {path}: {allowlist:?}");
+                    self.update_address_list(AddressList::Allowlist, &path, allowlist);
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to reload allowlist from {path}: {e:?}"),
+            }
+        }
+
+        self.mempool_configs.reload().await;
+    }
+
+    // Applies the changes to the addresses of a list file since its last load
+    fn update_address_list(&mut self, list: AddressList, path: &str, addresses: HashSet<Address>) {
+        let previous = self
+            .file_addresses
+            .insert(path.to_string(), addresses.clone())
+            .unwrap_or_default();
+        let removed: Vec<Address> = previous.difference(&addresses).copied().collect();
+        let added: Vec<Address> = addresses.difference(&previous).copied().collect();
+        for mempool in &self.mempools {
+            if !removed.is_empty() {
+                mempool.update_address_list(list, AddressListUpdate::Remove(removed.clone()));
+            }
+            if !added.is_empty() {
+                mempool.update_address_list(list, AddressListUpdate::Add(added.clone()));
+            }
+        }
+    }
+}
+
+/// Task that polls the alternative mempool configuration file and the mempool
+/// registry, and applies changes to shared mempool configurations.
+///
+/// The pool runs one as part of its config reloading. A builder or RPC server
+/// running in its own process runs its own, so that its simulations match
+/// the same mempools as the pool's. The blocklist and allowlist settings are
+/// ignored.
+#[derive(Debug)]
+pub struct MempoolConfigReloader {
+    settings: ReloadSettings,
+    fetcher: Fetcher,
+    mempool_configs: SharedMempoolConfigs,
+    simulation_cache: Option<Arc<SimulationCache>>,
+}
+
+impl MempoolConfigReloader {
+    /// Create a new reloader updating `mempool_configs`, clearing
+    /// `simulation_cache` on each change as its results were matched
+    /// against the previous mempools.
+    pub fn new(
+        settings: ReloadSettings,
+        mempool_configs: SharedMempoolConfigs,
+        simulation_cache: Option<Arc<SimulationCache>>,
+    ) -> Self {
+        Self {
+            settings,
+            fetcher: Fetcher::default(),
+            mempool_configs,
+            simulation_cache,
+        }
+    }
+
+    /// Convert this task into a boxed task.
+    pub fn boxed(self) -> Box<dyn Task> {
+        Box::new(self)
+    }
+
+    async fn reload(&mut self) {
+        let mut configs = None;
+        if let Some(path) = self.settings.mempool_config_path.clone() {
+            match self
+                .fetcher
+                .fetch_if_changed::<HashMap<H256, MempoolConfig>>(&path)
+                .await
+            {
+                Ok(Some(mut reloaded)) => {
+                    match MempoolRegistry::check_conflicts(&reloaded, &self.settings.mempool_ids) {
+                        Ok(()) => {
+                            tracing::info!("Reloaded mempool configs from {path}: {reloaded:?}");
+                            // keep the mempools resolved from the registry
+                            let current = self.mempool_configs.configs();
+                            for id in &self.settings.mempool_ids {
+                                if let Some(config) = current.get(id) {
+                                    reloaded.insert(*id, config.clone());
+                                }
+                            }
+                            configs = Some(reloaded);
+                        }
+                        Err(e) => {
+                            tracing::error!("Ignoring reloaded mempool configs from {path}: {e:?}")
+                        }
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to reload mempool configs from {path}: {e:?}"),
+            }
+        }
+
+        if let Some(registry) = &self.settings.mempool_registry {
+            let unresolved = {
+                let current = configs
+                    .clone()
+                    .unwrap_or_else(|| self.mempool_configs.configs());
+                self.settings
+                    .mempool_ids
+                    .iter()
+                    .filter(|id| !current.contains_key(*id))
+                    .copied()
+                    .collect::<Vec<_>>()
+            };
+            if !unresolved.is_empty() {
+                let resolved = registry.resolve_all(&unresolved).await;
+                if !resolved.is_empty() {
+                    tracing::info!("Resolved mempool configs: {resolved:?}");
+                    configs
+                        .get_or_insert_with(|| self.mempool_configs.configs())
+                        .extend(resolved);
+                }
+            }
+        }
+
+        if let Some(configs) = configs {
+            self.mempool_configs.set(configs);
+            if let Some(cache) = &self.simulation_cache {
+                cache.clear();
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Task for MempoolConfigReloader {
+    async fn run(mut self: Box<Self>, shutdown_token: CancellationToken) -> anyhow::Result<()> {
+        let mut interval = time::interval(self.settings.poll_interval);
+        loop {
+            tokio::select! {
+                _ = shutdown_token.cancelled() => {
+                    tracing::info!("Shutting down mempool config reloader");
+                    return Ok(());
+                }
+                _ = interval.tick() => {
+                    self.reload().await;
+                }
+            }
+        }
+    }
+}
+
+/// Fetches config files, keeping their last contents to detect changes
+#[derive(Debug, Default)]
+struct Fetcher {
+    client: reqwest::Client,
+    contents: HashMap<String, Vec<u8>>,
+}
+
+impl Fetcher {
+    // Returns the parsed file if its contents changed since the last successful parse
+    async fn fetch_if_changed<T: DeserializeOwned>(
+        &mut self,
+        path: &str,
+    ) -> anyhow::Result<Option<T>> {
+        let contents = self.fetch(path).await?;
+        if self.contents.get(path) == Some(&contents) {
+            return Ok(None);
+        }
+
+        let parsed = serde_json::from_slice(&contents).context("should parse config file")?;
+        self.contents.insert(path.to_string(), contents);
+        Ok(Some(parsed))
+    }
+
+    async fn fetch(&self, path: &str) -> anyhow::Result<Vec<u8>> {
+        if path.starts_with("http://") || path.starts_with("https://") {
+            let resp = self.client.get(path).send().await?.error_for_status()?;
+            Ok(resp.bytes().await?.to_vec())
+        } else {
+            Ok(tokio::fs::read(path).await?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::mempool::MockMempool;
+
+    #[tokio::test]
+    async fn test_reload_blocklist_on_change() {
+        let addr = Address::random();
+        let mut file = tempfile();
+        write!(file.1, "[\"{addr:?}\"]").unwrap();
+
+        let mut mempool = MockMempool::new();
+        mempool
+            .expect_update_address_list()
+            .withf(move |list, update| {
+                *list == AddressList::Blocklist && *update == AddressListUpdate::Add(vec![addr])
+            })
+            .times(1)
+            .return_const(());
+
+        let mut reloader = ConfigReloader::new(
+            ReloadSettings {
+                blocklist_path: Some(file.0.clone()),
+                allowlist_path: None,
+                mempool_config_path: None,
+                mempool_ids: vec![],
+                mempool_registry: None,
+                poll_interval: Duration::from_secs(1),
+            },
+            vec![Arc::new(mempool)],
+            SharedMempoolConfigs::new(HashMap::new()),
+            None,
+        );
+
+        // only the first reload applies the unchanged file
+        reloader.reload().await;
+        reloader.reload().await;
+
+        std::fs::remove_file(file.0).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_keeps_admin_additions() {
+        let (file_addr, admin_addr, new_addr) =
+            (Address::random(), Address::random(), Address::random());
+        let file = tempfile();
+        std::fs::write(&file.0, format!("[\"{file_addr:?}\"]")).unwrap();
+
+        let blocklist = Arc::new(parking_lot::Mutex::new(HashSet::new()));
+        let mut mempool = MockMempool::new();
+        let mempool_blocklist = Arc::clone(&blocklist);
+        mempool
+            .expect_update_address_list()
+            .returning(move |_, update| {
+                let mut blocklist = mempool_blocklist.lock();
+                match update {
+                    AddressListUpdate::Replace(addresses) => *blocklist = addresses,
+                    AddressListUpdate::Add(addresses) => blocklist.extend(addresses),
+                    AddressListUpdate::Remove(addresses) => {
+                        for address in addresses {
+                            blocklist.remove(&address);
+                        }
+                    }
+                }
+            });
+        let mempool = Arc::new(mempool);
+
+        let mut reloader = ConfigReloader::new(
+            ReloadSettings {
+                blocklist_path: Some(file.0.clone()),
+                allowlist_path: None,
+                mempool_config_path: None,
+                mempool_ids: vec![],
+                mempool_registry: None,
+                poll_interval: Duration::from_secs(1),
+            },
+            vec![Arc::clone(&mempool)],
+            SharedMempoolConfigs::new(HashMap::new()),
+            None,
+        );
+        reloader.reload().await;
+
+        // added through the admin API
+        mempool.update_address_list(
+            AddressList::Blocklist,
+            AddressListUpdate::Add(vec![admin_addr]),
+        );
+
+        std::fs::write(&file.0, format!("[\"{new_addr:?}\"]")).unwrap();
+        reloader.reload().await;
+        assert_eq!(*blocklist.lock(), HashSet::from([admin_addr, new_addr]));
+
+        std::fs::remove_file(file.0).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_shared_mempool_configs() {
+        let id = H256::random();
+        let mut file = tempfile();
+        write!(file.1, "{{\"{id:?}\": {{\"allowlist\": []}}}}").unwrap();
+
+        // e.g. the handle of the builder's simulator
+        let shared = SharedMempoolConfigs::new(HashMap::new());
+        let mut reloader = MempoolConfigReloader::new(
+            ReloadSettings {
+                blocklist_path: None,
+                allowlist_path: None,
+                mempool_config_path: Some(file.0.clone()),
+                mempool_ids: vec![],
+                mempool_registry: None,
+                poll_interval: Duration::from_secs(1),
+            },
+            shared.clone(),
+            None,
+        );
+
+        reloader.reload().await;
+        assert!(shared.configs().contains_key(&id));
+
+        std::fs::remove_file(file.0).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_rejects_mempool_id_in_file() {
+        let id = H256::random();
+        let mut file = tempfile();
+        write!(file.1, "{{\"{id:?}\": {{\"allowlist\": []}}}}").unwrap();
+
+        let shared = SharedMempoolConfigs::new(HashMap::new());
+        let mut reloader = MempoolConfigReloader::new(
+            ReloadSettings {
+                blocklist_path: None,
+                allowlist_path: None,
+                mempool_config_path: Some(file.0.clone()),
+                mempool_ids: vec![id],
+                mempool_registry: None,
+                poll_interval: Duration::from_secs(1),
+            },
+            shared.clone(),
+            None,
+        );
+
+        reloader.reload().await;
+        assert!(shared.configs().is_empty());
+
+        std::fs::remove_file(file.0).unwrap();
+    }
+
+    fn tempfile() -> (String, std::fs::File) {
+        let path = std::env::temp_dir()
+            .join(format!("rundler-blocklist-{:?}.json", Address::random()))
+            .to_string_lossy()
+            .to_string();
+        let file = std::fs::File::create(&path).unwrap();
+        (path, file)
+    }
+}
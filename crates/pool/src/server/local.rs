@@ -29,7 +29,11 @@ use tracing::error;
 use super::{PoolResult, PoolServerError};
 use crate::{
     chain::ChainUpdate,
-    mempool::{Mempool, MempoolError, OperationOrigin, PoolOperation, StakeStatus},
+    emit::OpRemovalRequester,
+    mempool::{
+        AddressList, AddressListUpdate, Mempool, MempoolError, MempoolFilter, MempoolPage,
+        OpPosition, OperationOrigin, PaymasterStatus, PoolOperation, StakeStatus,
+    },
     server::{NewHead, PoolServer, Reputation},
     ReputationStatus,
 };
@@ -119,11 +123,17 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
-    async fn add_op(&self, entry_point: Address, op: UserOperation) -> PoolResult<H256> {
+    async fn add_op(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        partner: Option<String>,
+    ) -> PoolResult<H256> {
         let req = ServerRequestKind::AddOp {
             entry_point,
             op,
             origin: OperationOrigin::Local,
+            partner,
         };
         let resp = self.send(req).await?;
         match resp {
@@ -159,6 +169,51 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn get_op_position(
+        &self,
+        entry_point: Address,
+        hash: H256,
+    ) -> PoolResult<Option<OpPosition>> {
+        let req = ServerRequestKind::GetOpPosition { entry_point, hash };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetOpPosition { position } => Ok(position),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        let req = ServerRequestKind::GetOpsBySender {
+            entry_point,
+            sender,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetOpsBySender { ops } => Ok(ops),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn get_ops_by_paymaster(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        let req = ServerRequestKind::GetOpsByPaymaster {
+            entry_point,
+            paymaster,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetOpsByPaymaster { ops } => Ok(ops),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<H256>) -> PoolResult<()> {
         let req = ServerRequestKind::RemoveOps { entry_point, ops };
         let resp = self.send(req).await?;
@@ -168,6 +223,19 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn remove_op_by_hash(
+        &self,
+        hash: H256,
+        requester: OpRemovalRequester,
+    ) -> PoolResult<bool> {
+        let req = ServerRequestKind::RemoveOpByHash { hash, requester };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::RemoveOpByHash { removed } => Ok(removed),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn update_entities(
         &self,
         entry_point: Address,
@@ -220,6 +288,42 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn admin_import_reputation(
+        &self,
+        entry_point: Address,
+        reputations: Vec<Reputation>,
+        overwrite: bool,
+    ) -> PoolResult<()> {
+        let req = ServerRequestKind::AdminImportReputation {
+            entry_point,
+            reputations,
+            overwrite,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::AdminImportReputation => Ok(()),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
+    async fn admin_update_address_list(
+        &self,
+        entry_point: Address,
+        list: AddressList,
+        update: AddressListUpdate,
+    ) -> PoolResult<()> {
+        let req = ServerRequestKind::AdminUpdateAddressList {
+            entry_point,
+            list,
+            update,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::AdminUpdateAddressList => Ok(()),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>> {
         let req = ServerRequestKind::DebugDumpMempool { entry_point };
         let resp = self.send(req).await?;
@@ -229,6 +333,26 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn debug_dump_mempool_page(
+        &self,
+        entry_point: Address,
+        filter: MempoolFilter,
+        offset: u64,
+        limit: u64,
+    ) -> PoolResult<MempoolPage> {
+        let req = ServerRequestKind::DebugDumpMempoolPage {
+            entry_point,
+            filter,
+            offset,
+            limit,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::DebugDumpMempoolPage { page } => Ok(page),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn debug_set_reputations(
         &self,
         entry_point: Address,
@@ -270,6 +394,22 @@ impl PoolServer for LocalPoolHandle {
         }
     }
 
+    async fn get_paymaster_status(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> PoolResult<PaymasterStatus> {
+        let req = ServerRequestKind::GetPaymasterStatus {
+            entry_point,
+            paymaster,
+        };
+        let resp = self.send(req).await?;
+        match resp {
+            ServerResponse::GetPaymasterStatus { status } => Ok(status),
+            _ => Err(PoolServerError::UnexpectedResponse),
+        }
+    }
+
     async fn get_reputation_status(
         &self,
         entry_point: Address,
@@ -371,12 +511,53 @@ where
         Ok(None)
     }
 
+    fn get_op_position(&self, entry_point: Address, hash: H256) -> PoolResult<Option<OpPosition>> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool.operation_position(hash))
+    }
+
+    fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool
+            .operations_by_sender(sender)
+            .iter()
+            .map(|op| (**op).clone())
+            .collect())
+    }
+
+    fn get_ops_by_paymaster(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        let mempool = self.get_pool(entry_point)?;
+        Ok(mempool
+            .operations_by_paymaster(paymaster)
+            .iter()
+            .map(|op| (**op).clone())
+            .collect())
+    }
+
     fn remove_ops(&self, entry_point: Address, ops: &[H256]) -> PoolResult<()> {
         let mempool = self.get_pool(entry_point)?;
-        mempool.remove_operations(ops);
+        mempool.remove_operations(ops, OpRemovalRequester::Builder);
         Ok(())
     }
 
+    fn remove_op_by_hash(&self, hash: H256, requester: OpRemovalRequester) -> PoolResult<bool> {
+        for mempool in self.mempools.values() {
+            if mempool.get_user_operation_by_hash(hash).is_some() {
+                mempool.remove_operations(&[hash], requester);
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
     fn update_entities<'a>(
         &self,
         entry_point: Address,
@@ -412,6 +593,28 @@ where
         Ok(())
     }
 
+    fn admin_import_reputation(
+        &self,
+        entry_point: Address,
+        reputations: Vec<Reputation>,
+        overwrite: bool,
+    ) -> PoolResult<()> {
+        let mempool = self.get_pool(entry_point)?;
+        mempool.import_reputation(reputations, overwrite);
+        Ok(())
+    }
+
+    fn admin_update_address_list(
+        &self,
+        entry_point: Address,
+        list: AddressList,
+        update: AddressListUpdate,
+    ) -> PoolResult<()> {
+        let mempool = self.get_pool(entry_point)?;
+        mempool.update_address_list(list, update);
+        Ok(())
+    }
+
     fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>> {
         let mempool = self.get_pool(entry_point)?;
         Ok(mempool
@@ -421,6 +624,25 @@ where
             .collect())
     }
 
+    fn debug_dump_mempool_page(
+        &self,
+        entry_point: Address,
+        filter: &MempoolFilter,
+        offset: u64,
+        limit: u64,
+    ) -> PoolResult<MempoolPage> {
+        let mempool = self.get_pool(entry_point)?;
+        let (ops, total) = mempool.filtered_operations(
+            filter,
+            usize::try_from(offset).unwrap_or(usize::MAX),
+            usize::try_from(limit).unwrap_or(usize::MAX),
+        );
+        Ok(MempoolPage {
+            ops: ops.iter().map(|op| (**op).clone()).collect(),
+            total: total as u64,
+        })
+    }
+
     fn debug_set_reputations<'a>(
         &self,
         entry_point: Address,
@@ -478,12 +700,12 @@ where
                                 entry_points: self.mempools.keys().copied().collect()
                             })
                         },
-                        ServerRequestKind::AddOp { entry_point, op, origin } => {
+                        ServerRequestKind::AddOp { entry_point, op, origin, partner } => {
                             match self.get_pool(entry_point) {
                                 Ok(mempool) => {
                                     let mempool = Arc::clone(mempool);
                                     tokio::spawn(async move {
-                                        let resp = match mempool.add_operation(origin, op).await {
+                                        let resp = match mempool.add_operation(origin, op, partner).await {
                                             Ok(hash) => Ok(ServerResponse::AddOp { hash }),
                                             Err(e) => Err(e.into()),
                                         };
@@ -508,18 +730,54 @@ where
                                 Err(e) => Err(e),
                             }
                         }
+                        ServerRequestKind::GetOpPosition { entry_point, hash } => {
+                            match self.get_op_position(entry_point, hash) {
+                                Ok(position) => Ok(ServerResponse::GetOpPosition { position }),
+                                Err(e) => Err(e),
+                            }
+                        }
+                        ServerRequestKind::GetOpsBySender { entry_point, sender } => {
+                            match self.get_ops_by_sender(entry_point, sender) {
+                                Ok(ops) => Ok(ServerResponse::GetOpsBySender { ops }),
+                                Err(e) => Err(e),
+                            }
+                        }
+                        ServerRequestKind::GetOpsByPaymaster { entry_point, paymaster } => {
+                            match self.get_ops_by_paymaster(entry_point, paymaster) {
+                                Ok(ops) => Ok(ServerResponse::GetOpsByPaymaster { ops }),
+                                Err(e) => Err(e),
+                            }
+                        }
                         ServerRequestKind::RemoveOps { entry_point, ops } => {
                             match self.remove_ops(entry_point, &ops) {
                                 Ok(_) => Ok(ServerResponse::RemoveOps),
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::RemoveOpByHash { hash, requester } => {
+                            match self.remove_op_by_hash(hash, requester) {
+                                Ok(removed) => Ok(ServerResponse::RemoveOpByHash { removed }),
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::AdminSetTracking{ entry_point, paymaster, reputation } => {
                             match self.admin_set_tracking(entry_point, paymaster, reputation) {
                                 Ok(_) => Ok(ServerResponse::AdminSetTracking),
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::AdminImportReputation { entry_point, reputations, overwrite } => {
+                            match self.admin_import_reputation(entry_point, reputations, overwrite) {
+                                Ok(_) => Ok(ServerResponse::AdminImportReputation),
+                                Err(e) => Err(e),
+                            }
+                        },
+                        ServerRequestKind::AdminUpdateAddressList { entry_point, list, update } => {
+                            match self.admin_update_address_list(entry_point, list, update) {
+                                Ok(_) => Ok(ServerResponse::AdminUpdateAddressList),
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::UpdateEntities { entry_point, entity_updates } => {
                             match self.update_entities(entry_point, &entity_updates) {
                                 Ok(_) => Ok(ServerResponse::UpdateEntities),
@@ -538,6 +796,12 @@ where
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::DebugDumpMempoolPage { entry_point, filter, offset, limit } => {
+                            match self.debug_dump_mempool_page(entry_point, &filter, offset, limit) {
+                                Ok(page) => Ok(ServerResponse::DebugDumpMempoolPage { page }),
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::DebugSetReputations { entry_point, reputations } => {
                             match self.debug_set_reputations(entry_point, &reputations) {
                                 Ok(_) => Ok(ServerResponse::DebugSetReputations),
@@ -574,6 +838,24 @@ where
                                 Err(e) => Err(e),
                             }
                         },
+                        ServerRequestKind::GetPaymasterStatus { entry_point, paymaster } => {
+                            match self.get_pool(entry_point) {
+                                Ok(mempool) => {
+                                    let mempool = Arc::clone(mempool);
+                                    tokio::spawn(async move {
+                                        let resp = match mempool.paymaster_status(paymaster).await {
+                                            Ok(status) => Ok(ServerResponse::GetPaymasterStatus { status }),
+                                            Err(e) => Err(PoolServerError::Other(e.into())),
+                                        };
+                                        if let Err(e) = req.response.send(resp) {
+                                            tracing::error!("Failed to send response: {:?}", e);
+                                        }
+                                    });
+                                    continue;
+                                },
+                                Err(e) => Err(e),
+                            }
+                        },
                         ServerRequestKind::SubscribeNewHeads => {
                             Ok(ServerResponse::SubscribeNewHeads { new_heads: self.block_sender.subscribe() } )
                         }
@@ -602,6 +884,7 @@ enum ServerRequestKind {
         entry_point: Address,
         op: UserOperation,
         origin: OperationOrigin,
+        partner: Option<String>,
     },
     GetOps {
         entry_point: Address,
@@ -611,10 +894,26 @@ enum ServerRequestKind {
     GetOpByHash {
         hash: H256,
     },
+    GetOpPosition {
+        entry_point: Address,
+        hash: H256,
+    },
+    GetOpsBySender {
+        entry_point: Address,
+        sender: Address,
+    },
+    GetOpsByPaymaster {
+        entry_point: Address,
+        paymaster: Address,
+    },
     RemoveOps {
         entry_point: Address,
         ops: Vec<H256>,
     },
+    RemoveOpByHash {
+        hash: H256,
+        requester: OpRemovalRequester,
+    },
     UpdateEntities {
         entry_point: Address,
         entity_updates: Vec<EntityUpdate>,
@@ -629,9 +928,25 @@ enum ServerRequestKind {
         paymaster: bool,
         reputation: bool,
     },
+    AdminImportReputation {
+        entry_point: Address,
+        reputations: Vec<Reputation>,
+        overwrite: bool,
+    },
+    AdminUpdateAddressList {
+        entry_point: Address,
+        list: AddressList,
+        update: AddressListUpdate,
+    },
     DebugDumpMempool {
         entry_point: Address,
     },
+    DebugDumpMempoolPage {
+        entry_point: Address,
+        filter: MempoolFilter,
+        offset: u64,
+        limit: u64,
+    },
     DebugSetReputations {
         entry_point: Address,
         reputations: Vec<Reputation>,
@@ -647,6 +962,10 @@ enum ServerRequestKind {
         entry_point: Address,
         address: Address,
     },
+    GetPaymasterStatus {
+        entry_point: Address,
+        paymaster: Address,
+    },
     SubscribeNewHeads,
 }
 
@@ -664,13 +983,30 @@ enum ServerResponse {
     GetOpByHash {
         op: Option<PoolOperation>,
     },
+    GetOpPosition {
+        position: Option<OpPosition>,
+    },
+    GetOpsBySender {
+        ops: Vec<PoolOperation>,
+    },
+    GetOpsByPaymaster {
+        ops: Vec<PoolOperation>,
+    },
     RemoveOps,
+    RemoveOpByHash {
+        removed: bool,
+    },
     UpdateEntities,
     DebugClearState,
     AdminSetTracking,
+    AdminImportReputation,
+    AdminUpdateAddressList,
     DebugDumpMempool {
         ops: Vec<PoolOperation>,
     },
+    DebugDumpMempoolPage {
+        page: MempoolPage,
+    },
     DebugSetReputations,
     DebugDumpReputation {
         reputations: Vec<Reputation>,
@@ -681,6 +1017,9 @@ enum ServerResponse {
     GetStakeStatus {
         status: StakeStatus,
     },
+    GetPaymasterStatus {
+        status: PaymasterStatus,
+    },
     SubscribeNewHeads {
         new_heads: broadcast::Receiver<NewHead>,
     },
@@ -701,14 +1040,14 @@ mod tests {
         let hash0 = H256::random();
         mock_pool
             .expect_add_operation()
-            .returning(move |_, _| Ok(hash0));
+            .returning(move |_, _, _| Ok(hash0));
 
         let ep = Address::random();
         let state = setup(HashMap::from([(ep, Arc::new(mock_pool))]));
 
         let hash1 = state
             .handle
-            .add_op(ep, UserOperation::default())
+            .add_op(ep, UserOperation::default(), None)
             .await
             .unwrap();
         assert_eq!(hash0, hash1);
@@ -767,13 +1106,13 @@ mod tests {
         let hashes = [h0, h1, h2];
         pools[0]
             .expect_add_operation()
-            .returning(move |_, _| Ok(h0));
+            .returning(move |_, _, _| Ok(h0));
         pools[1]
             .expect_add_operation()
-            .returning(move |_, _| Ok(h1));
+            .returning(move |_, _, _| Ok(h1));
         pools[2]
             .expect_add_operation()
-            .returning(move |_, _| Ok(h2));
+            .returning(move |_, _, _| Ok(h2));
 
         let state = setup(
             zip(eps.iter(), pools.into_iter())
@@ -786,7 +1125,7 @@ mod tests {
                 *hash,
                 state
                     .handle
-                    .add_op(*ep, UserOperation::default())
+                    .add_op(*ep, UserOperation::default(), None)
                     .await
                     .unwrap()
             );
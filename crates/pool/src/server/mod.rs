@@ -29,7 +29,11 @@ pub use remote::RemotePoolClient;
 use rundler_types::{EntityUpdate, UserOperation};
 
 use crate::{
-    mempool::{PoolOperation, Reputation, StakeStatus},
+    emit::OpRemovalRequester,
+    mempool::{
+        AddressList, AddressListUpdate, MempoolFilter, MempoolPage, OpPosition, PaymasterStatus,
+        PoolOperation, Reputation, StakeStatus,
+    },
     ReputationStatus,
 };
 
@@ -58,8 +62,13 @@ pub trait PoolServer: Send + Sync + 'static {
     /// Get the supported entry points of the pool
     async fn get_supported_entry_points(&self) -> PoolResult<Vec<Address>>;
 
-    /// Add an operation to the pool
-    async fn add_op(&self, entry_point: Address, op: UserOperation) -> PoolResult<H256>;
+    /// Add an operation to the pool, submitted by `partner` if known
+    async fn add_op(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        partner: Option<String>,
+    ) -> PoolResult<H256>;
 
     /// Get operations from the pool
     async fn get_ops(
@@ -74,9 +83,40 @@ pub trait PoolServer: Send + Sync + 'static {
     /// Returns None if the operation is not found
     async fn get_op_by_hash(&self, hash: H256) -> PoolResult<Option<PoolOperation>>;
 
-    /// Remove operations from the pool by hash
+    /// Get the position of an operation in pool order
+    /// Returns None if the operation is not in the pool
+    async fn get_op_position(
+        &self,
+        entry_point: Address,
+        hash: H256,
+    ) -> PoolResult<Option<OpPosition>>;
+
+    /// Get the operations in the pool from a sender, ordered by nonce
+    async fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PoolOperation>>;
+
+    /// Get the operations in the pool using a paymaster, in pool order
+    async fn get_ops_by_paymaster(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> PoolResult<Vec<PoolOperation>>;
+
+    /// Remove operations from the pool by hash, at the request of the builder
     async fn remove_ops(&self, entry_point: Address, ops: Vec<H256>) -> PoolResult<()>;
 
+    /// Remove an operation from the pool by hash on behalf of a requester
+    /// Checks each entry point in order until the operation is found
+    /// Returns false if the operation is not found
+    async fn remove_op_by_hash(
+        &self,
+        hash: H256,
+        requester: OpRemovalRequester,
+    ) -> PoolResult<bool>;
+
     /// Update operations associated with entities from the pool
     async fn update_entities(
         &self,
@@ -101,6 +141,16 @@ pub trait PoolServer: Send + Sync + 'static {
     /// Dump all operations in the pool, used for debug methods
     async fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>>;
 
+    /// Dump a page of the operations in the pool matching a filter, in pool
+    /// order, used for debug methods
+    async fn debug_dump_mempool_page(
+        &self,
+        entry_point: Address,
+        filter: MempoolFilter,
+        offset: u64,
+        limit: u64,
+    ) -> PoolResult<MempoolPage>;
+
     /// Set reputations for entities, used for debug methods
     async fn debug_set_reputations(
         &self,
@@ -122,6 +172,13 @@ pub trait PoolServer: Send + Sync + 'static {
         address: Address,
     ) -> PoolResult<StakeStatus>;
 
+    /// Get the balances and spend rate of a paymaster given entrypoint and address
+    async fn get_paymaster_status(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> PoolResult<PaymasterStatus>;
+
     /// Dump reputations for entities, used for debug methods
     async fn debug_dump_reputation(&self, entry_point: Address) -> PoolResult<Vec<Reputation>>;
 
@@ -132,4 +189,20 @@ pub trait PoolServer: Send + Sync + 'static {
         paymaster: bool,
         reputation: bool,
     ) -> PoolResult<()>;
+
+    /// Import reputations for entities, either merging with or overwriting the current state
+    async fn admin_import_reputation(
+        &self,
+        entry_point: Address,
+        reputations: Vec<Reputation>,
+        overwrite: bool,
+    ) -> PoolResult<()>;
+
+    /// Updates the blocklist or allowlist, removing operations that use newly blocked entities
+    async fn admin_update_address_list(
+        &self,
+        entry_point: Address,
+        list: AddressList,
+        update: AddressListUpdate,
+    ) -> PoolResult<()>;
 }
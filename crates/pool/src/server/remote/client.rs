@@ -18,6 +18,7 @@ use futures_util::Stream;
 use rundler_task::{
     grpc::protos::{from_bytes, ConversionError},
     server::{HealthCheck, ServerStatus},
+    trace::TracePropagation,
 };
 use rundler_types::{EntityUpdate, UserOperation};
 use rundler_utils::retry::{self, UnlimitedRetryOpts};
@@ -33,17 +34,27 @@ use tonic_health::{
 };
 
 use super::protos::{
-    self, add_op_response, admin_set_tracking_response, debug_clear_state_response,
-    debug_dump_mempool_response, debug_dump_reputation_response, debug_set_reputation_response,
-    get_op_by_hash_response, get_ops_response, get_reputation_status_response,
-    get_stake_status_response, op_pool_client::OpPoolClient, remove_ops_response,
-    update_entities_response, AddOpRequest, AdminSetTrackingRequest, DebugClearStateRequest,
-    DebugDumpMempoolRequest, DebugDumpReputationRequest, DebugSetReputationRequest, GetOpsRequest,
-    GetReputationStatusRequest, GetStakeStatusRequest, RemoveOpsRequest, SubscribeNewHeadsRequest,
-    SubscribeNewHeadsResponse, UpdateEntitiesRequest,
+    self, add_op_response, admin_import_reputation_response, admin_set_tracking_response,
+    admin_update_address_list_response, debug_clear_state_response,
+    debug_dump_mempool_page_response, debug_dump_mempool_response, debug_dump_reputation_response,
+    debug_set_reputation_response, get_op_by_hash_response, get_op_position_response,
+    get_ops_by_paymaster_response, get_ops_by_sender_response, get_ops_response,
+    get_paymaster_status_response, get_reputation_status_response, get_stake_status_response,
+    op_pool_client::OpPoolClient, remove_op_by_hash_response, remove_ops_response,
+    update_entities_response, AddOpRequest, AdminImportReputationRequest, AdminSetTrackingRequest,
+    AdminUpdateAddressListRequest, DebugClearStateRequest, DebugDumpMempoolPageRequest,
+    DebugDumpMempoolRequest, DebugDumpReputationRequest, DebugSetReputationRequest,
+    GetOpPositionRequest, GetOpsByPaymasterRequest, GetOpsBySenderRequest, GetOpsRequest,
+    GetPaymasterStatusRequest, GetReputationStatusRequest, GetStakeStatusRequest,
+    RemoveOpByHashRequest, RemoveOpsRequest, SubscribeNewHeadsRequest, SubscribeNewHeadsResponse,
+    UpdateEntitiesRequest,
 };
 use crate::{
-    mempool::{PoolOperation, Reputation, StakeStatus},
+    emit::OpRemovalRequester,
+    mempool::{
+        AddressList, AddressListUpdate, MempoolFilter, MempoolPage, OpPosition, PaymasterStatus,
+        PoolOperation, Reputation, StakeStatus,
+    },
     server::{error::PoolServerError, NewHead, PoolResult, PoolServer},
     ReputationStatus,
 };
@@ -53,14 +64,15 @@ use crate::{
 /// Used to submit requests to a remote pool server.
 #[derive(Debug, Clone)]
 pub struct RemotePoolClient {
-    op_pool_client: OpPoolClient<Channel>,
+    op_pool_client: OpPoolClient<TracePropagation<Channel>>,
     op_pool_health: HealthClient<Channel>,
 }
 
 impl RemotePoolClient {
     /// Connect to a remote pool server, returning a client for submitting requests.
     pub async fn connect(url: String) -> anyhow::Result<Self> {
-        let op_pool_client = OpPoolClient::connect(url.clone()).await?;
+        let channel = Channel::builder(Uri::from_str(&url)?).connect().await?;
+        let op_pool_client = OpPoolClient::new(TracePropagation::new(channel));
         let op_pool_health =
             HealthClient::new(Channel::builder(Uri::from_str(&url)?).connect().await?);
         Ok(Self {
@@ -72,7 +84,7 @@ impl RemotePoolClient {
     // Handler for the new block subscription. This will attempt to resubscribe if the gRPC
     // connection disconnects using exponential backoff.
     async fn new_heads_subscription_handler(
-        client: OpPoolClient<Channel>,
+        client: OpPoolClient<TracePropagation<Channel>>,
         tx: mpsc::UnboundedSender<NewHead>,
     ) {
         let mut stream = None;
@@ -136,13 +148,19 @@ impl PoolServer for RemotePoolClient {
             .collect::<Result<_, ConversionError>>()?)
     }
 
-    async fn add_op(&self, entry_point: Address, op: UserOperation) -> PoolResult<H256> {
+    async fn add_op(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        partner: Option<String>,
+    ) -> PoolResult<H256> {
         let res = self
             .op_pool_client
             .clone()
             .add_op(AddOpRequest {
                 entry_point: entry_point.as_bytes().to_vec(),
                 op: Some(protos::UserOperation::from(&op)),
+                partner: partner.unwrap_or_default(),
             })
             .await?
             .into_inner()
@@ -216,6 +234,93 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn get_op_position(
+        &self,
+        entry_point: Address,
+        hash: H256,
+    ) -> PoolResult<Option<OpPosition>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_op_position(GetOpPositionRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                hash: hash.as_bytes().to_vec(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_op_position_response::Result::Success(s)) => {
+                Ok(s.position.map(OpPosition::from))
+            }
+            Some(get_op_position_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn get_ops_by_sender(
+        &self,
+        entry_point: Address,
+        sender: Address,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_ops_by_sender(GetOpsBySenderRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                sender: sender.as_bytes().to_vec(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_ops_by_sender_response::Result::Success(s)) => s
+                .ops
+                .into_iter()
+                .map(PoolOperation::try_from)
+                .map(|res| res.map_err(PoolServerError::from))
+                .collect(),
+            Some(get_ops_by_sender_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn get_ops_by_paymaster(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> PoolResult<Vec<PoolOperation>> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_ops_by_paymaster(GetOpsByPaymasterRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                paymaster: paymaster.as_bytes().to_vec(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_ops_by_paymaster_response::Result::Success(s)) => s
+                .ops
+                .into_iter()
+                .map(PoolOperation::try_from)
+                .map(|res| res.map_err(PoolServerError::from))
+                .collect(),
+            Some(get_ops_by_paymaster_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn remove_ops(&self, entry_point: Address, ops: Vec<H256>) -> PoolResult<()> {
         let res = self
             .op_pool_client
@@ -237,6 +342,31 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn remove_op_by_hash(
+        &self,
+        hash: H256,
+        requester: OpRemovalRequester,
+    ) -> PoolResult<bool> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .remove_op_by_hash(RemoveOpByHashRequest {
+                hash: hash.as_bytes().to_vec(),
+                requester: protos::OpRemovalRequester::from(requester).into(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(remove_op_by_hash_response::Result::Success(s)) => Ok(s.removed),
+            Some(remove_op_by_hash_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn update_entities(
         &self,
         entry_point: Address,
@@ -319,6 +449,63 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn admin_import_reputation(
+        &self,
+        entry_point: Address,
+        reputations: Vec<Reputation>,
+        overwrite: bool,
+    ) -> PoolResult<()> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .admin_import_reputation(AdminImportReputationRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                reputations: reputations
+                    .into_iter()
+                    .map(protos::Reputation::from)
+                    .collect(),
+                overwrite,
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(admin_import_reputation_response::Result::Success(_)) => Ok(()),
+            Some(admin_import_reputation_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
+    async fn admin_update_address_list(
+        &self,
+        entry_point: Address,
+        list: AddressList,
+        update: AddressListUpdate,
+    ) -> PoolResult<()> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .admin_update_address_list(AdminUpdateAddressListRequest::new(
+                entry_point,
+                list,
+                update,
+            ))
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(admin_update_address_list_response::Result::Success(_)) => Ok(()),
+            Some(admin_update_address_list_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn debug_dump_mempool(&self, entry_point: Address) -> PoolResult<Vec<PoolOperation>> {
         let res = self
             .op_pool_client
@@ -344,6 +531,42 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn debug_dump_mempool_page(
+        &self,
+        entry_point: Address,
+        filter: MempoolFilter,
+        offset: u64,
+        limit: u64,
+    ) -> PoolResult<MempoolPage> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .debug_dump_mempool_page(DebugDumpMempoolPageRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                filter: Some((&filter).into()),
+                offset,
+                limit,
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(debug_dump_mempool_page_response::Result::Success(s)) => Ok(MempoolPage {
+                ops: s
+                    .ops
+                    .into_iter()
+                    .map(PoolOperation::try_from)
+                    .collect::<Result<_, _>>()?,
+                total: s.total,
+            }),
+            Some(debug_dump_mempool_page_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn debug_set_reputations(
         &self,
         entry_point: Address,
@@ -451,6 +674,33 @@ impl PoolServer for RemotePoolClient {
         }
     }
 
+    async fn get_paymaster_status(
+        &self,
+        entry_point: Address,
+        paymaster: Address,
+    ) -> PoolResult<PaymasterStatus> {
+        let res = self
+            .op_pool_client
+            .clone()
+            .get_paymaster_status(GetPaymasterStatusRequest {
+                entry_point: entry_point.as_bytes().to_vec(),
+                paymaster: paymaster.as_bytes().to_vec(),
+            })
+            .await?
+            .into_inner()
+            .result;
+
+        match res {
+            Some(get_paymaster_status_response::Result::Success(s)) => {
+                Ok(s.status.unwrap_or_default().try_into()?)
+            }
+            Some(get_paymaster_status_response::Result::Failure(f)) => Err(f.try_into()?),
+            None => Err(PoolServerError::Other(anyhow::anyhow!(
+                "should have received result from op pool"
+            )))?,
+        }
+    }
+
     async fn subscribe_new_heads(&self) -> PoolResult<Pin<Box<dyn Stream<Item = NewHead> + Send>>> {
         let (tx, rx) = mpsc::unbounded_channel();
         let client = self.op_pool_client.clone();
@@ -13,7 +13,9 @@
 
 use anyhow::{bail, Context};
 use ethers::types::Opcode;
-use rundler_sim::{NeedsStakeInformation, PrecheckViolation, SimulationViolation, ViolationOpCode};
+use rundler_sim::{
+    NeedsStakeInformation, PrecheckViolation, SimulationViolation, ValidationUsage, ViolationOpCode,
+};
 use rundler_task::grpc::protos::{from_bytes, to_le_bytes, ConversionError};
 use rundler_types::StorageSlot;
 
@@ -21,14 +23,15 @@ use super::protos::{
     mempool_error, precheck_violation_error, simulation_violation_error,
     AccessedUndeployedContract, AggregatorValidationFailed, AssociatedStorageIsAlternateSender,
     CallGasLimitTooLow, CallHadValue, CalledBannedEntryPointMethod, CodeHashChanged, DidNotRevert,
-    DiscardedOnInsertError, Entity, EntityThrottledError, EntityType, ExistingSenderWithInitCode,
-    FactoryCalledCreate2Twice, FactoryIsNotContract, InitCodeTooShort, InvalidSignature,
-    InvalidStorageAccess, MaxFeePerGasTooLow, MaxOperationsReachedError,
+    DiscardedOnInsertError, Entity, EntityThrottledError, EntityType, ExceededMempoolLimits,
+    ExistingSenderWithInitCode, FactoryCalledCreate2Twice, FactoryIsNotContract, InitCodeTooShort,
+    InvalidSignature, InvalidStorageAccess, MaxFeePerGasTooLow, MaxOperationsReachedError,
     MaxPriorityFeePerGasTooLow, MempoolError as ProtoMempoolError, MultipleRolesViolation,
-    NotStaked, OperationAlreadyKnownError, OutOfGas, PaymasterBalanceTooLow,
-    PaymasterDepositTooLow, PaymasterIsNotContract, PaymasterTooShort, PreVerificationGasTooLow,
-    PrecheckViolationError as ProtoPrecheckViolationError, ReplacementUnderpricedError,
-    SenderAddressUsedAsAlternateEntity, SenderFundsTooLow, SenderIsNotContractAndNoInitCode,
+    NotStaked, OperationAlreadyKnownError, OperationCancelledError, OutOfGas,
+    PaymasterBalanceTooLow, PaymasterDepositTooLow, PaymasterIsNotContract, PaymasterTooShort,
+    PreVerificationGasTooLow, PrecheckViolationError as ProtoPrecheckViolationError,
+    ReplacementUnderpricedError, SenderAddressUsedAsAlternateEntity, SenderFundsTooLow,
+    SenderIsNotContractAndNoInitCode, SenderTokenAllowanceTooLow, SenderTokenBalanceTooLow,
     SimulationViolationError as ProtoSimulationViolationError, TotalGasLimitTooHigh,
     UnintendedRevert, UnintendedRevertWithMessage, UnknownEntryPointError, UnstakedAggregator,
     UnstakedPaymasterContext, UnsupportedAggregatorError, UsedForbiddenOpcode,
@@ -81,6 +84,7 @@ impl TryFrom<ProtoMempoolError> for MempoolError {
             Some(mempool_error::Error::OperationAlreadyKnown(_)) => {
                 MempoolError::OperationAlreadyKnown
             }
+            Some(mempool_error::Error::OperationCancelled(_)) => MempoolError::OperationCancelled,
             Some(mempool_error::Error::ReplacementUnderpriced(e)) => {
                 MempoolError::ReplacementUnderpriced(
                     from_bytes(&e.current_fee)?,
@@ -125,6 +129,11 @@ impl From<MempoolError> for ProtoMempoolError {
                     OperationAlreadyKnownError {},
                 )),
             },
+            MempoolError::OperationCancelled => ProtoMempoolError {
+                error: Some(mempool_error::Error::OperationCancelled(
+                    OperationCancelledError {},
+                )),
+            },
             MempoolError::MultipleRolesViolation(entity) => ProtoMempoolError {
                 error: Some(mempool_error::Error::MultipleRolesViolation(
                     MultipleRolesViolation {
@@ -305,6 +314,32 @@ impl From<PrecheckViolation> for ProtoPrecheckViolationError {
                     },
                 )),
             },
+            PrecheckViolation::SenderTokenBalanceTooLow(token, actual, min) => {
+                ProtoPrecheckViolationError {
+                    violation: Some(
+                        precheck_violation_error::Violation::SenderTokenBalanceTooLow(
+                            SenderTokenBalanceTooLow {
+                                token_address: token.as_bytes().to_vec(),
+                                actual_balance: to_le_bytes(actual),
+                                min_balance: to_le_bytes(min),
+                            },
+                        ),
+                    ),
+                }
+            }
+            PrecheckViolation::SenderTokenAllowanceTooLow(token, actual, min) => {
+                ProtoPrecheckViolationError {
+                    violation: Some(
+                        precheck_violation_error::Violation::SenderTokenAllowanceTooLow(
+                            SenderTokenAllowanceTooLow {
+                                token_address: token.as_bytes().to_vec(),
+                                actual_allowance: to_le_bytes(actual),
+                                min_allowance: to_le_bytes(min),
+                            },
+                        ),
+                    ),
+                }
+            }
             PrecheckViolation::MaxFeePerGasTooLow(actual, min) => ProtoPrecheckViolationError {
                 violation: Some(precheck_violation_error::Violation::MaxFeePerGasTooLow(
                     MaxFeePerGasTooLow {
@@ -390,6 +425,20 @@ impl TryFrom<ProtoPrecheckViolationError> for PrecheckViolation {
                     from_bytes(&e.min_funds)?,
                 )
             }
+            Some(precheck_violation_error::Violation::SenderTokenBalanceTooLow(e)) => {
+                PrecheckViolation::SenderTokenBalanceTooLow(
+                    from_bytes(&e.token_address)?,
+                    from_bytes(&e.actual_balance)?,
+                    from_bytes(&e.min_balance)?,
+                )
+            }
+            Some(precheck_violation_error::Violation::SenderTokenAllowanceTooLow(e)) => {
+                PrecheckViolation::SenderTokenAllowanceTooLow(
+                    from_bytes(&e.token_address)?,
+                    from_bytes(&e.actual_allowance)?,
+                    from_bytes(&e.min_allowance)?,
+                )
+            }
             Some(precheck_violation_error::Violation::MaxFeePerGasTooLow(e)) => {
                 PrecheckViolation::MaxFeePerGasTooLow(
                     from_bytes(&e.actual_fee)?,
@@ -579,6 +628,16 @@ impl From<SimulationViolation> for ProtoSimulationViolationError {
                     ),
                 ),
             },
+            SimulationViolation::ExceededMempoolLimits(usage) => ProtoSimulationViolationError {
+                violation: Some(
+                    simulation_violation_error::Violation::ExceededMempoolLimits(
+                        ExceededMempoolLimits {
+                            gas_used: usage.gas_used,
+                            opcodes: usage.opcodes,
+                        },
+                    ),
+                ),
+            },
         }
     }
 }
@@ -703,6 +762,12 @@ impl TryFrom<ProtoSimulationViolationError> for SimulationViolation {
             Some(simulation_violation_error::Violation::AggregatorValidationFailed(_)) => {
                 SimulationViolation::AggregatorValidationFailed
             }
+            Some(simulation_violation_error::Violation::ExceededMempoolLimits(e)) => {
+                SimulationViolation::ExceededMempoolLimits(ValidationUsage {
+                    gas_used: e.gas_used,
+                    opcodes: e.opcodes,
+                })
+            }
             None => {
                 bail!("unknown proto mempool simulation violation")
             }
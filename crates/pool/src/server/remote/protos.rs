@@ -12,8 +12,8 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use anyhow::{anyhow, Context};
-use ethers::types::{Address, H256};
-use rundler_task::grpc::protos::{from_bytes, to_le_bytes, ConversionError};
+use ethers::types::{Address, H256, U256};
+use rundler_task::grpc::protos::{from_bytes, to_le_bytes, ConversionError, FromProtoBytes};
 use rundler_types::{
     Entity as RundlerEntity, EntityType as RundlerEntityType, EntityUpdate as RundlerEntityUpdate,
     EntityUpdateType as RundlerEntityUpdateType, UserOperation as RundlerUserOperation,
@@ -21,9 +21,13 @@ use rundler_types::{
 };
 
 use crate::{
+    emit::OpRemovalRequester as PoolOpRemovalRequester,
     mempool::{
-        PoolOperation, Reputation as PoolReputation, ReputationStatus as PoolReputationStatus,
-        StakeInfo as RundlerStakeInfo, StakeStatus as RundlerStakeStatus,
+        AddressList as PoolAddressList, AddressListUpdate as PoolAddressListUpdate,
+        MempoolFilter as PoolMempoolFilter, OpPosition as PoolOpPosition,
+        PaymasterStatus as PoolPaymasterStatus, PoolOperation, Reputation as PoolReputation,
+        ReputationStatus as PoolReputationStatus, StakeInfo as RundlerStakeInfo,
+        StakeStatus as RundlerStakeStatus,
     },
     server::NewHead as PoolNewHead,
 };
@@ -186,6 +190,94 @@ impl From<PoolReputation> for Reputation {
             address: rep.address.as_bytes().to_vec(),
             ops_seen: rep.ops_seen,
             ops_included: rep.ops_included,
+            kinds: rep
+                .kinds
+                .into_iter()
+                .map(|kind| EntityType::from(kind).into())
+                .collect(),
+        }
+    }
+}
+
+impl From<PoolAddressList> for AddressList {
+    fn from(list: PoolAddressList) -> Self {
+        match list {
+            PoolAddressList::Blocklist => AddressList::Blocklist,
+            PoolAddressList::Allowlist => AddressList::Allowlist,
+        }
+    }
+}
+
+impl TryFrom<i32> for PoolAddressList {
+    type Error = ConversionError;
+
+    fn try_from(list: i32) -> Result<Self, Self::Error> {
+        match list {
+            x if x == AddressList::Blocklist as i32 => Ok(Self::Blocklist),
+            x if x == AddressList::Allowlist as i32 => Ok(Self::Allowlist),
+            _ => Err(ConversionError::InvalidEnumValue(list)),
+        }
+    }
+}
+
+impl AdminUpdateAddressListRequest {
+    pub fn new(entry_point: Address, list: PoolAddressList, update: PoolAddressListUpdate) -> Self {
+        let (action, addresses) = match update {
+            PoolAddressListUpdate::Add(addresses) => (AddressListAction::Add, addresses),
+            PoolAddressListUpdate::Remove(addresses) => (AddressListAction::Remove, addresses),
+            PoolAddressListUpdate::Replace(addresses) => {
+                (AddressListAction::Replace, addresses.into_iter().collect())
+            }
+        };
+
+        Self {
+            entry_point: entry_point.as_bytes().to_vec(),
+            list: AddressList::from(list).into(),
+            action: action.into(),
+            addresses: addresses.iter().map(|a| a.as_bytes().to_vec()).collect(),
+        }
+    }
+
+    pub fn address_list_update(
+        &self,
+    ) -> Result<(PoolAddressList, PoolAddressListUpdate), ConversionError> {
+        let list = PoolAddressList::try_from(self.list)?;
+        let addresses = self
+            .addresses
+            .iter()
+            .map(|a| from_bytes(a))
+            .collect::<Result<Vec<Address>, _>>()?;
+        let update = match self.action {
+            x if x == AddressListAction::Add as i32 => PoolAddressListUpdate::Add(addresses),
+            x if x == AddressListAction::Remove as i32 => PoolAddressListUpdate::Remove(addresses),
+            x if x == AddressListAction::Replace as i32 => {
+                PoolAddressListUpdate::Replace(addresses.into_iter().collect())
+            }
+            _ => return Err(ConversionError::InvalidEnumValue(self.action)),
+        };
+        Ok((list, update))
+    }
+}
+
+impl From<PoolOpRemovalRequester> for OpRemovalRequester {
+    fn from(requester: PoolOpRemovalRequester) -> Self {
+        match requester {
+            PoolOpRemovalRequester::Builder => OpRemovalRequester::Builder,
+            PoolOpRemovalRequester::Sender => OpRemovalRequester::Sender,
+            PoolOpRemovalRequester::Admin => OpRemovalRequester::Admin,
+        }
+    }
+}
+
+impl TryFrom<i32> for PoolOpRemovalRequester {
+    type Error = ConversionError;
+
+    fn try_from(requester: i32) -> Result<Self, Self::Error> {
+        match requester {
+            x if x == OpRemovalRequester::Builder as i32 => Ok(Self::Builder),
+            x if x == OpRemovalRequester::Sender as i32 => Ok(Self::Sender),
+            x if x == OpRemovalRequester::Admin as i32 => Ok(Self::Admin),
+            _ => Err(ConversionError::InvalidEnumValue(requester)),
         }
     }
 }
@@ -211,6 +303,15 @@ impl TryFrom<Reputation> for PoolReputation {
             address: from_bytes(&op.address)?,
             ops_seen: op.ops_seen,
             ops_included: op.ops_included,
+            kinds: op
+                .kinds
+                .into_iter()
+                .map(|kind| {
+                    EntityType::try_from(kind)
+                        .map_err(|_| ConversionError::InvalidEnumValue(kind))?
+                        .try_into()
+                })
+                .collect::<Result<_, _>>()?,
         })
     }
 }
@@ -245,6 +346,35 @@ impl From<RundlerStakeStatus> for StakeStatus {
     }
 }
 
+impl From<&PoolPaymasterStatus> for PaymasterStatus {
+    fn from(status: &PoolPaymasterStatus) -> Self {
+        PaymasterStatus {
+            address: status.address.as_bytes().to_vec(),
+            confirmed_balance: to_le_bytes(status.confirmed_balance),
+            pending_balance: to_le_bytes(status.pending_balance),
+            pending_ops: status.pending_ops,
+            spend_per_hour: to_le_bytes(status.spend_per_hour),
+            runway_seconds: status.runway_seconds.unwrap_or_default(),
+            has_runway: status.runway_seconds.is_some(),
+        }
+    }
+}
+
+impl TryFrom<PaymasterStatus> for PoolPaymasterStatus {
+    type Error = ConversionError;
+
+    fn try_from(status: PaymasterStatus) -> Result<Self, Self::Error> {
+        Ok(PoolPaymasterStatus {
+            address: from_bytes(&status.address)?,
+            confirmed_balance: from_bytes(&status.confirmed_balance)?,
+            pending_balance: from_bytes(&status.pending_balance)?,
+            pending_ops: status.pending_ops,
+            spend_per_hour: from_bytes(&status.spend_per_hour)?,
+            runway_seconds: status.has_runway.then_some(status.runway_seconds),
+        })
+    }
+}
+
 impl From<&PoolOperation> for MempoolOp {
     fn from(op: &PoolOperation) -> Self {
         MempoolOp {
@@ -265,6 +395,53 @@ impl From<&PoolOperation> for MempoolOp {
     }
 }
 
+impl From<&PoolMempoolFilter> for MempoolFilter {
+    fn from(filter: &PoolMempoolFilter) -> Self {
+        let address = |a: Option<Address>| a.map_or(vec![], |a| a.as_bytes().to_vec());
+        let fee = |f: Option<U256>| f.map_or(vec![], to_le_bytes);
+        MempoolFilter {
+            sender: address(filter.sender),
+            paymaster: address(filter.paymaster),
+            factory: address(filter.factory),
+            aggregator: address(filter.aggregator),
+            min_max_fee_per_gas: fee(filter.min_max_fee_per_gas),
+            max_max_fee_per_gas: fee(filter.max_max_fee_per_gas),
+            min_max_priority_fee_per_gas: fee(filter.min_max_priority_fee_per_gas),
+            max_max_priority_fee_per_gas: fee(filter.max_max_priority_fee_per_gas),
+            min_sim_block_number: filter.min_sim_block_number.unwrap_or_default(),
+            max_sim_block_number: filter.max_sim_block_number.unwrap_or_default(),
+        }
+    }
+}
+
+impl TryFrom<MempoolFilter> for PoolMempoolFilter {
+    type Error = ConversionError;
+
+    fn try_from(filter: MempoolFilter) -> Result<Self, Self::Error> {
+        fn optional<T: FromProtoBytes>(bytes: &[u8]) -> Result<Option<T>, ConversionError> {
+            if bytes.is_empty() {
+                Ok(None)
+            } else {
+                from_bytes(bytes).map(Some)
+            }
+        }
+        let block_number = |n: u64| (n != 0).then_some(n);
+
+        Ok(PoolMempoolFilter {
+            sender: optional(&filter.sender)?,
+            paymaster: optional(&filter.paymaster)?,
+            factory: optional(&filter.factory)?,
+            aggregator: optional(&filter.aggregator)?,
+            min_max_fee_per_gas: optional(&filter.min_max_fee_per_gas)?,
+            max_max_fee_per_gas: optional(&filter.max_max_fee_per_gas)?,
+            min_max_priority_fee_per_gas: optional(&filter.min_max_priority_fee_per_gas)?,
+            max_max_priority_fee_per_gas: optional(&filter.max_max_priority_fee_per_gas)?,
+            min_sim_block_number: block_number(filter.min_sim_block_number),
+            max_sim_block_number: block_number(filter.max_sim_block_number),
+        })
+    }
+}
+
 pub const MISSING_USER_OP_ERR_STR: &str = "Mempool op should contain user operation";
 impl TryFrom<MempoolOp> for PoolOperation {
     type Error = anyhow::Error;
@@ -305,6 +482,8 @@ impl TryFrom<MempoolOp> for PoolOperation {
             sim_block_number: 0,
             account_is_staked: op.account_is_staked,
             entity_infos: rundler_sim::EntityInfos::default(),
+            partner: None,
+            token_cost: None,
         })
     }
 }
@@ -328,3 +507,21 @@ impl From<PoolNewHead> for NewHead {
         }
     }
 }
+
+impl From<OpPosition> for PoolOpPosition {
+    fn from(position: OpPosition) -> Self {
+        Self {
+            position: position.position,
+            pool_size: position.pool_size,
+        }
+    }
+}
+
+impl From<PoolOpPosition> for OpPosition {
+    fn from(position: PoolOpPosition) -> Self {
+        Self {
+            position: position.position,
+            pool_size: position.pool_size,
+        }
+    }
+}
@@ -22,7 +22,10 @@ use std::{
 use async_trait::async_trait;
 use ethers::types::{Address, H256};
 use futures_util::StreamExt;
-use rundler_task::grpc::{metrics::GrpcMetricsLayer, protos::from_bytes};
+use rundler_task::{
+    grpc::{metrics::GrpcMetricsLayer, protos::from_bytes},
+    trace::TraceContextLayer,
+};
 use rundler_types::EntityUpdate;
 use tokio::{sync::mpsc, task::JoinHandle};
 use tokio_stream::wrappers::UnboundedReceiverStream;
@@ -30,24 +33,34 @@ use tokio_util::sync::CancellationToken;
 use tonic::{transport::Server, Request, Response, Result, Status};
 
 use super::protos::{
-    add_op_response, admin_set_tracking_response, debug_clear_state_response,
-    debug_dump_mempool_response, debug_dump_reputation_response, debug_set_reputation_response,
-    get_op_by_hash_response, get_ops_response, get_reputation_status_response,
-    get_stake_status_response,
+    add_op_response, admin_import_reputation_response, admin_set_tracking_response,
+    admin_update_address_list_response, debug_clear_state_response,
+    debug_dump_mempool_page_response, debug_dump_mempool_response, debug_dump_reputation_response,
+    debug_set_reputation_response, get_op_by_hash_response, get_op_position_response,
+    get_ops_by_paymaster_response, get_ops_by_sender_response, get_ops_response,
+    get_paymaster_status_response, get_reputation_status_response, get_stake_status_response,
     op_pool_server::{OpPool, OpPoolServer},
-    remove_ops_response, update_entities_response, AddOpRequest, AddOpResponse, AddOpSuccess,
-    AdminSetTrackingRequest, AdminSetTrackingResponse, AdminSetTrackingSuccess,
-    DebugClearStateRequest, DebugClearStateResponse, DebugClearStateSuccess,
-    DebugDumpMempoolRequest, DebugDumpMempoolResponse, DebugDumpMempoolSuccess,
-    DebugDumpReputationRequest, DebugDumpReputationResponse, DebugDumpReputationSuccess,
-    DebugSetReputationRequest, DebugSetReputationResponse, DebugSetReputationSuccess,
-    GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess, GetOpsRequest, GetOpsResponse,
-    GetOpsSuccess, GetReputationStatusRequest, GetReputationStatusResponse,
-    GetReputationStatusSuccess, GetStakeStatusRequest, GetStakeStatusResponse,
-    GetStakeStatusSuccess, GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse,
-    MempoolOp, RemoveOpsRequest, RemoveOpsResponse, RemoveOpsSuccess, SubscribeNewHeadsRequest,
-    SubscribeNewHeadsResponse, UpdateEntitiesRequest, UpdateEntitiesResponse,
-    UpdateEntitiesSuccess, OP_POOL_FILE_DESCRIPTOR_SET,
+    remove_op_by_hash_response, remove_ops_response, update_entities_response, AddOpRequest,
+    AddOpResponse, AddOpSuccess, AdminImportReputationRequest, AdminImportReputationResponse,
+    AdminImportReputationSuccess, AdminSetTrackingRequest, AdminSetTrackingResponse,
+    AdminSetTrackingSuccess, AdminUpdateAddressListRequest, AdminUpdateAddressListResponse,
+    AdminUpdateAddressListSuccess, DebugClearStateRequest, DebugClearStateResponse,
+    DebugClearStateSuccess, DebugDumpMempoolPageRequest, DebugDumpMempoolPageResponse,
+    DebugDumpMempoolPageSuccess, DebugDumpMempoolRequest, DebugDumpMempoolResponse,
+    DebugDumpMempoolSuccess, DebugDumpReputationRequest, DebugDumpReputationResponse,
+    DebugDumpReputationSuccess, DebugSetReputationRequest, DebugSetReputationResponse,
+    DebugSetReputationSuccess, GetOpByHashRequest, GetOpByHashResponse, GetOpByHashSuccess,
+    GetOpPositionRequest, GetOpPositionResponse, GetOpPositionSuccess, GetOpsByPaymasterRequest,
+    GetOpsByPaymasterResponse, GetOpsByPaymasterSuccess, GetOpsBySenderRequest,
+    GetOpsBySenderResponse, GetOpsBySenderSuccess, GetOpsRequest, GetOpsResponse, GetOpsSuccess,
+    GetPaymasterStatusRequest, GetPaymasterStatusResponse, GetPaymasterStatusSuccess,
+    GetReputationStatusRequest, GetReputationStatusResponse, GetReputationStatusSuccess,
+    GetStakeStatusRequest, GetStakeStatusResponse, GetStakeStatusSuccess,
+    GetSupportedEntryPointsRequest, GetSupportedEntryPointsResponse, MempoolOp, OpPosition,
+    RemoveOpByHashRequest, RemoveOpByHashResponse, RemoveOpByHashSuccess, RemoveOpsRequest,
+    RemoveOpsResponse, RemoveOpsSuccess, SubscribeNewHeadsRequest, SubscribeNewHeadsResponse,
+    UpdateEntitiesRequest, UpdateEntitiesResponse, UpdateEntitiesSuccess,
+    OP_POOL_FILE_DESCRIPTOR_SET,
 };
 use crate::{
     mempool::Reputation,
@@ -79,6 +92,7 @@ pub(crate) async fn spawn_remote_mempool_server(
     let handle = tokio::spawn(async move {
         Server::builder()
             .layer(metrics_layer)
+            .layer(TraceContextLayer::new("op_pool"))
             .add_service(op_pool_server)
             .add_service(reflection_service)
             .add_service(health_service)
@@ -148,7 +162,9 @@ impl OpPool for OpPoolImpl {
             Status::invalid_argument(format!("Failed to convert to UserOperation: {e}"))
         })?;
 
-        let resp = match self.local_pool.add_op(ep, uo).await {
+        let partner = (!req.partner.is_empty()).then_some(req.partner);
+
+        let resp = match self.local_pool.add_op(ep, uo, partner).await {
             Ok(hash) => AddOpResponse {
                 result: Some(add_op_response::Result::Success(AddOpSuccess {
                     hash: hash.as_bytes().to_vec(),
@@ -210,6 +226,80 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_op_position(
+        &self,
+        request: Request<GetOpPositionRequest>,
+    ) -> Result<Response<GetOpPositionResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let hash = from_bytes(&req.hash).map_err(|e| {
+            Status::invalid_argument(format!("Invalid hash in GetOpPositionRequest: {e}"))
+        })?;
+
+        let resp = match self.local_pool.get_op_position(ep, hash).await {
+            Ok(position) => GetOpPositionResponse {
+                result: Some(get_op_position_response::Result::Success(
+                    GetOpPositionSuccess {
+                        position: position.map(OpPosition::from),
+                    },
+                )),
+            },
+            Err(error) => GetOpPositionResponse {
+                result: Some(get_op_position_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn get_ops_by_sender(
+        &self,
+        request: Request<GetOpsBySenderRequest>,
+    ) -> Result<Response<GetOpsBySenderResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let sender = self.get_address(&req.sender)?;
+
+        let resp = match self.local_pool.get_ops_by_sender(ep, sender).await {
+            Ok(ops) => GetOpsBySenderResponse {
+                result: Some(get_ops_by_sender_response::Result::Success(
+                    GetOpsBySenderSuccess {
+                        ops: ops.iter().map(MempoolOp::from).collect(),
+                    },
+                )),
+            },
+            Err(error) => GetOpsBySenderResponse {
+                result: Some(get_ops_by_sender_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn get_ops_by_paymaster(
+        &self,
+        request: Request<GetOpsByPaymasterRequest>,
+    ) -> Result<Response<GetOpsByPaymasterResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let paymaster = self.get_address(&req.paymaster)?;
+
+        let resp = match self.local_pool.get_ops_by_paymaster(ep, paymaster).await {
+            Ok(ops) => GetOpsByPaymasterResponse {
+                result: Some(get_ops_by_paymaster_response::Result::Success(
+                    GetOpsByPaymasterSuccess {
+                        ops: ops.iter().map(MempoolOp::from).collect(),
+                    },
+                )),
+            },
+            Err(error) => GetOpsByPaymasterResponse {
+                result: Some(get_ops_by_paymaster_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn remove_ops(
         &self,
         request: Request<RemoveOpsRequest>,
@@ -240,6 +330,32 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn remove_op_by_hash(
+        &self,
+        request: Request<RemoveOpByHashRequest>,
+    ) -> Result<Response<RemoveOpByHashResponse>> {
+        let req = request.into_inner();
+        let hash: H256 = from_bytes(&req.hash).map_err(|e| {
+            Status::invalid_argument(format!("Invalid hash in RemoveOpByHashRequest: {e}"))
+        })?;
+        let requester = req.requester.try_into().map_err(|e| {
+            Status::invalid_argument(format!("Invalid requester in RemoveOpByHashRequest: {e}"))
+        })?;
+
+        let resp = match self.local_pool.remove_op_by_hash(hash, requester).await {
+            Ok(removed) => RemoveOpByHashResponse {
+                result: Some(remove_op_by_hash_response::Result::Success(
+                    RemoveOpByHashSuccess { removed },
+                )),
+            },
+            Err(error) => RemoveOpByHashResponse {
+                result: Some(remove_op_by_hash_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn update_entities(
         &self,
         request: Request<UpdateEntitiesRequest>,
@@ -316,6 +432,72 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn admin_import_reputation(
+        &self,
+        request: Request<AdminImportReputationRequest>,
+    ) -> Result<Response<AdminImportReputationResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+
+        let reps = req
+            .reputations
+            .into_iter()
+            .map(|r| r.try_into())
+            .collect::<Result<Vec<Reputation>, _>>()
+            .map_err(|e| {
+                Status::internal(format!("Failed to convert from proto reputation {e}"))
+            })?;
+
+        let resp = match self
+            .local_pool
+            .admin_import_reputation(ep, reps, req.overwrite)
+            .await
+        {
+            Ok(_) => AdminImportReputationResponse {
+                result: Some(admin_import_reputation_response::Result::Success(
+                    AdminImportReputationSuccess {},
+                )),
+            },
+            Err(error) => AdminImportReputationResponse {
+                result: Some(admin_import_reputation_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
+    async fn admin_update_address_list(
+        &self,
+        request: Request<AdminUpdateAddressListRequest>,
+    ) -> Result<Response<AdminUpdateAddressListResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let (list, update) = req.address_list_update().map_err(|e| {
+            Status::invalid_argument(format!("Failed to convert address list update {e}"))
+        })?;
+
+        let resp = match self
+            .local_pool
+            .admin_update_address_list(ep, list, update)
+            .await
+        {
+            Ok(_) => AdminUpdateAddressListResponse {
+                result: Some(admin_update_address_list_response::Result::Success(
+                    AdminUpdateAddressListSuccess {},
+                )),
+            },
+            Err(error) => AdminUpdateAddressListResponse {
+                result: Some(admin_update_address_list_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn debug_dump_mempool(
         &self,
         request: Request<DebugDumpMempoolRequest>,
@@ -339,6 +521,41 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn debug_dump_mempool_page(
+        &self,
+        request: Request<DebugDumpMempoolPageRequest>,
+    ) -> Result<Response<DebugDumpMempoolPageResponse>> {
+        let req = request.into_inner();
+        let ep = self.get_entry_point(&req.entry_point)?;
+        let filter = req
+            .filter
+            .unwrap_or_default()
+            .try_into()
+            .map_err(|e| Status::invalid_argument(format!("Invalid mempool filter: {e}")))?;
+
+        let resp = match self
+            .local_pool
+            .debug_dump_mempool_page(ep, filter, req.offset, req.limit)
+            .await
+        {
+            Ok(page) => DebugDumpMempoolPageResponse {
+                result: Some(debug_dump_mempool_page_response::Result::Success(
+                    DebugDumpMempoolPageSuccess {
+                        ops: page.ops.iter().map(MempoolOp::from).collect(),
+                        total: page.total,
+                    },
+                )),
+            },
+            Err(error) => DebugDumpMempoolPageResponse {
+                result: Some(debug_dump_mempool_page_response::Result::Failure(
+                    error.into(),
+                )),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn debug_set_reputation(
         &self,
         request: Request<DebugSetReputationRequest>,
@@ -432,6 +649,35 @@ impl OpPool for OpPoolImpl {
         Ok(Response::new(resp))
     }
 
+    async fn get_paymaster_status(
+        &self,
+        request: Request<GetPaymasterStatusRequest>,
+    ) -> Result<Response<GetPaymasterStatusResponse>> {
+        let req = request.into_inner();
+
+        let paymaster = self.get_address(&req.paymaster)?;
+        let entry_point = self.get_entry_point(&req.entry_point)?;
+
+        let resp = match self
+            .local_pool
+            .get_paymaster_status(entry_point, paymaster)
+            .await
+        {
+            Ok(status) => GetPaymasterStatusResponse {
+                result: Some(get_paymaster_status_response::Result::Success(
+                    GetPaymasterStatusSuccess {
+                        status: Some((&status).into()),
+                    },
+                )),
+            },
+            Err(error) => GetPaymasterStatusResponse {
+                result: Some(get_paymaster_status_response::Result::Failure(error.into())),
+            },
+        };
+
+        Ok(Response::new(resp))
+    }
+
     async fn debug_dump_reputation(
         &self,
         request: Request<DebugDumpReputationRequest>,
@@ -18,21 +18,27 @@ use async_trait::async_trait;
 use ethers::providers::Middleware;
 use rundler_provider::{EntryPoint, PaymasterHelper, Provider};
 use rundler_sim::{
-    Prechecker, PrecheckerImpl, SimulateValidationTracerImpl, Simulator, SimulatorImpl,
+    gas, Prechecker, PrecheckerImpl, SharedMempoolConfigs, SimulateValidationTracerImpl,
+    SimulationCache, Simulator, SimulatorImpl,
 };
 use rundler_task::Task;
 use rundler_types::contracts::{
     i_entry_point::IEntryPoint, paymaster_helper::PaymasterHelper as PaymasterHelperContract,
 };
-use rundler_utils::{emit::WithEntryPoint, eth, handle};
-use tokio::{sync::broadcast, try_join};
+use rundler_utils::{emit::WithEntryPoint, eth, handle, revert::AbiRegistry};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+    try_join,
+};
 use tokio_util::sync::CancellationToken;
 
 use super::mempool::{HourlyMovingAverageReputation, PoolConfig, ReputationParams};
 use crate::{
-    chain::{self, Chain},
+    chain::{self, Chain, ChainSubscription, ChainUpdate},
     emit::OpPoolEvent,
     mempool::UoPool,
+    reload::{ConfigReloader, ReloadSettings},
     server::{spawn_remote_mempool_server, LocalPoolBuilder},
 };
 
@@ -54,6 +60,13 @@ pub struct Args {
     pub remote_address: Option<SocketAddr>,
     /// Channel capacity for the chain update channel.
     pub chain_update_channel_capacity: usize,
+    /// Settings for reloading configuration files at runtime, if enabled.
+    pub reload_settings: Option<ReloadSettings>,
+    /// Cache of simulation results, if enabled. Enabling the cache also enables
+    /// loading the state diff of each new block to invalidate it.
+    pub simulation_cache: Option<Arc<SimulationCache>>,
+    /// Registry of errors to decode unexpected validation reverts with
+    pub abi_registry: Arc<AbiRegistry>,
 }
 
 /// Mempool task.
@@ -62,6 +75,7 @@ pub struct PoolTask {
     args: Args,
     event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
     pool_builder: LocalPoolBuilder,
+    update_sender: broadcast::Sender<Arc<ChainUpdate>>,
 }
 
 #[async_trait]
@@ -81,23 +95,64 @@ impl Task for PoolTask {
                 .iter()
                 .map(|config| config.entry_point)
                 .collect(),
+            track_state_diffs: self.args.simulation_cache.is_some(),
         };
         let provider = eth::new_provider(&self.args.http_url, Some(self.args.http_poll_interval))?;
+        for pool_config in &self.args.pool_configs {
+            gas::check_l1_gas_oracle(pool_config.precheck_settings.chain_spec.l1_gas, &*provider)
+                .await?;
+        }
         let chain = Chain::new(provider.clone(), chain_settings);
-        let (update_sender, _) = broadcast::channel(self.args.chain_update_channel_capacity);
+        let update_sender = self.update_sender.clone();
         let chain_handle = chain.spawn_watcher(update_sender.clone(), shutdown_token.clone());
+        let cache_handle = match &self.args.simulation_cache {
+            Some(cache) => tokio::spawn(update_simulation_cache(
+                Arc::clone(cache),
+                update_sender.subscribe(),
+                shutdown_token.clone(),
+            )),
+            None => tokio::spawn(async { Ok(()) }),
+        };
 
         // create mempools
         let mut mempools = HashMap::new();
+        let mut reputation_handles = vec![];
+        let reputation_token = shutdown_token.child_token();
         for pool_config in &self.args.pool_configs {
-            let pool =
-                PoolTask::create_mempool(pool_config, self.event_sender.clone(), provider.clone())
-                    .await
-                    .context("should have created mempool")?;
+            let (pool, reputation_handle) = PoolTask::create_mempool(
+                pool_config,
+                self.event_sender.clone(),
+                provider.clone(),
+                self.args.simulation_cache.clone(),
+                Arc::clone(&self.args.abi_registry),
+                reputation_token.clone(),
+            )
+            .await
+            .context("should have created mempool")?;
 
             mempools.insert(pool_config.entry_point, Arc::new(pool));
+            reputation_handles.push(reputation_handle);
         }
 
+        let reload_handle = match &self.args.reload_settings {
+            Some(settings) => {
+                let mempool_configs = self
+                    .args
+                    .pool_configs
+                    .first()
+                    .map(|config| config.mempool_channel_configs.clone())
+                    .unwrap_or_else(|| SharedMempoolConfigs::new(HashMap::new()));
+                let reloader = ConfigReloader::new(
+                    settings.clone(),
+                    mempools.values().cloned().collect(),
+                    mempool_configs,
+                    self.args.simulation_cache.clone(),
+                );
+                tokio::spawn(reloader.run(shutdown_token.clone()))
+            }
+            None => tokio::spawn(async { Ok(()) }),
+        };
+
         let pool_handle = self.pool_builder.get_handle();
         let pool_runner_handle =
             self.pool_builder
@@ -113,11 +168,24 @@ impl Task for PoolTask {
 
         tracing::info!("Started op_pool");
 
-        match try_join!(
+        let result = try_join!(
             handle::flatten_handle(pool_runner_handle),
             handle::flatten_handle(remote_handle),
+            handle::flatten_handle(reload_handle),
+            handle::flatten_handle(cache_handle),
             handle::as_anyhow_handle(chain_handle),
-        ) {
+        );
+
+        // Stop the reputation managers, even if the pool failed, and wait for
+        // them to persist their final snapshots
+        reputation_token.cancel();
+        for reputation_handle in reputation_handles {
+            if let Err(e) = reputation_handle.await {
+                tracing::error!("Reputation manager failed: {e:?}");
+            }
+        }
+
+        match result {
             Ok(_) => {
                 tracing::info!("Pool server shutdown");
                 Ok(())
@@ -137,13 +205,21 @@ impl PoolTask {
         event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
         pool_builder: LocalPoolBuilder,
     ) -> PoolTask {
+        let (update_sender, _) = broadcast::channel(args.chain_update_channel_capacity);
         Self {
             args,
             event_sender,
             pool_builder,
+            update_sender,
         }
     }
 
+    /// Subscribe to the chain updates of the pool. Updates are sent once the
+    /// task is running.
+    pub fn subscribe_chain(&self) -> ChainSubscription {
+        ChainSubscription(self.update_sender.subscribe())
+    }
+
     /// Convert this task into a boxed task.
     pub fn boxed(self) -> Box<dyn Task> {
         Box::new(self)
@@ -153,7 +229,10 @@ impl PoolTask {
         pool_config: &PoolConfig,
         event_sender: broadcast::Sender<WithEntryPoint<OpPoolEvent>>,
         provider: Arc<P>,
-    ) -> anyhow::Result<
+        simulation_cache: Option<Arc<SimulationCache>>,
+        abi_registry: Arc<AbiRegistry>,
+        shutdown_token: CancellationToken,
+    ) -> anyhow::Result<(
         UoPool<
             HourlyMovingAverageReputation,
             impl Prechecker,
@@ -161,16 +240,28 @@ impl PoolTask {
             impl EntryPoint,
             impl PaymasterHelper,
         >,
-    > {
+        JoinHandle<()>,
+    )> {
         // Reputation manager
-        let reputation = Arc::new(HourlyMovingAverageReputation::new(
-            ReputationParams::new(pool_config.reputation_tracking_enabled),
+        let reputation_params = ReputationParams {
+            tracking_enabled: pool_config.reputation_tracking_enabled,
+            ..pool_config.reputation_params.clone()
+        };
+        reputation_params.validate()?;
+        let mut reputation = HourlyMovingAverageReputation::new(
+            reputation_params,
             pool_config.blocklist.clone(),
             pool_config.allowlist.clone(),
-        ));
-        // Start reputation manager
+        );
+        if let Some(dir) = &pool_config.reputation_snapshot_dir {
+            let path = dir.join(format!("reputation_{:?}.json", pool_config.entry_point));
+            reputation = reputation.with_snapshot_path(path);
+        }
+        let reputation = Arc::new(reputation);
+        // Start reputation manager, which persists a final snapshot on shutdown
         let reputation_runner = Arc::clone(&reputation);
-        tokio::spawn(async move { reputation_runner.run().await });
+        let reputation_handle =
+            tokio::spawn(async move { reputation_runner.run(shutdown_token).await });
 
         let i_entry_point = IEntryPoint::new(pool_config.entry_point, Arc::clone(&provider));
         let paymaster_helper =
@@ -181,17 +272,28 @@ impl PoolTask {
         let prechecker = PrecheckerImpl::new(
             Arc::clone(&provider),
             i_entry_point.clone(),
-            pool_config.precheck_settings,
-        );
-        let simulator = SimulatorImpl::new(
+            pool_config.precheck_settings.clone(),
+        )
+        .with_token_paymasters(pool_config.token_paymasters.clone());
+        let mut simulator = SimulatorImpl::new(
             Arc::clone(&provider),
             i_entry_point.address(),
             simulate_validation_tracer,
             pool_config.sim_settings,
             pool_config.mempool_channel_configs.clone(),
-        );
+        )
+        .with_allowed_precompiles(
+            &pool_config
+                .precheck_settings
+                .chain_spec
+                .precompile_addresses,
+        )
+        .with_abi_registry(abi_registry);
+        if let Some(cache) = simulation_cache {
+            simulator = simulator.with_cache(cache);
+        }
 
-        Ok(UoPool::new(
+        let pool = UoPool::new(
             pool_config.clone(),
             Arc::clone(&reputation),
             event_sender,
@@ -199,6 +301,38 @@ impl PoolTask {
             simulator,
             i_entry_point,
             paymaster_helper,
-        ))
+        );
+        Ok((pool, reputation_handle))
+    }
+}
+
+// Carries cached simulation results forward on each new block, dropping the ones
+// whose dependencies changed. Reorgs and missed updates clear the cache.
+async fn update_simulation_cache(
+    cache: Arc<SimulationCache>,
+    mut chain_updates: broadcast::Receiver<Arc<ChainUpdate>>,
+    shutdown_token: CancellationToken,
+) -> anyhow::Result<()> {
+    loop {
+        tokio::select! {
+            _ = shutdown_token.cancelled() => return Ok(()),
+            update = chain_updates.recv() => match update {
+                Ok(update) => {
+                    // the state diff of a reorg doesn't undo the changes of the replaced blocks
+                    let state_diff = if update.reorg_depth == 0 {
+                        update.state_diff.as_ref()
+                    } else {
+                        None
+                    };
+                    cache.on_new_block(
+                        update.latest_block_hash,
+                        state_diff,
+                        &update.touched_entities(),
+                    )
+                }
+                Err(RecvError::Lagged(_)) => cache.clear(),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
     }
 }
@@ -11,7 +11,11 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    sync::Arc,
+};
 
 use anyhow::Context;
 use ethers::{
@@ -24,28 +28,22 @@ use ethers::{
     types::{
         spoof, transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
         Eip1559TransactionRequest, FeeHistory, Filter, GethDebugTracingCallOptions,
-        GethDebugTracingOptions, GethTrace, Log, Transaction, TransactionReceipt, TxHash, H160,
-        H256, U256, U64,
+        GethDebugTracingOptions, GethTrace, Log, Transaction, TransactionReceipt, TxHash, H256,
+        U256, U64,
     },
 };
 use rundler_types::{
     contracts::{
         gas_price_oracle::GasPriceOracle, i_aggregator::IAggregator, i_entry_point::IEntryPoint,
-        node_interface::NodeInterface,
+        i_token_oracle::ITokenOracle, ierc20::IERC20, node_interface::NodeInterface,
     },
     UserOperation,
 };
-use serde::{de::DeserializeOwned, Serialize};
-
-use crate::{AggregatorOut, AggregatorSimOut, Provider, ProviderError, ProviderResult};
-
-const ARBITRUM_NITRO_NODE_INTERFACE_ADDRESS: Address = H160([
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xc8,
-]);
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use tracing::instrument;
 
-const OPTIMISM_BEDROCK_GAS_ORACLE_ADDRESS: Address = H160([
-    0x42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0F,
-]);
+use crate::{AggregatorOut, AggregatorSimOut, Provider, ProviderError, ProviderResult, StateDiff};
 
 #[async_trait::async_trait]
 impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
@@ -53,6 +51,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
     // `Middleware` because forming a `PendingTransaction` specifically requires
     // a `Provider`.
 
+    #[instrument(skip_all, fields(method = %method))]
     async fn request<T, R>(&self, method: &str, params: T) -> ProviderResult<R>
     where
         T: Debug + Serialize + Send + Sync + 'static,
@@ -61,6 +60,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(EthersProvider::request(self, method, params).await?)
     }
 
+    #[instrument(skip_all, fields(block = ?block))]
     async fn call(
         &self,
         tx: &TypedTransaction,
@@ -74,6 +74,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(call.await?)
     }
 
+    #[instrument(skip_all)]
     async fn fee_history<T: Into<U256> + Send + Sync + Serialize + 'static>(
         &self,
         t: T,
@@ -83,6 +84,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(Middleware::fee_history(self, t, block_number, reward_percentiles).await?)
     }
 
+    #[instrument(skip_all)]
     async fn get_block_number(&self) -> ProviderResult<u64> {
         Ok(Middleware::get_block_number(self)
             .await
@@ -90,6 +92,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
             .as_u64())
     }
 
+    #[instrument(skip_all)]
     async fn get_block<T: Into<BlockId> + Send + Sync + 'static>(
         &self,
         block_hash_or_number: T,
@@ -97,6 +100,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(Middleware::get_block(self, block_hash_or_number).await?)
     }
 
+    #[instrument(skip_all)]
     async fn get_transaction<T: Send + Sync + Into<TxHash>>(
         &self,
         transaction_hash: T,
@@ -104,6 +108,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(Middleware::get_transaction(self, transaction_hash).await?)
     }
 
+    #[instrument(skip_all)]
     async fn get_transaction_receipt<T: Send + Sync + Into<TxHash> + 'static>(
         &self,
         transaction_hash: T,
@@ -111,6 +116,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(Middleware::get_transaction_receipt(self, transaction_hash).await?)
     }
 
+    #[instrument(skip_all)]
     async fn debug_trace_transaction(
         &self,
         tx_hash: TxHash,
@@ -119,6 +125,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(Middleware::debug_trace_transaction(self, tx_hash, trace_options).await?)
     }
 
+    #[instrument(skip_all)]
     async fn debug_trace_call(
         &self,
         tx: TypedTransaction,
@@ -128,10 +135,98 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(Middleware::debug_trace_call(self, tx, block_id, trace_options).await?)
     }
 
+    #[instrument(skip_all)]
     async fn get_balance(&self, address: Address, block: Option<BlockId>) -> ProviderResult<U256> {
         Ok(Middleware::get_balance(self, address, block).await?)
     }
 
+    #[instrument(skip_all)]
+    async fn get_block_state_diff(&self, block_hash: H256) -> ProviderResult<StateDiff> {
+        let traces: Vec<TxPrestateDiff> = EthersProvider::request(
+            self,
+            "debug_traceBlockByHash",
+            (
+                block_hash,
+                json!({ "tracer": "prestateTracer", "tracerConfig": { "diffMode": true } }),
+            ),
+        )
+        .await?;
+
+        let mut state_diff = StateDiff::default();
+        for trace in traces {
+            let PrestateDiff { pre, post } = trace.result;
+            for (address, account) in &pre {
+                // accounts are omitted from the post state when deleted
+                if !post.contains_key(address) {
+                    state_diff.accounts.insert(*address);
+                }
+                if let Some(storage) = &account.storage {
+                    state_diff
+                        .storage
+                        .entry(*address)
+                        .or_default()
+                        .extend(storage.keys());
+                }
+            }
+            for (address, account) in post {
+                // the post state only contains the fields that changed
+                if account.balance.is_some() || account.nonce.is_some() || account.code.is_some() {
+                    state_diff.accounts.insert(address);
+                }
+                if let Some(storage) = account.storage {
+                    state_diff
+                        .storage
+                        .entry(address)
+                        .or_default()
+                        .extend(storage.into_keys());
+                }
+            }
+        }
+        Ok(state_diff)
+    }
+
+    #[instrument(skip_all)]
+    async fn apply_call_state_changes(
+        &self,
+        tx: TypedTransaction,
+        block_hash: H256,
+        mut state_override: spoof::State,
+    ) -> ProviderResult<spoof::State> {
+        let diff: PrestateDiff = EthersProvider::request(
+            self,
+            "debug_traceCall",
+            (
+                tx,
+                block_hash,
+                json!({
+                    "tracer": "prestateTracer",
+                    "tracerConfig": { "diffMode": true },
+                    "stateOverrides": state_override,
+                }),
+            ),
+        )
+        .await?;
+
+        // the post state only contains the fields that changed
+        for (address, account) in diff.post {
+            let account_override = state_override.account(address);
+            if let Some(balance) = account.balance {
+                account_override.balance(balance);
+            }
+            if let Some(nonce) = account.nonce {
+                account_override.nonce(nonce.into());
+            }
+            if let Some(code) = account.code {
+                account_override.code(code);
+            }
+            for (slot, value) in account.storage.unwrap_or_default() {
+                account_override.store(slot, value);
+            }
+        }
+        Ok(state_override)
+    }
+
+    #[instrument(skip_all)]
     async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(H256, U64)> {
         let latest_block = Middleware::get_block(self, BlockId::Number(BlockNumber::Latest))
             .await
@@ -147,6 +242,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         ))
     }
 
+    #[instrument(skip_all)]
     async fn get_base_fee(&self) -> ProviderResult<U256> {
         Ok(Middleware::get_block(self, BlockNumber::Pending)
             .await
@@ -156,14 +252,22 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
             .context("pending block should have a nonempty base fee")?)
     }
 
+    #[instrument(skip_all)]
     async fn get_max_priority_fee(&self) -> ProviderResult<U256> {
         Ok(self.request("eth_maxPriorityFeePerGas", ()).await?)
     }
 
+    #[instrument(skip_all)]
+    async fn get_gas_price(&self) -> ProviderResult<U256> {
+        Ok(Middleware::get_gas_price(self).await?)
+    }
+
+    #[instrument(skip_all)]
     async fn get_logs(&self, filter: &Filter) -> ProviderResult<Vec<Log>> {
         Ok(Middleware::get_logs(self, filter).await?)
     }
 
+    #[instrument(skip_all)]
     async fn aggregate_signatures(
         self: Arc<Self>,
         aggregator_address: Address,
@@ -179,6 +283,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         }
     }
 
+    #[instrument(skip_all)]
     async fn validate_user_op_signature(
         self: Arc<Self>,
         aggregator_address: Address,
@@ -202,16 +307,20 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         }
     }
 
+    #[instrument(skip_all)]
     async fn get_code(&self, address: Address, block_hash: Option<H256>) -> ProviderResult<Bytes> {
         Ok(Middleware::get_code(self, address, block_hash.map(|b| b.into())).await?)
     }
 
+    #[instrument(skip_all)]
     async fn get_transaction_count(&self, address: Address) -> ProviderResult<U256> {
         Ok(Middleware::get_transaction_count(self, address, None).await?)
     }
 
+    #[instrument(skip_all)]
     async fn calc_arbitrum_l1_gas(
         self: Arc<Self>,
+        node_interface_address: Address,
         entry_point_address: Address,
         op: UserOperation,
     ) -> ProviderResult<U256> {
@@ -221,7 +330,7 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
             .calldata()
             .context("should get calldata for entry point handle ops")?;
 
-        let arb_node = NodeInterface::new(ARBITRUM_NITRO_NODE_INTERFACE_ADDRESS, self);
+        let arb_node = NodeInterface::new(node_interface_address, self);
         let gas = arb_node
             .gas_estimate_l1_component(entry_point_address, false, data)
             .call()
@@ -229,11 +338,12 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
         Ok(U256::from(gas.0))
     }
 
-    async fn calc_optimism_l1_gas(
+    #[instrument(skip_all)]
+    async fn get_l1_fee(
         self: Arc<Self>,
+        oracle_address: Address,
         entry_point_address: Address,
         op: UserOperation,
-        gas_price: U256,
     ) -> ProviderResult<U256> {
         let entry_point = IEntryPoint::new(entry_point_address, Arc::clone(&self));
         let data = entry_point
@@ -254,11 +364,48 @@ impl<C: JsonRpcClient + 'static> Provider for EthersProvider<C> {
             .chain_id(U64::from(100_000))
             .rlp();
 
-        let gas_oracle =
-            GasPriceOracle::new(OPTIMISM_BEDROCK_GAS_ORACLE_ADDRESS, Arc::clone(&self));
+        let gas_oracle = GasPriceOracle::new(oracle_address, Arc::clone(&self));
+        Ok(gas_oracle.get_l1_fee(tx).call().await?)
+    }
 
-        let l1_fee = gas_oracle.get_l1_fee(tx).call().await?;
-        Ok(l1_fee.checked_div(gas_price).unwrap_or(U256::MAX))
+    #[instrument(skip_all)]
+    async fn get_token_balance(
+        self: Arc<Self>,
+        token_address: Address,
+        owner: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        let token = IERC20::new(token_address, self);
+        let mut call = token.balance_of(owner);
+        call.block = block;
+        Ok(call.call().await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn get_token_allowance(
+        self: Arc<Self>,
+        token_address: Address,
+        owner: Address,
+        spender: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        let token = IERC20::new(token_address, self);
+        let mut call = token.allowance(owner, spender);
+        call.block = block;
+        Ok(call.call().await?)
+    }
+
+    #[instrument(skip_all)]
+    async fn get_token_value_of_eth(
+        self: Arc<Self>,
+        oracle_address: Address,
+        eth_amount: U256,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256> {
+        let oracle = ITokenOracle::new(oracle_address, self);
+        let mut call = oracle.get_token_value_of_eth(eth_amount);
+        call.block = block;
+        Ok(call.call().await?)
     }
 }
 
@@ -282,3 +429,24 @@ impl<M: Middleware> From<EthersContractError<M>> for ProviderError {
         ProviderError::ContractError(e.to_string())
     }
 }
+
+#[derive(Debug, Deserialize)]
+struct TxPrestateDiff {
+    result: PrestateDiff,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrestateDiff {
+    #[serde(default)]
+    pre: HashMap<Address, PrestateAccount>,
+    #[serde(default)]
+    post: HashMap<Address, PrestateAccount>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrestateAccount {
+    balance: Option<U256>,
+    nonce: Option<u64>,
+    code: Option<Bytes>,
+    storage: Option<BTreeMap<H256, H256>>,
+}
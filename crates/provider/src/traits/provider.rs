@@ -13,7 +13,11 @@
 
 //! Trait for interacting with chain data and contracts.
 
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    sync::Arc,
+};
 
 use ethers::types::{
     spoof, transaction::eip2718::TypedTransaction, Address, Block, BlockId, BlockNumber, Bytes,
@@ -47,6 +51,32 @@ pub enum AggregatorOut {
     ValidationReverted,
 }
 
+/// The accounts and storage slots whose state changed in a block
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateDiff {
+    /// Accounts whose balance, nonce, or code changed, or that were deleted
+    pub accounts: HashSet<Address>,
+    /// Changed storage slots, by account
+    pub storage: HashMap<Address, HashSet<H256>>,
+}
+
+impl StateDiff {
+    /// Add the changes of another diff to this one
+    pub fn extend(&mut self, other: StateDiff) {
+        self.accounts.extend(other.accounts);
+        for (address, slots) in other.storage {
+            self.storage.entry(address).or_default().extend(slots);
+        }
+    }
+
+    /// Returns true if the slot of the account changed
+    pub fn is_slot_changed(&self, address: Address, slot: H256) -> bool {
+        self.storage
+            .get(&address)
+            .is_some_and(|slots| slots.contains(&slot))
+    }
+}
+
 /// Result of a provider method call
 pub type ProviderResult<T> = Result<T, ProviderError>;
 
@@ -115,6 +145,20 @@ pub trait Provider: Send + Sync + Debug + 'static {
         trace_options: GethDebugTracingCallOptions,
     ) -> ProviderResult<GethTrace>;
 
+    /// Get the accounts and storage slots changed by the transactions of a block,
+    /// using the node's `prestateTracer` in diff mode
+    async fn get_block_state_diff(&self, block_hash: H256) -> ProviderResult<StateDiff>;
+
+    /// Simulate a transaction via `debug_traceCall` using the node's
+    /// `prestateTracer` in diff mode, and return `state_override` with the
+    /// accounts and storage changed by the transaction set to their new values
+    async fn apply_call_state_changes(
+        &self,
+        tx: TypedTransaction,
+        block_hash: H256,
+        state_override: spoof::State,
+    ) -> ProviderResult<spoof::State>;
+
     /// Get the latest block hash and number
     async fn get_latest_block_hash_and_number(&self) -> ProviderResult<(H256, U64)>;
 
@@ -124,6 +168,9 @@ pub trait Provider: Send + Sync + Debug + 'static {
     /// Get the max fee per gas as reported by the node's RPC
     async fn get_max_priority_fee(&self) -> ProviderResult<U256>;
 
+    /// Get the legacy gas price as reported by the node's RPC
+    async fn get_gas_price(&self) -> ProviderResult<U256>;
+
     /// Get the code at an address
     async fn get_code(&self, address: Address, block_hash: Option<H256>) -> ProviderResult<Bytes>;
 
@@ -148,18 +195,48 @@ pub trait Provider: Send + Sync + Debug + 'static {
         gas_cap: u64,
     ) -> ProviderResult<AggregatorOut>;
 
-    /// Calculate the L1 portion of the gas for a user operation on Arbitrum
+    /// Calculate the L1 portion of the gas for a user operation on Arbitrum using
+    /// the `NodeInterface` virtual contract at the given address
     async fn calc_arbitrum_l1_gas(
         self: Arc<Self>,
+        node_interface_address: Address,
         entry_point_address: Address,
         op: UserOperation,
     ) -> ProviderResult<U256>;
 
-    /// Calculate the L1 portion of the gas for a user operation on optimism
-    async fn calc_optimism_l1_gas(
+    /// Get the L1 fee, in wei, of a bundle containing a user operation from a
+    /// `getL1Fee(bytes)` gas price oracle, as deployed on OP Stack chains and Scroll
+    async fn get_l1_fee(
         self: Arc<Self>,
+        oracle_address: Address,
         entry_point_address: Address,
         op: UserOperation,
-        gas_price: U256,
+    ) -> ProviderResult<U256>;
+
+    /// Get the ERC-20 token balance of an address
+    async fn get_token_balance(
+        self: Arc<Self>,
+        token_address: Address,
+        owner: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256>;
+
+    /// Get the amount of an ERC-20 token that a spender is allowed to transfer
+    /// from an owner
+    async fn get_token_allowance(
+        self: Arc<Self>,
+        token_address: Address,
+        owner: Address,
+        spender: Address,
+        block: Option<BlockId>,
+    ) -> ProviderResult<U256>;
+
+    /// Get the amount of a token worth an amount of wei from a token paymaster
+    /// oracle implementing `getTokenValueOfEth(uint256)`
+    async fn get_token_value_of_eth(
+        self: Arc<Self>,
+        oracle_address: Address,
+        eth_amount: U256,
+        block: Option<BlockId>,
     ) -> ProviderResult<U256>;
 }
@@ -12,13 +12,14 @@
 // If not, see https://www.gnu.org/licenses/.
 
 use async_trait::async_trait;
-use ethers::types::Address;
+use ethers::types::{Address, H256};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::error::INTERNAL_ERROR_CODE};
-use rundler_pool::PoolServer;
+use rundler_builder::{BuilderServer, LedgerQuery, LedgerReport};
+use rundler_pool::{AddressListUpdate, OpRemovalRequester, PoolServer};
 
 use crate::{
     error::rpc_err,
-    types::{RpcAdminClearState, RpcAdminSetTracking},
+    types::{RpcAddressList, RpcAdminClearState, RpcAdminSetTracking, RpcReputationInput},
 };
 
 /// Admin API
@@ -35,22 +36,74 @@ pub trait AdminApi {
         entrypoint: Address,
         tracking_info: RpcAdminSetTracking,
     ) -> RpcResult<String>;
+
+    /// Exports the reputation state of all tracked entities.
+    #[method(name = "exportReputation")]
+    async fn admin_export_reputation(
+        &self,
+        entrypoint: Address,
+    ) -> RpcResult<Vec<RpcReputationInput>>;
+
+    /// Imports reputation state. If `overwrite` is true the existing state is replaced,
+    /// else entries are only imported for entities without existing reputation state.
+    #[method(name = "importReputation")]
+    async fn admin_import_reputation(
+        &self,
+        entrypoint: Address,
+        reputations: Vec<RpcReputationInput>,
+        overwrite: bool,
+    ) -> RpcResult<String>;
+
+    /// Adds addresses to the blocklist or allowlist. Operations using newly blocked
+    /// entities are removed from the mempool.
+    #[method(name = "addToAddressList")]
+    async fn admin_add_to_address_list(
+        &self,
+        entrypoint: Address,
+        list: RpcAddressList,
+        addresses: Vec<Address>,
+    ) -> RpcResult<String>;
+
+    /// Removes addresses from the blocklist or allowlist.
+    #[method(name = "removeFromAddressList")]
+    async fn admin_remove_from_address_list(
+        &self,
+        entrypoint: Address,
+        list: RpcAddressList,
+        addresses: Vec<Address>,
+    ) -> RpcResult<String>;
+
+    /// Removes a user operation from the mempool by hash. Returns false if the
+    /// operation was not found.
+    #[method(name = "removeUserOperation")]
+    async fn admin_remove_user_operation(&self, hash: H256) -> RpcResult<bool>;
+
+    /// Returns a page of the mined bundles recorded in the builder's ledger that
+    /// match the query, along with their total costs and revenue. Pages hold at
+    /// most 1000 bundles.
+    #[method(name = "getLedger")]
+    async fn admin_get_ledger(&self, query: LedgerQuery) -> RpcResult<LedgerReport>;
 }
 
-pub(crate) struct AdminApi<P> {
+/// Maximum number of bundles returned by `admin_getLedger`
+const MAX_LEDGER_PAGE_SIZE: u64 = 1000;
+
+pub(crate) struct AdminApi<P, B> {
     pool: P,
+    builder: B,
 }
 
-impl<P> AdminApi<P> {
-    pub(crate) fn new(pool: P) -> Self {
-        Self { pool }
+impl<P, B> AdminApi<P, B> {
+    pub(crate) fn new(pool: P, builder: B) -> Self {
+        Self { pool, builder }
     }
 }
 
 #[async_trait]
-impl<P> AdminApiServer for AdminApi<P>
+impl<P, B> AdminApiServer for AdminApi<P, B>
 where
     P: PoolServer,
+    B: BuilderServer,
 {
     async fn admin_clear_state(&self, clear_params: RpcAdminClearState) -> RpcResult<String> {
         let _ = self
@@ -83,4 +136,94 @@ where
 
         Ok("ok".to_string())
     }
+
+    async fn admin_export_reputation(
+        &self,
+        entrypoint: Address,
+    ) -> RpcResult<Vec<RpcReputationInput>> {
+        let reputations = self
+            .pool
+            .debug_dump_reputation(entrypoint)
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+
+        reputations
+            .into_iter()
+            .map(|r| r.try_into())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e: anyhow::Error| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))
+    }
+
+    async fn admin_import_reputation(
+        &self,
+        entrypoint: Address,
+        reputations: Vec<RpcReputationInput>,
+        overwrite: bool,
+    ) -> RpcResult<String> {
+        let _ = self
+            .pool
+            .admin_import_reputation(
+                entrypoint,
+                reputations.into_iter().map(Into::into).collect(),
+                overwrite,
+            )
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+
+        Ok("ok".to_string())
+    }
+
+    async fn admin_add_to_address_list(
+        &self,
+        entrypoint: Address,
+        list: RpcAddressList,
+        addresses: Vec<Address>,
+    ) -> RpcResult<String> {
+        let _ = self
+            .pool
+            .admin_update_address_list(entrypoint, list.into(), AddressListUpdate::Add(addresses))
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+
+        Ok("ok".to_string())
+    }
+
+    async fn admin_remove_from_address_list(
+        &self,
+        entrypoint: Address,
+        list: RpcAddressList,
+        addresses: Vec<Address>,
+    ) -> RpcResult<String> {
+        let _ = self
+            .pool
+            .admin_update_address_list(
+                entrypoint,
+                list.into(),
+                AddressListUpdate::Remove(addresses),
+            )
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+
+        Ok("ok".to_string())
+    }
+
+    async fn admin_remove_user_operation(&self, hash: H256) -> RpcResult<bool> {
+        self.pool
+            .remove_op_by_hash(hash, OpRemovalRequester::Admin)
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))
+    }
+
+    async fn admin_get_ledger(&self, mut query: LedgerQuery) -> RpcResult<LedgerReport> {
+        query.limit = Some(
+            query
+                .limit
+                .unwrap_or(MAX_LEDGER_PAGE_SIZE)
+                .min(MAX_LEDGER_PAGE_SIZE),
+        );
+        self.builder
+            .get_ledger(query)
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))
+    }
 }
@@ -11,20 +11,31 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 use ethers::types::{Address, H256};
 use futures_util::StreamExt;
-use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::error::INTERNAL_ERROR_CODE};
+use jsonrpsee::{
+    core::RpcResult,
+    proc_macros::rpc,
+    types::error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+};
 use rundler_builder::{BuilderServer, BundlingMode};
 use rundler_pool::PoolServer;
+use rundler_sim::Simulator;
 
 use crate::{
     error::rpc_err,
     types::{
-        RpcReputationInput, RpcReputationOutput, RpcStakeInfo, RpcStakeStatus, RpcUserOperation,
+        RpcMempoolDump, RpcMempoolFilter, RpcMempoolPage, RpcReputationInput, RpcReputationOutput,
+        RpcStakeInfo, RpcStakeStatus, RpcUserOperation, RpcValidationReport,
     },
 };
 
+/// Maximum number of operations returned by one page of `debug_bundler_dumpMempool`
+const MAX_DUMP_MEMPOOL_PAGE_SIZE: u64 = 1000;
+
 /// Debug API
 #[rpc(client, server, namespace = "debug")]
 pub trait DebugApi {
@@ -37,8 +48,15 @@ pub trait DebugApi {
     async fn bundler_clear_mempool(&self) -> RpcResult<String>;
 
     /// Dumps the mempool.
+    ///
+    /// If a filter is given, only returns the page of operations matching it,
+    /// in mempool order, along with the total number of matching operations.
     #[method(name = "bundler_dumpMempool")]
-    async fn bundler_dump_mempool(&self, entry_point: Address) -> RpcResult<Vec<RpcUserOperation>>;
+    async fn bundler_dump_mempool(
+        &self,
+        entry_point: Address,
+        filter: Option<RpcMempoolFilter>,
+    ) -> RpcResult<RpcMempoolDump>;
 
     /// Triggers the builder to send a bundle now
     ///
@@ -72,24 +90,40 @@ pub trait DebugApi {
         entry_point: Address,
         address: Address,
     ) -> RpcResult<RpcStakeStatus>;
+
+    /// Simulates the validation of a user operation and reports on every phase
+    /// and every violation found, including the mempool allowlist entries that
+    /// would allow each violation.
+    #[method(name = "bundler_traceUserOperationValidation")]
+    async fn bundler_trace_user_operation_validation(
+        &self,
+        op: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<RpcValidationReport>;
 }
 
-pub(crate) struct DebugApi<P, B> {
+pub(crate) struct DebugApi<P, B, S> {
     pool: P,
     builder: B,
+    simulators: HashMap<Address, S>,
 }
 
-impl<P, B> DebugApi<P, B> {
-    pub(crate) fn new(pool: P, builder: B) -> Self {
-        Self { pool, builder }
+impl<P, B, S> DebugApi<P, B, S> {
+    pub(crate) fn new(pool: P, builder: B, simulators: HashMap<Address, S>) -> Self {
+        Self {
+            pool,
+            builder,
+            simulators,
+        }
     }
 }
 
 #[async_trait]
-impl<P, B> DebugApiServer for DebugApi<P, B>
+impl<P, B, S> DebugApiServer for DebugApi<P, B, S>
 where
     P: PoolServer,
     B: BuilderServer,
+    S: Simulator,
 {
     async fn bundler_clear_state(&self) -> RpcResult<String> {
         let _ = self
@@ -111,15 +145,44 @@ where
         Ok("ok".to_string())
     }
 
-    async fn bundler_dump_mempool(&self, entry_point: Address) -> RpcResult<Vec<RpcUserOperation>> {
-        Ok(self
+    async fn bundler_dump_mempool(
+        &self,
+        entry_point: Address,
+        filter: Option<RpcMempoolFilter>,
+    ) -> RpcResult<RpcMempoolDump> {
+        let Some(filter) = filter else {
+            let ops = self
+                .pool
+                .debug_dump_mempool(entry_point)
+                .await
+                .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+            return Ok(RpcMempoolDump::All(
+                ops.into_iter().map(|pop| pop.uo.into()).collect(),
+            ));
+        };
+
+        let limit = filter.limit.unwrap_or(MAX_DUMP_MEMPOOL_PAGE_SIZE);
+        if limit > MAX_DUMP_MEMPOOL_PAGE_SIZE {
+            return Err(rpc_err(
+                INVALID_PARAMS_CODE,
+                format!("limit must be at most {MAX_DUMP_MEMPOOL_PAGE_SIZE}"),
+            ));
+        }
+        let page = self
             .pool
-            .debug_dump_mempool(entry_point)
+            .debug_dump_mempool_page(
+                entry_point,
+                (&filter).into(),
+                filter.offset.unwrap_or_default(),
+                limit,
+            )
             .await
-            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?
-            .into_iter()
-            .map(|pop| pop.uo.into())
-            .collect::<Vec<RpcUserOperation>>())
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+
+        Ok(RpcMempoolDump::Page(RpcMempoolPage {
+            ops: page.ops.into_iter().map(|pop| pop.uo.into()).collect(),
+            total: page.total,
+        }))
     }
 
     async fn bundler_send_bundle_now(&self) -> RpcResult<H256> {
@@ -231,4 +294,91 @@ where
             },
         })
     }
+
+    async fn bundler_trace_user_operation_validation(
+        &self,
+        op: RpcUserOperation,
+        entry_point: Address,
+    ) -> RpcResult<RpcValidationReport> {
+        let simulator = self.simulators.get(&entry_point).ok_or_else(|| {
+            rpc_err(
+                INVALID_PARAMS_CODE,
+                format!("Unsupported entry point: {entry_point:?}"),
+            )
+        })?;
+
+        let report = simulator
+            .trace_validation(op.into(), None)
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.violation_error.to_string()))?;
+
+        Ok(report.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_builder::MockBuilderServer;
+    use rundler_pool::{MempoolPage, MockPoolServer, PoolOperation};
+    use rundler_sim::MockSimulator;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dump_mempool_page() {
+        let mut pool = MockPoolServer::default();
+        pool.expect_debug_dump_mempool_page()
+            .withf(|_, filter, offset, limit| {
+                filter.sender.is_some() && *offset == 1 && *limit == MAX_DUMP_MEMPOOL_PAGE_SIZE
+            })
+            .returning(|_, _, _, _| {
+                Ok(MempoolPage {
+                    ops: vec![PoolOperation::default()],
+                    total: 2,
+                })
+            });
+        let api = DebugApi::<_, _, MockSimulator>::new(
+            pool,
+            MockBuilderServer::default(),
+            HashMap::new(),
+        );
+
+        let filter = RpcMempoolFilter {
+            sender: Some(Address::random()),
+            offset: Some(1),
+            ..Default::default()
+        };
+        let dump = api
+            .bundler_dump_mempool(Address::random(), Some(filter))
+            .await
+            .unwrap();
+        let page = match dump {
+            RpcMempoolDump::Page(page) => page,
+            dump => panic!("expected a page, got {dump:?}"),
+        };
+        assert_eq!(page.ops.len(), 1);
+        assert_eq!(page.total, 2);
+        let json = serde_json::to_value(&page).unwrap();
+        assert_eq!(json["total"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_dump_mempool_all() {
+        let mut pool = MockPoolServer::default();
+        pool.expect_debug_dump_mempool()
+            .returning(|_| Ok(vec![PoolOperation::default(); 3]));
+        let api = DebugApi::<_, _, MockSimulator>::new(
+            pool,
+            MockBuilderServer::default(),
+            HashMap::new(),
+        );
+
+        let dump = api
+            .bundler_dump_mempool(Address::random(), None)
+            .await
+            .unwrap();
+        // the unfiltered dump stays a plain array, as required by the spec
+        assert!(serde_json::to_value(&dump).unwrap().is_array());
+        assert!(matches!(dump, RpcMempoolDump::All(ops) if ops.len() == 3));
+    }
 }
@@ -27,23 +27,27 @@ use ethers::{
     },
     utils::to_checksum,
 };
-use rundler_pool::PoolServer;
+use rundler_pool::{PoolServer, UserOperationIndex};
 use rundler_provider::{EntryPoint, Provider};
 use rundler_sim::{
-    EstimationSettings, FeeEstimator, GasEstimate, GasEstimationError, GasEstimator,
-    GasEstimatorImpl, PrecheckSettings, UserOperationOptionalGas,
+    EstimationSettings, FeeEstimator, GasEstimate, GasEstimator, GasEstimatorImpl,
+    PrecheckSettings, SimulateValidationTracerImpl, UserOperationOptionalGas,
 };
 use rundler_types::{
+    chain::L1GasConfig,
     contracts::i_entry_point::{
         IEntryPointCalls, UserOperationEventFilter, UserOperationRevertReasonFilter,
     },
     UserOperation,
 };
-use rundler_utils::{eth::log_to_raw_log, log::LogOnError};
-use tracing::Level;
+use rundler_utils::{eth::log_to_raw_log, log::LogOnError, revert::AbiRegistry};
+use tracing::{instrument, Instrument, Level};
 
-use super::error::{EthResult, EthRpcError, ExecutionRevertedWithBytesData};
-use crate::types::{RichUserOperation, RpcUserOperation, UserOperationReceipt};
+use super::error::{EthResult, EthRpcError};
+use crate::{
+    partner,
+    types::{RichUserOperation, RpcUserOperation, UserOperationReceipt},
+};
 
 /// Settings for the `eth_` API
 #[derive(Copy, Clone, Debug)]
@@ -63,27 +67,29 @@ impl Settings {
 
 #[derive(Debug)]
 struct EntryPointContext<P, E> {
-    gas_estimator: GasEstimatorImpl<P, E>,
+    gas_estimator: GasEstimatorImpl<P, E, SimulateValidationTracerImpl<P, E>>,
 }
 
 impl<P, E> EntryPointContext<P, E>
 where
     P: Provider,
-    E: EntryPoint,
+    E: EntryPoint + Clone,
 {
     fn new(
-        chain_id: u64,
+        l1_gas: L1GasConfig,
         provider: Arc<P>,
         entry_point: E,
         estimation_settings: EstimationSettings,
         fee_estimator: FeeEstimator<P>,
     ) -> Self {
+        let tracer = SimulateValidationTracerImpl::new(Arc::clone(&provider), entry_point.clone());
         let gas_estimator = GasEstimatorImpl::new(
-            chain_id,
+            l1_gas,
             provider,
             entry_point,
             estimation_settings,
             fee_estimator,
+            tracer,
         );
         Self { gas_estimator }
     }
@@ -96,6 +102,8 @@ pub(crate) struct EthApi<P, E, PS> {
     chain_id: u64,
     pool: PS,
     settings: Settings,
+    abi_registry: Arc<AbiRegistry>,
+    user_operation_index: Option<Arc<UserOperationIndex>>,
 }
 
 impl<P, E, PS> EthApi<P, E, PS>
@@ -112,6 +120,8 @@ where
         settings: Settings,
         estimation_settings: EstimationSettings,
         precheck_settings: PrecheckSettings,
+        abi_registry: Arc<AbiRegistry>,
+        user_operation_index: Option<Arc<UserOperationIndex>>,
     ) -> Self
     where
         E: Clone,
@@ -122,13 +132,13 @@ where
                 (
                     entry_point.address(),
                     EntryPointContext::new(
-                        chain_id,
+                        precheck_settings.chain_spec.l1_gas,
                         Arc::clone(&provider),
                         entry_point,
                         estimation_settings,
                         FeeEstimator::new(
                             Arc::clone(&provider),
-                            chain_id,
+                            &precheck_settings.chain_spec,
                             precheck_settings.priority_fee_mode,
                             precheck_settings.bundle_priority_fee_overhead_percent,
                         ),
@@ -143,6 +153,8 @@ where
             provider,
             chain_id,
             pool,
+            abi_registry,
+            user_operation_index,
         }
     }
 
@@ -156,13 +168,21 @@ where
                 "supplied entry point addr is not a known entry point".to_string(),
             ));
         }
+        let op: UserOperation = op.into();
+        let span = tracing::info_span!(
+            "send_user_operation",
+            op_hash = ?op.op_hash(entry_point, self.chain_id),
+            ?entry_point,
+        );
         self.pool
-            .add_op(entry_point, op.into())
+            .add_op(entry_point, op, partner::current_partner())
+            .instrument(span)
             .await
             .map_err(EthRpcError::from)
             .log_on_error_level(Level::DEBUG, "failed to add op to the mempool")
     }
 
+    #[instrument(skip_all, fields(sender = ?op.sender, nonce = %op.nonce, ?entry_point))]
     pub(crate) async fn estimate_user_operation_gas(
         &self,
         op: UserOperationOptionalGas,
@@ -178,25 +198,11 @@ where
                 )
             })?;
 
-        let result = context
+        context
             .gas_estimator
             .estimate_op_gas(op, state_override.unwrap_or_default())
-            .await;
-        match result {
-            Ok(estimate) => Ok(estimate),
-            Err(GasEstimationError::RevertInValidation(message)) => {
-                Err(EthRpcError::EntryPointValidationRejected(message))?
-            }
-            Err(GasEstimationError::RevertInCallWithMessage(message)) => {
-                Err(EthRpcError::ExecutionReverted(message))?
-            }
-            Err(GasEstimationError::RevertInCallWithBytes(b)) => {
-                Err(EthRpcError::ExecutionRevertedWithBytes(
-                    ExecutionRevertedWithBytesData { revert_data: b },
-                ))?
-            }
-            Err(GasEstimationError::Other(error)) => Err(error)?,
-        }
+            .await
+            .map_err(|e| EthRpcError::from(e).decode_revert(&self.abi_registry))
     }
 
     pub(crate) async fn get_user_operation_by_hash(
@@ -274,13 +280,19 @@ where
         let uo_event = self
             .decode_user_operation_event(log)
             .context("should have decoded user operation event")?;
-        let reason: String = if uo_event.success {
-            "".to_owned()
+        let revert_reason = if uo_event.success {
+            None
         } else {
             EthApi::<P, E, PS>::get_user_operation_failure_reason(&tx_receipt.logs, hash)
                 .context("should have found revert reason if tx wasn't successful")?
-                .unwrap_or_default()
         };
+        let reason = revert_reason
+            .as_ref()
+            .map(|revert_reason| revert_reason.to_string())
+            .unwrap_or_default();
+        let decoded_reason = revert_reason
+            .as_ref()
+            .and_then(|revert_reason| self.abi_registry.decode(revert_reason));
 
         Ok(Some(UserOperationReceipt {
             user_op_hash: hash,
@@ -294,6 +306,7 @@ where
             logs: filtered_logs,
             receipt: tx_receipt,
             reason,
+            decoded_reason,
         }))
     }
 
@@ -386,6 +399,19 @@ where
     }
 
     async fn get_user_operation_event_by_hash(&self, hash: H256) -> EthResult<Option<Log>> {
+        // The index may not cover every block, such as blocks mined before it
+        // was backfilled or while it was catching up, so a miss falls back to
+        // loading logs
+        if let Some(index) = &self.user_operation_index {
+            let op = index
+                .get(hash)
+                .await
+                .context("should read user operation from index")?;
+            if let Some(op) = op {
+                return Ok(Some(op.log()));
+            }
+        }
+
         let to_block = self.provider.get_block_number().await?;
 
         let from_block = match self.settings.user_operation_event_block_distance {
@@ -485,7 +511,7 @@ where
     fn get_user_operation_failure_reason(
         logs: &[Log],
         user_op_hash: H256,
-    ) -> EthResult<Option<String>> {
+    ) -> EthResult<Option<Bytes>> {
         let revert_reason_evt: Option<UserOperationRevertReasonFilter> = logs
             .iter()
             .filter(|l| l.topics.len() > 1 && l.topics[1] == user_op_hash)
@@ -498,7 +524,7 @@ where
             })
             .next();
 
-        Ok(revert_reason_evt.map(|r| r.revert_reason.to_string()))
+        Ok(revert_reason_evt.map(|r| r.revert_reason))
     }
 
     /// This method takes a transaction hash and a user operation hash and returns the full user operation if it exists.
@@ -563,10 +589,10 @@ mod tests {
         utils::keccak256,
     };
     use mockall::predicate::eq;
-    use rundler_pool::{MockPoolServer, PoolOperation};
+    use rundler_pool::{IndexedUserOperation, MockPoolServer, PoolOperation};
     use rundler_provider::{MockEntryPoint, MockProvider};
     use rundler_sim::PriorityFeeMode;
-    use rundler_types::contracts::i_entry_point::HandleOpsCall;
+    use rundler_types::{chain::ChainSpec, contracts::i_entry_point::HandleOpsCall};
 
     use super::*;
 
@@ -808,6 +834,138 @@ mod tests {
         assert_eq!(res, Some(ro));
     }
 
+    #[tokio::test]
+    async fn test_get_user_op_by_hash_mined_from_index() {
+        let ep = Address::random();
+        let uo = UserOperation::default();
+        let hash = uo.op_hash(ep, 1);
+        let block_number = 1000;
+        let block_hash = H256::random();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(None));
+
+        let tx_data: Bytes = IEntryPointCalls::HandleOps(HandleOpsCall {
+            beneficiary: Address::zero(),
+            ops: vec![uo.clone()],
+        })
+        .encode()
+        .into();
+        let tx = Transaction {
+            to: Some(ep),
+            input: tx_data,
+            block_number: Some(block_number.into()),
+            block_hash: Some(block_hash),
+            ..Default::default()
+        };
+        let tx_hash = tx.hash();
+
+        // index the op's event, after which lookups should not load logs
+        let path = std::env::temp_dir().join(format!("rundler-uo-index-{hash:?}.redb"));
+        let index = Arc::new(UserOperationIndex::open(&path).unwrap());
+        let log = IndexedUserOperation {
+            hash,
+            entry_point: ep,
+            block_number,
+            block_hash,
+            transaction_hash: tx_hash,
+            ..Default::default()
+        }
+        .log();
+        let mut log_provider = MockProvider::default();
+        log_provider
+            .expect_get_logs()
+            .returning(move |_| Ok(vec![log.clone()]));
+        index
+            .backfill(&log_provider, &[ep], 0, block_number, 10_000)
+            .await
+            .unwrap();
+
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_transaction()
+            .with(eq(tx_hash))
+            .returning(move |_| Ok(Some(tx.clone())));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let mut api = create_api(provider, entry_point, pool);
+        api.user_operation_index = Some(index);
+        let res = api.get_user_operation_by_hash(hash).await.unwrap();
+        let ro = RichUserOperation {
+            user_operation: uo.into(),
+            entry_point: ep.into(),
+            block_number: Some(block_number.into()),
+            block_hash: Some(block_hash),
+            transaction_hash: Some(tx_hash),
+        };
+        assert_eq!(res, Some(ro));
+
+        drop(api);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_get_user_op_by_hash_index_miss_loads_logs() {
+        let ep = Address::random();
+        let uo = UserOperation::default();
+        let hash = uo.op_hash(ep, 1);
+        let block_number = 1000;
+        let block_hash = H256::random();
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(None));
+
+        let tx_data: Bytes = IEntryPointCalls::HandleOps(HandleOpsCall {
+            beneficiary: Address::zero(),
+            ops: vec![uo.clone()],
+        })
+        .encode()
+        .into();
+        let tx = Transaction {
+            to: Some(ep),
+            input: tx_data,
+            block_number: Some(block_number.into()),
+            block_hash: Some(block_hash),
+            ..Default::default()
+        };
+        let tx_hash = tx.hash();
+        let log = Log {
+            address: ep,
+            transaction_hash: Some(tx_hash),
+            ..Default::default()
+        };
+
+        // the op is not in the index, so its event is loaded from the logs
+        let mut provider = MockProvider::default();
+        provider.expect_get_block_number().returning(|| Ok(1000));
+        provider
+            .expect_get_logs()
+            .times(1)
+            .returning(move |_| Ok(vec![log.clone()]));
+        provider
+            .expect_get_transaction()
+            .with(eq(tx_hash))
+            .returning(move |_| Ok(Some(tx.clone())));
+
+        let mut entry_point = MockEntryPoint::default();
+        entry_point.expect_address().returning(move || ep);
+
+        let path = std::env::temp_dir().join(format!("rundler-uo-index-{hash:?}.redb"));
+        let mut api = create_api(provider, entry_point, pool);
+        api.user_operation_index = Some(Arc::new(UserOperationIndex::open(&path).unwrap()));
+        let res = api.get_user_operation_by_hash(hash).await.unwrap();
+        assert_eq!(res.and_then(|ro| ro.transaction_hash), Some(tx_hash));
+
+        drop(api);
+        std::fs::remove_file(path).unwrap();
+    }
+
     #[tokio::test]
     async fn test_get_user_op_by_hash_not_found() {
         let ep = Address::random();
@@ -859,7 +1017,7 @@ mod tests {
         contexts_by_entry_point.insert(
             ep.address(),
             EntryPointContext::new(
-                1,
+                L1GasConfig::default(),
                 Arc::clone(&provider),
                 ep,
                 EstimationSettings {
@@ -870,7 +1028,7 @@ mod tests {
                 },
                 FeeEstimator::new(
                     Arc::clone(&provider),
-                    1,
+                    &ChainSpec::default(),
                     PriorityFeeMode::BaseFeePercent(0),
                     0,
                 ),
@@ -882,6 +1040,8 @@ mod tests {
             chain_id: 1,
             pool,
             settings: Settings::new(None),
+            abi_registry: Arc::new(AbiRegistry::default()),
+            user_operation_index: None,
         }
     }
 }
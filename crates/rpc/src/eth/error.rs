@@ -18,8 +18,9 @@ use jsonrpsee::types::{
 };
 use rundler_pool::{MempoolError, PoolServerError};
 use rundler_provider::ProviderError;
-use rundler_sim::{PrecheckViolation, SimulationViolation};
+use rundler_sim::{GasEstimationError, PrecheckViolation, SimulationViolation};
 use rundler_types::{Entity, EntityType, Timestamp};
+use rundler_utils::revert::{AbiRegistry, DecodedRevert};
 use serde::Serialize;
 
 use crate::error::{rpc_err, rpc_err_with_data};
@@ -105,6 +106,9 @@ pub enum EthRpcError {
     /// Operation already known
     #[error("already known")]
     OperationAlreadyKnown,
+    /// Operation was recently cancelled by its sender
+    #[error("operation was cancelled by its sender")]
+    OperationCancelled,
     /// Other internal errors
     #[error("Invalid UserOp signature or paymaster signature")]
     SignatureCheckFailed,
@@ -114,7 +118,10 @@ pub enum EthRpcError {
     SimulationFailed(SimulationViolation),
     #[error("{0}")]
     ExecutionReverted(String),
-    #[error("execution reverted")]
+    #[error(
+        "execution reverted{}",
+        .0.decoded_reason.as_ref().map(|reason| format!(": {reason}")).unwrap_or_default()
+    )]
     ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData),
     #[error("operation rejected by mempool: {0}")]
     OperationRejected(String),
@@ -191,6 +198,8 @@ pub struct UnsupportedAggregatorData {
 #[serde(rename_all = "camelCase")]
 pub struct ExecutionRevertedWithBytesData {
     pub revert_data: Bytes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decoded_reason: Option<DecodedRevert>,
 }
 
 impl From<PoolServerError> for EthRpcError {
@@ -210,6 +219,7 @@ impl From<MempoolError> for EthRpcError {
         match value {
             MempoolError::Other(e) => EthRpcError::Internal(e),
             MempoolError::OperationAlreadyKnown => EthRpcError::OperationAlreadyKnown,
+            MempoolError::OperationCancelled => EthRpcError::OperationCancelled,
             MempoolError::ReplacementUnderpriced(priority_fee, fee) => {
                 EthRpcError::ReplacementUnderpriced(ReplacementUnderpricedData {
                     current_max_priority_fee: priority_fee,
@@ -247,6 +257,54 @@ impl From<MempoolError> for EthRpcError {
     }
 }
 
+impl From<GasEstimationError> for EthRpcError {
+    fn from(value: GasEstimationError) -> Self {
+        match value {
+            GasEstimationError::RevertInValidation(message) => {
+                Self::EntryPointValidationRejected(message)
+            }
+            GasEstimationError::RevertInCallWithMessage(message) => {
+                Self::ExecutionReverted(message)
+            }
+            GasEstimationError::RevertInCallWithBytes(b) => {
+                Self::ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData {
+                    revert_data: b,
+                    decoded_reason: None,
+                })
+            }
+            GasEstimationError::InBatch { index, error } => match Self::from(*error) {
+                Self::EntryPointValidationRejected(message) => {
+                    Self::EntryPointValidationRejected(format!("user operation {index}: {message}"))
+                }
+                Self::ExecutionReverted(message) => {
+                    Self::ExecutionReverted(format!("user operation {index}: {message}"))
+                }
+                Self::Internal(error) => {
+                    Self::Internal(error.context(format!("user operation {index}")))
+                }
+                error => error,
+            },
+            GasEstimationError::Other(error) => Self::Internal(error),
+        }
+    }
+}
+
+impl EthRpcError {
+    /// Decodes the revert data of an execution revert with the ABI registry
+    pub(crate) fn decode_revert(self, abi_registry: &AbiRegistry) -> Self {
+        match self {
+            Self::ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData {
+                revert_data,
+                decoded_reason: None,
+            }) => Self::ExecutionRevertedWithBytes(ExecutionRevertedWithBytesData {
+                decoded_reason: abi_registry.decode(&revert_data),
+                revert_data,
+            }),
+            error => error,
+        }
+    }
+}
+
 impl From<PrecheckViolation> for EthRpcError {
     fn from(value: PrecheckViolation) -> Self {
         Self::PrecheckFailed(value)
@@ -334,7 +392,9 @@ impl From<EthRpcError> for ErrorObjectOwned {
             EthRpcError::ReplacementUnderpriced(data) => {
                 rpc_err_with_data(INVALID_PARAMS_CODE, msg, data)
             }
-            EthRpcError::OperationAlreadyKnown => rpc_err(INVALID_PARAMS_CODE, msg),
+            EthRpcError::OperationAlreadyKnown | EthRpcError::OperationCancelled => {
+                rpc_err(INVALID_PARAMS_CODE, msg)
+            }
             EthRpcError::MaxOperationsReached(_, _) => rpc_err(STAKE_TOO_LOW_CODE, msg),
             EthRpcError::SignatureCheckFailed => rpc_err(SIGNATURE_CHECK_FAILED_CODE, msg),
             EthRpcError::PrecheckFailed(_) => rpc_err(CALL_EXECUTION_FAILED_CODE, msg),
@@ -11,33 +11,350 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
 use async_trait::async_trait;
+use ethers::types::{Address, BlockNumber, U256};
+use futures_util::StreamExt;
+use hyper::{header, Body, Method, Request, Response, StatusCode};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::error::INTERNAL_ERROR_CODE};
+use parking_lot::Mutex;
+use rundler_builder::{BuilderServer, BundlingMode};
+use rundler_pool::PoolServer;
+use rundler_provider::Provider;
 use rundler_task::server::{HealthCheck, ServerStatus};
+use rundler_types::Timestamp;
+use rundler_utils::handle::SpawnGuard;
+use serde::Serialize;
+use tower::{Layer, Service};
 
 use crate::error::rpc_err;
 
+/// Path of the liveness endpoint
+const LIVENESS_PATH: &str = "/health/live";
+/// Path of the readiness endpoint
+const READINESS_PATH: &str = "/health/ready";
+/// Delay before resubscribing to pool blocks after the subscription ends
+const RESUBSCRIBE_DELAY: Duration = Duration::from_secs(1);
+
 #[rpc(server, namespace = "system")]
 pub trait SystemApi {
     #[method(name = "health")]
     async fn get_health(&self) -> RpcResult<String>;
+
+    /// Whether the process and its event loop are up, without checking any
+    /// dependencies
+    #[method(name = "liveness")]
+    async fn get_liveness(&self) -> RpcResult<HealthReport>;
+
+    /// Whether the bundler is ready to accept and bundle user operations, with
+    /// the state of each dependency
+    #[method(name = "readiness")]
+    async fn get_readiness(&self) -> RpcResult<HealthReport>;
+}
+
+/// Thresholds used by the readiness check.
+#[derive(Clone, Copy, Debug)]
+pub struct HealthSettings {
+    /// Maximum age of the node's latest block
+    pub max_head_age: Duration,
+    /// Maximum time since the pool last reported a new block
+    pub max_pool_head_staleness: Duration,
+    /// Minimum balance of each builder signer, in wei
+    pub min_signer_balance: U256,
+    /// Window in which a builder sending or mining a bundle counts as recent
+    pub recent_bundle_window: Duration,
+    /// Number of builder signers that may be below `min_signer_balance` while
+    /// the bundler is still ready. With the default of 0, a single underfunded
+    /// signer makes the bundler unready.
+    pub max_underfunded_signers: usize,
 }
 
-pub(crate) struct HealthChecker {
-    servers: Vec<Box<dyn HealthCheck>>,
+pub(crate) struct HealthChecker<P, B> {
+    servers: Arc<Vec<Box<dyn HealthCheck>>>,
+    provider: Arc<P>,
+    builder: B,
+    settings: HealthSettings,
+    pool_head: Arc<Mutex<PoolHead>>,
+    _pool_watcher: Arc<SpawnGuard>,
 }
 
-impl HealthChecker {
-    pub(crate) fn new(servers: Vec<Box<dyn HealthCheck>>) -> Self {
-        Self { servers }
+// Manual impl as derive would require `P: Clone`
+impl<P, B: Clone> Clone for HealthChecker<P, B> {
+    fn clone(&self) -> Self {
+        Self {
+            servers: Arc::clone(&self.servers),
+            provider: Arc::clone(&self.provider),
+            builder: self.builder.clone(),
+            settings: self.settings,
+            pool_head: Arc::clone(&self.pool_head),
+            _pool_watcher: Arc::clone(&self._pool_watcher),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PoolHead {
+    block_number: Option<u64>,
+    updated_at: Instant,
+}
+
+/// Health of the bundler and its dependencies.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthReport {
+    ok: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    servers: Vec<ServerHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    node: Option<NodeHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pool: Option<PoolHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    builder: Option<BuilderHealth>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ServerHealth {
+    name: &'static str,
+    serving: bool,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeHealth {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block_number: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head_age_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PoolHealth {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_block_number: Option<u64>,
+    seconds_since_last_block: u64,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BuilderHealth {
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bundling_mode: Option<BundlingMode>,
+    senders: Vec<SenderHealth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SenderHealth {
+    builder_index: u64,
+    signer: Address,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    balance: Option<U256>,
+    balance_ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_sent_seconds_ago: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_mined_seconds_ago: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_mined_block: Option<u64>,
+    recently_sent: bool,
+    recently_mined: bool,
+}
+
+impl<P, B> HealthChecker<P, B>
+where
+    P: Provider,
+    B: BuilderServer + Clone,
+{
+    pub(crate) fn new<PS: PoolServer>(
+        servers: Vec<Box<dyn HealthCheck>>,
+        provider: Arc<P>,
+        pool: PS,
+        builder: B,
+        settings: HealthSettings,
+    ) -> Self {
+        let pool_head = Arc::new(Mutex::new(PoolHead {
+            block_number: None,
+            updated_at: Instant::now(),
+        }));
+        let pool_watcher =
+            SpawnGuard::spawn_with_guard(watch_pool_heads(pool, Arc::clone(&pool_head)));
+        Self {
+            servers: Arc::new(servers),
+            provider,
+            builder,
+            settings,
+            pool_head,
+            _pool_watcher: Arc::new(pool_watcher),
+        }
+    }
+
+    /// Always live: answering at all shows that the process and its event loop
+    /// are up. Dependencies, including the pool and builder servers, which may
+    /// run in other processes, are only checked for readiness so that their
+    /// failures don't get this process restarted.
+    pub(crate) fn liveness(&self) -> HealthReport {
+        HealthReport {
+            ok: true,
+            servers: vec![],
+            node: None,
+            pool: None,
+            builder: None,
+        }
+    }
+
+    /// Ready when the pool and builder servers are serving, the node is reachable and its head is recent, the pool
+    /// is following the chain, and no more than `max_underfunded_signers`
+    /// builder signers are below the minimum balance. Bundling
+    /// mode and recent bundles are reported but don't affect readiness, as an
+    /// idle bundler is still ready.
+    pub(crate) async fn readiness(&self) -> HealthReport {
+        let (servers, node, builder) = tokio::join!(
+            self.server_health(),
+            self.node_health(),
+            self.builder_health()
+        );
+        let pool = self.pool_health();
+        HealthReport {
+            ok: servers.iter().all(|s| s.serving) && node.ok && pool.ok && builder.ok,
+            servers,
+            node: Some(node),
+            pool: Some(pool),
+            builder: Some(builder),
+        }
+    }
+
+    async fn server_health(&self) -> Vec<ServerHealth> {
+        let mut servers = vec![];
+        for server in self.servers.iter() {
+            servers.push(ServerHealth {
+                name: server.name(),
+                serving: matches!(server.status().await, ServerStatus::Serving),
+            });
+        }
+        servers
+    }
+
+    async fn node_health(&self) -> NodeHealth {
+        match self.provider.get_block(BlockNumber::Latest).await {
+            Ok(Some(block)) => {
+                let head_age = Timestamp::now()
+                    .seconds_since_epoch()
+                    .saturating_sub(block.timestamp.as_u64());
+                NodeHealth {
+                    ok: head_age <= self.settings.max_head_age.as_secs(),
+                    block_number: block.number.map(|n| n.as_u64()),
+                    head_age_seconds: Some(head_age),
+                    error: None,
+                }
+            }
+            Ok(None) => NodeHealth {
+                error: Some("node returned no latest block".to_string()),
+                ..Default::default()
+            },
+            Err(e) => NodeHealth {
+                error: Some(e.to_string()),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn pool_health(&self) -> PoolHealth {
+        let head = self.pool_head.lock();
+        let staleness = head.updated_at.elapsed();
+        PoolHealth {
+            ok: staleness <= self.settings.max_pool_head_staleness,
+            last_block_number: head.block_number,
+            seconds_since_last_block: staleness.as_secs(),
+        }
+    }
+
+    async fn builder_health(&self) -> BuilderHealth {
+        let status = match self.builder.get_status().await {
+            Ok(status) => status,
+            Err(e) => {
+                return BuilderHealth {
+                    error: Some(e.to_string()),
+                    ..Default::default()
+                }
+            }
+        };
+
+        let now = Timestamp::now().seconds_since_epoch();
+        let window = self.settings.recent_bundle_window.as_secs();
+        let mut senders = vec![];
+        for sender in status.senders {
+            let balance = self.provider.get_balance(sender.signer, None).await.ok();
+            let last_sent_seconds_ago = sender.last_sent_at.map(|t| now.saturating_sub(t));
+            let last_mined_seconds_ago = sender.last_mined_at.map(|t| now.saturating_sub(t));
+            senders.push(SenderHealth {
+                builder_index: sender.builder_index,
+                signer: sender.signer,
+                balance,
+                balance_ok: balance.is_some_and(|b| b >= self.settings.min_signer_balance),
+                last_sent_seconds_ago,
+                last_mined_seconds_ago,
+                last_mined_block: sender.last_mined_block,
+                recently_sent: last_sent_seconds_ago.is_some_and(|s| s <= window),
+                recently_mined: last_mined_seconds_ago.is_some_and(|s| s <= window),
+            });
+        }
+
+        let underfunded = senders.iter().filter(|s| !s.balance_ok).count();
+        BuilderHealth {
+            ok: underfunded <= self.settings.max_underfunded_signers,
+            bundling_mode: Some(status.bundling_mode),
+            senders,
+            error: None,
+        }
+    }
+}
+
+/// Records when the pool last reported a new block, resubscribing if the
+/// subscription ends.
+async fn watch_pool_heads<PS: PoolServer>(pool: PS, pool_head: Arc<Mutex<PoolHead>>) {
+    loop {
+        match pool.subscribe_new_heads().await {
+            Ok(mut new_heads) => {
+                while let Some(head) = new_heads.next().await {
+                    *pool_head.lock() = PoolHead {
+                        block_number: Some(head.block_number),
+                        updated_at: Instant::now(),
+                    };
+                }
+                tracing::warn!("Pool block subscription ended, resubscribing");
+            }
+            Err(e) => tracing::warn!("Failed to subscribe to pool blocks: {e:?}"),
+        }
+        tokio::time::sleep(RESUBSCRIBE_DELAY).await;
     }
 }
 
 #[async_trait]
-impl SystemApiServer for HealthChecker {
+impl<P, B> SystemApiServer for HealthChecker<P, B>
+where
+    P: Provider,
+    B: BuilderServer + Clone,
+{
     async fn get_health(&self) -> RpcResult<String> {
         let mut errors = Vec::new();
-        for server in &self.servers {
+        for server in self.servers.iter() {
             match server.status().await {
                 ServerStatus::Serving => {}
                 ServerStatus::NotServing => errors.push(server.name()),
@@ -52,4 +369,415 @@ impl SystemApiServer for HealthChecker {
             ))
         }
     }
+
+    async fn get_liveness(&self) -> RpcResult<HealthReport> {
+        Ok(self.liveness())
+    }
+
+    async fn get_readiness(&self) -> RpcResult<HealthReport> {
+        Ok(self.readiness().await)
+    }
+}
+
+/// Layer serving `GET /health/live` and `GET /health/ready` with a JSON
+/// [`HealthReport`], and a 503 status when the check fails.
+pub(crate) struct HealthEndpointLayer<P, B> {
+    checker: HealthChecker<P, B>,
+}
+
+impl<P, B> HealthEndpointLayer<P, B> {
+    pub(crate) fn new(checker: HealthChecker<P, B>) -> Self {
+        Self { checker }
+    }
+}
+
+impl<S, P, B: Clone> Layer<S> for HealthEndpointLayer<P, B> {
+    type Service = HealthEndpoint<S, P, B>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HealthEndpoint {
+            inner,
+            checker: self.checker.clone(),
+        }
+    }
+}
+
+/// Service serving the health endpoints, see [`HealthEndpointLayer`].
+pub(crate) struct HealthEndpoint<S, P, B> {
+    inner: S,
+    checker: HealthChecker<P, B>,
+}
+
+impl<S, P, B> Service<Request<Body>> for HealthEndpoint<S, P, B>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+    S::Future: Send + 'static,
+    P: Provider,
+    B: BuilderServer + Clone,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        if request.method() != Method::GET {
+            return Box::pin(self.inner.call(request));
+        }
+        let checker = self.checker.clone();
+        match request.uri().path() {
+            LIVENESS_PATH => Box::pin(async move { Ok(report_response(checker.liveness())) }),
+            READINESS_PATH => {
+                Box::pin(async move { Ok(report_response(checker.readiness().await)) })
+            }
+            _ => Box::pin(self.inner.call(request)),
+        }
+    }
+}
+
+impl<S: Clone, P, B: Clone> Clone for HealthEndpoint<S, P, B> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            checker: self.checker.clone(),
+        }
+    }
+}
+
+fn report_response(report: HealthReport) -> Response<Body> {
+    let status = if report.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = serde_json::to_string(&report).expect("health report should serialize");
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .expect("health response should be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Block;
+    use futures_util::{stream, Stream};
+    use rundler_builder::{
+        BuilderResult, BuilderServerError, BuilderStatus, BundleSenderStatus, LedgerQuery,
+        LedgerReport,
+    };
+    use rundler_pool::{MockPoolServer, NewHead};
+    use rundler_provider::MockProvider;
+
+    use super::*;
+
+    /// A server that is either always serving or never serving.
+    struct TestServer(bool);
+
+    #[async_trait]
+    impl HealthCheck for TestServer {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        async fn status(&self) -> ServerStatus {
+            if self.0 {
+                ServerStatus::Serving
+            } else {
+                ServerStatus::NotServing
+            }
+        }
+    }
+
+    /// A builder reporting a fixed status, or an error if there is none.
+    #[derive(Clone)]
+    struct TestBuilder(Option<BuilderStatus>);
+
+    #[async_trait]
+    impl BuilderServer for TestBuilder {
+        async fn get_supported_entry_points(&self) -> BuilderResult<Vec<Address>> {
+            unimplemented!()
+        }
+
+        async fn get_inclusion_latencies(&self) -> BuilderResult<Vec<u64>> {
+            unimplemented!()
+        }
+
+        async fn debug_send_bundle_now(&self) -> BuilderResult<(ethers::types::H256, u64)> {
+            unimplemented!()
+        }
+
+        async fn debug_set_bundling_mode(&self, _mode: BundlingMode) -> BuilderResult<()> {
+            unimplemented!()
+        }
+
+        async fn get_status(&self) -> BuilderResult<BuilderStatus> {
+            self.0
+                .clone()
+                .ok_or_else(|| BuilderServerError::Other(anyhow::anyhow!("builder is down")))
+        }
+
+        async fn get_ledger(&self, _query: LedgerQuery) -> BuilderResult<LedgerReport> {
+            unimplemented!()
+        }
+    }
+
+    /// Responds to requests the health endpoints don't serve.
+    #[derive(Clone)]
+    struct Inner;
+
+    impl Service<Request<Body>> for Inner {
+        type Response = Response<Body>;
+        type Error = ();
+        type Future = Pin<Box<dyn Future<Output = Result<Response<Body>, ()>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<Body>) -> Self::Future {
+            Box::pin(async { Ok(Response::new(Body::from("inner"))) })
+        }
+    }
+
+    fn settings() -> HealthSettings {
+        HealthSettings {
+            max_head_age: Duration::from_secs(60),
+            max_pool_head_staleness: Duration::from_secs(60),
+            min_signer_balance: 50.into(),
+            recent_bundle_window: Duration::from_secs(600),
+            max_underfunded_signers: 0,
+        }
+    }
+
+    fn now() -> u64 {
+        Timestamp::now().seconds_since_epoch()
+    }
+
+    /// A node whose latest block is `head_age` seconds old, and where every
+    /// signer has a balance of `balance`.
+    fn provider(head_age: u64, balance: u64) -> MockProvider {
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_block::<BlockNumber>()
+            .returning(move |_| {
+                Ok(Some(Block {
+                    number: Some(7.into()),
+                    timestamp: (now() - head_age).into(),
+                    ..Default::default()
+                }))
+            });
+        provider
+            .expect_get_balance()
+            .returning(move |_, _| Ok(balance.into()));
+        provider
+    }
+
+    fn builder_status(senders: Vec<BundleSenderStatus>) -> TestBuilder {
+        TestBuilder(Some(BuilderStatus {
+            bundling_mode: BundlingMode::Auto,
+            senders,
+        }))
+    }
+
+    fn sender(builder_index: u64) -> BundleSenderStatus {
+        BundleSenderStatus {
+            builder_index,
+            signer: Address::random(),
+            last_sent_at: None,
+            last_mined_at: None,
+            last_mined_block: None,
+        }
+    }
+
+    fn checker(
+        serving: bool,
+        provider: MockProvider,
+        builder: TestBuilder,
+        settings: HealthSettings,
+    ) -> HealthChecker<MockProvider, TestBuilder> {
+        let mut pool = MockPoolServer::default();
+        // the pool reports no blocks, so tests control its head
+        pool.expect_subscribe_new_heads().returning(|| {
+            let heads: Pin<Box<dyn Stream<Item = NewHead> + Send>> = Box::pin(stream::pending());
+            Ok(heads)
+        });
+        HealthChecker::new(
+            vec![Box::new(TestServer(serving))],
+            Arc::new(provider),
+            pool,
+            builder,
+            settings,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_node_head_age() {
+        let fresh = checker(true, provider(10, 100), builder_status(vec![]), settings());
+        let node = fresh.node_health().await;
+        assert!(node.ok);
+        assert_eq!(node.block_number, Some(7));
+        // the clock may tick between fetching the block and checking its age
+        assert!(matches!(node.head_age_seconds, Some(10..=11)));
+
+        let stale = checker(true, provider(61, 100), builder_status(vec![]), settings());
+        assert!(!stale.node_health().await.ok);
+        assert!(!stale.readiness().await.ok);
+    }
+
+    #[tokio::test]
+    async fn test_pool_staleness() {
+        let checker = checker(true, provider(10, 100), builder_status(vec![]), settings());
+        assert!(checker.pool_health().ok);
+
+        checker.pool_head.lock().updated_at =
+            Instant::now().checked_sub(Duration::from_secs(61)).unwrap();
+        let pool = checker.pool_health();
+        assert!(!pool.ok);
+        assert_eq!(pool.seconds_since_last_block, 61);
+        assert!(!checker.readiness().await.ok);
+    }
+
+    #[tokio::test]
+    async fn test_signer_health() {
+        let mut active = sender(0);
+        active.last_sent_at = Some(now() - 10);
+        active.last_mined_at = Some(now() - 601);
+        active.last_mined_block = Some(3);
+
+        let funded = checker(
+            true,
+            provider(10, 50),
+            builder_status(vec![active.clone(), sender(1)]),
+            settings(),
+        );
+        let builder = funded.builder_health().await;
+        assert!(builder.ok);
+        assert_eq!(builder.bundling_mode, Some(BundlingMode::Auto));
+        let sender_health = &builder.senders[0];
+        assert_eq!(sender_health.signer, active.signer);
+        assert!(sender_health.balance_ok);
+        assert!(sender_health.recently_sent);
+        assert!(!sender_health.recently_mined);
+        assert_eq!(sender_health.last_mined_block, Some(3));
+        assert!(!builder.senders[1].recently_sent);
+        assert!(!builder.senders[1].recently_mined);
+
+        // one underfunded signer makes the bundler unready by default
+        let underfunded = checker(
+            true,
+            provider(10, 49),
+            builder_status(vec![sender(0)]),
+            settings(),
+        );
+        let builder = underfunded.builder_health().await;
+        assert!(!builder.senders[0].balance_ok);
+        assert!(!builder.ok);
+
+        let tolerant = checker(
+            true,
+            provider(10, 49),
+            builder_status(vec![sender(0)]),
+            HealthSettings {
+                max_underfunded_signers: 1,
+                ..settings()
+            },
+        );
+        assert!(tolerant.builder_health().await.ok);
+
+        let down = checker(true, provider(10, 100), TestBuilder(None), settings());
+        let builder = down.builder_health().await;
+        assert!(!builder.ok);
+        assert!(builder.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_health_endpoint_status() {
+        async fn status(
+            checker: HealthChecker<MockProvider, TestBuilder>,
+            method: Method,
+            path: &str,
+        ) -> StatusCode {
+            let mut service = HealthEndpointLayer::new(checker).layer(Inner);
+            let request = Request::builder()
+                .method(method)
+                .uri(path)
+                .body(Body::empty())
+                .unwrap();
+            service.call(request).await.unwrap().status()
+        }
+
+        let healthy = || {
+            checker(
+                true,
+                provider(10, 100),
+                builder_status(vec![sender(0)]),
+                settings(),
+            )
+        };
+        assert_eq!(
+            status(healthy(), Method::GET, LIVENESS_PATH).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(healthy(), Method::GET, READINESS_PATH).await,
+            StatusCode::OK
+        );
+        // other requests are passed through
+        assert_eq!(
+            status(healthy(), Method::POST, READINESS_PATH).await,
+            StatusCode::OK
+        );
+
+        // dependencies that are down make the bundler unready, but it is still live
+        let not_serving = || checker(false, provider(10, 100), builder_status(vec![]), settings());
+        assert_eq!(
+            status(not_serving(), Method::GET, LIVENESS_PATH).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(not_serving(), Method::GET, READINESS_PATH).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let stale = || checker(true, provider(61, 100), builder_status(vec![]), settings());
+        assert_eq!(
+            status(stale(), Method::GET, LIVENESS_PATH).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(stale(), Method::GET, READINESS_PATH).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let underfunded = || {
+            checker(
+                true,
+                provider(10, 49),
+                builder_status(vec![sender(0)]),
+                settings(),
+            )
+        };
+        assert_eq!(
+            status(underfunded(), Method::GET, LIVENESS_PATH).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(underfunded(), Method::GET, READINESS_PATH).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+
+        let down = || checker(true, provider(10, 100), TestBuilder(None), settings());
+        assert_eq!(
+            status(down(), Method::GET, LIVENESS_PATH).await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(down(), Method::GET, READINESS_PATH).await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+    }
 }
@@ -31,11 +31,21 @@ mod eth;
 pub use eth::{EthApiClient, EthApiSettings};
 
 mod health;
+pub use health::HealthSettings;
+
+mod limits;
+pub use limits::RpcLimitSettings;
+
 mod metrics;
 
+mod partner;
+
 mod rundler;
 pub use rundler::RundlerApiClient;
 
+mod status;
+pub use status::{OpStatusSettings, OpStatusTracker};
+
 mod task;
 pub use task::{Args as RpcTaskArgs, RpcTask};
 
@@ -0,0 +1,583 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Middleware enforcing request size, batch, timeout, and concurrency limits
+//! on JSON-RPC requests.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use hyper::{body::HttpBody, header, Body, Request, Response};
+use jsonrpsee::types::error::{
+    OVERSIZED_REQUEST_CODE, SERVER_IS_BUSY_CODE, TOO_BIG_BATCH_REQUEST_CODE,
+};
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower::{Layer, Service};
+
+use crate::metrics::RpcMetrics;
+
+/// Limits applied to JSON-RPC requests before they reach the API handlers.
+#[derive(Clone, Debug)]
+pub struct RpcLimitSettings {
+    /// Maximum size of a request body, in bytes.
+    pub max_request_body_size: u32,
+    /// Maximum number of calls in a batch request. 0 disables batch requests.
+    pub max_batch_len: usize,
+    /// Timeout for methods without their own timeout.
+    pub default_timeout: Duration,
+    /// Per-method timeouts, overriding the default.
+    pub method_timeouts: HashMap<String, Duration>,
+    /// Per-method caps on the number of concurrent calls.
+    pub method_max_concurrent: HashMap<String, usize>,
+    /// Maximum number of concurrent calls in the standard lane, counting each
+    /// call of a batch. 0 for no limit.
+    pub max_concurrent_requests: usize,
+    /// Methods served in the priority lane, which is not subject to
+    /// `max_concurrent_requests`.
+    pub priority_methods: HashSet<String>,
+}
+
+/// Layer applying [`RpcLimitSettings`] to HTTP JSON-RPC requests.
+#[derive(Clone, Debug)]
+pub(crate) struct RpcLimitLayer {
+    limits: Arc<Limits>,
+}
+
+impl RpcLimitLayer {
+    /// Creates the layer. Metrics are labeled with the method names in
+    /// `known_methods`, and with `other` for any other method.
+    pub(crate) fn new<'a>(
+        settings: RpcLimitSettings,
+        known_methods: impl IntoIterator<Item = &'a str>,
+    ) -> Self {
+        let method_caps = settings
+            .method_max_concurrent
+            .iter()
+            .map(|(method, &max)| (method.clone(), Cap::new(max)))
+            .collect();
+        let standard_lane = (settings.max_concurrent_requests > 0)
+            .then(|| Cap::new(settings.max_concurrent_requests));
+        Self {
+            limits: Arc::new(Limits {
+                settings,
+                known_methods: known_methods.into_iter().map(str::to_string).collect(),
+                method_caps,
+                standard_lane,
+            }),
+        }
+    }
+}
+
+impl<S> Layer<S> for RpcLimitLayer {
+    type Service = RpcLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcLimit {
+            inner,
+            limits: Arc::clone(&self.limits),
+        }
+    }
+}
+
+/// Service applying [`RpcLimitSettings`] to HTTP JSON-RPC requests.
+#[derive(Clone, Debug)]
+pub(crate) struct RpcLimit<S> {
+    inner: S,
+    limits: Arc<Limits>,
+}
+
+#[derive(Debug)]
+struct Limits {
+    settings: RpcLimitSettings,
+    known_methods: HashSet<String>,
+    method_caps: HashMap<String, Cap>,
+    standard_lane: Option<Cap>,
+}
+
+/// A cap on the number of concurrent calls.
+#[derive(Debug)]
+struct Cap {
+    semaphore: Arc<Semaphore>,
+    max: u32,
+}
+
+impl Cap {
+    fn new(max: usize) -> Self {
+        let max = u32::try_from(max).unwrap_or(u32::MAX);
+        Self {
+            semaphore: Arc::new(Semaphore::new(max as usize)),
+            max,
+        }
+    }
+
+    /// Waits for a permit for each of `calls`. Requests with more calls than
+    /// the cap take the whole cap rather than waiting forever.
+    async fn acquire(&self, calls: usize) -> OwnedSemaphorePermit {
+        let count = u32::try_from(calls)
+            .unwrap_or(u32::MAX)
+            .min(self.max)
+            .max(1);
+        Arc::clone(&self.semaphore)
+            .acquire_many_owned(count)
+            .await
+            .expect("limit semaphores should never be closed")
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Lane {
+    Priority,
+    Standard,
+}
+
+impl Lane {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Lane::Priority => "priority",
+            Lane::Standard => "standard",
+        }
+    }
+}
+
+impl Limits {
+    /// A request goes in the priority lane only if every call in it is to a
+    /// priority method, so batches can't be used to skip the queue.
+    fn lane(&self, methods: &[String]) -> Lane {
+        if !methods.is_empty()
+            && methods
+                .iter()
+                .all(|m| self.settings.priority_methods.contains(m))
+        {
+            Lane::Priority
+        } else {
+            Lane::Standard
+        }
+    }
+
+    /// The shortest timeout of the methods called.
+    fn timeout(&self, methods: &[String]) -> Duration {
+        methods
+            .iter()
+            .map(|m| {
+                self.settings
+                    .method_timeouts
+                    .get(m)
+                    .copied()
+                    .unwrap_or(self.settings.default_timeout)
+            })
+            .min()
+            .unwrap_or(self.settings.default_timeout)
+    }
+
+    /// Waits for a permit from the lane and from each capped method for each
+    /// call in the request. Method permits are taken in name order so
+    /// concurrent batches can't deadlock on each other.
+    async fn acquire(&self, lane: Lane, methods: &[String]) -> Vec<OwnedSemaphorePermit> {
+        let mut permits = vec![];
+        if lane == Lane::Standard {
+            if let Some(cap) = &self.standard_lane {
+                permits.push(cap.acquire(methods.len()).await);
+            }
+        }
+        let mut calls: BTreeMap<&String, usize> = BTreeMap::new();
+        for method in methods {
+            *calls.entry(method).or_default() += 1;
+        }
+        for (method, count) in calls {
+            if let Some(cap) = self.method_caps.get(method) {
+                permits.push(cap.acquire(count).await);
+            }
+        }
+        permits
+    }
+
+    /// The metric label of a method, which is `other` for unknown methods so
+    /// that requests can't create arbitrary label values.
+    fn method_label(&self, method: &str) -> String {
+        if self.known_methods.contains(method) {
+            method.to_string()
+        } else {
+            "other".to_string()
+        }
+    }
+}
+
+/// Counts a request as waiting for permits in its lane until dropped, which
+/// includes when the request times out while waiting.
+struct LaneWaiting(Lane);
+
+impl LaneWaiting {
+    fn new(lane: Lane) -> Self {
+        RpcMetrics::increment_lane_waiting(lane.as_str());
+        Self(lane)
+    }
+}
+
+impl Drop for LaneWaiting {
+    fn drop(&mut self) {
+        RpcMetrics::decrement_lane_waiting(self.0.as_str());
+    }
+}
+
+impl<S> Service<Request<Body>> for RpcLimit<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // Take the service that was driven to readiness, leaving a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let limits = Arc::clone(&self.limits);
+
+        Box::pin(async move {
+            let (parts, body) = request.into_parts();
+            let max_size = limits.settings.max_request_body_size as usize;
+            let Some(bytes) = read_body(body, max_size).await else {
+                RpcMetrics::increment_rejected_requests("oversized_request");
+                return Ok(error_response(
+                    OVERSIZED_REQUEST_CODE,
+                    &format!("request body exceeds {max_size} bytes"),
+                ));
+            };
+            RpcMetrics::record_request_body_size(bytes.len());
+
+            let methods = method_names(&bytes);
+            if let Some(MethodCalls::Batch(calls)) = &methods {
+                RpcMetrics::record_batch_len(calls.len());
+                if calls.len() > limits.settings.max_batch_len {
+                    RpcMetrics::increment_rejected_requests("batch_too_large");
+                    return Ok(error_response(
+                        TOO_BIG_BATCH_REQUEST_CODE,
+                        &format!(
+                            "batch of {} calls exceeds the limit of {}",
+                            calls.len(),
+                            limits.settings.max_batch_len
+                        ),
+                    ));
+                }
+            }
+            // Unparseable requests are passed through for the server to reject,
+            // under the standard lane and default timeout
+            let methods = methods.map(MethodCalls::into_methods).unwrap_or_default();
+            let lane = limits.lane(&methods);
+            let timeout = limits.timeout(&methods);
+            let request = Request::from_parts(parts, Body::from(bytes));
+
+            let started_at = Instant::now();
+            let limited = async {
+                let waiting = LaneWaiting::new(lane);
+                let permits = limits.acquire(lane, &methods).await;
+                drop(waiting);
+                RpcMetrics::record_limit_wait(lane.as_str(), started_at.elapsed());
+                let response = inner.call(request).await;
+                drop(permits);
+                response
+            };
+            match tokio::time::timeout(timeout, limited).await {
+                Ok(response) => response,
+                Err(_) => {
+                    for method in &methods {
+                        RpcMetrics::increment_timeouts(limits.method_label(method));
+                    }
+                    Ok(error_response(
+                        SERVER_IS_BUSY_CODE,
+                        &format!("request timed out after {}ms", timeout.as_millis()),
+                    ))
+                }
+            }
+        })
+    }
+}
+
+/// Reads the request body, returning `None` if it is larger than `max_size`.
+async fn read_body(mut body: Body, max_size: usize) -> Option<Vec<u8>> {
+    if body.size_hint().lower() > max_size as u64 {
+        return None;
+    }
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        // A broken body is passed on empty for the server to reject
+        let Ok(chunk) = chunk else {
+            return Some(vec![]);
+        };
+        if bytes.len() + chunk.len() > max_size {
+            return None;
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Some(bytes)
+}
+
+#[derive(Debug, Deserialize)]
+struct MethodCall {
+    #[serde(default)]
+    method: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum MethodCalls {
+    Batch(Vec<MethodCall>),
+    Single(MethodCall),
+}
+
+impl MethodCalls {
+    fn into_methods(self) -> Vec<String> {
+        match self {
+            MethodCalls::Batch(calls) => calls.into_iter().map(|c| c.method).collect(),
+            MethodCalls::Single(call) => vec![call.method],
+        }
+    }
+}
+
+fn method_names(body: &[u8]) -> Option<MethodCalls> {
+    serde_json::from_slice(body).ok()
+}
+
+fn error_response(code: i32, message: &str) -> Response<Body> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "error": { "code": code, "message": message },
+        "id": null,
+    });
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("error response should be valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use super::*;
+
+    const KNOWN_METHODS: [&str; 3] = [
+        "eth_chainId",
+        "eth_estimateUserOperationGas",
+        "eth_sendUserOperation",
+    ];
+
+    fn settings() -> RpcLimitSettings {
+        RpcLimitSettings {
+            max_request_body_size: 1024,
+            max_batch_len: 10,
+            default_timeout: Duration::from_secs(20),
+            method_timeouts: HashMap::from([(
+                "eth_estimateUserOperationGas".to_string(),
+                Duration::from_secs(5),
+            )]),
+            method_max_concurrent: HashMap::from([("eth_estimateUserOperationGas".to_string(), 2)]),
+            max_concurrent_requests: 3,
+            priority_methods: HashSet::from(["eth_sendUserOperation".to_string()]),
+        }
+    }
+
+    fn limits() -> Limits {
+        let layer = RpcLimitLayer::new(settings(), KNOWN_METHODS);
+        Arc::try_unwrap(layer.limits).unwrap()
+    }
+
+    fn methods(methods: &[&str]) -> Vec<String> {
+        methods.iter().map(|m| m.to_string()).collect()
+    }
+
+    /// Responds after a delay.
+    #[derive(Clone)]
+    struct Delayed(Duration);
+
+    impl Service<Request<Body>> for Delayed {
+        type Response = Response<Body>;
+        type Error = Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Response<Body>, Infallible>> + Send>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<Body>) -> Self::Future {
+            let delay = self.0;
+            Box::pin(async move {
+                tokio::time::sleep(delay).await;
+                Ok(Response::new(Body::from("ok")))
+            })
+        }
+    }
+
+    async fn call(service: &mut RpcLimit<Delayed>, body: &'static str) -> String {
+        let response = service.call(Request::new(Body::from(body))).await.unwrap();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn parses_single_and_batch_methods() {
+        let single = method_names(br#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#)
+            .unwrap()
+            .into_methods();
+        assert_eq!(single, vec!["eth_chainId"]);
+
+        let batch = method_names(
+            br#"[{"jsonrpc":"2.0","id":1,"method":"eth_chainId"},{"jsonrpc":"2.0","id":2,"method":"eth_sendUserOperation","params":[]}]"#,
+        )
+        .unwrap()
+        .into_methods();
+        assert_eq!(batch, vec!["eth_chainId", "eth_sendUserOperation"]);
+
+        assert!(method_names(b"not json").is_none());
+    }
+
+    #[test]
+    fn lane_and_timeout_use_all_methods() {
+        let limits = limits();
+        let send = vec!["eth_sendUserOperation".to_string()];
+        let mixed = vec![
+            "eth_sendUserOperation".to_string(),
+            "eth_estimateUserOperationGas".to_string(),
+        ];
+
+        assert_eq!(limits.lane(&send), Lane::Priority);
+        assert_eq!(limits.lane(&mixed), Lane::Standard);
+        assert_eq!(limits.lane(&[]), Lane::Standard);
+
+        assert_eq!(limits.timeout(&send), Duration::from_secs(20));
+        assert_eq!(limits.timeout(&mixed), Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn takes_a_permit_per_call() {
+        let limits = limits();
+        let lane = limits.standard_lane.as_ref().unwrap();
+        let estimate = &limits.method_caps["eth_estimateUserOperationGas"];
+
+        let permits = limits
+            .acquire(
+                Lane::Standard,
+                &methods(&[
+                    "eth_estimateUserOperationGas",
+                    "eth_estimateUserOperationGas",
+                ]),
+            )
+            .await;
+        assert_eq!(lane.semaphore.available_permits(), 1);
+        assert_eq!(estimate.semaphore.available_permits(), 0);
+        drop(permits);
+        assert_eq!(lane.semaphore.available_permits(), 3);
+        assert_eq!(estimate.semaphore.available_permits(), 2);
+
+        // batches larger than a cap take the whole cap
+        let permits = limits
+            .acquire(Lane::Standard, &methods(&["eth_chainId"; 5]))
+            .await;
+        assert_eq!(lane.semaphore.available_permits(), 0);
+        drop(permits);
+
+        // requests in the priority lane only take method permits
+        let permits = limits
+            .acquire(Lane::Priority, &methods(&["eth_sendUserOperation"]))
+            .await;
+        assert!(permits.is_empty());
+        assert_eq!(lane.semaphore.available_permits(), 3);
+    }
+
+    #[test]
+    fn labels_unknown_methods_as_other() {
+        let limits = limits();
+        assert_eq!(limits.method_label("eth_chainId"), "eth_chainId");
+        assert_eq!(limits.method_label("made_up_method"), "other");
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_requests() {
+        let layer = RpcLimitLayer::new(
+            RpcLimitSettings {
+                default_timeout: Duration::from_millis(50),
+                ..settings()
+            },
+            KNOWN_METHODS,
+        );
+
+        let mut service = layer.layer(Delayed(Duration::from_millis(10)));
+        let response = call(
+            &mut service,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#,
+        )
+        .await;
+        assert_eq!(response, "ok");
+
+        let mut service = layer.layer(Delayed(Duration::from_secs(10)));
+        let response = call(
+            &mut service,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#,
+        )
+        .await;
+        assert!(response.contains(&SERVER_IS_BUSY_CODE.to_string()));
+        // the permits of the timed out request are released
+        let lane = layer.limits.standard_lane.as_ref().unwrap();
+        assert_eq!(lane.semaphore.available_permits(), 3);
+    }
+
+    #[tokio::test]
+    async fn times_out_requests_waiting_for_permits() {
+        let layer = RpcLimitLayer::new(
+            RpcLimitSettings {
+                default_timeout: Duration::from_millis(50),
+                ..settings()
+            },
+            KNOWN_METHODS,
+        );
+        let mut service = layer.layer(Delayed(Duration::ZERO));
+        let lane = layer.limits.standard_lane.as_ref().unwrap();
+
+        let held = lane.acquire(3).await;
+        let response = call(
+            &mut service,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#,
+        )
+        .await;
+        assert!(response.contains(&SERVER_IS_BUSY_CODE.to_string()));
+
+        // priority requests don't wait for the standard lane
+        let response = call(
+            &mut service,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_sendUserOperation"}"#,
+        )
+        .await;
+        assert_eq!(response, "ok");
+
+        drop(held);
+        let response = call(
+            &mut service,
+            r#"{"jsonrpc":"2.0","id":1,"method":"eth_chainId"}"#,
+        )
+        .await;
+        assert_eq!(response, "ok");
+    }
+}
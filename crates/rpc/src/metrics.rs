@@ -100,4 +100,32 @@ impl RpcMetrics {
     fn record_request_latency(method_name: String, latency: Duration) {
         metrics::histogram!("rpc_request_latency", latency, "method_name" => method_name)
     }
+
+    pub(crate) fn increment_timeouts(method_name: String) {
+        metrics::increment_counter!("rpc_timeout_count", "method_name" => method_name)
+    }
+
+    pub(crate) fn increment_rejected_requests(reason: &'static str) {
+        metrics::increment_counter!("rpc_rejected_requests", "reason" => reason)
+    }
+
+    pub(crate) fn record_request_body_size(size: usize) {
+        metrics::histogram!("rpc_request_body_size", size as f64)
+    }
+
+    pub(crate) fn record_batch_len(len: usize) {
+        metrics::histogram!("rpc_batch_len", len as f64)
+    }
+
+    pub(crate) fn increment_lane_waiting(lane: &'static str) {
+        metrics::increment_gauge!("rpc_lane_waiting_requests", 1_f64, "lane" => lane)
+    }
+
+    pub(crate) fn decrement_lane_waiting(lane: &'static str) {
+        metrics::decrement_gauge!("rpc_lane_waiting_requests", 1_f64, "lane" => lane)
+    }
+
+    pub(crate) fn record_limit_wait(lane: &'static str, wait: Duration) {
+        metrics::histogram!("rpc_limit_wait", wait, "lane" => lane)
+    }
 }
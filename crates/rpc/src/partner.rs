@@ -0,0 +1,138 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Middleware identifying the partner making a request from its API key, so
+//! the pool can boost the priority of the partner's operations.
+
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use hyper::Request;
+use tower::{Layer, Service};
+
+/// Header carrying a partner's API key.
+pub(crate) const API_KEY_HEADER: &str = "x-api-key";
+
+tokio::task_local! {
+    static PARTNER: Option<String>;
+}
+
+/// Returns the partner making the current request, if its API key is known.
+pub(crate) fn current_partner() -> Option<String> {
+    PARTNER.try_with(|p| p.clone()).ok().flatten()
+}
+
+/// Layer resolving the API key of HTTP requests to a partner name, which is
+/// available to the API handlers through [`current_partner`].
+///
+/// Requests without a key, or with an unknown key, are served without a partner.
+#[derive(Clone, Debug)]
+pub(crate) struct PartnerLayer {
+    partners_by_api_key: Arc<HashMap<String, String>>,
+}
+
+impl PartnerLayer {
+    pub(crate) fn new(partners_by_api_key: HashMap<String, String>) -> Self {
+        Self {
+            partners_by_api_key: Arc::new(partners_by_api_key),
+        }
+    }
+}
+
+impl<S> Layer<S> for PartnerLayer {
+    type Service = Partner<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Partner {
+            inner,
+            partners_by_api_key: Arc::clone(&self.partners_by_api_key),
+        }
+    }
+}
+
+/// Service resolving the partner of HTTP requests, see [`PartnerLayer`].
+#[derive(Clone, Debug)]
+pub(crate) struct Partner<S> {
+    inner: S,
+    partners_by_api_key: Arc<HashMap<String, String>>,
+}
+
+impl<S, Body> Service<Request<Body>> for Partner<S>
+where
+    S: Service<Request<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = tokio::task::futures::TaskLocalFuture<Option<String>, S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let partner = request
+            .headers()
+            .get(API_KEY_HEADER)
+            .and_then(|key| key.to_str().ok())
+            .and_then(|key| self.partners_by_api_key.get(key))
+            .cloned();
+        PARTNER.scope(partner, self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{future::Future, pin::Pin};
+
+    use super::*;
+
+    /// Responds with the partner of the request.
+    struct EchoPartner;
+
+    impl Service<Request<()>> for EchoPartner {
+        type Response = Option<String>;
+        type Error = ();
+        type Future = Pin<Box<dyn Future<Output = Result<Option<String>, ()>>>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _request: Request<()>) -> Self::Future {
+            Box::pin(async { Ok(current_partner()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolves_partner_from_api_key() {
+        let layer = PartnerLayer::new(HashMap::from([("key".to_string(), "acme".to_string())]));
+        let mut service = layer.layer(EchoPartner);
+
+        for (key, expected) in [
+            (Some("key"), Some("acme".to_string())),
+            (Some("unknown"), None),
+            (None, None),
+        ] {
+            let mut request = Request::builder();
+            if let Some(key) = key {
+                request = request.header(API_KEY_HEADER, key);
+            }
+            let partner = service.call(request.body(()).unwrap()).await.unwrap();
+            assert_eq!(partner, expected);
+        }
+        assert_eq!(current_partner(), None);
+    }
+}
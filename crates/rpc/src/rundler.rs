@@ -11,47 +11,200 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use async_trait::async_trait;
-use ethers::types::U256;
+use ethers::{
+    abi::{self, AbiDecode, AbiEncode, Token},
+    types::{
+        spoof, transaction::eip2718::TypedTransaction, Address, Bytes, Eip1559TransactionRequest,
+        H256, U256,
+    },
+    utils::keccak256,
+};
 use jsonrpsee::{core::RpcResult, proc_macros::rpc, types::error::INTERNAL_ERROR_CODE};
-use rundler_provider::Provider;
-use rundler_sim::{FeeEstimator, PrecheckSettings};
+use rundler_builder::BuilderServer;
+use rundler_pool::{OpRemovalRequester, PoolServer, UserOperationIndex};
+use rundler_provider::{EntryPoint, Provider};
+use rundler_sim::{
+    EstimationSettings, FeeEstimator, GasEstimate, GasEstimateWithBreakdown, GasEstimator,
+    GasEstimatorImpl, PrecheckSettings, SimulateValidationTracerImpl, UserOperationOptionalGas,
+};
+use rundler_types::{
+    contracts::simple_account::ValidateUserOpCall, Timestamp, UserOperation, ValidTimeRange,
+};
+use rundler_utils::revert::AbiRegistry;
 
-use crate::error::rpc_err;
+use crate::{
+    error::rpc_err,
+    eth::EthRpcError,
+    status::{OpStage, OpStatusTracker},
+    types::{
+        RpcMinedStatus, RpcPaymasterStatus, RpcPoolStatus, RpcUserOperation,
+        RpcUserOperationGasPrice, RpcUserOperationStatus, RpcUserOperationStatusKind,
+    },
+};
+
+/// Maximum number of user operations in a gas estimation batch
+const MAX_ESTIMATION_BATCH_SIZE: usize = 16;
+
+/// Selector of ERC-1271 `isValidSignature(bytes32,bytes)`, which is also the
+/// value returned by the account when the signature is valid
+const ERC1271_MAGIC_VALUE: [u8; 4] = [0x16, 0x26, 0xba, 0x7e];
+/// EIP-712 type of the message signed to cancel an operation
+const CANCEL_TYPE: &str = "Cancel(bytes32 userOpHash,address entryPoint,uint256 chainId)";
+const EIP712_DOMAIN_TYPE: &str = "EIP712Domain(string name,string version,uint256 chainId)";
+const EIP712_DOMAIN_NAME: &str = "Rundler";
+const EIP712_DOMAIN_VERSION: &str = "1";
 
 #[rpc(client, server, namespace = "rundler")]
 pub trait RundlerApi {
     /// Returns the maximum priority fee per gas required by Rundler
     #[method(name = "maxPriorityFeePerGas")]
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U256>;
+
+    /// Returns recommended fees for slow, standard, and fast inclusion of a user operation
+    #[method(name = "getUserOperationGasPrice")]
+    async fn get_user_operation_gas_price(&self) -> RpcResult<RpcUserOperationGasPrice>;
+
+    /// Estimates the gas fields for a sequence of user operations, where each
+    /// operation is estimated after the nonce increments and deployments of
+    /// the operations before it.
+    #[method(name = "estimateUserOperationGasBatch")]
+    async fn estimate_user_operation_gas_batch(
+        &self,
+        ops: Vec<UserOperationOptionalGas>,
+        entry_point: Address,
+        state_override: Option<spoof::State>,
+    ) -> RpcResult<Vec<GasEstimate>>;
+
+    /// Estimates the gas fields for a user operation, along with the gas used by
+    /// each verification phase: factory deployment, account validation, paymaster
+    /// validation, and paymaster `postOp`.
+    #[method(name = "estimateUserOperationGasWithBreakdown")]
+    async fn estimate_user_operation_gas_with_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        entry_point: Address,
+        state_override: Option<spoof::State>,
+    ) -> RpcResult<GasEstimateWithBreakdown>;
+
+    /// Returns the user operations in the pool from a sender, ordered by nonce
+    #[method(name = "getUserOperationsBySender")]
+    async fn get_user_operations_by_sender(
+        &self,
+        sender: Address,
+        entry_point: Address,
+    ) -> RpcResult<Vec<RpcUserOperation>>;
+
+    /// Returns the user operations in the pool using a paymaster, in pool order
+    #[method(name = "getUserOperationsByPaymaster")]
+    async fn get_user_operations_by_paymaster(
+        &self,
+        paymaster: Address,
+        entry_point: Address,
+    ) -> RpcResult<Vec<RpcUserOperation>>;
+
+    /// Returns a paymaster's confirmed and pending balances, pending operation
+    /// count, rate of spend, and projected runway. Paymasters without pending
+    /// operations are reported from their on-chain deposit.
+    #[method(name = "getPaymasterStatus")]
+    async fn get_paymaster_status(
+        &self,
+        paymaster: Address,
+        entry_point: Address,
+    ) -> RpcResult<RpcPaymasterStatus>;
+
+    /// Returns where a user operation is in its lifecycle: pending in the pool,
+    /// proposed or submitted in a bundle, mined, dropped, or rejected.
+    #[method(name = "getUserOperationStatus")]
+    async fn get_user_operation_status(&self, hash: H256) -> RpcResult<RpcUserOperationStatus>;
+
+    /// Drops a pending user operation at the request of its sender.
+    ///
+    /// The signature must be the sender's signature over the EIP-712 digest of
+    /// `Cancel(bytes32 userOpHash,address entryPoint,uint256 chainId)`. It is
+    /// verified with the account's `isValidSignature` (ERC-1271) or, failing
+    /// that, its `validateUserOp`.
+    #[method(name = "cancelUserOperation")]
+    async fn cancel_user_operation(&self, hash: H256, signature: Bytes) -> RpcResult<bool>;
 }
 
-pub(crate) struct RundlerApi<P: Provider> {
+pub(crate) struct RundlerApi<P: Provider, E: EntryPoint, PS, B> {
+    provider: Arc<P>,
+    chain_id: u64,
     fee_estimator: FeeEstimator<P>,
+    gas_estimators: HashMap<Address, GasEstimatorImpl<P, E, SimulateValidationTracerImpl<P, E>>>,
+    pool: PS,
+    builder: B,
+    abi_registry: Arc<AbiRegistry>,
+    op_status_tracker: Option<Arc<OpStatusTracker>>,
+    user_operation_index: Option<Arc<UserOperationIndex>>,
 }
 
-impl<P> RundlerApi<P>
+impl<P, E, PS, B> RundlerApi<P, E, PS, B>
 where
     P: Provider,
+    E: EntryPoint + Clone,
 {
-    pub(crate) fn new(provider: Arc<P>, chain_id: u64, settings: PrecheckSettings) -> Self {
-        Self {
-            fee_estimator: FeeEstimator::new(
-                provider,
-                chain_id,
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        provider: Arc<P>,
+        entry_points: Vec<E>,
+        pool: PS,
+        builder: B,
+        settings: PrecheckSettings,
+        estimation_settings: EstimationSettings,
+        abi_registry: Arc<AbiRegistry>,
+        op_status_tracker: Option<Arc<OpStatusTracker>>,
+        user_operation_index: Option<Arc<UserOperationIndex>>,
+    ) -> Self {
+        let new_fee_estimator = || {
+            FeeEstimator::new(
+                Arc::clone(&provider),
+                &settings.chain_spec,
                 settings.priority_fee_mode,
                 settings.bundle_priority_fee_overhead_percent,
-            ),
+            )
+        };
+        let gas_estimators = entry_points
+            .into_iter()
+            .map(|entry_point| {
+                (
+                    entry_point.address(),
+                    GasEstimatorImpl::new(
+                        settings.chain_spec.l1_gas,
+                        Arc::clone(&provider),
+                        entry_point.clone(),
+                        estimation_settings,
+                        new_fee_estimator(),
+                        SimulateValidationTracerImpl::new(Arc::clone(&provider), entry_point),
+                    ),
+                )
+            })
+            .collect();
+
+        Self {
+            provider,
+            chain_id: settings.chain_spec.id,
+            fee_estimator: new_fee_estimator(),
+            gas_estimators,
+            pool,
+            builder,
+            abi_registry,
+            op_status_tracker,
+            user_operation_index,
         }
     }
 }
 
 #[async_trait]
-impl<P> RundlerApiServer for RundlerApi<P>
+impl<P, E, PS, B> RundlerApiServer for RundlerApi<P, E, PS, B>
 where
     P: Provider,
+    E: EntryPoint,
+    PS: PoolServer,
+    B: BuilderServer,
 {
     async fn max_priority_fee_per_gas(&self) -> RpcResult<U256> {
         let (bundle_fees, _) = self
@@ -64,4 +217,582 @@ where
             .required_op_fees(bundle_fees)
             .max_priority_fee_per_gas)
     }
+
+    async fn get_user_operation_gas_price(&self) -> RpcResult<RpcUserOperationGasPrice> {
+        let latencies = self
+            .builder
+            .get_inclusion_latencies()
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to get bundle inclusion latencies: {e:?}");
+                vec![]
+            });
+        // Assume next block inclusion until the builder has mined bundles
+        let inclusion_latency_blocks = if latencies.is_empty() {
+            1
+        } else {
+            let len = latencies.len() as u64;
+            (latencies.iter().sum::<u64>() + len - 1) / len
+        };
+
+        let tiers = self
+            .fee_estimator
+            .required_op_fee_tiers(inclusion_latency_blocks)
+            .await
+            .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+        Ok(RpcUserOperationGasPrice::new(
+            tiers,
+            inclusion_latency_blocks,
+        ))
+    }
+
+    async fn estimate_user_operation_gas_batch(
+        &self,
+        ops: Vec<UserOperationOptionalGas>,
+        entry_point: Address,
+        state_override: Option<spoof::State>,
+    ) -> RpcResult<Vec<GasEstimate>> {
+        let gas_estimator = self.gas_estimator(entry_point)?;
+        if ops.is_empty() || ops.len() > MAX_ESTIMATION_BATCH_SIZE {
+            Err(EthRpcError::InvalidParams(format!(
+                "batch must contain between 1 and {MAX_ESTIMATION_BATCH_SIZE} user operations"
+            )))?;
+        }
+
+        Ok(gas_estimator
+            .estimate_op_gas_batch(ops, state_override.unwrap_or_default())
+            .await
+            .map_err(|e| EthRpcError::from(e).decode_revert(&self.abi_registry))?)
+    }
+
+    async fn estimate_user_operation_gas_with_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        entry_point: Address,
+        state_override: Option<spoof::State>,
+    ) -> RpcResult<GasEstimateWithBreakdown> {
+        Ok(self
+            .gas_estimator(entry_point)?
+            .estimate_op_gas_with_breakdown(op, state_override.unwrap_or_default())
+            .await
+            .map_err(|e| EthRpcError::from(e).decode_revert(&self.abi_registry))?)
+    }
+
+    async fn get_user_operations_by_sender(
+        &self,
+        sender: Address,
+        entry_point: Address,
+    ) -> RpcResult<Vec<RpcUserOperation>> {
+        Ok(self
+            .pool
+            .get_ops_by_sender(entry_point, sender)
+            .await
+            .map_err(EthRpcError::from)?
+            .into_iter()
+            .map(|op| op.uo.into())
+            .collect())
+    }
+
+    async fn get_user_operations_by_paymaster(
+        &self,
+        paymaster: Address,
+        entry_point: Address,
+    ) -> RpcResult<Vec<RpcUserOperation>> {
+        Ok(self
+            .pool
+            .get_ops_by_paymaster(entry_point, paymaster)
+            .await
+            .map_err(EthRpcError::from)?
+            .into_iter()
+            .map(|op| op.uo.into())
+            .collect())
+    }
+
+    async fn get_paymaster_status(
+        &self,
+        paymaster: Address,
+        entry_point: Address,
+    ) -> RpcResult<RpcPaymasterStatus> {
+        Ok(self
+            .pool
+            .get_paymaster_status(entry_point, paymaster)
+            .await
+            .map_err(EthRpcError::from)?
+            .into())
+    }
+
+    async fn get_user_operation_status(&self, hash: H256) -> RpcResult<RpcUserOperationStatus> {
+        let tracked = self
+            .op_status_tracker
+            .as_ref()
+            .and_then(|tracker| tracker.get(hash));
+        let in_flight = tracked.as_ref().map_or(true, |status| {
+            matches!(
+                status.stage,
+                OpStage::Pending | OpStage::Proposed | OpStage::Submitted
+            )
+        });
+
+        if in_flight {
+            let pool_op = self
+                .pool
+                .get_op_by_hash(hash)
+                .await
+                .map_err(EthRpcError::from)?;
+            if let Some(op) = pool_op {
+                let mut status = match tracked {
+                    Some(status) => RpcUserOperationStatus::from(status),
+                    None => RpcUserOperationStatus {
+                        status: RpcUserOperationStatusKind::Pending,
+                        entry_point: Some(op.entry_point.into()),
+                        ..RpcUserOperationStatus::unknown()
+                    },
+                };
+                // the op may have left the pool since it was looked up
+                let position = self
+                    .pool
+                    .get_op_position(op.entry_point, hash)
+                    .await
+                    .map_err(EthRpcError::from)?;
+                if let Some(position) = position {
+                    let (bundle_fees, _) = self
+                        .fee_estimator
+                        .required_bundle_fees(None)
+                        .await
+                        .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+                    let required_fees = self.fee_estimator.required_op_fees(bundle_fees);
+                    status.pool = Some(RpcPoolStatus {
+                        position: position.position,
+                        pool_size: position.pool_size,
+                        max_fee_per_gas: op.uo.max_fee_per_gas,
+                        max_priority_fee_per_gas: op.uo.max_priority_fee_per_gas,
+                        required_fees: required_fees.into(),
+                        competitive: op.uo.max_fee_per_gas >= required_fees.max_fee_per_gas
+                            && op.uo.max_priority_fee_per_gas
+                                >= required_fees.max_priority_fee_per_gas,
+                    });
+                }
+                return Ok(status);
+            }
+        }
+
+        if let Some(status) = tracked {
+            return Ok(status.into());
+        }
+
+        if let Some(index) = &self.user_operation_index {
+            let indexed = index
+                .get(hash)
+                .await
+                .map_err(|e| rpc_err(INTERNAL_ERROR_CODE, e.to_string()))?;
+            if let Some(op) = indexed {
+                return Ok(RpcUserOperationStatus {
+                    status: RpcUserOperationStatusKind::Mined,
+                    entry_point: Some(op.entry_point.into()),
+                    mined: Some(RpcMinedStatus {
+                        block_number: op.block_number,
+                        block_hash: op.block_hash,
+                        transaction_hash: op.transaction_hash,
+                    }),
+                    ..RpcUserOperationStatus::unknown()
+                });
+            }
+        }
+
+        Ok(RpcUserOperationStatus::unknown())
+    }
+
+    async fn cancel_user_operation(&self, hash: H256, signature: Bytes) -> RpcResult<bool> {
+        let Some(op) = self
+            .pool
+            .get_op_by_hash(hash)
+            .await
+            .map_err(EthRpcError::from)?
+        else {
+            Err(EthRpcError::InvalidParams(format!(
+                "user operation {hash:?} is not pending"
+            )))?
+        };
+
+        let digest = cancellation_digest(hash, op.entry_point, self.chain_id);
+        if !self
+            .verify_cancellation(op.entry_point, op.uo, digest, signature)
+            .await?
+        {
+            Err(EthRpcError::InvalidParams(
+                "invalid cancellation signature".to_string(),
+            ))?;
+        }
+
+        Ok(self
+            .pool
+            .remove_op_by_hash(hash, OpRemovalRequester::Sender)
+            .await
+            .map_err(EthRpcError::from)?)
+    }
+}
+
+impl<P, E, PS, B> RundlerApi<P, E, PS, B>
+where
+    P: Provider,
+    E: EntryPoint,
+{
+    fn gas_estimator(
+        &self,
+        entry_point: Address,
+    ) -> Result<&GasEstimatorImpl<P, E, SimulateValidationTracerImpl<P, E>>, EthRpcError> {
+        self.gas_estimators.get(&entry_point).ok_or_else(|| {
+            EthRpcError::InvalidParams(
+                "supplied entry_point address is not a known entry point".to_string(),
+            )
+        })
+    }
+
+    /// Checks that the cancellation digest is signed by the sender of the
+    /// operation, first with ERC-1271 and then by calling `validateUserOp` as
+    /// the entry point with the signature in place of the operation's own.
+    async fn verify_cancellation(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        digest: H256,
+        signature: Bytes,
+    ) -> Result<bool, EthRpcError> {
+        let sender = op.sender;
+        if self.provider.get_code(sender, None).await?.is_empty() {
+            return Err(EthRpcError::InvalidParams(
+                "sender account is not deployed".to_string(),
+            ));
+        }
+
+        let mut data = ERC1271_MAGIC_VALUE.to_vec();
+        data.extend(abi::encode(&[
+            Token::FixedBytes(digest.as_bytes().to_vec()),
+            Token::Bytes(signature.to_vec()),
+        ]));
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(sender)
+            .data(data)
+            .into();
+        // Accounts without ERC-1271 support revert or return garbage here
+        if let Ok(ret) = self
+            .provider
+            .call(&tx, None, &spoof::State::default())
+            .await
+        {
+            if ret.starts_with(&ERC1271_MAGIC_VALUE) {
+                return Ok(true);
+            }
+        }
+
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .from(entry_point)
+            .to(sender)
+            .data(
+                ValidateUserOpCall {
+                    user_op: UserOperation { signature, ..op },
+                    user_op_hash: digest.into(),
+                    missing_account_funds: U256::zero(),
+                }
+                .encode(),
+            )
+            .into();
+        let Ok(ret) = self
+            .provider
+            .call(&tx, None, &spoof::State::default())
+            .await
+        else {
+            return Ok(false);
+        };
+        let validation_data =
+            U256::decode(ret).map_err(|e| EthRpcError::Internal(anyhow::anyhow!(e)))?;
+        Ok(is_valid_signature_data(validation_data, Timestamp::now()))
+    }
+}
+
+/// Returns the EIP-712 digest a sender signs to cancel a pending operation.
+fn cancellation_digest(user_op_hash: H256, entry_point: Address, chain_id: u64) -> H256 {
+    let domain_separator = keccak256(abi::encode(&[
+        Token::FixedBytes(keccak256(EIP712_DOMAIN_TYPE).to_vec()),
+        Token::FixedBytes(keccak256(EIP712_DOMAIN_NAME).to_vec()),
+        Token::FixedBytes(keccak256(EIP712_DOMAIN_VERSION).to_vec()),
+        Token::Uint(chain_id.into()),
+    ]));
+    let struct_hash = keccak256(abi::encode(&[
+        Token::FixedBytes(keccak256(CANCEL_TYPE).to_vec()),
+        Token::FixedBytes(user_op_hash.as_bytes().to_vec()),
+        Token::Address(entry_point),
+        Token::Uint(chain_id.into()),
+    ]));
+    let mut message = vec![0x19, 0x01];
+    message.extend(domain_separator);
+    message.extend(struct_hash);
+    keccak256(message).into()
+}
+
+/// Returns whether the validation data returned by `validateUserOp` accepts
+/// the signature at the given time.
+fn is_valid_signature_data(validation_data: U256, now: Timestamp) -> bool {
+    let mask_48 = (U256::one() << 48) - 1;
+    // The low 160 bits hold the aggregator, which is zero only when the
+    // account accepted the signature itself
+    if !(validation_data & ((U256::one() << 160) - 1)).is_zero() {
+        return false;
+    }
+    let valid_until = ((validation_data >> 160) & mask_48).as_u64();
+    let valid_after = ((validation_data >> 208) & mask_48).as_u64();
+    // A zero valid until means the signature doesn't expire
+    let valid_until = if valid_until == 0 {
+        Timestamp::MAX
+    } else {
+        valid_until.into()
+    };
+    ValidTimeRange::new(valid_after.into(), valid_until).contains(now, Duration::ZERO)
+}
+
+#[cfg(test)]
+mod tests {
+    use mockall::predicate::eq;
+    use rundler_builder::MockBuilderServer;
+    use rundler_pool::{
+        EntitySummary, MockPoolServer, OpPosition, OperationOrigin, PoolEvent, PoolOperation,
+    };
+    use rundler_provider::{MockEntryPoint, MockProvider, ProviderError};
+    use rundler_sim::PriorityFeeMode;
+    use rundler_types::chain::ChainSpec;
+    use rundler_utils::emit::WithEntryPoint;
+
+    use super::*;
+    use crate::status::OpStatusSettings;
+
+    #[tokio::test]
+    async fn test_get_user_operation_status_pending() {
+        let ep = Address::random();
+        let uo = UserOperation {
+            max_fee_per_gas: 1_000.into(),
+            max_priority_fee_per_gas: 1_000.into(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+
+        let mut pool = MockPoolServer::default();
+        let po = PoolOperation {
+            uo: uo.clone(),
+            entry_point: ep,
+            ..Default::default()
+        };
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(Some(po.clone())));
+        pool.expect_get_op_position()
+            .with(eq(ep), eq(hash))
+            .returning(|_, _| {
+                Ok(Some(OpPosition {
+                    position: 1,
+                    pool_size: 3,
+                }))
+            });
+
+        let tracker = Arc::new(OpStatusTracker::new(OpStatusSettings {
+            max_ops: 10,
+            retention: Duration::from_secs(60),
+        }));
+        tracker.on_pool_event(&WithEntryPoint {
+            entry_point: ep,
+            event: PoolEvent::ReceivedOp {
+                op_hash: hash,
+                op: uo,
+                block_number: 7,
+                origin: OperationOrigin::Local,
+                valid_after: Timestamp::default(),
+                valid_until: Timestamp::default(),
+                entities: EntitySummary::default(),
+            },
+        });
+
+        let api = create_api(MockProvider::default(), pool, Some(tracker));
+        let status = api.get_user_operation_status(hash).await.unwrap();
+        assert_eq!(status.status, RpcUserOperationStatusKind::Pending);
+        assert_eq!(status.received_block_number, Some(7));
+        let pool_status = status.pool.unwrap();
+        assert_eq!(pool_status.position, 1);
+        assert_eq!(pool_status.pool_size, 3);
+        assert_eq!(pool_status.required_fees.max_fee_per_gas, 110.into());
+        assert_eq!(
+            pool_status.required_fees.max_priority_fee_per_gas,
+            10.into()
+        );
+        assert!(pool_status.competitive);
+    }
+
+    #[tokio::test]
+    async fn test_get_user_operation_status_left_pool() {
+        let ep = Address::random();
+        let uo = UserOperation::default();
+        let hash = uo.op_hash(ep, 1);
+
+        // the op leaves the pool between the lookups
+        let mut pool = MockPoolServer::default();
+        let po = PoolOperation {
+            uo,
+            entry_point: ep,
+            ..Default::default()
+        };
+        pool.expect_get_op_by_hash()
+            .returning(move |_| Ok(Some(po.clone())));
+        pool.expect_get_op_position().returning(|_, _| Ok(None));
+
+        let api = create_api(MockProvider::default(), pool, None);
+        let status = api.get_user_operation_status(hash).await.unwrap();
+        assert_eq!(status.status, RpcUserOperationStatusKind::Pending);
+        assert_eq!(status.pool, None);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_user_operation_erc1271() {
+        let (pool, hash, digest) = cancel_pool(true);
+        let mut provider = deployed_sender_provider();
+        provider
+            .expect_call()
+            .withf(move |tx, _, _| {
+                let data = tx.data().unwrap();
+                data.starts_with(&ERC1271_MAGIC_VALUE) && &data[4..36] == digest.as_bytes()
+            })
+            .returning(|_, _, _| Ok(magic_value()));
+
+        let api = create_api(provider, pool, None);
+        assert!(api
+            .cancel_user_operation(hash, Bytes::from(vec![1]))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_user_operation_validate_user_op() {
+        let (pool, hash, digest) = cancel_pool(true);
+        let mut provider = deployed_sender_provider();
+        // the account doesn't support ERC-1271
+        provider
+            .expect_call()
+            .withf(|tx, _, _| tx.data().unwrap().starts_with(&ERC1271_MAGIC_VALUE))
+            .returning(|_, _, _| Err(ProviderError::Other(anyhow::anyhow!("reverted"))));
+        provider
+            .expect_call()
+            .withf(move |tx, _, _| {
+                let call = ValidateUserOpCall::decode(tx.data().unwrap()).unwrap();
+                call.user_op_hash == digest.0 && call.user_op.signature == Bytes::from(vec![1])
+            })
+            .returning(|_, _, _| Ok(U256::zero().encode().into()));
+
+        let api = create_api(provider, pool, None);
+        assert!(api
+            .cancel_user_operation(hash, Bytes::from(vec![1]))
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_user_operation_bad_signature() {
+        let (pool, hash, _) = cancel_pool(false);
+        let mut provider = deployed_sender_provider();
+        provider
+            .expect_call()
+            .withf(|tx, _, _| tx.data().unwrap().starts_with(&ERC1271_MAGIC_VALUE))
+            .returning(|_, _, _| Ok(U256::zero().encode().into()));
+        // SIG_VALIDATION_FAILED
+        provider
+            .expect_call()
+            .withf(|tx, _, _| !tx.data().unwrap().starts_with(&ERC1271_MAGIC_VALUE))
+            .returning(|_, _, _| Ok(U256::one().encode().into()));
+
+        let api = create_api(provider, pool, None);
+        assert!(api
+            .cancel_user_operation(hash, Bytes::from(vec![1]))
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_is_valid_signature_data() {
+        let now = Timestamp::new(1_000);
+        let data = |valid_until: u64, valid_after: u64| {
+            (U256::from(valid_after) << 208) | (U256::from(valid_until) << 160)
+        };
+        assert!(is_valid_signature_data(data(0, 0), now));
+        assert!(is_valid_signature_data(data(2_000, 500), now));
+        // expired
+        assert!(!is_valid_signature_data(data(999, 0), now));
+        // not yet valid
+        assert!(!is_valid_signature_data(data(0, 1_001), now));
+        // signature failure
+        assert!(!is_valid_signature_data(data(0, 0) | U256::one(), now));
+    }
+
+    // Returns a pool with a pending operation, expecting it to be removed by
+    // its sender if `removed`, along with its hash and cancellation digest
+    fn cancel_pool(removed: bool) -> (MockPoolServer, H256, H256) {
+        let ep = Address::random();
+        let uo = UserOperation {
+            sender: Address::random(),
+            ..Default::default()
+        };
+        let hash = uo.op_hash(ep, 1);
+        let po = PoolOperation {
+            uo,
+            entry_point: ep,
+            ..Default::default()
+        };
+
+        let mut pool = MockPoolServer::default();
+        pool.expect_get_op_by_hash()
+            .with(eq(hash))
+            .returning(move |_| Ok(Some(po.clone())));
+        pool.expect_remove_op_by_hash()
+            .with(eq(hash), eq(OpRemovalRequester::Sender))
+            .times(usize::from(removed))
+            .returning(|_, _| Ok(true));
+        (pool, hash, cancellation_digest(hash, ep, 1))
+    }
+
+    fn deployed_sender_provider() -> MockProvider {
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(Bytes::from(vec![1])));
+        provider
+    }
+
+    fn magic_value() -> Bytes {
+        let mut ret = ERC1271_MAGIC_VALUE.to_vec();
+        ret.resize(32, 0);
+        ret.into()
+    }
+
+    fn create_api(
+        mut provider: MockProvider,
+        pool: MockPoolServer,
+        op_status_tracker: Option<Arc<OpStatusTracker>>,
+    ) -> RundlerApi<MockProvider, MockEntryPoint, MockPoolServer, MockBuilderServer> {
+        provider.expect_get_base_fee().returning(|| Ok(100.into()));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(10.into()));
+        let provider = Arc::new(provider);
+        RundlerApi {
+            provider: Arc::clone(&provider),
+            chain_id: 1,
+            fee_estimator: FeeEstimator::new(
+                provider,
+                &ChainSpec::default(),
+                PriorityFeeMode::PriorityFeeIncreasePercent(0),
+                0,
+            ),
+            gas_estimators: HashMap::new(),
+            pool,
+            builder: MockBuilderServer::default(),
+            abi_registry: Arc::new(AbiRegistry::default()),
+            op_status_tracker,
+            user_operation_index: None,
+        }
+    }
 }
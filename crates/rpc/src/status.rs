@@ -0,0 +1,489 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::time::{Duration, Instant};
+
+use ethers::types::{Address, H256};
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use rundler_builder::{BuilderEvent, BuilderEventKind, OpRejectionReason, SkipReason};
+use rundler_pool::{OpRemovalReason, OpRemovalRequester, PoolEvent};
+use rundler_sim::ViolationError;
+use rundler_utils::emit::WithEntryPoint;
+
+use crate::types::RpcStatusReasonCode;
+
+/// Settings for tracking the status of user operations
+#[derive(Clone, Copy, Debug)]
+pub struct OpStatusSettings {
+    /// Maximum number of operations to track
+    pub max_ops: usize,
+    /// How long to keep the status of an operation after its last update
+    pub retention: Duration,
+}
+
+/// Tracks the lifecycle of user operations from pool and builder events.
+///
+/// Operations are forgotten once they have not been updated for the retention
+/// window, or when the maximum number of operations is exceeded, least
+/// recently updated first.
+#[derive(Debug)]
+pub struct OpStatusTracker {
+    settings: OpStatusSettings,
+    ops: Mutex<IndexMap<H256, (OpStatus, Instant)>>,
+}
+
+/// The last known status of an operation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct OpStatus {
+    pub(crate) entry_point: Address,
+    pub(crate) stage: OpStage,
+    /// Block number the operation was added to the pool at
+    pub(crate) received_block_number: Option<u64>,
+    /// The latest bundle the operation was included in
+    pub(crate) bundle: Option<BundleAttempt>,
+    /// Why the builder last skipped the operation, if it did
+    pub(crate) skip_reason: Option<StatusReason>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum OpStage {
+    /// In the pool, waiting to be bundled
+    Pending,
+    /// Included in a bundle that is about to be sent
+    Proposed,
+    /// Included in a bundle transaction that was sent
+    Submitted,
+    Mined {
+        block_number: u64,
+        block_hash: H256,
+        tx_hash: H256,
+    },
+    /// Removed from the pool without being mined
+    Dropped { reason: StatusReason },
+    /// Rejected by the builder and removed from the pool
+    Rejected { reason: StatusReason },
+}
+
+/// Why an operation was skipped, dropped, or rejected, as a stable code and a
+/// message for users
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct StatusReason {
+    pub(crate) code: RpcStatusReasonCode,
+    pub(crate) message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct BundleAttempt {
+    pub(crate) builder_index: u64,
+    pub(crate) nonce: u64,
+    pub(crate) fee_increase_count: u64,
+    /// Hash of the bundle transaction, once sent
+    pub(crate) tx_hash: Option<H256>,
+}
+
+impl OpStatusTracker {
+    /// Create a new tracker
+    pub fn new(settings: OpStatusSettings) -> Self {
+        Self {
+            settings,
+            ops: Mutex::new(IndexMap::new()),
+        }
+    }
+
+    /// Update the status of the operation a pool event is about
+    pub fn on_pool_event(&self, event: &WithEntryPoint<PoolEvent>) {
+        match &event.event {
+            PoolEvent::ReceivedOp {
+                op_hash,
+                block_number,
+                ..
+            } => self.update(*op_hash, event.entry_point, |status| {
+                *status = OpStatus {
+                    received_block_number: Some(*block_number),
+                    ..OpStatus::new(event.entry_point)
+                };
+            }),
+            PoolEvent::RemovedOp { op_hash, reason } => {
+                self.update(*op_hash, event.entry_point, |status| {
+                    status.stage = match reason {
+                        OpRemovalReason::Mined {
+                            block_number,
+                            block_hash,
+                            tx_hash,
+                        } => OpStage::Mined {
+                            block_number: *block_number,
+                            block_hash: *block_hash,
+                            tx_hash: *tx_hash,
+                        },
+                        // the builder removes the ops it rejects
+                        _ if matches!(status.stage, OpStage::Rejected { .. }) => {
+                            return;
+                        }
+                        _ => match StatusReason::dropped(reason) {
+                            Some(reason) => OpStage::Dropped { reason },
+                            None => return,
+                        },
+                    };
+                })
+            }
+            PoolEvent::RemovedEntity { .. } => {}
+        }
+    }
+
+    /// Update the status of the operations a builder event is about
+    pub fn on_builder_event(&self, event: &WithEntryPoint<BuilderEvent>) {
+        let builder_index = event.event.builder_index;
+        match &event.event.kind {
+            BuilderEventKind::ProposedBundle {
+                op_hashes,
+                nonce,
+                fee_increase_count,
+            } => {
+                for op_hash in op_hashes.iter() {
+                    self.update(*op_hash, event.entry_point, |status| {
+                        status.stage = OpStage::Proposed;
+                        status.bundle = Some(BundleAttempt {
+                            builder_index,
+                            nonce: *nonce,
+                            fee_increase_count: *fee_increase_count,
+                            tx_hash: None,
+                        });
+                    });
+                }
+            }
+            BuilderEventKind::FormedBundle {
+                tx_details: Some(tx_details),
+                nonce,
+                fee_increase_count,
+                ..
+            } => {
+                for op_hash in tx_details.op_hashes.iter() {
+                    self.update(*op_hash, event.entry_point, |status| {
+                        status.stage = OpStage::Submitted;
+                        status.bundle = Some(BundleAttempt {
+                            builder_index,
+                            nonce: *nonce,
+                            fee_increase_count: *fee_increase_count,
+                            tx_hash: Some(tx_details.tx_hash),
+                        });
+                    });
+                }
+            }
+            BuilderEventKind::SkippedOp { op_hash, reason } => {
+                self.update(*op_hash, event.entry_point, |status| {
+                    status.skip_reason = Some(StatusReason::skipped(reason));
+                });
+            }
+            BuilderEventKind::RejectedOp { op_hash, reason } => {
+                self.update(*op_hash, event.entry_point, |status| {
+                    status.stage = OpStage::Rejected {
+                        reason: StatusReason::rejected(reason),
+                    };
+                });
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn get(&self, op_hash: H256) -> Option<OpStatus> {
+        let mut ops = self.ops.lock();
+        self.evict(&mut ops);
+        ops.get(&op_hash).map(|(status, _)| status.clone())
+    }
+
+    // Applies an update to the status of an operation, moving it to the back of
+    // the eviction order. Operations first seen in a builder event were added to
+    // the pool before tracking started.
+    fn update(&self, op_hash: H256, entry_point: Address, f: impl FnOnce(&mut OpStatus)) {
+        if self.settings.max_ops == 0 {
+            return;
+        }
+        let mut ops = self.ops.lock();
+        let (mut status, _) = ops
+            .shift_remove(&op_hash)
+            .unwrap_or_else(|| (OpStatus::new(entry_point), Instant::now()));
+        f(&mut status);
+        ops.insert(op_hash, (status, Instant::now()));
+        self.evict(&mut ops);
+    }
+
+    fn evict(&self, ops: &mut IndexMap<H256, (OpStatus, Instant)>) {
+        while let Some((_, (_, updated_at))) = ops.first() {
+            if ops.len() <= self.settings.max_ops && updated_at.elapsed() < self.settings.retention
+            {
+                break;
+            }
+            ops.shift_remove_index(0);
+        }
+    }
+}
+
+impl OpStatus {
+    fn new(entry_point: Address) -> Self {
+        Self {
+            entry_point,
+            stage: OpStage::Pending,
+            received_block_number: None,
+            bundle: None,
+            skip_reason: None,
+        }
+    }
+}
+
+impl StatusReason {
+    fn new(code: RpcStatusReasonCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    // Returns `None` for mined operations, which weren't dropped
+    fn dropped(reason: &OpRemovalReason) -> Option<Self> {
+        let reason = match reason {
+            OpRemovalReason::Requested { requester } => {
+                let requester = match requester {
+                    OpRemovalRequester::Builder => "the bundle builder",
+                    OpRemovalRequester::Sender => "the sender",
+                    OpRemovalRequester::Admin => "an operator",
+                };
+                Self::new(
+                    RpcStatusReasonCode::RemovalRequested,
+                    format!("removal requested by {requester}"),
+                )
+            }
+            OpRemovalReason::Mined { .. } => return None,
+            OpRemovalReason::ThrottledAndOld {
+                added_at_block_number,
+                current_block_number,
+            } => Self::new(
+                RpcStatusReasonCode::ThrottledAndOld,
+                format!(
+                    "an entity of the operation is throttled, and the operation was not mined between blocks {added_at_block_number} and {current_block_number}"
+                ),
+            ),
+            OpRemovalReason::EntityRemoved { entity } => Self::new(
+                RpcStatusReasonCode::EntityRemoved,
+                format!("the operations of {entity} were removed"),
+            ),
+            OpRemovalReason::Expired { valid_until } => Self::new(
+                RpcStatusReasonCode::Expired,
+                format!("expired at {valid_until}"),
+            ),
+        };
+        Some(reason)
+    }
+
+    fn skipped(reason: &SkipReason) -> Self {
+        match reason {
+            SkipReason::AccessedOtherSender { other_sender } => Self::new(
+                RpcStatusReasonCode::AccessedOtherSender,
+                format!("accessed sender {other_sender:?} of another operation in the bundle"),
+            ),
+            SkipReason::InvalidTimeRange { .. } => Self::new(
+                RpcStatusReasonCode::InvalidTimeRange,
+                "outside of its valid time range",
+            ),
+            SkipReason::InsufficientFees {
+                required_fees,
+                actual_fees,
+            } => Self::new(
+                RpcStatusReasonCode::InsufficientFees,
+                format!(
+                    "max fee per gas {} and max priority fee per gas {} are below the required {} and {}",
+                    actual_fees.max_fee_per_gas,
+                    actual_fees.max_priority_fee_per_gas,
+                    required_fees.max_fee_per_gas,
+                    required_fees.max_priority_fee_per_gas,
+                ),
+            ),
+            SkipReason::InsufficientPreVerificationGas {
+                required_pvg,
+                actual_pvg,
+                ..
+            } => Self::new(
+                RpcStatusReasonCode::InsufficientPreVerificationGas,
+                format!(
+                    "pre-verification gas {actual_pvg} is below the required {required_pvg}"
+                ),
+            ),
+            SkipReason::GasLimit => Self::new(
+                RpcStatusReasonCode::GasLimit,
+                "the bundle reached its gas limit",
+            ),
+            // other reasons are internal errors
+            SkipReason::Other { .. } => {
+                Self::new(RpcStatusReasonCode::Other, "internal bundler error")
+            }
+        }
+    }
+
+    fn rejected(reason: &OpRejectionReason) -> Self {
+        match reason {
+            OpRejectionReason::FailedRevalidation { error } => {
+                let message = match &error.violation_error {
+                    violations @ ViolationError::Violations(_) => {
+                        format!("failed validation when bundled: {violations}")
+                    }
+                    // other errors are internal errors
+                    ViolationError::Other(_) => "failed validation when bundled".to_string(),
+                };
+                Self::new(RpcStatusReasonCode::FailedRevalidation, message)
+            }
+            OpRejectionReason::FailedInBundle { message } => Self::new(
+                RpcStatusReasonCode::FailedInBundle,
+                format!("failed in the bundle: {message}"),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rundler_pool::{EntitySummary, OperationOrigin};
+    use rundler_types::{Timestamp, UserOperation};
+
+    use super::*;
+
+    fn tracker(max_ops: usize) -> OpStatusTracker {
+        OpStatusTracker::new(OpStatusSettings {
+            max_ops,
+            retention: Duration::from_secs(60),
+        })
+    }
+
+    fn pool_event(event: PoolEvent) -> WithEntryPoint<PoolEvent> {
+        WithEntryPoint {
+            entry_point: Address::zero(),
+            event,
+        }
+    }
+
+    fn builder_event(kind: BuilderEventKind) -> WithEntryPoint<BuilderEvent> {
+        WithEntryPoint {
+            entry_point: Address::zero(),
+            event: BuilderEvent {
+                builder_index: 1,
+                kind,
+            },
+        }
+    }
+
+    fn receive(tracker: &OpStatusTracker, op_hash: H256) {
+        tracker.on_pool_event(&pool_event(PoolEvent::ReceivedOp {
+            op_hash,
+            op: UserOperation::default(),
+            block_number: 7,
+            origin: OperationOrigin::Local,
+            valid_after: Timestamp::default(),
+            valid_until: Timestamp::default(),
+            entities: EntitySummary::default(),
+        }));
+    }
+
+    #[test]
+    fn test_lifecycle() {
+        let tracker = tracker(10);
+        let op_hash = H256::random();
+        receive(&tracker, op_hash);
+        assert_eq!(tracker.get(op_hash).unwrap().stage, OpStage::Pending);
+        assert_eq!(tracker.get(op_hash).unwrap().received_block_number, Some(7));
+
+        tracker.on_builder_event(&builder_event(BuilderEventKind::ProposedBundle {
+            op_hashes: Arc::new(vec![op_hash]),
+            nonce: 3,
+            fee_increase_count: 1,
+        }));
+        let status = tracker.get(op_hash).unwrap();
+        assert_eq!(status.stage, OpStage::Proposed);
+        assert_eq!(
+            status.bundle,
+            Some(BundleAttempt {
+                builder_index: 1,
+                nonce: 3,
+                fee_increase_count: 1,
+                tx_hash: None,
+            })
+        );
+
+        let (tx_hash, block_hash) = (H256::random(), H256::random());
+        tracker.on_pool_event(&pool_event(PoolEvent::RemovedOp {
+            op_hash,
+            reason: OpRemovalReason::Mined {
+                block_number: 9,
+                block_hash,
+                tx_hash,
+            },
+        }));
+        assert_eq!(
+            tracker.get(op_hash).unwrap().stage,
+            OpStage::Mined {
+                block_number: 9,
+                block_hash,
+                tx_hash,
+            }
+        );
+    }
+
+    #[test]
+    fn test_rejected_op_stays_rejected_when_removed() {
+        let tracker = tracker(10);
+        let op_hash = H256::random();
+        receive(&tracker, op_hash);
+        tracker.on_builder_event(&builder_event(BuilderEventKind::RejectedOp {
+            op_hash,
+            reason: rundler_builder::OpRejectionReason::FailedInBundle {
+                message: Arc::new("AA23 reverted".to_string()),
+            },
+        }));
+        tracker.on_pool_event(&pool_event(PoolEvent::RemovedOp {
+            op_hash,
+            reason: OpRemovalReason::Requested {
+                requester: OpRemovalRequester::Builder,
+            },
+        }));
+        assert_eq!(
+            tracker.get(op_hash).unwrap().stage,
+            OpStage::Rejected {
+                reason: StatusReason {
+                    code: RpcStatusReasonCode::FailedInBundle,
+                    message: "failed in the bundle: AA23 reverted".to_string(),
+                }
+            }
+        );
+    }
+
+    #[test]
+    fn test_evicts_least_recently_updated() {
+        let tracker = tracker(2);
+        let hashes = [H256::random(), H256::random(), H256::random()];
+        receive(&tracker, hashes[0]);
+        receive(&tracker, hashes[1]);
+        // updating the first op makes the second the least recently updated
+        tracker.on_builder_event(&builder_event(BuilderEventKind::SkippedOp {
+            op_hash: hashes[0],
+            reason: rundler_builder::SkipReason::GasLimit,
+        }));
+        receive(&tracker, hashes[2]);
+
+        assert_eq!(
+            tracker.get(hashes[0]).unwrap().skip_reason.unwrap().code,
+            RpcStatusReasonCode::GasLimit
+        );
+        assert!(tracker.get(hashes[1]).is_none());
+        assert!(tracker.get(hashes[2]).is_some());
+    }
+}
@@ -11,7 +11,7 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::{net::SocketAddr, sync::Arc, time::Duration};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 
 use anyhow::bail;
 use async_trait::async_trait;
@@ -24,15 +24,19 @@ use jsonrpsee::{
     RpcModule,
 };
 use rundler_builder::BuilderServer;
-use rundler_pool::PoolServer;
+use rundler_pool::{PoolServer, UserOperationIndex};
 use rundler_provider::EntryPoint;
-use rundler_sim::{EstimationSettings, PrecheckSettings};
+use rundler_sim::{
+    gas, EstimationSettings, PrecheckSettings, SharedMempoolConfigs, SimulateValidationTracerImpl,
+    SimulationSettings, SimulatorImpl,
+};
 use rundler_task::{
     server::{format_socket_addr, HealthCheck},
+    trace::TraceContextLayer,
     Task,
 };
 use rundler_types::contracts::i_entry_point::IEntryPoint;
-use rundler_utils::eth;
+use rundler_utils::{eth, revert::AbiRegistry};
 use tokio_util::sync::CancellationToken;
 use tracing::info;
 
@@ -40,9 +44,12 @@ use crate::{
     admin::{AdminApi, AdminApiServer},
     debug::{DebugApi, DebugApiServer},
     eth::{EthApi, EthApiServer, EthApiSettings},
-    health::{HealthChecker, SystemApiServer},
+    health::{HealthChecker, HealthEndpointLayer, HealthSettings, SystemApiServer},
+    limits::{RpcLimitLayer, RpcLimitSettings},
     metrics::RpcMetricsLogger,
+    partner::PartnerLayer,
     rundler::{RundlerApi, RundlerApiServer},
+    status::OpStatusTracker,
     types::ApiNamespace,
 };
 
@@ -67,8 +74,23 @@ pub struct Args {
     pub eth_api_settings: EthApiSettings,
     /// Estimation settings.
     pub estimation_settings: EstimationSettings,
-    /// RPC timeout.
-    pub rpc_timeout: Duration,
+    /// Simulation settings, used to trace validation.
+    pub sim_settings: SimulationSettings,
+    /// Alternative mempool configurations, used to report allowlist matches
+    /// when tracing validation.
+    pub mempool_configs: SharedMempoolConfigs,
+    /// Registry used to decode revert data in errors and receipts.
+    pub abi_registry: Arc<AbiRegistry>,
+    /// Index of mined user operations to serve lookups by hash from, if enabled.
+    pub user_operation_index: Option<Arc<UserOperationIndex>>,
+    /// Tracker of user operation lifecycles, if enabled.
+    pub op_status_tracker: Option<Arc<OpStatusTracker>>,
+    /// Thresholds for the readiness check.
+    pub health_settings: HealthSettings,
+    /// Request size, batch, timeout, and concurrency limits.
+    pub limit_settings: RpcLimitSettings,
+    /// Partner names by API key, used to boost the priority of partner operations.
+    pub partner_api_keys: HashMap<String, String>,
     /// Max number of connections.
     pub max_connections: u32,
 }
@@ -96,6 +118,7 @@ where
         }
 
         let provider = eth::new_provider(&self.args.rpc_url, None)?;
+        gas::check_l1_gas_oracle(self.args.precheck_settings.chain_spec.l1_gas, &*provider).await?;
         let entry_points = self
             .args
             .entry_points
@@ -104,22 +127,38 @@ where
             .collect();
 
         let mut module = RpcModule::new(());
-        self.attach_namespaces(provider, entry_points, &mut module)?;
+        self.attach_namespaces(provider.clone(), entry_points, &mut module)?;
 
         let servers: Vec<Box<dyn HealthCheck>> =
             vec![Box::new(self.pool.clone()), Box::new(self.builder.clone())];
-        let health_checker = HealthChecker::new(servers);
-        module.merge(health_checker.into_rpc())?;
+        let health_checker = HealthChecker::new(
+            servers,
+            provider,
+            self.pool.clone(),
+            self.builder.clone(),
+            self.args.health_settings,
+        );
+        module.merge(health_checker.clone().into_rpc())?;
 
         // Set up health check endpoint via GET /health registers the jsonrpc handler
         let service_builder = tower::ServiceBuilder::new()
+            // Serve `GET /health/live` and `GET /health/ready` with JSON reports.
+            .layer(HealthEndpointLayer::new(health_checker))
             // Proxy `GET /health` requests to internal `system_health` method.
             .layer(ProxyGetRequestLayer::new("/health", "system_health")?)
-            .timeout(self.args.rpc_timeout);
+            // Continue the caller's trace, if any, in a span per request.
+            .layer(TraceContextLayer::new("rpc"))
+            // Identify the partner making the request from its API key, if any.
+            .layer(PartnerLayer::new(self.args.partner_api_keys.clone()))
+            .layer(RpcLimitLayer::new(
+                self.args.limit_settings.clone(),
+                module.method_names(),
+            ));
 
         let server = ServerBuilder::default()
             .set_logger(RpcMetricsLogger)
             .set_middleware(service_builder)
+            .max_request_body_size(self.args.limit_settings.max_request_body_size)
             .max_connections(self.args.max_connections)
             .http_only()
             .build(addr)
@@ -176,18 +215,50 @@ where
                         self.pool.clone(),
                         self.args.eth_api_settings,
                         self.args.estimation_settings,
-                        self.args.precheck_settings,
+                        self.args.precheck_settings.clone(),
+                        Arc::clone(&self.args.abi_registry),
+                        self.args.user_operation_index.clone(),
                     )
                     .into_rpc(),
                 )?,
-                ApiNamespace::Debug => module
-                    .merge(DebugApi::new(self.pool.clone(), self.builder.clone()).into_rpc())?,
-                ApiNamespace::Admin => module.merge(AdminApi::new(self.pool.clone()).into_rpc())?,
+                ApiNamespace::Debug => {
+                    let simulators = entry_points
+                        .iter()
+                        .map(|entry_point| {
+                            let i_entry_point =
+                                IEntryPoint::new(entry_point.address(), provider.clone());
+                            let simulator = SimulatorImpl::new(
+                                provider.clone(),
+                                i_entry_point.address(),
+                                SimulateValidationTracerImpl::new(provider.clone(), i_entry_point),
+                                self.args.sim_settings,
+                                self.args.mempool_configs.clone(),
+                            )
+                            .with_allowed_precompiles(
+                                &self.args.precheck_settings.chain_spec.precompile_addresses,
+                            )
+                            .with_abi_registry(Arc::clone(&self.args.abi_registry));
+                            (entry_point.address(), simulator)
+                        })
+                        .collect();
+                    module.merge(
+                        DebugApi::new(self.pool.clone(), self.builder.clone(), simulators)
+                            .into_rpc(),
+                    )?
+                }
+                ApiNamespace::Admin => module
+                    .merge(AdminApi::new(self.pool.clone(), self.builder.clone()).into_rpc())?,
                 ApiNamespace::Rundler => module.merge(
                     RundlerApi::new(
                         provider.clone(),
-                        self.args.chain_id,
-                        self.args.precheck_settings,
+                        entry_points.clone(),
+                        self.pool.clone(),
+                        self.builder.clone(),
+                        self.args.precheck_settings.clone(),
+                        self.args.estimation_settings,
+                        Arc::clone(&self.args.abi_registry),
+                        self.args.op_status_tracker.clone(),
+                        self.args.user_operation_index.clone(),
                     )
                     .into_rpc(),
                 )?,
@@ -15,10 +15,16 @@ use ethers::{
     types::{Address, Bytes, Log, TransactionReceipt, H160, H256, U256},
     utils::to_checksum,
 };
-use rundler_pool::{Reputation, ReputationStatus};
-use rundler_types::UserOperation;
+use rundler_pool::{AddressList, MempoolFilter, PaymasterStatus, Reputation, ReputationStatus};
+use rundler_sim::{
+    AllowlistMatch, FeeTiers, PhaseReport, StorageAccessReport, ValidationReport, ViolationReport,
+};
+use rundler_types::{Entity, EntityType, GasFees, UserOperation};
+use rundler_utils::revert::DecodedRevert;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
+use crate::status::{OpStage, OpStatus, StatusReason};
+
 /// API namespace
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumString)]
 #[strum(serialize_all = "lowercase", ascii_case_insensitive)]
@@ -170,12 +176,84 @@ pub struct UserOperationReceipt {
     pub success: bool,
     /// If not successful, the revert reason string
     pub reason: String,
+    /// If not successful and the revert data could be decoded, the decoded revert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded_reason: Option<DecodedRevert>,
     /// Logs emitted by this operation
     pub logs: Vec<Log>,
     /// The receipt of the transaction that included this operation
     pub receipt: TransactionReceipt,
 }
 
+/// Filter and page of the operations to dump from the mempool. Unset filter
+/// fields match every operation.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcMempoolFilter {
+    /// Sender of the operation
+    pub sender: Option<Address>,
+    /// Paymaster of the operation
+    pub paymaster: Option<Address>,
+    /// Factory of the operation
+    pub factory: Option<Address>,
+    /// Aggregator of the operation
+    pub aggregator: Option<Address>,
+    /// Minimum max fee per gas, inclusive
+    pub min_max_fee_per_gas: Option<U256>,
+    /// Maximum max fee per gas, inclusive
+    pub max_max_fee_per_gas: Option<U256>,
+    /// Minimum max priority fee per gas, inclusive
+    pub min_max_priority_fee_per_gas: Option<U256>,
+    /// Maximum max priority fee per gas, inclusive
+    pub max_max_priority_fee_per_gas: Option<U256>,
+    /// Earliest block the operation was simulated at, inclusive
+    pub min_block_number: Option<u64>,
+    /// Latest block the operation was simulated at, inclusive
+    pub max_block_number: Option<u64>,
+    /// Number of matching operations to skip
+    pub offset: Option<u64>,
+    /// Maximum number of operations to return
+    pub limit: Option<u64>,
+}
+
+impl From<&RpcMempoolFilter> for MempoolFilter {
+    fn from(filter: &RpcMempoolFilter) -> Self {
+        MempoolFilter {
+            sender: filter.sender,
+            paymaster: filter.paymaster,
+            factory: filter.factory,
+            aggregator: filter.aggregator,
+            min_max_fee_per_gas: filter.min_max_fee_per_gas,
+            max_max_fee_per_gas: filter.max_max_fee_per_gas,
+            min_max_priority_fee_per_gas: filter.min_max_priority_fee_per_gas,
+            max_max_priority_fee_per_gas: filter.max_max_priority_fee_per_gas,
+            min_sim_block_number: filter.min_block_number,
+            max_sim_block_number: filter.max_block_number,
+        }
+    }
+}
+
+/// A page of the operations matching a mempool filter
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcMempoolPage {
+    /// Operations in the page, in mempool order
+    pub ops: Vec<RpcUserOperation>,
+    /// Total number of operations matching the filter
+    pub total: u64,
+}
+
+/// Result of `debug_bundler_dumpMempool`: the whole mempool, or a page of it
+/// when a filter is given
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum RpcMempoolDump {
+    /// Every operation in the mempool
+    All(Vec<RpcUserOperation>),
+    /// A page of the operations matching the filter
+    Page(RpcMempoolPage),
+}
+
 /// Reputation of an entity
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -208,6 +286,7 @@ impl From<RpcReputationInput> for Reputation {
             address: rpc_reputation.address,
             ops_seen: rpc_reputation.ops_seen.as_u64(),
             ops_included: rpc_reputation.ops_included.as_u64(),
+            kinds: vec![],
         }
     }
 }
@@ -236,6 +315,25 @@ pub struct RpcAdminSetTracking {
     pub reputation_tracking: bool,
 }
 
+/// Address list that overrides the reputation of its entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcAddressList {
+    /// Addresses that are always banned
+    Blocklist,
+    /// Addresses that are always exempt from throttling and banning
+    Allowlist,
+}
+
+impl From<RpcAddressList> for AddressList {
+    fn from(list: RpcAddressList) -> Self {
+        match list {
+            RpcAddressList::Blocklist => AddressList::Blocklist,
+            RpcAddressList::Allowlist => AddressList::Allowlist,
+        }
+    }
+}
+
 /// Reputation of an entity
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -247,3 +345,531 @@ pub struct RpcAdminClearState {
     /// Field to set whether to clear reputation state
     pub clear_reputation: bool,
 }
+
+/// EIP-1559 gas fees for a user operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcGasFees {
+    /// Max fee per gas
+    pub max_fee_per_gas: U256,
+    /// Max priority fee per gas
+    pub max_priority_fee_per_gas: U256,
+}
+
+impl From<GasFees> for RpcGasFees {
+    fn from(fees: GasFees) -> Self {
+        RpcGasFees {
+            max_fee_per_gas: fees.max_fee_per_gas,
+            max_priority_fee_per_gas: fees.max_priority_fee_per_gas,
+        }
+    }
+}
+
+/// Recommended user operation gas prices for increasing speeds of inclusion
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcUserOperationGasPrice {
+    /// Fees at the minimum priority fee accepted by the bundler
+    pub slow: RpcGasFees,
+    /// Fees at the median recent priority fee
+    pub standard: RpcGasFees,
+    /// Fees at a high recent priority fee
+    pub fast: RpcGasFees,
+    /// The current base fee
+    pub base_fee: U256,
+    /// The average number of blocks recent bundles took to be mined
+    pub inclusion_latency_blocks: u64,
+}
+
+impl RpcUserOperationGasPrice {
+    pub(crate) fn new(tiers: FeeTiers, inclusion_latency_blocks: u64) -> Self {
+        Self {
+            slow: tiers.slow.into(),
+            standard: tiers.standard.into(),
+            fast: tiers.fast.into(),
+            base_fee: tiers.base_fee,
+            inclusion_latency_blocks,
+        }
+    }
+}
+
+/// Balances and spending of a paymaster as tracked by the pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPaymasterStatus {
+    /// Paymaster address
+    pub address: Address,
+    /// The paymaster's deposit in the entry point
+    pub confirmed_balance: U256,
+    /// The confirmed balance less the max cost of the paymaster's pending operations
+    pub pending_balance: U256,
+    /// Number of operations in the pool using the paymaster
+    pub pending_ops: u64,
+    /// Gas costs charged to the paymaster per hour over the pool's spend window
+    pub spend_per_hour: U256,
+    /// Seconds until the pending balance runs out at the current spend rate,
+    /// or null if the paymaster has not spent anything in the spend window
+    pub runway_seconds: Option<u64>,
+}
+
+impl From<PaymasterStatus> for RpcPaymasterStatus {
+    fn from(status: PaymasterStatus) -> Self {
+        RpcPaymasterStatus {
+            address: status.address,
+            confirmed_balance: status.confirmed_balance,
+            pending_balance: status.pending_balance,
+            pending_ops: status.pending_ops,
+            spend_per_hour: status.spend_per_hour,
+            runway_seconds: status.runway_seconds,
+        }
+    }
+}
+
+/// Lifecycle stage of a user operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcUserOperationStatusKind {
+    /// Not known to the bundler
+    Unknown,
+    /// In the pool, waiting to be bundled
+    Pending,
+    /// Included in a bundle that is about to be sent
+    Proposed,
+    /// Included in a bundle transaction that was sent
+    Submitted,
+    /// Mined on chain
+    Mined,
+    /// Removed from the pool without being mined
+    Dropped,
+    /// Rejected by the builder and removed from the pool
+    Rejected,
+}
+
+/// Lifecycle status of a user operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcUserOperationStatus {
+    /// The stage the operation is in
+    pub status: RpcUserOperationStatusKind,
+    /// Entry point of the operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entry_point: Option<RpcAddress>,
+    /// Block number the operation was added to the pool at
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub received_block_number: Option<u64>,
+    /// Where the operation stands in the pool, if it is in the pool
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool: Option<RpcPoolStatus>,
+    /// The latest bundle the operation was included in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bundle: Option<RpcBundleStatus>,
+    /// Why the builder last skipped the operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skip_reason: Option<RpcStatusReason>,
+    /// Where the operation was mined
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mined: Option<RpcMinedStatus>,
+    /// Why the operation was dropped or rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<RpcStatusReason>,
+}
+
+/// Why a user operation was skipped, dropped, or rejected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStatusReason {
+    /// Stable code of the reason
+    pub code: RpcStatusReasonCode,
+    /// Human-readable description of the reason, which may change between
+    /// versions
+    pub message: String,
+}
+
+/// Stable code of why a user operation was skipped, dropped, or rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RpcStatusReasonCode {
+    /// Removal was requested by the builder, the sender, or an operator
+    RemovalRequested,
+    /// An entity of the operation is throttled and the operation was too old
+    ThrottledAndOld,
+    /// All the operations of one of its entities were removed
+    EntityRemoved,
+    /// The operation's valid time range ended
+    Expired,
+    /// Accessed the sender of another operation in the bundle
+    AccessedOtherSender,
+    /// Outside of the operation's valid time range
+    InvalidTimeRange,
+    /// Fees below those required for the bundle
+    InsufficientFees,
+    /// Pre-verification gas below that required at the current fees
+    InsufficientPreVerificationGas,
+    /// The bundle reached its gas limit
+    GasLimit,
+    /// Failed validation when simulated again for a bundle
+    FailedRevalidation,
+    /// Failed when the bundle was simulated
+    FailedInBundle,
+    /// An internal error
+    Other,
+}
+
+impl From<StatusReason> for RpcStatusReason {
+    fn from(reason: StatusReason) -> Self {
+        Self {
+            code: reason.code,
+            message: reason.message,
+        }
+    }
+}
+
+/// Where a user operation stands in the pool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPoolStatus {
+    /// Position of the operation in the pool's ordering, starting at 0
+    pub position: u64,
+    /// Number of operations in the pool
+    pub pool_size: u64,
+    /// The operation's max fee per gas
+    pub max_fee_per_gas: U256,
+    /// The operation's max priority fee per gas
+    pub max_priority_fee_per_gas: U256,
+    /// Fees currently required for an operation to be bundled
+    pub required_fees: RpcGasFees,
+    /// Whether the operation pays at least the required fees
+    pub competitive: bool,
+}
+
+/// A bundle a user operation was included in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcBundleStatus {
+    /// Index of the builder that formed the bundle
+    pub builder_index: u64,
+    /// Nonce of the bundle transaction
+    pub nonce: u64,
+    /// Number of times the bundle's fees were increased
+    pub fee_increase_count: u64,
+    /// Hash of the bundle transaction, once sent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction_hash: Option<H256>,
+}
+
+/// Where a user operation was mined
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcMinedStatus {
+    /// Block number
+    pub block_number: u64,
+    /// Block hash
+    pub block_hash: H256,
+    /// Transaction hash
+    pub transaction_hash: H256,
+}
+
+impl RpcUserOperationStatus {
+    pub(crate) fn unknown() -> Self {
+        Self {
+            status: RpcUserOperationStatusKind::Unknown,
+            entry_point: None,
+            received_block_number: None,
+            pool: None,
+            bundle: None,
+            skip_reason: None,
+            mined: None,
+            reason: None,
+        }
+    }
+}
+
+impl From<OpStatus> for RpcUserOperationStatus {
+    fn from(status: OpStatus) -> Self {
+        let (kind, mined, reason) = match status.stage {
+            OpStage::Pending => (RpcUserOperationStatusKind::Pending, None, None),
+            OpStage::Proposed => (RpcUserOperationStatusKind::Proposed, None, None),
+            OpStage::Submitted => (RpcUserOperationStatusKind::Submitted, None, None),
+            OpStage::Mined {
+                block_number,
+                block_hash,
+                tx_hash,
+            } => (
+                RpcUserOperationStatusKind::Mined,
+                Some(RpcMinedStatus {
+                    block_number,
+                    block_hash,
+                    transaction_hash: tx_hash,
+                }),
+                None,
+            ),
+            OpStage::Dropped { reason } => (
+                RpcUserOperationStatusKind::Dropped,
+                None,
+                Some(reason.into()),
+            ),
+            OpStage::Rejected { reason } => (
+                RpcUserOperationStatusKind::Rejected,
+                None,
+                Some(reason.into()),
+            ),
+        };
+        Self {
+            status: kind,
+            entry_point: Some(status.entry_point.into()),
+            received_block_number: status.received_block_number,
+            pool: None,
+            bundle: status.bundle.map(|bundle| RpcBundleStatus {
+                builder_index: bundle.builder_index,
+                nonce: bundle.nonce,
+                fee_increase_count: bundle.fee_increase_count,
+                transaction_hash: bundle.tx_hash,
+            }),
+            skip_reason: status.skip_reason.map(Into::into),
+            mined,
+            reason,
+        }
+    }
+}
+
+/// A report of the validation of a user operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcValidationReport {
+    /// Block hash the operation was simulated against
+    pub block_hash: H256,
+    /// The entities of the operation and whether they are staked
+    pub entities: Vec<RpcValidationEntity>,
+    /// The validation phases run by the entry point, in order
+    pub phases: Vec<RpcPhaseReport>,
+    /// All violations found during validation
+    pub violations: Vec<RpcViolationReport>,
+    /// The mempools that allow all of the violations. Empty if the operation
+    /// is only valid in the canonical mempool, or in no mempool at all.
+    pub mempools: Vec<H256>,
+}
+
+/// An entity of a user operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcValidationEntity {
+    /// Type of the entity
+    pub kind: EntityType,
+    /// Address of the entity
+    pub address: Address,
+    /// Whether the entity is staked
+    pub is_staked: bool,
+}
+
+/// What a single validation phase did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcPhaseReport {
+    /// The entity validated in this phase
+    pub entity: EntityType,
+    /// Address of the entity, if the operation uses it
+    pub address: Option<Address>,
+    /// Gas used by the phase
+    pub gas_used: u64,
+    /// Number of opcodes executed by the entity and the contracts it called
+    pub opcode_count: u64,
+    /// Forbidden opcodes used
+    pub forbidden_opcodes_used: Vec<RpcContractOpcode>,
+    /// Forbidden precompiles used
+    pub forbidden_precompiles_used: Vec<RpcContractPrecompile>,
+    /// Storage slots accessed, by contract
+    pub storage_accesses: Vec<RpcStorageAccess>,
+    /// Contracts whose code was accessed, and the opcode used
+    pub ext_code_accesses: Vec<RpcContractOpcode>,
+    /// Addresses without code that were accessed
+    pub undeployed_contract_accesses: Vec<Address>,
+    /// Addresses that made calls with value
+    pub addresses_calling_with_value: Vec<Address>,
+    /// Whether a call with value was made to a contract other than the entry point
+    pub called_non_entry_point_with_value: bool,
+    /// Whether an entry point method other than `depositTo` was called
+    pub called_banned_entry_point_method: bool,
+    /// Whether the phase ran out of gas
+    pub ran_out_of_gas: bool,
+}
+
+/// An opcode used by a contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcContractOpcode {
+    /// The contract that used the opcode
+    pub contract: Address,
+    /// Name of the opcode
+    pub opcode: String,
+}
+
+/// A precompile called by a contract
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcContractPrecompile {
+    /// The contract that called the precompile
+    pub contract: Address,
+    /// Address of the precompile
+    pub precompile: Address,
+}
+
+/// The storage slots of a contract accessed in a phase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStorageAccess {
+    /// The contract whose storage was accessed
+    pub contract: Address,
+    /// Slots read, with their values before the operation
+    pub reads: Vec<RpcStorageRead>,
+    /// Slots written
+    pub writes: Vec<U256>,
+}
+
+/// A storage slot read and its value
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcStorageRead {
+    /// The slot
+    pub slot: U256,
+    /// The value of the slot before the operation
+    pub value: String,
+}
+
+/// A violation found during validation and how it could be allowed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcViolationReport {
+    /// Description of the violation
+    pub message: String,
+    /// Type of the entity responsible for the violation, if known
+    pub entity: Option<EntityType>,
+    /// Address of the entity responsible for the violation, if known
+    pub entity_address: Option<Address>,
+    /// The offending contract, if any
+    pub contract: Option<Address>,
+    /// The offending storage slot, if any
+    pub slot: Option<U256>,
+    /// Name of the offending opcode, if any
+    pub opcode: Option<String>,
+    /// The offending precompile, if any
+    pub precompile: Option<Address>,
+    /// Names of the allowlist rules that can allow the violation. Empty if the
+    /// violation cannot be allowlisted.
+    pub allow_rules: Vec<String>,
+    /// The configured allowlist entries that allow the violation
+    pub allowed_by: Vec<RpcAllowlistMatch>,
+}
+
+/// A mempool allowlist entry that allows a violation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcAllowlistMatch {
+    /// ID of the mempool
+    pub mempool: H256,
+    /// Index of the entry in the mempool's allowlist
+    pub index: usize,
+    /// Name of the entry's rule
+    pub rule: String,
+    /// Description of the entry, if any
+    pub description: Option<String>,
+}
+
+impl From<ValidationReport> for RpcValidationReport {
+    fn from(report: ValidationReport) -> Self {
+        Self {
+            block_hash: report.block_hash,
+            entities: report
+                .entity_infos
+                .map(|infos| {
+                    infos
+                        .entities()
+                        .map(|(kind, info)| RpcValidationEntity {
+                            kind,
+                            address: info.address,
+                            is_staked: info.is_staked,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
+            phases: report.phases.into_iter().map(Into::into).collect(),
+            violations: report.violations.into_iter().map(Into::into).collect(),
+            mempools: report.mempools,
+        }
+    }
+}
+
+impl From<PhaseReport> for RpcPhaseReport {
+    fn from(phase: PhaseReport) -> Self {
+        let opcodes = |accesses: Vec<_>| {
+            accesses
+                .into_iter()
+                .map(|(contract, opcode)| RpcContractOpcode {
+                    contract,
+                    opcode: format!("{opcode:?}"),
+                })
+                .collect()
+        };
+        Self {
+            entity: phase.entity,
+            address: phase.address,
+            gas_used: phase.gas_used,
+            opcode_count: phase.opcode_count,
+            forbidden_opcodes_used: opcodes(phase.forbidden_opcodes_used),
+            forbidden_precompiles_used: phase
+                .forbidden_precompiles_used
+                .into_iter()
+                .map(|(contract, precompile)| RpcContractPrecompile {
+                    contract,
+                    precompile,
+                })
+                .collect(),
+            storage_accesses: phase
+                .storage_accesses
+                .into_iter()
+                .map(
+                    |(contract, StorageAccessReport { reads, writes })| RpcStorageAccess {
+                        contract,
+                        reads: reads
+                            .into_iter()
+                            .map(|(slot, value)| RpcStorageRead { slot, value })
+                            .collect(),
+                        writes,
+                    },
+                )
+                .collect(),
+            ext_code_accesses: opcodes(phase.ext_code_accesses),
+            undeployed_contract_accesses: phase.undeployed_contract_accesses,
+            addresses_calling_with_value: phase.addresses_calling_with_value,
+            called_non_entry_point_with_value: phase.called_non_entry_point_with_value,
+            called_banned_entry_point_method: phase.called_banned_entry_point_method,
+            ran_out_of_gas: phase.ran_out_of_gas,
+        }
+    }
+}
+
+impl From<ViolationReport> for RpcViolationReport {
+    fn from(report: ViolationReport) -> Self {
+        Self {
+            message: report.violation.to_string(),
+            entity: report.entity.map(|Entity { kind, .. }| kind),
+            entity_address: report.entity.map(|Entity { address, .. }| address),
+            contract: report.contract,
+            slot: report.slot,
+            opcode: report.opcode.map(|opcode| format!("{opcode:?}")),
+            precompile: report.precompile,
+            allow_rules: report.allow_rules.into_iter().map(String::from).collect(),
+            allowed_by: report.allowed_by.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl From<AllowlistMatch> for RpcAllowlistMatch {
+    fn from(allowed: AllowlistMatch) -> Self {
+        Self {
+            mempool: allowed.mempool,
+            index: allowed.index,
+            rule: allowed.rule.to_string(),
+            description: allowed.description,
+        }
+    }
+}
@@ -15,16 +15,18 @@ use std::{cmp, ops::Deref, sync::Arc};
 
 use anyhow::{anyhow, Context};
 use ethers::{
-    abi::AbiDecode,
+    abi::{self, AbiDecode, AbiEncode, Token},
     contract::EthCall,
     providers::spoof,
-    types::{Address, Bytes, H256, U256},
+    types::{Address, BlockId, Bytes, Eip1559TransactionRequest, H256, U256},
+    utils::{get_contract_address, keccak256},
 };
 #[cfg(feature = "test-utils")]
 use mockall::automock;
 use rand::Rng;
 use rundler_provider::{EntryPoint, Provider};
 use rundler_types::{
+    chain::L1GasConfig,
     contracts::{
         call_gas_estimation_proxy::{
             EstimateCallGasArgs, EstimateCallGasCall, EstimateCallGasContinuation,
@@ -32,14 +34,23 @@ use rundler_types::{
             CALLGASESTIMATIONPROXY_DEPLOYED_BYTECODE,
         },
         i_entry_point,
+        sender_creator::CreateSenderCall,
     },
     UserOperation,
 };
 use rundler_utils::{eth, math};
 use tokio::join;
+use tracing::instrument;
 
-use super::types::{GasEstimate, Settings, UserOperationOptionalGas};
-use crate::{gas, precheck::MIN_CALL_GAS_LIMIT, utils, FeeEstimator};
+use super::types::{
+    GasBreakdown, GasEstimate, GasEstimateWithBreakdown, Settings, UserOperationOptionalGas,
+};
+use crate::{
+    gas::{self, L1GasCalculator},
+    precheck::MIN_CALL_GAS_LIMIT,
+    simulation::SimulateValidationTracer,
+    utils, FeeEstimator,
+};
 
 /// Gas estimates will be rounded up to the next multiple of this. Increasing
 /// this value reduces the number of rounds of `eth_call` needed in binary
@@ -60,6 +71,10 @@ const VERIFICATION_GAS_BUFFER_PERCENT: u64 = 10;
 /// failure will tell you the new value.
 const PROXY_TARGET_OFFSET: usize = 137;
 
+/// Storage slot of the entry point's `nonceSequenceNumber` mapping, following
+/// the `deposits` mapping of `StakeManager`.
+const NONCE_SEQUENCE_NUMBER_SLOT: u64 = 1;
+
 /// Error type for gas estimation
 #[derive(Debug, thiserror::Error)]
 pub enum GasEstimationError {
@@ -72,6 +87,14 @@ pub enum GasEstimationError {
     /// Call reverted with bytes
     #[error("user operation's call reverted: {0:#x}")]
     RevertInCallWithBytes(Bytes),
+    /// Estimation of an operation in a batch failed
+    #[error("user operation {index} in batch: {error}")]
+    InBatch {
+        /// Index of the failed operation in the batch
+        index: usize,
+        /// Error estimating the operation
+        error: Box<GasEstimationError>,
+    },
     /// Other error
     #[error(transparent)]
     Other(#[from] anyhow::Error),
@@ -88,33 +111,226 @@ pub trait GasEstimator: Send + Sync + 'static {
         op: UserOperationOptionalGas,
         state_override: spoof::State,
     ) -> Result<GasEstimate, GasEstimationError>;
+
+    /// Returns gas estimates for a sequence of operations, estimating each
+    /// operation against the state left by the operations before it.
+    ///
+    /// Only the nonce increment and the sender deployment of each operation
+    /// are applied to the state, the effects of its call are not. Operations
+    /// of a sender deployed earlier in the batch are estimated without the
+    /// deployment.
+    async fn estimate_op_gas_batch(
+        &self,
+        ops: Vec<UserOperationOptionalGas>,
+        state_override: spoof::State,
+    ) -> Result<Vec<GasEstimate>, GasEstimationError>;
+
+    /// Returns a gas estimate along with a breakdown of the gas used by each
+    /// verification phase, including the paymaster's `postOp`.
+    async fn estimate_op_gas_with_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> Result<GasEstimateWithBreakdown, GasEstimationError>;
 }
 
 /// Gas estimator implementation
 #[derive(Debug)]
-pub struct GasEstimatorImpl<P, E> {
-    chain_id: u64,
+pub struct GasEstimatorImpl<P, E, T> {
     provider: Arc<P>,
     entry_point: E,
     settings: Settings,
     fee_estimator: FeeEstimator<P>,
+    tracer: T,
+    l1_gas_calculator: Arc<dyn L1GasCalculator>,
 }
 
 #[async_trait::async_trait]
-impl<P: Provider, E: EntryPoint> GasEstimator for GasEstimatorImpl<P, E> {
+impl<P, E, T> GasEstimator for GasEstimatorImpl<P, E, T>
+where
+    P: Provider,
+    E: EntryPoint,
+    T: SimulateValidationTracer,
+{
+    #[instrument(skip_all, fields(sender = ?op.sender, nonce = %op.nonce))]
     async fn estimate_op_gas(
         &self,
         op: UserOperationOptionalGas,
         state_override: spoof::State,
     ) -> Result<GasEstimate, GasEstimationError> {
-        let Self {
-            provider, settings, ..
-        } = self;
+        let (estimate, _) = self
+            .estimate_op_gas_with_post_op(op, state_override, None)
+            .await?;
+        Ok(estimate)
+    }
+
+    async fn estimate_op_gas_with_breakdown(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+    ) -> Result<GasEstimateWithBreakdown, GasEstimationError> {
+        let (estimate, paymaster_post_op_gas) = self
+            .estimate_op_gas_with_post_op(op.clone(), state_override, None)
+            .await?;
 
-        let (block_hash, _) = provider
+        // Trace validation with the estimated limits to measure the gas used
+        // between the entry point's phase boundaries
+        let op = UserOperation {
+            pre_verification_gas: estimate.pre_verification_gas,
+            verification_gas_limit: estimate.verification_gas_limit,
+            call_gas_limit: estimate.call_gas_limit,
+            ..op.into_user_operation(&self.settings)
+        };
+        let (block_hash, _) = self
+            .provider
             .get_latest_block_hash_and_number()
             .await
             .map_err(anyhow::Error::from)?;
+        let tracer_out = self
+            .tracer
+            .trace_simulate_validation(
+                op.clone(),
+                BlockId::Hash(block_hash),
+                self.settings.max_verification_gas,
+            )
+            .await?;
+        let phase_gas = |index: usize| {
+            tracer_out
+                .phases
+                .get(index)
+                .map_or(U256::zero(), |phase| phase.gas_used.into())
+        };
+
+        let breakdown = GasBreakdown {
+            factory_deployment_gas: if op.init_code.is_empty() {
+                U256::zero()
+            } else {
+                phase_gas(0)
+            },
+            account_validation_gas: phase_gas(1),
+            paymaster_validation_gas: if op.paymaster_and_data.is_empty() {
+                U256::zero()
+            } else {
+                phase_gas(2)
+            },
+            paymaster_post_op_gas,
+        };
+
+        Ok(GasEstimateWithBreakdown {
+            estimate,
+            breakdown,
+        })
+    }
+
+    async fn estimate_op_gas_batch(
+        &self,
+        ops: Vec<UserOperationOptionalGas>,
+        mut state_override: spoof::State,
+    ) -> Result<Vec<GasEstimate>, GasEstimationError> {
+        // Estimate all operations against the same block so that the state
+        // changes applied for earlier operations stay consistent
+        let (block_hash, _) = self
+            .provider
+            .get_latest_block_hash_and_number()
+            .await
+            .map_err(anyhow::Error::from)?;
+        let mut estimates = Vec::with_capacity(ops.len());
+
+        for (index, op) in ops.into_iter().enumerate() {
+            let (sender, nonce) = (op.sender, op.nonce);
+            let init_code = op.init_code.clone();
+            let in_batch = |error: GasEstimationError| GasEstimationError::InBatch {
+                index,
+                error: Box::new(error),
+            };
+
+            let (estimate, _) = self
+                .estimate_op_gas_with_post_op(op, state_override.clone(), Some(block_hash))
+                .await
+                .map_err(in_batch)?;
+            estimates.push(estimate);
+
+            // Deploy the sender for the following operations
+            if !init_code.is_empty() {
+                state_override = self
+                    .deploy_sender(init_code, block_hash, state_override)
+                    .await
+                    .map_err(in_batch)?;
+            }
+
+            // Mark the nonce as used for the following operations
+            let (slot, value) = next_nonce_storage(sender, nonce);
+            state_override
+                .account(self.entry_point.address())
+                .store(slot, value);
+        }
+
+        Ok(estimates)
+    }
+}
+
+impl<P, E, T> GasEstimatorImpl<P, E, T>
+where
+    P: Provider,
+    E: EntryPoint,
+    T: SimulateValidationTracer,
+{
+    /// Create a new gas estimator
+    pub fn new(
+        l1_gas: L1GasConfig,
+        provider: Arc<P>,
+        entry_point: E,
+        settings: Settings,
+        fee_estimator: FeeEstimator<P>,
+        tracer: T,
+    ) -> Self {
+        Self {
+            l1_gas_calculator: gas::new_l1_gas_calculator(l1_gas, Arc::clone(&provider)),
+            provider,
+            entry_point,
+            settings,
+            fee_estimator,
+            tracer,
+        }
+    }
+
+    /// Returns a gas estimate of an operation simulated against the state of a
+    /// block rather than the latest block. Fees are still the current fees.
+    pub async fn estimate_op_gas_at_block(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+        block_hash: H256,
+    ) -> Result<GasEstimate, GasEstimationError> {
+        let (estimate, _) = self
+            .estimate_op_gas_with_post_op(op, state_override, Some(block_hash))
+            .await?;
+        Ok(estimate)
+    }
+
+    // Estimates an operation, returning the estimate and the gas used by the
+    // paymaster's `postOp`. Simulations run at `block_hash`, or the latest
+    // block if it is not set.
+    async fn estimate_op_gas_with_post_op(
+        &self,
+        op: UserOperationOptionalGas,
+        state_override: spoof::State,
+        block_hash: Option<H256>,
+    ) -> Result<(GasEstimate, U256), GasEstimationError> {
+        let Self {
+            provider, settings, ..
+        } = self;
+
+        let block_hash = match block_hash {
+            Some(block_hash) => block_hash,
+            None => {
+                provider
+                    .get_latest_block_hash_and_number()
+                    .await
+                    .map_err(anyhow::Error::from)?
+                    .0
+            }
+        };
 
         // Estimate pre verification gas at the current fees
         // If the user provides fees, use them, otherwise use the current bundle fees
@@ -150,7 +366,13 @@ impl<P: Provider, E: EntryPoint> GasEstimator for GasEstimatorImpl<P, E> {
             return Err(GasEstimationError::RevertInValidation(err));
         }
 
-        Ok(GasEstimate {
+        // `postOp` is limited by the verification gas limit
+        let post_op_gas = self
+            .estimate_post_op_gas(&op, block_hash, &state_override)
+            .await?;
+        let verification_gas_limit = verification_gas_limit.max(post_op_gas);
+
+        let estimate = GasEstimate {
             pre_verification_gas,
             verification_gas_limit: math::increase_by_percent(
                 verification_gas_limit,
@@ -158,26 +380,80 @@ impl<P: Provider, E: EntryPoint> GasEstimator for GasEstimatorImpl<P, E> {
             )
             .min(settings.max_verification_gas.into()),
             call_gas_limit: call_gas_limit.clamp(MIN_CALL_GAS_LIMIT, settings.max_call_gas.into()),
-        })
+        };
+        Ok((estimate, post_op_gas))
     }
-}
 
-impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
-    /// Create a new gas estimator
-    pub fn new(
-        chain_id: u64,
-        provider: Arc<P>,
-        entry_point: E,
-        settings: Settings,
-        fee_estimator: FeeEstimator<P>,
-    ) -> Self {
-        Self {
-            chain_id,
-            provider,
-            entry_point,
-            settings,
-            fee_estimator,
+    // Returns the state override with the sender of `init_code` deployed, by
+    // tracing the deployment through the entry point's sender creator as the
+    // entry point would run it
+    async fn deploy_sender(
+        &self,
+        init_code: Bytes,
+        block_hash: H256,
+        state_override: spoof::State,
+    ) -> Result<spoof::State, GasEstimationError> {
+        let entry_point = self.entry_point.address();
+        let tx = Eip1559TransactionRequest::new()
+            .from(entry_point)
+            .to(sender_creator_address(entry_point))
+            .gas(self.settings.max_verification_gas)
+            .data(CreateSenderCall { init_code }.encode());
+        let state_override = self
+            .provider
+            .apply_call_state_changes(tx.into(), block_hash, state_override)
+            .await
+            .context("should deploy the sender of an earlier operation")?;
+        Ok(state_override)
+    }
+
+    // Measures the gas used by the paymaster's `postOp` by simulating the
+    // operation without its call. The gas charged beyond the pre-op gas is
+    // the gas used by `postOp` plus a small entry point overhead.
+    async fn estimate_post_op_gas(
+        &self,
+        op: &UserOperation,
+        block_hash: H256,
+        state_override: &spoof::State,
+    ) -> Result<U256, GasEstimationError> {
+        if op.paymaster_and_data.is_empty() {
+            return Ok(U256::zero());
         }
+
+        // Use the same constant gas fee as verification gas estimation so
+        // that fee payments in `postOp` are triggered
+        let verification_gas_limit = U256::from(self.settings.max_verification_gas);
+        let gas_price = U256::from(self.settings.validation_estimation_gas_fee)
+            .checked_div(verification_gas_limit + op.pre_verification_gas)
+            .unwrap_or(U256::MAX);
+        if gas_price.is_zero() {
+            return Err(anyhow!(
+                "validation_estimation_gas_fee is too low to estimate postOp gas"
+            ))?;
+        }
+
+        let op = UserOperation {
+            call_data: Bytes::new(),
+            call_gas_limit: 0.into(),
+            verification_gas_limit,
+            max_fee_per_gas: gas_price,
+            max_priority_fee_per_gas: gas_price,
+            ..op.clone()
+        };
+        let result = self
+            .entry_point
+            .call_spoofed_simulate_op(
+                op,
+                Address::zero(),
+                Bytes::new(),
+                block_hash,
+                self.settings.max_simulate_handle_ops_gas.into(),
+                state_override,
+            )
+            .await?
+            .map_err(GasEstimationError::RevertInValidation)?;
+
+        Ok((result.paid / gas_price).saturating_sub(result.pre_op_gas))
     }
 
     async fn binary_search_verification_gas(
@@ -409,14 +685,38 @@ impl<P: Provider, E: EntryPoint> GasEstimatorImpl<P, E> {
             &op.max_fill(&self.settings),
             &op.random_fill(&self.settings),
             self.entry_point.address(),
-            self.provider.clone(),
-            self.chain_id,
+            self.l1_gas_calculator.as_ref(),
             gas_price,
         )
         .await?)
     }
 }
 
+/// Returns the entry point storage slot and value of the nonce sequence number
+/// of `sender` after an operation with `nonce` is executed.
+fn next_nonce_storage(sender: Address, nonce: U256) -> (H256, H256) {
+    let key = nonce >> 64;
+    let sequence = U256::from(nonce.low_u64()) + 1;
+    let sender_slot = keccak256(abi::encode(&[
+        Token::Address(sender),
+        Token::Uint(NONCE_SEQUENCE_NUMBER_SLOT.into()),
+    ]));
+    let slot = keccak256(abi::encode(&[
+        Token::Uint(key),
+        Token::FixedBytes(sender_slot.to_vec()),
+    ]));
+
+    let mut value = [0_u8; 32];
+    sequence.to_big_endian(&mut value);
+    (H256(slot), H256(value))
+}
+
+/// Returns the address of the entry point's sender creator, the first contract
+/// the entry point deploys in its constructor.
+fn sender_creator_address(entry_point: Address) -> Address {
+    get_contract_address(entry_point, 1)
+}
+
 /// Replaces the address of the proxy target where it appears in the proxy
 /// bytecode so we don't need the same fixed address every time.
 fn estimation_proxy_bytecode_with_target(target: Address) -> Bytes {
@@ -430,14 +730,17 @@ mod tests {
     use ethers::{
         abi::{AbiEncode, Address},
         providers::JsonRpcError,
-        types::{Chain, U64},
+        types::U64,
         utils::hex,
     };
     use rundler_provider::{MockEntryPoint, MockProvider, ProviderError};
-    use rundler_types::contracts::{get_gas_used::GasUsedResult, i_entry_point::ExecutionResult};
+    use rundler_types::{
+        chain::{ChainSpec, L1GasOracleContractType},
+        contracts::{get_gas_used::GasUsedResult, i_entry_point::ExecutionResult},
+    };
 
     use super::*;
-    use crate::PriorityFeeMode;
+    use crate::{simulation::MockSimulateValidationTracer, PriorityFeeMode};
 
     // Gas overhead defaults
     const FIXED: u32 = 21000;
@@ -456,13 +759,21 @@ mod tests {
     }
 
     fn create_fee_estimator(provider: Arc<MockProvider>) -> FeeEstimator<MockProvider> {
-        FeeEstimator::new(provider, 0, PriorityFeeMode::BaseFeePercent(0), 0)
+        FeeEstimator::new(
+            provider,
+            &ChainSpec::default(),
+            PriorityFeeMode::BaseFeePercent(0),
+            0,
+        )
     }
 
     fn create_estimator(
         entry: MockEntryPoint,
         provider: MockProvider,
-    ) -> (GasEstimatorImpl<MockProvider, MockEntryPoint>, Settings) {
+    ) -> (
+        GasEstimatorImpl<MockProvider, MockEntryPoint, MockSimulateValidationTracer>,
+        Settings,
+    ) {
         let settings = Settings {
             max_verification_gas: 10000000000,
             max_call_gas: 10000000000,
@@ -470,12 +781,17 @@ mod tests {
             validation_estimation_gas_fee: 1_000_000_000_000,
         };
         let provider = Arc::new(provider);
-        let estimator: GasEstimatorImpl<MockProvider, MockEntryPoint> = GasEstimatorImpl::new(
-            0,
+        let estimator: GasEstimatorImpl<
+            MockProvider,
+            MockEntryPoint,
+            MockSimulateValidationTracer,
+        > = GasEstimatorImpl::new(
+            L1GasConfig::default(),
             provider.clone(),
             entry,
             settings,
             create_fee_estimator(provider),
+            MockSimulateValidationTracer::new(),
         );
 
         (estimator, settings)
@@ -563,7 +879,7 @@ mod tests {
         entry.expect_address().return_const(Address::zero());
         provider
             .expect_calc_arbitrum_l1_gas()
-            .returning(|_a, _b| Ok(U256::from(1000)));
+            .returning(|_a, _b, _c| Ok(U256::from(1000)));
 
         let settings = Settings {
             max_verification_gas: 10000000000,
@@ -574,12 +890,20 @@ mod tests {
 
         // Chose arbitrum
         let provider = Arc::new(provider);
-        let estimator: GasEstimatorImpl<MockProvider, MockEntryPoint> = GasEstimatorImpl::new(
-            Chain::Arbitrum as u64,
+        let estimator: GasEstimatorImpl<
+            MockProvider,
+            MockEntryPoint,
+            MockSimulateValidationTracer,
+        > = GasEstimatorImpl::new(
+            L1GasConfig {
+                oracle_type: L1GasOracleContractType::ArbitrumNitro,
+                oracle_address: None,
+            },
             provider.clone(),
             entry,
             settings,
             create_fee_estimator(provider),
+            MockSimulateValidationTracer::new(),
         );
 
         let user_op = demo_user_op_optional_gas();
@@ -615,8 +939,8 @@ mod tests {
 
         entry.expect_address().return_const(Address::zero());
         provider
-            .expect_calc_optimism_l1_gas()
-            .returning(|_a, _b, _c| Ok(U256::from(1000)));
+            .expect_get_l1_fee()
+            .returning(|_a, _b, _c| Ok(U256::from(10_000)));
 
         let settings = Settings {
             max_verification_gas: 10000000000,
@@ -627,17 +951,25 @@ mod tests {
 
         // Chose OP
         let provider = Arc::new(provider);
-        let estimator: GasEstimatorImpl<MockProvider, MockEntryPoint> = GasEstimatorImpl::new(
-            Chain::Optimism as u64,
+        let estimator: GasEstimatorImpl<
+            MockProvider,
+            MockEntryPoint,
+            MockSimulateValidationTracer,
+        > = GasEstimatorImpl::new(
+            L1GasConfig {
+                oracle_type: L1GasOracleContractType::OptimismBedrock,
+                oracle_address: None,
+            },
             provider.clone(),
             entry,
             settings,
             create_fee_estimator(provider),
+            MockSimulateValidationTracer::new(),
         );
 
         let user_op = demo_user_op_optional_gas();
         let estimation = estimator
-            .estimate_pre_verification_gas(&user_op, U256::zero())
+            .estimate_pre_verification_gas(&user_op, U256::from(10))
             .await
             .unwrap();
 
@@ -656,7 +988,7 @@ mod tests {
             + U256::from(PER_USER_OP)
             + U256::from(PER_USER_OP_WORD) * length_in_words;
 
-        //OP dynamic gas
+        //OP dynamic gas, the L1 fee divided by the gas price
         let dynamic_gas = 1000;
 
         assert_eq!(result + dynamic_gas, estimation);
@@ -1287,12 +1619,17 @@ mod tests {
         };
 
         let provider = Arc::new(provider);
-        let estimator: GasEstimatorImpl<MockProvider, MockEntryPoint> = GasEstimatorImpl::new(
-            0,
+        let estimator: GasEstimatorImpl<
+            MockProvider,
+            MockEntryPoint,
+            MockSimulateValidationTracer,
+        > = GasEstimatorImpl::new(
+            L1GasConfig::default(),
             provider.clone(),
             entry,
             settings,
             create_fee_estimator(provider),
+            MockSimulateValidationTracer::new(),
         );
         let user_op = demo_user_op_optional_gas();
         let estimation = estimator
@@ -1305,4 +1642,284 @@ mod tests {
             Some(GasEstimationError::RevertInValidation(..))
         ));
     }
+
+    #[tokio::test]
+    async fn test_estimation_batch_deployed_sender() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = U256::from(10_000);
+        let factory_gas = U256::from(100_000);
+        let sender = Address::random();
+        let init_code = Bytes::from(vec![1, 2, 3]);
+
+        let is_deployed = move |state_override: &spoof::State| {
+            serde_json::to_value(state_override).unwrap()[format!("{sender:?}")]["code"].is_string()
+        };
+        entry.expect_address().return_const(Address::zero());
+        entry.expect_call_spoofed_simulate_op().returning(
+            move |op, _b, _c, _d, _e, state_override| {
+                let required_gas = if op.init_code.is_empty() {
+                    // the second op runs against the state left by the first
+                    if !is_deployed(state_override) {
+                        return Ok(Err("AA20 account not deployed".to_string()));
+                    }
+                    gas_usage
+                } else {
+                    gas_usage + factory_gas
+                };
+                if op.verification_gas_limit < required_gas {
+                    return Ok(Err("AA13".to_string()));
+                }
+
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gas_estimate: U256::from(10000),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            },
+        );
+        entry
+            .expect_decode_simulate_handle_ops_revert()
+            .returning(|_a| Ok(ExecutionResult::default()));
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((H256::zero(), U64::zero())));
+        provider.expect_call().returning(move |_a, _b, _c| {
+            let result_data: Bytes = GasUsedResult {
+                gas_used: gas_usage,
+                success: false,
+                result: Bytes::new(),
+            }
+            .encode()
+            .into();
+
+            let json_rpc_error = JsonRpcError {
+                code: -32000,
+                message: "execution reverted".to_string(),
+                data: Some(serde_json::Value::String(result_data.to_string())),
+            };
+            Err(ProviderError::JsonRpcError(json_rpc_error))
+        });
+        let expected_data: Bytes = CreateSenderCall {
+            init_code: init_code.clone(),
+        }
+        .encode()
+        .into();
+        provider
+            .expect_apply_call_state_changes()
+            .times(1)
+            .returning(move |tx, _b, mut state_override| {
+                assert_eq!(tx.from(), Some(&Address::zero()));
+                assert_eq!(tx.to_addr(), Some(&sender_creator_address(Address::zero())));
+                assert_eq!(tx.data(), Some(&expected_data));
+                state_override
+                    .account(sender)
+                    .code(Bytes::from(vec![4, 5, 6]));
+                Ok(state_override)
+            });
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::from(1000)));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::from(1000)));
+
+        let (estimator, _) = create_estimator(entry, provider);
+
+        let deploy_op = UserOperationOptionalGas {
+            sender,
+            init_code,
+            ..demo_user_op_optional_gas()
+        };
+        let call_op = UserOperationOptionalGas {
+            sender,
+            nonce: U256::one(),
+            ..demo_user_op_optional_gas()
+        };
+
+        let estimates = estimator
+            .estimate_op_gas_batch(vec![deploy_op, call_op], spoof::state())
+            .await
+            .unwrap();
+
+        assert_eq!(estimates.len(), 2);
+        assert!(estimates[0].verification_gas_limit > factory_gas);
+        // the deployment is not counted again for the second op
+        assert!(estimates[1].verification_gas_limit < factory_gas);
+        assert!(estimates[0].pre_verification_gas > estimates[1].pre_verification_gas);
+    }
+
+    #[test]
+    fn test_sender_creator_address() {
+        // sender creator of the v0.6 entry point
+        assert_eq!(
+            sender_creator_address(
+                "0x5FF137D4b0FDCD49DcA30c7CF57E578a026d2789"
+                    .parse()
+                    .unwrap()
+            ),
+            "0x7fc98430eaedbb6070b35b39d798725049088348"
+                .parse()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_next_nonce_storage() {
+        let sender = Address::random();
+        let key = U256::from(5) << 64;
+
+        let (slot, value) = next_nonce_storage(sender, key + 7);
+        assert_eq!(value, H256::from_low_u64_be(8));
+        // same key, same slot
+        assert_eq!(next_nonce_storage(sender, key).0, slot);
+        // different key, different slot
+        assert_ne!(next_nonce_storage(sender, U256::from(7)).0, slot);
+    }
+
+    #[tokio::test]
+    async fn test_estimation_with_breakdown() {
+        let (mut entry, mut provider) = create_base_config();
+        let gas_usage = 10_000.into();
+        let post_op_gas = U256::from(5_000);
+
+        entry.expect_address().return_const(Address::zero());
+        entry
+            .expect_call_spoofed_simulate_op()
+            .returning(move |op, _b, _c, _d, _e, _f| {
+                if op.verification_gas_limit == U256::from(10000000000_u64)
+                    && !op.max_fee_per_gas.is_zero()
+                {
+                    // postOp measurement
+                    let pre_op_gas = U256::from(20_000);
+                    return Ok(Ok(ExecutionResult {
+                        pre_op_gas,
+                        paid: (pre_op_gas + post_op_gas) * op.max_fee_per_gas,
+                        ..Default::default()
+                    }));
+                }
+                if op.verification_gas_limit < gas_usage {
+                    return Ok(Err("AA33".to_string()));
+                }
+
+                Ok(Ok(ExecutionResult {
+                    target_result: EstimateCallGasResult {
+                        gas_estimate: U256::from(10000),
+                        num_rounds: U256::from(10),
+                    }
+                    .encode()
+                    .into(),
+                    target_success: true,
+                    ..Default::default()
+                }))
+            });
+        entry
+            .expect_decode_simulate_handle_ops_revert()
+            .returning(|_a| Ok(ExecutionResult::default()));
+
+        provider
+            .expect_get_code()
+            .returning(|_a, _b| Ok(Bytes::new()));
+        provider
+            .expect_get_latest_block_hash_and_number()
+            .returning(|| Ok((H256::zero(), U64::zero())));
+        provider.expect_call().returning(move |_a, _b, _c| {
+            let result_data: Bytes = GasUsedResult {
+                gas_used: gas_usage,
+                success: false,
+                result: Bytes::new(),
+            }
+            .encode()
+            .into();
+
+            let json_rpc_error = JsonRpcError {
+                code: -32000,
+                message: "execution reverted".to_string(),
+                data: Some(serde_json::Value::String(result_data.to_string())),
+            };
+            Err(ProviderError::JsonRpcError(json_rpc_error))
+        });
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::from(1000)));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::from(1000)));
+
+        let mut tracer = MockSimulateValidationTracer::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _, _| {
+                let phase = |gas_used: u64| {
+                    serde_json::json!({
+                        "forbiddenOpcodesUsed": [],
+                        "forbiddenPrecompilesUsed": [],
+                        "storageAccesses": {},
+                        "calledBannedEntryPointMethod": false,
+                        "addressesCallingWithValue": [],
+                        "calledNonEntryPointWithValue": false,
+                        "ranOutOfGas": false,
+                        "undeployedContractAccesses": [],
+                        "extCodeAccessInfo": {},
+                        "gasUsed": gas_used,
+                    })
+                };
+                Ok(serde_json::from_value(serde_json::json!({
+                    "phases": [phase(100), phase(2_000), phase(3_000)],
+                    "revertData": null,
+                    "accessedContractAddresses": [],
+                    "associatedSlotsByAddress": {},
+                    "factoryCalledCreate2Twice": false,
+                    "expectedStorage": {},
+                }))
+                .unwrap())
+            });
+
+        let settings = Settings {
+            max_verification_gas: 10000000000,
+            max_call_gas: 10000000000,
+            max_simulate_handle_ops_gas: 100000000,
+            validation_estimation_gas_fee: 1_000_000_000_000,
+        };
+        let provider = Arc::new(provider);
+        let estimator = GasEstimatorImpl::new(
+            L1GasConfig::default(),
+            provider.clone(),
+            entry,
+            settings,
+            create_fee_estimator(provider),
+            tracer,
+        );
+
+        let user_op = UserOperationOptionalGas {
+            paymaster_and_data: Address::random().as_bytes().to_vec().into(),
+            ..demo_user_op_optional_gas()
+        };
+        let estimation = estimator
+            .estimate_op_gas_with_breakdown(user_op, spoof::state())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            estimation.breakdown,
+            GasBreakdown {
+                factory_deployment_gas: U256::zero(),
+                account_validation_gas: U256::from(2_000),
+                paymaster_validation_gas: U256::from(3_000),
+                paymaster_post_op_gas: post_op_gas,
+            }
+        );
+        assert_eq!(
+            estimation.estimate.verification_gas_limit,
+            math::increase_by_percent(gas_usage, 10)
+        );
+    }
 }
@@ -228,3 +228,28 @@ pub struct GasEstimate {
     /// Call gas limit estimate
     pub call_gas_limit: U256,
 }
+
+/// Gas used by each verification phase of a user operation
+#[derive(Debug, Copy, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct GasBreakdown {
+    /// Gas used to deploy the sender with its factory
+    pub factory_deployment_gas: U256,
+    /// Gas used by the account's validation
+    pub account_validation_gas: U256,
+    /// Gas used by the paymaster's validation
+    pub paymaster_validation_gas: U256,
+    /// Gas used by the paymaster's `postOp`
+    pub paymaster_post_op_gas: U256,
+}
+
+/// Gas estimate for a user operation with a breakdown of its verification gas
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GasEstimateWithBreakdown {
+    /// Gas estimate
+    #[serde(flatten)]
+    pub estimate: GasEstimate,
+    /// Gas used by each verification phase
+    pub breakdown: GasBreakdown,
+}
@@ -16,18 +16,20 @@ use std::{cmp, fmt::Debug, sync::Arc};
 use anyhow::Context;
 use ethers::{
     abi::AbiEncode,
-    types::{Address, Chain, U256},
+    types::{Address, U256},
 };
 use rundler_provider::Provider;
 use rundler_types::{
-    chain::{ARBITRUM_CHAIN_IDS, OP_BEDROCK_CHAIN_IDS, POLYGON_CHAIN_IDS},
+    chain::{ChainSpec, L1GasConfig, PriorityFeeOracleType},
     GasFees, UserOperation,
 };
 use rundler_utils::math;
 use tokio::try_join;
 
+use super::l1::L1GasCalculator;
 use super::oracle::{
-    ConstantOracle, FeeOracle, ProviderOracle, UsageBasedFeeOracle, UsageBasedFeeOracleConfig,
+    ConstantOracle, FeeHistoryOracle, FeeHistoryOracleConfig, FeeOracle, ProviderOracle,
+    UsageBasedFeeOracle, UsageBasedFeeOracleConfig,
 };
 
 /// Gas overheads for user operations used in calculating the pre-verification gas. See: https://github.com/eth-infinitism/bundler/blob/main/packages/sdk/src/calcPreVerificationGas.ts
@@ -69,30 +71,17 @@ impl Default for GasOverheads {
 ///
 /// Networks that require dynamic pre_verification_gas are typically those that charge extra calldata fees
 /// that can scale based on dynamic gas prices.
-pub async fn estimate_pre_verification_gas<P: Provider>(
+pub async fn estimate_pre_verification_gas(
     full_op: &UserOperation,
     random_op: &UserOperation,
     entry_point: Address,
-    provider: Arc<P>,
-    chain_id: u64,
+    l1_gas_calculator: &dyn L1GasCalculator,
     gas_price: U256,
 ) -> anyhow::Result<U256> {
     let static_gas = calc_static_pre_verification_gas(full_op, true);
-    let dynamic_gas = match chain_id {
-        _ if ARBITRUM_CHAIN_IDS.contains(&chain_id) => {
-            provider
-                .clone()
-                .calc_arbitrum_l1_gas(entry_point, random_op.clone())
-                .await?
-        }
-        _ if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) => {
-            provider
-                .clone()
-                .calc_optimism_l1_gas(entry_point, random_op.clone(), gas_price)
-                .await?
-        }
-        _ => U256::zero(),
-    };
+    let dynamic_gas = l1_gas_calculator
+        .calc_l1_gas(entry_point, random_op.clone(), gas_price)
+        .await?;
 
     Ok(static_gas + dynamic_gas)
 }
@@ -100,31 +89,17 @@ pub async fn estimate_pre_verification_gas<P: Provider>(
 /// Calculate the required pre_verification_gas for the given user operation and the provided base fee.
 ///
 /// The effective gas price is calculated as min(base_fee + max_priority_fee_per_gas, max_fee_per_gas)
-pub async fn calc_required_pre_verification_gas<P: Provider>(
+pub async fn calc_required_pre_verification_gas(
     op: &UserOperation,
     entry_point: Address,
-    provider: Arc<P>,
-    chain_id: u64,
+    l1_gas_calculator: &dyn L1GasCalculator,
     base_fee: U256,
 ) -> anyhow::Result<U256> {
     let static_gas = calc_static_pre_verification_gas(op, true);
-    let dynamic_gas = match chain_id {
-        _ if ARBITRUM_CHAIN_IDS.contains(&chain_id) => {
-            provider
-                .clone()
-                .calc_arbitrum_l1_gas(entry_point, op.clone())
-                .await?
-        }
-        _ if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) => {
-            let gas_price = cmp::min(base_fee + op.max_priority_fee_per_gas, op.max_fee_per_gas);
-
-            provider
-                .clone()
-                .calc_optimism_l1_gas(entry_point, op.clone(), gas_price)
-                .await?
-        }
-        _ => U256::zero(),
-    };
+    let gas_price = cmp::min(base_fee + op.max_priority_fee_per_gas, op.max_fee_per_gas);
+    let dynamic_gas = l1_gas_calculator
+        .calc_l1_gas(entry_point, op.clone(), gas_price)
+        .await?;
 
     Ok(static_gas + dynamic_gas)
 }
@@ -148,11 +123,11 @@ pub async fn calc_required_pre_verification_gas<P: Provider>(
 /// Returns the gas limit for the user operation that applies to bundle transaction's limit
 pub fn user_operation_gas_limit(
     uo: &UserOperation,
-    chain_id: u64,
+    l1_gas: &L1GasConfig,
     assume_single_op_bundle: bool,
     paymaster_post_op: bool,
 ) -> U256 {
-    user_operation_pre_verification_gas_limit(uo, chain_id, assume_single_op_bundle)
+    user_operation_pre_verification_gas_limit(uo, l1_gas, assume_single_op_bundle)
         + uo.call_gas_limit
         + uo.verification_gas_limit
             * verification_gas_limit_multiplier(assume_single_op_bundle, paymaster_post_op)
@@ -161,11 +136,11 @@ pub fn user_operation_gas_limit(
 /// Returns the gas limit for the user operation that applies to bundle transaction's execution limit
 pub fn user_operation_execution_gas_limit(
     uo: &UserOperation,
-    chain_id: u64,
+    l1_gas: &L1GasConfig,
     assume_single_op_bundle: bool,
     paymaster_post_op: bool,
 ) -> U256 {
-    user_operation_pre_verification_execution_gas_limit(uo, chain_id, assume_single_op_bundle)
+    user_operation_pre_verification_execution_gas_limit(uo, l1_gas, assume_single_op_bundle)
         + uo.call_gas_limit
         + uo.verification_gas_limit
             * verification_gas_limit_multiplier(assume_single_op_bundle, paymaster_post_op)
@@ -174,13 +149,13 @@ pub fn user_operation_execution_gas_limit(
 /// Returns the static pre-verification gas cost of a user operation
 pub fn user_operation_pre_verification_execution_gas_limit(
     uo: &UserOperation,
-    chain_id: u64,
+    l1_gas: &L1GasConfig,
     include_fixed_gas_overhead: bool,
 ) -> U256 {
-    // On some chains (OP bedrock, Arbitrum) the L1 gas fee is charged via pre_verification_gas
+    // On chains with an L1 data cost the L1 gas fee is charged via pre_verification_gas
     // but this not part of the EXECUTION gas limit of the transaction.
     // In such cases we only consider the static portion of the pre_verification_gas in the gas limit.
-    if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) | ARBITRUM_CHAIN_IDS.contains(&chain_id) {
+    if l1_gas.is_dynamic_pvg() {
         calc_static_pre_verification_gas(uo, include_fixed_gas_overhead)
     } else {
         uo.pre_verification_gas
@@ -190,13 +165,13 @@ pub fn user_operation_pre_verification_execution_gas_limit(
 /// Returns the gas limit for the user operation that applies to bundle transaction's limit
 pub fn user_operation_pre_verification_gas_limit(
     uo: &UserOperation,
-    chain_id: u64,
+    l1_gas: &L1GasConfig,
     include_fixed_gas_overhead: bool,
 ) -> U256 {
-    // On some chains (OP bedrock) the L1 gas fee is charged via pre_verification_gas
+    // On some chains (OP Stack) the L1 gas fee is charged via pre_verification_gas
     // but this not part of the execution TOTAL limit of the transaction.
     // In such cases we only consider the static portion of the pre_verification_gas in the gas limit.
-    if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) {
+    if l1_gas.is_dynamic_pvg() && !l1_gas.charges_l1_gas() {
         calc_static_pre_verification_gas(uo, include_fixed_gas_overhead)
     } else {
         uo.pre_verification_gas
@@ -303,10 +278,36 @@ impl PriorityFeeMode {
     }
 }
 
+/// Percentile of recent priority fees used for the standard fee tier
+const STANDARD_TIER_PERCENTILE: f64 = 50.0;
+/// Percentile of recent priority fees used for the fast fee tier
+const FAST_TIER_PERCENTILE: f64 = 90.0;
+/// Maximum inclusion latency, in blocks, used to calculate fee tier base fee headroom
+const MAX_TIER_INCLUSION_LATENCY_BLOCKS: u64 = 4;
+
+/// Recommended user operation fees for increasing speeds of inclusion
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct FeeTiers {
+    /// The current base fee
+    pub base_fee: U256,
+    /// Fees at the minimum priority fee required by the bundler
+    pub slow: GasFees,
+    /// Fees at the median recent priority fee
+    pub standard: GasFees,
+    /// Fees at the 90th percentile recent priority fee
+    pub fast: GasFees,
+}
+
 /// Gas fee estimator for a 4337 user operation.
+///
+/// On chains without EIP-1559, the network gas price takes the place of the base fee
+/// and bundles pay the chain's minimum priority fee on top of it. Operations must pay
+/// their max fee as their max priority fee, which the entry point then charges without
+/// reading the base fee.
 #[derive(Debug, Clone)]
 pub struct FeeEstimator<P> {
     provider: Arc<P>,
+    eip1559_enabled: bool,
     priority_fee_mode: PriorityFeeMode,
     bundle_priority_fee_overhead_percent: u64,
     fee_oracle: Arc<Box<dyn FeeOracle>>,
@@ -315,21 +316,25 @@ pub struct FeeEstimator<P> {
 impl<P: Provider> FeeEstimator<P> {
     /// Create a new fee estimator.
     ///
+    /// `chain_spec` selects the oracle used to estimate the network priority fee, and
+    /// whether fees are EIP-1559 or legacy fees.
+    ///
     /// `priority_fee_mode` is used to determine how the required priority fee is calculated.
     ///
     /// `bundle_priority_fee_overhead_percent` is used to determine the overhead percentage to add
     /// to the network returned priority fee to ensure the bundle priority fee is high enough.
     pub fn new(
         provider: Arc<P>,
-        chain_id: u64,
+        chain_spec: &ChainSpec,
         priority_fee_mode: PriorityFeeMode,
         bundle_priority_fee_overhead_percent: u64,
     ) -> Self {
         Self {
             provider: provider.clone(),
+            eip1559_enabled: chain_spec.eip1559_enabled,
             priority_fee_mode,
             bundle_priority_fee_overhead_percent,
-            fee_oracle: get_fee_oracle(chain_id, provider),
+            fee_oracle: get_fee_oracle(chain_spec, provider),
         }
     }
 
@@ -370,11 +375,84 @@ impl<P: Provider> FeeEstimator<P> {
 
     /// Returns the required operation fees for the given bundle fees.
     pub fn required_op_fees(&self, bundle_fees: GasFees) -> GasFees {
-        self.priority_fee_mode.required_fees(bundle_fees)
+        let fees = self.priority_fee_mode.required_fees(bundle_fees);
+        if self.eip1559_enabled {
+            fees
+        } else {
+            GasFees {
+                max_fee_per_gas: fees.max_fee_per_gas,
+                max_priority_fee_per_gas: fees.max_fee_per_gas,
+            }
+        }
+    }
+
+    /// Returns recommended operation fees for slow, standard, and fast inclusion.
+    ///
+    /// The slow tier pays the currently required operation fees, while the standard and fast
+    /// tiers pay recent priority fee percentiles plus the bundle priority fee overhead. No tier
+    /// pays a priority fee below the bundle priority fee, which is the fee used to estimate the
+    /// L1 data portion of pre-verification gas, so estimated operations remain valid at every tier.
+    ///
+    /// `inclusion_latency_blocks` is the number of blocks bundles recently took to be mined. The
+    /// max fee of each tier covers base fee growth over one, two, and three times that latency.
+    ///
+    /// On legacy chains every tier pays the currently required gas price.
+    pub async fn required_op_fee_tiers(
+        &self,
+        inclusion_latency_blocks: u64,
+    ) -> anyhow::Result<FeeTiers> {
+        let (bundle_fees, base_fee) = self.required_bundle_fees(None).await?;
+        let (standard_priority_fee, fast_priority_fee) = if self.eip1559_enabled {
+            try_join!(
+                self.get_priority_fee_percentile(STANDARD_TIER_PERCENTILE),
+                self.get_priority_fee_percentile(FAST_TIER_PERCENTILE),
+            )?
+        } else {
+            // legacy chains have no priority fee history
+            (U256::zero(), U256::zero())
+        };
+
+        let latency = inclusion_latency_blocks.clamp(1, MAX_TIER_INCLUSION_LATENCY_BLOCKS);
+        let tier_fees = |priority_fee: U256, headroom_blocks: u64| {
+            let bundle_priority_fee =
+                bundle_fees
+                    .max_priority_fee_per_gas
+                    .max(math::increase_by_percent(
+                        priority_fee,
+                        self.bundle_priority_fee_overhead_percent,
+                    ));
+            let op_fees = self.required_op_fees(GasFees {
+                max_fee_per_gas: base_fee + bundle_priority_fee,
+                max_priority_fee_per_gas: bundle_priority_fee,
+            });
+            if !self.eip1559_enabled {
+                // legacy gas prices are paid in full, so no base fee headroom is added
+                return op_fees;
+            }
+            let max_priority_fee_per_gas = op_fees
+                .max_priority_fee_per_gas
+                .max(bundle_fees.max_priority_fee_per_gas);
+            GasFees {
+                max_fee_per_gas: max_base_fee_after_blocks(base_fee, headroom_blocks)
+                    + max_priority_fee_per_gas,
+                max_priority_fee_per_gas,
+            }
+        };
+
+        Ok(FeeTiers {
+            base_fee,
+            slow: tier_fees(U256::zero(), latency),
+            standard: tier_fees(standard_priority_fee, 2 * latency),
+            fast: tier_fees(fast_priority_fee, 3 * latency),
+        })
     }
 
     async fn get_base_fee(&self) -> anyhow::Result<U256> {
-        Ok(self.provider.get_base_fee().await?)
+        if self.eip1559_enabled {
+            Ok(self.provider.get_base_fee().await?)
+        } else {
+            Ok(self.provider.get_gas_price().await?)
+        }
     }
 
     async fn get_priority_fee(&self) -> anyhow::Result<U256> {
@@ -383,44 +461,129 @@ impl<P: Provider> FeeEstimator<P> {
             .await
             .context("should get priority fee")
     }
-}
 
-// TODO move all of this to ChainSpec
-/// ETHEREUM_MAINNET_MAX_PRIORITY_FEE_MIN
-pub const ETHEREUM_MAINNET_MAX_PRIORITY_FEE_MIN: u64 = 100_000_000;
-/// Polygon Mumbai max priority fee min
-pub const POLYGON_MUMBAI_MAX_PRIORITY_FEE_MIN: u64 = 1_500_000_000;
-/// Polygon Mainnet max priority fee min
-pub const POLYGON_MAINNET_MAX_PRIORITY_FEE_MIN: u64 = 30_000_000_000;
-/// Optimism Bedrock chains max priority fee min
-pub const OPTIMISM_BEDROCK_MAX_PRIORITY_FEE_MIN: u64 = 100_000;
-
-/// Returns the minimum max priority fee per gas for the given chain id.
-pub fn get_min_max_priority_fee_per_gas(chain_id: u64) -> U256 {
-    match chain_id {
-        x if x == Chain::Mainnet as u64 => ETHEREUM_MAINNET_MAX_PRIORITY_FEE_MIN.into(),
-        x if x == Chain::Polygon as u64 => POLYGON_MAINNET_MAX_PRIORITY_FEE_MIN.into(),
-        x if x == Chain::PolygonMumbai as u64 => POLYGON_MUMBAI_MAX_PRIORITY_FEE_MIN.into(),
-        x if OP_BEDROCK_CHAIN_IDS.contains(&x) => OPTIMISM_BEDROCK_MAX_PRIORITY_FEE_MIN.into(),
-        _ => U256::zero(),
+    async fn get_priority_fee_percentile(&self, percentile: f64) -> anyhow::Result<U256> {
+        let config = FeeHistoryOracleConfig {
+            percentile,
+            ..Default::default()
+        };
+        FeeHistoryOracle::new(Arc::clone(&self.provider), config)
+            .estimate_priority_fee()
+            .await
+            .context("should get priority fee percentile")
     }
 }
 
-fn get_fee_oracle<P>(chain_id: u64, provider: Arc<P>) -> Arc<Box<dyn FeeOracle>>
+// The maximum base fee after the given number of blocks, as the base fee
+// can increase by at most 12.5% per block under EIP-1559
+fn max_base_fee_after_blocks(base_fee: U256, blocks: u64) -> U256 {
+    (0..blocks).fold(base_fee, |fee, _| fee + fee / 8)
+}
+
+fn get_fee_oracle<P>(chain_spec: &ChainSpec, provider: Arc<P>) -> Arc<Box<dyn FeeOracle>>
 where
     P: Provider + Debug,
 {
-    let minimum_fee = get_min_max_priority_fee_per_gas(chain_id);
+    let minimum_fee = U256::from(chain_spec.min_max_priority_fee_per_gas);
+    if !chain_spec.eip1559_enabled {
+        // the network gas price already includes any priority fee
+        return Arc::new(Box::new(ConstantOracle::new(minimum_fee)));
+    }
+
+    match chain_spec.priority_fee_oracle_type {
+        PriorityFeeOracleType::Constant => Arc::new(Box::new(ConstantOracle::new(minimum_fee))),
+        PriorityFeeOracleType::UsageBased => {
+            let config = UsageBasedFeeOracleConfig {
+                minimum_fee,
+                ..Default::default()
+            };
+            Arc::new(Box::new(UsageBasedFeeOracle::new(provider, config)))
+        }
+        PriorityFeeOracleType::Provider => Arc::new(Box::new(ProviderOracle::new(provider))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::FeeHistory;
+    use rundler_provider::MockProvider;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_required_op_fee_tiers() {
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_base_fee()
+            .returning(|| Ok(U256::from(800)));
+        provider
+            .expect_get_max_priority_fee()
+            .returning(|| Ok(U256::from(100)));
+        provider
+            .expect_fee_history()
+            .returning(|_: u64, _, percentiles: &[f64]| {
+                let reward = if percentiles[0] == FAST_TIER_PERCENTILE {
+                    400
+                } else {
+                    50
+                };
+                Ok(FeeHistory {
+                    base_fee_per_gas: vec![],
+                    gas_used_ratio: vec![],
+                    oldest_block: U256::zero(),
+                    reward: vec![vec![U256::from(reward)]],
+                })
+            });
+
+        let estimator = FeeEstimator::new(
+            Arc::new(provider),
+            &ChainSpec::default(),
+            PriorityFeeMode::PriorityFeeIncreasePercent(0),
+            10,
+        );
+        let tiers = estimator.required_op_fee_tiers(1).await.unwrap();
+
+        assert_eq!(tiers.base_fee, U256::from(800));
+        // the standard percentile is below the bundle priority fee, so it is raised to it
+        assert_eq!(tiers.slow.max_priority_fee_per_gas, U256::from(110));
+        assert_eq!(tiers.standard.max_priority_fee_per_gas, U256::from(110));
+        assert_eq!(tiers.fast.max_priority_fee_per_gas, U256::from(440));
+        assert_eq!(tiers.slow.max_fee_per_gas, U256::from(900 + 110));
+        assert_eq!(tiers.standard.max_fee_per_gas, U256::from(1012 + 110));
+        assert_eq!(tiers.fast.max_fee_per_gas, U256::from(1138 + 440));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_fees() {
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_gas_price()
+            .returning(|| Ok(U256::from(1000)));
 
-    if ARBITRUM_CHAIN_IDS.contains(&chain_id) {
-        Arc::new(Box::new(ConstantOracle::new(U256::zero())))
-    } else if OP_BEDROCK_CHAIN_IDS.contains(&chain_id) || POLYGON_CHAIN_IDS.contains(&chain_id) {
-        let config = UsageBasedFeeOracleConfig {
-            minimum_fee,
+        let chain_spec = ChainSpec {
+            eip1559_enabled: false,
+            min_max_priority_fee_per_gas: 100,
             ..Default::default()
         };
-        Arc::new(Box::new(UsageBasedFeeOracle::new(provider, config)))
-    } else {
-        Arc::new(Box::new(ProviderOracle::new(provider)))
+        let estimator = FeeEstimator::new(
+            Arc::new(provider),
+            &chain_spec,
+            PriorityFeeMode::PriorityFeeIncreasePercent(0),
+            10,
+        );
+
+        // the gas price is the base fee, and bundles pay the minimum priority fee plus overhead
+        let (bundle_fees, base_fee) = estimator.required_bundle_fees(None).await.unwrap();
+        assert_eq!(base_fee, U256::from(1000));
+        assert_eq!(bundle_fees.max_fee_per_gas, U256::from(1110));
+
+        // operations pay their max fee as their priority fee
+        let op_fees = estimator.required_op_fees(bundle_fees);
+        assert_eq!(op_fees.max_fee_per_gas, U256::from(1110));
+        assert_eq!(op_fees.max_priority_fee_per_gas, U256::from(1110));
+
+        let tiers = estimator.required_op_fee_tiers(2).await.unwrap();
+        assert_eq!(tiers.slow, op_fees);
+        assert_eq!(tiers.fast, op_fees);
     }
 }
@@ -0,0 +1,405 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{fmt::Debug, io::Write, sync::Arc};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use ethers::{
+    abi::AbiEncode,
+    types::{Address, BlockNumber, Bytes, U256},
+};
+use flate2::{write::DeflateEncoder, Compression};
+use rundler_provider::Provider;
+use rundler_types::{
+    chain::{L1GasConfig, L1GasOracleContractType},
+    contracts::i_entry_point::HandleOpsCall,
+    UserOperation,
+};
+use serde::{Deserialize, Serialize};
+
+/// Calculates the L1 data cost portion of a user operation's pre-verification gas
+#[async_trait]
+pub trait L1GasCalculator: Send + Sync + Debug {
+    /// Returns the L2 gas needed to pay for the L1 data cost of a bundle containing
+    /// only `op`, at the given L2 gas price.
+    async fn calc_l1_gas(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        gas_price: U256,
+    ) -> anyhow::Result<U256>;
+}
+
+/// Create the L1 gas calculator for a chain's L1 gas configuration
+pub fn new_l1_gas_calculator<P: Provider>(
+    config: L1GasConfig,
+    provider: Arc<P>,
+) -> Arc<dyn L1GasCalculator> {
+    match (config.oracle_type, config.oracle_address()) {
+        (L1GasOracleContractType::ArbitrumNitro, Some(node_interface_address)) => {
+            Arc::new(ArbitrumNitroL1GasCalculator {
+                provider,
+                node_interface_address,
+            })
+        }
+        (
+            L1GasOracleContractType::OptimismBedrock
+            | L1GasOracleContractType::Scroll
+            | L1GasOracleContractType::Mantle,
+            Some(oracle_address),
+        ) => Arc::new(GasPriceOracleL1GasCalculator {
+            provider,
+            oracle_address,
+        }),
+        (L1GasOracleContractType::Linea, _) => Arc::new(LineaL1GasCalculator { provider }),
+        (L1GasOracleContractType::ZkSync, _) => Arc::new(ZkSyncL1GasCalculator { provider }),
+        _ => Arc::new(NoL1GasCalculator),
+    }
+}
+
+/// Check that the L1 gas oracle of a chain's L1 gas configuration is usable:
+/// that an oracle address is set if the oracle type requires one, and that a
+/// contract is deployed there.
+pub async fn check_l1_gas_oracle<P: Provider>(
+    config: L1GasConfig,
+    provider: &P,
+) -> anyhow::Result<()> {
+    match (config.oracle_type, config.oracle_address()) {
+        // `NodeInterface` is a virtual contract without code
+        (L1GasOracleContractType::ArbitrumNitro, _) => Ok(()),
+        (
+            L1GasOracleContractType::OptimismBedrock
+            | L1GasOracleContractType::Scroll
+            | L1GasOracleContractType::Mantle,
+            None,
+        ) => bail!(
+            "L1 gas oracle type {} requires an oracle address",
+            config.oracle_type
+        ),
+        (_, Some(oracle_address)) => {
+            let code = provider
+                .get_code(oracle_address, None)
+                .await
+                .context("should get L1 gas oracle code")?;
+            if code.is_empty() {
+                bail!(
+                    "no {} L1 gas oracle deployed at {oracle_address:?}",
+                    config.oracle_type
+                );
+            }
+            Ok(())
+        }
+        (_, None) => Ok(()),
+    }
+}
+
+/// Calculator for chains without an L1 data cost
+#[derive(Debug)]
+struct NoL1GasCalculator;
+
+#[async_trait]
+impl L1GasCalculator for NoL1GasCalculator {
+    async fn calc_l1_gas(
+        &self,
+        _entry_point: Address,
+        _op: UserOperation,
+        _gas_price: U256,
+    ) -> anyhow::Result<U256> {
+        Ok(U256::zero())
+    }
+}
+
+/// Calculator for Arbitrum Nitro and Orbit chains, where the `NodeInterface`
+/// returns the L1 data cost directly in L2 gas.
+#[derive(Debug)]
+struct ArbitrumNitroL1GasCalculator<P> {
+    provider: Arc<P>,
+    node_interface_address: Address,
+}
+
+#[async_trait]
+impl<P: Provider> L1GasCalculator for ArbitrumNitroL1GasCalculator<P> {
+    async fn calc_l1_gas(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        _gas_price: U256,
+    ) -> anyhow::Result<U256> {
+        Ok(Arc::clone(&self.provider)
+            .calc_arbitrum_l1_gas(self.node_interface_address, entry_point, op)
+            .await?)
+    }
+}
+
+/// Calculator for chains with a `getL1Fee(bytes)` gas price oracle: OP Stack
+/// chains, Scroll, and Mantle.
+#[derive(Debug)]
+struct GasPriceOracleL1GasCalculator<P> {
+    provider: Arc<P>,
+    oracle_address: Address,
+}
+
+#[async_trait]
+impl<P: Provider> L1GasCalculator for GasPriceOracleL1GasCalculator<P> {
+    async fn calc_l1_gas(
+        &self,
+        entry_point: Address,
+        op: UserOperation,
+        gas_price: U256,
+    ) -> anyhow::Result<U256> {
+        let l1_fee = Arc::clone(&self.provider)
+            .get_l1_fee(self.oracle_address, entry_point, op)
+            .await?;
+        Ok(l1_fee.checked_div(gas_price).unwrap_or(U256::MAX))
+    }
+}
+
+/// Calculator for Linea, which prices L1 data with a variable cost per byte of
+/// compressed transaction data published in the extra data of each block.
+#[derive(Debug)]
+struct LineaL1GasCalculator<P> {
+    provider: Arc<P>,
+}
+
+#[async_trait]
+impl<P: Provider> L1GasCalculator for LineaL1GasCalculator<P> {
+    async fn calc_l1_gas(
+        &self,
+        _entry_point: Address,
+        op: UserOperation,
+        gas_price: U256,
+    ) -> anyhow::Result<U256> {
+        let block = self
+            .provider
+            .get_block(BlockNumber::Latest)
+            .await?
+            .context("latest block should exist")?;
+        let variable_cost = linea_variable_cost(&block.extra_data)?;
+
+        let data_size = compressed_size(&handle_ops_calldata(op))?;
+        let l1_fee = variable_cost * data_size;
+        Ok(l1_fee.checked_div(gas_price).unwrap_or(U256::MAX))
+    }
+}
+
+/// Calculator for zkSync-style chains, which price the data published to L1
+/// with a pubdata price returned by `zks_getFeeParams`.
+///
+/// The data published for a bundle is estimated by the size of its calldata.
+#[derive(Debug)]
+struct ZkSyncL1GasCalculator<P> {
+    provider: Arc<P>,
+}
+
+#[async_trait]
+impl<P: Provider> L1GasCalculator for ZkSyncL1GasCalculator<P> {
+    async fn calc_l1_gas(
+        &self,
+        _entry_point: Address,
+        op: UserOperation,
+        gas_price: U256,
+    ) -> anyhow::Result<U256> {
+        let fee_params: ZkSyncFeeParams = self
+            .provider
+            .request("zks_getFeeParams", ())
+            .await
+            .context("should get zkSync fee params")?;
+        let data_size = handle_ops_calldata(op).len();
+        let l1_fee = fee_params.pubdata_price() * data_size;
+        Ok(l1_fee.checked_div(gas_price).unwrap_or(U256::MAX))
+    }
+}
+
+/// Gas charged on L1 per byte of pubdata by zkSync chains before pubdata
+/// had its own price
+const ZKSYNC_L1_GAS_PER_PUBDATA_BYTE: u64 = 17;
+
+/// Response of `zks_getFeeParams`, with prices in wei
+#[derive(Debug, Deserialize, Serialize)]
+enum ZkSyncFeeParams {
+    V1 { l1_gas_price: u64 },
+    V2 { l1_pubdata_price: u64 },
+}
+
+impl ZkSyncFeeParams {
+    /// The price of a byte of pubdata, in wei
+    fn pubdata_price(&self) -> U256 {
+        match self {
+            ZkSyncFeeParams::V1 { l1_gas_price } => {
+                U256::from(*l1_gas_price) * ZKSYNC_L1_GAS_PER_PUBDATA_BYTE
+            }
+            ZkSyncFeeParams::V2 { l1_pubdata_price } => U256::from(*l1_pubdata_price),
+        }
+    }
+}
+
+fn handle_ops_calldata(op: UserOperation) -> Vec<u8> {
+    HandleOpsCall {
+        ops: vec![op],
+        beneficiary: Address::zero(),
+    }
+    .encode()
+}
+
+// Estimates the size of data once compressed for publishing to L1
+fn compressed_size(data: &[u8]) -> anyhow::Result<usize> {
+    let mut encoder = DeflateEncoder::new(vec![], Compression::best());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?.len())
+}
+
+// Parses the variable cost, in wei per byte, from Linea block extra data. Version 1
+// extra data is a version byte followed by the fixed cost, variable cost, and L1 gas
+// price, each a big endian uint32 in kwei.
+fn linea_variable_cost(extra_data: &Bytes) -> anyhow::Result<U256> {
+    if extra_data.len() < 9 || extra_data[0] != 1 {
+        anyhow::bail!("unsupported Linea extra data: {extra_data}");
+    }
+    let kwei = u32::from_be_bytes(extra_data[5..9].try_into()?);
+    Ok(U256::from(kwei) * 1000)
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::types::Block;
+    use rundler_provider::MockProvider;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gas_price_oracle_calculator() {
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_l1_fee()
+            .withf(|oracle, _, _| *oracle == rundler_types::chain::SCROLL_L1_GAS_ORACLE_ADDRESS)
+            .returning(|_, _, _| Ok(U256::from(1_000_000)));
+
+        let calculator = new_l1_gas_calculator(
+            L1GasConfig {
+                oracle_type: L1GasOracleContractType::Scroll,
+                oracle_address: None,
+            },
+            Arc::new(provider),
+        );
+        let gas = calculator
+            .calc_l1_gas(Address::zero(), UserOperation::default(), U256::from(100))
+            .await
+            .unwrap();
+        assert_eq!(gas, U256::from(10_000));
+    }
+
+    #[tokio::test]
+    async fn test_linea_calculator() {
+        let mut provider = MockProvider::default();
+        provider.expect_get_block::<BlockNumber>().returning(|_| {
+            // version 1, fixed cost 0, variable cost 2 kwei, L1 gas price 0
+            let extra_data = Bytes::from(vec![1, 0, 0, 0, 0, 0, 0, 0, 2, 0, 0, 0, 0]);
+            Ok(Some(Block {
+                extra_data,
+                ..Default::default()
+            }))
+        });
+
+        let calculator = new_l1_gas_calculator(
+            L1GasConfig {
+                oracle_type: L1GasOracleContractType::Linea,
+                oracle_address: None,
+            },
+            Arc::new(provider),
+        );
+        let op = UserOperation::default();
+        let calldata = handle_ops_calldata(op.clone());
+        let data_size = compressed_size(&calldata).unwrap();
+        assert!(data_size < calldata.len());
+        let gas = calculator
+            .calc_l1_gas(Address::zero(), op, U256::from(1000))
+            .await
+            .unwrap();
+        assert_eq!(gas, U256::from(2 * data_size));
+    }
+
+    #[tokio::test]
+    async fn test_zksync_calculator() {
+        let mut provider = MockProvider::default();
+        provider
+            .expect_request::<(), ZkSyncFeeParams>()
+            .withf(|method, _| method == "zks_getFeeParams")
+            .returning(|_, _| {
+                Ok(serde_json::from_value(serde_json::json!({
+                    "V2": {
+                        "config": { "minimal_l2_gas_price": 25000000 },
+                        "l1_gas_price": 1000,
+                        "l1_pubdata_price": 3000
+                    }
+                }))
+                .unwrap())
+            });
+
+        let calculator = new_l1_gas_calculator(
+            L1GasConfig {
+                oracle_type: L1GasOracleContractType::ZkSync,
+                oracle_address: None,
+            },
+            Arc::new(provider),
+        );
+        let op = UserOperation::default();
+        let data_size = handle_ops_calldata(op.clone()).len();
+        let gas = calculator
+            .calc_l1_gas(Address::zero(), op, U256::from(1000))
+            .await
+            .unwrap();
+        assert_eq!(gas, U256::from(3 * data_size));
+    }
+
+    #[test]
+    fn test_zksync_v1_pubdata_price() {
+        let params: ZkSyncFeeParams = serde_json::from_value(serde_json::json!({
+            "V1": { "config": {}, "l1_gas_price": 1000 }
+        }))
+        .unwrap();
+        assert_eq!(params.pubdata_price(), U256::from(17_000));
+    }
+
+    #[tokio::test]
+    async fn test_check_l1_gas_oracle() {
+        let mantle = L1GasConfig {
+            oracle_type: L1GasOracleContractType::Mantle,
+            oracle_address: None,
+        };
+        assert!(check_l1_gas_oracle(mantle, &MockProvider::default())
+            .await
+            .is_err());
+
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(Bytes::default()));
+        let mantle = L1GasConfig {
+            oracle_address: Some(Address::random()),
+            ..mantle
+        };
+        assert!(check_l1_gas_oracle(mantle, &provider).await.is_err());
+
+        let mut provider = MockProvider::default();
+        provider
+            .expect_get_code()
+            .returning(|_, _| Ok(Bytes::from(vec![1])));
+        assert!(check_l1_gas_oracle(mantle, &provider).await.is_ok());
+    }
+
+    #[test]
+    fn test_linea_variable_cost_unsupported_version() {
+        assert!(linea_variable_cost(&Bytes::from(vec![0; 32])).is_err());
+    }
+}
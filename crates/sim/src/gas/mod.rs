@@ -17,4 +17,7 @@
 mod gas;
 pub use gas::*;
 
+mod l1;
+pub use l1::{check_l1_gas_oracle, new_l1_gas_calculator, L1GasCalculator};
+
 mod oracle;
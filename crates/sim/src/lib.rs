@@ -32,28 +32,30 @@
 
 mod estimation;
 pub use estimation::{
-    GasEstimate, GasEstimationError, GasEstimator, GasEstimatorImpl,
-    Settings as EstimationSettings, UserOperationOptionalGas,
+    GasBreakdown, GasEstimate, GasEstimateWithBreakdown, GasEstimationError, GasEstimator,
+    GasEstimatorImpl, Settings as EstimationSettings, UserOperationOptionalGas,
 };
 
 pub mod gas;
-pub use gas::{FeeEstimator, PriorityFeeMode};
+pub use gas::{FeeEstimator, FeeTiers, PriorityFeeMode};
 
 mod precheck;
 #[cfg(feature = "test-utils")]
 pub use precheck::MockPrechecker;
 pub use precheck::{
-    PrecheckError, PrecheckViolation, Prechecker, PrecheckerImpl, Settings as PrecheckSettings,
-    MIN_CALL_GAS_LIMIT,
+    PrecheckError, PrecheckReturn, PrecheckViolation, Prechecker, PrecheckerImpl,
+    Settings as PrecheckSettings, TokenFunds, TokenPaymasterProfile, MIN_CALL_GAS_LIMIT,
 };
 
 mod simulation;
 #[cfg(feature = "test-utils")]
 pub use simulation::MockSimulator;
 pub use simulation::{
-    EntityInfo, EntityInfos, MempoolConfig, NeedsStakeInformation, Settings as SimulationSettings,
-    SimulateValidationTracer, SimulateValidationTracerImpl, SimulationError, SimulationResult,
-    SimulationViolation, Simulator, SimulatorImpl, ViolationOpCode,
+    AllowlistMatch, EntityInfo, EntityInfos, MempoolConfig, MempoolRegistry, NeedsStakeInformation,
+    PhaseReport, Settings as SimulationSettings, SharedMempoolConfigs, SimulateValidationTracer,
+    SimulateValidationTracerImpl, SimulationCache, SimulationError, SimulationResult,
+    SimulationViolation, Simulator, SimulatorImpl, StorageAccessReport, ValidationReport,
+    ValidationUsage, ViolationOpCode, ViolationReport,
 };
 
 mod types;
@@ -11,19 +11,23 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 
 use anyhow::Context;
 use arrayvec::ArrayVec;
-use ethers::types::{Address, U256};
+use ethers::types::{Address, BlockId, H256, U256};
 #[cfg(feature = "test-utils")]
 use mockall::automock;
 use rundler_provider::{EntryPoint, Provider};
-use rundler_types::{GasFees, UserOperation};
+use rundler_types::{chain::ChainSpec, GasFees, UserOperation};
 use rundler_utils::math;
+use serde::Deserialize;
 
 use crate::{
-    gas::{self, get_min_max_priority_fee_per_gas},
+    gas::{self, L1GasCalculator},
     types::ViolationError,
 };
 
@@ -36,7 +40,7 @@ pub const MIN_CALL_GAS_LIMIT: U256 = U256([9100, 0, 0, 0]);
 #[async_trait::async_trait]
 pub trait Prechecker: Send + Sync + 'static {
     /// Run the precheck on the given operation and return an error if it fails.
-    async fn check(&self, op: &UserOperation) -> Result<(), PrecheckError>;
+    async fn check(&self, op: &UserOperation) -> Result<PrecheckReturn, PrecheckError>;
     /// Update and return the bundle fees.
     async fn update_fees(&self) -> anyhow::Result<(GasFees, U256)>;
 }
@@ -44,6 +48,38 @@ pub trait Prechecker: Send + Sync + 'static {
 /// Precheck error
 pub type PrecheckError = ViolationError<PrecheckViolation>;
 
+/// Data loaded by a successful precheck
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrecheckReturn {
+    /// The sender's funds in the paymaster's token, if the operation uses a
+    /// token paymaster
+    pub token_funds: Option<TokenFunds>,
+}
+
+/// An ERC-20 token paymaster, which pays for operations from its deposit and
+/// charges their senders in a token.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPaymasterProfile {
+    /// The token the paymaster charges senders in
+    pub token: Address,
+    /// The paymaster's price oracle, implementing `getTokenValueOfEth(uint256)`
+    pub oracle: Address,
+}
+
+/// A sender's funds in the token of a token paymaster
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TokenFunds {
+    /// The token the paymaster charges in
+    pub token: Address,
+    /// The operation's maximum gas cost, in the token
+    pub max_token_cost: U256,
+    /// The sender's token balance
+    pub balance: U256,
+    /// The amount of the token the paymaster is allowed to transfer from the sender
+    pub allowance: U256,
+}
+
 /// Prechecker implementation
 #[derive(Debug)]
 pub struct PrecheckerImpl<P: Provider, E: EntryPoint> {
@@ -51,15 +87,17 @@ pub struct PrecheckerImpl<P: Provider, E: EntryPoint> {
     entry_point: E,
     settings: Settings,
     fee_estimator: gas::FeeEstimator<P>,
+    l1_gas_calculator: Arc<dyn L1GasCalculator>,
+    token_paymasters: HashMap<Address, TokenPaymasterProfile>,
 
     cache: RwLock<AsyncDataCache>,
 }
 
 /// Precheck settings
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug)]
 pub struct Settings {
-    /// Chain ID
-    pub chain_id: u64,
+    /// Chain specification
+    pub chain_spec: ChainSpec,
     /// Maximum verification gas allowed for a user operation
     pub max_verification_gas: U256,
     /// Maximum total execution gas allowed for a user operation
@@ -83,7 +121,7 @@ impl Default for Settings {
             bundle_priority_fee_overhead_percent: 0,
             priority_fee_mode: gas::PriorityFeeMode::BaseFeePercent(0),
             max_total_execution_gas: 10_000_000.into(),
-            chain_id: 1,
+            chain_spec: ChainSpec::default(),
             base_fee_accept_percent: 50,
             pre_verification_gas_accept_percent: 100,
         }
@@ -96,6 +134,7 @@ struct AsyncData {
     sender_exists: bool,
     paymaster_exists: bool,
     payer_funds: U256,
+    token_funds: Option<TokenFunds>,
     base_fee: U256,
     min_pre_verification_gas: U256,
 }
@@ -113,16 +152,8 @@ struct FeeCache {
 
 #[async_trait::async_trait]
 impl<P: Provider, E: EntryPoint> Prechecker for PrecheckerImpl<P, E> {
-    async fn check(&self, op: &UserOperation) -> Result<(), PrecheckError> {
-        let async_data = self.load_async_data(op).await?;
-        let mut violations: Vec<PrecheckViolation> = vec![];
-        violations.extend(self.check_init_code(op, async_data));
-        violations.extend(self.check_gas(op, async_data));
-        violations.extend(self.check_payer(op, async_data));
-        if !violations.is_empty() {
-            Err(violations)?
-        }
-        Ok(())
+    async fn check(&self, op: &UserOperation) -> Result<PrecheckReturn, PrecheckError> {
+        self.check_at_block(op, None).await
     }
 
     async fn update_fees(&self) -> anyhow::Result<(GasFees, U256)> {
@@ -139,22 +170,55 @@ impl<P: Provider, E: EntryPoint> Prechecker for PrecheckerImpl<P, E> {
 }
 
 impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
+    /// Run the precheck on the given operation against the state and base fee
+    /// of a block, or of the latest block if `block_hash` is not set.
+    pub async fn check_at_block(
+        &self,
+        op: &UserOperation,
+        block_hash: Option<H256>,
+    ) -> Result<PrecheckReturn, PrecheckError> {
+        let async_data = self.load_async_data(op, block_hash).await?;
+        let mut violations: Vec<PrecheckViolation> = vec![];
+        violations.extend(self.check_init_code(op, async_data));
+        violations.extend(self.check_gas(op, async_data));
+        violations.extend(self.check_payer(op, async_data));
+        violations.extend(self.check_token_payer(async_data));
+        if !violations.is_empty() {
+            Err(violations)?
+        }
+        Ok(PrecheckReturn {
+            token_funds: async_data.token_funds,
+        })
+    }
+
     /// Create a new prechecker
     pub fn new(provider: Arc<P>, entry_point: E, settings: Settings) -> Self {
         Self {
             provider: provider.clone(),
             entry_point,
-            settings,
             fee_estimator: gas::FeeEstimator::new(
-                provider,
-                settings.chain_id,
+                Arc::clone(&provider),
+                &settings.chain_spec,
                 settings.priority_fee_mode,
                 settings.bundle_priority_fee_overhead_percent,
             ),
+            l1_gas_calculator: gas::new_l1_gas_calculator(settings.chain_spec.l1_gas, provider),
+            settings,
+            token_paymasters: HashMap::new(),
             cache: RwLock::new(AsyncDataCache { fees: None }),
         }
     }
 
+    /// Check the token funds of senders using the given token paymasters, by
+    /// paymaster address
+    pub fn with_token_paymasters(
+        mut self,
+        token_paymasters: HashMap<Address, TokenPaymasterProfile>,
+    ) -> Self {
+        self.token_paymasters = token_paymasters;
+        self
+    }
+
     fn check_init_code(
         &self,
         op: &UserOperation,
@@ -194,7 +258,7 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
         async_data: AsyncData,
     ) -> ArrayVec<PrecheckViolation, 6> {
         let Settings {
-            chain_id,
+            ref chain_spec,
             max_verification_gas,
             max_total_execution_gas,
             ..
@@ -215,7 +279,7 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
 
         // compute the worst case total gas limit by assuming the UO is in its own bundle and has a postOp call.
         // This is conservative and potentially may invalidate some very large UOs that would otherwise be valid.
-        let gas_limit = gas::user_operation_execution_gas_limit(op, chain_id, true, true);
+        let gas_limit = gas::user_operation_execution_gas_limit(op, &chain_spec.l1_gas, true, true);
         if gas_limit > max_total_execution_gas {
             violations.push(PrecheckViolation::TotalGasLimitTooHigh(
                 gas_limit,
@@ -241,9 +305,16 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
         let min_priority_fee = self.settings.priority_fee_mode.minimum_priority_fee(
             base_fee,
             self.settings.base_fee_accept_percent,
-            get_min_max_priority_fee_per_gas(self.settings.chain_id),
+            self.settings.chain_spec.min_max_priority_fee_per_gas.into(),
         );
         let min_max_fee = min_base_fee + min_priority_fee;
+        // Without EIP-1559, the entry point only charges the max fee when it equals the
+        // max priority fee, so the priority fee must cover the whole gas price
+        let min_priority_fee = if self.settings.chain_spec.eip1559_enabled {
+            min_priority_fee
+        } else {
+            min_max_fee
+        };
 
         // check priority fee first, since once ruled out we can check max fee
         if op.max_priority_fee_per_gas < min_priority_fee {
@@ -301,20 +372,55 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
         None
     }
 
-    async fn load_async_data(&self, op: &UserOperation) -> anyhow::Result<AsyncData> {
-        let (_, base_fee) = self.get_fees().await?;
+    /// The sender of an operation using a token paymaster must hold, and have
+    /// approved the paymaster to transfer, enough tokens to pay the
+    /// operation's maximum gas cost.
+    fn check_token_payer(&self, async_data: AsyncData) -> ArrayVec<PrecheckViolation, 2> {
+        let mut violations = ArrayVec::new();
+        let Some(token_funds) = async_data.token_funds else {
+            return violations;
+        };
+        if token_funds.balance < token_funds.max_token_cost {
+            violations.push(PrecheckViolation::SenderTokenBalanceTooLow(
+                token_funds.token,
+                token_funds.balance,
+                token_funds.max_token_cost,
+            ));
+        }
+        if token_funds.allowance < token_funds.max_token_cost {
+            violations.push(PrecheckViolation::SenderTokenAllowanceTooLow(
+                token_funds.token,
+                token_funds.allowance,
+                token_funds.max_token_cost,
+            ));
+        }
+        violations
+    }
+
+    async fn load_async_data(
+        &self,
+        op: &UserOperation,
+        block_hash: Option<H256>,
+    ) -> anyhow::Result<AsyncData> {
+        let base_fee = match block_hash {
+            Some(block_hash) => self.get_block_base_fee(block_hash).await?,
+            None => self.get_fees().await?.1,
+        };
+        let block = block_hash.map(BlockId::from);
 
         let (
             factory_exists,
             sender_exists,
             paymaster_exists,
             payer_funds,
+            token_funds,
             min_pre_verification_gas,
         ) = tokio::try_join!(
-            self.is_contract(op.factory()),
-            self.is_contract(Some(op.sender)),
-            self.is_contract(op.paymaster()),
-            self.get_payer_funds(op),
+            self.is_contract(op.factory(), block_hash),
+            self.is_contract(Some(op.sender), block_hash),
+            self.is_contract(op.paymaster(), block_hash),
+            self.get_payer_funds(op, block),
+            self.get_token_funds(op, block),
             self.get_required_pre_verification_gas(op.clone(), base_fee)
         )?;
         Ok(AsyncData {
@@ -322,51 +428,117 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
             sender_exists,
             paymaster_exists,
             payer_funds,
+            token_funds,
             base_fee,
             min_pre_verification_gas,
         })
     }
 
-    async fn is_contract(&self, address: Option<Address>) -> anyhow::Result<bool> {
+    async fn is_contract(
+        &self,
+        address: Option<Address>,
+        block_hash: Option<H256>,
+    ) -> anyhow::Result<bool> {
         let Some(address) = address else {
             return Ok(false);
         };
         let bytecode = self
             .provider
-            .get_code(address, None)
+            .get_code(address, block_hash)
             .await
             .context("should load code to check if contract exists")?;
         Ok(!bytecode.is_empty())
     }
 
-    async fn get_payer_funds(&self, op: &UserOperation) -> anyhow::Result<U256> {
-        let (deposit, balance) =
-            tokio::try_join!(self.get_payer_deposit(op), self.get_payer_balance(op),)?;
+    async fn get_payer_funds(
+        &self,
+        op: &UserOperation,
+        block: Option<BlockId>,
+    ) -> anyhow::Result<U256> {
+        let (deposit, balance) = tokio::try_join!(
+            self.get_payer_deposit(op, block),
+            self.get_payer_balance(op, block),
+        )?;
         Ok(deposit + balance)
     }
 
-    async fn get_payer_deposit(&self, op: &UserOperation) -> anyhow::Result<U256> {
+    async fn get_payer_deposit(
+        &self,
+        op: &UserOperation,
+        block: Option<BlockId>,
+    ) -> anyhow::Result<U256> {
         let payer = match op.paymaster() {
             Some(paymaster) => paymaster,
             None => op.sender,
         };
         self.entry_point
-            .balance_of(payer, None)
+            .balance_of(payer, block)
             .await
             .context("precheck should get payer balance")
     }
 
-    async fn get_payer_balance(&self, op: &UserOperation) -> anyhow::Result<U256> {
+    async fn get_payer_balance(
+        &self,
+        op: &UserOperation,
+        block: Option<BlockId>,
+    ) -> anyhow::Result<U256> {
         if !op.paymaster_and_data.is_empty() {
             // Paymasters must deposit eth, and cannot pay with their own.
             return Ok(0.into());
         }
         self.provider
-            .get_balance(op.sender, None)
+            .get_balance(op.sender, block)
             .await
             .context("precheck should get sender balance")
     }
 
+    async fn get_token_funds(
+        &self,
+        op: &UserOperation,
+        block: Option<BlockId>,
+    ) -> anyhow::Result<Option<TokenFunds>> {
+        let Some((paymaster, profile)) = op
+            .paymaster()
+            .and_then(|paymaster| Some((paymaster, self.token_paymasters.get(&paymaster)?)))
+        else {
+            return Ok(None);
+        };
+        let (max_token_cost, balance, allowance) = tokio::try_join!(
+            Arc::clone(&self.provider).get_token_value_of_eth(
+                profile.oracle,
+                op.max_gas_cost(),
+                block
+            ),
+            Arc::clone(&self.provider).get_token_balance(profile.token, op.sender, block),
+            Arc::clone(&self.provider).get_token_allowance(
+                profile.token,
+                op.sender,
+                paymaster,
+                block
+            ),
+        )
+        .context("precheck should get sender token funds")?;
+        Ok(Some(TokenFunds {
+            token: profile.token,
+            max_token_cost,
+            balance,
+            allowance,
+        }))
+    }
+
+    // Chains without a base fee price operations at the current gas price
+    async fn get_block_base_fee(&self, block_hash: H256) -> anyhow::Result<U256> {
+        let block = self
+            .provider
+            .get_block(block_hash)
+            .await?
+            .with_context(|| format!("block {block_hash:?} should exist"))?;
+        match block.base_fee_per_gas {
+            Some(base_fee) => Ok(base_fee),
+            None => Ok(self.get_fees().await?.1),
+        }
+    }
+
     async fn get_fees(&self) -> anyhow::Result<(GasFees, U256)> {
         if let Some(fees) = self.cache.read().unwrap().fees {
             return Ok((fees.bundle_fees, fees.base_fee));
@@ -382,8 +554,7 @@ impl<P: Provider, E: EntryPoint> PrecheckerImpl<P, E> {
         gas::calc_required_pre_verification_gas(
             &op,
             self.entry_point.address(),
-            self.provider.clone(),
-            self.settings.chain_id,
+            self.l1_gas_calculator.as_ref(),
             base_fee,
         )
         .await
@@ -431,6 +602,18 @@ pub enum PrecheckViolation {
     /// (when not using a paymaster)
     #[display("sender balance and deposit together is {0} but must be at least {1} to pay for this operation")]
     SenderFundsTooLow(U256, U256),
+    /// The sender's balance of a token paymaster's token is too low to pay
+    /// for the user operation's maximum cost in the token. The pool raises
+    /// it too when, with paymaster tracking enabled, the balance can't cover
+    /// the sender's pending operations using the paymaster as well.
+    #[display("sender balance of token {0:?} is {1} but must be at least {2} to pay the paymaster for this operation")]
+    SenderTokenBalanceTooLow(Address, U256, U256),
+    /// The sender's allowance to a token paymaster is too low to pay for the
+    /// user operation's maximum cost in the token. The pool raises it too
+    /// when, with paymaster tracking enabled, the allowance can't cover the
+    /// sender's pending operations using the paymaster as well.
+    #[display("sender allowance of token {0:?} to the paymaster is {1} but must be at least {2} to pay for this operation")]
+    SenderTokenAllowanceTooLow(Address, U256, U256),
     /// The provided max priority fee per gas is too low based on the current network rate.
     #[display("maxPriorityFeePerGas is {0} but must be at least {1}")]
     MaxPriorityFeePerGasTooLow(U256, U256),
@@ -446,7 +629,7 @@ pub enum PrecheckViolation {
 mod tests {
     use std::str::FromStr;
 
-    use ethers::types::{Bytes, Chain};
+    use ethers::types::Bytes;
     use rundler_provider::{MockEntryPoint, MockProvider};
 
     use super::*;
@@ -461,6 +644,7 @@ mod tests {
             sender_exists: true,
             paymaster_exists: true,
             payer_funds: 5_000_000.into(),
+            token_funds: None,
             base_fee: 4_000.into(),
             min_pre_verification_gas: 1_000.into(),
         }
@@ -500,7 +684,7 @@ mod tests {
     async fn test_check_gas() {
         let (provider, entry_point) = create_base_config();
         let test_settings = Settings {
-            chain_id: 1,
+            chain_spec: ChainSpec::default(),
             max_verification_gas: 5_000_000.into(),
             max_total_execution_gas: 10_000_000.into(),
             bundle_priority_fee_overhead_percent: 0,
@@ -569,22 +753,74 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_check_token_payer() {
+        let (provider, entry_point) = create_base_config();
+        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, Settings::default());
+        let token = Address::random();
+        let token_funds = TokenFunds {
+            token,
+            max_token_cost: 1_000.into(),
+            balance: 999.into(),
+            allowance: 1_000.into(),
+        };
+        let async_data = AsyncData {
+            token_funds: Some(token_funds),
+            ..get_test_async_data()
+        };
+
+        let res = prechecker.check_token_payer(async_data);
+        assert_eq!(
+            res.to_vec(),
+            vec![PrecheckViolation::SenderTokenBalanceTooLow(
+                token,
+                999.into(),
+                1_000.into()
+            )]
+        );
+
+        let async_data = AsyncData {
+            token_funds: Some(TokenFunds {
+                balance: 1_000.into(),
+                allowance: 0.into(),
+                ..token_funds
+            }),
+            ..get_test_async_data()
+        };
+        let res = prechecker.check_token_payer(async_data);
+        assert_eq!(
+            res.to_vec(),
+            vec![PrecheckViolation::SenderTokenAllowanceTooLow(
+                token,
+                0.into(),
+                1_000.into()
+            )]
+        );
+
+        assert!(prechecker
+            .check_token_payer(get_test_async_data())
+            .is_empty());
+    }
+
     #[tokio::test]
     async fn test_check_fees() {
         let settings = Settings {
-            chain_id: Chain::Optimism as u64,
+            chain_spec: ChainSpec {
+                min_max_priority_fee_per_gas: 100_000,
+                ..Default::default()
+            },
             base_fee_accept_percent: 80,
             priority_fee_mode: gas::PriorityFeeMode::PriorityFeeIncreasePercent(0),
             ..Default::default()
         };
         let (provider, entry_point) = create_base_config();
-        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings);
+        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings.clone());
 
         let mut async_data = get_test_async_data();
         async_data.base_fee = 5_000.into();
         async_data.min_pre_verification_gas = 1_000.into();
 
-        let mintip = get_min_max_priority_fee_per_gas(Chain::Optimism as u64);
+        let mintip = U256::from(settings.chain_spec.min_max_priority_fee_per_gas);
         let op = UserOperation {
             max_fee_per_gas: U256::from(math::percent(5000, settings.base_fee_accept_percent))
                 + mintip,
@@ -605,13 +841,12 @@ mod tests {
     #[tokio::test]
     async fn test_check_fees_too_low() {
         let settings = Settings {
-            chain_id: 10000000,
             base_fee_accept_percent: 80,
             priority_fee_mode: gas::PriorityFeeMode::PriorityFeeIncreasePercent(0),
             ..Default::default()
         };
         let (provider, entry_point) = create_base_config();
-        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings);
+        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings.clone());
 
         let mut async_data = get_test_async_data();
         async_data.base_fee = 5_000.into();
@@ -638,19 +873,22 @@ mod tests {
     #[tokio::test]
     async fn test_check_fees_min() {
         let settings = Settings {
-            chain_id: Chain::Optimism as u64,
+            chain_spec: ChainSpec {
+                min_max_priority_fee_per_gas: 100_000,
+                ..Default::default()
+            },
             base_fee_accept_percent: 100,
             priority_fee_mode: gas::PriorityFeeMode::PriorityFeeIncreasePercent(0),
             ..Default::default()
         };
         let (provider, entry_point) = create_base_config();
-        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings);
+        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings.clone());
 
         let mut async_data = get_test_async_data();
         async_data.base_fee = 5_000.into();
         async_data.min_pre_verification_gas = 1_000.into();
 
-        let mintip = get_min_max_priority_fee_per_gas(Chain::Optimism as u64);
+        let mintip = U256::from(settings.chain_spec.min_max_priority_fee_per_gas);
         let undertip = mintip - U256::from(1);
 
         let op = UserOperation {
@@ -664,23 +902,62 @@ mod tests {
         let res = prechecker.check_gas(&op, async_data);
         let mut expected = ArrayVec::<PrecheckViolation, 6>::new();
         expected.push(PrecheckViolation::MaxPriorityFeePerGasTooLow(
-            get_min_max_priority_fee_per_gas(Chain::Optimism as u64) - U256::from(1),
-            get_min_max_priority_fee_per_gas(Chain::Optimism as u64),
+            undertip, mintip,
         ));
 
         assert_eq!(res, expected);
     }
 
+    #[tokio::test]
+    async fn test_check_fees_legacy() {
+        let settings = Settings {
+            chain_spec: ChainSpec {
+                eip1559_enabled: false,
+                ..Default::default()
+            },
+            base_fee_accept_percent: 100,
+            priority_fee_mode: gas::PriorityFeeMode::PriorityFeeIncreasePercent(0),
+            ..Default::default()
+        };
+        let (provider, entry_point) = create_base_config();
+        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings);
+
+        let mut async_data = get_test_async_data();
+        async_data.base_fee = 5_000.into();
+        async_data.min_pre_verification_gas = 1_000.into();
+
+        // a low priority fee is rejected even though the max fee covers the gas price
+        let op = UserOperation {
+            max_fee_per_gas: 5_000.into(),
+            max_priority_fee_per_gas: 0.into(),
+            pre_verification_gas: 1_000.into(),
+            call_gas_limit: MIN_CALL_GAS_LIMIT,
+            ..Default::default()
+        };
+        let res = prechecker.check_gas(&op, async_data);
+        let mut expected = ArrayVec::<PrecheckViolation, 6>::new();
+        expected.push(PrecheckViolation::MaxPriorityFeePerGasTooLow(
+            0.into(),
+            5_000.into(),
+        ));
+        assert_eq!(res, expected);
+
+        let op = UserOperation {
+            max_priority_fee_per_gas: 5_000.into(),
+            ..op
+        };
+        assert!(prechecker.check_gas(&op, async_data).is_empty());
+    }
+
     #[tokio::test]
     async fn test_pvg_too_low() {
         let settings = Settings {
-            chain_id: 10000000,
             base_fee_accept_percent: 80,
             priority_fee_mode: gas::PriorityFeeMode::PriorityFeeIncreasePercent(0),
             ..Default::default()
         };
         let (provider, entry_point) = create_base_config();
-        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings);
+        let prechecker = PrecheckerImpl::new(Arc::new(provider), entry_point, settings.clone());
 
         let mut async_data = get_test_async_data();
         async_data.base_fee = 5_000.into();
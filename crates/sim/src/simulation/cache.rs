@@ -0,0 +1,335 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::collections::HashSet;
+
+use ethers::{
+    abi::AbiEncode,
+    types::{Address, H256},
+    utils::keccak256,
+};
+use indexmap::IndexMap;
+use parking_lot::Mutex;
+use rundler_provider::StateDiff;
+use rundler_types::UserOperation;
+
+use super::SimulationResult;
+
+/// A cache of successful simulation results, shared by the simulators of all
+/// entry points.
+///
+/// A result is reused for an identical user operation simulated against the
+/// block it is known to be valid at, as long as the expected code hash matches.
+/// On each new block the cached results whose dependencies did not change are
+/// carried forward to the new block, and all others are dropped. Only results
+/// at the previous head are carried forward, as the state diff of the new block
+/// does not cover the changes since any older block. A result
+/// depends on the state of its entities, the accounts whose storage it
+/// accessed, and the values of the storage slots it read.
+///
+/// The entry point's own storage, such as deposits and nonces, is not covered
+/// by the state diff, so results are also dropped when any of their entities
+/// had an operation mined or a deposit change.
+#[derive(Debug)]
+pub struct SimulationCache {
+    max_entries: usize,
+    entries: Mutex<IndexMap<(Address, H256), SimulationResult>>,
+    head: Mutex<Option<H256>>,
+}
+
+impl SimulationCache {
+    /// Create a new cache holding at most `max_entries` results
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries,
+            entries: Mutex::new(IndexMap::new()),
+            head: Mutex::new(None),
+        }
+    }
+
+    /// Carry the cached results forward to a new block.
+    ///
+    /// `state_diff` contains the changes made by all blocks since the previous
+    /// head, and `touched_entities` the senders and paymasters of ops mined in
+    /// them along with the accounts whose entry point deposit changed. If the
+    /// state diff is unknown, such as after a reorg, the cache is cleared.
+    pub fn on_new_block(
+        &self,
+        block_hash: H256,
+        state_diff: Option<&StateDiff>,
+        touched_entities: &HashSet<Address>,
+    ) {
+        let mut entries = self.entries.lock();
+        let previous_head = self.head.lock().replace(block_hash);
+        let Some(state_diff) = state_diff else {
+            SimulationCacheMetrics::increment_invalidations(entries.len());
+            entries.clear();
+            SimulationCacheMetrics::set_num_entries(0);
+            return;
+        };
+
+        let num_entries = entries.len();
+        entries.retain(|&(entry_point, _), result| {
+            // results simulated at an older block may depend on changes
+            // that are not in the state diff
+            if Some(result.block_hash) != previous_head
+                || is_stale(entry_point, result, state_diff, touched_entities)
+            {
+                return false;
+            }
+            result.block_hash = block_hash;
+            true
+        });
+        SimulationCacheMetrics::increment_invalidations(num_entries - entries.len());
+        SimulationCacheMetrics::set_num_entries(entries.len());
+    }
+
+    /// Remove all cached results
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock();
+        SimulationCacheMetrics::increment_invalidations(entries.len());
+        entries.clear();
+        SimulationCacheMetrics::set_num_entries(0);
+    }
+
+    // Keys results by the hash of the full operation, including its signature
+    pub(crate) fn key(op: &UserOperation) -> H256 {
+        keccak256(op.clone().encode()).into()
+    }
+
+    pub(crate) fn get(
+        &self,
+        entry_point: Address,
+        key: H256,
+        block_hash: H256,
+        expected_code_hash: Option<H256>,
+    ) -> Option<SimulationResult> {
+        let result = self
+            .entries
+            .lock()
+            .get(&(entry_point, key))
+            .filter(|result| {
+                result.block_hash == block_hash
+                    && expected_code_hash.map_or(true, |hash| hash == result.code_hash)
+            })
+            .cloned();
+        SimulationCacheMetrics::increment_lookups(entry_point, result.is_some());
+        result
+    }
+
+    pub(crate) fn insert(&self, entry_point: Address, key: H256, result: SimulationResult) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        entries.shift_remove(&(entry_point, key));
+        if entries.len() >= self.max_entries {
+            entries.shift_remove_index(0);
+        }
+        entries.insert((entry_point, key), result);
+        SimulationCacheMetrics::set_num_entries(entries.len());
+    }
+}
+
+fn is_stale(
+    entry_point: Address,
+    result: &SimulationResult,
+    state_diff: &StateDiff,
+    touched_entities: &HashSet<Address>,
+) -> bool {
+    let mut entities = result.entity_infos.entities().map(|(_, info)| info.address);
+    if entities.any(|address| {
+        touched_entities.contains(&address) || state_diff.accounts.contains(&address)
+    }) {
+        return true;
+    }
+    // The entry point's balance changes with every bundle, and its storage is
+    // covered by the touched entities
+    if result
+        .accessed_addresses
+        .iter()
+        .any(|address| *address != entry_point && state_diff.accounts.contains(address))
+    {
+        return true;
+    }
+    result
+        .expected_storage
+        .slots()
+        .any(|(address, slot)| state_diff.is_slot_changed(address, slot))
+}
+
+struct SimulationCacheMetrics {}
+
+impl SimulationCacheMetrics {
+    fn increment_lookups(entry_point: Address, hit: bool) {
+        if hit {
+            metrics::increment_counter!("sim_cache_hits", "entrypoint" => entry_point.to_string());
+        } else {
+            metrics::increment_counter!("sim_cache_misses", "entrypoint" => entry_point.to_string());
+        }
+    }
+
+    fn increment_invalidations(num_entries: usize) {
+        metrics::counter!("sim_cache_invalidations", num_entries as u64);
+    }
+
+    fn set_num_entries(num_entries: usize) {
+        metrics::gauge!("sim_cache_num_entries", num_entries as f64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{simulation::EntityInfos, ExpectedStorage};
+
+    fn result(sender: Address, block_hash: H256, storage: &[(Address, H256)]) -> SimulationResult {
+        let mut expected_storage = ExpectedStorage::default();
+        for &(address, slot) in storage {
+            expected_storage.insert(address, slot, H256::zero());
+        }
+        SimulationResult {
+            block_hash,
+            code_hash: H256::repeat_byte(1),
+            accessed_addresses: storage.iter().map(|(address, _)| *address).collect(),
+            expected_storage,
+            entity_infos: EntityInfos {
+                sender: crate::simulation::EntityInfo {
+                    address: sender,
+                    is_staked: false,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_requires_block_and_code_hash() {
+        let cache = SimulationCache::new(10);
+        let (entry_point, key, block) = (Address::random(), H256::random(), H256::random());
+        cache.insert(entry_point, key, result(Address::random(), block, &[]));
+
+        assert!(cache.get(entry_point, key, block, None).is_some());
+        assert!(cache
+            .get(entry_point, key, block, Some(H256::repeat_byte(1)))
+            .is_some());
+        assert!(cache
+            .get(entry_point, key, block, Some(H256::repeat_byte(2)))
+            .is_none());
+        assert!(cache.get(entry_point, key, H256::random(), None).is_none());
+        assert!(cache.get(Address::random(), key, block, None).is_none());
+    }
+
+    #[test]
+    fn test_on_new_block_invalidates_changed_state() {
+        let cache = SimulationCache::new(10);
+        let entry_point = Address::random();
+        let (block, next_block) = (H256::random(), H256::random());
+        let token = Address::random();
+        let (unchanged, changed_slot, changed_account, mined) = (
+            H256::random(),
+            H256::random(),
+            H256::random(),
+            H256::random(),
+        );
+        let mined_sender = Address::random();
+        let changed_sender = Address::random();
+        cache.on_new_block(block, None, &HashSet::new());
+
+        cache.insert(
+            entry_point,
+            unchanged,
+            result(
+                Address::random(),
+                block,
+                &[(token, H256::from_low_u64_be(1))],
+            ),
+        );
+        cache.insert(
+            entry_point,
+            changed_slot,
+            result(
+                Address::random(),
+                block,
+                &[(token, H256::from_low_u64_be(2))],
+            ),
+        );
+        cache.insert(
+            entry_point,
+            changed_account,
+            result(changed_sender, block, &[]),
+        );
+        cache.insert(entry_point, mined, result(mined_sender, block, &[]));
+
+        let state_diff = StateDiff {
+            accounts: HashSet::from([changed_sender, entry_point]),
+            storage: HashMap::from([(token, HashSet::from([H256::from_low_u64_be(2)]))]),
+        };
+        cache.on_new_block(
+            next_block,
+            Some(&state_diff),
+            &HashSet::from([mined_sender]),
+        );
+
+        assert!(cache.get(entry_point, unchanged, block, None).is_none());
+        assert!(cache
+            .get(entry_point, unchanged, next_block, None)
+            .is_some());
+        for key in [changed_slot, changed_account, mined] {
+            assert!(cache.get(entry_point, key, next_block, None).is_none());
+        }
+
+        // without a state diff, nothing can be carried forward
+        cache.on_new_block(H256::random(), None, &HashSet::new());
+        assert!(cache.entries.lock().is_empty());
+    }
+
+    #[test]
+    fn test_on_new_block_drops_results_behind_head() {
+        let cache = SimulationCache::new(10);
+        let entry_point = Address::random();
+        let (old_block, block, next_block) = (H256::random(), H256::random(), H256::random());
+        let (at_head, behind_head) = (H256::random(), H256::random());
+        cache.on_new_block(block, None, &HashSet::new());
+        cache.insert(entry_point, at_head, result(Address::random(), block, &[]));
+        cache.insert(
+            entry_point,
+            behind_head,
+            result(Address::random(), old_block, &[]),
+        );
+
+        cache.on_new_block(next_block, Some(&StateDiff::default()), &HashSet::new());
+        assert!(cache.get(entry_point, at_head, next_block, None).is_some());
+        assert!(cache
+            .get(entry_point, behind_head, next_block, None)
+            .is_none());
+        assert_eq!(cache.entries.lock().len(), 1);
+    }
+
+    #[test]
+    fn test_insert_evicts_oldest() {
+        let cache = SimulationCache::new(2);
+        let entry_point = Address::random();
+        let block = H256::random();
+        let keys = [H256::random(), H256::random(), H256::random()];
+        for key in keys {
+            cache.insert(entry_point, key, result(Address::random(), block, &[]));
+        }
+        assert!(cache.get(entry_point, keys[0], block, None).is_none());
+        assert!(cache.get(entry_point, keys[1], block, None).is_some());
+        assert!(cache.get(entry_point, keys[2], block, None).is_some());
+    }
+}
@@ -13,20 +13,85 @@
 
 use std::{collections::HashMap, str::FromStr};
 
-use ethers::types::{Address, Opcode, H256, U256};
+use anyhow::{bail, ensure};
+use ethers::{
+    types::{Address, Opcode, H256, U256},
+    utils::keccak256,
+};
 use rundler_types::{Entity, EntityType};
-use serde::Deserialize;
+use serde::{de, Deserialize, Deserializer};
 use serde_with::{serde_as, DisplayFromStr};
+use strum::{EnumDiscriminants, IntoStaticStr};
 
-use crate::simulation::SimulationViolation;
+use crate::simulation::{SimulationViolation, ValidationUsage};
 
 /// A mempool configuration.
 ///
-/// Typically read from a JSON file using the `Deserialize` trait.
-#[derive(Debug, Clone, Deserialize, Default)]
+/// Typically read from a JSON file using the `Deserialize` trait. Each allowlist
+/// entry is validated when deserialized, and errors identify the offending entry
+/// by its index and description.
+#[derive(Debug, Clone, Default)]
 pub struct MempoolConfig {
     /// Allowlist to match violations against.
     pub(crate) allowlist: Vec<AllowlistEntry>,
+    /// Maximum gas the validation phases may use, in total, for an operation
+    /// to be accepted into the mempool.
+    pub(crate) max_verification_gas: Option<u64>,
+    /// Maximum number of opcodes the entities may execute during validation,
+    /// in total, for an operation to be accepted into the mempool.
+    pub(crate) max_opcodes: Option<u64>,
+}
+
+impl<'de> Deserialize<'de> for MempoolConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct RawMempoolConfig {
+            allowlist: Vec<serde_json::Value>,
+            #[serde(default)]
+            max_verification_gas: Option<u64>,
+            #[serde(default)]
+            max_opcodes: Option<u64>,
+        }
+
+        let raw = RawMempoolConfig::deserialize(deserializer)?;
+        let allowlist = raw
+            .allowlist
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let description = value
+                    .get("description")
+                    .and_then(|d| d.as_str())
+                    .map(|d| format!(" ({d})"))
+                    .unwrap_or_default();
+                serde_json::from_value::<AllowlistEntry>(value)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|entry| entry.validate().map(|_| entry))
+                    .map_err(|e| {
+                        de::Error::custom(format!("invalid allowlist entry {i}{description}: {e}"))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            allowlist,
+            max_verification_gas: raw.max_verification_gas,
+            max_opcodes: raw.max_opcodes,
+        })
+    }
+}
+
+impl MempoolConfig {
+    /// Check if validation stayed within the limits of the mempool.
+    fn within_limits(&self, usage: &ValidationUsage) -> bool {
+        self.max_verification_gas
+            .map_or(true, |max| usage.gas_used <= max)
+            && self.max_opcodes.map_or(true, |max| usage.opcodes <= max)
+    }
 }
 
 /// The entity allowed by an allowlist entry.
@@ -64,11 +129,89 @@ impl AllowEntity {
             AllowEntity::Address(address) => entity.address == *address,
         }
     }
+
+    fn can_be(&self, kind: EntityType) -> bool {
+        match self {
+            AllowEntity::Any | AllowEntity::Address(_) => true,
+            AllowEntity::Type(t) => *t == kind,
+        }
+    }
 }
 
-/// An allowlist rule.
+/// A pattern matching storage slots.
 #[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub(crate) enum SlotPattern {
+    /// Any slot
+    Any,
+    /// A single slot
+    Exact(U256),
+    /// Slots in the range `[start, end)`
+    Range { start: U256, end: U256 },
+    /// Slots of a mapping entry, `keccak256(key . base_slot) + offset` for
+    /// `offset` in `[0, max_offset]`. Keys are 32 byte words, addresses must be
+    /// left padded.
+    #[serde(rename_all = "camelCase")]
+    Mapping {
+        base_slot: U256,
+        keys: Vec<H256>,
+        #[serde(default)]
+        max_offset: u64,
+    },
+}
+
+impl SlotPattern {
+    fn matches(&self, slot: U256) -> bool {
+        match self {
+            SlotPattern::Any => true,
+            SlotPattern::Exact(s) => *s == slot,
+            SlotPattern::Range { start, end } => *start <= slot && slot < *end,
+            SlotPattern::Mapping {
+                base_slot,
+                keys,
+                max_offset,
+            } => keys.iter().any(|key| {
+                let mut preimage = [0_u8; 64];
+                preimage[..32].copy_from_slice(key.as_bytes());
+                base_slot.to_big_endian(&mut preimage[32..]);
+                let entry_slot = U256::from_big_endian(&keccak256(preimage));
+                slot >= entry_slot && slot - entry_slot <= U256::from(*max_offset)
+            }),
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            SlotPattern::Range { start, end } => {
+                ensure!(
+                    start < end,
+                    "slot range start {start} must be less than end {end}"
+                )
+            }
+            SlotPattern::Mapping { keys, .. } => {
+                ensure!(!keys.is_empty(), "slot mapping must have at least one key")
+            }
+            SlotPattern::Any | SlotPattern::Exact(_) => {}
+        }
+        Ok(())
+    }
+}
+
+// Opcodes used to access the code of another contract
+const CODE_ACCESS_OPCODES: [Opcode; 3] = [
+    Opcode::EXTCODEHASH,
+    Opcode::EXTCODESIZE,
+    Opcode::EXTCODECOPY,
+];
+
+/// An allowlist rule.
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq, EnumDiscriminants)]
 #[serde(tag = "rule", rename_all = "camelCase")]
+#[strum_discriminants(
+    name(AllowRuleKind),
+    derive(IntoStaticStr),
+    strum(serialize_all = "camelCase")
+)]
 pub(crate) enum AllowRule {
     /// Allowlist a forbidden opcode on a contract.
     ForbiddenOpcode { contract: Address, opcode: Opcode },
@@ -79,16 +222,33 @@ pub(crate) enum AllowRule {
     },
     /// Allowlist an invalid storage access by its address/slot
     InvalidStorageAccess { contract: Address, slot: U256 },
+    /// Allowlist invalid storage accesses on a contract matching a slot pattern
+    StorageAccess {
+        contract: Address,
+        slots: SlotPattern,
+    },
     /// Allowlist a call with value
     CallWithValue,
     /// Allowlist a not staked violation
     NotStaked,
+    /// Allowlist a not staked violation only for storage accesses on a contract
+    /// matching a slot pattern
+    NotStakedStorageAccess {
+        contract: Address,
+        slots: SlotPattern,
+    },
+    /// Allowlist accessing the code of a contract, either while it is undeployed
+    /// or via the `EXTCODE*` opcodes
+    CodeAccess { contract: Address },
+    /// Allowlist a factory creating more than one contract with `CREATE2`
+    MultipleCreate2,
+    /// Allowlist calling entry point methods other than `depositTo`
+    EntryPointMethod,
 }
 
 /// An allowlist entry
 #[serde_as]
 #[derive(Debug, Clone, Deserialize)]
-
 pub(crate) struct AllowlistEntry {
     /// The entity allowed by this entry.
     #[serde_as(as = "DisplayFromStr")]
@@ -96,12 +256,46 @@ pub(crate) struct AllowlistEntry {
     /// The rule allowed by this entry.
     #[serde(flatten)]
     pub(crate) rule: AllowRule,
+    /// Optional human readable description of the entry.
+    #[serde(default)]
+    pub(crate) description: Option<String>,
 }
 
 impl AllowlistEntry {
     #[cfg(test)]
     fn new(entity: AllowEntity, rule: AllowRule) -> Self {
-        Self { entity, rule }
+        Self {
+            entity,
+            rule,
+            description: None,
+        }
+    }
+
+    /// Check that the entry can match a violation.
+    fn validate(&self) -> anyhow::Result<()> {
+        match &self.rule {
+            AllowRule::ForbiddenPrecompile { precompile, .. } => {
+                // precompiles live at the lowest addresses
+                ensure!(
+                    precompile.as_bytes()[..18].iter().all(|b| *b == 0),
+                    "{precompile:?} is not a precompile address"
+                );
+            }
+            AllowRule::StorageAccess { slots, .. }
+            | AllowRule::NotStakedStorageAccess { slots, .. } => slots.validate()?,
+            AllowRule::MultipleCreate2 => {
+                if !self.entity.can_be(EntityType::Factory) {
+                    bail!("rule multipleCreate2 only applies to factories");
+                }
+            }
+            AllowRule::ForbiddenOpcode { .. }
+            | AllowRule::InvalidStorageAccess { .. }
+            | AllowRule::CallWithValue
+            | AllowRule::NotStaked
+            | AllowRule::CodeAccess { .. }
+            | AllowRule::EntryPointMethod => {}
+        }
+        Ok(())
     }
 
     /// Check if the allowlist entry allows the given violation.
@@ -149,6 +343,17 @@ impl AllowlistEntry {
                     false
                 }
             }
+            AllowRule::StorageAccess { contract, slots } => {
+                if let SimulationViolation::InvalidStorageAccess(violation_entity, violation_slot) =
+                    violation
+                {
+                    self.entity.is_allowed(violation_entity)
+                        && *contract == violation_slot.address
+                        && slots.matches(violation_slot.slot)
+                } else {
+                    false
+                }
+            }
             AllowRule::CallWithValue => {
                 if let SimulationViolation::CallHadValue(violation_entity) = violation {
                     self.entity.is_allowed(violation_entity)
@@ -163,10 +368,121 @@ impl AllowlistEntry {
                     false
                 }
             }
+            AllowRule::NotStakedStorageAccess { contract, slots } => {
+                if let SimulationViolation::NotStaked(stake_data) = violation {
+                    self.entity.is_allowed(&stake_data.entity)
+                        && *contract == stake_data.accessed_address
+                        && slots.matches(stake_data.slot)
+                } else {
+                    false
+                }
+            }
+            AllowRule::CodeAccess { contract } => match violation {
+                SimulationViolation::AccessedUndeployedContract(
+                    violation_entity,
+                    violation_contract,
+                ) => self.entity.is_allowed(violation_entity) && contract == violation_contract,
+                SimulationViolation::UsedForbiddenOpcode(
+                    violation_entity,
+                    violation_contract,
+                    violation_opcode,
+                ) => {
+                    self.entity.is_allowed(violation_entity)
+                        && contract == violation_contract
+                        && CODE_ACCESS_OPCODES.contains(&violation_opcode.0)
+                }
+                _ => false,
+            },
+            AllowRule::MultipleCreate2 => {
+                if let SimulationViolation::FactoryCalledCreate2Twice(factory) = violation {
+                    self.entity.is_allowed(&Entity::factory(*factory))
+                } else {
+                    false
+                }
+            }
+            AllowRule::EntryPointMethod => {
+                if let SimulationViolation::CalledBannedEntryPointMethod(violation_entity) =
+                    violation
+                {
+                    self.entity.is_allowed(violation_entity)
+                } else {
+                    false
+                }
+            }
         }
     }
 }
 
+impl AllowRule {
+    /// The name of the rule in mempool configurations
+    fn name(&self) -> &'static str {
+        AllowRuleKind::from(self).into()
+    }
+}
+
+/// Returns the names of the allowlist rules that can allow a violation. Empty if
+/// the violation cannot be allowlisted.
+pub(crate) fn allow_rules_for(violation: &SimulationViolation) -> Vec<&'static str> {
+    let kinds: &[AllowRuleKind] = match violation {
+        SimulationViolation::UsedForbiddenOpcode(_, _, opcode)
+            if CODE_ACCESS_OPCODES.contains(&opcode.0) =>
+        {
+            &[AllowRuleKind::ForbiddenOpcode, AllowRuleKind::CodeAccess]
+        }
+        SimulationViolation::UsedForbiddenOpcode(..) => &[AllowRuleKind::ForbiddenOpcode],
+        SimulationViolation::UsedForbiddenPrecompile(..) => &[AllowRuleKind::ForbiddenPrecompile],
+        SimulationViolation::AccessedUndeployedContract(..) => &[AllowRuleKind::CodeAccess],
+        SimulationViolation::FactoryCalledCreate2Twice(_) => &[AllowRuleKind::MultipleCreate2],
+        SimulationViolation::InvalidStorageAccess(..) => &[
+            AllowRuleKind::InvalidStorageAccess,
+            AllowRuleKind::StorageAccess,
+        ],
+        SimulationViolation::CalledBannedEntryPointMethod(_) => &[AllowRuleKind::EntryPointMethod],
+        SimulationViolation::CallHadValue(_) => &[AllowRuleKind::CallWithValue],
+        SimulationViolation::NotStaked(_) => &[
+            AllowRuleKind::NotStaked,
+            AllowRuleKind::NotStakedStorageAccess,
+        ],
+        _ => &[],
+    };
+    kinds.iter().map(|&kind| kind.into()).collect()
+}
+
+/// An allowlist entry of a mempool that allows a violation
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AllowlistMatch {
+    /// ID of the mempool
+    pub mempool: H256,
+    /// Index of the entry in the mempool's allowlist
+    pub index: usize,
+    /// Name of the entry's rule
+    pub rule: &'static str,
+    /// Description of the entry, if any
+    pub description: Option<String>,
+}
+
+/// Returns every allowlist entry, across all mempools, that allows a violation
+pub(crate) fn find_allowlist_matches(
+    mempools: &HashMap<H256, MempoolConfig>,
+    violation: &SimulationViolation,
+) -> Vec<AllowlistMatch> {
+    let mut matches = vec![];
+    for (&mempool, config) in mempools {
+        for (index, entry) in config.allowlist.iter().enumerate() {
+            if entry.is_allowed(violation) {
+                matches.push(AllowlistMatch {
+                    mempool,
+                    index,
+                    rule: entry.rule.name(),
+                    description: entry.description.clone(),
+                });
+            }
+        }
+    }
+    matches.sort_by_key(|m| (m.mempool, m.index));
+    matches
+}
+
 /// Return value for matching mempools
 #[derive(Debug, PartialEq, Eq)]
 pub(crate) enum MempoolMatchResult {
@@ -174,16 +490,27 @@ pub(crate) enum MempoolMatchResult {
     Matches(Vec<H256>),
     /// No mempools matched, with the index of the first violation that didn't match
     NoMatch(usize),
+    /// Validation exceeded the limits of every mempool
+    ExceededLimits,
 }
 
 /// Match mempools based on a list of violations. Operations are matched to each of the
-/// mempools in which all of their violations are allowlisted. If zero violations,
-/// an operation will match all mempools.
+/// mempools whose limits their validation stayed within, and in which all of their
+/// violations are allowlisted. If zero violations, an operation will match all
+/// mempools whose limits it stayed within.
 pub(crate) fn match_mempools(
     mempools: &HashMap<H256, MempoolConfig>,
     violations: &[SimulationViolation],
+    usage: &ValidationUsage,
 ) -> MempoolMatchResult {
-    let mut candidate_pools: Vec<H256> = mempools.keys().cloned().collect();
+    let mut candidate_pools: Vec<H256> = mempools
+        .iter()
+        .filter(|(_, config)| config.within_limits(usage))
+        .map(|(id, _)| *id)
+        .collect();
+    if candidate_pools.is_empty() && !mempools.is_empty() {
+        return MempoolMatchResult::ExceededLimits;
+    }
     for (i, violation) in violations.iter().enumerate() {
         candidate_pools.retain(|p| {
             mempools[p]
@@ -206,6 +533,49 @@ mod tests {
     use super::*;
     use crate::simulation::{simulation::NeedsStakeInformation, ViolationOpCode};
 
+    #[test]
+    fn test_find_allowlist_matches() {
+        let contract = Address::random();
+        let entity = Entity::paymaster(Address::random());
+        let violation = SimulationViolation::InvalidStorageAccess(
+            entity,
+            StorageSlot {
+                address: contract,
+                slot: 5.into(),
+            },
+        );
+        let config = MempoolConfig {
+            allowlist: vec![
+                AllowlistEntry::new(AllowEntity::Any, AllowRule::CallWithValue),
+                AllowlistEntry::new(
+                    AllowEntity::Type(EntityType::Paymaster),
+                    AllowRule::StorageAccess {
+                        contract,
+                        slots: SlotPattern::Range {
+                            start: 0.into(),
+                            end: 10.into(),
+                        },
+                    },
+                ),
+            ],
+            ..Default::default()
+        };
+        let mempool = H256::random();
+
+        let matches = find_allowlist_matches(&HashMap::from([(mempool, config)]), &violation);
+        assert_eq!(
+            matches,
+            vec![AllowlistMatch {
+                mempool,
+                index: 1,
+                rule: "storageAccess",
+                description: None,
+            }]
+        );
+        assert!(allow_rules_for(&violation).contains(&matches[0].rule));
+        assert!(allow_rules_for(&SimulationViolation::InvalidSignature).is_empty());
+    }
+
     #[test]
     fn test_allow_entity_any() {
         let allow = AllowEntity::Any;
@@ -480,6 +850,7 @@ mod tests {
                             opcode: Opcode::GAS,
                         },
                     )],
+                    ..Default::default()
                 },
             ),
         ]);
@@ -492,7 +863,7 @@ mod tests {
             ViolationOpCode(Opcode::BLOCKHASH),
         );
         assert_eq!(
-            match_mempools(&mempools, &[violation]),
+            match_mempools(&mempools, &[violation], &ValidationUsage::default()),
             MempoolMatchResult::NoMatch(0)
         );
     }
@@ -512,6 +883,7 @@ mod tests {
                             opcode: Opcode::GAS,
                         },
                     )],
+                    ..Default::default()
                 },
             ),
         ]);
@@ -534,11 +906,65 @@ mod tests {
             ),
         ];
         assert_eq!(
-            match_mempools(&mempools, &violations),
+            match_mempools(&mempools, &violations, &ValidationUsage::default()),
             MempoolMatchResult::NoMatch(1)
         );
     }
 
+    #[test]
+    fn test_match_limits() {
+        let unlimited = H256::random();
+        let limited = H256::random();
+        let mempools = HashMap::from([
+            (unlimited, MempoolConfig::default()),
+            (
+                limited,
+                MempoolConfig {
+                    max_verification_gas: Some(100_000),
+                    max_opcodes: Some(1_000),
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        let within = ValidationUsage {
+            gas_used: 100_000,
+            opcodes: 1_000,
+        };
+        match match_mempools(&mempools, &[], &within) {
+            MempoolMatchResult::Matches(mempools) => assert_eq!(mempools.len(), 2),
+            _ => panic!("Expected matches"),
+        }
+        for exceeded in [
+            ValidationUsage {
+                gas_used: 100_001,
+                ..within
+            },
+            ValidationUsage {
+                opcodes: 1_001,
+                ..within
+            },
+        ] {
+            assert_eq!(
+                match_mempools(&mempools, &[], &exceeded),
+                MempoolMatchResult::Matches(vec![unlimited])
+            );
+        }
+
+        let mempools = HashMap::from([(limited, mempools[&limited].clone())]);
+        assert_eq!(
+            match_mempools(
+                &mempools,
+                &[],
+                &ValidationUsage {
+                    gas_used: 100_001,
+                    ..within
+                }
+            ),
+            MempoolMatchResult::ExceededLimits
+        );
+    }
+
     #[test]
     fn test_match_one() {
         let mempool0 = H256::random();
@@ -556,6 +982,7 @@ mod tests {
                             opcode: Opcode::GAS,
                         },
                     )],
+                    ..Default::default()
                 },
             ),
         ]);
@@ -568,7 +995,7 @@ mod tests {
             ViolationOpCode(Opcode::GAS),
         )];
         assert_eq!(
-            match_mempools(&mempools, &violations),
+            match_mempools(&mempools, &violations, &ValidationUsage::default()),
             MempoolMatchResult::Matches(vec![mempool1])
         );
     }
@@ -600,6 +1027,7 @@ mod tests {
                             },
                         ),
                     ],
+                    ..Default::default()
                 },
             ),
             (
@@ -628,6 +1056,7 @@ mod tests {
                             },
                         ),
                     ],
+                    ..Default::default()
                 },
             ),
         ]);
@@ -650,7 +1079,7 @@ mod tests {
             ),
         ];
 
-        match match_mempools(&mempools, &violations) {
+        match match_mempools(&mempools, &violations, &ValidationUsage::default()) {
             MempoolMatchResult::Matches(mempools) => {
                 assert_eq!(mempools.len(), 2);
                 assert!(mempools.contains(&mempool1));
@@ -659,4 +1088,227 @@ mod tests {
             _ => panic!("Expected matches"),
         }
     }
+
+    #[test]
+    fn test_allowlist_storage_access_mapping() {
+        let entity_addr = Address::random();
+        let contract = Address::random();
+        let key = H256::from(Address::random());
+        let entry = AllowlistEntry::new(
+            AllowEntity::Address(entity_addr),
+            AllowRule::StorageAccess {
+                contract,
+                slots: SlotPattern::Mapping {
+                    base_slot: U256::from(3),
+                    keys: vec![key],
+                    max_offset: 1,
+                },
+            },
+        );
+
+        let mut preimage = [0_u8; 64];
+        preimage[..32].copy_from_slice(key.as_bytes());
+        preimage[63] = 3;
+        let entry_slot = U256::from_big_endian(&keccak256(preimage));
+
+        let violation = |slot| {
+            SimulationViolation::InvalidStorageAccess(
+                Entity::account(entity_addr),
+                StorageSlot {
+                    address: contract,
+                    slot,
+                },
+            )
+        };
+        assert!(entry.is_allowed(&violation(entry_slot)));
+        assert!(entry.is_allowed(&violation(entry_slot + 1)));
+        assert!(!entry.is_allowed(&violation(entry_slot + 2)));
+        assert!(!entry.is_allowed(&violation(entry_slot - 1)));
+    }
+
+    #[test]
+    fn test_allowlist_not_staked_storage_access() {
+        let entity_addr = Address::random();
+        let contract = Address::random();
+        let entry = AllowlistEntry::new(
+            AllowEntity::Type(EntityType::Paymaster),
+            AllowRule::NotStakedStorageAccess {
+                contract,
+                slots: SlotPattern::Range {
+                    start: U256::from(10),
+                    end: U256::from(20),
+                },
+            },
+        );
+
+        let violation = |accessed_address, slot: u64| {
+            SimulationViolation::NotStaked(Box::new(NeedsStakeInformation {
+                entity: Entity::paymaster(entity_addr),
+                accessed_entity: None,
+                accessed_address,
+                slot: U256::from(slot),
+                min_stake: U256::zero(),
+                min_unstake_delay: U256::zero(),
+            }))
+        };
+        assert!(entry.is_allowed(&violation(contract, 10)));
+        assert!(entry.is_allowed(&violation(contract, 19)));
+        assert!(!entry.is_allowed(&violation(contract, 20)));
+        assert!(!entry.is_allowed(&violation(Address::random(), 15)));
+    }
+
+    #[test]
+    fn test_match_not_staked_storage_access_requires_every_slot() {
+        let entity_addr = Address::random();
+        let contract = Address::random();
+        let mempool = H256::random();
+        let mempools = HashMap::from([(
+            mempool,
+            MempoolConfig {
+                allowlist: vec![AllowlistEntry::new(
+                    AllowEntity::Type(EntityType::Paymaster),
+                    AllowRule::NotStakedStorageAccess {
+                        contract,
+                        slots: SlotPattern::Exact(U256::from(1)),
+                    },
+                )],
+                ..Default::default()
+            },
+        )]);
+
+        let violation = |slot: u64| {
+            SimulationViolation::NotStaked(Box::new(NeedsStakeInformation {
+                entity: Entity::paymaster(entity_addr),
+                accessed_entity: None,
+                accessed_address: contract,
+                slot: U256::from(slot),
+                min_stake: U256::zero(),
+                min_unstake_delay: U256::zero(),
+            }))
+        };
+        assert_eq!(
+            match_mempools(&mempools, &[violation(1)], &ValidationUsage::default()),
+            MempoolMatchResult::Matches(vec![mempool])
+        );
+        assert_eq!(
+            match_mempools(
+                &mempools,
+                &[violation(1), violation(2)],
+                &ValidationUsage::default()
+            ),
+            MempoolMatchResult::NoMatch(1)
+        );
+    }
+
+    #[test]
+    fn test_allowlist_code_access() {
+        let entity_addr = Address::random();
+        let contract = Address::random();
+        let entry = AllowlistEntry::new(
+            AllowEntity::Address(entity_addr),
+            AllowRule::CodeAccess { contract },
+        );
+
+        assert!(
+            entry.is_allowed(&SimulationViolation::AccessedUndeployedContract(
+                Entity::account(entity_addr),
+                contract
+            ))
+        );
+        assert!(entry.is_allowed(&SimulationViolation::UsedForbiddenOpcode(
+            Entity::account(entity_addr),
+            contract,
+            ViolationOpCode(Opcode::EXTCODEHASH)
+        )));
+        assert!(!entry.is_allowed(&SimulationViolation::UsedForbiddenOpcode(
+            Entity::account(entity_addr),
+            contract,
+            ViolationOpCode(Opcode::GAS)
+        )));
+        assert!(
+            !entry.is_allowed(&SimulationViolation::AccessedUndeployedContract(
+                Entity::account(entity_addr),
+                Address::random()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_allowlist_multiple_create2() {
+        let factory = Address::random();
+        let entry = AllowlistEntry::new(AllowEntity::Address(factory), AllowRule::MultipleCreate2);
+
+        assert!(entry.is_allowed(&SimulationViolation::FactoryCalledCreate2Twice(factory)));
+        assert!(
+            !entry.is_allowed(&SimulationViolation::FactoryCalledCreate2Twice(
+                Address::random()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_deserialize_config() {
+        let contract = Address::random();
+        let config: MempoolConfig = serde_json::from_value(serde_json::json!({
+            "allowlist": [
+                {
+                    "description": "token balances",
+                    "entity": "paymaster",
+                    "rule": "notStakedStorageAccess",
+                    "contract": contract,
+                    "slots": { "mapping": { "baseSlot": "0x0", "keys": [H256::random()] } }
+                },
+                {
+                    "entity": "factory",
+                    "rule": "multipleCreate2"
+                }
+            ],
+            "maxVerificationGas": 500000
+        }))
+        .unwrap();
+
+        assert_eq!(config.allowlist.len(), 2);
+        assert_eq!(config.max_verification_gas, Some(500_000));
+        assert_eq!(config.max_opcodes, None);
+        assert_eq!(
+            config.allowlist[0].description.as_deref(),
+            Some("token balances")
+        );
+        assert_eq!(config.allowlist[1].rule, AllowRule::MultipleCreate2);
+        // rule names match the names used in configurations
+        assert_eq!(config.allowlist[0].rule.name(), "notStakedStorageAccess");
+        assert_eq!(config.allowlist[1].rule.name(), "multipleCreate2");
+    }
+
+    #[test]
+    fn test_deserialize_config_invalid_entry() {
+        let err = serde_json::from_value::<MempoolConfig>(serde_json::json!({
+            "allowlist": [
+                { "entity": "*", "rule": "callWithValue" },
+                {
+                    "description": "bad range",
+                    "entity": "account",
+                    "rule": "storageAccess",
+                    "contract": Address::random(),
+                    "slots": { "range": { "start": "0x2", "end": "0x1" } }
+                }
+            ]
+        }))
+        .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("invalid allowlist entry 1 (bad range)"));
+
+        let err = serde_json::from_value::<MempoolConfig>(serde_json::json!({
+            "allowlist": [{ "entity": "account", "rule": "multipleCreate2" }]
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid allowlist entry 0"));
+
+        let err = serde_json::from_value::<MempoolConfig>(serde_json::json!({
+            "allowlist": [{ "entity": "*", "rule": "unknownRule" }]
+        }))
+        .unwrap_err();
+        assert!(err.to_string().contains("invalid allowlist entry 0"));
+    }
 }
@@ -16,14 +16,26 @@ mod simulation;
 #[cfg(feature = "test-utils")]
 pub use simulation::MockSimulator;
 pub use simulation::{
-    EntityInfo, EntityInfos, NeedsStakeInformation, Settings, SimulationError, SimulationResult,
-    SimulationViolation, Simulator, SimulatorImpl, ViolationOpCode,
+    EntityInfo, EntityInfos, NeedsStakeInformation, Settings, SharedMempoolConfigs,
+    SimulationError, SimulationResult, SimulationViolation, Simulator, SimulatorImpl,
+    ValidationUsage, ViolationOpCode,
 };
 
+mod cache;
+pub use cache::SimulationCache;
+
 mod mempool;
-pub use mempool::MempoolConfig;
+pub use mempool::{AllowlistMatch, MempoolConfig};
+
+mod registry;
+pub use registry::MempoolRegistry;
+
+mod report;
+pub use report::{PhaseReport, StorageAccessReport, ValidationReport, ViolationReport};
 
 mod tracer;
+#[cfg(test)]
+pub(crate) use tracer::MockSimulateValidationTracer;
 pub use tracer::{SimulateValidationTracer, SimulateValidationTracerImpl};
 
 mod validation_results;
@@ -0,0 +1,183 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::{collections::HashMap, fs, path::PathBuf};
+
+use anyhow::{bail, Context};
+use ethers::{types::H256, utils::keccak256};
+
+use super::MempoolConfig;
+
+/// Resolves alternative mempool IDs to their configurations.
+///
+/// A mempool ID is the keccak256 hash of the mempool's canonical JSON configuration.
+/// Configurations are first looked up in a local content-addressed cache directory,
+/// where each file is named by its ID, and otherwise fetched from an HTTP gateway at
+/// `{gateway_url}/{id}`. The content hash of every configuration is verified against
+/// its ID, and fetched configurations are written to the cache.
+#[derive(Debug, Clone)]
+pub struct MempoolRegistry {
+    gateway_url: Option<String>,
+    cache_dir: Option<PathBuf>,
+    client: reqwest::Client,
+}
+
+impl MempoolRegistry {
+    /// Create a new registry. At least one of `gateway_url` or `cache_dir` must be set.
+    pub fn new(gateway_url: Option<String>, cache_dir: Option<PathBuf>) -> anyhow::Result<Self> {
+        if gateway_url.is_none() && cache_dir.is_none() {
+            bail!("mempool registry requires a gateway url or a cache directory");
+        }
+        Ok(Self {
+            gateway_url: gateway_url.map(|url| url.trim_end_matches('/').to_string()),
+            cache_dir,
+            client: reqwest::Client::new(),
+        })
+    }
+
+    /// Resolve a mempool ID to its configuration.
+    pub async fn resolve(&self, id: H256) -> anyhow::Result<MempoolConfig> {
+        if let Some(contents) = self.read_cache(id) {
+            match parse_verified(id, &contents) {
+                Ok(config) => return Ok(config),
+                Err(e) => tracing::warn!("Ignoring cached config for mempool {id:?}: {e:?}"),
+            }
+        }
+
+        let Some(gateway_url) = &self.gateway_url else {
+            bail!("mempool {id:?} not found in cache and no gateway is configured");
+        };
+        let contents = self
+            .client
+            .get(format!("{gateway_url}/{id:?}"))
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let config = parse_verified(id, &contents)?;
+
+        if let Err(e) = self.write_cache(id, &contents) {
+            tracing::warn!("Failed to cache config for mempool {id:?}: {e:?}");
+        }
+        Ok(config)
+    }
+
+    /// Resolve a list of mempool IDs, returning the resolved configurations.
+    ///
+    /// IDs that fail to resolve are logged and omitted.
+    pub async fn resolve_all(&self, ids: &[H256]) -> HashMap<H256, MempoolConfig> {
+        let mut configs = HashMap::new();
+        for &id in ids {
+            match self.resolve(id).await {
+                Ok(config) => {
+                    configs.insert(id, config);
+                }
+                Err(e) => tracing::error!("Failed to resolve mempool {id:?}: {e:?}"),
+            }
+        }
+        configs
+    }
+
+    /// Check that none of `mempool_ids` is also configured in `file_configs`.
+    ///
+    /// The content hash of a configuration embedded in a larger file can't be
+    /// verified against its ID, so a mempool joined by ID must only come from
+    /// the registry.
+    pub fn check_conflicts(
+        file_configs: &HashMap<H256, MempoolConfig>,
+        mempool_ids: &[H256],
+    ) -> anyhow::Result<()> {
+        let conflicts = mempool_ids
+            .iter()
+            .filter(|id| file_configs.contains_key(*id))
+            .collect::<Vec<_>>();
+        if !conflicts.is_empty() {
+            bail!(
+                "mempools {conflicts:?} are configured by ID and in the mempool config file, which can't be verified against their IDs"
+            );
+        }
+        Ok(())
+    }
+
+    fn read_cache(&self, id: H256) -> Option<Vec<u8>> {
+        let path = self.cache_dir.as_ref()?.join(format!("{id:?}.json"));
+        fs::read(path).ok()
+    }
+
+    fn write_cache(&self, id: H256, contents: &[u8]) -> anyhow::Result<()> {
+        let Some(dir) = &self.cache_dir else {
+            return Ok(());
+        };
+        fs::create_dir_all(dir)?;
+        // Write to a temporary file first so a partial write is never read back
+        let tmp_path = dir.join(format!("{id:?}.json.tmp"));
+        fs::write(&tmp_path, contents)?;
+        fs::rename(tmp_path, dir.join(format!("{id:?}.json")))?;
+        Ok(())
+    }
+}
+
+fn parse_verified(id: H256, contents: &[u8]) -> anyhow::Result<MempoolConfig> {
+    let hash = H256(keccak256(contents));
+    if hash != id {
+        bail!("content hash {hash:?} does not match mempool id {id:?}");
+    }
+    serde_json::from_slice(contents).with_context(|| format!("should parse mempool {id:?} config"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &[u8] = br#"{"allowlist":[{"entity":"*","rule":"callWithValue"}]}"#;
+
+    #[tokio::test]
+    async fn test_resolve_from_cache() {
+        let dir = std::env::temp_dir().join(format!("rundler-mempools-{:?}", H256::random()));
+        fs::create_dir_all(&dir).unwrap();
+        let id = H256(keccak256(CONFIG));
+        fs::write(dir.join(format!("{id:?}.json")), CONFIG).unwrap();
+
+        let registry = MempoolRegistry::new(None, Some(dir.clone())).unwrap();
+        let config = registry.resolve(id).await.unwrap();
+        assert_eq!(config.allowlist.len(), 1);
+
+        // a config whose hash doesn't match its name is rejected
+        let other_id = H256::random();
+        fs::write(dir.join(format!("{other_id:?}.json")), CONFIG).unwrap();
+        assert!(registry.resolve(other_id).await.is_err());
+        assert!(registry
+            .resolve_all(&[id, other_id])
+            .await
+            .contains_key(&id));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_conflicts() {
+        let id = H256(keccak256(CONFIG));
+        let file_configs = HashMap::from([(H256::random(), MempoolConfig::default())]);
+        assert!(MempoolRegistry::check_conflicts(&file_configs, &[id]).is_ok());
+
+        let file_configs = HashMap::from([(id, MempoolConfig::default())]);
+        assert!(MempoolRegistry::check_conflicts(&file_configs, &[id]).is_err());
+    }
+
+    #[test]
+    fn test_parse_verified_mismatch() {
+        let err = parse_verified(H256::zero(), CONFIG).unwrap_err();
+        assert!(err.to_string().contains("does not match mempool id"));
+    }
+}
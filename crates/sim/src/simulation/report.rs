@@ -0,0 +1,274 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+use std::collections::{BTreeMap, HashMap};
+
+use ethers::types::{Address, Opcode, H256, U256};
+use rundler_types::{Entity, EntityType};
+
+use super::{
+    mempool::{allow_rules_for, find_allowlist_matches, AllowlistMatch, MempoolConfig},
+    tracer::{parse_combined_tracer_str, Phase},
+    EntityInfos, SimulationViolation,
+};
+
+/// A report of the validation of a user operation, listing everything found
+/// during simulation rather than only the first unsupported violation.
+#[derive(Clone, Debug, Default)]
+pub struct ValidationReport {
+    /// Block hash the operation was simulated against
+    pub block_hash: H256,
+    /// The entities of the operation and their staking state, if simulation got
+    /// far enough to determine them
+    pub entity_infos: Option<EntityInfos>,
+    /// The validation phases run by the entry point, in order
+    pub phases: Vec<PhaseReport>,
+    /// All violations found during validation, sorted by importance
+    pub violations: Vec<ViolationReport>,
+    /// The mempools that allow all of the violations
+    pub mempools: Vec<H256>,
+}
+
+/// What a single validation phase did
+#[derive(Clone, Debug)]
+pub struct PhaseReport {
+    /// The entity validated in this phase
+    pub entity: EntityType,
+    /// Address of the entity, if the operation uses it
+    pub address: Option<Address>,
+    /// Gas used by the phase
+    pub gas_used: u64,
+    /// Number of opcodes executed by the entity and the contracts it called
+    pub opcode_count: u64,
+    /// Forbidden opcodes used, by the contract that used them
+    pub forbidden_opcodes_used: Vec<(Address, Opcode)>,
+    /// Forbidden precompiles used, by the contract that used them
+    pub forbidden_precompiles_used: Vec<(Address, Address)>,
+    /// Storage slots accessed, by contract
+    pub storage_accesses: BTreeMap<Address, StorageAccessReport>,
+    /// Contracts whose code was accessed, and the opcode used
+    pub ext_code_accesses: Vec<(Address, Opcode)>,
+    /// Addresses without code that were accessed
+    pub undeployed_contract_accesses: Vec<Address>,
+    /// Addresses that made calls with value
+    pub addresses_calling_with_value: Vec<Address>,
+    /// Whether a call with value was made to a contract other than the entry point
+    pub called_non_entry_point_with_value: bool,
+    /// Whether an entry point method other than `depositTo` was called
+    pub called_banned_entry_point_method: bool,
+    /// Whether the phase ran out of gas
+    pub ran_out_of_gas: bool,
+}
+
+/// The storage slots of a contract accessed in a phase
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageAccessReport {
+    /// Slots read, with their values before the operation
+    pub reads: BTreeMap<U256, String>,
+    /// Slots written
+    pub writes: Vec<U256>,
+}
+
+/// A violation found during validation and how it could be allowed
+#[derive(Clone, Debug)]
+pub struct ViolationReport {
+    /// The violation
+    pub violation: SimulationViolation,
+    /// The entity responsible for the violation, if known
+    pub entity: Option<Entity>,
+    /// The offending contract, if any
+    pub contract: Option<Address>,
+    /// The offending storage slot, if any
+    pub slot: Option<U256>,
+    /// The offending opcode, if any
+    pub opcode: Option<Opcode>,
+    /// The offending precompile, if any
+    pub precompile: Option<Address>,
+    /// Names of the allowlist rules that can allow the violation. Empty if the
+    /// violation cannot be allowlisted.
+    pub allow_rules: Vec<&'static str>,
+    /// The configured allowlist entries that allow the violation
+    pub allowed_by: Vec<AllowlistMatch>,
+}
+
+impl ViolationReport {
+    pub(crate) fn new(
+        violation: SimulationViolation,
+        mempools: &HashMap<H256, MempoolConfig>,
+    ) -> Self {
+        let mut report = Self {
+            entity: None,
+            contract: None,
+            slot: None,
+            opcode: None,
+            precompile: None,
+            allow_rules: allow_rules_for(&violation),
+            allowed_by: find_allowlist_matches(mempools, &violation),
+            violation,
+        };
+        match &report.violation {
+            SimulationViolation::UsedForbiddenOpcode(entity, contract, opcode) => {
+                report.entity = Some(*entity);
+                report.contract = Some(*contract);
+                report.opcode = Some(opcode.0);
+            }
+            SimulationViolation::UsedForbiddenPrecompile(entity, contract, precompile) => {
+                report.entity = Some(*entity);
+                report.contract = Some(*contract);
+                report.precompile = Some(*precompile);
+            }
+            SimulationViolation::AccessedUndeployedContract(entity, contract) => {
+                report.entity = Some(*entity);
+                report.contract = Some(*contract);
+            }
+            SimulationViolation::FactoryCalledCreate2Twice(factory) => {
+                report.entity = Some(Entity::factory(*factory));
+            }
+            SimulationViolation::InvalidStorageAccess(entity, slot) => {
+                report.entity = Some(*entity);
+                report.contract = Some(slot.address);
+                report.slot = Some(slot.slot);
+            }
+            SimulationViolation::NotStaked(info) => {
+                report.entity = Some(info.entity);
+                report.contract = Some(info.accessed_address);
+                report.slot = Some(info.slot);
+            }
+            SimulationViolation::CalledBannedEntryPointMethod(entity)
+            | SimulationViolation::CallHadValue(entity)
+            | SimulationViolation::OutOfGas(entity) => {
+                report.entity = Some(*entity);
+            }
+            SimulationViolation::UnintendedRevertWithMessage(kind, _, address)
+            | SimulationViolation::UnintendedRevert(kind, address) => {
+                report.entity = address.map(|address| Entity {
+                    kind: *kind,
+                    address,
+                });
+            }
+            SimulationViolation::InvalidSignature
+            | SimulationViolation::CodeHashChanged
+            | SimulationViolation::UnstakedPaymasterContext
+            | SimulationViolation::UnstakedAggregator
+            | SimulationViolation::DidNotRevert
+            | SimulationViolation::WrongNumberOfPhases(_)
+            | SimulationViolation::AggregatorValidationFailed
+            | SimulationViolation::ExceededMempoolLimits(_) => {}
+        }
+        report
+    }
+}
+
+impl PhaseReport {
+    pub(crate) fn new(
+        entity: EntityType,
+        address: Option<Address>,
+        phase: &Phase,
+    ) -> anyhow::Result<Self> {
+        let mut storage_accesses = BTreeMap::new();
+        for (&contract, access_info) in &phase.storage_accesses {
+            let mut writes: Vec<U256> = access_info.writes.keys().copied().collect();
+            writes.sort();
+            storage_accesses.insert(
+                contract,
+                StorageAccessReport {
+                    reads: access_info
+                        .reads
+                        .iter()
+                        .map(|(slot, value)| (*slot, value.clone()))
+                        .collect(),
+                    writes,
+                },
+            );
+        }
+
+        let mut ext_code_accesses: Vec<(Address, Opcode)> = phase
+            .ext_code_access_info
+            .iter()
+            .map(|(address, opcode)| (*address, *opcode))
+            .collect();
+        ext_code_accesses.sort_by_key(|(address, opcode)| (*address, *opcode as u8));
+
+        Ok(Self {
+            entity,
+            address,
+            gas_used: phase.gas_used,
+            opcode_count: phase.opcode_count,
+            forbidden_opcodes_used: phase
+                .forbidden_opcodes_used
+                .iter()
+                .map(|combined| parse_combined_tracer_str::<Address, Opcode>(combined))
+                .collect::<anyhow::Result<_>>()?,
+            forbidden_precompiles_used: phase
+                .forbidden_precompiles_used
+                .iter()
+                .map(|combined| parse_combined_tracer_str::<Address, Address>(combined))
+                .collect::<anyhow::Result<_>>()?,
+            storage_accesses,
+            ext_code_accesses,
+            undeployed_contract_accesses: phase.undeployed_contract_accesses.clone(),
+            addresses_calling_with_value: phase.addresses_calling_with_value.clone(),
+            called_non_entry_point_with_value: phase.called_non_entry_point_with_value,
+            called_banned_entry_point_method: phase.called_banned_entry_point_method,
+            ran_out_of_gas: phase.ran_out_of_gas,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rundler_types::StorageSlot;
+
+    use super::*;
+    use crate::simulation::mempool::{AllowEntity, AllowRule, AllowlistEntry};
+
+    #[test]
+    fn test_violation_report() {
+        let contract = Address::random();
+        let entity = Entity::account(Address::random());
+        let violation = SimulationViolation::InvalidStorageAccess(
+            entity,
+            StorageSlot {
+                address: contract,
+                slot: 7.into(),
+            },
+        );
+        let mempool = H256::random();
+        let mempools = HashMap::from([(
+            mempool,
+            MempoolConfig {
+                allowlist: vec![AllowlistEntry {
+                    entity: AllowEntity::Any,
+                    rule: AllowRule::InvalidStorageAccess {
+                        contract,
+                        slot: 7.into(),
+                    },
+                    description: Some("account registry".to_string()),
+                }],
+                ..Default::default()
+            },
+        )]);
+
+        let report = ViolationReport::new(violation, &mempools);
+        assert_eq!(report.entity, Some(entity));
+        assert_eq!(report.contract, Some(contract));
+        assert_eq!(report.slot, Some(7.into()));
+        assert_eq!(report.opcode, None);
+        assert_eq!(report.allowed_by.len(), 1);
+        assert_eq!(report.allowed_by[0].mempool, mempool);
+        assert_eq!(
+            report.allowed_by[0].description.as_deref(),
+            Some("account registry")
+        );
+    }
+}
@@ -23,19 +23,25 @@ use async_trait::async_trait;
 use ethers::{
     abi::AbiDecode,
     types::{Address, BlockId, Opcode, H256, U256},
+    utils::hex,
 };
 use indexmap::IndexSet;
 #[cfg(feature = "test-utils")]
 use mockall::automock;
+use parking_lot::RwLock;
 use rundler_provider::{AggregatorOut, AggregatorSimOut, Provider};
 use rundler_types::{
     contracts::i_entry_point::FailedOp, Entity, EntityType, StorageSlot, UserOperation,
     ValidTimeRange,
 };
+use rundler_utils::revert::AbiRegistry;
 use strum::IntoEnumIterator;
+use tracing::instrument;
 
 use super::{
+    cache::SimulationCache,
     mempool::{match_mempools, AllowEntity, AllowRule, MempoolConfig, MempoolMatchResult},
+    report::{PhaseReport, ValidationReport, ViolationReport},
     tracer::{
         parse_combined_tracer_str, AccessInfo, AssociatedSlotsByAddress, SimulateValidationTracer,
         SimulationTracerOutput,
@@ -122,6 +128,17 @@ pub trait Simulator: Send + Sync + 'static {
         block_hash: Option<H256>,
         expected_code_hash: Option<H256>,
     ) -> Result<SimulationResult, SimulationError>;
+
+    /// Simulate a user operation and report on every validation phase and every
+    /// violation found, along with the allowlist entries that allow them.
+    ///
+    /// Unlike `simulate_validation`, violations are returned in the report rather
+    /// than as an error. Errors are only returned if simulation could not be run.
+    async fn trace_validation(
+        &self,
+        op: UserOperation,
+        block_hash: Option<H256>,
+    ) -> Result<ValidationReport, SimulationError>;
 }
 
 /// Simulator implementation.
@@ -141,10 +158,75 @@ pub struct SimulatorImpl<P: Provider, T: SimulateValidationTracer> {
     entry_point_address: Address,
     simulate_validation_tracer: T,
     sim_settings: Settings,
-    mempool_configs: HashMap<H256, MempoolConfig>,
+    mempools: SharedMempoolConfigs,
+    cache: Option<Arc<SimulationCache>>,
+    allowed_precompiles: HashSet<Address>,
+    abi_registry: Arc<AbiRegistry>,
+}
+
+/// Mempool configurations that can be shared between simulators, such as the
+/// simulators of the pool, builder, and RPC server of a node.
+///
+/// Updates made through any clone of the handle apply to every simulator using it.
+#[derive(Debug, Clone)]
+pub struct SharedMempoolConfigs(Arc<RwLock<Arc<MempoolState>>>);
+
+impl SharedMempoolConfigs {
+    /// Create a new handle with the given mempool configurations
+    pub fn new(configs: HashMap<H256, MempoolConfig>) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(MempoolState::new(configs)))))
+    }
+
+    /// Returns the current mempool configurations
+    pub fn configs(&self) -> HashMap<H256, MempoolConfig> {
+        self.0.read().configs.clone()
+    }
+
+    /// Replaces the mempool configurations
+    pub fn set(&self, configs: HashMap<H256, MempoolConfig>) {
+        *self.0.write() = Arc::new(MempoolState::new(configs));
+    }
+
+    fn state(&self) -> Arc<MempoolState> {
+        Arc::clone(&self.0.read())
+    }
+}
+
+impl From<HashMap<H256, MempoolConfig>> for SharedMempoolConfigs {
+    fn from(configs: HashMap<H256, MempoolConfig>) -> Self {
+        Self::new(configs)
+    }
+}
+
+/// Mempool configurations and the state derived from them, swapped as a unit
+/// when the configurations are updated.
+#[derive(Debug)]
+struct MempoolState {
+    configs: HashMap<H256, MempoolConfig>,
     allow_unstaked_addresses: HashSet<Address>,
 }
 
+impl MempoolState {
+    fn new(configs: HashMap<H256, MempoolConfig>) -> Self {
+        // Get a list of entities that are allowed to act as staked entities despite being unstaked
+        let mut allow_unstaked_addresses = HashSet::new();
+        for config in configs.values() {
+            for entry in &config.allowlist {
+                if entry.rule == AllowRule::NotStaked {
+                    if let AllowEntity::Address(address) = entry.entity {
+                        allow_unstaked_addresses.insert(address);
+                    }
+                }
+            }
+        }
+
+        Self {
+            configs,
+            allow_unstaked_addresses,
+        }
+    }
+}
+
 impl<P, T> SimulatorImpl<P, T>
 where
     P: Provider,
@@ -154,36 +236,50 @@ where
     ///
     /// `mempool_configs` is a map of mempool IDs to mempool configurations.
     /// It is used during simulation to determine which mempools support
-    /// the violations found during simulation.
+    /// the violations found during simulation. Pass a [`SharedMempoolConfigs`]
+    /// to follow updates made by other simulators.
     pub fn new(
         provider: Arc<P>,
         entry_point_address: Address,
         simulate_validation_tracer: T,
         sim_settings: Settings,
-        mempool_configs: HashMap<H256, MempoolConfig>,
+        mempool_configs: impl Into<SharedMempoolConfigs>,
     ) -> Self {
-        // Get a list of entities that are allowed to act as staked entities despite being unstaked
-        let mut allow_unstaked_addresses = HashSet::new();
-        for config in mempool_configs.values() {
-            for entry in &config.allowlist {
-                if entry.rule == AllowRule::NotStaked {
-                    if let AllowEntity::Address(address) = entry.entity {
-                        allow_unstaked_addresses.insert(address);
-                    }
-                }
-            }
-        }
-
         Self {
             provider,
             entry_point_address,
             simulate_validation_tracer,
             sim_settings,
-            mempool_configs,
-            allow_unstaked_addresses,
+            mempools: mempool_configs.into(),
+            cache: None,
+            allowed_precompiles: HashSet::new(),
+            abi_registry: Arc::new(AbiRegistry::default()),
         }
     }
 
+    /// Allow entities to call the given chain specific precompiles during
+    /// validation, in addition to the standard Ethereum precompiles.
+    pub fn with_allowed_precompiles(mut self, precompiles: &[Address]) -> Self {
+        self.allowed_precompiles = precompiles.iter().copied().collect();
+        self
+    }
+
+    /// Decode unexpected validation reverts with the errors of an ABI registry,
+    /// instead of only the errors of the entry point.
+    pub fn with_abi_registry(mut self, abi_registry: Arc<AbiRegistry>) -> Self {
+        self.abi_registry = abi_registry;
+        self
+    }
+
+    /// Reuse successful simulation results from a cache.
+    ///
+    /// The cache may be shared with other simulators using the same settings
+    /// and mempool configurations.
+    pub fn with_cache(mut self, cache: Arc<SimulationCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
     /// Return the associated settings
     pub fn settings(&self) -> &Settings {
         &self.sim_settings
@@ -196,13 +292,22 @@ where
         op: UserOperation,
         block_id: BlockId,
     ) -> Result<ValidationContext, SimulationError> {
-        let factory_address = op.factory();
-        let sender_address = op.sender;
-        let paymaster_address = op.paymaster();
         let tracer_out = self
             .simulate_validation_tracer
             .trace_simulate_validation(op.clone(), block_id, self.sim_settings.max_verification_gas)
             .await?;
+        self.context_from_tracer_out(&op, block_id, tracer_out)
+    }
+
+    fn context_from_tracer_out(
+        &self,
+        op: &UserOperation,
+        block_id: BlockId,
+        tracer_out: SimulationTracerOutput,
+    ) -> Result<ValidationContext, SimulationError> {
+        let factory_address = op.factory();
+        let sender_address = op.sender;
+        let paymaster_address = op.paymaster();
         let num_phases = tracer_out.phases.len() as u32;
         // Check if there are too many phases here, then check too few at the
         // end. We are detecting cases where the entry point is broken. Too many
@@ -253,10 +358,21 @@ where
                 EntityType::Account => Some(sender_address),
                 _ => None,
             };
+            // a revert that isn't from the entry point, such as a custom error,
+            // is reported with its message if the registry can decode it
+            let violation = match hex::decode(revert_data.trim_start_matches("0x"))
+                .ok()
+                .and_then(|data| self.abi_registry.decode(&data))
+            {
+                Some(decoded) => SimulationViolation::UnintendedRevertWithMessage(
+                    last_entity_type,
+                    decoded.to_string(),
+                    entity_addr,
+                ),
+                None => SimulationViolation::UnintendedRevert(last_entity_type, entity_addr),
+            };
             Err(SimulationError {
-                violation_error: ViolationError::Violations(vec![
-                    SimulationViolation::UnintendedRevert(last_entity_type, entity_addr),
-                ]),
+                violation_error: ViolationError::Violations(vec![violation]),
                 entity_infos: None,
             })?
         };
@@ -331,8 +447,10 @@ where
         }
 
         let sender_address = entity_infos.sender_address();
-        let mut entity_types_needing_stake: HashMap<Entity, (Address, Option<EntityType>, U256)> =
-            HashMap::new();
+        // Each slot accessed by an unstaked entity that needs stake is its own
+        // violation, so that allowlisting one slot doesn't allow the others
+        let mut slots_needing_stake =
+            IndexSet::<(Entity, Address, Option<EntityType>, U256)>::new();
 
         for (index, phase) in tracer_out.phases.iter().enumerate().take(3) {
             let kind = entity_type_from_simulation_phase(index).unwrap();
@@ -364,6 +482,9 @@ where
 
             for precompile in &phase.forbidden_precompiles_used {
                 let (contract, precompile) = parse_combined_tracer_str(precompile)?;
+                if self.allowed_precompiles.contains(&precompile) {
+                    continue;
+                }
                 violations.push(SimulationViolation::UsedForbiddenPrecompile(
                     entity, contract, precompile,
                 ));
@@ -395,9 +516,11 @@ where
 
                 match violation {
                     StorageRestriction::Allowed => {}
-                    StorageRestriction::NeedsStake(addr, entity_type, slot) => {
+                    StorageRestriction::NeedsStake(addr, entity_type, slots) => {
                         if !entity_info.is_staked {
-                            entity_types_needing_stake.insert(entity, (addr, entity_type, slot));
+                            for slot in slots {
+                                slots_needing_stake.insert((entity, addr, entity_type, slot));
+                            }
                         }
                     }
                     StorageRestriction::Banned(slot) => {
@@ -420,15 +543,16 @@ where
                 violations.push(SimulationViolation::CalledBannedEntryPointMethod(entity));
             }
 
-            // These violations are not allowlistable but we need to collect them here
-            if phase.ran_out_of_gas {
-                violations.push(SimulationViolation::OutOfGas(entity));
-            }
             for &address in &phase.undeployed_contract_accesses {
                 violations.push(SimulationViolation::AccessedUndeployedContract(
                     entity, address,
                 ))
             }
+
+            // This violation is not allowlistable but we need to collect it here
+            if phase.ran_out_of_gas {
+                violations.push(SimulationViolation::OutOfGas(entity));
+            }
         }
 
         if let Some(aggregator_info) = entry_point_out.aggregator_info {
@@ -437,8 +561,10 @@ where
             }
         }
 
-        for (ent, (accessed_address, accessed_entity, slot)) in entity_types_needing_stake {
-            entities_needing_stake.push(ent.kind);
+        for (ent, accessed_address, accessed_entity, slot) in slots_needing_stake {
+            if !entities_needing_stake.contains(&ent.kind) {
+                entities_needing_stake.push(ent.kind);
+            }
 
             violations.push(SimulationViolation::NotStaked(Box::new(
                 NeedsStakeInformation {
@@ -538,6 +664,7 @@ where
     P: Provider,
     T: SimulateValidationTracer,
 {
+    #[instrument(skip_all, fields(sender = ?op.sender, nonce = %op.nonce))]
     async fn simulate_validation(
         &self,
         op: UserOperation,
@@ -556,6 +683,21 @@ where
                 (hash_and_num.0, Some(hash_and_num.1.as_u64()))
             }
         };
+        let cache_key = self.cache.as_ref().map(|_| SimulationCache::key(&op));
+        if let (Some(cache), Some(key)) = (&self.cache, cache_key) {
+            if let Some(result) = cache.get(
+                self.entry_point_address,
+                key,
+                block_hash,
+                expected_code_hash,
+            ) {
+                return Ok(SimulationResult {
+                    block_number,
+                    ..result
+                });
+            }
+        }
+
         let block_id = block_hash.into();
         let mut context = match self.create_context(op.clone(), block_id).await {
             Ok(context) => context,
@@ -567,7 +709,10 @@ where
         // Sort violations so that the final error message is deterministic
         overridable_violations.sort();
         // Check violations against mempool rules, find supporting mempools, error if none found
-        let mempools = match match_mempools(&self.mempool_configs, &overridable_violations) {
+        let mempool_state = self.mempools.state();
+        let usage = ValidationUsage::from_tracer_out(&context.tracer_out);
+        let mempools = match match_mempools(&mempool_state.configs, &overridable_violations, &usage)
+        {
             MempoolMatchResult::Matches(pools) => pools,
             MempoolMatchResult::NoMatch(i) => {
                 return Err(SimulationError {
@@ -577,6 +722,14 @@ where
                     entity_infos: Some(context.entity_infos),
                 })
             }
+            MempoolMatchResult::ExceededLimits => {
+                return Err(SimulationError {
+                    violation_error: ViolationError::Violations(vec![
+                        SimulationViolation::ExceededMempoolLimits(usage),
+                    ]),
+                    entity_infos: Some(context.entity_infos),
+                })
+            }
         };
 
         // Check code hash and aggregator signature, these can't fail
@@ -610,9 +763,9 @@ where
         // Conduct any stake overrides before assigning entity_infos
         context
             .entity_infos
-            .override_is_staked(&self.allow_unstaked_addresses);
+            .override_is_staked(&mempool_state.allow_unstaked_addresses);
 
-        Ok(SimulationResult {
+        let result = SimulationResult {
             mempools,
             block_hash,
             block_number,
@@ -627,6 +780,100 @@ where
             expected_storage: tracer_out.expected_storage,
             requires_post_op: !paymaster_context.is_empty(),
             entity_infos: context.entity_infos,
+        };
+        if let (Some(cache), Some(key)) = (&self.cache, cache_key) {
+            cache.insert(self.entry_point_address, key, result.clone());
+        }
+        Ok(result)
+    }
+
+    async fn trace_validation(
+        &self,
+        op: UserOperation,
+        block_hash: Option<H256>,
+    ) -> Result<ValidationReport, SimulationError> {
+        let block_hash = match block_hash {
+            Some(block_hash) => block_hash,
+            None => {
+                self.provider
+                    .get_latest_block_hash_and_number()
+                    .await
+                    .map_err(anyhow::Error::from)?
+                    .0
+            }
+        };
+        let block_id = block_hash.into();
+        let tracer_out = self
+            .simulate_validation_tracer
+            .trace_simulate_validation(op.clone(), block_id, self.sim_settings.max_verification_gas)
+            .await?;
+
+        let mut phases = vec![];
+        for (index, phase) in tracer_out.phases.iter().enumerate() {
+            let Some(entity) = entity_type_from_simulation_phase(index) else {
+                break;
+            };
+            let address = match entity {
+                EntityType::Factory => op.factory(),
+                EntityType::Account => Some(op.sender),
+                EntityType::Paymaster => op.paymaster(),
+                _ => None,
+            };
+            phases.push(PhaseReport::new(entity, address, phase)?);
+        }
+
+        let mempool_state = self.mempools.state();
+        let into_reports = |violations: Vec<SimulationViolation>| {
+            violations
+                .into_iter()
+                .map(|violation| ViolationReport::new(violation, &mempool_state.configs))
+                .collect::<Vec<_>>()
+        };
+
+        let mut context = match self.context_from_tracer_out(&op, block_id, tracer_out) {
+            Ok(context) => context,
+            Err(SimulationError {
+                violation_error: ViolationError::Violations(violations),
+                entity_infos,
+            }) => {
+                return Ok(ValidationReport {
+                    block_hash,
+                    entity_infos,
+                    phases,
+                    violations: into_reports(violations),
+                    mempools: vec![],
+                })
+            }
+            Err(error) => return Err(error),
+        };
+
+        let mut violations = self.gather_context_violations(&mut context)?;
+        violations.sort();
+        let usage = ValidationUsage::from_tracer_out(&context.tracer_out);
+        let mempools = match match_mempools(&mempool_state.configs, &violations, &usage) {
+            MempoolMatchResult::Matches(pools) => pools,
+            MempoolMatchResult::NoMatch(_) => vec![],
+            MempoolMatchResult::ExceededLimits => {
+                violations.push(SimulationViolation::ExceededMempoolLimits(usage));
+                vec![]
+            }
+        };
+        // Aggregator failures can't be allowed by any mempool
+        match self.check_contracts(op, &mut context, None).await {
+            Ok(_) => {}
+            Err(SimulationError {
+                violation_error: ViolationError::Violations(other_violations),
+                ..
+            }) => violations.extend(other_violations),
+            Err(error) => return Err(error),
+        }
+
+        Ok(ValidationReport {
+            block_hash,
+            entity_infos: Some(context.entity_infos),
+            phases,
+            violations: into_reports(violations),
+            mempools,
         })
     }
 }
@@ -693,6 +940,9 @@ pub enum SimulationViolation {
     /// The user operation aggregator signature validation failed
     #[display("aggregator signature validation failed")]
     AggregatorValidationFailed,
+    /// Validation used more gas or opcodes than any mempool allows
+    #[display("validation used {0.gas_used} gas and {0.opcodes} opcodes, exceeding the limits of every mempool")]
+    ExceededMempoolLimits(ValidationUsage),
 }
 
 /// A wrapper around Opcode that implements extra traits
@@ -866,7 +1116,7 @@ fn is_staked(info: StakeInfo, sim_settings: Settings) -> bool {
 #[derive(Clone, Debug, Eq, PartialEq)]
 enum StorageRestriction {
     Allowed,
-    NeedsStake(Address, Option<EntityType>, U256),
+    NeedsStake(Address, Option<EntityType>, Vec<U256>),
     Banned(U256),
 }
 
@@ -887,6 +1137,29 @@ pub struct NeedsStakeInformation {
     pub min_unstake_delay: U256,
 }
 
+/// Resources used by the entities of a user operation during validation,
+/// checked against the limits of each mempool
+#[derive(Debug, Default, PartialEq, Clone, Copy, PartialOrd, Eq, Ord)]
+pub struct ValidationUsage {
+    /// Gas used by the validation phases
+    pub gas_used: u64,
+    /// Opcodes executed by the entities and the contracts they called
+    pub opcodes: u64,
+}
+
+impl ValidationUsage {
+    fn from_tracer_out(tracer_out: &SimulationTracerOutput) -> Self {
+        tracer_out
+            .phases
+            .iter()
+            .take(3)
+            .fold(Self::default(), |usage, phase| Self {
+                gas_used: usage.gas_used + phase.gas_used,
+                opcodes: usage.opcodes + phase.opcode_count,
+            })
+    }
+}
+
 #[derive(Clone, Debug)]
 struct ParseStorageAccess<'a> {
     access_info: &'a AccessInfo,
@@ -916,7 +1189,7 @@ fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Result<StorageRestric
         return Ok(StorageRestriction::Allowed);
     }
 
-    let mut required_stake_slot = None;
+    let mut required_stake_slots = vec![];
 
     let slots: Vec<&U256> = access_info
         .reads
@@ -943,28 +1216,20 @@ fn parse_storage_accesses(args: ParseStorageAccess<'_>) -> Result<StorageRestric
                         .expect("Factory needs to be present and staked")
                         .is_staked)
             {
-                required_stake_slot = Some(slot);
+                required_stake_slots.push(*slot);
             }
         } else if is_entity_associated || is_same_address || is_read_permission {
-            required_stake_slot = Some(slot);
+            required_stake_slots.push(*slot);
         } else {
             return Ok(StorageRestriction::Banned(*slot));
         }
     }
 
-    if let Some(required_stake_slot) = required_stake_slot {
-        if let Some(entity_type) = entity_infos.type_from_address(address) {
-            return Ok(StorageRestriction::NeedsStake(
-                address,
-                Some(entity_type),
-                *required_stake_slot,
-            ));
-        }
-
+    if !required_stake_slots.is_empty() {
         return Ok(StorageRestriction::NeedsStake(
             address,
-            None,
-            *required_stake_slot,
+            entity_infos.type_from_address(address),
+            required_stake_slots,
         ));
     }
 
@@ -1023,7 +1288,7 @@ mod tests {
     use std::str::FromStr;
 
     use ethers::{
-        abi::AbiEncode,
+        abi::{AbiEncode, ParamType, Token},
         providers::JsonRpcError,
         types::{Address, BlockNumber, Bytes, U64},
         utils::hex,
@@ -1077,6 +1342,8 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_used: 0,
+                    opcode_count: 0,
                 },
                 Phase {
                     addresses_calling_with_value: vec![Address::from_str("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4").unwrap()],
@@ -1088,6 +1355,8 @@ mod tests {
                     storage_accesses:  HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_used: 0,
+                    opcode_count: 0,
                 },
                 Phase {
                     addresses_calling_with_value: vec![],
@@ -1099,12 +1368,30 @@ mod tests {
                     storage_accesses: HashMap::new(),
                     undeployed_contract_accesses: vec![],
                     ext_code_access_info: HashMap::new(),
+                    gas_used: 0,
+                    opcode_count: 0,
                 }
             ],
             revert_data: Some("0xe0cff05f00000000000000000000000000000000000000000000000000000000000000e00000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000014eff00000000000000000000000000000000000000000000000000000b7679c50c24000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000ffffffffffff00000000000000000000000000000000000000000000000000000000000000c00000000000000000000000000000000000000000000000000000000000000000".into()),
         }
     }
 
+    fn get_test_user_operation() -> UserOperation {
+        UserOperation {
+            sender: Address::from_str("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4").unwrap(),
+            nonce: U256::from(264),
+            init_code: Bytes::from_str("0x").unwrap(),
+            call_data: Bytes::from_str("0xb61d27f6000000000000000000000000b856dbd4fa1a79a46d426f537455e7d3e79ab7c4000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000004d087d28800000000000000000000000000000000000000000000000000000000").unwrap(),
+            call_gas_limit: U256::from(9100),
+            verification_gas_limit: U256::from(64805),
+            pre_verification_gas: U256::from(46128),
+            max_fee_per_gas: U256::from(105000100),
+            max_priority_fee_per_gas: U256::from(105000000),
+            paymaster_and_data: Bytes::from_str("0x").unwrap(),
+            signature: Bytes::from_str("0x98f89993ce573172635b44ef3b0741bd0c19dd06909d3539159f6d66bef8c0945550cc858b1cf5921dfce0986605097ba34c2cf3fc279154dd25e161ea7b3d0f1c").unwrap(),
+        }
+    }
+
     fn create_simulator(
         provider: MockProvider,
         simulate_validation_tracer: MockSimulateValidationTracer,
@@ -1165,19 +1452,7 @@ mod tests {
             .expect_validate_user_op_signature()
             .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
 
-        let user_operation = UserOperation {
-            sender: Address::from_str("b856dbd4fa1a79a46d426f537455e7d3e79ab7c4").unwrap(),
-            nonce: U256::from(264),
-            init_code: Bytes::from_str("0x").unwrap(),
-            call_data: Bytes::from_str("0xb61d27f6000000000000000000000000b856dbd4fa1a79a46d426f537455e7d3e79ab7c4000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000600000000000000000000000000000000000000000000000000000000000000004d087d28800000000000000000000000000000000000000000000000000000000").unwrap(),
-            call_gas_limit: U256::from(9100),
-            verification_gas_limit: U256::from(64805),
-            pre_verification_gas: U256::from(46128),
-            max_fee_per_gas: U256::from(105000100),
-            max_priority_fee_per_gas: U256::from(105000000),
-            paymaster_and_data: Bytes::from_str("0x").unwrap(),
-            signature: Bytes::from_str("0x98f89993ce573172635b44ef3b0741bd0c19dd06909d3539159f6d66bef8c0945550cc858b1cf5921dfce0986605097ba34c2cf3fc279154dd25e161ea7b3d0f1c").unwrap(),
-        };
+        let user_operation = get_test_user_operation();
 
         let simulator = create_simulator(provider, tracer);
         let res = simulator
@@ -1186,6 +1461,96 @@ mod tests {
         assert!(res.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_simulate_validation_cached() {
+        let (mut provider, mut tracer) = create_base_config();
+        let block_hash = H256::random();
+
+        // only the first simulation is traced
+        tracer
+            .expect_trace_simulate_validation()
+            .times(1)
+            .returning(move |_, _, _| Ok(get_test_tracer_output()));
+        provider.expect_call().times(1).returning(|_, _, _| {
+            let json_rpc_error = JsonRpcError {
+                code: -32000,
+                message: "execution reverted".to_string(),
+                data: Some(serde_json::Value::String(
+                    "0x091cd005abf68e7b82c951a8619f065986132f67a0945153533cfcdd93b6895f33dbc0c7"
+                        .to_string(),
+                )),
+            };
+            Err(ProviderError::JsonRpcError(json_rpc_error))
+        });
+        provider
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let cache = Arc::new(SimulationCache::new(10));
+        let simulator = create_simulator(provider, tracer).with_cache(Arc::clone(&cache));
+        let op = get_test_user_operation();
+        let first = simulator
+            .simulate_validation(op.clone(), Some(block_hash), None)
+            .await
+            .unwrap();
+        let second = simulator
+            .simulate_validation(op, Some(block_hash), Some(first.code_hash))
+            .await
+            .unwrap();
+        assert_eq!(first.pre_op_gas, second.pre_op_gas);
+        assert_eq!(second.block_hash, block_hash);
+    }
+
+    #[tokio::test]
+    async fn test_trace_validation_reports_all_violations() {
+        let (mut provider, mut tracer) = create_base_config();
+
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(move |_, _, _| {
+                let mut tracer_output = get_test_tracer_output();
+                tracer_output.phases[1].forbidden_opcodes_used = vec![
+                    String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE"),
+                    String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:COINBASE"),
+                ];
+                Ok(tracer_output)
+            });
+        provider.expect_call().returning(|_, _, _| {
+            let json_rpc_error = JsonRpcError {
+                code: -32000,
+                message: "execution reverted".to_string(),
+                data: Some(serde_json::Value::String(
+                    "0x091cd005abf68e7b82c951a8619f065986132f67a0945153533cfcdd93b6895f33dbc0c7"
+                        .to_string(),
+                )),
+            };
+            Err(ProviderError::JsonRpcError(json_rpc_error))
+        });
+        provider
+            .expect_validate_user_op_signature()
+            .returning(|_, _, _| Ok(AggregatorOut::NotNeeded));
+
+        let block_hash = H256::random();
+        let simulator = create_simulator(provider, tracer);
+        let report = simulator
+            .trace_validation(get_test_user_operation(), Some(block_hash))
+            .await
+            .unwrap();
+
+        assert_eq!(report.block_hash, block_hash);
+        assert_eq!(report.phases.len(), 3);
+        assert_eq!(report.phases[1].entity, EntityType::Account);
+        assert_eq!(report.phases[1].forbidden_opcodes_used.len(), 2);
+        // both violations are reported, not only the first
+        assert_eq!(report.violations.len(), 2);
+        for violation in &report.violations {
+            assert!(violation.opcode.is_some());
+            assert!(violation.allow_rules.contains(&"forbiddenOpcode"));
+            assert!(violation.allowed_by.is_empty());
+        }
+        assert!(report.mempools.is_empty());
+    }
+
     #[tokio::test]
     async fn test_create_context_two_phases_unintended_revert() {
         let (provider, mut tracer) = create_base_config();
@@ -1237,40 +1602,51 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_gather_context_violations() {
-        let (provider, tracer) = create_base_config();
-
-        let mut tracer_output = get_test_tracer_output();
-
-        // add forbidden opcodes and precompiles
-        tracer_output.phases[1].forbidden_opcodes_used = vec![
-            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE"),
-            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:COINBASE"),
-        ];
-        tracer_output.phases[1].forbidden_precompiles_used = vec![String::from(
-            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000019",
-        )];
+    async fn test_create_context_custom_error_revert() {
+        let (provider, mut tracer) = create_base_config();
 
-        // add a storage access for a random unrelated address
-        let mut writes = HashMap::new();
+        tracer
+            .expect_trace_simulate_validation()
+            .returning(|_, _, _| {
+                let mut tracer_output = get_test_tracer_output();
+                tracer_output.phases.truncate(2);
+                // InsufficientBalance(uint256 available) with available = 5
+                let mut revert_data =
+                    ethers::abi::short_signature("InsufficientBalance", &[ParamType::Uint(256)])
+                        .to_vec();
+                revert_data.extend(ethers::abi::encode(&[Token::Uint(5.into())]));
+                tracer_output.revert_data = Some(hex::encode(revert_data));
+                Ok(tracer_output)
+            });
 
-        writes.insert(
-            H256::from_str("0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b")
-                .unwrap()
-                .to_fixed_bytes()
-                .into(),
-            1,
-        );
+        let mut abi_registry = AbiRegistry::default();
+        abi_registry
+            .add_signature("InsufficientBalance(uint256 available)")
+            .unwrap();
+        let simulator =
+            create_simulator(provider, tracer).with_abi_registry(Arc::new(abi_registry));
+        let res = simulator
+            .create_context(
+                UserOperation::default(),
+                BlockId::Number(BlockNumber::Latest),
+            )
+            .await;
 
-        tracer_output.phases[1].storage_accesses.insert(
-            Address::from_str("0x1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6").unwrap(),
-            AccessInfo {
-                reads: HashMap::new(),
-                writes,
-            },
-        );
+        assert!(matches!(
+            res,
+            Err(SimulationError { violation_error: ViolationError::Violations(violations), entity_infos: None}) if matches!(
+                violations.first(),
+                Some(&SimulationViolation::UnintendedRevertWithMessage(
+                    EntityType::Account,
+                    ref reason,
+                    _
+                )) if reason == "InsufficientBalance(available: 5)"
+            )
+        ));
+    }
 
-        let mut validation_context = ValidationContext {
+    fn create_validation_context(tracer_output: SimulationTracerOutput) -> ValidationContext {
+        ValidationContext {
             initcode_length: 10,
             associated_addresses: HashSet::new(),
             block_id: BlockId::Number(BlockNumber::Latest),
@@ -1311,7 +1687,44 @@ mod tests {
             },
             entities_needing_stake: vec![],
             accessed_addresses: HashSet::new(),
-        };
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gather_context_violations() {
+        let (provider, tracer) = create_base_config();
+
+        let mut tracer_output = get_test_tracer_output();
+
+        // add forbidden opcodes and precompiles
+        tracer_output.phases[1].forbidden_opcodes_used = vec![
+            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:GASPRICE"),
+            String::from("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:COINBASE"),
+        ];
+        tracer_output.phases[1].forbidden_precompiles_used = vec![String::from(
+            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000019",
+        )];
+
+        // add a storage access for a random unrelated address
+        let mut writes = HashMap::new();
+
+        writes.insert(
+            H256::from_str("0xa3f946b7ed2f016739c6be6031c5579a53d3784a471c3b5f9c2a1f8706c65a4b")
+                .unwrap()
+                .to_fixed_bytes()
+                .into(),
+            1,
+        );
+
+        tracer_output.phases[1].storage_accesses.insert(
+            Address::from_str("0x1c0e100fcf093c64cdaa545b425ad7ed8e8a0db6").unwrap(),
+            AccessInfo {
+                reads: HashMap::new(),
+                writes,
+            },
+        );
+
+        let mut validation_context = create_validation_context(tracer_output);
 
         let simulator = create_simulator(provider, tracer);
         let res = simulator.gather_context_violations(&mut validation_context);
@@ -1365,4 +1778,66 @@ mod tests {
             ]
         );
     }
+
+    #[tokio::test]
+    async fn test_gather_context_violations_allowed_precompile() {
+        let (provider, tracer) = create_base_config();
+
+        let mut tracer_output = get_test_tracer_output();
+        tracer_output.phases[1].forbidden_precompiles_used = vec![String::from(
+            "0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4:0x0000000000000000000000000000000000000100",
+        )];
+        let mut validation_context = create_validation_context(tracer_output);
+
+        let simulator =
+            create_simulator(provider, tracer).with_allowed_precompiles(&[Address::from_str(
+                "0x0000000000000000000000000000000000000100",
+            )
+            .unwrap()]);
+        let res = simulator.gather_context_violations(&mut validation_context);
+
+        assert_eq!(res.unwrap(), vec![SimulationViolation::InvalidSignature]);
+    }
+
+    #[tokio::test]
+    async fn test_gather_context_violations_not_staked_per_slot() {
+        let (provider, tracer) = create_base_config();
+
+        let contract = Address::random();
+        let mut tracer_output = get_test_tracer_output();
+        tracer_output.phases[1].storage_accesses.insert(
+            contract,
+            AccessInfo {
+                reads: HashMap::from([
+                    (U256::from(1), "0x0".to_string()),
+                    (U256::from(2), "0x0".to_string()),
+                ]),
+                writes: HashMap::new(),
+            },
+        );
+        let mut validation_context = create_validation_context(tracer_output);
+
+        let simulator = create_simulator(provider, tracer);
+        let mut violations = simulator
+            .gather_context_violations(&mut validation_context)
+            .unwrap();
+        violations.retain(|v| matches!(v, SimulationViolation::NotStaked(_)));
+
+        let sender = Address::from_str("0xb856dbd4fa1a79a46d426f537455e7d3e79ab7c4").unwrap();
+        let not_staked = |slot: u64| {
+            SimulationViolation::NotStaked(Box::new(NeedsStakeInformation {
+                entity: Entity::account(sender),
+                accessed_address: contract,
+                accessed_entity: None,
+                slot: U256::from(slot),
+                min_stake: Settings::default().min_stake_value.into(),
+                min_unstake_delay: Settings::default().min_unstake_delay.into(),
+            }))
+        };
+        assert_eq!(violations, vec![not_staked(1), not_staked(2)]);
+        assert_eq!(
+            validation_context.entities_needing_stake,
+            vec![EntityType::Account]
+        );
+    }
 }
@@ -66,6 +66,8 @@ pub(crate) struct Phase {
     pub(crate) ran_out_of_gas: bool,
     pub(crate) undeployed_contract_accesses: Vec<Address>,
     pub(crate) ext_code_access_info: HashMap<Address, Opcode>,
+    pub(crate) gas_used: u64,
+    pub(crate) opcode_count: u64,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
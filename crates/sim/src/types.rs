@@ -46,6 +46,18 @@ impl ExpectedStorage {
         }
         Ok(())
     }
+
+    /// Iterate over all accessed slots, by address
+    pub(crate) fn slots(&self) -> impl Iterator<Item = (Address, H256)> + '_ {
+        self.0
+            .iter()
+            .flat_map(|(&address, values)| values.keys().map(move |&slot| (address, slot)))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn insert(&mut self, address: Address, slot: H256, value: H256) {
+        self.0.entry(address).or_default().insert(slot, value);
+    }
 }
 
 use std::fmt::{Display, Formatter};
@@ -37,6 +37,9 @@ interface Phase {
   ranOutOfGas: boolean;
   undeployedContractAccesses: string[];
   extCodeAccessInfo: Record<string, string>;
+  gasUsed: number;
+  // Number of opcodes executed outside of the entry point
+  opcodeCount: number;
 }
 
 interface AccessInfo {
@@ -58,6 +61,7 @@ type InternalPhase = Omit<
   | "storageAccesses"
   | "addressesCallingWithValue"
   | "undeployedContractAccesses"
+  | "gasUsed"
 > & {
   forbiddenOpcodesUsed: StringSet;
   forbiddenPrecompilesUsed: StringSet;
@@ -125,6 +129,9 @@ type StringSet = Record<string, boolean | undefined>;
   const allStorageAccesses: Record<string, Record<string, string | null>> = {};
   let factoryCreate2Count = 0;
   let currentPhase = newInternalPhase();
+  // Gas remaining at the first step of the current phase
+  let phaseStartGas: number | null = null;
+  let lastGas = 0;
   let entryPointAddress = "";
   let pendingKeccakAddress = "";
   let last: RelevantStepData | null = null;
@@ -141,15 +148,17 @@ type StringSet = Record<string, boolean | undefined>;
       ranOutOfGas: false,
       undeployedContractAccesses: {},
       extCodeAccessInfo: {},
+      opcodeCount: 0,
     };
   }
 
-  function concludePhase(): void {
+  function concludePhase(gasRemaining: number): void {
     const {
       calledBannedEntryPointMethod,
       calledNonEntryPointWithValue,
       ranOutOfGas,
       extCodeAccessInfo,
+      opcodeCount,
     } = currentPhase;
     const forbiddenOpcodesUsed = Object.keys(currentPhase.forbiddenOpcodesUsed);
     const forbiddenPrecompilesUsed = Object.keys(
@@ -172,9 +181,12 @@ type StringSet = Record<string, boolean | undefined>;
       ranOutOfGas,
       undeployedContractAccesses,
       extCodeAccessInfo,
+      gasUsed: phaseStartGas === null ? 0 : phaseStartGas - gasRemaining,
+      opcodeCount,
     };
     phases.push(phase);
     currentPhase = newInternalPhase();
+    phaseStartGas = null;
   }
 
   function bigIntToNumber(n: BigInt): number {
@@ -205,7 +217,7 @@ type StringSet = Record<string, boolean | undefined>;
 
   return {
     result(_ctx, _db): Output {
-      concludePhase();
+      concludePhase(lastGas);
       const associatedSlotsByAddress: Record<string, string[]> = {};
       Object.keys(associatedSlotsByAddressMap).forEach((address) => {
         const slots = associatedSlotsByAddressMap[address];
@@ -246,6 +258,10 @@ type StringSet = Record<string, boolean | undefined>;
       }
 
       const opcode = log.op.toString();
+      lastGas = log.getGas();
+      if (phaseStartGas === null) {
+        phaseStartGas = lastGas;
+      }
 
       if (log.getGas() < log.getCost() || (
         opcode === 'SSTORE' && log.getGas() < SSTORE_REQUIRED_GAS
@@ -268,13 +284,14 @@ type StringSet = Record<string, boolean | undefined>;
       const entryPointIsExecuting = log.getDepth() === 1;
       if (entryPointIsExecuting) {
         if (opcode === "NUMBER") {
-          concludePhase();
+          concludePhase(lastGas);
         } else if (opcode === "REVERT") {
           const offset = bigIntToNumber(log.stack.peek(0));
           const length = bigIntToNumber(log.stack.peek(1));
           revertData = toHex(log.memory.slice(offset, offset + length));
         }
       } else {
+        currentPhase.opcodeCount++;
         // The entry point is allowed to freely call `GAS`, but otherwise we
         // require that a call opcode comes next.
         if (last?.opcode === "GAS" && !CALL_OPCODES[opcode]) {
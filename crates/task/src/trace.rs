@@ -0,0 +1,240 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Middleware propagating W3C trace context through HTTP headers, used by the
+//! gRPC and JSON-RPC servers and the gRPC clients.
+//!
+//! Propagation uses the global OpenTelemetry propagator, so it is a no-op
+//! unless trace export is configured.
+
+use std::task::{Context, Poll};
+
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+};
+use tonic::codegen::http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap, Request,
+};
+use tower::{Layer, Service};
+use tracing::{instrument::Instrumented, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+/// A layer continuing the trace of incoming requests in a span per request.
+#[derive(Debug, Clone)]
+pub struct TraceContextLayer {
+    service: &'static str,
+}
+
+impl TraceContextLayer {
+    /// Create a new `TraceContextLayer` for the named service
+    pub fn new(service: &'static str) -> Self {
+        Self { service }
+    }
+}
+
+impl<S> Layer<S> for TraceContextLayer {
+    type Service = TraceContext<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceContext {
+            inner,
+            service: self.service,
+        }
+    }
+}
+
+/// Service continuing the trace of incoming requests, see [`TraceContextLayer`].
+#[derive(Debug, Clone)]
+pub struct TraceContext<S> {
+    inner: S,
+    service: &'static str,
+}
+
+impl<S, Body> Service<Request<Body>> for TraceContext<S>
+where
+    S: Service<Request<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Instrumented<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let parent = global::get_text_map_propagator(|propagator| {
+            propagator.extract(&HeaderExtractor(request.headers()))
+        });
+        let span = tracing::info_span!(
+            "request",
+            service = self.service,
+            path = %request.uri().path(),
+        );
+        span.set_parent(parent);
+        let _entered = span.enter();
+        self.inner.call(request).in_current_span()
+    }
+}
+
+/// A layer adding the current trace context to the headers of outgoing requests.
+#[derive(Debug, Clone, Default)]
+pub struct TracePropagationLayer;
+
+impl<S> Layer<S> for TracePropagationLayer {
+    type Service = TracePropagation<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TracePropagation::new(inner)
+    }
+}
+
+/// Service adding the current trace context to outgoing requests, see
+/// [`TracePropagationLayer`].
+#[derive(Debug, Clone)]
+pub struct TracePropagation<S> {
+    inner: S,
+}
+
+impl<S> TracePropagation<S> {
+    /// Create a new `TracePropagation` middleware service.
+    pub fn new(inner: S) -> Self {
+        Self { inner }
+    }
+}
+
+impl<S, Body> Service<Request<Body>> for TracePropagation<S>
+where
+    S: Service<Request<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        let context = Span::current().context();
+        global::get_text_map_propagator(|propagator| {
+            propagator.inject_context(&context, &mut HeaderInjector(request.headers_mut()))
+        });
+        self.inner.call(request)
+    }
+}
+
+struct HeaderInjector<'a>(&'a mut HeaderMap);
+
+impl Injector for HeaderInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::Infallible;
+
+    use futures::{
+        executor::block_on,
+        future::{ready, Ready},
+    };
+    use opentelemetry::trace::{TraceContextExt, TraceId, TracerProvider as _};
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, trace::TracerProvider};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::*;
+
+    /// Responds with the `traceparent` header of the request and the trace
+    /// ID of the span the request is handled in.
+    struct Echo;
+
+    impl Service<Request<()>> for Echo {
+        type Response = (Option<String>, TraceId);
+        type Error = Infallible;
+        type Future = Ready<Result<Self::Response, Infallible>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request<()>) -> Self::Future {
+            let traceparent = request
+                .headers()
+                .get("traceparent")
+                .map(|value| value.to_str().unwrap().to_string());
+            ready(Ok((traceparent, trace_id(&Span::current()))))
+        }
+    }
+
+    fn trace_id(span: &Span) -> TraceId {
+        span.context().span().span_context().trace_id()
+    }
+
+    fn with_tracing(f: impl FnOnce()) {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let tracer = TracerProvider::builder().build().tracer("test");
+        let subscriber =
+            tracing_subscriber::registry().with(tracing_opentelemetry::layer().with_tracer(tracer));
+        tracing::subscriber::with_default(subscriber, f);
+    }
+
+    #[test]
+    fn test_propagates_trace_context() {
+        with_tracing(|| {
+            let mut service =
+                TracePropagationLayer.layer(TraceContextLayer::new("test").layer(Echo));
+
+            let span = tracing::info_span!("client");
+            let client_trace_id = trace_id(&span);
+            assert_ne!(client_trace_id, TraceId::INVALID);
+            let (traceparent, server_trace_id) = span
+                .in_scope(|| block_on(service.call(Request::new(()))))
+                .unwrap();
+
+            assert!(traceparent.unwrap().contains(&client_trace_id.to_string()));
+            assert_eq!(server_trace_id, client_trace_id);
+        });
+    }
+
+    #[test]
+    fn test_starts_trace_without_context() {
+        with_tracing(|| {
+            let mut service = TraceContextLayer::new("test").layer(Echo);
+
+            let (traceparent, server_trace_id) = block_on(service.call(Request::new(()))).unwrap();
+            assert_eq!(traceparent, None);
+            assert_ne!(server_trace_id, TraceId::INVALID);
+        });
+    }
+}
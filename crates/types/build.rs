@@ -29,6 +29,7 @@ fn generate_contract_bindings() -> Result<(), Box<dyn error::Error>> {
     MultiAbigen::from_abigens([
         abigen_of("IEntryPoint")?,
         abigen_of("EntryPoint")?,
+        abigen_of("SenderCreator")?,
         abigen_of("IAggregator")?,
         abigen_of("IStakeManager")?,
         abigen_of("GetCodeHashes")?,
@@ -40,6 +41,8 @@ fn generate_contract_bindings() -> Result<(), Box<dyn error::Error>> {
         abigen_of("VerifyingPaymaster")?,
         abigen_of("NodeInterface")?,
         abigen_of("GasPriceOracle")?,
+        abigen_of("IERC20")?,
+        abigen_of("ITokenOracle")?,
     ])
     .build()?
     .write_to_module("src/contracts", false)?;
@@ -0,0 +1,10 @@
+// SPDX-License-Identifier: GPL-3.0
+pragma solidity ^0.8.12;
+
+// Price oracle of an ERC-20 token paymaster, with the same interface as the
+// `IOracle` used by the sample `DepositPaymaster`.
+
+interface ITokenOracle {
+    /// return amount of tokens that are required to receive that much eth.
+    function getTokenValueOfEth(uint256 ethOutput) external view returns (uint256 tokenInput);
+}
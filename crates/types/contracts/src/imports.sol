@@ -7,5 +7,7 @@ import "account-abstraction/samples/SimpleAccount.sol";
 import "account-abstraction/samples/SimpleAccountFactory.sol";
 import "account-abstraction/samples/VerifyingPaymaster.sol";
 import "account-abstraction/core/EntryPoint.sol";
+import "account-abstraction/core/SenderCreator.sol";
 import "account-abstraction/interfaces/IAggregator.sol";
 import "account-abstraction/interfaces/IStakeManager.sol";
+import "@openzeppelin/contracts/token/ERC20/IERC20.sol";
@@ -11,41 +11,193 @@
 // You should have received a copy of the GNU General Public License along with Rundler.
 // If not, see https://www.gnu.org/licenses/.
 
-//! Grouped/Labeled chain IDs for various networks
-
-use ethers::types::Chain;
-
-/// Known chain IDs that use the Optimism Bedrock stack
-pub const OP_BEDROCK_CHAIN_IDS: &[u64] = &[
-    Chain::Optimism as u64,
-    Chain::OptimismGoerli as u64,
-    11155420, // OptimismSepolia
-    Chain::Base as u64,
-    Chain::BaseGoerli as u64,
-    84532, // BaseSepolia
-];
-
-// TODO use chain from ethers types once my PR is merged into ethers
-// https://github.com/gakonst/ethers-rs/pull/2657
-/// Known chain IDs for the Base ecosystem
-pub const ARBITRUM_CHAIN_IDS: &[u64] = &[
-    Chain::Arbitrum as u64,
-    Chain::ArbitrumGoerli as u64,
-    421614, /* ArbitrumSepolia */
-    Chain::ArbitrumNova as u64,
-];
-
-/// Known chain IDs for the Base ecosystem
-pub const BASE_CHAIN_IDS: &[u64] = &[
-    Chain::Base as u64,
-    Chain::BaseGoerli as u64,
-    84532, /* BaseSepolia */
-];
-
-/// Known chain IDs for the Polygon ecosystem
-pub const POLYGON_CHAIN_IDS: &[u64] = &[Chain::Polygon as u64, Chain::PolygonMumbai as u64];
-
-/// Return true if the chain ID has a dynamic preVerificationGas field
-pub fn is_dynamic_pvg(chain_id: u64) -> bool {
-    ARBITRUM_CHAIN_IDS.contains(&chain_id) || OP_BEDROCK_CHAIN_IDS.contains(&chain_id)
+//! Chain specifications
+
+use anyhow::bail;
+use ethers::types::{Address, H160};
+use parse_display::{Display, FromStr};
+use serde::{Deserialize, Serialize};
+
+/// Address of the Arbitrum Nitro `NodeInterface` virtual contract
+pub const ARBITRUM_NITRO_NODE_INTERFACE_ADDRESS: Address = H160([
+    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xc8,
+]);
+
+/// Address of the OP Stack `GasPriceOracle` predeploy
+pub const OPTIMISM_BEDROCK_GAS_ORACLE_ADDRESS: Address = H160([
+    0x42, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x0F,
+]);
+
+/// Address of the Scroll `L1GasPriceOracle` predeploy
+pub const SCROLL_L1_GAS_ORACLE_ADDRESS: Address = H160([
+    0x53, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x02,
+]);
+
+/// Method used to calculate the L1 data cost portion of a user operation's
+/// pre-verification gas
+#[derive(
+    Display, FromStr, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize,
+)]
+#[display(style = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum L1GasOracleContractType {
+    /// No L1 data cost
+    #[default]
+    None,
+    /// Arbitrum Nitro and Orbit chains, using `NodeInterface.gasEstimateL1Component`.
+    /// The L1 cost is charged as gas.
+    ArbitrumNitro,
+    /// OP Stack chains, using `GasPriceOracle.getL1Fee`. The predeploy applies the
+    /// Bedrock, Ecotone (blob base fee scalars), or Fjord formula active on the chain.
+    OptimismBedrock,
+    /// Scroll, using `L1GasPriceOracle.getL1Fee`
+    Scroll,
+    /// Linea, using the variable cost per byte published in the block extra data
+    Linea,
+    /// Mantle, using its OP Stack derived `GasPriceOracle.getL1Fee`. The oracle
+    /// address must be set, and is checked to be deployed at startup.
+    Mantle,
+    /// zkSync-style chains, pricing the data published to L1 with the pubdata
+    /// price returned by `zks_getFeeParams`
+    ZkSync,
+}
+
+/// L1 data cost configuration of a chain
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct L1GasConfig {
+    /// The method used to calculate the L1 data cost
+    pub oracle_type: L1GasOracleContractType,
+    /// The address of the oracle contract. Defaults to the canonical address
+    /// for the oracle type.
+    pub oracle_address: Option<Address>,
+}
+
+impl L1GasConfig {
+    /// Returns the address of the oracle contract, if the oracle type uses one
+    pub fn oracle_address(&self) -> Option<Address> {
+        self.oracle_address.or(match self.oracle_type {
+            L1GasOracleContractType::ArbitrumNitro => Some(ARBITRUM_NITRO_NODE_INTERFACE_ADDRESS),
+            L1GasOracleContractType::OptimismBedrock => Some(OPTIMISM_BEDROCK_GAS_ORACLE_ADDRESS),
+            L1GasOracleContractType::Scroll => Some(SCROLL_L1_GAS_ORACLE_ADDRESS),
+            L1GasOracleContractType::None
+            | L1GasOracleContractType::Linea
+            | L1GasOracleContractType::Mantle
+            | L1GasOracleContractType::ZkSync => None,
+        })
+    }
+
+    /// Returns true if the chain has a dynamic L1 data cost in preVerificationGas
+    pub fn is_dynamic_pvg(&self) -> bool {
+        self.oracle_type != L1GasOracleContractType::None
+    }
+
+    /// Returns true if the L1 data cost is charged as gas, counting towards the
+    /// gas limit of the bundle transaction
+    pub fn charges_l1_gas(&self) -> bool {
+        matches!(
+            self.oracle_type,
+            L1GasOracleContractType::ArbitrumNitro | L1GasOracleContractType::ZkSync
+        )
+    }
+}
+
+/// Oracle used to estimate the network priority fee
+#[derive(
+    Display, FromStr, Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize,
+)]
+#[display(style = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityFeeOracleType {
+    /// The node's `eth_maxPriorityFeePerGas`
+    #[default]
+    Provider,
+    /// The minimum priority fee, unless recent blocks are congested
+    UsageBased,
+    /// Always the minimum priority fee
+    Constant,
+}
+
+/// Transaction sender types
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[display(style = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum TransactionSenderType {
+    /// Raw transaction sender
+    Raw,
+    /// Conditional transaction sender
+    Conditional,
+    /// Flashbots transaction sender
+    ///
+    /// Currently only supported on Eth mainnet
+    Flashbots,
+    /// Bloxroute transaction sender
+    ///
+    /// Currently only supported on Polygon mainnet
+    PolygonBloxroute,
+}
+
+impl std::str::FromStr for TransactionSenderType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "raw" => Ok(TransactionSenderType::Raw),
+            "conditional" => Ok(TransactionSenderType::Conditional),
+            "flashbots" => Ok(TransactionSenderType::Flashbots),
+            "polygon_bloxroute" => Ok(TransactionSenderType::PolygonBloxroute),
+            _ => bail!("Invalid sender input. Must be one of either 'raw', 'conditional', 'flashbots' or 'polygon_bloxroute'"),
+        }
+    }
+}
+
+/// Chain specific parameters used by the bundler in place of chain ID checks.
+///
+/// Every field has a default, so a specification only needs to set the fields
+/// that differ from a generic EIP-1559 chain. Unknown fields are rejected so
+/// that misspelled fields don't silently fall back to their defaults.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ChainSpec {
+    /// Chain ID
+    pub id: u64,
+    /// L1 data cost configuration
+    pub l1_gas: L1GasConfig,
+    /// Minimum max priority fee per gas required of user operations and bundles, in wei
+    pub min_max_priority_fee_per_gas: u64,
+    /// Oracle used to estimate the network priority fee
+    pub priority_fee_oracle_type: PriorityFeeOracleType,
+    /// True if the chain supports EIP-1559 transactions
+    pub eip1559_enabled: bool,
+    /// Maximum gas of a bundle transaction
+    pub max_bundle_gas: u64,
+    /// Average time between blocks, in milliseconds
+    pub block_time_millis: u64,
+    /// Number of recent blocks tracked to handle reorgs
+    pub chain_history_size: u64,
+    /// Transaction senders the builder may use to send bundles
+    pub supported_senders: Vec<TransactionSenderType>,
+    /// Addresses of chain specific precompiles that entities may call during
+    /// validation, in addition to the standard Ethereum precompiles
+    pub precompile_addresses: Vec<Address>,
+}
+
+impl Default for ChainSpec {
+    fn default() -> Self {
+        Self {
+            id: 1337,
+            l1_gas: L1GasConfig::default(),
+            min_max_priority_fee_per_gas: 0,
+            priority_fee_oracle_type: PriorityFeeOracleType::default(),
+            eip1559_enabled: true,
+            max_bundle_gas: 25_000_000,
+            block_time_millis: 12_000,
+            chain_history_size: 128,
+            supported_senders: vec![
+                TransactionSenderType::Raw,
+                TransactionSenderType::Conditional,
+            ],
+            precompile_addresses: vec![],
+        }
+    }
 }
@@ -23,6 +23,7 @@ pub struct GasFees {
     pub max_priority_fee_per_gas: U256,
 }
 
+/// Legacy transactions pay their gas price as the max fee, with no priority fee.
 impl From<&TypedTransaction> for GasFees {
     fn from(tx: &TypedTransaction) -> Self {
         match tx {
@@ -30,6 +31,11 @@ impl From<&TypedTransaction> for GasFees {
                 max_fee_per_gas: tx.max_fee_per_gas.unwrap_or_default(),
                 max_priority_fee_per_gas: tx.max_priority_fee_per_gas.unwrap_or_default(),
             },
+            TypedTransaction::Legacy(_) | TypedTransaction::Eip2930(_) => Self {
+                max_fee_per_gas: tx.gas_price().unwrap_or_default(),
+                max_priority_fee_per_gas: U256::zero(),
+            },
+            #[allow(unreachable_patterns)]
             _ => Self::default(),
         }
     }
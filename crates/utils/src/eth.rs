@@ -22,7 +22,7 @@ use ethers::{
     providers::{
         Http, HttpRateLimitRetryPolicy, Middleware, Provider, RetryClient, RetryClientBuilder,
     },
-    types::{Address, Bytes, Log},
+    types::{Address, Bytes, Log, U256},
 };
 use url::Url;
 
@@ -44,11 +44,23 @@ pub struct ContractRevertError {
     pub reason: String,
 }
 
-/// Parses the revert message from the revert data
+/// The abi for a failed `assert`, arithmetic error, or other compiler check
+#[derive(Clone, Debug, Default, Eq, PartialEq, ethers::contract::EthError)]
+#[etherror(name = "Panic", abi = "Panic(uint256)")]
+pub struct ContractPanic {
+    /// Panic code
+    pub code: U256,
+}
+
+/// Parses the revert message from the revert data of a `revert("message")` or a
+/// panic. Use an [`AbiRegistry`](crate::revert::AbiRegistry) to decode custom errors.
 pub fn parse_revert_message(revert_data: &[u8]) -> Option<String> {
-    ContractRevertError::decode(revert_data)
+    if let Ok(err) = ContractRevertError::decode(revert_data) {
+        return Some(err.reason);
+    }
+    ContractPanic::decode(revert_data)
         .ok()
-        .map(|err| err.reason)
+        .map(|panic| format!("panic code {:#x}", panic.code))
 }
 
 /// Construct a new Ethers provider from a URL and a poll interval.
@@ -26,4 +26,5 @@ pub mod handle;
 pub mod log;
 pub mod math;
 pub mod retry;
+pub mod revert;
 pub mod strs;
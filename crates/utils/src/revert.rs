@@ -0,0 +1,602 @@
+// This file is part of Rundler.
+//
+// Rundler is free software: you can redistribute it and/or modify it under the
+// terms of the GNU Lesser General Public License as published by the Free Software
+// Foundation, either version 3 of the License, or (at your option) any later version.
+//
+// Rundler is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+// without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+// See the GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License along with Rundler.
+// If not, see https://www.gnu.org/licenses/.
+
+//! Decoding of revert data into human-readable reasons.
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    fs,
+    path::Path,
+};
+
+use anyhow::{bail, Context};
+use ethers::{
+    abi::{self, param_type::Reader, Abi, AbiError, Param, ParamType, Token},
+    types::{I256, U256},
+    utils::hex,
+};
+use serde::{Deserialize, Serialize};
+
+/// Errors that are always known to the registry
+const BUILTIN_ERRORS: &[&str] = &[
+    "Error(string reason)",
+    "Panic(uint256 code)",
+    "FailedOp(uint256 opIndex, string reason)",
+    "FailedOpWithRevert(uint256 opIndex, string reason, bytes inner)",
+    "ExecutionResult(uint256 preOpGas, uint256 paid, uint48 validAfter, uint48 validUntil, bool targetSuccess, bytes targetResult)",
+    "SignatureValidationFailed(address aggregator)",
+];
+
+/// Maximum depth of revert data nested in `bytes` arguments that is decoded
+const MAX_NESTING_DEPTH: usize = 4;
+
+/// A registry of Solidity custom errors, by selector, used to decode revert data.
+///
+/// The registry always knows `Error(string)`, `Panic(uint256)`, and the errors of
+/// the entry point. More errors can be loaded from JSON ABIs, either bare or in
+/// the `abi` key of a compiler artifact, and from signature files with one error
+/// signature such as `InsufficientBalance(uint256,uint256)` per line.
+#[derive(Clone, Debug)]
+pub struct AbiRegistry {
+    errors: HashMap<[u8; 4], Vec<AbiError>>,
+}
+
+impl Default for AbiRegistry {
+    fn default() -> Self {
+        let mut registry = Self {
+            errors: HashMap::new(),
+        };
+        for signature in BUILTIN_ERRORS {
+            registry
+                .add_signature(signature)
+                .expect("builtin error signatures should parse");
+        }
+        registry
+    }
+}
+
+impl AbiRegistry {
+    /// Load every JSON ABI and signature file in a directory into the registry,
+    /// returning the number of errors loaded.
+    ///
+    /// Files ending in `.json` are read as ABIs, all others as signature files.
+    pub fn load_dir(&mut self, dir: &Path) -> anyhow::Result<usize> {
+        let mut num_errors = 0;
+        let entries =
+            fs::read_dir(dir).with_context(|| format!("should read abi directory {dir:?}"))?;
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("should read abi file {path:?}"))?;
+            num_errors += if path.extension().is_some_and(|ext| ext == "json") {
+                self.add_abi_json(&contents)
+            } else {
+                self.add_signatures(&contents)
+            }
+            .with_context(|| format!("should parse abi file {path:?}"))?;
+        }
+        Ok(num_errors)
+    }
+
+    /// Add the errors of a JSON ABI, returning the number of errors added
+    pub fn add_abi_json(&mut self, json: &str) -> anyhow::Result<usize> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        // compiler artifacts hold the ABI in an `abi` key
+        if let Some(abi) = value.get_mut("abi") {
+            value = abi.take();
+        }
+        let abi: Abi = serde_json::from_value(value)?;
+        let mut num_errors = 0;
+        for error in abi.errors() {
+            self.add_error(error.clone());
+            num_errors += 1;
+        }
+        Ok(num_errors)
+    }
+
+    /// Add the errors of a signature file, returning the number of errors added.
+    ///
+    /// Blank lines and lines starting with `#` are skipped. A line may start with
+    /// the selector of its error, as in 4byte directory exports, which is ignored.
+    pub fn add_signatures(&mut self, contents: &str) -> anyhow::Result<usize> {
+        let mut num_errors = 0;
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let signature = match line.split_once(|c: char| c.is_whitespace() || c == ',') {
+                Some((selector, signature)) if selector.starts_with("0x") => signature.trim(),
+                _ => line,
+            };
+            self.add_signature(signature)?;
+            num_errors += 1;
+        }
+        Ok(num_errors)
+    }
+
+    /// Add an error by its signature, such as `Unauthorized(address)`. Parameters
+    /// may be named, as in `Unauthorized(address caller)`.
+    pub fn add_signature(&mut self, signature: &str) -> anyhow::Result<()> {
+        let signature = signature.trim().trim_start_matches("error ").trim();
+        let Some((name, params)) = signature
+            .strip_suffix(')')
+            .and_then(|signature| signature.split_once('('))
+        else {
+            bail!("invalid error signature {signature}");
+        };
+        let inputs = split_params(params)
+            .into_iter()
+            .map(parse_param)
+            .collect::<anyhow::Result<_>>()
+            .with_context(|| format!("invalid error signature {signature}"))?;
+        self.add_error(AbiError {
+            name: name.trim().to_string(),
+            inputs,
+        });
+        Ok(())
+    }
+
+    fn add_error(&mut self, error: AbiError) {
+        let selector = selector(&error);
+        let errors = self.errors.entry(selector).or_default();
+        if !errors.iter().any(|known| known.inputs == error.inputs) {
+            errors.push(error);
+        }
+    }
+
+    /// Decode revert data, returning `None` if its selector is unknown or it
+    /// doesn't match the parameters of any error with that selector.
+    pub fn decode(&self, revert_data: &[u8]) -> Option<DecodedRevert> {
+        self.decode_nested(revert_data, 0)
+    }
+
+    /// Decode revert data into a human-readable reason, falling back to the
+    /// hex encoded data if it can't be decoded.
+    pub fn format(&self, revert_data: &[u8]) -> String {
+        match self.decode(revert_data) {
+            Some(decoded) => decoded.to_string(),
+            None => format!("0x{}", hex::encode(revert_data)),
+        }
+    }
+
+    /// Replace the revert data in a message, written as `0x` prefixed hex, with
+    /// its decoded reason. Data that can't be decoded is left as is.
+    pub fn decode_message(&self, message: &str) -> String {
+        let mut decoded_message = String::with_capacity(message.len());
+        let mut rest = message;
+        while let Some(start) = rest.find("0x") {
+            let (before, data) = rest.split_at(start);
+            decoded_message.push_str(before);
+            let end = data[2..]
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .map_or(data.len(), |end| end + 2);
+            let decoded = hex::decode(&data[2..end])
+                .ok()
+                .and_then(|revert_data| self.decode(&revert_data));
+            match decoded {
+                Some(decoded) => decoded_message.push_str(&decoded.to_string()),
+                None => decoded_message.push_str(&data[..end]),
+            }
+            rest = &data[end..];
+        }
+        decoded_message.push_str(rest);
+        decoded_message
+    }
+
+    fn decode_nested(&self, revert_data: &[u8], depth: usize) -> Option<DecodedRevert> {
+        if revert_data.len() < 4 || depth > MAX_NESTING_DEPTH {
+            return None;
+        }
+        let (selector, data) = revert_data.split_at(4);
+        self.errors.get(selector)?.iter().find_map(|error| {
+            let kinds: Vec<ParamType> = error.inputs.iter().map(|p| p.kind.clone()).collect();
+            let tokens = abi::decode(&kinds, data).ok()?;
+            let args = error
+                .inputs
+                .iter()
+                .zip(tokens)
+                .map(|(param, token)| {
+                    let decoded = match &token {
+                        Token::Bytes(bytes) => self.decode_nested(bytes, depth + 1).map(Box::new),
+                        _ => None,
+                    };
+                    let value = match token {
+                        Token::String(value) => value,
+                        token => format_token(&token),
+                    };
+                    DecodedArg {
+                        name: param.name.clone(),
+                        kind: param.kind.to_string(),
+                        value,
+                        decoded,
+                    }
+                })
+                .collect();
+            Some(DecodedRevert {
+                name: error.name.clone(),
+                selector: format!("0x{}", hex::encode(selector)),
+                args,
+            })
+        })
+    }
+}
+
+/// Revert data decoded as a Solidity error
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedRevert {
+    /// Name of the error
+    pub name: String,
+    /// Selector of the error, hex encoded
+    pub selector: String,
+    /// Arguments of the error
+    pub args: Vec<DecodedArg>,
+}
+
+/// An argument of a decoded error
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DecodedArg {
+    /// Name of the parameter, empty if unnamed
+    pub name: String,
+    /// Solidity type of the parameter
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The value. Strings are unquoted, and other types are formatted as in
+    /// Solidity.
+    pub value: String,
+    /// For `bytes` arguments that hold revert data, the decoded revert
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<Box<DecodedRevert>>,
+}
+
+impl DecodedRevert {
+    /// The message of an `Error(string)` revert
+    pub fn message(&self) -> Option<&str> {
+        match (self.name.as_str(), self.args.as_slice()) {
+            ("Error", [arg]) if arg.kind == "string" => Some(&arg.value),
+            _ => None,
+        }
+    }
+}
+
+impl Display for DecodedRevert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(message) = self.message() {
+            return write!(f, "{message}");
+        }
+        if let ("Panic", [code]) = (self.name.as_str(), self.args.as_slice()) {
+            return match panic_description(&code.value) {
+                Some(description) => write!(f, "Panic({}: {description})", code.value),
+                None => write!(f, "Panic({})", code.value),
+            };
+        }
+        write!(f, "{}(", self.name)?;
+        for (i, arg) in self.args.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            if !arg.name.is_empty() {
+                write!(f, "{}: ", arg.name)?;
+            }
+            match &arg.decoded {
+                Some(decoded) => write!(f, "{decoded}")?,
+                None if arg.kind == "string" => write!(f, "{:?}", arg.value)?,
+                None => write!(f, "{}", arg.value)?,
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+fn selector(error: &AbiError) -> [u8; 4] {
+    let kinds: Vec<ParamType> = error.inputs.iter().map(|p| p.kind.clone()).collect();
+    abi::short_signature(&error.name, &kinds)
+}
+
+// Splits a parameter list on the commas outside of tuple parentheses
+fn split_params(params: &str) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in params.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = params[start..].trim();
+    if !last.is_empty() || !parts.is_empty() {
+        parts.push(&params[start..]);
+    }
+    parts
+}
+
+// Parses a parameter with an optional name, such as `uint256 amount`
+fn parse_param(param: &str) -> anyhow::Result<Param> {
+    let param = param.trim();
+    let (kind, name) = match param.rfind(')') {
+        // a tuple type, with the name after its closing parenthesis
+        Some(end) => (&param[..=end], param[end + 1..].trim()),
+        None => match param.split_once(' ') {
+            Some((kind, name)) => (kind, name.trim()),
+            None => (param, ""),
+        },
+    };
+    // tuple types may be followed by array dimensions
+    let (kind, name) = match name.split_once(' ') {
+        Some((dims, name)) if dims.starts_with('[') => (format!("{kind}{dims}"), name),
+        _ if name.starts_with('[') => (format!("{kind}{name}"), ""),
+        _ => (kind.to_string(), name),
+    };
+    Ok(Param {
+        name: name.to_string(),
+        kind: parse_kind(&kind)?,
+        internal_type: None,
+    })
+}
+
+// Parses a type, skipping the names of tuple components, as in
+// `(uint256 amount, address token)[]`
+fn parse_kind(kind: &str) -> anyhow::Result<ParamType> {
+    let Some(inner) = kind.strip_prefix('(') else {
+        return Reader::read(kind).with_context(|| format!("invalid type {kind}"));
+    };
+    let end = inner
+        .rfind(')')
+        .with_context(|| format!("invalid type {kind}"))?;
+    let components = split_params(&inner[..end])
+        .into_iter()
+        .map(|component| parse_param(component).map(|param| param.kind))
+        .collect::<anyhow::Result<_>>()?;
+    let mut kind_with_dims = ParamType::Tuple(components);
+    let mut dims = inner[end + 1..].trim();
+    while let Some(rest) = dims.strip_prefix('[') {
+        let (size, rest) = rest
+            .split_once(']')
+            .with_context(|| format!("invalid type {kind}"))?;
+        kind_with_dims = match size.trim() {
+            "" => ParamType::Array(Box::new(kind_with_dims)),
+            size => ParamType::FixedArray(
+                Box::new(kind_with_dims),
+                size.parse()
+                    .with_context(|| format!("invalid array size in type {kind}"))?,
+            ),
+        };
+        dims = rest.trim_start();
+    }
+    if !dims.is_empty() {
+        bail!("invalid type {kind}");
+    }
+    Ok(kind_with_dims)
+}
+
+fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(address) => format!("{address:?}"),
+        Token::FixedBytes(bytes) | Token::Bytes(bytes) => format!("0x{}", hex::encode(bytes)),
+        Token::Int(value) => I256::from_raw(*value).to_string(),
+        Token::Uint(value) => value.to_string(),
+        Token::Bool(value) => value.to_string(),
+        Token::String(value) => format!("{value:?}"),
+        Token::FixedArray(tokens) | Token::Array(tokens) => {
+            format!("[{}]", format_tokens(tokens))
+        }
+        Token::Tuple(tokens) => format!("({})", format_tokens(tokens)),
+    }
+}
+
+fn format_tokens(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(format_token)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// Describes the panic codes emitted by the Solidity compiler
+fn panic_description(code: &str) -> Option<&'static str> {
+    let code = U256::from_dec_str(code).ok()?;
+    if code > U256::from(u8::MAX) {
+        return None;
+    }
+    Some(match code.as_u32() {
+        0x00 => "generic compiler panic",
+        0x01 => "assertion failed",
+        0x11 => "arithmetic underflow or overflow",
+        0x12 => "division or modulo by zero",
+        0x21 => "invalid enum value",
+        0x22 => "invalid storage byte array encoding",
+        0x31 => "pop on empty array",
+        0x32 => "array index out of bounds",
+        0x41 => "out of memory",
+        0x51 => "call to zero-initialized internal function",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        abi::{encode, short_signature},
+        types::Address,
+    };
+
+    use super::*;
+
+    fn revert_data(signature: &str, kinds: &[ParamType], tokens: &[Token]) -> Vec<u8> {
+        let mut data = short_signature(signature, kinds).to_vec();
+        data.extend(encode(tokens));
+        data
+    }
+
+    #[test]
+    fn test_decode_builtin() {
+        let registry = AbiRegistry::default();
+
+        let error = revert_data(
+            "Error",
+            &[ParamType::String],
+            &[Token::String("not allowed".to_string())],
+        );
+        assert_eq!(registry.format(&error), "not allowed");
+
+        let panic = revert_data(
+            "Panic",
+            &[ParamType::Uint(256)],
+            &[Token::Uint(U256::from(0x11))],
+        );
+        assert_eq!(
+            registry.format(&panic),
+            "Panic(17: arithmetic underflow or overflow)"
+        );
+
+        assert_eq!(registry.format(&[1, 2, 3]), "0x010203");
+    }
+
+    #[test]
+    fn test_decode_custom_nested() {
+        let mut registry = AbiRegistry::default();
+        assert_eq!(
+            registry
+                .add_signatures(
+                    "# custom errors\n0x12345678 InsufficientBalance(uint256 needed, address token)\n"
+                )
+                .unwrap(),
+            1
+        );
+
+        let token = Address::random();
+        let inner = revert_data(
+            "InsufficientBalance",
+            &[ParamType::Uint(256), ParamType::Address],
+            &[Token::Uint(5.into()), Token::Address(token)],
+        );
+        let outer = revert_data(
+            "FailedOpWithRevert",
+            &[ParamType::Uint(256), ParamType::String, ParamType::Bytes],
+            &[
+                Token::Uint(0.into()),
+                Token::String("AA23 reverted".to_string()),
+                Token::Bytes(inner),
+            ],
+        );
+
+        let decoded = registry.decode(&outer).unwrap();
+        assert_eq!(decoded.name, "FailedOpWithRevert");
+        let nested = decoded.args[2].decoded.as_ref().unwrap();
+        assert_eq!(nested.name, "InsufficientBalance");
+        assert_eq!(nested.args[0].value, "5");
+        assert_eq!(
+            decoded.to_string(),
+            format!(
+                "FailedOpWithRevert(opIndex: 0, reason: \"AA23 reverted\", inner: InsufficientBalance(needed: 5, token: {token:?}))"
+            )
+        );
+    }
+
+    #[test]
+    fn test_decode_message() {
+        let registry = AbiRegistry::default();
+        let error = revert_data(
+            "Error",
+            &[ParamType::String],
+            &[Token::String("not allowed".to_string())],
+        );
+        let message = format!(
+            "execution reverted: 0x{}, sender 0x0102",
+            hex::encode(error)
+        );
+        assert_eq!(
+            registry.decode_message(&message),
+            "execution reverted: not allowed, sender 0x0102"
+        );
+        assert_eq!(registry.decode_message("AA23 reverted"), "AA23 reverted");
+    }
+
+    #[test]
+    fn test_add_abi_json() {
+        let mut registry = AbiRegistry::default();
+        let artifact = r#"{"abi": [
+            {"type": "error", "name": "Unauthorized", "inputs": [{"name": "caller", "type": "address"}]},
+            {"type": "function", "name": "foo", "inputs": [], "outputs": [], "stateMutability": "view"}
+        ]}"#;
+        assert_eq!(registry.add_abi_json(artifact).unwrap(), 1);
+
+        let data = revert_data(
+            "Unauthorized",
+            &[ParamType::Address],
+            &[Token::Address(Address::zero())],
+        );
+        assert_eq!(registry.decode(&data).unwrap().name, "Unauthorized");
+    }
+
+    #[test]
+    fn test_parse_tuple_param() {
+        let param = parse_param("(uint256,address)[] items").unwrap();
+        assert_eq!(param.name, "items");
+        assert_eq!(
+            param.kind,
+            ParamType::Array(Box::new(ParamType::Tuple(vec![
+                ParamType::Uint(256),
+                ParamType::Address
+            ])))
+        );
+        assert!(split_params("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_named_tuple_components() {
+        let param = parse_param("(uint256 a, (address b, bool c)[2] d) items").unwrap();
+        assert_eq!(param.name, "items");
+        assert_eq!(
+            param.kind,
+            ParamType::Tuple(vec![
+                ParamType::Uint(256),
+                ParamType::FixedArray(
+                    Box::new(ParamType::Tuple(vec![ParamType::Address, ParamType::Bool])),
+                    2
+                ),
+            ])
+        );
+
+        let mut registry = AbiRegistry::default();
+        registry
+            .add_signature("OrderFailed((uint256 id,address maker) order)")
+            .unwrap();
+        let data = revert_data(
+            "OrderFailed",
+            &[ParamType::Tuple(vec![
+                ParamType::Uint(256),
+                ParamType::Address,
+            ])],
+            &[Token::Tuple(vec![
+                Token::Uint(7.into()),
+                Token::Address(Address::zero()),
+            ])],
+        );
+        assert_eq!(
+            registry.format(&data),
+            format!("OrderFailed(order: (7, {:?}))", Address::zero())
+        );
+    }
+}